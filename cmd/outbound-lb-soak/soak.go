@@ -0,0 +1,416 @@
+//go:build soak
+
+package main
+
+import (
+	"bufio"
+	"bytes"
+	"context"
+	"crypto/rand"
+	"fmt"
+	"io"
+	mathrand "math/rand/v2"
+	"net"
+	"net/http"
+	"net/url"
+	"os"
+	"runtime"
+	"sync"
+	"sync/atomic"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/internal/limiter"
+	"github.com/cr0hn/outbound-lb/internal/logger"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/internal/proxy"
+)
+
+// options configures a soak run.
+type options struct {
+	IPs            []string
+	Duration       time.Duration
+	Concurrency    int
+	AbortRate      float64
+	MaxPayload     int
+	FlapInterval   time.Duration
+	ReportInterval time.Duration
+	// Settle is how long to wait after traffic stops before checking for leaks.
+	Settle time.Duration
+	// MaxHeapGrowth is the allowed ratio of the final heap to the heap after warm-up.
+	MaxHeapGrowth float64
+	// GoroutineSlack and FDSlack are how many goroutines and fds may outlive the run.
+	GoroutineSlack int
+	FDSlack        int
+}
+
+// usage is a point-in-time view of the resources the proxy accounts for.
+type usage struct {
+	Tunnels     int
+	Conns       int64
+	Goroutines  int
+	FDs         int
+	HeapInUse   uint64
+	fdsReported bool
+}
+
+// counters tally the operations of a run.
+type counters struct {
+	connects  atomic.Int64
+	requests  atomic.Int64
+	aborts    atomic.Int64
+	failures  atomic.Int64
+	flaps     atomic.Int64
+	bytesEcho atomic.Int64
+}
+
+// report is the outcome of a soak run.
+type report struct {
+	Baseline usage
+	Warm     usage
+	Final    usage
+	Connects int64
+	Requests int64
+	Aborts   int64
+	Failures int64
+	Flaps    int64
+	// Leaks describes each accounting check that failed; empty means the run passed.
+	Leaks []string
+}
+
+// soak drives an in-process proxy with mixed CONNECT and plain-HTTP traffic.
+type soak struct {
+	opts    options
+	server  *proxy.Server
+	proxy   string
+	echo    string
+	backend string
+	n       counters
+}
+
+// run starts the proxy and its targets, drives traffic for opts.Duration and
+// checks that every tunnel, connection slot, goroutine and fd was released.
+func run(ctx context.Context, opts options) (report, error) {
+	logger.Init("error", "json")
+	baseline := sample(nil, nil)
+
+	cfg := config.DefaultConfig()
+	cfg.IPs = opts.IPs
+	cfg.Port = 0
+	cfg.MaxConnsPerIP = opts.Concurrency * 4
+	cfg.MaxConnsTotal = opts.Concurrency * 4 * len(opts.IPs)
+	cfg.LogLevel = "error"
+	// The echo and HTTP targets listen on loopback on arbitrary ports
+	cfg.BlockPrivateDestinations = false
+	cfg.ConnectPorts = nil
+
+	lim := limiter.New(cfg.MaxConnsPerIP, cfg.MaxConnsTotal, cfg.IPs)
+	bal := balancer.New(balancer.Config{
+		IPs:           cfg.IPs,
+		HistoryWindow: int64(cfg.HistoryWindow.Seconds()),
+		HistorySize:   cfg.HistorySize,
+		Limiter:       lim,
+	})
+	bal.Start()
+	stopBalancer := sync.OnceFunc(bal.Stop)
+	defer stopBalancer()
+
+	s := &soak{opts: opts}
+	s.server = proxy.NewServer(cfg, bal, lim, metrics.NewStatsCollector(cfg.IPs))
+
+	proxyL, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		return report{}, err
+	}
+	go s.server.Serve(proxyL)
+	s.proxy = proxyL.Addr().String()
+
+	echoL, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		return report{}, err
+	}
+	defer echoL.Close()
+	go serveEcho(echoL)
+	s.echo = echoL.Addr().String()
+
+	backendL, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		return report{}, err
+	}
+	backend := &http.Server{Handler: http.HandlerFunc(s.serveBackend)}
+	defer backend.Close()
+	go backend.Serve(backendL)
+	s.backend = "http://" + backendL.Addr().String()
+
+	runCtx, cancel := context.WithTimeout(ctx, opts.Duration)
+	defer cancel()
+
+	var wg sync.WaitGroup
+	for i := 0; i < opts.Concurrency; i++ {
+		wg.Add(1)
+		go func() {
+			defer wg.Done()
+			s.drive(runCtx)
+		}()
+	}
+	if len(opts.IPs) > 1 && opts.FlapInterval > 0 {
+		wg.Add(1)
+		go func() {
+			defer wg.Done()
+			s.flap(runCtx)
+		}()
+	}
+
+	// Warm-up is the first report interval; the heap is compared against it
+	var warm usage
+	ticker := time.NewTicker(opts.ReportInterval)
+	defer ticker.Stop()
+	for done := false; !done; {
+		select {
+		case <-ticker.C:
+			u := sample(s.server, lim)
+			if warm == (usage{}) {
+				warm = u
+			}
+			s.log("soak_progress", u)
+		case <-runCtx.Done():
+			done = true
+		}
+	}
+	wg.Wait()
+	if warm == (usage{}) {
+		warm = sample(s.server, lim)
+	}
+
+	// Let the proxy finish closing what the clients walked away from
+	time.Sleep(opts.Settle)
+	s.server.Shutdown(context.Background())
+	stopBalancer()
+	backend.Close()
+	echoL.Close()
+	time.Sleep(opts.Settle)
+	final := sample(s.server, lim)
+	s.log("soak_finished", final)
+
+	r := report{
+		Baseline: baseline,
+		Warm:     warm,
+		Final:    final,
+		Connects: s.n.connects.Load(),
+		Requests: s.n.requests.Load(),
+		Aborts:   s.n.aborts.Load(),
+		Failures: s.n.failures.Load(),
+		Flaps:    s.n.flaps.Load(),
+	}
+	r.Leaks = checkLeaks(opts, baseline, warm, final)
+	if r.Connects+r.Requests == 0 {
+		r.Leaks = append(r.Leaks, "no traffic completed")
+	}
+	return r, nil
+}
+
+// checkLeaks compares the final accounting against the baseline and warm-up samples.
+func checkLeaks(opts options, baseline, warm, final usage) []string {
+	var leaks []string
+	if final.Tunnels != 0 {
+		leaks = append(leaks, fmt.Sprintf("%d tunnels still registered", final.Tunnels))
+	}
+	if final.Conns != 0 {
+		leaks = append(leaks, fmt.Sprintf("%d connection slots still held", final.Conns))
+	}
+	if final.Goroutines > baseline.Goroutines+opts.GoroutineSlack {
+		leaks = append(leaks, fmt.Sprintf("%d goroutines, %d before the run", final.Goroutines, baseline.Goroutines))
+	}
+	if final.fdsReported && baseline.fdsReported && final.FDs > baseline.FDs+opts.FDSlack {
+		leaks = append(leaks, fmt.Sprintf("%d open fds, %d before the run", final.FDs, baseline.FDs))
+	}
+	if warm.HeapInUse > 0 && float64(final.HeapInUse) > float64(warm.HeapInUse)*opts.MaxHeapGrowth {
+		leaks = append(leaks, fmt.Sprintf("heap grew from %d to %d bytes", warm.HeapInUse, final.HeapInUse))
+	}
+	return leaks
+}
+
+// sample collects the current usage. server and lim may be nil before the proxy starts.
+func sample(server *proxy.Server, lim *limiter.Limiter) usage {
+	runtime.GC()
+	var ms runtime.MemStats
+	runtime.ReadMemStats(&ms)
+	u := usage{Goroutines: runtime.NumGoroutine(), HeapInUse: ms.HeapInuse}
+	if server != nil {
+		u.Tunnels = server.ActiveTunnels()
+	}
+	if lim != nil {
+		u.Conns = lim.GetTotalCount()
+	}
+	if entries, err := os.ReadDir("/proc/self/fd"); err == nil {
+		u.FDs, u.fdsReported = len(entries), true
+	}
+	return u
+}
+
+// log prints a progress line with the run's counters and u. The proxy's own
+// logs are kept at error level so they don't drown these out.
+func (s *soak) log(event string, u usage) {
+	fmt.Printf("%s %s connects=%d requests=%d aborts=%d failures=%d flaps=%d echoed_bytes=%d tunnels=%d connections=%d goroutines=%d fds=%d heap_inuse=%d\n",
+		time.Now().Format(time.RFC3339), event,
+		s.n.connects.Load(), s.n.requests.Load(), s.n.aborts.Load(), s.n.failures.Load(), s.n.flaps.Load(), s.n.bytesEcho.Load(),
+		u.Tunnels, u.Conns, u.Goroutines, u.FDs, u.HeapInUse,
+	)
+}
+
+// drive issues CONNECT tunnels and plain-HTTP requests until ctx is done.
+func (s *soak) drive(ctx context.Context) {
+	proxyURL, _ := url.Parse("http://" + s.proxy)
+	client := &http.Client{
+		Transport: &http.Transport{Proxy: http.ProxyURL(proxyURL), DisableKeepAlives: mathrand.IntN(2) == 0},
+		Timeout:   30 * time.Second,
+	}
+	defer client.CloseIdleConnections()
+
+	for ctx.Err() == nil {
+		var err error
+		if mathrand.IntN(2) == 0 {
+			err = s.connect(ctx)
+		} else {
+			err = s.request(ctx, client)
+		}
+		if err != nil && ctx.Err() == nil {
+			s.n.failures.Add(1)
+		}
+	}
+}
+
+// abort reports whether the current operation should be abandoned midway.
+func (s *soak) abort() bool {
+	return mathrand.Float64() < s.opts.AbortRate
+}
+
+// connect opens a tunnel to the echo server and round-trips a random payload,
+// or walks away after sending half of it.
+func (s *soak) connect(ctx context.Context) error {
+	var d net.Dialer
+	conn, err := d.DialContext(ctx, "tcp", s.proxy)
+	if err != nil {
+		return err
+	}
+	defer conn.Close()
+	conn.SetDeadline(time.Now().Add(30 * time.Second))
+
+	fmt.Fprintf(conn, "CONNECT %s HTTP/1.1\r\nHost: %s\r\n\r\n", s.echo, s.echo)
+	br := bufio.NewReader(conn)
+	resp, err := http.ReadResponse(br, &http.Request{Method: http.MethodConnect})
+	if err != nil {
+		return err
+	}
+	if resp.StatusCode != http.StatusOK {
+		return fmt.Errorf("CONNECT status %d", resp.StatusCode)
+	}
+	s.n.connects.Add(1)
+
+	payload := make([]byte, 1+mathrand.IntN(s.opts.MaxPayload))
+	rand.Read(payload)
+	if s.abort() {
+		conn.Write(payload[:len(payload)/2])
+		s.n.aborts.Add(1)
+		return nil
+	}
+	if _, err := conn.Write(payload); err != nil {
+		return err
+	}
+	got := make([]byte, len(payload))
+	if _, err := io.ReadFull(br, got); err != nil {
+		return err
+	}
+	if !bytes.Equal(got, payload) {
+		return fmt.Errorf("echo mismatch")
+	}
+	s.n.bytesEcho.Add(int64(len(payload)))
+	return nil
+}
+
+// request fetches a random-size body from the backend through the proxy, or
+// cancels it after the headers arrive.
+func (s *soak) request(ctx context.Context, client *http.Client) error {
+	size := mathrand.IntN(s.opts.MaxPayload)
+	reqCtx, cancel := context.WithCancel(ctx)
+	defer cancel()
+	req, err := http.NewRequestWithContext(reqCtx, http.MethodGet, fmt.Sprintf("%s/?size=%d", s.backend, size), nil)
+	if err != nil {
+		return err
+	}
+	resp, err := client.Do(req)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	s.n.requests.Add(1)
+	if s.abort() {
+		cancel()
+		s.n.aborts.Add(1)
+		return nil
+	}
+	n, err := io.Copy(io.Discard, resp.Body)
+	if err != nil {
+		return err
+	}
+	if n != int64(size) {
+		return fmt.Errorf("short body: %d of %d bytes", n, size)
+	}
+	return nil
+}
+
+// flap drains a random outbound IP each interval and returns the previous one
+// to service, so selections keep moving between egresses.
+func (s *soak) flap(ctx context.Context) {
+	ticker := time.NewTicker(s.opts.FlapInterval)
+	defer ticker.Stop()
+	var drained string
+	for {
+		select {
+		case <-ticker.C:
+			if drained != "" {
+				s.server.UndrainIP(drained)
+			}
+			drained = s.opts.IPs[mathrand.IntN(len(s.opts.IPs))]
+			s.server.DrainIP(drained)
+			s.n.flaps.Add(1)
+		case <-ctx.Done():
+			if drained != "" {
+				s.server.UndrainIP(drained)
+			}
+			return
+		}
+	}
+}
+
+// serveBackend answers with ?size= bytes.
+func (s *soak) serveBackend(w http.ResponseWriter, r *http.Request) {
+	var size int
+	fmt.Sscan(r.URL.Query().Get("size"), &size)
+	w.Header().Set("Content-Length", fmt.Sprint(size))
+	io.CopyN(w, zeroReader{}, int64(size))
+}
+
+// zeroReader reads zeros.
+type zeroReader struct{}
+
+// Read fills p with zeros.
+func (zeroReader) Read(p []byte) (int, error) {
+	clear(p)
+	return len(p), nil
+}
+
+// serveEcho echoes every accepted connection until l is closed.
+func serveEcho(l net.Listener) {
+	for {
+		conn, err := l.Accept()
+		if err != nil {
+			return
+		}
+		go func() {
+			defer conn.Close()
+			io.Copy(conn, conn)
+		}()
+	}
+}
@@ -0,0 +1,47 @@
+//go:build soak
+
+package main
+
+import (
+	"context"
+	"testing"
+	"time"
+)
+
+func TestSoak_Short(t *testing.T) {
+	r, err := run(context.Background(), options{
+		IPs:            []string{"127.0.0.1"},
+		Duration:       3 * time.Second,
+		Concurrency:    8,
+		AbortRate:      0.2,
+		MaxPayload:     32 * 1024,
+		ReportInterval: time.Second,
+		Settle:         time.Second,
+		MaxHeapGrowth:  4,
+		GoroutineSlack: 10,
+		FDSlack:        5,
+	})
+	if err != nil {
+		t.Fatalf("run: %v", err)
+	}
+	if len(r.Leaks) > 0 {
+		t.Errorf("leaks: %v", r.Leaks)
+	}
+	if r.Connects == 0 || r.Requests == 0 {
+		t.Errorf("expected both CONNECT and plain-HTTP traffic, got %d connects and %d requests", r.Connects, r.Requests)
+	}
+}
+
+func TestCheckLeaks(t *testing.T) {
+	opts := options{MaxHeapGrowth: 2, GoroutineSlack: 2, FDSlack: 2}
+	baseline := usage{Goroutines: 5, FDs: 10, fdsReported: true}
+	warm := usage{HeapInUse: 1000}
+
+	if leaks := checkLeaks(opts, baseline, warm, usage{Goroutines: 7, FDs: 12, HeapInUse: 1500, fdsReported: true}); len(leaks) != 0 {
+		t.Errorf("expected no leaks within slack, got %v", leaks)
+	}
+	leaks := checkLeaks(opts, baseline, warm, usage{Tunnels: 1, Conns: 1, Goroutines: 20, FDs: 30, HeapInUse: 5000, fdsReported: true})
+	if len(leaks) != 5 {
+		t.Errorf("expected 5 leaks, got %v", leaks)
+	}
+}
@@ -0,0 +1,35 @@
+package main
+
+import (
+	"net/http"
+	"os"
+	"time"
+
+	"github.com/spf13/pflag"
+)
+
+// adminTokenFlag registers the --token flag of the admin commands, defaulting
+// to OUTBOUND_LB_ADMIN_TOKEN.
+func adminTokenFlag(fs *pflag.FlagSet) *string {
+	return fs.String("token", os.Getenv("OUTBOUND_LB_ADMIN_TOKEN"), "Admin token sent as a bearer token (default $OUTBOUND_LB_ADMIN_TOKEN)")
+}
+
+// adminClient returns a client for the admin endpoints that sends token, when
+// set, on every request. A zero timeout means no deadline.
+func adminClient(timeout time.Duration, token string) *http.Client {
+	return &http.Client{Timeout: timeout, Transport: bearerTransport{token: token}}
+}
+
+// bearerTransport adds an Authorization header to requests.
+type bearerTransport struct {
+	token string
+}
+
+// RoundTrip implements http.RoundTripper.
+func (t bearerTransport) RoundTrip(req *http.Request) (*http.Response, error) {
+	if t.token != "" {
+		req = req.Clone(req.Context())
+		req.Header.Set("Authorization", "Bearer "+t.token)
+	}
+	return http.DefaultTransport.RoundTrip(req)
+}
@@ -0,0 +1,97 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"net/http"
+	"net/url"
+	"os"
+	"strings"
+	"time"
+
+	"github.com/spf13/pflag"
+)
+
+// drainReport is the /admin/drain response.
+type drainReport struct {
+	Draining []string           `json:"draining"`
+	Weights  map[string]float64 `json:"weights"`
+	Active   map[string]int64   `json:"active"`
+}
+
+// runDrain implements the drain command: it drains (or with --undo, undrains)
+// an outbound IP through /admin/drain on the metrics port. With --wait it
+// blocks until the IP's existing connections have finished.
+func runDrain(args []string) int {
+	fs := pflag.NewFlagSet("drain", pflag.ContinueOnError)
+	addr := fs.String("addr", "http://127.0.0.1:9090", "Metrics server address")
+	token := adminTokenFlag(fs)
+	period := fs.Duration("period", 0, "Period over which the IP loses its traffic (0 = server default)")
+	undo := fs.Bool("undo", false, "Return the IP to service")
+	wait := fs.Bool("wait", false, "Wait until the IP's existing connections have finished")
+	pollInterval := fs.Duration("poll-interval", 2*time.Second, "How often to check connections with --wait")
+	fs.Usage = func() {
+		fmt.Fprintln(os.Stderr, "Usage: outbound-lb drain [flags] <ip>")
+		fs.PrintDefaults()
+	}
+	if err := fs.Parse(args); err != nil {
+		return 2
+	}
+	if fs.NArg() != 1 {
+		fs.Usage()
+		return 2
+	}
+	ip := fs.Arg(0)
+
+	client := adminClient(5*time.Second, *token)
+	q := url.Values{"ip": {ip}}
+	method := http.MethodPost
+	if *undo {
+		method = http.MethodDelete
+	} else if *period > 0 {
+		q.Set("period", period.String())
+	}
+	endpoint := strings.TrimSuffix(*addr, "/") + "/admin/drain"
+
+	report, err := drainRequest(client, method, endpoint+"?"+q.Encode())
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "drain: %v\n", err)
+		return 1
+	}
+	if *undo {
+		fmt.Printf("%s returned to service\n", ip)
+		return 0
+	}
+	fmt.Printf("%s draining, %d active connections\n", ip, report.Active[ip])
+
+	for *wait && report.Active[ip] > 0 {
+		time.Sleep(*pollInterval)
+		if report, err = drainRequest(client, http.MethodGet, endpoint); err != nil {
+			fmt.Fprintf(os.Stderr, "drain: %v\n", err)
+			return 1
+		}
+		fmt.Printf("%s: %d active connections\n", ip, report.Active[ip])
+	}
+	if *wait {
+		fmt.Printf("%s drained\n", ip)
+	}
+	return 0
+}
+
+// drainRequest sends a drain request and decodes the report.
+func drainRequest(client *http.Client, method, url string) (drainReport, error) {
+	var report drainReport
+	req, err := http.NewRequest(method, url, nil)
+	if err != nil {
+		return report, err
+	}
+	resp, err := client.Do(req)
+	if err != nil {
+		return report, err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return report, fmt.Errorf("%s returned %s", url, resp.Status)
+	}
+	return report, json.NewDecoder(resp.Body).Decode(&report)
+}
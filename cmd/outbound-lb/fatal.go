@@ -0,0 +1,92 @@
+package main
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"net"
+	"os"
+	"runtime/debug"
+	"syscall"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+)
+
+// Process exit codes, so supervisors can tell failure causes apart without parsing logs.
+const (
+	exitRuntime   = 1 // unexpected error while running
+	exitConfig    = 2 // invalid configuration
+	exitBind      = 3 // a listener could not bind its address
+	exitPrivilege = 4 // insufficient privileges, e.g. for a port below 1024
+	exitPanic     = 5 // unrecovered panic
+)
+
+// exitReasons names each exit code in the fatal-error record.
+var exitReasons = map[int]string{
+	exitRuntime:   "runtime",
+	exitConfig:    "config",
+	exitBind:      "bind",
+	exitPrivilege: "privilege",
+	exitPanic:     "panic",
+}
+
+// fatalRecord is the final JSON line written to stderr before a fatal exit.
+type fatalRecord struct {
+	Time     string `json:"time"`
+	Level    string `json:"level"`
+	Reason   string `json:"reason"`
+	ExitCode int    `json:"exit_code"`
+	Msg      string `json:"msg"`
+	Error    string `json:"error"`
+	Stack    string `json:"stack,omitempty"`
+}
+
+// writeFatalRecord writes the fatal-error record for code as one JSON line to w.
+func writeFatalRecord(w io.Writer, code int, msg string, err error, stack []byte) {
+	json.NewEncoder(w).Encode(fatalRecord{
+		Time:     time.Now().UTC().Format(time.RFC3339Nano),
+		Level:    "fatal",
+		Reason:   exitReasons[code],
+		ExitCode: code,
+		Msg:      msg,
+		Error:    err.Error(),
+		Stack:    string(stack),
+	})
+}
+
+// fatal logs err, writes the fatal-error record to stderr and exits with code.
+func fatal(code int, msg string, err error) {
+	logger.Error(msg, "error", err, "exit_code", code)
+	writeFatalRecord(os.Stderr, code, msg, err, nil)
+	os.Exit(code)
+}
+
+// recoverFatal turns a panic into a fatal-error record with the stack and exitPanic.
+// It must be deferred at the top of main and of every long-running goroutine.
+func recoverFatal() {
+	r := recover()
+	if r == nil {
+		return
+	}
+	err := fmt.Errorf("%v", r)
+	logger.Error("panic", "error", err, "exit_code", exitPanic)
+	writeFatalRecord(os.Stderr, exitPanic, "panic", err, debug.Stack())
+	os.Exit(exitPanic)
+}
+
+// listenExitCode classifies a listener error: exitPrivilege when the port needs
+// privileges, exitBind when the address is in use or unavailable, otherwise exitRuntime.
+func listenExitCode(err error) int {
+	var opErr *net.OpError
+	switch {
+	case errors.Is(err, os.ErrPermission):
+		return exitPrivilege
+	case errors.Is(err, syscall.EADDRINUSE), errors.Is(err, syscall.EADDRNOTAVAIL):
+		return exitBind
+	case errors.As(err, &opErr) && opErr.Op == "listen":
+		return exitBind
+	}
+	return exitRuntime
+}
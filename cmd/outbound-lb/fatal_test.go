@@ -0,0 +1,49 @@
+package main
+
+import (
+	"bytes"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"net"
+	"os"
+	"syscall"
+	"testing"
+)
+
+func TestListenExitCode(t *testing.T) {
+	tests := []struct {
+		name string
+		err  error
+		want int
+	}{
+		{"address in use", &net.OpError{Op: "listen", Net: "tcp", Err: os.NewSyscallError("bind", syscall.EADDRINUSE)}, exitBind},
+		{"permission denied", &net.OpError{Op: "listen", Net: "tcp", Err: os.NewSyscallError("bind", syscall.EACCES)}, exitPrivilege},
+		{"other listen error", &net.OpError{Op: "listen", Net: "tcp", Err: errors.New("boom")}, exitBind},
+		{"wrapped address in use", fmt.Errorf("proxy: %w", syscall.EADDRINUSE), exitBind},
+		{"runtime error", errors.New("accept failed"), exitRuntime},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			if got := listenExitCode(tt.err); got != tt.want {
+				t.Errorf("listenExitCode(%v) = %d, want %d", tt.err, got, tt.want)
+			}
+		})
+	}
+}
+
+func TestWriteFatalRecord(t *testing.T) {
+	var buf bytes.Buffer
+	writeFatalRecord(&buf, exitConfig, "failed to parse configuration", errors.New("invalid port"), nil)
+
+	var record fatalRecord
+	if err := json.Unmarshal(buf.Bytes(), &record); err != nil {
+		t.Fatalf("record is not JSON: %v (%s)", err, buf.String())
+	}
+	if record.Level != "fatal" || record.Reason != "config" || record.ExitCode != exitConfig || record.Error != "invalid port" {
+		t.Errorf("unexpected record: %+v", record)
+	}
+	if bytes.Count(buf.Bytes(), []byte("\n")) != 1 {
+		t.Errorf("expected a single line, got %q", buf.String())
+	}
+}
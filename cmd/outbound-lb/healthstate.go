@@ -0,0 +1,108 @@
+package main
+
+import (
+	"errors"
+	"io/fs"
+	"slices"
+	"sort"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+	"github.com/cr0hn/outbound-lb/internal/health"
+	"github.com/cr0hn/outbound-lb/internal/logger"
+)
+
+// snapshotHealthState collects the IPs that are currently unhealthy or ejected.
+// Either source may be nil.
+func snapshotHealthState(hc *health.HealthChecker, outliers *balancer.OutlierDetector) health.State {
+	backends := make(map[string]*health.BackendState)
+	backend := func(ip string) *health.BackendState {
+		b, ok := backends[ip]
+		if !ok {
+			b = &health.BackendState{IP: ip}
+			backends[ip] = b
+		}
+		return b
+	}
+
+	if hc != nil {
+		for _, status := range hc.GetAllStatus() {
+			if status.State != health.StateHealthy.String() {
+				b := backend(status.IP)
+				b.Unhealthy = true
+				b.LastError = status.LastError
+			}
+		}
+	}
+	if outliers != nil {
+		for ip, until := range outliers.EjectedUntil() {
+			backend(ip).EjectedUntil = until
+		}
+	}
+
+	state := health.State{SavedAt: time.Now(), Backends: make([]health.BackendState, 0, len(backends))}
+	for _, b := range backends {
+		state.Backends = append(state.Backends, *b)
+	}
+	sort.Slice(state.Backends, func(i, j int) bool { return state.Backends[i].IP < state.Backends[j].IP })
+	return state
+}
+
+// restoreHealthState applies a state file written by a previous run. Entries for
+// IPs that are no longer configured are skipped, and a missing file is not an error.
+func restoreHealthState(path string, ips []string, hc *health.HealthChecker, outliers *balancer.OutlierDetector) {
+	state, err := health.LoadState(path)
+	if err != nil {
+		if !errors.Is(err, fs.ErrNotExist) {
+			logger.Warn("health_state_load_failed", "path", path, "error", err)
+		}
+		return
+	}
+
+	for _, b := range state.Backends {
+		if !slices.Contains(ips, b.IP) {
+			continue
+		}
+		if b.Unhealthy && hc != nil {
+			hc.MarkUnhealthy(b.IP, b.LastError)
+			logger.Info("health_state_restored", "ip", b.IP, "state", health.StateUnhealthy.String(), "saved_at", state.SavedAt)
+		}
+		if !b.EjectedUntil.IsZero() && outliers != nil && time.Now().Before(b.EjectedUntil) {
+			outliers.Eject(b.IP, b.EjectedUntil)
+			logger.Info("health_state_restored", "ip", b.IP, "ejected_until", b.EjectedUntil, "saved_at", state.SavedAt)
+		}
+	}
+}
+
+// saveHealthState writes the current health state to path, logging failures.
+func saveHealthState(path string, hc *health.HealthChecker, outliers *balancer.OutlierDetector) {
+	if err := health.SaveState(path, snapshotHealthState(hc, outliers)); err != nil {
+		logger.Warn("health_state_save_failed", "path", path, "error", err)
+	}
+}
+
+// startHealthStateSaver saves the health state every interval so that it
+// survives a crash as well as a clean shutdown. The returned func stops the
+// saver and writes the state one last time.
+func startHealthStateSaver(path string, interval time.Duration, hc *health.HealthChecker, outliers *balancer.OutlierDetector) func() {
+	stopCh := make(chan struct{})
+	done := make(chan struct{})
+	go func() {
+		defer close(done)
+		ticker := time.NewTicker(interval)
+		defer ticker.Stop()
+		for {
+			select {
+			case <-ticker.C:
+				saveHealthState(path, hc, outliers)
+			case <-stopCh:
+				return
+			}
+		}
+	}()
+	return func() {
+		close(stopCh)
+		<-done
+		saveHealthState(path, hc, outliers)
+	}
+}
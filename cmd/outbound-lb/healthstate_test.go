@@ -0,0 +1,63 @@
+package main
+
+import (
+	"path/filepath"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+	"github.com/cr0hn/outbound-lb/internal/health"
+)
+
+func TestHealthStateRoundTrip(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "health.json")
+	ips := []string{"192.0.2.1", "192.0.2.2", "192.0.2.3"}
+	newSources := func() (*health.HealthChecker, *balancer.OutlierDetector) {
+		hc := health.NewHealthChecker(health.HealthCheckerConfig{
+			IPs:              ips,
+			Interval:         time.Hour,
+			Timeout:          time.Second,
+			FailureThreshold: 3,
+			SuccessThreshold: 2,
+		})
+		outliers := balancer.NewOutlierDetector(balancer.OutlierConfig{
+			ErrorRate:    0.5,
+			MinRequests:  4,
+			Window:       10 * time.Second,
+			EjectionTime: time.Hour,
+		})
+		return hc, outliers
+	}
+
+	hc, outliers := newSources()
+	hc.MarkUnhealthy("192.0.2.1", "connection refused")
+	outliers.Eject("192.0.2.2", time.Now().Add(time.Hour))
+	saveHealthState(path, hc, outliers)
+
+	// A restart with fewer configured IPs only restores the ones still present
+	restoredHC, restoredOutliers := newSources()
+	restoreHealthState(path, ips[:1], restoredHC, restoredOutliers)
+	if restoredHC.IsHealthy("192.0.2.1") {
+		t.Error("unhealthy IP should stay unhealthy after restart")
+	}
+	if !restoredOutliers.IsHealthy("192.0.2.2") {
+		t.Error("IP no longer configured should not be restored")
+	}
+
+	restoredHC, restoredOutliers = newSources()
+	restoreHealthState(path, ips, restoredHC, restoredOutliers)
+	if restoredOutliers.IsHealthy("192.0.2.2") {
+		t.Error("ejection should outlive the restart")
+	}
+	if !restoredHC.IsHealthy("192.0.2.3") || !restoredOutliers.IsHealthy("192.0.2.3") {
+		t.Error("healthy IP should stay healthy")
+	}
+}
+
+func TestRestoreHealthState_MissingFile(t *testing.T) {
+	hc := health.NewHealthChecker(health.HealthCheckerConfig{IPs: []string{"192.0.2.1"}})
+	restoreHealthState(filepath.Join(t.TempDir(), "missing.json"), []string{"192.0.2.1"}, hc, nil)
+	if !hc.IsHealthy("192.0.2.1") {
+		t.Error("a missing state file should leave IPs healthy")
+	}
+}
@@ -3,6 +3,7 @@ package main
 
 import (
 	"context"
+	"crypto/x509"
 	"errors"
 	"net/http"
 	"os"
@@ -10,13 +11,18 @@ import (
 	"syscall"
 	"time"
 
+	"github.com/cr0hn/outbound-lb/internal/activation"
+	"github.com/cr0hn/outbound-lb/internal/auth"
 	"github.com/cr0hn/outbound-lb/internal/balancer"
 	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/internal/etw"
 	"github.com/cr0hn/outbound-lb/internal/health"
 	"github.com/cr0hn/outbound-lb/internal/limiter"
 	"github.com/cr0hn/outbound-lb/internal/logger"
 	"github.com/cr0hn/outbound-lb/internal/metrics"
 	"github.com/cr0hn/outbound-lb/internal/proxy"
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
 )
 
 // Version information set via ldflags at build time.
@@ -27,15 +33,31 @@ var (
 )
 
 func main() {
+	defer recoverFatal()
+
+	// Subcommands
+	if len(os.Args) > 1 && os.Args[1] == "status" {
+		os.Exit(runStatus(os.Args[2:]))
+	}
+	if len(os.Args) > 1 && os.Args[1] == "drain" {
+		os.Exit(runDrain(os.Args[2:]))
+	}
+	if len(os.Args) > 1 && os.Args[1] == "tail" {
+		os.Exit(runTail(os.Args[2:]))
+	}
+	if len(os.Args) > 1 && os.Args[1] == "maintenance" {
+		os.Exit(runMaintenance(os.Args[2:]))
+	}
+
 	// Parse configuration
 	cfg, err := config.ParseFlags()
 	if err != nil {
-		logger.Error("failed to parse configuration", "error", err)
-		os.Exit(1)
+		fatal(exitConfig, "failed to parse configuration", err)
 	}
 
 	// Initialize logger
 	logger.Init(cfg.LogLevel, cfg.LogFormat)
+	logger.SetModuleLevels(cfg.ModuleLogLevels())
 	logger.Info("outbound-lb starting",
 		"version", version,
 		"commit", commit,
@@ -43,6 +65,10 @@ func main() {
 		"ips", cfg.IPs,
 		"port", cfg.Port,
 		"metrics_port", cfg.MetricsPort,
+		"socks5_port", cfg.SOCKS5Port,
+		"transparent_port", cfg.TransparentPort,
+		"forward_port", cfg.ForwardPort,
+		"public_status_port", cfg.PublicStatusPort,
 	)
 
 	// Create components
@@ -52,19 +78,36 @@ func main() {
 	// Create health checker if enabled
 	var healthChecker *health.HealthChecker
 	if cfg.HealthCheckEnabled {
-		var checker health.Checker
-		switch cfg.HealthCheckType {
-		case "http":
-			checker = health.NewHTTPChecker(cfg.HealthCheckTarget, cfg.HealthCheckTimeout)
-			logger.Info("health_check_configured", "type", "http", "target", cfg.HealthCheckTarget)
-		default:
-			checker = health.NewTCPChecker(cfg.HealthCheckTarget, cfg.HealthCheckTimeout)
-			logger.Info("health_check_configured", "type", "tcp", "target", cfg.HealthCheckTarget)
+		newChecker := func(target string, route netutil.Route) health.Checker {
+			if cfg.HealthCheckType == "http" {
+				return health.NewHTTPChecker(target, cfg.HealthCheckTimeout,
+					health.WithExpectedStatus(cfg.HealthCheckExpectedStatus),
+					health.WithExpectedBody(cfg.HealthCheckExpectedBody),
+					health.WithHTTPRoute(route))
+			}
+			return health.NewTCPChecker(target, cfg.HealthCheckTimeout, health.WithTCPRoute(route))
+		}
+		logger.Info("health_check_configured", "type", cfg.HealthCheckType, "target", cfg.HealthCheckTarget)
+
+		// Backends may probe their own target, e.g. when uplinks reach different
+		// networks, and probe through their own routing table
+		checkers := make(map[string]health.Checker)
+		routes := cfg.Routes()
+		for _, b := range cfg.Backends {
+			target := cfg.HealthCheckTarget
+			if b.HealthCheckTarget != "" {
+				target = b.HealthCheckTarget
+				logger.Info("health_check_configured", "ip", b.IP, "type", cfg.HealthCheckType, "target", b.HealthCheckTarget)
+			}
+			if route, ok := routes[b.IP]; ok || b.HealthCheckTarget != "" {
+				checkers[b.IP] = newChecker(target, route)
+			}
 		}
 
 		healthChecker = health.NewHealthChecker(health.HealthCheckerConfig{
 			IPs:              cfg.IPs,
-			Checker:          checker,
+			Checker:          newChecker(cfg.HealthCheckTarget, netutil.Route{}),
+			Checkers:         checkers,
 			Interval:         cfg.HealthCheckInterval,
 			Timeout:          cfg.HealthCheckTimeout,
 			FailureThreshold: cfg.HealthCheckFailureThreshold,
@@ -73,19 +116,227 @@ func main() {
 		healthChecker.Start()
 	}
 
+	// Create keep-warm prober if enabled
+	var keepWarm *health.KeepWarm
+	if cfg.KeepWarmInterval > 0 {
+		keepWarm = health.NewKeepWarm(health.KeepWarmConfig{
+			IPs:          cfg.IPs,
+			Checker:      health.NewTCPChecker(cfg.KeepWarmTarget, cfg.HealthCheckTimeout),
+			Interval:     cfg.KeepWarmInterval,
+			Timeout:      cfg.HealthCheckTimeout,
+			LastActivity: stats.LastActivity,
+		})
+		keepWarm.Start()
+	}
+
+	// Create passive outlier detector if enabled
+	var outliers *balancer.OutlierDetector
+	if cfg.OutlierErrorRate > 0 {
+		outliers = balancer.NewOutlierDetector(balancer.OutlierConfig{
+			ErrorRate:    cfg.OutlierErrorRate,
+			MinRequests:  cfg.OutlierMinRequests,
+			Window:       cfg.OutlierWindow,
+			EjectionTime: cfg.OutlierEjectionTime,
+		})
+	}
+
+	// Create 429/403 quarantine if enabled
+	var quarantine *balancer.Quarantine
+	if cfg.QuarantineBlockRate > 0 {
+		quarantine = balancer.NewQuarantine(balancer.QuarantineConfig{
+			BlockRate:    cfg.QuarantineBlockRate,
+			MinResponses: cfg.QuarantineMinResponses,
+			Window:       cfg.QuarantineWindow,
+			Duration:     cfg.QuarantineTime,
+		})
+	}
+
+	// Create per-IP circuit breaker if enabled
+	var breaker *balancer.CircuitBreaker
+	if cfg.CircuitBreakerEnabled {
+		breaker = balancer.NewCircuitBreaker(balancer.CircuitBreakerConfig{
+			FailureThreshold: cfg.CBFailureThreshold,
+			SuccessThreshold: cfg.CBSuccessThreshold,
+			Timeout:          cfg.CBTimeout,
+		})
+	}
+
+	// Create exit IP verifier if enabled
+	var exitVerifier *health.ExitVerifier
+	if cfg.ExitIPCheckURL != "" {
+		exitVerifier = health.NewExitVerifier(health.ExitVerifierConfig{
+			IPs:      cfg.IPs,
+			URL:      cfg.ExitIPCheckURL,
+			Interval: cfg.ExitIPCheckInterval,
+			Timeout:  cfg.HealthCheckTimeout,
+			Eject:    cfg.ExitIPCheckEject,
+		})
+		exitVerifier.Start()
+	}
+
+	// Restore health state from the previous run before traffic flows
+	var stopHealthState func()
+	if cfg.HealthStateFile != "" {
+		restoreHealthState(cfg.HealthStateFile, cfg.IPs, healthChecker, outliers)
+		stopHealthState = startHealthStateSaver(cfg.HealthStateFile, cfg.HealthCheckInterval, healthChecker, outliers)
+	}
+
+	// An IP stays in rotation only while every enabled health source agrees
+	var healthSources []balancer.IPHealthChecker
+	if healthChecker != nil {
+		healthSources = append(healthSources, healthChecker)
+	}
+	if outliers != nil {
+		healthSources = append(healthSources, outliers)
+	}
+	if breaker != nil {
+		healthSources = append(healthSources, breaker)
+	}
+	if quarantine != nil {
+		healthSources = append(healthSources, quarantine)
+	}
+	if exitVerifier != nil && cfg.ExitIPCheckEject {
+		healthSources = append(healthSources, exitVerifier)
+	}
+	ipHealth := balancer.CombineHealth(healthSources...)
+
 	balCfg := balancer.Config{
-		IPs:           cfg.IPs,
-		HistoryWindow: int64(cfg.HistoryWindow.Seconds()),
-		HistorySize:   cfg.HistorySize,
-		Limiter:       lim,
-		HealthChecker: healthChecker,
+		IPs:             cfg.IPs,
+		HistoryWindow:   int64(cfg.HistoryWindow.Seconds()),
+		HistorySize:     cfg.HistorySize,
+		Limiter:         lim,
+		HealthChecker:   ipHealth,
+		SlowStartWindow: cfg.SlowStartWindow,
 	}
 	bal := balancer.New(balCfg)
 	bal.Start()
 
 	// Create servers
 	proxyServer := proxy.NewServer(cfg, bal, lim, stats)
+	proxyServer.SetOutlierDetector(outliers)
+	proxyServer.SetCircuitBreaker(breaker)
+	proxyServer.SetQuarantine(quarantine)
+	if exitVerifier != nil {
+		proxyServer.SetExitLookup(exitVerifier.Exit)
+	}
+	if err := proxyServer.OpenAccessLog(); err != nil {
+		fatal(exitConfig, "failed to open access log", err)
+	}
+	var traceExporter *tracing.Exporter
+	if cfg.OTLPEndpoint != "" {
+		traceExporter = tracing.NewExporter(tracing.ExporterConfig{
+			Endpoint:    cfg.OTLPEndpoint,
+			ServiceName: cfg.OTLPServiceName,
+		})
+		traceExporter.Start()
+		proxyServer.SetTracer(tracing.NewTracer(traceExporter))
+	}
+	if cfg.AuthFile != "" {
+		users, err := auth.LoadUsersFile(cfg.AuthFile)
+		if err != nil {
+			fatal(exitConfig, "failed to load auth file", err)
+		}
+		logger.Info("auth_file_loaded", "path", cfg.AuthFile, "users", users.Len())
+		proxyServer.SetUsers(users)
+	}
+	// Reload the users file on change, keeping live tunnels open
+	var usersWatcher *auth.UsersWatcher
+	if cfg.AuthFile != "" {
+		var watcherErr error
+		usersWatcher, watcherErr = auth.NewUsersWatcher(cfg.AuthFile, proxyServer.SetUsers)
+		if watcherErr != nil {
+			logger.Error("failed to create auth file watcher", "error", watcherErr)
+		} else if startErr := usersWatcher.Start(); startErr != nil {
+			logger.Error("failed to start auth file watcher", "error", startErr)
+		}
+	}
+	// Apply the policy file and reload it on change
+	var policyWatcher *config.PolicyWatcher
+	if cfg.PolicyFile != "" {
+		policy, err := cfg.LoadPolicyFile(cfg.PolicyFile)
+		if err != nil {
+			fatal(exitConfig, "failed to load policy file", err)
+		}
+		logger.Info("policy_file_loaded", "path", cfg.PolicyFile, "rules", len(policy.Rules))
+		proxyServer.SetPolicy(policy)
+		var watcherErr error
+		policyWatcher, watcherErr = config.NewPolicyWatcher(cfg.PolicyFile, cfg, proxyServer.SetPolicy)
+		if watcherErr != nil {
+			logger.Error("failed to create policy file watcher", "error", watcherErr)
+		} else if startErr := policyWatcher.Start(); startErr != nil {
+			logger.Error("failed to start policy file watcher", "error", startErr)
+		}
+	}
+	if cfg.AuthWebhook != "" {
+		proxyServer.SetAuthWebhook(auth.NewWebhookAuthenticator(auth.WebhookConfig{
+			URL:     cfg.AuthWebhook,
+			Timeout: cfg.AuthWebhookTimeout,
+		}))
+	}
+	if cfg.AuthPAMService != "" {
+		pam, err := auth.NewPAMAuthenticator(cfg.AuthPAMService)
+		if err != nil {
+			fatal(exitConfig, "failed to enable pam authentication", err)
+		}
+		logger.Info("auth_pam_enabled", "service", cfg.AuthPAMService)
+		proxyServer.SetPAM(pam)
+	}
+	if cfg.TLSClientCAFile != "" {
+		caPEM, err := os.ReadFile(cfg.TLSClientCAFile)
+		if err != nil {
+			fatal(exitConfig, "failed to load tls client ca file", err)
+		}
+		clientCAs := x509.NewCertPool()
+		if !clientCAs.AppendCertsFromPEM(caPEM) {
+			fatal(exitConfig, "failed to load tls client ca file", errors.New("no PEM certificates in "+cfg.TLSClientCAFile))
+		}
+		proxyServer.SetClientCAs(clientCAs)
+	}
+	var jwtVerifier *auth.JWTVerifier
+	if cfg.JWTEnabled() {
+		jwtVerifier = auth.NewJWTVerifier(auth.JWTConfig{
+			Secret:   cfg.JWTSecret,
+			JWKSURL:  cfg.JWTJWKSURL,
+			Issuer:   cfg.JWTIssuer,
+			Audience: cfg.JWTAudience,
+			Refresh:  cfg.JWTJWKSRefresh,
+		})
+		if err := jwtVerifier.Start(); err != nil {
+			fatal(exitConfig, "failed to load jwks", err)
+		}
+		proxyServer.SetJWTVerifier(jwtVerifier)
+	}
 	metricsServer := metrics.NewServer(cfg.MetricsPort, stats)
+	metricsServer.SetAdminToken(cfg.AdminToken)
+	metricsServer.HandleAdmin("/admin/slo", proxyServer.SLOs().Handler())
+	metricsServer.HandleAdmin("/admin/drain", proxyServer.DrainHandler())
+	metricsServer.HandleAdmin("/admin/dns", proxyServer.Resolver().Handler())
+	metricsServer.HandleAdmin("/admin/pins", proxyServer.PinHandler())
+	metricsServer.HandleAdmin("/admin/pools", proxyServer.PoolHandler())
+	metricsServer.HandleAdmin("/admin/maintenance", proxyServer.MaintenanceHandler())
+	metricsServer.HandleAdmin("/admin/quarantine", proxyServer.QuarantineHandler())
+	metricsServer.HandleAdmin("/admin/status", proxyServer.StatusHandler(ipHealth))
+	metricsServer.HandleAdmin("/admin/tail", proxyServer.TailHandler())
+	metricsServer.HandleAdmin("/admin/users", proxyServer.UsersHandler())
+
+	// Publish counters to ETW if enabled (Windows only)
+	var etwPublisher *etw.Publisher
+	if cfg.ETWInterval > 0 {
+		var etwErr error
+		etwPublisher, etwErr = etw.NewPublisher(cfg.ETWInterval, func() etw.Snapshot {
+			s := stats.GetStats()
+			return etw.Snapshot{
+				ActiveTunnels:     proxyServer.ActiveTunnels(),
+				ActiveConnections: s.ActiveConnections,
+				Bytes:             s.BytesSent + s.BytesReceived,
+			}
+		})
+		if etwErr != nil {
+			logger.Warn("etw publisher disabled", "error", etwErr)
+		} else {
+			etwPublisher.Start()
+		}
+	}
 
 	// Set up config watcher if config file is specified
 	var cfgWatcher *config.ConfigWatcher
@@ -99,12 +350,16 @@ func main() {
 			cfgWatcher.RegisterCallback(func(newCfg *config.Config) {
 				// Reconfigure logger
 				logger.Reconfigure(newCfg.LogLevel, newCfg.LogFormat)
+				logger.SetModuleLevels(newCfg.ModuleLogLevels())
 
 				// Update limiter
 				lim.UpdateLimits(newCfg.MaxConnsPerIP, newCfg.MaxConnsTotal)
 
 				// Update balancer history config
 				bal.UpdateHistoryConfig(newCfg.HistoryWindow, newCfg.HistorySize)
+
+				// Apply maintenance drains
+				proxyServer.SetConfigDrains(newCfg.Drain)
 			})
 
 			if startErr := cfgWatcher.Start(); startErr != nil {
@@ -113,31 +368,118 @@ func main() {
 		}
 	}
 
+	// Use sockets passed by systemd socket activation, if any
+	activated, err := activation.Listeners()
+	if err != nil {
+		fatal(exitBind, "socket activation failed", err)
+	}
+	enabled := map[string]bool{
+		activation.Proxy:       true,
+		activation.Metrics:     true,
+		activation.SOCKS5:      cfg.SOCKS5Port > 0,
+		activation.Transparent: cfg.TransparentPort > 0,
+		activation.Forward:     cfg.ForwardPort > 0,
+		activation.Status:      cfg.PublicStatusPort > 0,
+	}
+	for name := range activated {
+		if !enabled[name] {
+			logger.Warn("activated socket ignored, listener is disabled", "listener", name)
+		}
+	}
+	proxyServer.SetActivatedListeners(activated)
+
 	// Start metrics server
 	go func() {
+		defer recoverFatal()
 		logger.Info("starting metrics server", "port", cfg.MetricsPort)
-		if err := metricsServer.Start(); err != nil && !errors.Is(err, http.ErrServerClosed) {
+		start := metricsServer.Start
+		if l, ok := activated[activation.Metrics]; ok {
+			start = func() error { return metricsServer.Serve(l) }
+		}
+		if err := start(); err != nil && !errors.Is(err, http.ErrServerClosed) {
 			logger.Error("metrics server error", "error", err)
 		}
 	}()
 
 	// Start proxy server
 	go func() {
+		defer recoverFatal()
 		metricsServer.SetReady(true)
 		if err := proxyServer.Start(); err != nil && !errors.Is(err, http.ErrServerClosed) {
-			logger.Error("proxy server error", "error", err)
-			os.Exit(1)
+			fatal(listenExitCode(err), "proxy server error", err)
 		}
 	}()
 
+	// Start SOCKS5 listener if enabled
+	if cfg.SOCKS5Port > 0 {
+		go func() {
+			defer recoverFatal()
+			if err := proxyServer.StartSOCKS5(); err != nil {
+				fatal(listenExitCode(err), "socks5 server error", err)
+			}
+		}()
+	}
+
+	// Start DNS forwarder if enabled
+	if cfg.DNSForwarderPort > 0 {
+		go func() {
+			defer recoverFatal()
+			if err := proxyServer.StartDNSForwarder(); err != nil {
+				fatal(listenExitCode(err), "dns forwarder error", err)
+			}
+		}()
+	}
+
+	// Start transparent listener if enabled
+	if cfg.TransparentPort > 0 {
+		go func() {
+			defer recoverFatal()
+			if err := proxyServer.StartTransparent(); err != nil {
+				fatal(listenExitCode(err), "transparent server error", err)
+			}
+		}()
+	}
+
+	// Start port-forward listener if enabled
+	if cfg.ForwardPort > 0 {
+		go func() {
+			defer recoverFatal()
+			if err := proxyServer.StartForward(); err != nil {
+				fatal(listenExitCode(err), "port-forward server error", err)
+			}
+		}()
+	}
+
+	// Start public status page if enabled
+	if cfg.PublicStatusPort > 0 {
+		go func() {
+			defer recoverFatal()
+			if err := proxyServer.StartPublicStatus(ipHealth); err != nil {
+				fatal(listenExitCode(err), "public status server error", err)
+			}
+		}()
+	}
+
 	// Set up signal handling
 	sigCh := make(chan os.Signal, 1)
-	signal.Notify(sigCh, syscall.SIGINT, syscall.SIGTERM, syscall.SIGHUP)
+	signal.Notify(sigCh, append([]os.Signal{syscall.SIGINT, syscall.SIGTERM, syscall.SIGHUP}, maintenanceSignals...)...)
 
 	// Wait for signals
 	for {
 		sig := <-sigCh
 
+		// SIGUSR1 and SIGUSR2 are the kill switch: start or end maintenance
+		if on, ok := maintenanceSignal(sig); ok {
+			logger.Warn("received maintenance signal", "signal", sig, "maintenance", on)
+			if err := proxyServer.SetMaintenance("", on); err != nil {
+				logger.Error("maintenance toggle failed", "error", err)
+			}
+			if on && cfg.MaintenanceCloseTunnels {
+				proxyServer.CloseTunnels("")
+			}
+			continue
+		}
+
 		// Handle SIGHUP for manual config reload
 		if sig == syscall.SIGHUP {
 			logger.Info("received SIGHUP, reloading configuration")
@@ -145,9 +487,29 @@ func main() {
 				if reloadErr := cfgWatcher.Reload(); reloadErr != nil {
 					logger.Error("config reload failed", "error", reloadErr)
 				}
-			} else {
+			} else if usersWatcher == nil && jwtVerifier == nil && policyWatcher == nil {
 				logger.Warn("config reload requested but no config file specified")
 			}
+			// Credentials are reloaded too, without touching live tunnels
+			if usersWatcher != nil {
+				if reloadErr := usersWatcher.Reload(); reloadErr != nil {
+					logger.Error("auth file reload failed", "error", reloadErr)
+				}
+			}
+			if jwtVerifier != nil {
+				if reloadErr := jwtVerifier.Refresh(); reloadErr != nil {
+					logger.Error("jwks reload failed", "error", reloadErr)
+				}
+			}
+			if policyWatcher != nil {
+				if reloadErr := policyWatcher.Reload(); reloadErr != nil {
+					logger.Error("policy file reload failed", "error", reloadErr)
+				}
+			}
+			// Reopen the access log so rotated files are released
+			if reopenErr := proxyServer.OpenAccessLog(); reopenErr != nil {
+				logger.Error("access log reopen failed", "error", reopenErr)
+			}
 			continue
 		}
 
@@ -160,17 +522,21 @@ func main() {
 	if cfgWatcher != nil {
 		cfgWatcher.Stop()
 	}
+	if usersWatcher != nil {
+		usersWatcher.Stop()
+	}
+	if policyWatcher != nil {
+		policyWatcher.Stop()
+	}
 
 	metricsServer.SetReady(false)
 
-	// Stop accepting new connections
+	// Stop accepting new connections and let in-flight transfers finish
+	proxyServer.Drain(cfg.ShutdownGracePeriod)
+
 	ctx, cancel := context.WithTimeout(context.Background(), 30*time.Second)
 	defer cancel()
 
-	// Wait for active connections
-	logger.Info("waiting for active connections to complete")
-	proxyServer.WaitForConnections(30 * time.Second)
-
 	// Shutdown servers
 	if err := proxyServer.Shutdown(ctx); err != nil {
 		logger.Error("proxy server shutdown error", "error", err)
@@ -178,11 +544,40 @@ func main() {
 
 	bal.Stop()
 
+	if etwPublisher != nil {
+		etwPublisher.Stop()
+	}
+
+	// Persist health state before the checker stops
+	if stopHealthState != nil {
+		stopHealthState()
+	}
+
 	// Stop health checker
 	if healthChecker != nil {
 		healthChecker.Stop()
 	}
 
+	// Stop keep-warm prober
+	if keepWarm != nil {
+		keepWarm.Stop()
+	}
+
+	// Stop exit IP verifier
+	if exitVerifier != nil {
+		exitVerifier.Stop()
+	}
+
+	// Stop JWKS refresh
+	if jwtVerifier != nil {
+		jwtVerifier.Stop()
+	}
+
+	// Send the spans still queued
+	if traceExporter != nil {
+		traceExporter.Stop()
+	}
+
 	if err := metricsServer.Shutdown(ctx); err != nil {
 		logger.Error("metrics server shutdown error", "error", err)
 	}
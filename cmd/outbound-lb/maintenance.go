@@ -0,0 +1,90 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"net/http"
+	"net/url"
+	"os"
+	"strings"
+	"time"
+
+	"github.com/spf13/pflag"
+)
+
+// maintenanceReport is the /admin/maintenance response.
+type maintenanceReport struct {
+	Global        bool     `json:"global"`
+	Pools         []string `json:"pools"`
+	ActiveTunnels int      `json:"active_tunnels"`
+	ClosedTunnels int      `json:"closed_tunnels"`
+}
+
+// runMaintenance implements the maintenance command, the kill switch: it puts
+// the proxy (or with --pool, a named pool) in maintenance through
+// /admin/maintenance on the metrics port, so new connections are refused.
+// --close-tunnels also cuts the tunnels already open and --off ends it.
+func runMaintenance(args []string) int {
+	fs := pflag.NewFlagSet("maintenance", pflag.ContinueOnError)
+	addr := fs.String("addr", "http://127.0.0.1:9090", "Metrics server address")
+	token := adminTokenFlag(fs)
+	pool := fs.String("pool", "", "Named pool to put in maintenance instead of the whole proxy")
+	off := fs.Bool("off", false, "End maintenance")
+	closeTunnels := fs.Bool("close-tunnels", false, "Also close the tunnels already open")
+	fs.Usage = func() {
+		fmt.Fprintln(os.Stderr, "Usage: outbound-lb maintenance [flags]")
+		fs.PrintDefaults()
+	}
+	if err := fs.Parse(args); err != nil {
+		return 2
+	}
+	if fs.NArg() != 0 || (*off && *closeTunnels) {
+		fs.Usage()
+		return 2
+	}
+
+	q := url.Values{}
+	if *pool != "" {
+		q.Set("pool", *pool)
+	}
+	method := http.MethodPost
+	if *off {
+		method = http.MethodDelete
+	} else if *closeTunnels {
+		q.Set("close_tunnels", "true")
+	}
+	endpoint := strings.TrimSuffix(*addr, "/") + "/admin/maintenance?" + q.Encode()
+
+	client := adminClient(5*time.Second, *token)
+	req, err := http.NewRequest(method, endpoint, nil)
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "maintenance: %v\n", err)
+		return 1
+	}
+	resp, err := client.Do(req)
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "maintenance: %v\n", err)
+		return 1
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		fmt.Fprintf(os.Stderr, "maintenance: %s returned %s\n", endpoint, resp.Status)
+		return 1
+	}
+	var report maintenanceReport
+	if err := json.NewDecoder(resp.Body).Decode(&report); err != nil {
+		fmt.Fprintf(os.Stderr, "maintenance: %v\n", err)
+		return 1
+	}
+
+	scope := "proxy"
+	if *pool != "" {
+		scope = "pool " + *pool
+	}
+	if *off {
+		fmt.Printf("%s back in service\n", scope)
+		return 0
+	}
+	fmt.Printf("%s in maintenance, %d tunnels closed, %d still open\n", scope, report.ClosedTunnels, report.ActiveTunnels)
+	return 0
+}
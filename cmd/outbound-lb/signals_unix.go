@@ -0,0 +1,23 @@
+//go:build !windows
+
+package main
+
+import (
+	"os"
+	"syscall"
+)
+
+// maintenanceSignals toggle maintenance of the whole proxy.
+var maintenanceSignals = []os.Signal{syscall.SIGUSR1, syscall.SIGUSR2}
+
+// maintenanceSignal reports whether sig toggles maintenance: SIGUSR1 starts
+// it and SIGUSR2 ends it.
+func maintenanceSignal(sig os.Signal) (on, ok bool) {
+	switch sig {
+	case syscall.SIGUSR1:
+		return true, true
+	case syscall.SIGUSR2:
+		return false, true
+	}
+	return false, false
+}
@@ -0,0 +1,14 @@
+//go:build windows
+
+package main
+
+import "os"
+
+// maintenanceSignals is empty: Windows has no user signals, so maintenance is
+// toggled through /admin/maintenance only.
+var maintenanceSignals []os.Signal
+
+// maintenanceSignal never matches on Windows.
+func maintenanceSignal(os.Signal) (on, ok bool) {
+	return false, false
+}
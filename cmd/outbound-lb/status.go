@@ -0,0 +1,146 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"io"
+	"net/http"
+	"os"
+	"strings"
+	"time"
+
+	"github.com/spf13/pflag"
+
+	"github.com/cr0hn/outbound-lb/internal/proxy"
+)
+
+// ANSI color codes for the status command.
+const (
+	colorReset  = "\033[0m"
+	colorRed    = "\033[31m"
+	colorGreen  = "\033[32m"
+	colorYellow = "\033[33m"
+	colorBold   = "\033[1m"
+)
+
+// statusPrinter writes the status summary, with or without color.
+type statusPrinter struct {
+	w     io.Writer
+	color bool
+}
+
+// paint wraps s in the given color when color output is enabled.
+func (p statusPrinter) paint(color, s string) string {
+	if !p.color {
+		return s
+	}
+	return color + s + colorReset
+}
+
+// runStatus implements the status command: it queries /admin/status on the
+// metrics port twice to derive the request rate and prints a compact summary.
+func runStatus(args []string) int {
+	fs := pflag.NewFlagSet("status", pflag.ContinueOnError)
+	addr := fs.String("addr", "http://127.0.0.1:9090", "Metrics server address")
+	token := adminTokenFlag(fs)
+	interval := fs.Duration("interval", time.Second, "Sampling interval for the request rate")
+	noColor := fs.Bool("no-color", false, "Disable colored output")
+	if err := fs.Parse(args); err != nil {
+		return 2
+	}
+
+	client := adminClient(5*time.Second, *token)
+	url := strings.TrimSuffix(*addr, "/") + "/admin/status"
+
+	first, err := fetchStatus(client, url)
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "status: %v\n", err)
+		return 1
+	}
+	time.Sleep(*interval)
+	second, err := fetchStatus(client, url)
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "status: %v\n", err)
+		return 1
+	}
+	rps := float64(second.TotalRequests-first.TotalRequests) / interval.Seconds()
+
+	p := statusPrinter{w: os.Stdout, color: !*noColor && useColor()}
+	p.print(second, rps)
+	return 0
+}
+
+// fetchStatus retrieves the status summary from url.
+func fetchStatus(client *http.Client, url string) (proxy.Status, error) {
+	var status proxy.Status
+	resp, err := client.Get(url)
+	if err != nil {
+		return status, err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return status, fmt.Errorf("%s returned %s", url, resp.Status)
+	}
+	return status, json.NewDecoder(resp.Body).Decode(&status)
+}
+
+// useColor reports whether stdout is a terminal and NO_COLOR is unset.
+func useColor() bool {
+	if os.Getenv("NO_COLOR") != "" {
+		return false
+	}
+	fi, err := os.Stdout.Stat()
+	return err == nil && fi.Mode()&os.ModeCharDevice != 0
+}
+
+// print writes the summary.
+func (p statusPrinter) print(status proxy.Status, rps float64) {
+	var listeners []string
+	for _, l := range status.Listeners {
+		name := l.Name
+		if l.TLS {
+			name += "+tls"
+		}
+		listeners = append(listeners, fmt.Sprintf("%s :%d", name, l.Port))
+	}
+	fmt.Fprintf(p.w, "%s %s\n", p.paint(colorBold, "Listeners"), strings.Join(listeners, ", "))
+
+	fmt.Fprintf(p.w, "%s %.1f req/s, %d active connections, %d tunnels\n",
+		p.paint(colorBold, "Traffic  "), rps, status.ActiveConnections, status.ActiveTunnels)
+
+	var healthy int
+	for _, e := range status.Egresses {
+		if e.Healthy {
+			healthy++
+		}
+	}
+	summary := fmt.Sprintf("%d/%d healthy", healthy, len(status.Egresses))
+	switch {
+	case healthy == 0:
+		summary = p.paint(colorRed, summary)
+	case healthy < len(status.Egresses):
+		summary = p.paint(colorYellow, summary)
+	default:
+		summary = p.paint(colorGreen, summary)
+	}
+	fmt.Fprintf(p.w, "%s %s\n", p.paint(colorBold, "Egresses "), summary)
+	for _, e := range status.Egresses {
+		state := p.paint(colorGreen, "healthy")
+		switch {
+		case !e.Healthy:
+			state = p.paint(colorRed, "unhealthy")
+		case e.Draining:
+			state = p.paint(colorYellow, "draining")
+		}
+		fmt.Fprintf(p.w, "  %-39s %s  %d conns\n", e.IP, state, e.ActiveConnections)
+	}
+
+	if len(status.TopErrors) == 0 {
+		fmt.Fprintf(p.w, "%s %s\n", p.paint(colorBold, "Errors   "), p.paint(colorGreen, "none"))
+		return
+	}
+	fmt.Fprintf(p.w, "%s\n", p.paint(colorBold, "Errors"))
+	for _, e := range status.TopErrors {
+		fmt.Fprintf(p.w, "  %-8s %s  %d\n", e.Method, p.paint(colorRed, e.Status), e.Count)
+	}
+}
@@ -0,0 +1,148 @@
+package main
+
+import (
+	"bufio"
+	"encoding/json"
+	"fmt"
+	"io"
+	"net/http"
+	"net/url"
+	"os"
+	"strings"
+	"sync"
+	"sync/atomic"
+
+	"github.com/spf13/pflag"
+
+	"github.com/cr0hn/outbound-lb/internal/proxy"
+)
+
+// runTail implements the tail command: it streams access records from
+// /admin/tail on the metrics port of one or more proxies and prints them as
+// they happen, until interrupted or every stream ends.
+func runTail(args []string) int {
+	fs := pflag.NewFlagSet("tail", pflag.ContinueOnError)
+	addrs := fs.StringSlice("addr", []string{"http://127.0.0.1:9090"}, "Metrics server address (repeat or comma-separate for several proxies)")
+	token := adminTokenFlag(fs)
+	user := fs.String("user", "", "Only show records of this proxy username")
+	host := fs.String("host", "", "Only show records for this destination host and its subdomains")
+	egress := fs.String("egress", "", "Only show records through this outbound IP")
+	errorsOnly := fs.Bool("errors", false, "Only show records with a status of 400 or more")
+	raw := fs.Bool("json", false, "Print records as newline-delimited JSON")
+	noColor := fs.Bool("no-color", false, "Disable colored output")
+	if err := fs.Parse(args); err != nil {
+		return 2
+	}
+
+	q := url.Values{}
+	for key, value := range map[string]string{"user": *user, "host": *host, "egress": *egress} {
+		if value != "" {
+			q.Set(key, value)
+		}
+	}
+	if *errorsOnly {
+		q.Set("errors", "true")
+	}
+
+	p := &tailPrinter{
+		w:      os.Stdout,
+		color:  !*noColor && useColor(),
+		json:   *raw,
+		source: len(*addrs) > 1,
+	}
+	client := adminClient(0, *token) // streams have no deadline
+	var wg sync.WaitGroup
+	var failed atomic.Bool
+	for _, addr := range *addrs {
+		wg.Add(1)
+		go func(addr string) {
+			defer wg.Done()
+			endpoint := strings.TrimSuffix(addr, "/") + "/admin/tail?" + q.Encode()
+			if err := streamTail(client, endpoint, func(rec proxy.AccessRecord) { p.print(addr, rec) }); err != nil {
+				fmt.Fprintf(os.Stderr, "tail: %s: %v\n", addr, err)
+				failed.Store(true)
+			}
+		}(addr)
+	}
+	wg.Wait()
+	if failed.Load() {
+		return 1
+	}
+	return 0
+}
+
+// streamTail reads access records from endpoint and passes each to fn.
+func streamTail(client *http.Client, endpoint string, fn func(proxy.AccessRecord)) error {
+	resp, err := client.Get(endpoint)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return fmt.Errorf("%s returned %s", endpoint, resp.Status)
+	}
+	scanner := bufio.NewScanner(resp.Body)
+	for scanner.Scan() {
+		var rec proxy.AccessRecord
+		if err := json.Unmarshal(scanner.Bytes(), &rec); err != nil {
+			return err
+		}
+		fn(rec)
+	}
+	return scanner.Err()
+}
+
+// tailPrinter writes access records, one per line, from several streams.
+type tailPrinter struct {
+	w      io.Writer
+	color  bool
+	json   bool
+	source bool // prefix each line with the proxy it came from
+	mu     sync.Mutex
+}
+
+// paint wraps s in the given color when color output is enabled.
+func (p *tailPrinter) paint(color, s string) string {
+	return statusPrinter{color: p.color}.paint(color, s)
+}
+
+// print writes rec, received from addr.
+func (p *tailPrinter) print(addr string, rec proxy.AccessRecord) {
+	p.mu.Lock()
+	defer p.mu.Unlock()
+	if p.json {
+		line, _ := json.Marshal(rec)
+		fmt.Fprintf(p.w, "%s\n", line)
+		return
+	}
+
+	var b strings.Builder
+	b.WriteString(rec.Time.Local().Format("15:04:05.000"))
+	if p.source {
+		b.WriteString(" " + p.paint(colorBold, "["+tailSource(addr)+"]"))
+	}
+	status := fmt.Sprintf("%d", rec.Status)
+	switch {
+	case rec.Status >= 500:
+		status = p.paint(colorRed, status)
+	case rec.Status >= 400:
+		status = p.paint(colorYellow, status)
+	default:
+		status = p.paint(colorGreen, status)
+	}
+	user := rec.User
+	if user == "" {
+		user = "-"
+	}
+	fmt.Fprintf(&b, " %s %-7s %s %s@%s -> %s %dms in=%d out=%d",
+		status, rec.Method, rec.Host, user, rec.SourceIP, rec.OutboundIP, rec.DurationMS, rec.BytesIn, rec.BytesOut)
+	fmt.Fprintln(p.w, b.String())
+}
+
+// tailSource returns the host of a metrics server address, for line prefixes.
+func tailSource(addr string) string {
+	if u, err := url.Parse(addr); err == nil && u.Host != "" {
+		return u.Hostname()
+	}
+	return addr
+}
@@ -0,0 +1,107 @@
+// Package activation receives listening sockets passed by systemd socket
+// activation (sd_listen_fds), so the service can bind privileged ports without
+// running as root and restart without dropping its listening sockets.
+package activation
+
+import (
+	"errors"
+	"fmt"
+	"net"
+	"os"
+	"strconv"
+	"strings"
+)
+
+// Listener names matched against the socket unit's FileDescriptorName=. The DNS
+// forwarder serves both UDP and TCP on its port and always binds it itself.
+const (
+	Proxy       = "proxy"
+	SOCKS5      = "socks5"
+	Transparent = "transparent"
+	Forward     = "forward"
+	Metrics     = "metrics"
+	Status      = "status"
+)
+
+// listenFDsStart is the first file descriptor passed by systemd.
+const listenFDsStart = 3
+
+// known lists the recognized listener names.
+var known = map[string]bool{Proxy: true, SOCKS5: true, Transparent: true, Forward: true, Metrics: true, Status: true}
+
+// ErrUnsupported is returned when socket activation is not available on this platform.
+var ErrUnsupported = errors.New("socket activation is only supported on unix")
+
+// Listeners returns the sockets passed by systemd keyed by listener name, or
+// nil if the process was not socket-activated. A single socket without a
+// recognized name is used as the proxy listener. The activation variables are
+// unset so child processes don't inherit them.
+func Listeners() (map[string]net.Listener, error) {
+	defer os.Unsetenv("LISTEN_PID")
+	defer os.Unsetenv("LISTEN_FDS")
+	defer os.Unsetenv("LISTEN_FDNAMES")
+
+	n, err := count(os.Getenv("LISTEN_PID"), os.Getenv("LISTEN_FDS"), os.Getpid())
+	if err != nil || n == 0 {
+		return nil, err
+	}
+	names, err := assign(n, os.Getenv("LISTEN_FDNAMES"))
+	if err != nil {
+		return nil, err
+	}
+
+	listeners := make(map[string]net.Listener, n)
+	for i, name := range names {
+		l, err := fileListener(listenFDsStart+i, name)
+		if err != nil {
+			for _, opened := range listeners {
+				opened.Close()
+			}
+			return nil, fmt.Errorf("socket %q: %w", name, err)
+		}
+		listeners[name] = l
+	}
+	return listeners, nil
+}
+
+// count returns the number of passed sockets, or 0 if they are meant for another process.
+func count(pid, fds string, self int) (int, error) {
+	if pid == "" || fds == "" {
+		return 0, nil
+	}
+	if p, err := strconv.Atoi(pid); err != nil || p != self {
+		return 0, nil
+	}
+	n, err := strconv.Atoi(fds)
+	if err != nil || n < 0 {
+		return 0, fmt.Errorf("invalid LISTEN_FDS: %q", fds)
+	}
+	return n, nil
+}
+
+// assign maps each of the n passed sockets to a listener name from the
+// colon-separated LISTEN_FDNAMES value.
+func assign(n int, fdNames string) ([]string, error) {
+	var names []string
+	if fdNames != "" {
+		names = strings.Split(fdNames, ":")
+	}
+	if n == 1 && (len(names) == 0 || !known[names[0]]) {
+		return []string{Proxy}, nil
+	}
+	if len(names) != n {
+		return nil, fmt.Errorf("%d sockets passed but %d names in LISTEN_FDNAMES; set FileDescriptorName= on each socket", n, len(names))
+	}
+
+	seen := make(map[string]bool, n)
+	for _, name := range names {
+		if !known[name] {
+			return nil, fmt.Errorf("unknown socket name %q (want proxy, socks5, transparent, forward, metrics or status)", name)
+		}
+		if seen[name] {
+			return nil, fmt.Errorf("socket name %q passed more than once", name)
+		}
+		seen[name] = true
+	}
+	return names, nil
+}
@@ -0,0 +1,10 @@
+//go:build !unix
+
+package activation
+
+import "net"
+
+// fileListener reports that inherited sockets are not supported on this platform.
+func fileListener(int, string) (net.Listener, error) {
+	return nil, ErrUnsupported
+}
@@ -0,0 +1,70 @@
+package activation
+
+import (
+	"reflect"
+	"testing"
+)
+
+func TestCount(t *testing.T) {
+	tests := []struct {
+		name    string
+		pid     string
+		fds     string
+		want    int
+		wantErr bool
+	}{
+		{"not activated", "", "", 0, false},
+		{"activated", "42", "2", 2, false},
+		{"other process", "7", "2", 0, false},
+		{"invalid count", "42", "x", 0, true},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			got, err := count(tt.pid, tt.fds, 42)
+			if (err != nil) != tt.wantErr {
+				t.Fatalf("count() error = %v, wantErr %v", err, tt.wantErr)
+			}
+			if got != tt.want {
+				t.Errorf("count() = %d, want %d", got, tt.want)
+			}
+		})
+	}
+}
+
+func TestAssign(t *testing.T) {
+	tests := []struct {
+		name    string
+		n       int
+		fdNames string
+		want    []string
+		wantErr bool
+	}{
+		{"single unnamed socket", 1, "", []string{Proxy}, false},
+		{"single default unit name", 1, "outbound-lb.socket", []string{Proxy}, false},
+		{"single named socket", 1, "metrics", []string{Metrics}, false},
+		{"several named sockets", 3, "proxy:socks5:metrics", []string{Proxy, SOCKS5, Metrics}, false},
+		{"missing names", 2, "", nil, true},
+		{"unknown name", 2, "proxy:admin", nil, true},
+		{"duplicate name", 2, "proxy:proxy", nil, true},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			got, err := assign(tt.n, tt.fdNames)
+			if (err != nil) != tt.wantErr {
+				t.Fatalf("assign() error = %v, wantErr %v", err, tt.wantErr)
+			}
+			if !reflect.DeepEqual(got, tt.want) {
+				t.Errorf("assign() = %v, want %v", got, tt.want)
+			}
+		})
+	}
+}
+
+func TestListeners_NotActivated(t *testing.T) {
+	t.Setenv("LISTEN_PID", "")
+	t.Setenv("LISTEN_FDS", "")
+	listeners, err := Listeners()
+	if err != nil || listeners != nil {
+		t.Errorf("Listeners() = %v, %v; want nil, nil", listeners, err)
+	}
+}
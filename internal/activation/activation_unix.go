@@ -0,0 +1,17 @@
+//go:build unix
+
+package activation
+
+import (
+	"net"
+	"os"
+	"syscall"
+)
+
+// fileListener wraps the inherited descriptor fd in a net.Listener.
+func fileListener(fd int, name string) (net.Listener, error) {
+	syscall.CloseOnExec(fd)
+	f := os.NewFile(uintptr(fd), name)
+	defer f.Close()
+	return net.FileListener(f)
+}
@@ -0,0 +1,130 @@
+// Package auth verifies proxy credentials: htpasswd-style users files, JWT bearer tokens and auth webhooks.
+package auth
+
+import (
+	"crypto/hmac"
+	"crypto/rand"
+	"crypto/sha256"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// CredentialCache remembers successful credential checks for a TTL, so clients
+// sending the same credentials on every request don't pay a slow hash
+// verification or backend round trip each time. Entries are keyed on an HMAC
+// of the username and password under a per-process random key, so the cache
+// holds no passwords. Failed checks are never cached.
+type CredentialCache struct {
+	key       []byte
+	ttl       time.Duration
+	entries   map[[sha256.Size]byte]cacheEntry
+	lastSweep time.Time
+	// generation counts Clear calls, so checks that were in flight during
+	// one don't store their result afterwards.
+	generation uint64
+	clock      clock.Clock
+	mu         sync.Mutex
+}
+
+// cacheEntry is a cached successful check and the identity it returned.
+type cacheEntry struct {
+	id      Identity
+	expires time.Time
+}
+
+// NewCredentialCache creates a CredentialCache whose entries last ttl.
+func NewCredentialCache(ttl time.Duration, c clock.Clock) *CredentialCache {
+	c = clock.OrReal(c)
+	key := make([]byte, 32)
+	rand.Read(key)
+	return &CredentialCache{
+		key:       key,
+		ttl:       ttl,
+		entries:   make(map[[sha256.Size]byte]cacheEntry),
+		lastSweep: c.Now(),
+		clock:     c,
+	}
+}
+
+// Check reports whether password is valid for user, calling verify unless a
+// successful check of the same credentials is cached. Safe to call on a nil
+// CredentialCache, which always calls verify.
+func (c *CredentialCache) Check(user, password string, verify func(user, password string) bool) bool {
+	_, ok := c.CheckIdentity(user, password, func(user, password string) (Identity, bool) {
+		return Identity{}, verify(user, password)
+	})
+	return ok
+}
+
+// CheckIdentity is Check for verifiers that also return the user's identity,
+// which is cached along with the successful check. Safe to call on a nil
+// CredentialCache, which always calls verify.
+func (c *CredentialCache) CheckIdentity(user, password string, verify func(user, password string) (Identity, bool)) (Identity, bool) {
+	if c == nil {
+		return verify(user, password)
+	}
+	k := c.digest(user, password)
+	now := c.clock.Now()
+
+	c.mu.Lock()
+	e, ok := c.entries[k]
+	gen := c.generation
+	c.mu.Unlock()
+	if ok && now.Before(e.expires) {
+		metrics.AuthCacheLookups.WithLabelValues("hit").Inc()
+		return e.id, true
+	}
+	metrics.AuthCacheLookups.WithLabelValues("miss").Inc()
+
+	id, ok := verify(user, password)
+	if !ok {
+		return Identity{}, false
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	if c.generation != gen {
+		return id, true
+	}
+	c.sweep(now)
+	c.entries[k] = cacheEntry{id: id, expires: now.Add(c.ttl)}
+	return id, true
+}
+
+// Clear drops every cached check, e.g. after the credentials changed. Checks
+// still in flight are not cached when they complete.
+func (c *CredentialCache) Clear() {
+	if c == nil {
+		return
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	clear(c.entries)
+	c.generation++
+}
+
+// digest returns the cache key of a username and password.
+func (c *CredentialCache) digest(user, password string) [sha256.Size]byte {
+	mac := hmac.New(sha256.New, c.key)
+	mac.Write([]byte(user))
+	mac.Write([]byte{0})
+	mac.Write([]byte(password))
+	var k [sha256.Size]byte
+	mac.Sum(k[:0])
+	return k
+}
+
+// sweep drops expired entries at most once per ttl. The caller holds mu.
+func (c *CredentialCache) sweep(now time.Time) {
+	if now.Sub(c.lastSweep) < c.ttl {
+		return
+	}
+	c.lastSweep = now
+	for k, e := range c.entries {
+		if !now.Before(e.expires) {
+			delete(c.entries, k)
+		}
+	}
+}
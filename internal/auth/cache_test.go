@@ -0,0 +1,110 @@
+package auth
+
+import (
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func TestCredentialCache_Check(t *testing.T) {
+	fake := clock.NewFake(time.Unix(0, 0))
+	c := NewCredentialCache(time.Minute, fake)
+	calls := 0
+	verify := func(user, password string) bool {
+		calls++
+		return user == "alice" && password == "s3cret"
+	}
+
+	for i := 0; i < 3; i++ {
+		if !c.Check("alice", "s3cret", verify) {
+			t.Fatal("expected valid credentials to pass")
+		}
+	}
+	if calls != 1 {
+		t.Errorf("verify called %d times, want 1", calls)
+	}
+
+	// Failures are not cached, and other passwords miss the cache
+	for i := 0; i < 2; i++ {
+		if c.Check("alice", "wrong", verify) {
+			t.Fatal("expected a wrong password to fail")
+		}
+	}
+	if calls != 3 {
+		t.Errorf("verify called %d times, want 3", calls)
+	}
+
+	// Entries expire after the TTL
+	fake.Advance(time.Minute)
+	c.Check("alice", "s3cret", verify)
+	if calls != 4 {
+		t.Errorf("verify called %d times after expiry, want 4", calls)
+	}
+
+	c.Clear()
+	c.Check("alice", "s3cret", verify)
+	if calls != 5 {
+		t.Errorf("verify called %d times after Clear, want 5", calls)
+	}
+}
+
+func TestCredentialCache_ClearDuringCheck(t *testing.T) {
+	c := NewCredentialCache(time.Minute, clock.NewFake(time.Unix(0, 0)))
+	calls := 0
+
+	// A check in flight when the cache is cleared doesn't store its result
+	c.Check("alice", "s3cret", func(string, string) bool {
+		calls++
+		c.Clear()
+		return true
+	})
+	c.Check("alice", "s3cret", func(string, string) bool { calls++; return true })
+	if calls != 2 {
+		t.Errorf("verify called %d times, want 2", calls)
+	}
+}
+
+func TestCredentialCache_Sweep(t *testing.T) {
+	fake := clock.NewFake(time.Unix(0, 0))
+	c := NewCredentialCache(time.Minute, fake)
+	allow := func(string, string) bool { return true }
+
+	c.Check("alice", "a", allow)
+	fake.Advance(2 * time.Minute)
+	c.Check("bob", "b", allow)
+	if len(c.entries) != 1 {
+		t.Errorf("entries = %d after sweep, want 1", len(c.entries))
+	}
+}
+
+func TestCredentialCache_Nil(t *testing.T) {
+	var c *CredentialCache
+	calls := 0
+	verify := func(string, string) bool { calls++; return true }
+	c.Check("alice", "s3cret", verify)
+	c.Check("alice", "s3cret", verify)
+	if calls != 2 {
+		t.Errorf("verify called %d times through a nil cache, want 2", calls)
+	}
+	c.Clear()
+}
+
+func TestCredentialCache_CheckIdentity(t *testing.T) {
+	c := NewCredentialCache(time.Minute, clock.NewFake(time.Unix(0, 0)))
+	calls := 0
+	verify := func(user, password string) (Identity, bool) {
+		calls++
+		return Identity{User: user, Pool: "premium"}, password == "s3cret"
+	}
+
+	for i := 0; i < 2; i++ {
+		id, ok := c.CheckIdentity("alice", "s3cret", verify)
+		if !ok || id.Pool != "premium" {
+			t.Fatalf("CheckIdentity() = %+v, %v; want the premium identity", id, ok)
+		}
+	}
+	if calls != 1 {
+		t.Errorf("verify called %d times, want 1", calls)
+	}
+}
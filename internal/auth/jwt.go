@@ -0,0 +1,534 @@
+// Package auth verifies proxy credentials: htpasswd-style users files, JWT bearer tokens and auth webhooks.
+package auth
+
+import (
+	"context"
+	"crypto"
+	"crypto/ecdh"
+	"crypto/ecdsa"
+	"crypto/elliptic"
+	"crypto/hmac"
+	"crypto/rsa"
+	"crypto/sha256"
+	"crypto/sha512"
+	"encoding/base64"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"hash"
+	"io"
+	"math/big"
+	"net/http"
+	"slices"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+// Errors returned by JWT verification.
+var (
+	// ErrInvalidToken is returned for malformed tokens and bad signatures.
+	ErrInvalidToken = errors.New("invalid token")
+	// ErrTokenExpired is returned for tokens outside their exp/nbf window.
+	ErrTokenExpired = errors.New("token expired or not yet valid")
+)
+
+const (
+	// jwtLeeway tolerates clock skew when checking exp and nbf.
+	jwtLeeway = 30 * time.Second
+	// jwksMinRefetch bounds how often a token with an unknown key ID may
+	// trigger an out-of-band JWKS fetch.
+	jwksMinRefetch = time.Minute
+	// maxJWKSBody bounds the JWKS document read from the JWKS URL.
+	maxJWKSBody = 1 << 20
+	// defaultJWKSTimeout bounds a JWKS fetch when JWTConfig.Timeout is unset.
+	defaultJWKSTimeout = 10 * time.Second
+)
+
+// Identity is an authenticated proxy user and the routing attributes its
+// credential carries.
+type Identity struct {
+	// User is the proxy username, from the token's "sub" claim.
+	User string
+	// Pool is the named pool the user's connections use, from the "pool" claim.
+	Pool string
+	// Rate caps the user's requests per second, from the "rate" claim (0 = unlimited).
+	Rate float64
+	// MaxConns caps the user's concurrent connections, from the "max_conns" claim (0 = unlimited).
+	MaxConns int
+	// MonthlyBytes caps the user's traffic per calendar month, from the "monthly_bytes" claim (0 = unlimited).
+	MonthlyBytes int64
+}
+
+// JWTConfig holds configuration for JWTVerifier.
+type JWTConfig struct {
+	// Secret verifies HS256, HS384 and HS512 tokens.
+	Secret string
+	// JWKSURL serves the RSA and EC keys that verify RS*, PS* and ES* tokens.
+	JWKSURL string
+	// Issuer, if set, must match the token's "iss" claim.
+	Issuer string
+	// Audience, if set, must be one of the token's "aud" values.
+	Audience string
+	// Refresh is how often the JWKS is fetched again.
+	Refresh time.Duration
+	// Timeout bounds each JWKS fetch (default 10s).
+	Timeout time.Duration
+	// Clock checks token expiry and refetch intervals. Nil means the real clock.
+	Clock clock.Clock
+}
+
+// JWTVerifier validates bearer tokens against a shared secret or the keys of a
+// JWKS URL, which it keeps refreshed in the background.
+type JWTVerifier struct {
+	config    JWTConfig
+	client    *http.Client
+	keys      map[string]crypto.PublicKey
+	lastFetch time.Time // last successful fetch or refetch attempt
+	stopCh    chan struct{}
+	wg        sync.WaitGroup
+	mu        sync.RWMutex
+	fetchMu   sync.Mutex
+}
+
+// NewJWTVerifier creates a new JWTVerifier.
+func NewJWTVerifier(cfg JWTConfig) *JWTVerifier {
+	if cfg.Clock == nil {
+		cfg.Clock = clock.Real
+	}
+	if cfg.Timeout <= 0 {
+		cfg.Timeout = defaultJWKSTimeout
+	}
+	return &JWTVerifier{
+		config: cfg,
+		client: &http.Client{Timeout: cfg.Timeout},
+		stopCh: make(chan struct{}),
+	}
+}
+
+// Start fetches the JWKS, if configured, then refreshes it every Refresh.
+// Later fetch failures keep the previous keys.
+func (v *JWTVerifier) Start() error {
+	if v.config.JWKSURL == "" {
+		return nil
+	}
+	if err := v.fetch(); err != nil {
+		return err
+	}
+	if v.config.Refresh <= 0 {
+		return nil
+	}
+	v.wg.Add(1)
+	go v.run()
+	return nil
+}
+
+// Refresh fetches the JWKS now, e.g. on SIGHUP after a key was revoked. A
+// failed fetch keeps the previous keys. It is a no-op without a JWKS URL.
+func (v *JWTVerifier) Refresh() error {
+	if v.config.JWKSURL == "" {
+		return nil
+	}
+	return v.fetch()
+}
+
+// Stop stops refreshing the JWKS.
+func (v *JWTVerifier) Stop() {
+	select {
+	case <-v.stopCh:
+	default:
+		close(v.stopCh)
+	}
+	v.wg.Wait()
+}
+
+func (v *JWTVerifier) run() {
+	defer v.wg.Done()
+	ticker := time.NewTicker(v.config.Refresh)
+	defer ticker.Stop()
+	for {
+		select {
+		case <-v.stopCh:
+			return
+		case <-ticker.C:
+			if err := v.fetch(); err != nil {
+				log.Warn("jwks_refresh_failed", "url", v.config.JWKSURL, "error", err)
+			}
+		}
+	}
+}
+
+// fetch replaces the keys with those of the JWKS URL.
+func (v *JWTVerifier) fetch() error {
+	v.fetchMu.Lock()
+	defer v.fetchMu.Unlock()
+
+	ctx, cancel := context.WithTimeout(context.Background(), v.config.Timeout)
+	defer cancel()
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, v.config.JWKSURL, nil)
+	if err != nil {
+		return err
+	}
+	resp, err := v.client.Do(req)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return fmt.Errorf("jwks: unexpected status %d", resp.StatusCode)
+	}
+	body, err := io.ReadAll(io.LimitReader(resp.Body, maxJWKSBody))
+	if err != nil {
+		return err
+	}
+	keys, err := ParseJWKS(body)
+	if err != nil {
+		return err
+	}
+
+	v.mu.Lock()
+	v.keys = keys
+	v.lastFetch = v.config.Clock.Now()
+	v.mu.Unlock()
+	log.Debug("jwks_loaded", "url", v.config.JWKSURL, "keys", len(keys))
+	return nil
+}
+
+// key returns the JWKS key with the given ID. A token without a key ID
+// matches the only key of a single-key set. An unknown ID triggers a fetch,
+// at most once per jwksMinRefetch, to pick up rotated keys. Failed fetches
+// count too, so tokens with random key IDs can't hammer the JWKS URL or
+// queue every request behind it while it is down.
+func (v *JWTVerifier) key(kid string) (crypto.PublicKey, bool) {
+	v.mu.Lock()
+	k, ok := lookupKey(v.keys, kid)
+	now := v.config.Clock.Now()
+	refetch := !ok && v.config.JWKSURL != "" && now.Sub(v.lastFetch) >= jwksMinRefetch
+	if refetch {
+		v.lastFetch = now
+	}
+	v.mu.Unlock()
+	if !refetch {
+		return k, ok
+	}
+	if err := v.fetch(); err != nil {
+		log.Warn("jwks_refresh_failed", "url", v.config.JWKSURL, "error", err)
+		return nil, false
+	}
+	v.mu.RLock()
+	defer v.mu.RUnlock()
+	return lookupKey(v.keys, kid)
+}
+
+func lookupKey(keys map[string]crypto.PublicKey, kid string) (crypto.PublicKey, bool) {
+	if kid == "" && len(keys) == 1 {
+		for _, k := range keys {
+			return k, true
+		}
+	}
+	k, ok := keys[kid]
+	return k, ok
+}
+
+// jwtHeader is the JOSE header of a token.
+type jwtHeader struct {
+	Alg string `json:"alg"`
+	Kid string `json:"kid"`
+}
+
+// jwtClaims are the registered, routing and limit claims of a token.
+type jwtClaims struct {
+	Subject      string      `json:"sub"`
+	Issuer       string      `json:"iss"`
+	Audience     audience    `json:"aud"`
+	ExpiresAt    *float64    `json:"exp"`
+	NotBefore    *float64    `json:"nbf"`
+	Pool         string      `json:"pool"`
+	Rate         json.Number `json:"rate"`
+	MaxConns     json.Number `json:"max_conns"`
+	MonthlyBytes json.Number `json:"monthly_bytes"`
+}
+
+// audience is an "aud" claim, which is either a string or an array of strings.
+type audience []string
+
+func (a *audience) UnmarshalJSON(data []byte) error {
+	var single string
+	if err := json.Unmarshal(data, &single); err == nil {
+		*a = audience{single}
+		return nil
+	}
+	var list []string
+	if err := json.Unmarshal(data, &list); err != nil {
+		return err
+	}
+	*a = list
+	return nil
+}
+
+// Verify checks a compact-serialized JWT's signature and claims and returns
+// the identity it asserts. Tokens must carry a "sub" claim and an "exp" claim.
+func (v *JWTVerifier) Verify(token string) (Identity, error) {
+	parts := strings.Split(token, ".")
+	if len(parts) != 3 {
+		return Identity{}, ErrInvalidToken
+	}
+	var header jwtHeader
+	if err := decodeSegment(parts[0], &header); err != nil {
+		return Identity{}, ErrInvalidToken
+	}
+	sig, err := base64.RawURLEncoding.DecodeString(parts[2])
+	if err != nil {
+		return Identity{}, ErrInvalidToken
+	}
+	if !v.verifySignature(header, parts[0]+"."+parts[1], sig) {
+		return Identity{}, ErrInvalidToken
+	}
+
+	var claims jwtClaims
+	if err := decodeSegment(parts[1], &claims); err != nil {
+		return Identity{}, ErrInvalidToken
+	}
+	now := v.config.Clock.Now()
+	if claims.ExpiresAt == nil || now.After(unixTime(*claims.ExpiresAt).Add(jwtLeeway)) {
+		return Identity{}, ErrTokenExpired
+	}
+	if claims.NotBefore != nil && now.Before(unixTime(*claims.NotBefore).Add(-jwtLeeway)) {
+		return Identity{}, ErrTokenExpired
+	}
+	if v.config.Issuer != "" && claims.Issuer != v.config.Issuer {
+		return Identity{}, fmt.Errorf("%w: issuer %q", ErrInvalidToken, claims.Issuer)
+	}
+	if v.config.Audience != "" && !slices.Contains(claims.Audience, v.config.Audience) {
+		return Identity{}, fmt.Errorf("%w: audience", ErrInvalidToken)
+	}
+	if claims.Subject == "" {
+		return Identity{}, fmt.Errorf("%w: missing sub", ErrInvalidToken)
+	}
+
+	id := Identity{User: claims.Subject, Pool: claims.Pool}
+	if claims.Rate != "" {
+		rate, err := claims.Rate.Float64()
+		if err != nil || rate < 0 {
+			return Identity{}, fmt.Errorf("%w: rate %q", ErrInvalidToken, claims.Rate)
+		}
+		id.Rate = rate
+	}
+	maxConns, err := limitClaim("max_conns", claims.MaxConns)
+	if err != nil {
+		return Identity{}, err
+	}
+	id.MaxConns = int(maxConns)
+	if id.MonthlyBytes, err = limitClaim("monthly_bytes", claims.MonthlyBytes); err != nil {
+		return Identity{}, err
+	}
+	return id, nil
+}
+
+// limitClaim parses a non-negative integer limit claim. A missing claim is 0.
+func limitClaim(name string, n json.Number) (int64, error) {
+	if n == "" {
+		return 0, nil
+	}
+	v, err := n.Int64()
+	if err != nil || v < 0 {
+		return 0, fmt.Errorf("%w: %s %q", ErrInvalidToken, name, n)
+	}
+	return v, nil
+}
+
+// verifySignature checks sig over signed with the key the header selects.
+// HMAC tokens are only accepted with a shared secret and public-key tokens
+// only with a JWKS key of the matching type, so a public key can never be
+// used as an HMAC secret.
+func (v *JWTVerifier) verifySignature(header jwtHeader, signed string, sig []byte) bool {
+	newHash, cryptoHash, ok := algHash(header.Alg)
+	if !ok {
+		return false
+	}
+	if strings.HasPrefix(header.Alg, "HS") {
+		if v.config.Secret == "" {
+			return false
+		}
+		mac := hmac.New(newHash, []byte(v.config.Secret))
+		mac.Write([]byte(signed))
+		return hmac.Equal(sig, mac.Sum(nil))
+	}
+
+	key, ok := v.key(header.Kid)
+	if !ok {
+		return false
+	}
+	h := newHash()
+	h.Write([]byte(signed))
+	digest := h.Sum(nil)
+	switch k := key.(type) {
+	case *rsa.PublicKey:
+		switch header.Alg[:2] {
+		case "RS":
+			return rsa.VerifyPKCS1v15(k, cryptoHash, digest, sig) == nil
+		case "PS":
+			return rsa.VerifyPSS(k, cryptoHash, digest, sig, nil) == nil
+		}
+	case *ecdsa.PublicKey:
+		bits := k.Curve.Params().BitSize
+		size := (bits + 7) / 8
+		if header.Alg[:2] != "ES" || ecdsaAlgBits[header.Alg[2:]] != bits || len(sig) != 2*size {
+			return false
+		}
+		r := new(big.Int).SetBytes(sig[:size])
+		s := new(big.Int).SetBytes(sig[size:])
+		return ecdsa.Verify(k, digest, r, s)
+	}
+	return false
+}
+
+// ecdsaAlgBits maps the ES* algorithm suffixes to the curve each requires.
+var ecdsaAlgBits = map[string]int{"256": 256, "384": 384, "512": 521}
+
+// algHash returns the hash of a JWS algorithm. "none" and unknown algorithms
+// are rejected.
+func algHash(alg string) (func() hash.Hash, crypto.Hash, bool) {
+	if len(alg) != 5 {
+		return nil, 0, false
+	}
+	switch alg[:2] {
+	case "HS", "RS", "PS", "ES":
+	default:
+		return nil, 0, false
+	}
+	switch alg[2:] {
+	case "256":
+		return sha256.New, crypto.SHA256, true
+	case "384":
+		return sha512.New384, crypto.SHA384, true
+	case "512":
+		return sha512.New, crypto.SHA512, true
+	}
+	return nil, 0, false
+}
+
+// jwk is a JSON Web Key. Only RSA and EC public keys are used.
+type jwk struct {
+	Kty string `json:"kty"`
+	Kid string `json:"kid"`
+	Use string `json:"use"`
+	N   string `json:"n"`
+	E   string `json:"e"`
+	Crv string `json:"crv"`
+	X   string `json:"x"`
+	Y   string `json:"y"`
+}
+
+// ParseJWKS parses a JWKS document into its signing keys by key ID. Keys of
+// other types or for encryption are skipped.
+func ParseJWKS(data []byte) (map[string]crypto.PublicKey, error) {
+	var set struct {
+		Keys []jwk `json:"keys"`
+	}
+	if err := json.Unmarshal(data, &set); err != nil {
+		return nil, fmt.Errorf("jwks: %w", err)
+	}
+	keys := make(map[string]crypto.PublicKey, len(set.Keys))
+	for _, k := range set.Keys {
+		if k.Use != "" && k.Use != "sig" {
+			continue
+		}
+		var (
+			key crypto.PublicKey
+			err error
+		)
+		switch k.Kty {
+		case "RSA":
+			key, err = k.rsaKey()
+		case "EC":
+			key, err = k.ecKey()
+		default:
+			continue
+		}
+		if err != nil {
+			return nil, fmt.Errorf("jwks: key %q: %w", k.Kid, err)
+		}
+		keys[k.Kid] = key
+	}
+	if len(keys) == 0 {
+		return nil, errors.New("jwks: no signing keys")
+	}
+	return keys, nil
+}
+
+func (k jwk) rsaKey() (*rsa.PublicKey, error) {
+	n, err := decodeBigInt(k.N)
+	if err != nil {
+		return nil, err
+	}
+	e, err := decodeBigInt(k.E)
+	if err != nil {
+		return nil, err
+	}
+	if !e.IsInt64() || e.Int64() < 3 || e.Int64() > 1<<31-1 {
+		return nil, errors.New("invalid exponent")
+	}
+	return &rsa.PublicKey{N: n, E: int(e.Int64())}, nil
+}
+
+func (k jwk) ecKey() (*ecdsa.PublicKey, error) {
+	var (
+		curve elliptic.Curve
+		check ecdh.Curve
+	)
+	switch k.Crv {
+	case "P-256":
+		curve, check = elliptic.P256(), ecdh.P256()
+	case "P-384":
+		curve, check = elliptic.P384(), ecdh.P384()
+	case "P-521":
+		curve, check = elliptic.P521(), ecdh.P521()
+	default:
+		return nil, fmt.Errorf("unsupported curve %q", k.Crv)
+	}
+	x, err := decodeBigInt(k.X)
+	if err != nil {
+		return nil, err
+	}
+	y, err := decodeBigInt(k.Y)
+	if err != nil {
+		return nil, err
+	}
+
+	// Reject points off the curve
+	size := (curve.Params().BitSize + 7) / 8
+	if len(x.Bytes()) > size || len(y.Bytes()) > size {
+		return nil, errors.New("invalid point")
+	}
+	point := make([]byte, 1+2*size)
+	point[0] = 4
+	x.FillBytes(point[1 : 1+size])
+	y.FillBytes(point[1+size:])
+	if _, err := check.NewPublicKey(point); err != nil {
+		return nil, errors.New("invalid point")
+	}
+	return &ecdsa.PublicKey{Curve: curve, X: x, Y: y}, nil
+}
+
+func decodeBigInt(s string) (*big.Int, error) {
+	b, err := base64.RawURLEncoding.DecodeString(s)
+	if err != nil || len(b) == 0 {
+		return nil, errors.New("invalid base64url integer")
+	}
+	return new(big.Int).SetBytes(b), nil
+}
+
+func decodeSegment(segment string, v any) error {
+	data, err := base64.RawURLEncoding.DecodeString(segment)
+	if err != nil {
+		return err
+	}
+	return json.Unmarshal(data, v)
+}
+
+func unixTime(seconds float64) time.Time {
+	return time.Unix(int64(seconds), 0)
+}
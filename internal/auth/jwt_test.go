@@ -0,0 +1,287 @@
+package auth
+
+import (
+	"crypto"
+	"crypto/ecdsa"
+	"crypto/elliptic"
+	"crypto/hmac"
+	"crypto/rand"
+	"crypto/rsa"
+	"crypto/sha256"
+	"encoding/base64"
+	"encoding/json"
+	"errors"
+	"math/big"
+	"net/http"
+	"net/http/httptest"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+var jwtNow = time.Date(2026, 1, 1, 0, 0, 0, 0, time.UTC)
+
+func b64(v any) string {
+	data, _ := json.Marshal(v)
+	return base64.RawURLEncoding.EncodeToString(data)
+}
+
+func hsToken(secret string, claims map[string]any) string {
+	signed := b64(map[string]string{"alg": "HS256", "typ": "JWT"}) + "." + b64(claims)
+	mac := hmac.New(sha256.New, []byte(secret))
+	mac.Write([]byte(signed))
+	return signed + "." + base64.RawURLEncoding.EncodeToString(mac.Sum(nil))
+}
+
+func rsToken(t *testing.T, key *rsa.PrivateKey, kid string, claims map[string]any) string {
+	t.Helper()
+	signed := b64(map[string]string{"alg": "RS256", "kid": kid}) + "." + b64(claims)
+	digest := sha256.Sum256([]byte(signed))
+	sig, err := rsa.SignPKCS1v15(rand.Reader, key, crypto.SHA256, digest[:])
+	if err != nil {
+		t.Fatal(err)
+	}
+	return signed + "." + base64.RawURLEncoding.EncodeToString(sig)
+}
+
+func esToken(t *testing.T, key *ecdsa.PrivateKey, kid string, claims map[string]any) string {
+	t.Helper()
+	signed := b64(map[string]string{"alg": "ES256", "kid": kid}) + "." + b64(claims)
+	digest := sha256.Sum256([]byte(signed))
+	r, s, err := ecdsa.Sign(rand.Reader, key, digest[:])
+	if err != nil {
+		t.Fatal(err)
+	}
+	sig := make([]byte, 64)
+	r.FillBytes(sig[:32])
+	s.FillBytes(sig[32:])
+	return signed + "." + base64.RawURLEncoding.EncodeToString(sig)
+}
+
+func rsaJWK(kid string, key *rsa.PublicKey) map[string]string {
+	return map[string]string{
+		"kty": "RSA",
+		"kid": kid,
+		"n":   base64.RawURLEncoding.EncodeToString(key.N.Bytes()),
+		"e":   base64.RawURLEncoding.EncodeToString(big.NewInt(int64(key.E)).Bytes()),
+	}
+}
+
+func ecJWK(kid string, key *ecdsa.PublicKey) map[string]string {
+	x, y := make([]byte, 32), make([]byte, 32)
+	key.X.FillBytes(x)
+	key.Y.FillBytes(y)
+	return map[string]string{
+		"kty": "EC",
+		"kid": kid,
+		"crv": "P-256",
+		"x":   base64.RawURLEncoding.EncodeToString(x),
+		"y":   base64.RawURLEncoding.EncodeToString(y),
+	}
+}
+
+func validClaims() map[string]any {
+	return map[string]any{"sub": "alice", "exp": jwtNow.Add(time.Hour).Unix()}
+}
+
+func TestJWTVerifier_Secret(t *testing.T) {
+	v := NewJWTVerifier(JWTConfig{Secret: "s3cret", Issuer: "idp", Audience: "proxy", Clock: clock.NewFake(jwtNow)})
+
+	claims := validClaims()
+	claims["iss"] = "idp"
+	claims["aud"] = []string{"other", "proxy"}
+	claims["pool"] = "premium"
+	claims["rate"] = 2.5
+	claims["max_conns"] = 20
+	claims["monthly_bytes"] = int64(50 << 30)
+	id, err := v.Verify(hsToken("s3cret", claims))
+	if err != nil {
+		t.Fatalf("Verify() error = %v", err)
+	}
+	if id != (Identity{User: "alice", Pool: "premium", Rate: 2.5, MaxConns: 20, MonthlyBytes: 50 << 30}) {
+		t.Errorf("Verify() = %+v", id)
+	}
+
+	tests := []struct {
+		name   string
+		secret string
+		modify func(map[string]any)
+		want   error
+	}{
+		{name: "wrong secret", secret: "other", want: ErrInvalidToken},
+		{name: "expired", modify: func(c map[string]any) { c["exp"] = jwtNow.Add(-time.Hour).Unix() }, want: ErrTokenExpired},
+		{name: "expired within leeway", modify: func(c map[string]any) { c["exp"] = jwtNow.Add(-10 * time.Second).Unix() }},
+		{name: "missing exp", modify: func(c map[string]any) { delete(c, "exp") }, want: ErrTokenExpired},
+		{name: "not yet valid", modify: func(c map[string]any) { c["nbf"] = jwtNow.Add(time.Hour).Unix() }, want: ErrTokenExpired},
+		{name: "wrong issuer", modify: func(c map[string]any) { c["iss"] = "evil" }, want: ErrInvalidToken},
+		{name: "single audience", modify: func(c map[string]any) { c["aud"] = "proxy" }},
+		{name: "wrong audience", modify: func(c map[string]any) { c["aud"] = "other" }, want: ErrInvalidToken},
+		{name: "missing sub", modify: func(c map[string]any) { delete(c, "sub") }, want: ErrInvalidToken},
+		{name: "negative rate", modify: func(c map[string]any) { c["rate"] = -1 }, want: ErrInvalidToken},
+		{name: "fractional max conns", modify: func(c map[string]any) { c["max_conns"] = 1.5 }, want: ErrInvalidToken},
+		{name: "negative monthly bytes", modify: func(c map[string]any) { c["monthly_bytes"] = -1 }, want: ErrInvalidToken},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			c := validClaims()
+			c["iss"] = "idp"
+			c["aud"] = "proxy"
+			if tt.modify != nil {
+				tt.modify(c)
+			}
+			secret := "s3cret"
+			if tt.secret != "" {
+				secret = tt.secret
+			}
+			_, err := v.Verify(hsToken(secret, c))
+			if !errors.Is(err, tt.want) {
+				t.Errorf("Verify() error = %v, want %v", err, tt.want)
+			}
+		})
+	}
+}
+
+func TestJWTVerifier_RejectsUnsignedTokens(t *testing.T) {
+	v := NewJWTVerifier(JWTConfig{Secret: "s3cret", Clock: clock.NewFake(jwtNow)})
+	unsigned := b64(map[string]string{"alg": "none"}) + "." + b64(validClaims()) + "."
+	if _, err := v.Verify(unsigned); !errors.Is(err, ErrInvalidToken) {
+		t.Errorf("Verify(alg none) error = %v, want ErrInvalidToken", err)
+	}
+	if _, err := v.Verify("not-a-token"); !errors.Is(err, ErrInvalidToken) {
+		t.Errorf("Verify(garbage) error = %v, want ErrInvalidToken", err)
+	}
+}
+
+func TestJWTVerifier_JWKS(t *testing.T) {
+	rsaKey, err := rsa.GenerateKey(rand.Reader, 2048)
+	if err != nil {
+		t.Fatal(err)
+	}
+	ecKey, err := ecdsa.GenerateKey(elliptic.P256(), rand.Reader)
+	if err != nil {
+		t.Fatal(err)
+	}
+	rotated, err := ecdsa.GenerateKey(elliptic.P256(), rand.Reader)
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	var fetches atomic.Int32
+	keys := []map[string]string{rsaJWK("rsa-1", &rsaKey.PublicKey), ecJWK("ec-1", &ecKey.PublicKey)}
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if fetches.Add(1) > 1 {
+			keys = append(keys, ecJWK("ec-2", &rotated.PublicKey))
+		}
+		_ = json.NewEncoder(w).Encode(map[string]any{"keys": keys})
+	}))
+	defer srv.Close()
+
+	fake := clock.NewFake(jwtNow)
+	v := NewJWTVerifier(JWTConfig{JWKSURL: srv.URL, Clock: fake})
+	if err := v.Start(); err != nil {
+		t.Fatalf("Start() error = %v", err)
+	}
+	defer v.Stop()
+
+	if id, err := v.Verify(rsToken(t, rsaKey, "rsa-1", validClaims())); err != nil || id.User != "alice" {
+		t.Errorf("Verify(RS256) = %+v, %v", id, err)
+	}
+	if _, err := v.Verify(esToken(t, ecKey, "ec-1", validClaims())); err != nil {
+		t.Errorf("Verify(ES256) error = %v", err)
+	}
+	if _, err := v.Verify(esToken(t, ecKey, "rsa-1", validClaims())); !errors.Is(err, ErrInvalidToken) {
+		t.Errorf("Verify(ES256 with RSA key) error = %v, want ErrInvalidToken", err)
+	}
+
+	// HMAC tokens are refused without a shared secret
+	if _, err := v.Verify(hsToken("", validClaims())); !errors.Is(err, ErrInvalidToken) {
+		t.Errorf("Verify(HS256) error = %v, want ErrInvalidToken", err)
+	}
+
+	// An unknown key ID refetches the JWKS, but not more than once a minute
+	token := esToken(t, rotated, "ec-2", validClaims())
+	if _, err := v.Verify(token); !errors.Is(err, ErrInvalidToken) {
+		t.Errorf("Verify(rotated key) error = %v, want ErrInvalidToken before refetch", err)
+	}
+	fake.Advance(jwksMinRefetch)
+	if _, err := v.Verify(token); err != nil {
+		t.Errorf("Verify(rotated key) error = %v after refetch", err)
+	}
+	if got := fetches.Load(); got != 2 {
+		t.Errorf("fetches = %d, want 2", got)
+	}
+
+	// Refresh fetches at once, without waiting for the refetch interval
+	if err := v.Refresh(); err != nil {
+		t.Errorf("Refresh() error = %v", err)
+	}
+	if got := fetches.Load(); got != 3 {
+		t.Errorf("fetches after Refresh = %d, want 3", got)
+	}
+}
+
+func TestJWTVerifier_JWKSRefetchFailure(t *testing.T) {
+	ecKey, err := ecdsa.GenerateKey(elliptic.P256(), rand.Reader)
+	if err != nil {
+		t.Fatal(err)
+	}
+	var fetches atomic.Int32
+	srv := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if fetches.Add(1) > 1 {
+			http.Error(w, "unavailable", http.StatusServiceUnavailable)
+			return
+		}
+		_ = json.NewEncoder(w).Encode(map[string]any{"keys": []map[string]string{ecJWK("ec-1", &ecKey.PublicKey)}})
+	}))
+	defer srv.Close()
+
+	fake := clock.NewFake(jwtNow)
+	v := NewJWTVerifier(JWTConfig{JWKSURL: srv.URL, Clock: fake})
+	if err := v.Start(); err != nil {
+		t.Fatalf("Start() error = %v", err)
+	}
+	defer v.Stop()
+
+	// Random key IDs trigger one failed refetch, not one per token
+	fake.Advance(jwksMinRefetch)
+	for _, kid := range []string{"random-1", "random-2", "random-3"} {
+		if _, err := v.Verify(esToken(t, ecKey, kid, validClaims())); !errors.Is(err, ErrInvalidToken) {
+			t.Errorf("Verify(%s) error = %v, want ErrInvalidToken", kid, err)
+		}
+	}
+	if got := fetches.Load(); got != 2 {
+		t.Errorf("fetches = %d, want 2", got)
+	}
+	if _, err := v.Verify(esToken(t, ecKey, "ec-1", validClaims())); err != nil {
+		t.Errorf("Verify(known key) error = %v", err)
+	}
+}
+
+func TestParseJWKS(t *testing.T) {
+	tests := []struct {
+		name    string
+		data    string
+		wantLen int
+		wantErr bool
+	}{
+		{name: "invalid json", data: `{`, wantErr: true},
+		{name: "no keys", data: `{"keys":[]}`, wantErr: true},
+		{name: "encryption keys skipped", data: `{"keys":[{"kty":"RSA","use":"enc","n":"AQAB","e":"AQAB"}]}`, wantErr: true},
+		{name: "unknown types skipped", data: `{"keys":[{"kty":"oct","k":"c2VjcmV0"},{"kty":"RSA","kid":"a","n":"AQAB","e":"AQAB"}]}`, wantLen: 1},
+		{name: "point off curve", data: `{"keys":[{"kty":"EC","crv":"P-256","x":"AQ","y":"AQ"}]}`, wantErr: true},
+		{name: "unsupported curve", data: `{"keys":[{"kty":"EC","crv":"P-192","x":"AQ","y":"AQ"}]}`, wantErr: true},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			keys, err := ParseJWKS([]byte(tt.data))
+			if (err != nil) != tt.wantErr {
+				t.Fatalf("ParseJWKS() error = %v, wantErr %v", err, tt.wantErr)
+			}
+			if len(keys) != tt.wantLen {
+				t.Errorf("ParseJWKS() = %d keys, want %d", len(keys), tt.wantLen)
+			}
+		})
+	}
+}
@@ -0,0 +1,47 @@
+// Package auth verifies proxy credentials: htpasswd-style users files, JWT bearer tokens and auth webhooks.
+package auth
+
+import (
+	"errors"
+	"strings"
+)
+
+// ErrPAMUnsupported is returned when the binary was built without PAM support.
+var ErrPAMUnsupported = errors.New("PAM support not built in (build with -tags pam and cgo)")
+
+// maxPAMChecks bounds concurrent PAM transactions, which are slow and may
+// run external modules.
+const maxPAMChecks = 8
+
+// PAMAuthenticator checks credentials against the host's accounts through a
+// PAM service, so system users can be reused as proxy users. Both the
+// password and the account (expiry, lock) must pass.
+type PAMAuthenticator struct {
+	service string
+	sem     chan struct{}
+}
+
+// NewPAMAuthenticator creates a PAMAuthenticator for the PAM service, e.g.
+// "outbound-lb" for /etc/pam.d/outbound-lb. It returns ErrPAMUnsupported if
+// the binary was built without PAM.
+func NewPAMAuthenticator(service string) (*PAMAuthenticator, error) {
+	if !pamSupported {
+		return nil, ErrPAMUnsupported
+	}
+	return &PAMAuthenticator{service: service, sem: make(chan struct{}, maxPAMChecks)}, nil
+}
+
+// Check reports whether password is valid for user.
+func (a *PAMAuthenticator) Check(user, password string) bool {
+	// C strings end at the first NUL, which would check a truncated credential
+	if user == "" || password == "" || strings.ContainsRune(user+password, 0) {
+		return false
+	}
+	a.sem <- struct{}{}
+	defer func() { <-a.sem }()
+	if err := pamAuthenticate(a.service, user, password); err != nil {
+		log.Debug("pam_check_failed", "service", a.service, "user", user, "error", err)
+		return false
+	}
+	return true
+}
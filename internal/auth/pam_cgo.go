@@ -0,0 +1,89 @@
+//go:build pam && cgo
+
+package auth
+
+/*
+#cgo LDFLAGS: -lpam
+#include <security/pam_appl.h>
+#include <stdlib.h>
+#include <string.h>
+
+// password_conv answers every prompt with the password passed as appdata.
+static int password_conv(int n, const struct pam_message **msg, struct pam_response **resp, void *appdata) {
+	if (n <= 0 || n > PAM_MAX_NUM_MSG) {
+		return PAM_CONV_ERR;
+	}
+	struct pam_response *r = calloc(n, sizeof(struct pam_response));
+	if (r == NULL) {
+		return PAM_BUF_ERR;
+	}
+	for (int i = 0; i < n; i++) {
+		switch (msg[i]->msg_style) {
+		case PAM_PROMPT_ECHO_OFF:
+		case PAM_PROMPT_ECHO_ON:
+			r[i].resp = strdup((const char *)appdata);
+			if (r[i].resp == NULL) {
+				goto fail;
+			}
+			break;
+		case PAM_ERROR_MSG:
+		case PAM_TEXT_INFO:
+			break;
+		default:
+			goto fail;
+		}
+	}
+	*resp = r;
+	return PAM_SUCCESS;
+fail:
+	for (int i = 0; i < n; i++) {
+		free(r[i].resp);
+	}
+	free(r);
+	return PAM_CONV_ERR;
+}
+
+// pam_check authenticates user with password and checks the account.
+static int pam_check(const char *service, const char *user, const char *password) {
+	struct pam_conv conv = { password_conv, (void *)password };
+	pam_handle_t *h = NULL;
+	int rc = pam_start(service, user, &conv, &h);
+	if (rc != PAM_SUCCESS) {
+		return rc;
+	}
+	rc = pam_authenticate(h, PAM_SILENT | PAM_DISALLOW_NULL_AUTHTOK);
+	if (rc == PAM_SUCCESS) {
+		rc = pam_acct_mgmt(h, PAM_SILENT | PAM_DISALLOW_NULL_AUTHTOK);
+	}
+	pam_end(h, rc);
+	return rc;
+}
+*/
+import "C"
+
+import (
+	"fmt"
+	"unsafe"
+)
+
+// pamSupported reports whether PAM support is built in.
+const pamSupported = true
+
+// pamAuthenticate runs a PAM transaction for user against service.
+func pamAuthenticate(service, user, password string) error {
+	cService := C.CString(service)
+	defer C.free(unsafe.Pointer(cService))
+	cUser := C.CString(user)
+	defer C.free(unsafe.Pointer(cUser))
+	cPassword := C.CString(password)
+	defer func() {
+		// Don't leave the password in freed memory
+		C.memset(unsafe.Pointer(cPassword), 0, C.size_t(len(password)))
+		C.free(unsafe.Pointer(cPassword))
+	}()
+
+	if rc := C.pam_check(cService, cUser, cPassword); rc != C.PAM_SUCCESS {
+		return fmt.Errorf("pam: %s: error %d", service, int(rc))
+	}
+	return nil
+}
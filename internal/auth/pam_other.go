@@ -0,0 +1,11 @@
+//go:build !pam || !cgo
+
+package auth
+
+// pamSupported reports whether PAM support is built in.
+const pamSupported = false
+
+// pamAuthenticate is unavailable without PAM support.
+func pamAuthenticate(service, user, password string) error {
+	return ErrPAMUnsupported
+}
@@ -0,0 +1,26 @@
+package auth
+
+import (
+	"errors"
+	"testing"
+)
+
+func TestPAMAuthenticator(t *testing.T) {
+	a, err := NewPAMAuthenticator("outbound-lb-test")
+	if !pamSupported {
+		if !errors.Is(err, ErrPAMUnsupported) {
+			t.Fatalf("NewPAMAuthenticator() error = %v, want ErrPAMUnsupported", err)
+		}
+		return
+	}
+	if err != nil {
+		t.Fatalf("NewPAMAuthenticator() error = %v", err)
+	}
+
+	// Credentials that C would truncate or PAM might accept never reach PAM
+	for _, c := range [][2]string{{"", "secret"}, {"root", ""}, {"root\x00x", "secret"}, {"root", "secret\x00"}} {
+		if a.Check(c[0], c[1]) {
+			t.Errorf("Check(%q, %q) = true, want false", c[0], c[1])
+		}
+	}
+}
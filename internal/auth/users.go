@@ -0,0 +1,165 @@
+// Package auth verifies proxy credentials: htpasswd-style users files, JWT bearer tokens and auth webhooks.
+package auth
+
+import (
+	"bufio"
+	"crypto/subtle"
+	"encoding/base64"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"strings"
+	"sync"
+
+	"golang.org/x/crypto/argon2"
+	"golang.org/x/crypto/bcrypt"
+)
+
+// ErrUnsupportedHash is returned for password hashes other than bcrypt and argon2.
+var ErrUnsupportedHash = errors.New("unsupported password hash")
+
+// Users holds the password hash of each user of a users file.
+type Users struct {
+	hashes map[string]hash
+}
+
+// hash verifies a password against a stored hash.
+type hash interface {
+	verify(password string) bool
+}
+
+// dummyHash is checked for unknown users so their response time matches a
+// wrong password for a known user.
+var dummyHash = sync.OnceValue(func() hash {
+	h, _ := bcrypt.GenerateFromPassword([]byte("outbound-lb"), bcrypt.DefaultCost)
+	return bcryptHash(h)
+})
+
+// LoadUsersFile reads a users file in htpasswd format: one "user:hash" entry
+// per line, with bcrypt ($2a$, $2b$, $2y$) or argon2 ($argon2id$, $argon2i$)
+// hashes. Blank lines and lines starting with "#" are ignored.
+func LoadUsersFile(path string) (*Users, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return nil, err
+	}
+	defer f.Close()
+	users, err := ParseUsers(f)
+	if err != nil {
+		return nil, fmt.Errorf("%s: %w", path, err)
+	}
+	return users, nil
+}
+
+// ParseUsers reads users in htpasswd format from r.
+func ParseUsers(r io.Reader) (*Users, error) {
+	u := &Users{hashes: make(map[string]hash)}
+	scanner := bufio.NewScanner(r)
+	for line := 1; scanner.Scan(); line++ {
+		text := strings.TrimSpace(scanner.Text())
+		if text == "" || strings.HasPrefix(text, "#") {
+			continue
+		}
+		user, encoded, ok := strings.Cut(text, ":")
+		if !ok || user == "" {
+			return nil, fmt.Errorf("line %d: expected user:hash", line)
+		}
+		if _, exists := u.hashes[user]; exists {
+			return nil, fmt.Errorf("line %d: duplicate user %q", line, user)
+		}
+		h, err := parseHash(encoded)
+		if err != nil {
+			return nil, fmt.Errorf("line %d: user %q: %w", line, user, err)
+		}
+		u.hashes[user] = h
+	}
+	if err := scanner.Err(); err != nil {
+		return nil, err
+	}
+	return u, nil
+}
+
+// Check reports whether password is valid for user.
+func (u *Users) Check(user, password string) bool {
+	h, ok := u.hashes[user]
+	if !ok {
+		dummyHash().verify(password)
+		return false
+	}
+	return h.verify(password)
+}
+
+// Len returns the number of users.
+func (u *Users) Len() int {
+	return len(u.hashes)
+}
+
+// parseHash decodes a bcrypt or argon2 password hash.
+func parseHash(encoded string) (hash, error) {
+	switch {
+	case strings.HasPrefix(encoded, "$2a$"), strings.HasPrefix(encoded, "$2b$"), strings.HasPrefix(encoded, "$2y$"):
+		if _, err := bcrypt.Cost([]byte(encoded)); err != nil {
+			return nil, err
+		}
+		return bcryptHash(encoded), nil
+	case strings.HasPrefix(encoded, "$argon2id$"), strings.HasPrefix(encoded, "$argon2i$"):
+		return parseArgon2(encoded)
+	default:
+		return nil, ErrUnsupportedHash
+	}
+}
+
+// bcryptHash is a bcrypt hash as written by htpasswd -B.
+type bcryptHash []byte
+
+func (h bcryptHash) verify(password string) bool {
+	return bcrypt.CompareHashAndPassword(h, []byte(password)) == nil
+}
+
+// argon2Hash is an argon2 hash in PHC string format:
+// $argon2id$v=19$m=65536,t=3,p=4$<salt>$<key>.
+type argon2Hash struct {
+	id      bool
+	memory  uint32
+	time    uint32
+	threads uint8
+	salt    []byte
+	key     []byte
+}
+
+// parseArgon2 decodes an argon2 hash in PHC string format.
+func parseArgon2(encoded string) (hash, error) {
+	parts := strings.Split(encoded, "$")
+	if len(parts) != 6 {
+		return nil, errors.New("malformed argon2 hash")
+	}
+	h := argon2Hash{id: parts[1] == "argon2id"}
+	var version int
+	if _, err := fmt.Sscanf(parts[2], "v=%d", &version); err != nil || version != argon2.Version {
+		return nil, fmt.Errorf("unsupported argon2 version %q", parts[2])
+	}
+	if _, err := fmt.Sscanf(parts[3], "m=%d,t=%d,p=%d", &h.memory, &h.time, &h.threads); err != nil {
+		return nil, fmt.Errorf("malformed argon2 parameters %q", parts[3])
+	}
+	if h.time == 0 || h.threads == 0 {
+		return nil, fmt.Errorf("malformed argon2 parameters %q", parts[3])
+	}
+	var err error
+	if h.salt, err = base64.RawStdEncoding.DecodeString(parts[4]); err != nil {
+		return nil, errors.New("malformed argon2 salt")
+	}
+	if h.key, err = base64.RawStdEncoding.DecodeString(parts[5]); err != nil || len(h.key) == 0 {
+		return nil, errors.New("malformed argon2 key")
+	}
+	return h, nil
+}
+
+func (h argon2Hash) verify(password string) bool {
+	derive := argon2.Key
+	if h.id {
+		derive = argon2.IDKey
+	}
+	key := derive([]byte(password), h.salt, h.time, h.memory, h.threads, uint32(len(h.key)))
+	return subtle.ConstantTimeCompare(key, h.key) == 1
+}
@@ -0,0 +1,115 @@
+package auth
+
+import (
+	"encoding/base64"
+	"errors"
+	"fmt"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+
+	"golang.org/x/crypto/argon2"
+	"golang.org/x/crypto/bcrypt"
+)
+
+func bcryptLine(t *testing.T, user, password string) string {
+	t.Helper()
+	h, err := bcrypt.GenerateFromPassword([]byte(password), bcrypt.MinCost)
+	if err != nil {
+		t.Fatal(err)
+	}
+	return user + ":" + string(h)
+}
+
+func argon2Line(user, password, variant string) string {
+	salt := []byte("0123456789abcdef")
+	derive := argon2.IDKey
+	if variant == "argon2i" {
+		derive = argon2.Key
+	}
+	key := derive([]byte(password), salt, 1, 1024, 1, 32)
+	return fmt.Sprintf("%s:$%s$v=%d$m=1024,t=1,p=1$%s$%s", user, variant, argon2.Version,
+		base64.RawStdEncoding.EncodeToString(salt), base64.RawStdEncoding.EncodeToString(key))
+}
+
+func TestUsersCheck(t *testing.T) {
+	file := strings.Join([]string{
+		"# proxy users",
+		bcryptLine(t, "alice", "s3cret"),
+		"",
+		argon2Line("bob", "hunter2", "argon2id"),
+		argon2Line("carol", "pa:ss", "argon2i"),
+	}, "\n")
+
+	users, err := ParseUsers(strings.NewReader(file))
+	if err != nil {
+		t.Fatalf("ParseUsers() error = %v", err)
+	}
+	if users.Len() != 3 {
+		t.Errorf("Len() = %d, want 3", users.Len())
+	}
+
+	tests := []struct {
+		user     string
+		password string
+		want     bool
+	}{
+		{"alice", "s3cret", true},
+		{"alice", "wrong", false},
+		{"bob", "hunter2", true},
+		{"bob", "s3cret", false},
+		{"carol", "pa:ss", true},
+		{"dave", "s3cret", false},
+	}
+	for _, tt := range tests {
+		if got := users.Check(tt.user, tt.password); got != tt.want {
+			t.Errorf("Check(%q, %q) = %v, want %v", tt.user, tt.password, got, tt.want)
+		}
+	}
+}
+
+func TestParseUsers_Invalid(t *testing.T) {
+	tests := []struct {
+		name string
+		file string
+	}{
+		{"missing hash", "alice"},
+		{"empty user", ":$2y$05$abc"},
+		{"plaintext", "alice:s3cret"},
+		{"md5", "alice:$apr1$salt$hash"},
+		{"truncated bcrypt", "alice:$2y$05$abc"},
+		{"malformed argon2", "alice:$argon2id$v=19$m=1024$salt$key"},
+		{"duplicate user", bcryptLine(t, "alice", "a") + "\n" + bcryptLine(t, "alice", "b")},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			if _, err := ParseUsers(strings.NewReader(tt.file)); err == nil {
+				t.Error("expected error")
+			}
+		})
+	}
+
+	if _, err := ParseUsers(strings.NewReader("alice:s3cret")); !errors.Is(err, ErrUnsupportedHash) {
+		t.Errorf("error = %v, want ErrUnsupportedHash", err)
+	}
+}
+
+func TestLoadUsersFile(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "users")
+	if err := os.WriteFile(path, []byte(bcryptLine(t, "alice", "s3cret")+"\n"), 0o600); err != nil {
+		t.Fatal(err)
+	}
+
+	users, err := LoadUsersFile(path)
+	if err != nil {
+		t.Fatalf("LoadUsersFile() error = %v", err)
+	}
+	if !users.Check("alice", "s3cret") {
+		t.Error("expected alice to authenticate")
+	}
+
+	if _, err := LoadUsersFile(filepath.Join(t.TempDir(), "missing")); err == nil {
+		t.Error("expected error for missing file")
+	}
+}
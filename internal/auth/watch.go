@@ -0,0 +1,109 @@
+// Package auth verifies proxy credentials: htpasswd-style users files, JWT bearer tokens and auth webhooks.
+package auth
+
+import (
+	"path/filepath"
+	"time"
+
+	"github.com/fsnotify/fsnotify"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// log logs on behalf of the auth module, so its level can be set on its own.
+var log = logger.Module("auth")
+
+// usersDebounce collapses the burst of events a single save produces.
+const usersDebounce = 100 * time.Millisecond
+
+// UsersWatcher reloads a users file when it changes, so users can be added
+// or revoked without a restart.
+type UsersWatcher struct {
+	path    string
+	onLoad  func(*Users)
+	watcher *fsnotify.Watcher
+	stopCh  chan struct{}
+}
+
+// NewUsersWatcher creates a UsersWatcher that passes every successfully
+// reloaded users file at path to onLoad.
+func NewUsersWatcher(path string, onLoad func(*Users)) (*UsersWatcher, error) {
+	watcher, err := fsnotify.NewWatcher()
+	if err != nil {
+		return nil, err
+	}
+	return &UsersWatcher{
+		path:    filepath.Clean(path),
+		onLoad:  onLoad,
+		watcher: watcher,
+		stopCh:  make(chan struct{}),
+	}, nil
+}
+
+// Start begins watching the users file. Its directory is watched, so tools
+// that replace the file rather than write it in place are picked up too.
+func (w *UsersWatcher) Start() error {
+	if err := w.watcher.Add(filepath.Dir(w.path)); err != nil {
+		return err
+	}
+	go w.watchLoop()
+	log.Info("auth_file_watcher_started", "path", w.path)
+	return nil
+}
+
+// Stop stops watching the users file.
+func (w *UsersWatcher) Stop() {
+	close(w.stopCh)
+	w.watcher.Close()
+}
+
+// Reload reads the users file and passes it to onLoad. An invalid file is
+// rejected and the previous users are kept.
+func (w *UsersWatcher) Reload() error {
+	users, err := LoadUsersFile(w.path)
+	if err != nil {
+		metrics.AuthFileReloads.WithLabelValues("error").Inc()
+		return err
+	}
+	w.onLoad(users)
+	metrics.AuthFileReloads.WithLabelValues("success").Inc()
+	log.Info("auth_file_reloaded", "path", w.path, "users", users.Len())
+	return nil
+}
+
+// watchLoop reloads the users file, debounced, when it is written or replaced.
+func (w *UsersWatcher) watchLoop() {
+	var debounceTimer *time.Timer
+	for {
+		select {
+		case event, ok := <-w.watcher.Events:
+			if !ok {
+				return
+			}
+			if filepath.Clean(event.Name) != w.path || event.Op&(fsnotify.Write|fsnotify.Create) == 0 {
+				continue
+			}
+			if debounceTimer != nil {
+				debounceTimer.Stop()
+			}
+			debounceTimer = time.AfterFunc(usersDebounce, func() {
+				if err := w.Reload(); err != nil {
+					log.Error("auth_file_reload_failed", "path", w.path, "error", err)
+				}
+			})
+
+		case err, ok := <-w.watcher.Errors:
+			if !ok {
+				return
+			}
+			log.Error("auth_file_watcher_error", "error", err)
+
+		case <-w.stopCh:
+			if debounceTimer != nil {
+				debounceTimer.Stop()
+			}
+			return
+		}
+	}
+}
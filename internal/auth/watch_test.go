@@ -0,0 +1,57 @@
+package auth
+
+import (
+	"os"
+	"path/filepath"
+	"testing"
+	"time"
+)
+
+func TestUsersWatcher(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "users")
+	if err := os.WriteFile(path, []byte(bcryptLine(t, "alice", "s3cret")+"\n"), 0o600); err != nil {
+		t.Fatal(err)
+	}
+
+	loaded := make(chan *Users, 4)
+	w, err := NewUsersWatcher(path, func(u *Users) { loaded <- u })
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	// An invalid file is rejected without replacing the users
+	bad := filepath.Join(filepath.Dir(path), "bad")
+	if err := os.WriteFile(bad, []byte("alice:plaintext\n"), 0o600); err != nil {
+		t.Fatal(err)
+	}
+	badWatcher, err := NewUsersWatcher(bad, func(*Users) { t.Error("onLoad called for an invalid file") })
+	if err != nil {
+		t.Fatal(err)
+	}
+	if err := badWatcher.Reload(); err == nil {
+		t.Error("Reload() of an invalid file: expected error")
+	}
+
+	if err := w.Start(); err != nil {
+		t.Fatalf("Start() error = %v", err)
+	}
+	defer w.Stop()
+
+	// Replacing the file, as editors and config managers do, reloads it
+	tmp := path + ".tmp"
+	if err := os.WriteFile(tmp, []byte(bcryptLine(t, "bob", "hunter2")+"\n"), 0o600); err != nil {
+		t.Fatal(err)
+	}
+	if err := os.Rename(tmp, path); err != nil {
+		t.Fatal(err)
+	}
+
+	select {
+	case u := <-loaded:
+		if !u.Check("bob", "hunter2") || u.Check("alice", "s3cret") {
+			t.Error("reloaded users do not match the new file")
+		}
+	case <-time.After(5 * time.Second):
+		t.Fatal("users file change was not picked up")
+	}
+}
@@ -0,0 +1,131 @@
+// Package auth verifies proxy credentials: htpasswd-style users files, JWT bearer tokens and auth webhooks.
+package auth
+
+import (
+	"bytes"
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"net/http"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// ErrWebhookDenied is returned when the auth webhook refuses a credential.
+var ErrWebhookDenied = errors.New("denied by auth webhook")
+
+const (
+	// maxWebhookBody bounds the auth webhook's response read.
+	maxWebhookBody = 64 << 10
+	// defaultWebhookTimeout bounds a webhook call when WebhookConfig.Timeout is unset.
+	defaultWebhookTimeout = 5 * time.Second
+)
+
+// WebhookConfig holds configuration for WebhookAuthenticator.
+type WebhookConfig struct {
+	// URL receives a JSON POST for every credential to check.
+	URL string
+	// Timeout bounds each webhook call (default 5s).
+	Timeout time.Duration
+}
+
+// WebhookRequest is the JSON body POSTed to the auth webhook.
+type WebhookRequest struct {
+	// User is the proxy username without routing suffixes.
+	User     string `json:"user"`
+	Password string `json:"password"`
+	// ClientIP is the client's address without port.
+	ClientIP string `json:"client_ip"`
+	// Target is the requested host:port, if known when authenticating.
+	Target string `json:"target,omitempty"`
+	// Protocol is "http" or "socks".
+	Protocol string `json:"protocol"`
+}
+
+// webhookResponse is the auth webhook's reply. Its attributes carry the same
+// routing and limits as a bearer token's claims.
+type webhookResponse struct {
+	Allow        bool    `json:"allow"`
+	Pool         string  `json:"pool"`
+	Rate         float64 `json:"rate"`
+	MaxConns     int     `json:"max_conns"`
+	MonthlyBytes int64   `json:"monthly_bytes"`
+}
+
+// WebhookAuthenticator delegates credential checks to an external HTTP
+// service. The service answers 200 with {"allow": true} and optional "pool",
+// "rate", "max_conns" and "monthly_bytes" attributes; any other answer, or no
+// answer, denies the client.
+type WebhookAuthenticator struct {
+	url    string
+	client *http.Client
+}
+
+// NewWebhookAuthenticator creates a new WebhookAuthenticator.
+func NewWebhookAuthenticator(cfg WebhookConfig) *WebhookAuthenticator {
+	if cfg.Timeout <= 0 {
+		cfg.Timeout = defaultWebhookTimeout
+	}
+	return &WebhookAuthenticator{
+		url:    cfg.URL,
+		client: &http.Client{Timeout: cfg.Timeout},
+	}
+}
+
+// Authenticate asks the webhook about req and returns the allowed user's
+// identity. It returns ErrWebhookDenied if the webhook refuses the credential,
+// and another error if the webhook could not be asked; both deny the client.
+func (a *WebhookAuthenticator) Authenticate(ctx context.Context, req WebhookRequest) (Identity, error) {
+	id, err := a.authenticate(ctx, req)
+	switch {
+	case err == nil:
+		metrics.AuthWebhookRequests.WithLabelValues("allow").Inc()
+	case errors.Is(err, ErrWebhookDenied):
+		metrics.AuthWebhookRequests.WithLabelValues("deny").Inc()
+	default:
+		metrics.AuthWebhookRequests.WithLabelValues("error").Inc()
+	}
+	return id, err
+}
+
+// authenticate POSTs req to the webhook and decodes its answer.
+func (a *WebhookAuthenticator) authenticate(ctx context.Context, req WebhookRequest) (Identity, error) {
+	body, err := json.Marshal(req)
+	if err != nil {
+		return Identity{}, err
+	}
+	httpReq, err := http.NewRequestWithContext(ctx, http.MethodPost, a.url, bytes.NewReader(body))
+	if err != nil {
+		return Identity{}, err
+	}
+	httpReq.Header.Set("Content-Type", "application/json")
+	resp, err := a.client.Do(httpReq)
+	if err != nil {
+		return Identity{}, err
+	}
+	defer resp.Body.Close()
+
+	switch {
+	case resp.StatusCode == http.StatusUnauthorized || resp.StatusCode == http.StatusForbidden:
+		return Identity{}, ErrWebhookDenied
+	case resp.StatusCode != http.StatusOK:
+		return Identity{}, fmt.Errorf("auth webhook: unexpected status %d", resp.StatusCode)
+	}
+	var reply webhookResponse
+	if err := json.NewDecoder(io.LimitReader(resp.Body, maxWebhookBody)).Decode(&reply); err != nil {
+		return Identity{}, fmt.Errorf("auth webhook: %w", err)
+	}
+	if !reply.Allow {
+		return Identity{}, ErrWebhookDenied
+	}
+	return Identity{
+		User:         req.User,
+		Pool:         reply.Pool,
+		Rate:         max(reply.Rate, 0),
+		MaxConns:     max(reply.MaxConns, 0),
+		MonthlyBytes: max(reply.MonthlyBytes, 0),
+	}, nil
+}
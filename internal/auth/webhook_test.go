@@ -0,0 +1,75 @@
+package auth
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+)
+
+func TestWebhookAuthenticator(t *testing.T) {
+	hook := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		var req WebhookRequest
+		if err := json.NewDecoder(r.Body).Decode(&req); err != nil {
+			t.Errorf("failed to decode webhook body: %v", err)
+		}
+		switch {
+		case req.User == "broken":
+			w.WriteHeader(http.StatusBadGateway)
+		case req.User == "banned":
+			w.WriteHeader(http.StatusForbidden)
+		case req.Password != "s3cret" || req.ClientIP != "203.0.113.7":
+			json.NewEncoder(w).Encode(map[string]any{"allow": false})
+		default:
+			json.NewEncoder(w).Encode(map[string]any{"allow": true, "pool": "premium", "rate": 5})
+		}
+	}))
+	defer hook.Close()
+	a := NewWebhookAuthenticator(WebhookConfig{URL: hook.URL})
+
+	req := WebhookRequest{User: "alice", Password: "s3cret", ClientIP: "203.0.113.7", Protocol: "http"}
+	id, err := a.Authenticate(context.Background(), req)
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if id.User != "alice" || id.Pool != "premium" || id.Rate != 5 {
+		t.Errorf("identity = %+v, want alice with pool premium and rate 5", id)
+	}
+
+	tests := []struct {
+		name   string
+		modify func(r *WebhookRequest)
+		denied bool
+	}{
+		{"wrong password", func(r *WebhookRequest) { r.Password = "wrong" }, true},
+		{"other client", func(r *WebhookRequest) { r.ClientIP = "198.51.100.1" }, true},
+		{"forbidden", func(r *WebhookRequest) { r.User = "banned" }, true},
+		{"webhook error", func(r *WebhookRequest) { r.User = "broken" }, false},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			r := req
+			tt.modify(&r)
+			_, err := a.Authenticate(context.Background(), r)
+			if err == nil {
+				t.Fatal("expected the credential to be refused")
+			}
+			if errors.Is(err, ErrWebhookDenied) != tt.denied {
+				t.Errorf("error = %v, want denied = %v", err, tt.denied)
+			}
+		})
+	}
+}
+
+func TestWebhookAuthenticator_Unreachable(t *testing.T) {
+	hook := httptest.NewServer(http.NotFoundHandler())
+	url := hook.URL
+	hook.Close()
+
+	a := NewWebhookAuthenticator(WebhookConfig{URL: url})
+	if _, err := a.Authenticate(context.Background(), WebhookRequest{User: "alice"}); err == nil {
+		t.Error("expected an unreachable webhook to deny")
+	}
+}
@@ -1,12 +1,22 @@
 // Package balancer provides IP load balancing algorithms.
 package balancer
 
-import "time"
+import (
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/logger"
+)
+
+// log logs on behalf of the balancer module, so its level can be set on its own.
+var log = logger.Module("balancer")
 
 // Balancer is the interface for IP selection algorithms.
 type Balancer interface {
 	// Select returns the best IP to use for the given host.
 	Select(host string) (string, error)
+	// SelectWithOptions returns the best IP for the host subject to the given constraints.
+	SelectWithOptions(host string, opts SelectOptions) (string, error)
 	// Record records that an IP was used for a host.
 	Record(host, ip string)
 	// GetStats returns balancer statistics.
@@ -17,6 +27,26 @@ type Balancer interface {
 	Stop()
 	// UpdateHistoryConfig updates history configuration at runtime.
 	UpdateHistoryConfig(window time.Duration, size int)
+	// Drain stops new selections of an IP while existing connections finish.
+	Drain(ip string) bool
+	// DrainGradually lowers an IP's share of new selections to zero over a period.
+	DrainGradually(ip string, period time.Duration) bool
+	// Undrain returns a drained IP to service.
+	Undrain(ip string) bool
+	// IsDraining reports whether an IP is draining.
+	IsDraining(ip string) bool
+	// DrainWeight returns the share of selections an IP still receives (1 = in service).
+	DrainWeight(ip string) float64
+	// DrainingIPs returns the IPs currently draining.
+	DrainingIPs() []string
+}
+
+// SelectOptions constrains a single selection.
+type SelectOptions struct {
+	// Candidates restricts selection to the given IPs. Nil means all configured IPs.
+	Candidates []string
+	// Exclude lists IPs to avoid. Ignored if it would leave no available IP.
+	Exclude []string
 }
 
 // Stats holds balancer statistics.
@@ -33,6 +63,11 @@ type Config struct {
 	HistorySize   int
 	Limiter       IPLimiter
 	HealthChecker IPHealthChecker
+	// SlowStartWindow ramps IPs that return to health up to a full share over
+	// this window (0 = full share immediately).
+	SlowStartWindow time.Duration
+	// Clock timestamps history entries. Nil means the wall clock.
+	Clock clock.Clock
 }
 
 // IPLimiter is the interface for checking IP availability.
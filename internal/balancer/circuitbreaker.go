@@ -4,6 +4,9 @@ package balancer
 import (
 	"sync"
 	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
 )
 
 // State represents the circuit breaker state.
@@ -40,6 +43,8 @@ type CircuitBreakerConfig struct {
 	SuccessThreshold int
 	// Timeout is how long the circuit stays open before transitioning to half-open.
 	Timeout time.Duration
+	// Clock measures the open timeout. Nil means the wall clock.
+	Clock clock.Clock
 }
 
 // DefaultCircuitBreakerConfig returns sensible defaults.
@@ -68,6 +73,7 @@ type CircuitBreaker struct {
 
 // NewCircuitBreaker creates a new circuit breaker with the given configuration.
 func NewCircuitBreaker(config CircuitBreakerConfig) *CircuitBreaker {
+	config.Clock = clock.OrReal(config.Clock)
 	return &CircuitBreaker{
 		states: make(map[string]*ipState),
 		config: config,
@@ -116,8 +122,8 @@ func (cb *CircuitBreaker) IsHealthy(ip string) bool {
 		return true
 	case StateOpen:
 		// Check if timeout has elapsed
-		if time.Since(state.lastFailure) >= cb.config.Timeout {
-			state.state = StateHalfOpen
+		if cb.config.Clock.Since(state.lastFailure) >= cb.config.Timeout {
+			cb.transition(ip, state, StateHalfOpen)
 			state.successes = 0
 			return true // Allow one request to test
 		}
@@ -144,7 +150,7 @@ func (cb *CircuitBreaker) RecordSuccess(ip string) {
 		state.successes++
 		if state.successes >= cb.config.SuccessThreshold {
 			// Close the circuit
-			state.state = StateClosed
+			cb.transition(ip, state, StateClosed)
 			state.failures = 0
 			state.successes = 0
 		}
@@ -161,21 +167,55 @@ func (cb *CircuitBreaker) RecordFailure(ip string) {
 	cb.mu.Lock()
 	defer cb.mu.Unlock()
 
-	state.lastFailure = time.Now()
+	state.lastFailure = cb.config.Clock.Now()
 
 	switch state.state {
 	case StateClosed:
 		state.failures++
 		if state.failures >= cb.config.FailureThreshold {
-			state.state = StateOpen
+			cb.transition(ip, state, StateOpen)
 		}
 	case StateHalfOpen:
 		// Any failure in half-open opens the circuit again
-		state.state = StateOpen
+		cb.transition(ip, state, StateOpen)
 		state.successes = 0
 	}
 }
 
+// Record records the outcome of a connection through ip. Safe to call on a nil circuit breaker.
+func (cb *CircuitBreaker) Record(ip string, failed bool) {
+	if cb == nil {
+		return
+	}
+	if failed {
+		cb.RecordFailure(ip)
+	} else {
+		cb.RecordSuccess(ip)
+	}
+}
+
+// transition moves state to next, logging and exporting the change.
+// Must be called with mu held.
+func (cb *CircuitBreaker) transition(ip string, state *ipState, next State) {
+	if state.state == next {
+		return
+	}
+	log.Info("circuit_breaker_transition", "ip", ip, "from", state.state.String(), "to", next.String())
+	state.state = next
+	metrics.CircuitBreakerState.WithLabelValues(ip).Set(float64(next))
+}
+
+// GetHealthyIPs filters the given IPs and returns the ones whose circuit is not open.
+func (cb *CircuitBreaker) GetHealthyIPs(ips []string) []string {
+	healthy := make([]string, 0, len(ips))
+	for _, ip := range ips {
+		if cb.IsHealthy(ip) {
+			healthy = append(healthy, ip)
+		}
+	}
+	return healthy
+}
+
 // GetState returns the current state for an IP.
 func (cb *CircuitBreaker) GetState(ip string) State {
 	cb.mu.RLock()
@@ -219,11 +259,15 @@ func (cb *CircuitBreaker) Reset(ip string) {
 	cb.mu.Lock()
 	defer cb.mu.Unlock()
 	delete(cb.states, ip)
+	metrics.CircuitBreakerState.WithLabelValues(ip).Set(float64(StateClosed))
 }
 
 // ResetAll resets all circuit breaker states.
 func (cb *CircuitBreaker) ResetAll() {
 	cb.mu.Lock()
 	defer cb.mu.Unlock()
+	for ip := range cb.states {
+		metrics.CircuitBreakerState.WithLabelValues(ip).Set(float64(StateClosed))
+	}
 	cb.states = make(map[string]*ipState)
 }
@@ -4,6 +4,8 @@ import (
 	"sync"
 	"testing"
 	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
 )
 
 func TestCircuitBreaker_InitialState(t *testing.T) {
@@ -102,6 +104,27 @@ func TestCircuitBreaker_TransitionsToHalfOpen(t *testing.T) {
 	}
 }
 
+func TestCircuitBreaker_FakeClock(t *testing.T) {
+	c := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	cb := NewCircuitBreaker(CircuitBreakerConfig{
+		FailureThreshold: 1,
+		SuccessThreshold: 1,
+		Timeout:          time.Minute,
+		Clock:            c,
+	})
+	ip := "192.168.1.1"
+
+	cb.RecordFailure(ip)
+	c.Advance(59 * time.Second)
+	if cb.IsHealthy(ip) {
+		t.Error("expected circuit to stay open before the timeout")
+	}
+	c.Advance(time.Second)
+	if !cb.IsHealthy(ip) || cb.GetState(ip) != StateHalfOpen {
+		t.Errorf("expected half-open at the timeout, got %s", cb.GetState(ip))
+	}
+}
+
 func TestCircuitBreaker_ClosesAfterSuccessInHalfOpen(t *testing.T) {
 	config := CircuitBreakerConfig{
 		FailureThreshold: 2,
@@ -270,3 +293,26 @@ func TestState_String(t *testing.T) {
 		}
 	}
 }
+
+func TestCircuitBreaker_Record(t *testing.T) {
+	cb := NewCircuitBreaker(CircuitBreakerConfig{FailureThreshold: 2, SuccessThreshold: 1, Timeout: time.Minute})
+	ip := "192.168.1.1"
+
+	cb.Record(ip, true)
+	cb.Record(ip, false)
+	cb.Record(ip, true)
+	if state := cb.GetState(ip); state != StateClosed {
+		t.Errorf("a success should reset the failure count, got %s", state)
+	}
+
+	cb.Record(ip, true)
+	if state := cb.GetState(ip); state != StateOpen {
+		t.Errorf("expected StateOpen after consecutive failures, got %s", state)
+	}
+	if got := cb.GetHealthyIPs([]string{ip, "192.168.1.2"}); len(got) != 1 || got[0] != "192.168.1.2" {
+		t.Errorf("GetHealthyIPs = %v, want [192.168.1.2]", got)
+	}
+
+	var nilCB *CircuitBreaker
+	nilCB.Record(ip, true)
+}
@@ -0,0 +1,152 @@
+// Package balancer provides IP load balancing algorithms.
+package balancer
+
+import (
+	"sort"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// drainState is the drain of a single IP. A zero period drains immediately.
+type drainState struct {
+	start  time.Time
+	period time.Duration
+}
+
+// drainSet tracks outbound IPs that are being drained.
+// Drained IPs receive no new selections; existing connections are left to finish.
+// Gradually drained IPs keep receiving a shrinking share of selections until
+// their period has elapsed.
+type drainSet struct {
+	ips   map[string]drainState
+	clock clock.Clock
+	mu    sync.RWMutex
+}
+
+// newDrainSet creates an empty drainSet that measures drain periods by c.
+func newDrainSet(c clock.Clock) *drainSet {
+	return &drainSet{ips: make(map[string]drainState), clock: clock.OrReal(c)}
+}
+
+// add marks ip as draining over period. Returns false if it was already
+// draining, unless an immediate drain cuts short a gradual one.
+func (d *drainSet) add(ip string, period time.Duration) bool {
+	now := d.clock.Now()
+	d.mu.Lock()
+	defer d.mu.Unlock()
+	if state, ok := d.ips[ip]; ok && (period > 0 || state.weight(now) == 0) {
+		return false
+	}
+	d.ips[ip] = drainState{start: now, period: period}
+	metrics.IPDraining.WithLabelValues(ip).Set(1)
+	return true
+}
+
+// remove clears the draining mark for ip. Returns false if it was not draining.
+func (d *drainSet) remove(ip string) bool {
+	d.mu.Lock()
+	defer d.mu.Unlock()
+	if _, ok := d.ips[ip]; !ok {
+		return false
+	}
+	delete(d.ips, ip)
+	metrics.IPDraining.WithLabelValues(ip).Set(0)
+	return true
+}
+
+// contains reports whether ip is draining.
+func (d *drainSet) contains(ip string) bool {
+	d.mu.RLock()
+	defer d.mu.RUnlock()
+	_, ok := d.ips[ip]
+	return ok
+}
+
+// weight returns the share of selections ip still receives, from 1 (in service) to 0 (drained).
+func (d *drainSet) weight(ip string) float64 {
+	d.mu.RLock()
+	defer d.mu.RUnlock()
+	if len(d.ips) == 0 {
+		return 1
+	}
+	state, ok := d.ips[ip]
+	if !ok {
+		return 1
+	}
+	return state.weight(d.clock.Now())
+}
+
+// weight decreases linearly from 1 to 0 over the drain period.
+func (s drainState) weight(now time.Time) float64 {
+	elapsed := now.Sub(s.start)
+	if s.period <= 0 || elapsed >= s.period {
+		return 0
+	}
+	return 1 - float64(elapsed)/float64(s.period)
+}
+
+// list returns the draining IPs in sorted order.
+func (d *drainSet) list() []string {
+	d.mu.RLock()
+	defer d.mu.RUnlock()
+	result := make([]string, 0, len(d.ips))
+	for ip := range d.ips {
+		result = append(result, ip)
+	}
+	sort.Strings(result)
+	return result
+}
+
+// filter returns ips without the fully drained ones.
+// Returns ips unchanged (without allocating) if nothing is draining.
+func (d *drainSet) filter(ips []string) []string {
+	d.mu.RLock()
+	defer d.mu.RUnlock()
+	if len(d.ips) == 0 {
+		return ips
+	}
+	now := d.clock.Now()
+	result := make([]string, 0, len(ips))
+	for _, ip := range ips {
+		if state, ok := d.ips[ip]; !ok || state.weight(now) > 0 {
+			result = append(result, ip)
+		}
+	}
+	return result
+}
+
+// Drain stops new selections of ip while letting existing connections finish.
+// Returns false if the IP was already fully draining.
+func (l *LRU) Drain(ip string) bool {
+	return l.drains.add(ip, 0)
+}
+
+// DrainGradually lowers the share of new selections ip receives linearly to
+// zero over period, so its traffic moves to the rest of the pool without a
+// sudden spike. Returns false if the IP was already draining.
+func (l *LRU) DrainGradually(ip string, period time.Duration) bool {
+	return l.drains.add(ip, period)
+}
+
+// Undrain returns ip to service. Returns false if the IP was not draining.
+func (l *LRU) Undrain(ip string) bool {
+	return l.drains.remove(ip)
+}
+
+// IsDraining reports whether ip is draining.
+func (l *LRU) IsDraining(ip string) bool {
+	return l.drains.contains(ip)
+}
+
+// DrainWeight returns the share of selections ip still receives, from 1 (in service) to 0 (drained).
+func (l *LRU) DrainWeight(ip string) float64 {
+	return l.drains.weight(ip)
+}
+
+// DrainingIPs returns the IPs currently draining.
+func (l *LRU) DrainingIPs() []string {
+	return l.drains.list()
+}
@@ -0,0 +1,126 @@
+package balancer
+
+import (
+	"errors"
+	"reflect"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func TestLRU_DrainExcludesIP(t *testing.T) {
+	lru := NewLRU(Config{
+		IPs:           []string{"192.168.1.1", "192.168.1.2"},
+		HistoryWindow: 300,
+		HistorySize:   100,
+		Limiter:       &mockLimiter{},
+	})
+
+	if !lru.Drain("192.168.1.1") {
+		t.Fatal("expected first Drain to return true")
+	}
+	if lru.Drain("192.168.1.1") {
+		t.Error("expected repeated Drain to return false")
+	}
+
+	for i := 0; i < 5; i++ {
+		ip, err := lru.Select("example.com")
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		if ip != "192.168.1.2" {
+			t.Errorf("expected 192.168.1.2, got %s", ip)
+		}
+		lru.Record("example.com", ip)
+	}
+
+	if got := lru.DrainingIPs(); !reflect.DeepEqual(got, []string{"192.168.1.1"}) {
+		t.Errorf("DrainingIPs() = %v", got)
+	}
+
+	if !lru.Undrain("192.168.1.1") || lru.IsDraining("192.168.1.1") {
+		t.Error("expected IP to be back in service after Undrain")
+	}
+}
+
+func TestLRU_DrainAllIPs(t *testing.T) {
+	lru := NewLRU(Config{
+		IPs:           []string{"192.168.1.1"},
+		HistoryWindow: 300,
+		HistorySize:   100,
+		Limiter:       &mockLimiter{},
+	})
+	lru.Drain("192.168.1.1")
+
+	if _, err := lru.Select("example.com"); !errors.Is(err, ErrNoAvailableIPs) {
+		t.Errorf("expected ErrNoAvailableIPs with every IP draining, got %v", err)
+	}
+}
+
+func TestLRU_DrainGradually(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	lru := NewLRU(Config{
+		IPs:           []string{"192.168.1.1", "192.168.1.2"},
+		HistoryWindow: 3600,
+		HistorySize:   1000,
+		Limiter:       &mockLimiter{},
+		Clock:         c,
+	})
+
+	if !lru.DrainGradually("192.168.1.1", 10*time.Minute) {
+		t.Fatal("expected first DrainGradually to return true")
+	}
+	if lru.DrainGradually("192.168.1.1", 10*time.Minute) {
+		t.Error("expected repeated DrainGradually to return false")
+	}
+
+	c.Advance(5 * time.Minute)
+	if w := lru.DrainWeight("192.168.1.1"); w != 0.5 {
+		t.Errorf("DrainWeight halfway = %v, want 0.5", w)
+	}
+	if w := lru.DrainWeight("192.168.1.2"); w != 1 {
+		t.Errorf("DrainWeight of an IP in service = %v, want 1", w)
+	}
+
+	// At half weight the draining IP gets about half the share of the other
+	usage := make(map[string]int)
+	for i := 0; i < 30; i++ {
+		ip, err := lru.Select("example.com")
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		usage[ip]++
+		lru.Record("example.com", ip)
+	}
+	if got := usage["192.168.1.1"]; got < 8 || got > 12 {
+		t.Errorf("draining IP selected %d of 30 times at half weight, want about 10", got)
+	}
+
+	c.Advance(5 * time.Minute)
+	for i := 0; i < 5; i++ {
+		if ip, _ := lru.Select("other.example.com"); ip != "192.168.1.2" {
+			t.Fatalf("expected fully drained IP to be skipped, got %s", ip)
+		}
+	}
+	if !lru.IsDraining("192.168.1.1") {
+		t.Error("expected IP to stay draining after its period")
+	}
+}
+
+func TestLRU_DrainCutsShortGradualDrain(t *testing.T) {
+	lru := NewLRU(Config{
+		IPs:           []string{"192.168.1.1", "192.168.1.2"},
+		HistoryWindow: 300,
+		HistorySize:   100,
+		Limiter:       &mockLimiter{},
+	})
+
+	lru.DrainGradually("192.168.1.1", time.Hour)
+	if !lru.Drain("192.168.1.1") {
+		t.Fatal("expected Drain to cut short a gradual drain")
+	}
+	if w := lru.DrainWeight("192.168.1.1"); w != 0 {
+		t.Errorf("DrainWeight = %v, want 0", w)
+	}
+}
@@ -0,0 +1,44 @@
+// Package balancer provides IP load balancing algorithms.
+package balancer
+
+// combinedHealth reports an IP as healthy only if every checker does.
+type combinedHealth []IPHealthChecker
+
+// CombineHealth returns an IPHealthChecker that requires all checkers to
+// report an IP healthy. Nil checkers are skipped; returns nil if none remain.
+func CombineHealth(checkers ...IPHealthChecker) IPHealthChecker {
+	var combined combinedHealth
+	for _, c := range checkers {
+		if c != nil {
+			combined = append(combined, c)
+		}
+	}
+	switch len(combined) {
+	case 0:
+		return nil
+	case 1:
+		return combined[0]
+	}
+	return combined
+}
+
+// IsHealthy returns true if every checker reports ip healthy.
+func (c combinedHealth) IsHealthy(ip string) bool {
+	for _, checker := range c {
+		if !checker.IsHealthy(ip) {
+			return false
+		}
+	}
+	return true
+}
+
+// GetHealthyIPs filters ips through every checker in turn.
+func (c combinedHealth) GetHealthyIPs(ips []string) []string {
+	healthy := make([]string, 0, len(ips))
+	for _, ip := range ips {
+		if c.IsHealthy(ip) {
+			healthy = append(healthy, ip)
+		}
+	}
+	return healthy
+}
@@ -4,6 +4,8 @@ package balancer
 import (
 	"sync"
 	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
 )
 
 // Entry represents a single usage record.
@@ -15,13 +17,20 @@ type Entry struct {
 // HostHistory stores usage history for a single host.
 type HostHistory struct {
 	entries []Entry
+	clock   clock.Clock
 	mu      sync.RWMutex
 }
 
 // NewHostHistory creates a new HostHistory.
 func NewHostHistory() *HostHistory {
+	return newHostHistory(clock.Real)
+}
+
+// newHostHistory creates a new HostHistory timestamped by c.
+func newHostHistory(c clock.Clock) *HostHistory {
 	return &HostHistory{
 		entries: make([]Entry, 0, 100),
+		clock:   c,
 	}
 }
 
@@ -31,7 +40,7 @@ func (h *HostHistory) Add(ip string) {
 	defer h.mu.Unlock()
 	h.entries = append(h.entries, Entry{
 		IP:        ip,
-		Timestamp: time.Now(),
+		Timestamp: h.clock.Now(),
 	})
 }
 
@@ -40,7 +49,7 @@ func (h *HostHistory) GetFiltered(window time.Duration, maxSize int) []Entry {
 	h.mu.RLock()
 	defer h.mu.RUnlock()
 
-	cutoff := time.Now().Add(-window)
+	cutoff := h.clock.Now().Add(-window)
 	result := make([]Entry, 0, maxSize)
 
 	// Start from the end (most recent) and work backwards
@@ -58,7 +67,7 @@ func (h *HostHistory) Cleanup(window time.Duration) int {
 	h.mu.Lock()
 	defer h.mu.Unlock()
 
-	cutoff := time.Now().Add(-window)
+	cutoff := h.clock.Now().Add(-window)
 	newEntries := make([]Entry, 0, len(h.entries))
 
 	for _, e := range h.entries {
@@ -82,6 +91,7 @@ func (h *HostHistory) Len() int {
 // History stores usage history for all hosts.
 type History struct {
 	hosts           map[string]*HostHistory
+	clock           clock.Clock
 	mu              sync.RWMutex
 	maxTotalEntries int // Maximum total entries across all hosts (0 = unlimited)
 	totalEntries    int // Current total entry count
@@ -97,10 +107,18 @@ func WithMaxTotalEntries(max int) HistoryOption {
 	}
 }
 
+// WithClock sets the clock used to timestamp and expire entries.
+func WithClock(c clock.Clock) HistoryOption {
+	return func(h *History) {
+		h.clock = clock.OrReal(c)
+	}
+}
+
 // NewHistory creates a new History with optional configuration.
 func NewHistory(opts ...HistoryOption) *History {
 	h := &History{
 		hosts: make(map[string]*HostHistory),
+		clock: clock.Real,
 	}
 	for _, opt := range opts {
 		opt(h)
@@ -126,7 +144,7 @@ func (h *History) GetOrCreate(host string) *HostHistory {
 		return hh
 	}
 
-	hh = NewHostHistory()
+	hh = newHostHistory(h.clock)
 	h.hosts[host] = hh
 	return hh
 }
@@ -4,6 +4,8 @@ import (
 	"sync"
 	"testing"
 	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
 )
 
 func TestNewHostHistory(t *testing.T) {
@@ -190,6 +192,24 @@ func TestHistory_Record(t *testing.T) {
 	}
 }
 
+func TestHistory_WithClock(t *testing.T) {
+	c := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	h := NewHistory(WithClock(c))
+
+	h.Record("host1.com", "192.168.1.1")
+	c.Advance(30 * time.Second)
+	h.Record("host1.com", "192.168.1.2")
+	c.Advance(45 * time.Second)
+
+	entries := h.GetFiltered("host1.com", time.Minute, 100)
+	if len(entries) != 1 || entries[0].IP != "192.168.1.2" {
+		t.Errorf("expected only the entry inside the window, got %v", entries)
+	}
+	if removed, _ := h.Cleanup(time.Minute); removed != 1 {
+		t.Errorf("expected 1 expired entry removed, got %d", removed)
+	}
+}
+
 func TestHistory_GetFiltered_NonexistentHost(t *testing.T) {
 	h := NewHistory()
 
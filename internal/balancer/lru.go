@@ -4,10 +4,10 @@ package balancer
 import (
 	"errors"
 	"math"
+	"slices"
 	"sync"
 	"time"
 
-	"github.com/cr0hn/outbound-lb/internal/logger"
 	"github.com/cr0hn/outbound-lb/internal/metrics"
 )
 
@@ -41,6 +41,8 @@ type LRU struct {
 	limiter       IPLimiter
 	healthChecker IPHealthChecker
 	history       *History
+	drains        *drainSet
+	slowStart     *slowStartSet
 	stopCh        chan struct{}
 	wg            sync.WaitGroup
 	mu            sync.RWMutex
@@ -54,7 +56,9 @@ func NewLRU(cfg Config) *LRU {
 		historySize:   cfg.HistorySize,
 		limiter:       cfg.Limiter,
 		healthChecker: cfg.HealthChecker,
-		history:       NewHistory(),
+		history:       NewHistory(WithClock(cfg.Clock)),
+		drains:        newDrainSet(cfg.Clock),
+		slowStart:     newSlowStartSet(cfg.SlowStartWindow, cfg.Clock),
 		stopCh:        make(chan struct{}),
 	}
 }
@@ -65,7 +69,7 @@ func (l *LRU) UpdateHistoryConfig(window time.Duration, size int) {
 	l.historyWindow = window
 	l.historySize = size
 	l.mu.Unlock()
-	logger.Info("history_config_updated", "window", window, "size", size)
+	log.Info("history_config_updated", "window", window, "size", size)
 }
 
 // Start starts the background cleanup goroutine.
@@ -114,16 +118,30 @@ func (l *LRU) cleanupLoop() {
 // 3. Exclude IPs that have reached connection limits
 // 4. Select IP with lowest usage count (tie-break by oldest last use)
 func (l *LRU) Select(host string) (string, error) {
-	logger.Trace("balancer_select_start", "host", host)
+	return l.SelectWithOptions(host, SelectOptions{})
+}
+
+// SelectWithOptions runs the same algorithm as Select, restricted by opts.
+func (l *LRU) SelectWithOptions(host string, opts SelectOptions) (string, error) {
+	log.Trace("balancer_select_start", "host", host)
+
+	ips := l.ips
+	if opts.Candidates != nil {
+		ips = opts.Candidates
+	}
 
 	// Get available IPs (not at connection limit)
-	availableIPs := l.getAvailableIPs()
+	availableIPs := l.filterAvailableIPs(ips)
 	if len(availableIPs) == 0 {
-		logger.Trace("balancer_no_available_ips", "host", host, "total_ips", len(l.ips))
+		log.Trace("balancer_no_available_ips", "host", host, "total_ips", len(l.ips))
 		return "", ErrNoAvailableIPs
 	}
 
-	logger.Trace("balancer_available_ips", "host", host, "count", len(availableIPs), "ips", availableIPs)
+	if len(opts.Exclude) > 0 {
+		availableIPs = excludeIPs(availableIPs, opts.Exclude)
+	}
+
+	log.Trace("balancer_available_ips", "host", host, "count", len(availableIPs), "ips", availableIPs)
 
 	// Get history config under lock
 	l.mu.RLock()
@@ -133,7 +151,7 @@ func (l *LRU) Select(host string) (string, error) {
 
 	// Get filtered history for this host
 	entries := l.history.GetFiltered(host, window, size)
-	logger.Trace("balancer_history_entries", "host", host, "count", len(entries), "window", window, "max_size", size)
+	log.Trace("balancer_history_entries", "host", host, "count", len(entries), "window", window, "max_size", size)
 
 	// Get context from pool to avoid allocations
 	ctx := selectContextPool.Get().(*selectContext)
@@ -152,20 +170,25 @@ func (l *LRU) Select(host string) (string, error) {
 		}
 	}
 
-	// Find IP with lowest usage among available IPs
+	// Find IP with lowest usage among available IPs. Usage is scaled by the
+	// drain and slow-start weights, so gradually draining and recovering IPs
+	// get a proportionally smaller share.
 	var selectedIP string
-	minUsage := math.MaxInt
+	minUsage := 0
+	minScore := math.Inf(1)
 	var oldestUse time.Time
 
 	for _, ip := range availableIPs {
 		usage := ctx.usageCount[ip]
 		lastUse := ctx.lastUsed[ip]
+		score := float64(usage+1) / (l.drains.weight(ip) * l.slowStart.weight(ip))
 
-		if usage < minUsage {
+		if selectedIP == "" || score < minScore {
 			minUsage = usage
+			minScore = score
 			selectedIP = ip
 			oldestUse = lastUse
-		} else if usage == minUsage {
+		} else if score == minScore {
 			// Tie-break: prefer IP with oldest last use (or never used)
 			if lastUse.IsZero() || lastUse.Before(oldestUse) {
 				selectedIP = ip
@@ -174,7 +197,7 @@ func (l *LRU) Select(host string) (string, error) {
 		}
 	}
 
-	logger.Trace("balancer_selection_complete", "host", host, "selected", selectedIP, "usage_count", minUsage, "usage_counts", ctx.usageCount)
+	log.Trace("balancer_selection_complete", "host", host, "selected", selectedIP, "usage_count", minUsage, "usage_counts", ctx.usageCount)
 	return selectedIP, nil
 }
 
@@ -198,18 +221,29 @@ func (l *LRU) GetStats() Stats {
 	}
 }
 
-// getAvailableIPs returns IPs that are healthy and haven't reached connection limits.
-// Applies health check filter first, then limiter filter.
-// Implements graceful degradation: if all IPs are unhealthy, uses all IPs.
+// getAvailableIPs returns configured IPs that are healthy and haven't reached connection limits.
 func (l *LRU) getAvailableIPs() []string {
-	ips := l.ips
+	return l.filterAvailableIPs(l.ips)
+}
+
+// filterAvailableIPs returns the given IPs that are not draining, are healthy and haven't
+// reached connection limits. Applies drain filter first, then health check, then limiter.
+// Implements graceful degradation: if all IPs are unhealthy, uses all IPs.
+// Draining IPs are never used, even if that leaves none.
+func (l *LRU) filterAvailableIPs(ips []string) []string {
+	// 0. Drop draining IPs
+	ips = l.drains.filter(ips)
+	if len(ips) == 0 {
+		return ips
+	}
 
 	// 1. Filter by health check (if configured)
 	if l.healthChecker != nil {
 		healthyIPs := l.healthChecker.GetHealthyIPs(ips)
+		l.slowStart.observe(ips, healthyIPs)
 		// Graceful degradation: if all IPs are unhealthy, use all
 		if len(healthyIPs) == 0 {
-			logger.Warn("all_ips_unhealthy", "using_all", true, "total_ips", len(ips))
+			log.Warn("all_ips_unhealthy", "using_all", true, "total_ips", len(ips))
 		} else {
 			ips = healthyIPs
 		}
@@ -221,3 +255,19 @@ func (l *LRU) getAvailableIPs() []string {
 	}
 	return ips
 }
+
+// excludeIPs returns ips without the excluded ones.
+// If every IP would be excluded, ips is returned unchanged so the request can still be served.
+func excludeIPs(ips, exclude []string) []string {
+	result := make([]string, 0, len(ips))
+	for _, ip := range ips {
+		if !slices.Contains(exclude, ip) {
+			result = append(result, ip)
+		}
+	}
+	if len(result) == 0 {
+		log.Debug("exclusion_ignored", "reason", "would exclude all available IPs", "excluded", exclude)
+		return ips
+	}
+	return result
+}
@@ -0,0 +1,152 @@
+// Package balancer provides IP load balancing algorithms.
+package balancer
+
+import (
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// OutlierConfig holds configuration for passive outlier detection.
+type OutlierConfig struct {
+	// ErrorRate is the fraction of failed connections (0-1] that ejects an IP.
+	ErrorRate float64
+	// MinRequests is the number of connections in a window before an IP can be ejected.
+	MinRequests int
+	// Window is the interval over which the error rate is measured.
+	Window time.Duration
+	// EjectionTime is how long an ejected IP is kept out of rotation.
+	EjectionTime time.Duration
+	// Clock measures windows and ejections. Nil means the wall clock.
+	Clock clock.Clock
+}
+
+// outlierState holds the error counts and ejection of a single IP.
+type outlierState struct {
+	windowStart  time.Time
+	total        int
+	failures     int
+	ejectedUntil time.Time
+}
+
+// OutlierDetector ejects IPs from rotation when the error rate of real traffic
+// through them exceeds a threshold, without waiting for an active health check.
+type OutlierDetector struct {
+	mu     sync.Mutex
+	states map[string]*outlierState
+	config OutlierConfig
+}
+
+// NewOutlierDetector creates a new outlier detector with the given configuration.
+func NewOutlierDetector(config OutlierConfig) *OutlierDetector {
+	config.Clock = clock.OrReal(config.Clock)
+	return &OutlierDetector{
+		states: make(map[string]*outlierState),
+		config: config,
+	}
+}
+
+// Record records the outcome of a connection through ip. Safe to call on a nil detector.
+func (d *OutlierDetector) Record(ip string, failed bool) {
+	if d == nil {
+		return
+	}
+	now := d.config.Clock.Now()
+
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	state, ok := d.states[ip]
+	if !ok {
+		state = &outlierState{windowStart: now}
+		d.states[ip] = state
+	}
+	if now.Before(state.ejectedUntil) {
+		return
+	}
+	if now.Sub(state.windowStart) >= d.config.Window {
+		state.windowStart, state.total, state.failures = now, 0, 0
+	}
+
+	state.total++
+	if failed {
+		state.failures++
+	}
+	if state.total < d.config.MinRequests || float64(state.failures)/float64(state.total) < d.config.ErrorRate {
+		return
+	}
+
+	log.Warn("outlier_ejected",
+		"ip", ip,
+		"failures", state.failures,
+		"requests", state.total,
+		"ejection_time", d.config.EjectionTime,
+	)
+	metrics.OutlierEjections.WithLabelValues(ip).Inc()
+	state.ejectedUntil = now.Add(d.config.EjectionTime)
+	state.windowStart, state.total, state.failures = state.ejectedUntil, 0, 0
+}
+
+// IsHealthy returns false while ip is ejected.
+func (d *OutlierDetector) IsHealthy(ip string) bool {
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	state, ok := d.states[ip]
+	return !ok || !d.config.Clock.Now().Before(state.ejectedUntil)
+}
+
+// GetHealthyIPs filters the given IPs and returns the ones not ejected.
+func (d *OutlierDetector) GetHealthyIPs(ips []string) []string {
+	healthy := make([]string, 0, len(ips))
+	for _, ip := range ips {
+		if d.IsHealthy(ip) {
+			healthy = append(healthy, ip)
+		}
+	}
+	return healthy
+}
+
+// Ejected returns the IPs currently ejected.
+func (d *OutlierDetector) Ejected() []string {
+	now := d.config.Clock.Now()
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	var ips []string
+	for ip, state := range d.states {
+		if now.Before(state.ejectedUntil) {
+			ips = append(ips, ip)
+		}
+	}
+	return ips
+}
+
+// EjectedUntil returns the IPs currently ejected and when each ejection ends.
+func (d *OutlierDetector) EjectedUntil() map[string]time.Time {
+	now := d.config.Clock.Now()
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	ejected := make(map[string]time.Time)
+	for ip, state := range d.states {
+		if now.Before(state.ejectedUntil) {
+			ejected[ip] = state.ejectedUntil
+		}
+	}
+	return ejected
+}
+
+// Eject keeps ip out of rotation until the given time, e.g. to restore an
+// ejection that outlived a restart. Times in the past are ignored.
+func (d *OutlierDetector) Eject(ip string, until time.Time) {
+	if !d.config.Clock.Now().Before(until) {
+		return
+	}
+	d.mu.Lock()
+	defer d.mu.Unlock()
+
+	d.states[ip] = &outlierState{windowStart: until, ejectedUntil: until}
+}
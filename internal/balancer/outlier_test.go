@@ -0,0 +1,138 @@
+package balancer
+
+import (
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func newTestOutlierDetector(c *clock.Fake) *OutlierDetector {
+	return NewOutlierDetector(OutlierConfig{
+		ErrorRate:    0.5,
+		MinRequests:  4,
+		Window:       10 * time.Second,
+		EjectionTime: 30 * time.Second,
+		Clock:        c,
+	})
+}
+
+func TestOutlierDetector_EjectsAboveErrorRate(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	d := newTestOutlierDetector(c)
+	ip := "192.168.1.1"
+
+	d.Record(ip, true)
+	d.Record(ip, true)
+	d.Record(ip, true)
+	if !d.IsHealthy(ip) {
+		t.Fatal("IP should not be ejected before MinRequests")
+	}
+
+	d.Record(ip, false)
+	if d.IsHealthy(ip) {
+		t.Fatal("IP should be ejected at 75% errors")
+	}
+	if got := d.GetHealthyIPs([]string{ip, "192.168.1.2"}); len(got) != 1 || got[0] != "192.168.1.2" {
+		t.Errorf("GetHealthyIPs = %v, want [192.168.1.2]", got)
+	}
+	if got := d.Ejected(); len(got) != 1 || got[0] != ip {
+		t.Errorf("Ejected = %v, want [%s]", got, ip)
+	}
+
+	c.Advance(30 * time.Second)
+	if !d.IsHealthy(ip) {
+		t.Error("IP should return after the ejection time")
+	}
+}
+
+func TestOutlierDetector_BelowErrorRate(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	d := newTestOutlierDetector(c)
+	ip := "192.168.1.1"
+
+	for i := 0; i < 10; i++ {
+		d.Record(ip, i%3 == 0)
+	}
+	if !d.IsHealthy(ip) {
+		t.Error("IP below the error rate should not be ejected")
+	}
+}
+
+func TestOutlierDetector_WindowResets(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	d := newTestOutlierDetector(c)
+	ip := "192.168.1.1"
+
+	d.Record(ip, true)
+	d.Record(ip, true)
+	d.Record(ip, true)
+	c.Advance(10 * time.Second)
+
+	// Failures from the previous window no longer count
+	d.Record(ip, true)
+	d.Record(ip, false)
+	d.Record(ip, false)
+	d.Record(ip, false)
+	if !d.IsHealthy(ip) {
+		t.Error("failures from an expired window should not eject")
+	}
+}
+
+func TestOutlierDetector_Eject(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	d := newTestOutlierDetector(c)
+
+	d.Eject("192.168.1.1", c.Now().Add(time.Minute))
+	d.Eject("192.168.1.2", c.Now().Add(-time.Minute))
+	if d.IsHealthy("192.168.1.1") {
+		t.Error("IP should be ejected until the given time")
+	}
+	if !d.IsHealthy("192.168.1.2") {
+		t.Error("an ejection that already ended should be ignored")
+	}
+	if got := d.EjectedUntil(); len(got) != 1 || !got["192.168.1.1"].Equal(c.Now().Add(time.Minute)) {
+		t.Errorf("EjectedUntil = %v", got)
+	}
+
+	c.Advance(time.Minute)
+	if !d.IsHealthy("192.168.1.1") {
+		t.Error("IP should return once the ejection ends")
+	}
+}
+
+func TestOutlierDetector_NilRecord(t *testing.T) {
+	var d *OutlierDetector
+	d.Record("192.168.1.1", true)
+}
+
+type staticHealth map[string]bool
+
+func (h staticHealth) IsHealthy(ip string) bool { return !h[ip] }
+
+func (h staticHealth) GetHealthyIPs(ips []string) []string {
+	var healthy []string
+	for _, ip := range ips {
+		if h.IsHealthy(ip) {
+			healthy = append(healthy, ip)
+		}
+	}
+	return healthy
+}
+
+func TestCombineHealth(t *testing.T) {
+	if CombineHealth() != nil || CombineHealth(nil) != nil {
+		t.Error("CombineHealth without checkers should return nil")
+	}
+
+	a := staticHealth{"10.0.0.1": true}
+	if got := CombineHealth(nil, a); got == nil || got.IsHealthy("10.0.0.1") {
+		t.Error("a single checker should be used as is")
+	}
+
+	combined := CombineHealth(a, staticHealth{"10.0.0.2": true})
+	got := combined.GetHealthyIPs([]string{"10.0.0.1", "10.0.0.2", "10.0.0.3"})
+	if len(got) != 1 || got[0] != "10.0.0.3" {
+		t.Errorf("GetHealthyIPs = %v, want [10.0.0.3]", got)
+	}
+}
@@ -0,0 +1,146 @@
+// Package balancer provides IP load balancing algorithms.
+package balancer
+
+import (
+	"net/http"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// QuarantineConfig holds configuration for quarantining IPs that targets block.
+type QuarantineConfig struct {
+	// BlockRate is the fraction of 429/403 responses (0-1] that quarantines an IP.
+	BlockRate float64
+	// MinResponses is the number of responses in a window before an IP can be quarantined.
+	MinResponses int
+	// Window is the interval over which the block rate is measured.
+	Window time.Duration
+	// Duration is how long a quarantined IP is kept out of rotation.
+	Duration time.Duration
+	// Clock measures windows and quarantines. Nil means the wall clock.
+	Clock clock.Clock
+}
+
+// quarantineState holds the response counts and quarantine of a single IP.
+type quarantineState struct {
+	windowStart      time.Time
+	total            int
+	blocked          int
+	quarantinedUntil time.Time
+}
+
+// Quarantine takes IPs out of rotation when targets suddenly answer a high
+// share of their plain-HTTP requests with 429 or 403, which usually means the
+// IP has been rate-limited or banned rather than that it is unreachable.
+type Quarantine struct {
+	mu     sync.Mutex
+	states map[string]*quarantineState
+	config QuarantineConfig
+}
+
+// NewQuarantine creates a new quarantine with the given configuration.
+func NewQuarantine(config QuarantineConfig) *Quarantine {
+	config.Clock = clock.OrReal(config.Clock)
+	return &Quarantine{
+		states: make(map[string]*quarantineState),
+		config: config,
+	}
+}
+
+// blockedStatus reports whether a response status means the target refused the IP.
+func blockedStatus(status int) bool {
+	return status == http.StatusTooManyRequests || status == http.StatusForbidden
+}
+
+// Record records the status of a response received through ip. Safe to call on a nil quarantine.
+func (q *Quarantine) Record(ip string, status int) {
+	if q == nil {
+		return
+	}
+	now := q.config.Clock.Now()
+
+	q.mu.Lock()
+	defer q.mu.Unlock()
+
+	state, ok := q.states[ip]
+	if !ok {
+		state = &quarantineState{windowStart: now}
+		q.states[ip] = state
+	}
+	if now.Before(state.quarantinedUntil) {
+		return
+	}
+	if now.Sub(state.windowStart) >= q.config.Window {
+		state.windowStart, state.total, state.blocked = now, 0, 0
+	}
+
+	state.total++
+	if blockedStatus(status) {
+		state.blocked++
+	}
+	if state.total < q.config.MinResponses || float64(state.blocked)/float64(state.total) < q.config.BlockRate {
+		return
+	}
+
+	log.Warn("ip_quarantined",
+		"ip", ip,
+		"blocked", state.blocked,
+		"responses", state.total,
+		"duration", q.config.Duration,
+	)
+	metrics.Quarantines.WithLabelValues(ip).Inc()
+	state.quarantinedUntil = now.Add(q.config.Duration)
+	state.windowStart, state.total, state.blocked = state.quarantinedUntil, 0, 0
+}
+
+// IsHealthy returns false while ip is quarantined.
+func (q *Quarantine) IsHealthy(ip string) bool {
+	q.mu.Lock()
+	defer q.mu.Unlock()
+
+	state, ok := q.states[ip]
+	return !ok || !q.config.Clock.Now().Before(state.quarantinedUntil)
+}
+
+// GetHealthyIPs filters the given IPs and returns the ones not quarantined.
+func (q *Quarantine) GetHealthyIPs(ips []string) []string {
+	healthy := make([]string, 0, len(ips))
+	for _, ip := range ips {
+		if q.IsHealthy(ip) {
+			healthy = append(healthy, ip)
+		}
+	}
+	return healthy
+}
+
+// QuarantinedUntil returns the IPs currently quarantined and when each quarantine ends.
+func (q *Quarantine) QuarantinedUntil() map[string]time.Time {
+	now := q.config.Clock.Now()
+	q.mu.Lock()
+	defer q.mu.Unlock()
+
+	quarantined := make(map[string]time.Time)
+	for ip, state := range q.states {
+		if now.Before(state.quarantinedUntil) {
+			quarantined[ip] = state.quarantinedUntil
+		}
+	}
+	return quarantined
+}
+
+// Release returns a quarantined ip to rotation and reports whether it was quarantined.
+func (q *Quarantine) Release(ip string) bool {
+	now := q.config.Clock.Now()
+	q.mu.Lock()
+	defer q.mu.Unlock()
+
+	state, ok := q.states[ip]
+	if !ok || !now.Before(state.quarantinedUntil) {
+		return false
+	}
+	delete(q.states, ip)
+	return true
+}
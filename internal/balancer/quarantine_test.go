@@ -0,0 +1,74 @@
+package balancer
+
+import (
+	"net/http"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func newTestQuarantine(c *clock.Fake) *Quarantine {
+	return NewQuarantine(QuarantineConfig{
+		BlockRate:    0.5,
+		MinResponses: 4,
+		Window:       10 * time.Second,
+		Duration:     time.Minute,
+		Clock:        c,
+	})
+}
+
+func TestQuarantine_AboveBlockRate(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	q := newTestQuarantine(c)
+	ip := "192.168.1.1"
+
+	q.Record(ip, http.StatusTooManyRequests)
+	q.Record(ip, http.StatusForbidden)
+	q.Record(ip, http.StatusOK)
+	if !q.IsHealthy(ip) {
+		t.Fatal("IP should not be quarantined before MinResponses")
+	}
+
+	q.Record(ip, http.StatusTooManyRequests)
+	if q.IsHealthy(ip) {
+		t.Fatal("IP should be quarantined at 75% blocked responses")
+	}
+	if got := q.QuarantinedUntil(); len(got) != 1 || !got[ip].Equal(time.Unix(60, 0)) {
+		t.Errorf("QuarantinedUntil = %v, want %s until 1m", got, ip)
+	}
+
+	c.Advance(time.Minute)
+	if !q.IsHealthy(ip) {
+		t.Error("IP should return after the quarantine")
+	}
+}
+
+func TestQuarantine_IgnoresOtherErrors(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	q := newTestQuarantine(c)
+	ip := "192.168.1.1"
+
+	for i := 0; i < 10; i++ {
+		q.Record(ip, http.StatusInternalServerError)
+	}
+	if !q.IsHealthy(ip) {
+		t.Error("5xx responses should not quarantine an IP")
+	}
+}
+
+func TestQuarantine_Release(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	q := newTestQuarantine(c)
+	ip := "192.168.1.1"
+
+	for i := 0; i < 4; i++ {
+		q.Record(ip, http.StatusForbidden)
+	}
+	if !q.Release(ip) || !q.IsHealthy(ip) {
+		t.Fatal("Release should return the IP to rotation")
+	}
+	if q.Release(ip) {
+		t.Error("Release of an IP not in quarantine should report false")
+	}
+}
@@ -0,0 +1,78 @@
+// Package balancer provides IP load balancing algorithms.
+package balancer
+
+import (
+	"slices"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+// slowStartMinWeight is the share of selections a recovered IP starts with.
+const slowStartMinWeight = 0.1
+
+// slowStartSet ramps the share of selections of IPs that return to health
+// from slowStartMinWeight up to 1 over a window, so a recovering link isn't
+// handed a full share of new connections the moment it passes a check.
+type slowStartSet struct {
+	window time.Duration
+	down   map[string]bool
+	since  map[string]time.Time
+	clock  clock.Clock
+	mu     sync.Mutex
+}
+
+// newSlowStartSet creates a slowStartSet that ramps over window (0 = disabled).
+func newSlowStartSet(window time.Duration, c clock.Clock) *slowStartSet {
+	return &slowStartSet{
+		window: window,
+		down:   make(map[string]bool),
+		since:  make(map[string]time.Time),
+		clock:  clock.OrReal(c),
+	}
+}
+
+// observe records which of ips the health checker reported healthy. IPs seen
+// unhealthy and now healthy again start their ramp.
+func (s *slowStartSet) observe(ips, healthy []string) {
+	if s.window <= 0 {
+		return
+	}
+	s.mu.Lock()
+	defer s.mu.Unlock()
+
+	for _, ip := range ips {
+		if !slices.Contains(healthy, ip) {
+			s.down[ip] = true
+			delete(s.since, ip)
+			continue
+		}
+		if s.down[ip] {
+			delete(s.down, ip)
+			s.since[ip] = s.clock.Now()
+			log.Info("slow_start_begun", "ip", ip, "window", s.window)
+		}
+	}
+}
+
+// weight returns the share of selections ip receives, from slowStartMinWeight
+// right after recovery to 1 once the window has passed.
+func (s *slowStartSet) weight(ip string) float64 {
+	if s.window <= 0 {
+		return 1
+	}
+	s.mu.Lock()
+	defer s.mu.Unlock()
+
+	since, ok := s.since[ip]
+	if !ok {
+		return 1
+	}
+	elapsed := s.clock.Now().Sub(since)
+	if elapsed >= s.window {
+		delete(s.since, ip)
+		return 1
+	}
+	return slowStartMinWeight + (1-slowStartMinWeight)*float64(elapsed)/float64(s.window)
+}
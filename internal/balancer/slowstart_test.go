@@ -0,0 +1,72 @@
+package balancer
+
+import (
+	"math"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func TestSlowStartSet_Weight(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	s := newSlowStartSet(10*time.Minute, c)
+	ips := []string{"192.168.1.1", "192.168.1.2"}
+
+	s.observe(ips, ips)
+	if w := s.weight("192.168.1.1"); w != 1 {
+		t.Errorf("weight of an IP that never failed = %v, want 1", w)
+	}
+
+	s.observe(ips, ips[1:])
+	s.observe(ips, ips)
+	if w := s.weight("192.168.1.1"); w != slowStartMinWeight {
+		t.Errorf("weight right after recovery = %v, want %v", w, slowStartMinWeight)
+	}
+
+	c.Advance(5 * time.Minute)
+	if w := s.weight("192.168.1.1"); math.Abs(w-0.55) > 1e-9 {
+		t.Errorf("weight halfway = %v, want 0.55", w)
+	}
+
+	c.Advance(5 * time.Minute)
+	if w := s.weight("192.168.1.1"); w != 1 {
+		t.Errorf("weight after the window = %v, want 1", w)
+	}
+}
+
+func TestLRU_SlowStartAfterRecovery(t *testing.T) {
+	c := clock.NewFake(time.Unix(0, 0))
+	health := staticHealth{"192.168.1.1": true}
+	lru := NewLRU(Config{
+		IPs:             []string{"192.168.1.1", "192.168.1.2"},
+		HistoryWindow:   3600,
+		HistorySize:     1000,
+		Limiter:         &mockLimiter{},
+		HealthChecker:   health,
+		SlowStartWindow: 10 * time.Minute,
+		Clock:           c,
+	})
+
+	if ip, _ := lru.Select("example.com"); ip != "192.168.1.2" {
+		t.Fatalf("expected the unhealthy IP to be skipped, got %s", ip)
+	}
+
+	// Recovered and a tenth of the way into its ramp (weight 0.19)
+	delete(health, "192.168.1.1")
+	lru.Select("warmup.example.com")
+	c.Advance(time.Minute)
+
+	usage := make(map[string]int)
+	for i := 0; i < 40; i++ {
+		ip, err := lru.Select("example.com")
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		usage[ip]++
+		lru.Record("example.com", ip)
+	}
+	if got := usage["192.168.1.1"]; got < 4 || got > 9 {
+		t.Errorf("recovering IP selected %d of 40 times, want about 6", got)
+	}
+}
@@ -0,0 +1,33 @@
+package clock
+
+import (
+	"testing"
+	"time"
+)
+
+func TestFake(t *testing.T) {
+	start := time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC)
+	c := NewFake(start)
+
+	if !c.Now().Equal(start) {
+		t.Errorf("Now() = %v, want %v", c.Now(), start)
+	}
+	c.Advance(90 * time.Second)
+	if got := c.Since(start); got != 90*time.Second {
+		t.Errorf("Since() = %v, want 90s", got)
+	}
+	c.Set(start.Add(-time.Minute))
+	if got := c.Since(start); got != -time.Minute {
+		t.Errorf("Since() after Set = %v, want -1m", got)
+	}
+}
+
+func TestOrReal(t *testing.T) {
+	if OrReal(nil) != Real {
+		t.Error("expected nil to fall back to the real clock")
+	}
+	fake := NewFake(time.Time{})
+	if OrReal(fake) != Clock(fake) {
+		t.Error("expected a non-nil clock to be returned unchanged")
+	}
+}
@@ -0,0 +1,99 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"time"
+)
+
+// Session affinity scopes that session-affinity-scope can name.
+const (
+	// AffinityScopeHost keys affinity on the exact destination host.
+	AffinityScopeHost = "host"
+	// AffinityScopeDomain keys affinity on the registrable domain (eTLD+1), so
+	// www.example.com and api.example.com share an outbound IP.
+	AffinityScopeDomain = "domain"
+)
+
+// Rotation policies a user_rotation entry can name.
+const (
+	// RotationPerRequest picks a fresh outbound IP for every connection, even
+	// with session affinity enabled.
+	RotationPerRequest = "per-request"
+	// RotationSticky keeps the user on one outbound IP per destination while it
+	// returns within the entry's TTL.
+	RotationSticky = "sticky"
+	// RotationStickyUntilError is RotationSticky, but the user also moves to a
+	// fresh outbound IP as soon as a connection through its IP fails.
+	RotationStickyUntilError = "sticky-until-error"
+)
+
+// UserRotation overrides the rotation policy and affinity period of one user.
+type UserRotation struct {
+	// User is the proxy username (without routing suffixes) or, for unauthenticated clients, the client IP.
+	User string `yaml:"user"`
+	// Policy is one of the Rotation* values.
+	Policy string `yaml:"policy"`
+	// TTL is how long the user keeps an outbound IP after its last use, for
+	// sticky policies and username sessions (default: session-affinity-ttl,
+	// or username-session-ttl for sessions).
+	TTL time.Duration `yaml:"ttl"`
+}
+
+// validateSessionAffinity checks the session, destination affinity and
+// username session periods and the affinity scope. An empty scope means AffinityScopeHost.
+func (c *Config) validateSessionAffinity() error {
+	if c.SessionAffinityTTL < 0 {
+		return fmt.Errorf("session-affinity-ttl cannot be negative")
+	}
+	if c.DestinationAffinityTTL < 0 {
+		return fmt.Errorf("destination-affinity-ttl cannot be negative")
+	}
+	if c.UsernameSessionTTL < 0 {
+		return fmt.Errorf("username-session-ttl cannot be negative")
+	}
+	switch c.SessionAffinityScope {
+	case "", AffinityScopeHost, AffinityScopeDomain:
+	default:
+		return fmt.Errorf("session-affinity-scope must be %q or %q", AffinityScopeHost, AffinityScopeDomain)
+	}
+	return c.validateUserRotation()
+}
+
+// validateUserRotation checks the per-user rotation policies. Sticky policies
+// need a TTL, from the entry or session-affinity-ttl.
+func (c *Config) validateUserRotation() error {
+	seen := make(map[string]bool, len(c.UserRotation))
+	for _, r := range c.UserRotation {
+		if r.User == "" {
+			return fmt.Errorf("user rotation: user is required")
+		}
+		if seen[r.User] {
+			return fmt.Errorf("duplicate user rotation: %s", r.User)
+		}
+		seen[r.User] = true
+		if r.TTL < 0 {
+			return fmt.Errorf("user rotation %s: ttl cannot be negative", r.User)
+		}
+		switch r.Policy {
+		case RotationPerRequest:
+		case RotationSticky, RotationStickyUntilError:
+			if r.TTL == 0 && c.SessionAffinityTTL == 0 {
+				return fmt.Errorf("user rotation %s: %s needs a ttl when session-affinity-ttl is 0", r.User, r.Policy)
+			}
+		default:
+			return fmt.Errorf("user rotation %s: policy must be %q, %q or %q", r.User, RotationPerRequest, RotationSticky, RotationStickyUntilError)
+		}
+	}
+	return nil
+}
+
+// RotationForUser returns the rotation policy configured for user, if any.
+func (c *Config) RotationForUser(user string) (UserRotation, bool) {
+	for _, r := range c.UserRotation {
+		if r.User == user {
+			return r, true
+		}
+	}
+	return UserRotation{}, false
+}
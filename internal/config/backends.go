@@ -0,0 +1,165 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"math"
+	"net"
+	"strings"
+
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// maxDeviceName is the longest Linux interface name (IFNAMSIZ without the NUL).
+const maxDeviceName = 15
+
+// BackendConfig holds per-backend settings keyed by outbound IP.
+type BackendConfig struct {
+	// IP is the outbound IP these settings apply to. Must be listed in IPs.
+	IP string `yaml:"ip"`
+	// Country is an optional country/region tag (e.g. "de", "us-east") used for geo routing.
+	Country string `yaml:"country"`
+	// MaxMbps caps the bandwidth of this backend in megabits per second per direction (0 = unlimited).
+	MaxMbps float64 `yaml:"max_mbps"`
+	// HealthCheckTarget overrides the global health check target for this backend.
+	HealthCheckTarget string `yaml:"health_check_target"`
+	// Fwmark marks this backend's sockets (SO_MARK) so policy routing picks its table (Linux only).
+	Fwmark int `yaml:"fwmark"`
+	// Device binds this backend's sockets to an interface or VRF device (Linux only).
+	Device string `yaml:"device"`
+	// DSCP marks this backend's packets with a DSCP value (0-63) or class name such as "ef" or "af41" (Linux only).
+	DSCP string `yaml:"dscp"`
+	// SourceIPs are equivalent addresses on the same uplink that connections
+	// fail over to when the destination refuses IP. They are not pool members.
+	SourceIPs []string `yaml:"source_ips"`
+}
+
+// validateBackends checks that per-backend settings refer to configured IPs.
+func (c *Config) validateBackends() error {
+	seen := make(map[string]bool, len(c.Backends))
+	for _, b := range c.Backends {
+		if net.ParseIP(b.IP) == nil {
+			return fmt.Errorf("invalid backend IP address: %s", b.IP)
+		}
+		if !containsString(c.IPs, b.IP) {
+			return fmt.Errorf("backend %s is not listed in ips", b.IP)
+		}
+		if b.MaxMbps < 0 {
+			return fmt.Errorf("backend %s: max_mbps must not be negative", b.IP)
+		}
+		if b.HealthCheckTarget != "" {
+			if err := validateHealthCheckTarget(c.HealthCheckType, b.HealthCheckTarget); err != nil {
+				return fmt.Errorf("backend %s: %w", b.IP, err)
+			}
+		}
+		if b.Fwmark < 0 || int64(b.Fwmark) > math.MaxUint32 {
+			return fmt.Errorf("backend %s: fwmark must be between 0 and %d", b.IP, uint32(math.MaxUint32))
+		}
+		if len(b.Device) > maxDeviceName {
+			return fmt.Errorf("backend %s: device name %q is longer than %d characters", b.IP, b.Device, maxDeviceName)
+		}
+		if _, err := netutil.ParseDSCP(b.DSCP); err != nil {
+			return fmt.Errorf("backend %s: %w", b.IP, err)
+		}
+		if err := c.validateSourceIPs(b); err != nil {
+			return err
+		}
+		if seen[b.IP] {
+			return fmt.Errorf("duplicate backend entry: %s", b.IP)
+		}
+		seen[b.IP] = true
+	}
+	return nil
+}
+
+// validateSourceIPs checks a backend's alternate source addresses.
+func (c *Config) validateSourceIPs(b BackendConfig) error {
+	ipv4 := net.ParseIP(b.IP).To4() != nil
+	seen := make(map[string]bool, len(b.SourceIPs))
+	for _, src := range b.SourceIPs {
+		ip := net.ParseIP(src)
+		if ip == nil {
+			return fmt.Errorf("backend %s: invalid source IP address: %s", b.IP, src)
+		}
+		if (ip.To4() != nil) != ipv4 {
+			return fmt.Errorf("backend %s: source IP %s is not in the same address family", b.IP, src)
+		}
+		if containsString(c.IPs, src) {
+			return fmt.Errorf("backend %s: source IP %s is listed in ips", b.IP, src)
+		}
+		if seen[src] {
+			return fmt.Errorf("backend %s: duplicate source IP %s", b.IP, src)
+		}
+		seen[src] = true
+	}
+	return nil
+}
+
+// BackendFor returns the per-backend settings for the given IP.
+// Returns a zero BackendConfig with only IP set if none are configured.
+func (c *Config) BackendFor(ip string) BackendConfig {
+	for _, b := range c.Backends {
+		if b.IP == ip {
+			return b
+		}
+	}
+	return BackendConfig{IP: ip}
+}
+
+// IPsForCountry returns the configured IPs tagged with the given country.
+// Matching is case-insensitive.
+func (c *Config) IPsForCountry(country string) []string {
+	var ips []string
+	for _, b := range c.Backends {
+		if b.Country != "" && strings.EqualFold(b.Country, country) {
+			ips = append(ips, b.IP)
+		}
+	}
+	return ips
+}
+
+// BandwidthCaps returns the configured bandwidth caps in Mbps keyed by IP.
+// Only backends with a positive cap are included.
+func (c *Config) BandwidthCaps() map[string]float64 {
+	caps := make(map[string]float64)
+	for _, b := range c.Backends {
+		if b.MaxMbps > 0 {
+			caps[b.IP] = b.MaxMbps
+		}
+	}
+	return caps
+}
+
+// Routes returns the socket options of the backends that set a fwmark, device or DSCP, keyed by IP.
+func (c *Config) Routes() map[string]netutil.Route {
+	routes := make(map[string]netutil.Route)
+	for _, b := range c.Backends {
+		dscp, _ := netutil.ParseDSCP(b.DSCP) // validated with the config
+		if route := (netutil.Route{Mark: b.Fwmark, Device: b.Device, DSCP: dscp}); route != (netutil.Route{}) {
+			routes[b.IP] = route
+		}
+	}
+	return routes
+}
+
+// SourceIPs returns the source addresses of the backends with alternates,
+// keyed by IP: the IP itself first, then its source_ips.
+func (c *Config) SourceIPs() map[string][]string {
+	sources := make(map[string][]string)
+	for _, b := range c.Backends {
+		if len(b.SourceIPs) > 0 {
+			sources[b.IP] = append([]string{b.IP}, b.SourceIPs...)
+		}
+	}
+	return sources
+}
+
+// containsString reports whether s contains v.
+func containsString(s []string, v string) bool {
+	for _, item := range s {
+		if item == v {
+			return true
+		}
+	}
+	return false
+}
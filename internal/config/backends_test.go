@@ -0,0 +1,195 @@
+package config
+
+import (
+	"os"
+	"path/filepath"
+	"testing"
+)
+
+func TestValidateBackends(t *testing.T) {
+	tests := []struct {
+		name     string
+		backends []BackendConfig
+		wantErr  bool
+	}{
+		{
+			name:     "no backends",
+			backends: nil,
+			wantErr:  false,
+		},
+		{
+			name:     "valid backend",
+			backends: []BackendConfig{{IP: "192.168.1.1", Country: "de"}},
+			wantErr:  false,
+		},
+		{
+			name:     "invalid IP",
+			backends: []BackendConfig{{IP: "invalid"}},
+			wantErr:  true,
+		},
+		{
+			name:     "IP not in ips",
+			backends: []BackendConfig{{IP: "10.0.0.1"}},
+			wantErr:  true,
+		},
+		{
+			name:     "backend health check target",
+			backends: []BackendConfig{{IP: "192.168.1.1", HealthCheckTarget: "10.0.0.1:443"}},
+			wantErr:  false,
+		},
+		{
+			name:     "backend health check target without port",
+			backends: []BackendConfig{{IP: "192.168.1.1", HealthCheckTarget: "10.0.0.1"}},
+			wantErr:  true,
+		},
+		{
+			name:     "backend routing",
+			backends: []BackendConfig{{IP: "192.168.1.1", Fwmark: 100, Device: "vrf-wan1"}},
+			wantErr:  false,
+		},
+		{
+			name:     "negative fwmark",
+			backends: []BackendConfig{{IP: "192.168.1.1", Fwmark: -1}},
+			wantErr:  true,
+		},
+		{
+			name:     "device name too long",
+			backends: []BackendConfig{{IP: "192.168.1.1", Device: "vrf-uplink-primary"}},
+			wantErr:  true,
+		},
+		{
+			name:     "backend dscp class",
+			backends: []BackendConfig{{IP: "192.168.1.1", DSCP: "af41"}},
+			wantErr:  false,
+		},
+		{
+			name:     "invalid dscp",
+			backends: []BackendConfig{{IP: "192.168.1.1", DSCP: "64"}},
+			wantErr:  true,
+		},
+		{
+			name:     "backend source ips",
+			backends: []BackendConfig{{IP: "192.168.1.1", SourceIPs: []string{"192.168.1.11", "192.168.1.12"}}},
+			wantErr:  false,
+		},
+		{
+			name:     "source ip of another family",
+			backends: []BackendConfig{{IP: "192.168.1.1", SourceIPs: []string{"2001:db8::1"}}},
+			wantErr:  true,
+		},
+		{
+			name:     "source ip in ips",
+			backends: []BackendConfig{{IP: "192.168.1.1", SourceIPs: []string{"192.168.1.2"}}},
+			wantErr:  true,
+		},
+		{
+			name:     "duplicate source ip",
+			backends: []BackendConfig{{IP: "192.168.1.1", SourceIPs: []string{"192.168.1.11", "192.168.1.11"}}},
+			wantErr:  true,
+		},
+		{
+			name:     "duplicate backend",
+			backends: []BackendConfig{{IP: "192.168.1.1"}, {IP: "192.168.1.1"}},
+			wantErr:  true,
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			cfg := DefaultConfig()
+			cfg.IPs = []string{"192.168.1.1", "192.168.1.2"}
+			cfg.Backends = tt.backends
+			err := cfg.Validate()
+			if (err != nil) != tt.wantErr {
+				t.Errorf("Validate() error = %v, wantErr %v", err, tt.wantErr)
+			}
+		})
+	}
+}
+
+func TestIPsForCountry(t *testing.T) {
+	cfg := DefaultConfig()
+	cfg.IPs = []string{"10.0.0.1", "10.0.0.2", "10.0.0.3"}
+	cfg.Backends = []BackendConfig{
+		{IP: "10.0.0.1", Country: "de"},
+		{IP: "10.0.0.2", Country: "US"},
+		{IP: "10.0.0.3", Country: "de"},
+	}
+
+	if got := cfg.IPsForCountry("DE"); len(got) != 2 {
+		t.Errorf("expected 2 IPs for de, got %v", got)
+	}
+	if got := cfg.IPsForCountry("us"); len(got) != 1 || got[0] != "10.0.0.2" {
+		t.Errorf("expected [10.0.0.2] for us, got %v", got)
+	}
+	if got := cfg.IPsForCountry("fr"); len(got) != 0 {
+		t.Errorf("expected no IPs for fr, got %v", got)
+	}
+}
+
+func TestBackendFor(t *testing.T) {
+	cfg := DefaultConfig()
+	cfg.Backends = []BackendConfig{{IP: "10.0.0.1", Country: "de"}}
+
+	if b := cfg.BackendFor("10.0.0.1"); b.Country != "de" {
+		t.Errorf("expected country de, got %q", b.Country)
+	}
+	if b := cfg.BackendFor("10.0.0.2"); b.IP != "10.0.0.2" || b.Country != "" {
+		t.Errorf("expected empty settings for unknown backend, got %+v", b)
+	}
+}
+
+func TestLoadFromFile_Backends(t *testing.T) {
+	tmpDir := t.TempDir()
+	configPath := filepath.Join(tmpDir, "backends.yml")
+
+	configContent := `
+ips:
+  - 10.0.0.1
+  - 10.0.0.2
+backends:
+  - ip: 10.0.0.1
+    country: de
+  - ip: 10.0.0.2
+    country: us
+geo_header: X-Geo
+`
+	if err := os.WriteFile(configPath, []byte(configContent), 0644); err != nil {
+		t.Fatalf("failed to write config file: %v", err)
+	}
+
+	cfg, err := LoadFromFile(configPath)
+	if err != nil {
+		t.Fatalf("LoadFromFile() error: %v", err)
+	}
+
+	if len(cfg.Backends) != 2 {
+		t.Fatalf("expected 2 backends, got %d", len(cfg.Backends))
+	}
+	if cfg.Backends[1].Country != "us" {
+		t.Errorf("expected country us, got %s", cfg.Backends[1].Country)
+	}
+	if cfg.GeoHeader != "X-Geo" {
+		t.Errorf("expected geo header X-Geo, got %s", cfg.GeoHeader)
+	}
+}
+
+func TestRoutes(t *testing.T) {
+	cfg := DefaultConfig()
+	cfg.Backends = []BackendConfig{
+		{IP: "10.0.0.1", Fwmark: 101},
+		{IP: "10.0.0.2", DSCP: "ef"},
+		{IP: "10.0.0.3", Country: "de"},
+	}
+
+	routes := cfg.Routes()
+	if len(routes) != 2 {
+		t.Fatalf("expected routes for 2 backends, got %v", routes)
+	}
+	if routes["10.0.0.1"].Mark != 101 {
+		t.Errorf("expected fwmark 101, got %+v", routes["10.0.0.1"])
+	}
+	if routes["10.0.0.2"].DSCP != 46 {
+		t.Errorf("expected dscp 46 for ef, got %+v", routes["10.0.0.2"])
+	}
+}
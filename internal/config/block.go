@@ -0,0 +1,46 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"net/http"
+	"strings"
+	"text/template"
+)
+
+// BlockTemplate parses BlockBody; nil when no custom body is set.
+func (c *Config) BlockTemplate() *template.Template {
+	if c.BlockBody == "" {
+		return nil
+	}
+	tmpl, _ := template.New("block_body").Parse(c.BlockBody) // validated with the config
+	return tmpl
+}
+
+// BlockResponseHeaders returns BlockHeaders as a header map.
+func (c *Config) BlockResponseHeaders() http.Header {
+	h := make(http.Header, len(c.BlockHeaders))
+	for _, entry := range c.BlockHeaders {
+		name, value, _ := strings.Cut(entry, ":")
+		h.Add(strings.TrimSpace(name), strings.TrimSpace(value))
+	}
+	return h
+}
+
+// validateBlockResponse checks the response sent for requests refused by policy.
+func (c *Config) validateBlockResponse() error {
+	if c.BlockStatus < 400 || c.BlockStatus > 599 {
+		return fmt.Errorf("block-status must be between 400 and 599, got %d", c.BlockStatus)
+	}
+	if _, err := template.New("block_body").Parse(c.BlockBody); err != nil {
+		return fmt.Errorf("block-body: %w", err)
+	}
+	for _, entry := range c.BlockHeaders {
+		name, _, ok := strings.Cut(entry, ":")
+		name = strings.TrimSpace(name)
+		if !ok || name == "" || strings.ContainsAny(name, " \t") {
+			return fmt.Errorf("block-headers: invalid header %q, want \"Name: value\"", entry)
+		}
+	}
+	return nil
+}
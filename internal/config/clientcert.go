@@ -0,0 +1,33 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import "fmt"
+
+// Client certificate fields that tls-client-identity can name.
+const (
+	// ClientIdentityCN is the subject common name.
+	ClientIdentityCN = "cn"
+	// ClientIdentityDNS is the first DNS subject alternative name.
+	ClientIdentityDNS = "dns"
+	// ClientIdentityEmail is the first email subject alternative name.
+	ClientIdentityEmail = "email"
+	// ClientIdentityURI is the first URI subject alternative name, such as a SPIFFE ID.
+	ClientIdentityURI = "uri"
+)
+
+// validateClientCerts checks the client certificate settings. An empty
+// identity field means ClientIdentityCN.
+func (c *Config) validateClientCerts() error {
+	if c.TLSClientCAFile != "" && c.TLSCertFile == "" {
+		return fmt.Errorf("tls-client-ca-file requires tls-cert-file and tls-key-file")
+	}
+	if c.TLSClientCertOptional && c.TLSClientCAFile == "" {
+		return fmt.Errorf("tls-client-cert-optional requires tls-client-ca-file")
+	}
+	switch c.TLSClientIdentity {
+	case "", ClientIdentityCN, ClientIdentityDNS, ClientIdentityEmail, ClientIdentityURI:
+		return nil
+	}
+	return fmt.Errorf("tls-client-identity must be one of %q, %q, %q or %q",
+		ClientIdentityCN, ClientIdentityDNS, ClientIdentityEmail, ClientIdentityURI)
+}
@@ -4,6 +4,7 @@ package config
 import (
 	"fmt"
 	"net"
+	"net/url"
 	"os"
 	"strconv"
 	"strings"
@@ -11,6 +12,8 @@ import (
 
 	"github.com/spf13/pflag"
 	"gopkg.in/yaml.v3"
+
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
 )
 
 // Config holds all configuration for the proxy.
@@ -21,26 +24,170 @@ type Config struct {
 	Port int `yaml:"port"`
 	// MetricsPort is the metrics server port.
 	MetricsPort int `yaml:"metrics_port"`
+	// AdminToken is the bearer token required by the /admin endpoints
+	// (empty = only loopback clients may use them).
+	AdminToken string `yaml:"admin_token"`
+	// SOCKS5Port is the optional SOCKS5 listener port (0 = disabled).
+	SOCKS5Port int `yaml:"socks5_port"`
+	// TransparentPort is the optional listener for iptables-intercepted connections (0 = disabled).
+	TransparentPort int `yaml:"transparent_port"`
+	// ForwardPort is the optional listener that forwards every connection to ForwardTarget (0 = disabled).
+	ForwardPort int `yaml:"forward_port"`
+	// ForwardTarget is the fixed "host:port" destination of ForwardPort connections.
+	ForwardTarget string `yaml:"forward_target"`
+	// PublicStatusPort is the optional unauthenticated aggregate status page port (0 = disabled).
+	PublicStatusPort int `yaml:"public_status_port"`
+	// TLSCertFile is the certificate for serving the proxy over TLS (HTTPS proxy).
+	TLSCertFile string `yaml:"tls_cert_file"`
+	// TLSKeyFile is the private key for TLSCertFile.
+	TLSKeyFile string `yaml:"tls_key_file"`
+	// TLSClientCAFile holds the CA certificates that verify client certificates on
+	// the TLS listener. Clients are then identified by their certificate.
+	TLSClientCAFile string `yaml:"tls_client_ca_file"`
+	// TLSClientCertOptional admits clients without a certificate, which then
+	// authenticate with credentials as usual.
+	TLSClientCertOptional bool `yaml:"tls_client_cert_optional"`
+	// TLSClientIdentity is the certificate field naming the client: cn, dns, email or uri.
+	TLSClientIdentity string `yaml:"tls_client_identity"`
+	// ProxyProtocol requires a PROXY protocol v1/v2 header on proxy and SOCKS connections.
+	ProxyProtocol bool `yaml:"proxy_protocol"`
+	// ProxyProtocolTrusted limits which sources (IPs or CIDRs) must send the PROXY header;
+	// empty means all sources.
+	ProxyProtocolTrusted []string `yaml:"proxy_protocol_trusted"`
+	// ClientAllow limits which client IPs/CIDRs may connect to the listeners;
+	// empty allows every client not in ClientDeny.
+	ClientAllow []string `yaml:"client_allow"`
+	// ClientDeny lists client IPs/CIDRs that may not connect, even if in ClientAllow.
+	ClientDeny []string `yaml:"client_deny"`
 	// Auth is the optional basic auth in "user:pass" format.
 	Auth string `yaml:"auth"`
+	// AuthFile is an htpasswd-style users file with bcrypt or argon2 hashes,
+	// used instead of Auth.
+	AuthFile string `yaml:"auth_file"`
+	// AuthCacheTTL remembers successful auth-file and auth webhook credential checks
+	// for this long, so clients don't pay a slow verification per request (0 = disabled).
+	AuthCacheTTL time.Duration `yaml:"auth_cache_ttl"`
+	// AuthWebhook receives a JSON POST with each credential and client to check,
+	// used instead of Auth and AuthFile.
+	AuthWebhook string `yaml:"auth_webhook"`
+	// AuthWebhookTimeout bounds each auth webhook call.
+	AuthWebhookTimeout time.Duration `yaml:"auth_webhook_timeout"`
+	// AuthPAMService checks credentials against the host's accounts through
+	// this PAM service, used instead of Auth, AuthFile and AuthWebhook.
+	AuthPAMService string `yaml:"auth_pam_service"`
+	// JWTSecret verifies HS256/HS384/HS512 bearer tokens in Proxy-Authorization.
+	JWTSecret string `yaml:"jwt_secret"`
+	// JWTJWKSURL serves the RSA/EC keys that verify RS*/PS*/ES* bearer tokens.
+	JWTJWKSURL string `yaml:"jwt_jwks_url"`
+	// JWTJWKSRefresh is how often the JWKS is fetched again.
+	JWTJWKSRefresh time.Duration `yaml:"jwt_jwks_refresh"`
+	// JWTIssuer, if set, must match the "iss" claim of bearer tokens.
+	JWTIssuer string `yaml:"jwt_issuer"`
+	// JWTAudience, if set, must be one of the "aud" values of bearer tokens.
+	JWTAudience string `yaml:"jwt_audience"`
 	// Timeout is the connection timeout.
 	Timeout time.Duration `yaml:"timeout"`
 	// IdleTimeout is the idle connection timeout.
 	IdleTimeout time.Duration `yaml:"idle_timeout"`
+	// HeaderReadTimeout bounds reading a client's request headers or SOCKS/PROXY handshake (0 = Timeout).
+	HeaderReadTimeout time.Duration `yaml:"header_read_timeout"`
+	// DNSTimeout bounds resolving a target hostname (0 = Timeout).
+	DNSTimeout time.Duration `yaml:"dns_timeout"`
+	// ConnectTimeout bounds dialing a target from an outbound IP (0 = Timeout).
+	ConnectTimeout time.Duration `yaml:"connect_timeout"`
+	// TunnelIdleTimeout closes tunnels without traffic for this long (0 = IdleTimeout).
+	TunnelIdleTimeout time.Duration `yaml:"tunnel_idle_timeout"`
+	// TunnelMaxLifetime closes tunnels open for this long, busy or not (0 = unlimited).
+	TunnelMaxLifetime time.Duration `yaml:"tunnel_max_lifetime"`
+	// ShutdownGracePeriod is how long in-flight requests and tunnels keep relaying after SIGTERM (0 = close at once).
+	ShutdownGracePeriod time.Duration `yaml:"shutdown_grace_period"`
+	// ConnectRetries is how many alternate outbound IPs a failed CONNECT dial is retried on.
+	ConnectRetries int `yaml:"connect_retries"`
+	// ConnectRetryTimeout is the dial timeout of each CONNECT attempt (0 = ConnectTimeout).
+	ConnectRetryTimeout time.Duration `yaml:"connect_retry_timeout"`
+	// ConnectRetryBackoff is the wait before the first CONNECT retry, doubled for each further retry (0 = retry immediately).
+	ConnectRetryBackoff time.Duration `yaml:"connect_retry_backoff"`
+	// ConnectRetryMaxBackoff caps the wait between CONNECT retries.
+	ConnectRetryMaxBackoff time.Duration `yaml:"connect_retry_max_backoff"`
+	// ConnectRetryJitter is the fraction (0-1) of each backoff that is randomized.
+	ConnectRetryJitter float64 `yaml:"connect_retry_jitter"`
+	// ConnectRetryOn lists the dial error classes that are retried.
+	ConnectRetryOn []string `yaml:"connect_retry_on"`
+	// ConnectHedgeDelay starts a second CONNECT dial on another outbound IP if the first hasn't connected after this long (0 = disabled).
+	ConnectHedgeDelay time.Duration `yaml:"connect_hedge_delay"`
+	// ConnectRetryHeader is added to CONNECT responses that succeeded only after
+	// retries, with the retry count and the serving outbound IP (empty = disabled).
+	ConnectRetryHeader string `yaml:"connect_retry_header"`
 	// MaxConnsPerIP is the maximum concurrent connections per outbound IP.
 	MaxConnsPerIP int `yaml:"max_conns_per_ip"`
 	// MaxConnsTotal is the maximum total concurrent connections.
 	MaxConnsTotal int `yaml:"max_conns_total"`
+	// MaxHeaderBytes caps the size of a client's request headers (0 = 1 MiB).
+	MaxHeaderBytes int `yaml:"max_header_bytes"`
+	// MaxUploadBytes caps the body of a plain HTTP request (0 = unlimited).
+	MaxUploadBytes int `yaml:"max_upload_bytes"`
+	// FairShareThreshold is the fraction of MaxConnsTotal in use at which users are held to their fair share (0 = disabled).
+	FairShareThreshold float64 `yaml:"fair_share_threshold"`
+	// FairShareWeights sets per-user share weights (config file only).
+	FairShareWeights []FairShareWeight `yaml:"fair_share_weights"`
+	// UserLimits sets per-user request rates, connection caps and monthly traffic quotas (config file only).
+	UserLimits []UserLimit `yaml:"user_limits"`
 	// HistoryWindow is the time window for LRU history.
 	HistoryWindow time.Duration `yaml:"history_window"`
 	// HistorySize is the max entries per host in history.
 	HistorySize int `yaml:"history_size"`
+	// SlowStartWindow ramps IPs that return to health up to a full share of selections over this window (0 = disabled).
+	SlowStartWindow time.Duration `yaml:"slow_start_window"`
+	// SessionAffinityTTL keeps a client on the same outbound IP for a destination
+	// while it returns within this period (0 = disabled).
+	SessionAffinityTTL time.Duration `yaml:"session_affinity_ttl"`
+	// SessionAffinityScope is what affinity is keyed on: "host" or "domain" (eTLD+1).
+	SessionAffinityScope string `yaml:"session_affinity_scope"`
+	// DestinationAffinityTTL keeps every client on the same outbound IP for a
+	// destination while it is used within this period (0 = disabled).
+	DestinationAffinityTTL time.Duration `yaml:"destination_affinity_ttl"`
+	// UsernameSessionTTL keeps a client that names a session in its username
+	// ("alice-session-abc123") on one outbound IP for every destination while it
+	// returns within this period (0 = ignore session suffixes).
+	UsernameSessionTTL time.Duration `yaml:"username_session_ttl"`
+	// UserRotation sets per-user rotation policies and affinity periods (config file only).
+	UserRotation []UserRotation `yaml:"user_rotation"`
 	// HistoryMaxTotalEntries is the maximum total entries across all hosts.
 	HistoryMaxTotalEntries int `yaml:"history_max_total_entries"`
+	// DrainPeriod is how long /admin/drain takes to remove an IP from rotation by default (0 = immediately).
+	DrainPeriod time.Duration `yaml:"drain_period"`
+	// Drain lists outbound IPs kept out of rotation for maintenance; their existing tunnels finish.
+	Drain []string `yaml:"drain"`
+	// MaintenanceMessage is the 503 body sent while /admin/maintenance rejects new traffic.
+	MaintenanceMessage string `yaml:"maintenance_message"`
+	// MaintenanceRetryAfter is the Retry-After sent with maintenance 503s (0 = omitted).
+	MaintenanceRetryAfter time.Duration `yaml:"maintenance_retry_after"`
+	// MaintenanceCloseTunnels closes existing tunnels when SIGUSR1 starts maintenance.
+	MaintenanceCloseTunnels bool `yaml:"maintenance_close_tunnels"`
+	// BlockStatus is the HTTP status sent for requests refused by destination policy.
+	BlockStatus int `yaml:"block_status"`
+	// BlockBody is a text/template for the body of block responses, with
+	// .Rule, .Host, .TicketURL and .RequestID; empty keeps the built-in message.
+	BlockBody string `yaml:"block_body"`
+	// BlockTicketURL is passed to BlockBody as .TicketURL, e.g. an unblock request form.
+	BlockTicketURL string `yaml:"block_ticket_url"`
+	// BlockHeaders are "Name: value" headers added to block responses.
+	BlockHeaders []string `yaml:"block_headers"`
 	// LogLevel is the logging level (debug, info, warn, error).
 	LogLevel string `yaml:"log_level"`
 	// LogFormat is the log format (json, text).
 	LogFormat string `yaml:"log_format"`
+	// LogLevels are "module=level" overrides of LogLevel for single modules.
+	LogLevels []string `yaml:"log_levels"`
+	// AccessLog writes access records to a file, or stdout for "-", instead
+	// of as "request" records in the log.
+	AccessLog string `yaml:"access_log"`
+	// AccessLogFormat is the access log format (json, clf).
+	AccessLogFormat string `yaml:"access_log_format"`
+	// OTLPEndpoint is the OTLP/HTTP traces URL spans are exported to ("" = tracing disabled).
+	OTLPEndpoint string `yaml:"otlp_endpoint"`
+	// OTLPServiceName is the service.name reported with exported spans.
+	OTLPServiceName string `yaml:"otlp_service_name"`
 	// ConfigFile is the optional config file path.
 	ConfigFile string `yaml:"-"`
 
@@ -79,6 +226,161 @@ type Config struct {
 	HealthCheckFailureThreshold int `yaml:"health_check_failure_threshold"`
 	// HealthCheckSuccessThreshold is the number of successes before marking an IP healthy.
 	HealthCheckSuccessThreshold int `yaml:"health_check_success_threshold"`
+	// HealthCheckExpectedStatus is the exact status http checks require (0 = any 2xx/3xx).
+	HealthCheckExpectedStatus int `yaml:"health_check_expected_status"`
+	// HealthCheckExpectedBody is a substring the http check response body must contain.
+	HealthCheckExpectedBody string `yaml:"health_check_expected_body"`
+	// HealthStateFile persists unhealthy and ejected IPs across restarts (empty = not persisted).
+	HealthStateFile string `yaml:"health_state_file"`
+	// KeepWarmInterval probes outbound IPs idle for this long so dormant uplinks stay awake (0 = disabled).
+	KeepWarmInterval time.Duration `yaml:"keep_warm_interval"`
+	// KeepWarmTarget is the host:port the keep-warm probes connect to.
+	KeepWarmTarget string `yaml:"keep_warm_target"`
+	// OutlierErrorRate is the fraction of failed connections that ejects an IP (0 = disabled).
+	OutlierErrorRate float64 `yaml:"outlier_error_rate"`
+	// OutlierMinRequests is the number of connections in a window before an IP can be ejected.
+	OutlierMinRequests int `yaml:"outlier_min_requests"`
+	// OutlierWindow is the interval over which the outlier error rate is measured.
+	OutlierWindow time.Duration `yaml:"outlier_window"`
+	// OutlierEjectionTime is how long an ejected IP is kept out of rotation.
+	OutlierEjectionTime time.Duration `yaml:"outlier_ejection_time"`
+	// QuarantineBlockRate is the fraction of plain-HTTP responses that are 429/403 which quarantines an IP (0 = disabled).
+	QuarantineBlockRate float64 `yaml:"quarantine_block_rate"`
+	// QuarantineMinResponses is the number of responses in a window before an IP can be quarantined.
+	QuarantineMinResponses int `yaml:"quarantine_min_responses"`
+	// QuarantineWindow is the interval over which the quarantine block rate is measured.
+	QuarantineWindow time.Duration `yaml:"quarantine_window"`
+	// QuarantineTime is how long a quarantined IP is kept out of rotation.
+	QuarantineTime time.Duration `yaml:"quarantine_time"`
+	// RTTSampleInterval samples TCP RTT and retransmissions of active tunnels this often (0 = disabled, Linux only).
+	RTTSampleInterval time.Duration `yaml:"rtt_sample_interval"`
+	// RTTDegradeFactor flags an IP whose sampled RTT exceeds its baseline by this factor.
+	RTTDegradeFactor float64 `yaml:"rtt_degrade_factor"`
+	// RTTRetransmitThreshold flags an IP whose tunnels retransmit more than this fraction of segments.
+	RTTRetransmitThreshold float64 `yaml:"rtt_retransmit_threshold"`
+	// AnomalyInterval compares each IP's error rate, request rate and throughput with its own baseline this often (0 = disabled).
+	AnomalyInterval time.Duration `yaml:"anomaly_interval"`
+	// AnomalyThreshold raises an anomaly when a sample is this many standard deviations from the IP's baseline.
+	AnomalyThreshold float64 `yaml:"anomaly_threshold"`
+	// AnomalyWebhook receives a JSON POST for every anomaly raised ("" = log and count only).
+	AnomalyWebhook string `yaml:"anomaly_webhook"`
+	// ExitIPCheckURL is a "what is my IP" endpoint fetched through every outbound IP ("" = disabled).
+	ExitIPCheckURL string `yaml:"exit_ip_check_url"`
+	// ExitIPCheckInterval is how often exit IPs are verified.
+	ExitIPCheckInterval time.Duration `yaml:"exit_ip_check_interval"`
+	// ExitIPCheckEject removes IPs with a shared or changed exit from rotation.
+	ExitIPCheckEject bool `yaml:"exit_ip_check_eject"`
+
+	// Backend configuration
+	// Backends holds optional per-IP settings such as geo tags (config file only).
+	Backends []BackendConfig `yaml:"backends"`
+	// Pools defines named sets of outbound IPs (config file only).
+	Pools []Pool `yaml:"pools"`
+	// PoolsFile is where pools created through the admin API are persisted (empty = not persisted).
+	PoolsFile string `yaml:"pools_file"`
+	// DestinationPools routes destination networks through subsets of the outbound IPs (config file only).
+	DestinationPools []DestinationPool `yaml:"destination_pools"`
+	// UserPools restricts authenticated users to subsets of the outbound IPs (config file only).
+	UserPools []UserPool `yaml:"user_pools"`
+	// Rules are ordered routing rules choosing pools, rotation and bandwidth,
+	// or refusing connections (config file only).
+	Rules []RoutingRule `yaml:"rules"`
+	// GeoHeader is the request header clients can use to request a country.
+	GeoHeader string `yaml:"geo_header"`
+	// ExcludeHeader is the request header clients can use to list IPs to avoid.
+	ExcludeHeader string `yaml:"exclude_header"`
+	// PoolHeader is the request header trusted clients can use to choose a named pool (empty = disabled).
+	PoolHeader string `yaml:"pool_header"`
+	// PoolHeaderTrusted lists the clients (IPs or CIDRs) that may use PoolHeader;
+	// empty trusts no client.
+	PoolHeaderTrusted []string `yaml:"pool_header_trusted"`
+
+	// Protocol sniffing
+	// SniffProtocols enables protocol detection on CONNECT and SOCKS5 tunnels.
+	SniffProtocols bool `yaml:"sniff_protocols"`
+	// SniffTimeout is how long to wait for the client's first bytes.
+	SniffTimeout time.Duration `yaml:"sniff_timeout"`
+
+	// FTP
+	// FTPPassive watches FTP control tunnels for PASV/EPSV replies so the data
+	// connection leaves through the same outbound IP as the control connection.
+	FTPPassive bool `yaml:"ftp_passive"`
+
+	// CONNECT header policy. Names are case-insensitive; a trailing "*" matches a prefix.
+	// ConnectLogHeaders lists CONNECT request headers recorded in the access log.
+	ConnectLogHeaders []string `yaml:"connect_log_headers"`
+	// ConnectRequireHeaders lists headers every CONNECT request must carry.
+	ConnectRequireHeaders []string `yaml:"connect_require_headers"`
+	// ConnectRejectHeaders lists headers that cause a CONNECT request to be refused.
+	ConnectRejectHeaders []string `yaml:"connect_reject_headers"`
+	// ConnectPorts lists the destination ports ("443") and ranges ("8000-8100")
+	// CONNECT and SOCKS CONNECT may target; empty allows every port.
+	ConnectPorts []string `yaml:"connect_ports"`
+	// AllowedMethods lists the HTTP methods plain HTTP requests may use; empty
+	// allows every method. CONNECT is governed by ConnectPorts instead.
+	AllowedMethods []string `yaml:"allowed_methods"`
+
+	// Destination allowlist
+	// AllowedDestinations restricts the hosts clients may reach ("*.example.com"
+	// matches subdomains, "/regexp/" matches hosts the expression matches);
+	// empty allows every destination.
+	AllowedDestinations []string `yaml:"allowed_destinations"`
+	// DeniedDestinations lists hosts clients may never reach, in the same
+	// patterns; a denied host is refused even when it is allowed.
+	DeniedDestinations []string `yaml:"denied_destinations"`
+	// PolicyFile is a YAML file of destination lists and routing rules that is
+	// watched and reloaded on change (empty = none).
+	PolicyFile string `yaml:"policy_file"`
+	// BlockPrivateDestinations refuses outbound connections to private,
+	// loopback, link-local and metadata-service addresses, checked after DNS
+	// resolution. On by default.
+	BlockPrivateDestinations bool `yaml:"block_private_destinations"`
+	// PrivateDestinationsAllow lists private IPs/CIDRs clients may still reach
+	// while BlockPrivateDestinations is on.
+	PrivateDestinationsAllow []string `yaml:"private_destinations_allow"`
+	// LearnDestinationsFile records every requested destination with hit counts
+	// into a proposed allowed_destinations list at this path.
+	LearnDestinationsFile string `yaml:"learn_destinations_file"`
+
+	// Upstream protocol
+	// UpstreamHTTP1 disables HTTP/2 toward every origin except HTTP2Destinations.
+	UpstreamHTTP1 bool `yaml:"upstream_http1"`
+	// HTTP1Destinations lists hosts always reached over HTTP/1.1, for origins that
+	// misbehave with multiplexed connections ("*.example.com" matches subdomains).
+	HTTP1Destinations []string `yaml:"http1_destinations"`
+	// HTTP2Destinations lists hosts that attempt HTTP/2 even with UpstreamHTTP1 set.
+	HTTP2Destinations []string `yaml:"http2_destinations"`
+
+	// DNS configuration
+	// DNSServers lists upstream DNS servers (host:port) used for outbound lookups; empty uses the system resolver.
+	DNSServers []string `yaml:"dns_servers"`
+	// DNSCacheTTL is how long resolved names are cached (0 = no caching).
+	DNSCacheTTL time.Duration `yaml:"dns_cache_ttl"`
+	// DNSCacheSize is the maximum number of cached names.
+	DNSCacheSize int `yaml:"dns_cache_size"`
+	// DNSForwarderPort is the optional UDP/TCP port of a DNS proxy that queries dns_servers
+	// from the outbound IPs (0 = disabled).
+	DNSForwarderPort int `yaml:"dns_forwarder_port"`
+	// DNSForwarderAddress is the IP address the DNS forwarder binds to (empty = all interfaces).
+	DNSForwarderAddress string `yaml:"dns_forwarder_address"`
+
+	// File descriptor pressure
+	// FDEvictionThreshold is the fraction of the fd limit at which idle tunnels are evicted (0 = disabled).
+	FDEvictionThreshold float64 `yaml:"fd_eviction_threshold"`
+	// FDEvictionMinIdle is how long a tunnel must be idle before it can be evicted.
+	FDEvictionMinIdle time.Duration `yaml:"fd_eviction_min_idle"`
+
+	// Tunnel progress
+	// TunnelProgressInterval reports bytes relayed and throughput of long tunnels this often (0 = disabled).
+	TunnelProgressInterval time.Duration `yaml:"tunnel_progress_interval"`
+	// TunnelProgressMinBytes is how many bytes a tunnel must have relayed before it is reported.
+	TunnelProgressMinBytes int `yaml:"tunnel_progress_min_bytes"`
+
+	// ETWInterval is how often counters are published to Event Tracing for Windows (0 = disabled).
+	ETWInterval time.Duration `yaml:"etw_interval"`
+
+	// SLOs defines latency/error objectives per route (config file only).
+	SLOs []SLOConfig `yaml:"slos"`
 }
 
 // DefaultConfig returns a Config with sensible defaults.
@@ -86,15 +388,31 @@ func DefaultConfig() *Config {
 	return &Config{
 		Port:                   3128,
 		MetricsPort:            9090,
+		TLSClientIdentity:      ClientIdentityCN,
+		JWTJWKSRefresh:         10 * time.Minute,
+		AuthWebhookTimeout:     5 * time.Second,
 		Timeout:                30 * time.Second,
 		IdleTimeout:            60 * time.Second,
+		ShutdownGracePeriod:    30 * time.Second,
+		ConnectRetries:         2,
+		ConnectRetryMaxBackoff: time.Second,
+		ConnectRetryJitter:     0.2,
+		ConnectRetryOn:         []string{RetryOnRefused, RetryOnTimeout, RetryOnUnreachable, RetryOnReset, RetryOnBind},
 		MaxConnsPerIP:          100,
 		MaxConnsTotal:          1000,
 		HistoryWindow:          5 * time.Minute,
 		HistorySize:            100,
 		HistoryMaxTotalEntries: 100000,
+		SessionAffinityScope:   AffinityScopeHost,
+		UsernameSessionTTL:     10 * time.Minute,
+		MaintenanceMessage:     "Service under maintenance, please retry later",
+		MaintenanceRetryAfter:  5 * time.Minute,
+		BlockStatus:            403,
+		ConnectPorts:           []string{"443"},
 		LogLevel:               "info",
 		LogFormat:              "json",
+		AccessLogFormat:        "json",
+		OTLPServiceName:        "outbound-lb",
 		// Transport defaults
 		TCPKeepAlive:          30 * time.Second,
 		IdleConnTimeout:       90 * time.Second,
@@ -113,6 +431,29 @@ func DefaultConfig() *Config {
 		HealthCheckTarget:           "1.1.1.1:443",
 		HealthCheckFailureThreshold: 3,
 		HealthCheckSuccessThreshold: 2,
+		KeepWarmTarget:              "1.1.1.1:443",
+		OutlierMinRequests:          10,
+		OutlierWindow:               30 * time.Second,
+		OutlierEjectionTime:         30 * time.Second,
+		QuarantineMinResponses:      20,
+		QuarantineWindow:            time.Minute,
+		QuarantineTime:              10 * time.Minute,
+		RTTDegradeFactor:            3,
+		RTTRetransmitThreshold:      0.05,
+		AnomalyThreshold:            4,
+		ExitIPCheckInterval:         5 * time.Minute,
+		// Backend defaults
+		GeoHeader:     "X-Outbound-Country",
+		ExcludeHeader: "X-Outbound-Exclude",
+		// Destination defaults
+		BlockPrivateDestinations: true,
+		// Sniffing defaults
+		SniffTimeout: 500 * time.Millisecond,
+		// DNS defaults
+		DNSCacheSize:        10000,
+		DNSForwarderAddress: "127.0.0.1",
+		// FD pressure defaults
+		FDEvictionMinIdle: 30 * time.Second,
 	}
 }
 
@@ -123,15 +464,76 @@ func ParseFlags() (*Config, error) {
 	pflag.StringSliceVar(&cfg.IPs, "ips", nil, "Comma-separated list of outbound IPs")
 	pflag.IntVar(&cfg.Port, "port", cfg.Port, "Proxy listening port")
 	pflag.IntVar(&cfg.MetricsPort, "metrics-port", cfg.MetricsPort, "Metrics server port")
+	pflag.StringVar(&cfg.AdminToken, "admin-token", "", "Bearer token required by the /admin endpoints (empty = loopback clients only)")
+	pflag.IntVar(&cfg.SOCKS5Port, "socks5-port", cfg.SOCKS5Port, "SOCKS5 listener port (0 = disabled)")
+	pflag.IntVar(&cfg.TransparentPort, "transparent-port", cfg.TransparentPort, "Transparent proxy listener port for REDIRECT/TPROXY traffic (0 = disabled)")
+	pflag.IntVar(&cfg.ForwardPort, "forward-port", cfg.ForwardPort, "TCP port-forward listener port (0 = disabled)")
+	pflag.StringVar(&cfg.ForwardTarget, "forward-target", "", "Fixed host:port destination for --forward-port connections")
+	pflag.IntVar(&cfg.PublicStatusPort, "public-status-port", cfg.PublicStatusPort, "Public aggregate status page port (0 = disabled)")
+	pflag.StringVar(&cfg.TLSCertFile, "tls-cert-file", "", "Certificate file to serve the proxy over TLS")
+	pflag.StringVar(&cfg.TLSKeyFile, "tls-key-file", "", "Private key file for --tls-cert-file")
+	pflag.StringVar(&cfg.TLSClientCAFile, "tls-client-ca-file", "", "CA certificates verifying client certificates on the TLS listener")
+	pflag.BoolVar(&cfg.TLSClientCertOptional, "tls-client-cert-optional", cfg.TLSClientCertOptional, "Admit TLS clients without a certificate, authenticating them with credentials")
+	pflag.StringVar(&cfg.TLSClientIdentity, "tls-client-identity", cfg.TLSClientIdentity, "Client certificate field naming the user: cn, dns, email or uri")
+	pflag.BoolVar(&cfg.ProxyProtocol, "proxy-protocol", cfg.ProxyProtocol, "Require PROXY protocol v1/v2 headers from downstream load balancers")
+	pflag.StringSliceVar(&cfg.ProxyProtocolTrusted, "proxy-protocol-trusted", nil, "Comma-separated IPs/CIDRs that send PROXY headers (default: all sources)")
+	pflag.StringSliceVar(&cfg.ClientAllow, "client-allow", nil, "Comma-separated client IPs/CIDRs allowed to connect (default: all)")
+	pflag.StringSliceVar(&cfg.ClientDeny, "client-deny", nil, "Comma-separated client IPs/CIDRs refused even if allowed")
 	pflag.StringVar(&cfg.Auth, "auth", "", "Basic auth credentials (user:pass)")
+	pflag.StringVar(&cfg.AuthFile, "auth-file", "", "htpasswd-style users file with bcrypt or argon2 hashes")
+	pflag.DurationVar(&cfg.AuthCacheTTL, "auth-cache-ttl", cfg.AuthCacheTTL, "Remember successful auth-file, auth-webhook and PAM credential checks for this long (0 = verify every request)")
+	pflag.StringVar(&cfg.AuthWebhook, "auth-webhook", "", "URL receiving a JSON POST for each proxy credential to check")
+	pflag.DurationVar(&cfg.AuthWebhookTimeout, "auth-webhook-timeout", cfg.AuthWebhookTimeout, "Timeout of each auth webhook call")
+	pflag.StringVar(&cfg.AuthPAMService, "auth-pam-service", "", "PAM service checking proxy credentials against system accounts (requires a pam build)")
+	pflag.StringVar(&cfg.JWTSecret, "jwt-secret", "", "Shared secret verifying HS256/HS384/HS512 bearer tokens")
+	pflag.StringVar(&cfg.JWTJWKSURL, "jwt-jwks-url", "", "JWKS URL with the keys verifying RS*/PS*/ES* bearer tokens")
+	pflag.DurationVar(&cfg.JWTJWKSRefresh, "jwt-jwks-refresh", cfg.JWTJWKSRefresh, "How often the JWKS is fetched again")
+	pflag.StringVar(&cfg.JWTIssuer, "jwt-issuer", "", "Required iss claim of bearer tokens")
+	pflag.StringVar(&cfg.JWTAudience, "jwt-audience", "", "Required aud claim of bearer tokens")
 	pflag.DurationVar(&cfg.Timeout, "timeout", cfg.Timeout, "Connection timeout")
 	pflag.DurationVar(&cfg.IdleTimeout, "idle-timeout", cfg.IdleTimeout, "Idle connection timeout")
+	pflag.DurationVar(&cfg.HeaderReadTimeout, "header-read-timeout", cfg.HeaderReadTimeout, "Timeout for reading client request headers and SOCKS/PROXY handshakes (0 = --timeout)")
+	pflag.DurationVar(&cfg.DNSTimeout, "dns-timeout", cfg.DNSTimeout, "Timeout for resolving a target hostname (0 = --timeout)")
+	pflag.DurationVar(&cfg.ConnectTimeout, "connect-timeout", cfg.ConnectTimeout, "Timeout for dialing a target (0 = --timeout)")
+	pflag.DurationVar(&cfg.TunnelIdleTimeout, "tunnel-idle-timeout", cfg.TunnelIdleTimeout, "Close tunnels idle for this long (0 = --idle-timeout)")
+	pflag.DurationVar(&cfg.TunnelMaxLifetime, "tunnel-max-lifetime", cfg.TunnelMaxLifetime, "Close tunnels open for this long, busy or not (0 = unlimited)")
+	pflag.DurationVar(&cfg.ShutdownGracePeriod, "shutdown-grace-period", cfg.ShutdownGracePeriod, "How long in-flight requests and tunnels keep relaying after SIGTERM (0 = close at once)")
+	pflag.IntVar(&cfg.ConnectRetries, "connect-retries", cfg.ConnectRetries, "Alternate outbound IPs to retry a failed CONNECT dial on")
+	pflag.DurationVar(&cfg.ConnectRetryTimeout, "connect-retry-timeout", cfg.ConnectRetryTimeout, "Dial timeout of each CONNECT attempt (0 = --connect-timeout)")
+	pflag.DurationVar(&cfg.ConnectRetryBackoff, "connect-retry-backoff", cfg.ConnectRetryBackoff, "Wait before the first CONNECT retry, doubled per retry (0 = retry immediately)")
+	pflag.DurationVar(&cfg.ConnectRetryMaxBackoff, "connect-retry-max-backoff", cfg.ConnectRetryMaxBackoff, "Maximum wait between CONNECT retries")
+	pflag.Float64Var(&cfg.ConnectRetryJitter, "connect-retry-jitter", cfg.ConnectRetryJitter, "Fraction (0-1) of each CONNECT retry backoff that is randomized")
+	pflag.DurationVar(&cfg.ConnectHedgeDelay, "connect-hedge-delay", cfg.ConnectHedgeDelay, "Race a second CONNECT dial on another outbound IP after this delay (0 = disabled)")
+	pflag.StringVar(&cfg.ConnectRetryHeader, "connect-retry-header", "", "Response header reporting retries and the serving outbound IP on rescued CONNECTs (empty = disabled)")
+	pflag.StringSliceVar(&cfg.ConnectRetryOn, "connect-retry-on", cfg.ConnectRetryOn, "Comma-separated dial error classes to retry (refused, timeout, unreachable, reset, bind, other)")
 	pflag.IntVar(&cfg.MaxConnsPerIP, "max-conns-per-ip", cfg.MaxConnsPerIP, "Max connections per outbound IP")
 	pflag.IntVar(&cfg.MaxConnsTotal, "max-conns-total", cfg.MaxConnsTotal, "Max total connections")
+	pflag.IntVar(&cfg.MaxHeaderBytes, "max-header-bytes", cfg.MaxHeaderBytes, "Max size of client request headers in bytes (0 = 1 MiB)")
+	pflag.IntVar(&cfg.MaxUploadBytes, "max-upload-bytes", cfg.MaxUploadBytes, "Max body size of a plain HTTP request in bytes (0 = unlimited)")
+	pflag.Float64Var(&cfg.FairShareThreshold, "fair-share-threshold", cfg.FairShareThreshold, "Fraction of max-conns-total in use at which users are held to their fair share (0 = disabled)")
 	pflag.DurationVar(&cfg.HistoryWindow, "history-window", cfg.HistoryWindow, "LRU history time window")
 	pflag.IntVar(&cfg.HistorySize, "history-size", cfg.HistorySize, "Max history entries per host")
+	pflag.DurationVar(&cfg.SlowStartWindow, "slow-start-window", cfg.SlowStartWindow, "Ramp IPs that return to health up to a full share over this window (0 = disabled)")
+	pflag.DurationVar(&cfg.SessionAffinityTTL, "session-affinity-ttl", cfg.SessionAffinityTTL, "Keep a client on the same outbound IP for a destination while it returns within this period (0 = disabled)")
+	pflag.StringVar(&cfg.SessionAffinityScope, "session-affinity-scope", cfg.SessionAffinityScope, "Session affinity key: host or domain (eTLD+1)")
+	pflag.DurationVar(&cfg.DestinationAffinityTTL, "destination-affinity-ttl", cfg.DestinationAffinityTTL, "Keep all clients on the same outbound IP for a destination while it is used within this period (0 = disabled)")
+	pflag.DurationVar(&cfg.UsernameSessionTTL, "username-session-ttl", cfg.UsernameSessionTTL, "Keep a username session (user-session-<id>) on one outbound IP while it returns within this period (0 = ignore sessions)")
+	pflag.DurationVar(&cfg.DrainPeriod, "drain-period", cfg.DrainPeriod, "Default period over which drained IPs lose their traffic (0 = immediately)")
+	pflag.StringSliceVar(&cfg.Drain, "drain", nil, "Comma-separated outbound IPs to keep out of rotation while their existing tunnels finish")
+	pflag.StringVar(&cfg.MaintenanceMessage, "maintenance-message", cfg.MaintenanceMessage, "Body of the 503 sent while /admin/maintenance rejects new traffic")
+	pflag.DurationVar(&cfg.MaintenanceRetryAfter, "maintenance-retry-after", cfg.MaintenanceRetryAfter, "Retry-After sent with maintenance 503s (0 = omitted)")
+	pflag.BoolVar(&cfg.MaintenanceCloseTunnels, "maintenance-close-tunnels", false, "Close existing tunnels when SIGUSR1 starts maintenance")
+	pflag.IntVar(&cfg.BlockStatus, "block-status", cfg.BlockStatus, "HTTP status sent for requests refused by destination policy")
+	pflag.StringVar(&cfg.BlockBody, "block-body", "", "Template for the body of block responses (.Rule, .Host, .TicketURL, .RequestID)")
+	pflag.StringVar(&cfg.BlockTicketURL, "block-ticket-url", "", "URL passed to --block-body as .TicketURL")
+	pflag.StringSliceVar(&cfg.BlockHeaders, "block-headers", nil, "Comma-separated \"Name: value\" headers added to block responses")
 	pflag.StringVar(&cfg.LogLevel, "log-level", cfg.LogLevel, "Log level (debug, info, warn, error)")
 	pflag.StringVar(&cfg.LogFormat, "log-format", cfg.LogFormat, "Log format (json, text)")
+	pflag.StringSliceVar(&cfg.LogLevels, "log-levels", nil, "Comma-separated \"module=level\" log level overrides (e.g. relay=warn,balancer=debug)")
+	pflag.StringVar(&cfg.AccessLog, "access-log", "", "Write access records to this file, or stdout for \"-\" (empty = in the log)")
+	pflag.StringVar(&cfg.AccessLogFormat, "access-log-format", cfg.AccessLogFormat, "Access log format (json, clf)")
+	pflag.StringVar(&cfg.OTLPEndpoint, "otlp-endpoint", "", "OTLP/HTTP traces URL to export connection spans to, e.g. http://collector:4318/v1/traces (empty = disabled)")
+	pflag.StringVar(&cfg.OTLPServiceName, "otlp-service-name", cfg.OTLPServiceName, "Service name reported with exported spans")
 	pflag.StringVar(&cfg.ConfigFile, "config", "", "Config file path (YAML)")
 
 	// Transport tuning flags
@@ -155,6 +557,78 @@ func ParseFlags() (*Config, error) {
 	pflag.StringVar(&cfg.HealthCheckTarget, "health-check-target", cfg.HealthCheckTarget, "Health check target (host:port for tcp, URL for http)")
 	pflag.IntVar(&cfg.HealthCheckFailureThreshold, "health-check-failure-threshold", cfg.HealthCheckFailureThreshold, "Failures before marking IP unhealthy")
 	pflag.IntVar(&cfg.HealthCheckSuccessThreshold, "health-check-success-threshold", cfg.HealthCheckSuccessThreshold, "Successes before marking IP healthy")
+	pflag.IntVar(&cfg.HealthCheckExpectedStatus, "health-check-expected-status", cfg.HealthCheckExpectedStatus, "Exact status code http checks require (0 = any 2xx/3xx)")
+	pflag.StringVar(&cfg.HealthCheckExpectedBody, "health-check-expected-body", "", "Substring the http check response body must contain")
+	pflag.StringVar(&cfg.HealthStateFile, "health-state-file", "", "File that persists unhealthy and ejected IPs across restarts")
+	pflag.DurationVar(&cfg.KeepWarmInterval, "keep-warm-interval", cfg.KeepWarmInterval, "Probe outbound IPs idle for this long to keep uplinks awake (0 = disabled)")
+	pflag.StringVar(&cfg.KeepWarmTarget, "keep-warm-target", cfg.KeepWarmTarget, "Keep-warm probe target (host:port)")
+	pflag.Float64Var(&cfg.OutlierErrorRate, "outlier-error-rate", cfg.OutlierErrorRate, "Fraction of failed connections that ejects an IP from rotation (0 = disabled)")
+	pflag.IntVar(&cfg.OutlierMinRequests, "outlier-min-requests", cfg.OutlierMinRequests, "Connections in a window before an IP can be ejected")
+	pflag.DurationVar(&cfg.OutlierWindow, "outlier-window", cfg.OutlierWindow, "Interval over which the outlier error rate is measured")
+	pflag.DurationVar(&cfg.OutlierEjectionTime, "outlier-ejection-time", cfg.OutlierEjectionTime, "How long an ejected IP is kept out of rotation")
+	pflag.Float64Var(&cfg.QuarantineBlockRate, "quarantine-block-rate", cfg.QuarantineBlockRate, "Fraction of plain-HTTP responses that are 429/403 which quarantines an IP (0 = disabled)")
+	pflag.IntVar(&cfg.QuarantineMinResponses, "quarantine-min-responses", cfg.QuarantineMinResponses, "Responses in a window before an IP can be quarantined")
+	pflag.DurationVar(&cfg.QuarantineWindow, "quarantine-window", cfg.QuarantineWindow, "Interval over which the 429/403 rate is measured")
+	pflag.DurationVar(&cfg.QuarantineTime, "quarantine-time", cfg.QuarantineTime, "How long a quarantined IP is kept out of rotation")
+	pflag.DurationVar(&cfg.RTTSampleInterval, "rtt-sample-interval", cfg.RTTSampleInterval, "Sample TCP RTT and retransmissions of active tunnels this often (0 = disabled, Linux only)")
+	pflag.Float64Var(&cfg.RTTDegradeFactor, "rtt-degrade-factor", cfg.RTTDegradeFactor, "Flag an IP whose RTT exceeds its baseline by this factor")
+	pflag.Float64Var(&cfg.RTTRetransmitThreshold, "rtt-retransmit-threshold", cfg.RTTRetransmitThreshold, "Flag an IP whose tunnels retransmit more than this fraction of segments")
+	pflag.DurationVar(&cfg.AnomalyInterval, "anomaly-interval", cfg.AnomalyInterval, "Compare each IP's error rate, request rate and throughput with its baseline this often (0 = disabled)")
+	pflag.Float64Var(&cfg.AnomalyThreshold, "anomaly-threshold", cfg.AnomalyThreshold, "Raise an anomaly when a sample is this many standard deviations from the IP's baseline")
+	pflag.StringVar(&cfg.AnomalyWebhook, "anomaly-webhook", cfg.AnomalyWebhook, "URL receiving a JSON POST for every anomaly raised")
+	pflag.StringVar(&cfg.ExitIPCheckURL, "exit-ip-check-url", "", "\"What is my IP\" URL fetched through every outbound IP to verify exits (empty = disabled)")
+	pflag.DurationVar(&cfg.ExitIPCheckInterval, "exit-ip-check-interval", cfg.ExitIPCheckInterval, "Interval between exit IP verifications")
+	pflag.BoolVar(&cfg.ExitIPCheckEject, "exit-ip-check-eject", cfg.ExitIPCheckEject, "Remove IPs with a shared or changed exit from rotation")
+
+	// Backend flags
+	pflag.StringVar(&cfg.PoolsFile, "pools-file", cfg.PoolsFile, "File that persists pools created through the admin API")
+	pflag.StringVar(&cfg.GeoHeader, "geo-header", cfg.GeoHeader, "Request header used to select backends by country tag")
+	pflag.StringVar(&cfg.ExcludeHeader, "exclude-header", cfg.ExcludeHeader, "Request header listing outbound IPs to avoid")
+	pflag.StringVar(&cfg.PoolHeader, "pool-header", cfg.PoolHeader, "Request header trusted clients can use to choose a named pool (empty = disabled)")
+	pflag.StringSliceVar(&cfg.PoolHeaderTrusted, "pool-header-trusted", nil, "Comma-separated client IPs/CIDRs allowed to use the pool header (default: none)")
+
+	// Sniffing flags
+	pflag.BoolVar(&cfg.SniffProtocols, "sniff-protocols", cfg.SniffProtocols, "Detect TLS/HTTP on tunnels and check SNI/Host against the target")
+	pflag.DurationVar(&cfg.SniffTimeout, "sniff-timeout", cfg.SniffTimeout, "Time to wait for the client's first bytes when sniffing")
+
+	// FTP flags
+	pflag.BoolVar(&cfg.FTPPassive, "ftp-passive", cfg.FTPPassive, "Send FTP passive-mode data connections through the control connection's outbound IP")
+
+	// CONNECT header policy flags
+	pflag.StringSliceVar(&cfg.ConnectLogHeaders, "connect-log-headers", nil, "Comma-separated CONNECT request headers to record in the access log (\"X-*\" matches a prefix)")
+	pflag.StringSliceVar(&cfg.ConnectRequireHeaders, "connect-require-headers", nil, "Comma-separated headers every CONNECT request must carry")
+	pflag.StringSliceVar(&cfg.ConnectRejectHeaders, "connect-reject-headers", nil, "Comma-separated headers that cause a CONNECT request to be refused")
+	pflag.StringSliceVar(&cfg.ConnectPorts, "connect-ports", cfg.ConnectPorts, "Comma-separated destination ports and ranges CONNECT and SOCKS may target (empty = all)")
+	pflag.StringSliceVar(&cfg.AllowedMethods, "allowed-methods", nil, "Comma-separated HTTP methods plain HTTP requests may use (empty = all)")
+
+	// Destination allowlist flags
+	pflag.StringSliceVar(&cfg.AllowedDestinations, "allowed-destinations", nil, "Comma-separated hosts clients may reach (\"*.example.com\" matches subdomains, \"/regexp/\" a pattern; default: all)")
+	pflag.BoolVar(&cfg.BlockPrivateDestinations, "block-private-destinations", cfg.BlockPrivateDestinations, "Refuse connections to private, loopback, link-local and metadata addresses after DNS resolution")
+	pflag.StringSliceVar(&cfg.PrivateDestinationsAllow, "private-destinations-allow", nil, "Comma-separated private IPs/CIDRs still reachable with --block-private-destinations")
+	pflag.StringSliceVar(&cfg.DeniedDestinations, "denied-destinations", nil, "Comma-separated hosts clients may never reach, in the same patterns as --allowed-destinations")
+	pflag.StringVar(&cfg.PolicyFile, "policy-file", cfg.PolicyFile, "YAML file of destination lists and routing rules, reloaded on change")
+	pflag.StringVar(&cfg.LearnDestinationsFile, "learn-destinations-file", "", "Record requested destinations into a proposed allowlist at this path")
+
+	// Upstream protocol flags
+	pflag.BoolVar(&cfg.UpstreamHTTP1, "upstream-http1", cfg.UpstreamHTTP1, "Use HTTP/1.1 toward every origin except --http2-destinations")
+	pflag.StringSliceVar(&cfg.HTTP1Destinations, "http1-destinations", nil, "Comma-separated hosts always reached over HTTP/1.1 (\"*.example.com\" matches subdomains)")
+	pflag.StringSliceVar(&cfg.HTTP2Destinations, "http2-destinations", nil, "Comma-separated hosts that attempt HTTP/2 even with --upstream-http1")
+
+	// DNS flags
+	pflag.StringSliceVar(&cfg.DNSServers, "dns-servers", nil, "Comma-separated upstream DNS servers (default: system resolver)")
+	pflag.DurationVar(&cfg.DNSCacheTTL, "dns-cache-ttl", cfg.DNSCacheTTL, "DNS cache TTL (0 = no caching)")
+	pflag.IntVar(&cfg.DNSCacheSize, "dns-cache-size", cfg.DNSCacheSize, "Maximum number of cached DNS names")
+	pflag.IntVar(&cfg.DNSForwarderPort, "dns-forwarder-port", cfg.DNSForwarderPort, "DNS forwarder listener port, UDP and TCP (0 = disabled)")
+	pflag.StringVar(&cfg.DNSForwarderAddress, "dns-forwarder-address", cfg.DNSForwarderAddress, "IP address the DNS forwarder binds to (empty = all interfaces)")
+
+	// FD pressure flags
+	pflag.Float64Var(&cfg.FDEvictionThreshold, "fd-eviction-threshold", cfg.FDEvictionThreshold, "Fraction of the fd limit at which idle tunnels are evicted, oldest first (0 = disabled)")
+	pflag.DurationVar(&cfg.FDEvictionMinIdle, "fd-eviction-min-idle", cfg.FDEvictionMinIdle, "Minimum idle time before a tunnel can be evicted")
+	pflag.DurationVar(&cfg.TunnelProgressInterval, "tunnel-progress-interval", cfg.TunnelProgressInterval, "Report progress of tunnels open at least this long, at this interval (0 = disabled)")
+	pflag.IntVar(&cfg.TunnelProgressMinBytes, "tunnel-progress-min-bytes", cfg.TunnelProgressMinBytes, "Bytes a tunnel must have relayed before its progress is reported")
+
+	// Windows monitoring flags
+	pflag.DurationVar(&cfg.ETWInterval, "etw-interval", cfg.ETWInterval, "Interval for publishing counters to ETW on Windows (0 = disabled)")
 
 	pflag.Parse()
 
@@ -205,24 +679,146 @@ func mergeConfigs(file, cli *Config) *Config {
 			result.Port = cli.Port
 		case "metrics-port":
 			result.MetricsPort = cli.MetricsPort
+		case "admin-token":
+			result.AdminToken = cli.AdminToken
+		case "socks5-port":
+			result.SOCKS5Port = cli.SOCKS5Port
+		case "transparent-port":
+			result.TransparentPort = cli.TransparentPort
+		case "forward-port":
+			result.ForwardPort = cli.ForwardPort
+		case "forward-target":
+			result.ForwardTarget = cli.ForwardTarget
+		case "public-status-port":
+			result.PublicStatusPort = cli.PublicStatusPort
+		case "tls-cert-file":
+			result.TLSCertFile = cli.TLSCertFile
+		case "tls-key-file":
+			result.TLSKeyFile = cli.TLSKeyFile
+		case "tls-client-ca-file":
+			result.TLSClientCAFile = cli.TLSClientCAFile
+		case "tls-client-cert-optional":
+			result.TLSClientCertOptional = cli.TLSClientCertOptional
+		case "tls-client-identity":
+			result.TLSClientIdentity = cli.TLSClientIdentity
+		case "proxy-protocol":
+			result.ProxyProtocol = cli.ProxyProtocol
+		case "proxy-protocol-trusted":
+			result.ProxyProtocolTrusted = cli.ProxyProtocolTrusted
+		case "client-allow":
+			result.ClientAllow = cli.ClientAllow
+		case "client-deny":
+			result.ClientDeny = cli.ClientDeny
 		case "auth":
 			result.Auth = cli.Auth
+		case "auth-file":
+			result.AuthFile = cli.AuthFile
+		case "auth-cache-ttl":
+			result.AuthCacheTTL = cli.AuthCacheTTL
+		case "auth-webhook":
+			result.AuthWebhook = cli.AuthWebhook
+		case "auth-webhook-timeout":
+			result.AuthWebhookTimeout = cli.AuthWebhookTimeout
+		case "auth-pam-service":
+			result.AuthPAMService = cli.AuthPAMService
+		case "jwt-secret":
+			result.JWTSecret = cli.JWTSecret
+		case "jwt-jwks-url":
+			result.JWTJWKSURL = cli.JWTJWKSURL
+		case "jwt-jwks-refresh":
+			result.JWTJWKSRefresh = cli.JWTJWKSRefresh
+		case "jwt-issuer":
+			result.JWTIssuer = cli.JWTIssuer
+		case "jwt-audience":
+			result.JWTAudience = cli.JWTAudience
 		case "timeout":
 			result.Timeout = cli.Timeout
 		case "idle-timeout":
 			result.IdleTimeout = cli.IdleTimeout
+		case "header-read-timeout":
+			result.HeaderReadTimeout = cli.HeaderReadTimeout
+		case "dns-timeout":
+			result.DNSTimeout = cli.DNSTimeout
+		case "connect-timeout":
+			result.ConnectTimeout = cli.ConnectTimeout
+		case "tunnel-idle-timeout":
+			result.TunnelIdleTimeout = cli.TunnelIdleTimeout
+		case "tunnel-max-lifetime":
+			result.TunnelMaxLifetime = cli.TunnelMaxLifetime
+		case "shutdown-grace-period":
+			result.ShutdownGracePeriod = cli.ShutdownGracePeriod
+		case "connect-retries":
+			result.ConnectRetries = cli.ConnectRetries
+		case "connect-retry-timeout":
+			result.ConnectRetryTimeout = cli.ConnectRetryTimeout
+		case "connect-retry-backoff":
+			result.ConnectRetryBackoff = cli.ConnectRetryBackoff
+		case "connect-retry-max-backoff":
+			result.ConnectRetryMaxBackoff = cli.ConnectRetryMaxBackoff
+		case "connect-retry-jitter":
+			result.ConnectRetryJitter = cli.ConnectRetryJitter
+		case "connect-retry-on":
+			result.ConnectRetryOn = cli.ConnectRetryOn
+		case "connect-hedge-delay":
+			result.ConnectHedgeDelay = cli.ConnectHedgeDelay
+		case "connect-retry-header":
+			result.ConnectRetryHeader = cli.ConnectRetryHeader
 		case "max-conns-per-ip":
 			result.MaxConnsPerIP = cli.MaxConnsPerIP
 		case "max-conns-total":
 			result.MaxConnsTotal = cli.MaxConnsTotal
+		case "max-header-bytes":
+			result.MaxHeaderBytes = cli.MaxHeaderBytes
+		case "max-upload-bytes":
+			result.MaxUploadBytes = cli.MaxUploadBytes
+		case "fair-share-threshold":
+			result.FairShareThreshold = cli.FairShareThreshold
 		case "history-window":
 			result.HistoryWindow = cli.HistoryWindow
 		case "history-size":
 			result.HistorySize = cli.HistorySize
+		case "slow-start-window":
+			result.SlowStartWindow = cli.SlowStartWindow
+		case "session-affinity-ttl":
+			result.SessionAffinityTTL = cli.SessionAffinityTTL
+		case "session-affinity-scope":
+			result.SessionAffinityScope = cli.SessionAffinityScope
+		case "destination-affinity-ttl":
+			result.DestinationAffinityTTL = cli.DestinationAffinityTTL
+		case "username-session-ttl":
+			result.UsernameSessionTTL = cli.UsernameSessionTTL
+		case "drain-period":
+			result.DrainPeriod = cli.DrainPeriod
+		case "drain":
+			result.Drain = cli.Drain
+		case "maintenance-message":
+			result.MaintenanceMessage = cli.MaintenanceMessage
+		case "maintenance-retry-after":
+			result.MaintenanceRetryAfter = cli.MaintenanceRetryAfter
+		case "maintenance-close-tunnels":
+			result.MaintenanceCloseTunnels = cli.MaintenanceCloseTunnels
+		case "block-status":
+			result.BlockStatus = cli.BlockStatus
+		case "block-body":
+			result.BlockBody = cli.BlockBody
+		case "block-ticket-url":
+			result.BlockTicketURL = cli.BlockTicketURL
+		case "block-headers":
+			result.BlockHeaders = cli.BlockHeaders
 		case "log-level":
 			result.LogLevel = cli.LogLevel
 		case "log-format":
 			result.LogFormat = cli.LogFormat
+		case "log-levels":
+			result.LogLevels = cli.LogLevels
+		case "access-log":
+			result.AccessLog = cli.AccessLog
+		case "access-log-format":
+			result.AccessLogFormat = cli.AccessLogFormat
+		case "otlp-endpoint":
+			result.OTLPEndpoint = cli.OTLPEndpoint
+		case "otlp-service-name":
+			result.OTLPServiceName = cli.OTLPServiceName
 		case "health-check-enabled":
 			result.HealthCheckEnabled = cli.HealthCheckEnabled
 		case "health-check-type":
@@ -237,6 +833,50 @@ func mergeConfigs(file, cli *Config) *Config {
 			result.HealthCheckFailureThreshold = cli.HealthCheckFailureThreshold
 		case "health-check-success-threshold":
 			result.HealthCheckSuccessThreshold = cli.HealthCheckSuccessThreshold
+		case "health-check-expected-status":
+			result.HealthCheckExpectedStatus = cli.HealthCheckExpectedStatus
+		case "health-check-expected-body":
+			result.HealthCheckExpectedBody = cli.HealthCheckExpectedBody
+		case "health-state-file":
+			result.HealthStateFile = cli.HealthStateFile
+		case "keep-warm-interval":
+			result.KeepWarmInterval = cli.KeepWarmInterval
+		case "keep-warm-target":
+			result.KeepWarmTarget = cli.KeepWarmTarget
+		case "outlier-error-rate":
+			result.OutlierErrorRate = cli.OutlierErrorRate
+		case "outlier-min-requests":
+			result.OutlierMinRequests = cli.OutlierMinRequests
+		case "outlier-window":
+			result.OutlierWindow = cli.OutlierWindow
+		case "outlier-ejection-time":
+			result.OutlierEjectionTime = cli.OutlierEjectionTime
+		case "quarantine-block-rate":
+			result.QuarantineBlockRate = cli.QuarantineBlockRate
+		case "quarantine-min-responses":
+			result.QuarantineMinResponses = cli.QuarantineMinResponses
+		case "quarantine-window":
+			result.QuarantineWindow = cli.QuarantineWindow
+		case "quarantine-time":
+			result.QuarantineTime = cli.QuarantineTime
+		case "rtt-sample-interval":
+			result.RTTSampleInterval = cli.RTTSampleInterval
+		case "rtt-degrade-factor":
+			result.RTTDegradeFactor = cli.RTTDegradeFactor
+		case "rtt-retransmit-threshold":
+			result.RTTRetransmitThreshold = cli.RTTRetransmitThreshold
+		case "anomaly-interval":
+			result.AnomalyInterval = cli.AnomalyInterval
+		case "anomaly-threshold":
+			result.AnomalyThreshold = cli.AnomalyThreshold
+		case "anomaly-webhook":
+			result.AnomalyWebhook = cli.AnomalyWebhook
+		case "exit-ip-check-url":
+			result.ExitIPCheckURL = cli.ExitIPCheckURL
+		case "exit-ip-check-interval":
+			result.ExitIPCheckInterval = cli.ExitIPCheckInterval
+		case "exit-ip-check-eject":
+			result.ExitIPCheckEject = cli.ExitIPCheckEject
 		case "tcp-keepalive":
 			result.TCPKeepAlive = cli.TCPKeepAlive
 		case "idle-conn-timeout":
@@ -255,6 +895,70 @@ func mergeConfigs(file, cli *Config) *Config {
 			result.CBSuccessThreshold = cli.CBSuccessThreshold
 		case "cb-timeout":
 			result.CBTimeout = cli.CBTimeout
+		case "pools-file":
+			result.PoolsFile = cli.PoolsFile
+		case "geo-header":
+			result.GeoHeader = cli.GeoHeader
+		case "exclude-header":
+			result.ExcludeHeader = cli.ExcludeHeader
+		case "pool-header":
+			result.PoolHeader = cli.PoolHeader
+		case "pool-header-trusted":
+			result.PoolHeaderTrusted = cli.PoolHeaderTrusted
+		case "sniff-protocols":
+			result.SniffProtocols = cli.SniffProtocols
+		case "sniff-timeout":
+			result.SniffTimeout = cli.SniffTimeout
+		case "ftp-passive":
+			result.FTPPassive = cli.FTPPassive
+		case "connect-log-headers":
+			result.ConnectLogHeaders = cli.ConnectLogHeaders
+		case "connect-require-headers":
+			result.ConnectRequireHeaders = cli.ConnectRequireHeaders
+		case "connect-reject-headers":
+			result.ConnectRejectHeaders = cli.ConnectRejectHeaders
+		case "connect-ports":
+			result.ConnectPorts = cli.ConnectPorts
+		case "allowed-methods":
+			result.AllowedMethods = cli.AllowedMethods
+		case "allowed-destinations":
+			result.AllowedDestinations = cli.AllowedDestinations
+		case "denied-destinations":
+			result.DeniedDestinations = cli.DeniedDestinations
+		case "policy-file":
+			result.PolicyFile = cli.PolicyFile
+		case "block-private-destinations":
+			result.BlockPrivateDestinations = cli.BlockPrivateDestinations
+		case "private-destinations-allow":
+			result.PrivateDestinationsAllow = cli.PrivateDestinationsAllow
+		case "learn-destinations-file":
+			result.LearnDestinationsFile = cli.LearnDestinationsFile
+		case "upstream-http1":
+			result.UpstreamHTTP1 = cli.UpstreamHTTP1
+		case "http1-destinations":
+			result.HTTP1Destinations = cli.HTTP1Destinations
+		case "http2-destinations":
+			result.HTTP2Destinations = cli.HTTP2Destinations
+		case "dns-servers":
+			result.DNSServers = cli.DNSServers
+		case "dns-cache-ttl":
+			result.DNSCacheTTL = cli.DNSCacheTTL
+		case "dns-cache-size":
+			result.DNSCacheSize = cli.DNSCacheSize
+		case "dns-forwarder-port":
+			result.DNSForwarderPort = cli.DNSForwarderPort
+		case "dns-forwarder-address":
+			result.DNSForwarderAddress = cli.DNSForwarderAddress
+		case "fd-eviction-threshold":
+			result.FDEvictionThreshold = cli.FDEvictionThreshold
+		case "fd-eviction-min-idle":
+			result.FDEvictionMinIdle = cli.FDEvictionMinIdle
+		case "tunnel-progress-interval":
+			result.TunnelProgressInterval = cli.TunnelProgressInterval
+		case "tunnel-progress-min-bytes":
+			result.TunnelProgressMinBytes = cli.TunnelProgressMinBytes
+		case "etw-interval":
+			result.ETWInterval = cli.ETWInterval
 		}
 	})
 
@@ -285,10 +989,113 @@ func (c *Config) Validate() error {
 		return fmt.Errorf("proxy port and metrics port must be different")
 	}
 
+	if c.SOCKS5Port < 0 || c.SOCKS5Port > 65535 {
+		return fmt.Errorf("invalid socks5 port: %d", c.SOCKS5Port)
+	}
+
+	if c.SOCKS5Port != 0 && (c.SOCKS5Port == c.Port || c.SOCKS5Port == c.MetricsPort) {
+		return fmt.Errorf("socks5 port must differ from proxy and metrics ports")
+	}
+
+	if c.TransparentPort < 0 || c.TransparentPort > 65535 {
+		return fmt.Errorf("invalid transparent port: %d", c.TransparentPort)
+	}
+
+	if c.TransparentPort != 0 && (c.TransparentPort == c.Port || c.TransparentPort == c.MetricsPort || c.TransparentPort == c.SOCKS5Port) {
+		return fmt.Errorf("transparent port must differ from proxy, metrics and socks5 ports")
+	}
+
+	if c.ForwardPort < 0 || c.ForwardPort > 65535 {
+		return fmt.Errorf("invalid forward port: %d", c.ForwardPort)
+	}
+
+	if c.ForwardPort != 0 {
+		if c.ForwardPort == c.Port || c.ForwardPort == c.MetricsPort || c.ForwardPort == c.SOCKS5Port || c.ForwardPort == c.TransparentPort {
+			return fmt.Errorf("forward port must differ from proxy, metrics, socks5 and transparent ports")
+		}
+		host, port, err := net.SplitHostPort(c.ForwardTarget)
+		if err != nil || host == "" || port == "" {
+			return fmt.Errorf("forward-target must be host:port when forward-port is set: %q", c.ForwardTarget)
+		}
+	}
+
+	if c.PublicStatusPort < 0 || c.PublicStatusPort > 65535 {
+		return fmt.Errorf("invalid public status port: %d", c.PublicStatusPort)
+	}
+
+	if c.PublicStatusPort != 0 {
+		for _, port := range []int{c.Port, c.MetricsPort, c.SOCKS5Port, c.TransparentPort, c.ForwardPort} {
+			if c.PublicStatusPort == port {
+				return fmt.Errorf("public status port must differ from proxy, metrics, socks5, transparent and forward ports")
+			}
+		}
+	}
+
+	if (c.TLSCertFile == "") != (c.TLSKeyFile == "") {
+		return fmt.Errorf("tls-cert-file and tls-key-file must be set together")
+	}
+
+	if err := c.validateClientCerts(); err != nil {
+		return err
+	}
+
+	if _, err := netutil.ParseCIDRs(c.ProxyProtocolTrusted); err != nil {
+		return fmt.Errorf("proxy-protocol-trusted: %w", err)
+	}
+
+	if _, err := netutil.ParseCIDRs(c.PoolHeaderTrusted); err != nil {
+		return fmt.Errorf("pool-header-trusted: %w", err)
+	}
+
+	if _, err := netutil.ParseCIDRs(c.ClientAllow); err != nil {
+		return fmt.Errorf("client-allow: %w", err)
+	}
+
+	if _, err := netutil.ParseCIDRs(c.ClientDeny); err != nil {
+		return fmt.Errorf("client-deny: %w", err)
+	}
+
+	if _, err := netutil.ParseCIDRs(c.PrivateDestinationsAllow); err != nil {
+		return fmt.Errorf("private-destinations-allow: %w", err)
+	}
+
 	if c.Auth != "" && !strings.Contains(c.Auth, ":") {
 		return fmt.Errorf("auth must be in 'user:pass' format")
 	}
 
+	if c.Auth != "" && c.AuthFile != "" {
+		return fmt.Errorf("auth and auth-file are mutually exclusive")
+	}
+
+	if c.AuthCacheTTL < 0 {
+		return fmt.Errorf("auth-cache-ttl must not be negative")
+	}
+
+	if c.AuthWebhook != "" {
+		if c.Auth != "" || c.AuthFile != "" {
+			return fmt.Errorf("auth-webhook is mutually exclusive with auth and auth-file")
+		}
+		if u, err := url.Parse(c.AuthWebhook); err != nil || (u.Scheme != "http" && u.Scheme != "https") || u.Host == "" {
+			return fmt.Errorf("auth-webhook must be an http(s) URL: %q", c.AuthWebhook)
+		}
+		if c.AuthWebhookTimeout <= 0 {
+			return fmt.Errorf("auth-webhook-timeout must be positive")
+		}
+	}
+
+	if c.AuthPAMService != "" {
+		if c.Auth != "" || c.AuthFile != "" || c.AuthWebhook != "" {
+			return fmt.Errorf("auth-pam-service is mutually exclusive with auth, auth-file and auth-webhook")
+		}
+		if strings.ContainsAny(c.AuthPAMService, "/\x00") {
+			return fmt.Errorf("auth-pam-service must be a service name, not a path: %q", c.AuthPAMService)
+		}
+	}
+
+	if err := c.validateJWT(); err != nil {
+		return err
+	}
+
 	if c.Timeout <= 0 {
 		return fmt.Errorf("timeout must be positive")
 	}
@@ -297,6 +1104,14 @@ func (c *Config) Validate() error {
 		return fmt.Errorf("idle-timeout must be positive")
 	}
 
+	if err := c.validateTimeouts(); err != nil {
+		return err
+	}
+
+	if err := c.validateConnectRetry(); err != nil {
+		return err
+	}
+
 	if c.MaxConnsPerIP < 1 {
 		return fmt.Errorf("max-conns-per-ip must be at least 1")
 	}
@@ -305,6 +1120,14 @@ func (c *Config) Validate() error {
 		return fmt.Errorf("max-conns-total must be at least 1")
 	}
 
+	if c.MaxHeaderBytes < 0 {
+		return fmt.Errorf("max-header-bytes must not be negative")
+	}
+
+	if c.MaxUploadBytes < 0 {
+		return fmt.Errorf("max-upload-bytes must not be negative")
+	}
+
 	if c.HistoryWindow <= 0 {
 		return fmt.Errorf("history-window must be positive")
 	}
@@ -313,6 +1136,129 @@ func (c *Config) Validate() error {
 		return fmt.Errorf("history-size must be at least 1")
 	}
 
+	if c.SlowStartWindow < 0 {
+		return fmt.Errorf("slow-start-window must not be negative")
+	}
+
+	if c.DrainPeriod < 0 {
+		return fmt.Errorf("drain-period must not be negative")
+	}
+
+	for _, ip := range c.Drain {
+		if !containsString(c.IPs, ip) {
+			return fmt.Errorf("drain: %s is not listed in ips", ip)
+		}
+	}
+
+	if c.MaintenanceRetryAfter < 0 {
+		return fmt.Errorf("maintenance-retry-after must not be negative")
+	}
+
+	if err := c.validateBlockResponse(); err != nil {
+		return err
+	}
+
+	if err := c.validateHealthCheck(); err != nil {
+		return err
+	}
+
+	if c.HealthStateFile != "" && c.HealthCheckInterval <= 0 {
+		return fmt.Errorf("health-check-interval must be positive to save health-state-file")
+	}
+
+	if c.KeepWarmInterval < 0 {
+		return fmt.Errorf("keep-warm-interval must not be negative")
+	}
+
+	if c.KeepWarmInterval > 0 {
+		if _, _, err := net.SplitHostPort(c.KeepWarmTarget); err != nil {
+			return fmt.Errorf("keep-warm-target must be host:port: %q", c.KeepWarmTarget)
+		}
+	}
+
+	if c.CircuitBreakerEnabled {
+		if c.CBFailureThreshold < 1 {
+			return fmt.Errorf("cb-failure-threshold must be at least 1")
+		}
+		if c.CBSuccessThreshold < 1 {
+			return fmt.Errorf("cb-success-threshold must be at least 1")
+		}
+		if c.CBTimeout <= 0 {
+			return fmt.Errorf("cb-timeout must be positive")
+		}
+	}
+
+	if c.OutlierErrorRate < 0 || c.OutlierErrorRate > 1 {
+		return fmt.Errorf("outlier-error-rate must be between 0 and 1")
+	}
+
+	if c.OutlierErrorRate > 0 {
+		if c.OutlierMinRequests < 1 {
+			return fmt.Errorf("outlier-min-requests must be at least 1")
+		}
+		if c.OutlierWindow <= 0 {
+			return fmt.Errorf("outlier-window must be positive")
+		}
+		if c.OutlierEjectionTime <= 0 {
+			return fmt.Errorf("outlier-ejection-time must be positive")
+		}
+	}
+
+	if c.QuarantineBlockRate < 0 || c.QuarantineBlockRate > 1 {
+		return fmt.Errorf("quarantine-block-rate must be between 0 and 1")
+	}
+
+	if c.QuarantineBlockRate > 0 {
+		if c.QuarantineMinResponses < 1 {
+			return fmt.Errorf("quarantine-min-responses must be at least 1")
+		}
+		if c.QuarantineWindow <= 0 {
+			return fmt.Errorf("quarantine-window must be positive")
+		}
+		if c.QuarantineTime <= 0 {
+			return fmt.Errorf("quarantine-time must be positive")
+		}
+	}
+
+	if c.RTTSampleInterval < 0 {
+		return fmt.Errorf("rtt-sample-interval must not be negative")
+	}
+
+	if c.RTTSampleInterval > 0 {
+		if c.RTTDegradeFactor <= 1 {
+			return fmt.Errorf("rtt-degrade-factor must be greater than 1")
+		}
+		if c.RTTRetransmitThreshold <= 0 || c.RTTRetransmitThreshold > 1 {
+			return fmt.Errorf("rtt-retransmit-threshold must be between 0 and 1")
+		}
+	}
+
+	if c.AnomalyInterval < 0 {
+		return fmt.Errorf("anomaly-interval must not be negative")
+	}
+
+	if c.AnomalyInterval > 0 && c.AnomalyThreshold <= 0 {
+		return fmt.Errorf("anomaly-threshold must be positive")
+	}
+
+	if c.AnomalyWebhook != "" {
+		if u, err := url.Parse(c.AnomalyWebhook); err != nil || (u.Scheme != "http" && u.Scheme != "https") || u.Host == "" {
+			return fmt.Errorf("anomaly-webhook must be an http(s) URL: %q", c.AnomalyWebhook)
+		}
+		if c.AnomalyInterval == 0 {
+			return fmt.Errorf("anomaly-webhook requires anomaly-interval")
+		}
+	}
+
+	if c.ExitIPCheckURL != "" {
+		if u, err := url.Parse(c.ExitIPCheckURL); err != nil || (u.Scheme != "http" && u.Scheme != "https") || u.Host == "" {
+			return fmt.Errorf("exit-ip-check-url must be an http(s) URL: %q", c.ExitIPCheckURL)
+		}
+		if c.ExitIPCheckInterval <= 0 {
+			return fmt.Errorf("exit-ip-check-interval must be positive")
+		}
+	}
+
 	validLevels := map[string]bool{"trace": true, "debug": true, "info": true, "warn": true, "error": true}
 	if !validLevels[c.LogLevel] {
 		return fmt.Errorf("invalid log level: %s (must be trace, debug, info, warn, or error)", c.LogLevel)
@@ -323,6 +1269,107 @@ func (c *Config) Validate() error {
 		return fmt.Errorf("invalid log format: %s (must be json or text)", c.LogFormat)
 	}
 
+	if err := c.validateLogLevels(); err != nil {
+		return err
+	}
+
+	if c.AccessLogFormat != "json" && c.AccessLogFormat != "clf" {
+		return fmt.Errorf("invalid access log format: %s (must be json or clf)", c.AccessLogFormat)
+	}
+
+	if c.OTLPEndpoint != "" {
+		if u, err := url.Parse(c.OTLPEndpoint); err != nil || (u.Scheme != "http" && u.Scheme != "https") || u.Host == "" {
+			return fmt.Errorf("otlp-endpoint must be an http(s) URL: %q", c.OTLPEndpoint)
+		}
+		if c.OTLPServiceName == "" {
+			return fmt.Errorf("otlp-service-name must not be empty")
+		}
+	}
+
+	if c.SniffProtocols && c.SniffTimeout <= 0 {
+		return fmt.Errorf("sniff-timeout must be positive")
+	}
+
+	if err := c.validateDNS(); err != nil {
+		return err
+	}
+
+	if c.FDEvictionThreshold < 0 || c.FDEvictionThreshold >= 1 {
+		return fmt.Errorf("fd-eviction-threshold must be between 0 and 1")
+	}
+
+	if c.FDEvictionThreshold > 0 && c.FDEvictionMinIdle < 0 {
+		return fmt.Errorf("fd-eviction-min-idle must not be negative")
+	}
+
+	if c.TunnelProgressInterval < 0 {
+		return fmt.Errorf("tunnel-progress-interval must not be negative")
+	}
+
+	if c.TunnelProgressMinBytes < 0 {
+		return fmt.Errorf("tunnel-progress-min-bytes must not be negative")
+	}
+
+	if c.ETWInterval < 0 {
+		return fmt.Errorf("etw-interval must not be negative")
+	}
+
+	if err := c.validateBackends(); err != nil {
+		return err
+	}
+
+	if err := c.validatePools(); err != nil {
+		return err
+	}
+
+	if err := c.validateDestinationPools(); err != nil {
+		return err
+	}
+
+	if err := c.validateUserPools(); err != nil {
+		return err
+	}
+
+	if err := c.validateConnectHeaders(); err != nil {
+		return err
+	}
+
+	if err := c.validateConnectPorts(); err != nil {
+		return err
+	}
+
+	if err := c.validateAllowedMethods(); err != nil {
+		return err
+	}
+
+	if err := c.validateAllowedDestinations(); err != nil {
+		return err
+	}
+
+	if err := c.validateProtocolDestinations(); err != nil {
+		return err
+	}
+
+	if err := c.validateSLOs(); err != nil {
+		return err
+	}
+
+	if err := c.validateFairShare(); err != nil {
+		return err
+	}
+
+	if err := c.validateUserLimits(); err != nil {
+		return err
+	}
+
+	if err := c.validateSessionAffinity(); err != nil {
+		return err
+	}
+
+	if err := c.validateRules(); err != nil {
+		return err
+	}
+
 	return nil
 }
 
@@ -365,6 +1412,15 @@ func loadFromEnv(cfg *Config) {
 		return false, false
 	}
 
+	getEnvFloat := func(key string) (float64, bool) {
+		if v, ok := getEnvString(key); ok {
+			if f, err := strconv.ParseFloat(v, 64); err == nil {
+				return f, true
+			}
+		}
+		return 0, false
+	}
+
 	getEnvDuration := func(key string) (time.Duration, bool) {
 		if v, ok := getEnvString(key); ok {
 			if d, err := time.ParseDuration(v); err == nil {
@@ -406,10 +1462,116 @@ func loadFromEnv(cfg *Config) {
 		applyIfNotSet("metrics-port", func() { cfg.MetricsPort = v })
 	}
 
+	if v, ok := getEnvString("ADMIN_TOKEN"); ok {
+		applyIfNotSet("admin-token", func() { cfg.AdminToken = v })
+	}
+
+	if v, ok := getEnvInt("SOCKS5_PORT"); ok {
+		applyIfNotSet("socks5-port", func() { cfg.SOCKS5Port = v })
+	}
+
+	if v, ok := getEnvInt("TRANSPARENT_PORT"); ok {
+		applyIfNotSet("transparent-port", func() { cfg.TransparentPort = v })
+	}
+
+	if v, ok := getEnvInt("FORWARD_PORT"); ok {
+		applyIfNotSet("forward-port", func() { cfg.ForwardPort = v })
+	}
+
+	if v, ok := getEnvString("FORWARD_TARGET"); ok {
+		applyIfNotSet("forward-target", func() { cfg.ForwardTarget = v })
+	}
+
+	if v, ok := getEnvInt("PUBLIC_STATUS_PORT"); ok {
+		applyIfNotSet("public-status-port", func() { cfg.PublicStatusPort = v })
+	}
+
+	if v, ok := getEnvString("TLS_CERT_FILE"); ok {
+		applyIfNotSet("tls-cert-file", func() { cfg.TLSCertFile = v })
+	}
+
+	if v, ok := getEnvString("TLS_KEY_FILE"); ok {
+		applyIfNotSet("tls-key-file", func() { cfg.TLSKeyFile = v })
+	}
+
+	if v, ok := getEnvString("TLS_CLIENT_CA_FILE"); ok {
+		applyIfNotSet("tls-client-ca-file", func() { cfg.TLSClientCAFile = v })
+	}
+
+	if v, ok := getEnvBool("TLS_CLIENT_CERT_OPTIONAL"); ok {
+		applyIfNotSet("tls-client-cert-optional", func() { cfg.TLSClientCertOptional = v })
+	}
+
+	if v, ok := getEnvString("TLS_CLIENT_IDENTITY"); ok {
+		applyIfNotSet("tls-client-identity", func() { cfg.TLSClientIdentity = v })
+	}
+
+	if v, ok := getEnvBool("PROXY_PROTOCOL"); ok {
+		applyIfNotSet("proxy-protocol", func() { cfg.ProxyProtocol = v })
+	}
+
+	if v, ok := getEnvString("PROXY_PROTOCOL_TRUSTED"); ok {
+		applyIfNotSet("proxy-protocol-trusted", func() {
+			cfg.ProxyProtocolTrusted = strings.Split(v, ",")
+		})
+	}
+
+	if v, ok := getEnvString("CLIENT_ALLOW"); ok {
+		applyIfNotSet("client-allow", func() {
+			cfg.ClientAllow = strings.Split(v, ",")
+		})
+	}
+
+	if v, ok := getEnvString("CLIENT_DENY"); ok {
+		applyIfNotSet("client-deny", func() {
+			cfg.ClientDeny = strings.Split(v, ",")
+		})
+	}
+
 	if v, ok := getEnvString("AUTH"); ok {
 		applyIfNotSet("auth", func() { cfg.Auth = v })
 	}
 
+	if v, ok := getEnvString("AUTH_FILE"); ok {
+		applyIfNotSet("auth-file", func() { cfg.AuthFile = v })
+	}
+
+	if v, ok := getEnvDuration("AUTH_CACHE_TTL"); ok {
+		applyIfNotSet("auth-cache-ttl", func() { cfg.AuthCacheTTL = v })
+	}
+
+	if v, ok := getEnvString("AUTH_WEBHOOK"); ok {
+		applyIfNotSet("auth-webhook", func() { cfg.AuthWebhook = v })
+	}
+
+	if v, ok := getEnvDuration("AUTH_WEBHOOK_TIMEOUT"); ok {
+		applyIfNotSet("auth-webhook-timeout", func() { cfg.AuthWebhookTimeout = v })
+	}
+
+	if v, ok := getEnvString("AUTH_PAM_SERVICE"); ok {
+		applyIfNotSet("auth-pam-service", func() { cfg.AuthPAMService = v })
+	}
+
+	if v, ok := getEnvString("JWT_SECRET"); ok {
+		applyIfNotSet("jwt-secret", func() { cfg.JWTSecret = v })
+	}
+
+	if v, ok := getEnvString("JWT_JWKS_URL"); ok {
+		applyIfNotSet("jwt-jwks-url", func() { cfg.JWTJWKSURL = v })
+	}
+
+	if v, ok := getEnvDuration("JWT_JWKS_REFRESH"); ok {
+		applyIfNotSet("jwt-jwks-refresh", func() { cfg.JWTJWKSRefresh = v })
+	}
+
+	if v, ok := getEnvString("JWT_ISSUER"); ok {
+		applyIfNotSet("jwt-issuer", func() { cfg.JWTIssuer = v })
+	}
+
+	if v, ok := getEnvString("JWT_AUDIENCE"); ok {
+		applyIfNotSet("jwt-audience", func() { cfg.JWTAudience = v })
+	}
+
 	// Timeouts
 	if v, ok := getEnvDuration("TIMEOUT"); ok {
 		applyIfNotSet("timeout", func() { cfg.Timeout = v })
@@ -419,6 +1581,62 @@ func loadFromEnv(cfg *Config) {
 		applyIfNotSet("idle-timeout", func() { cfg.IdleTimeout = v })
 	}
 
+	if v, ok := getEnvDuration("HEADER_READ_TIMEOUT"); ok {
+		applyIfNotSet("header-read-timeout", func() { cfg.HeaderReadTimeout = v })
+	}
+
+	if v, ok := getEnvDuration("DNS_TIMEOUT"); ok {
+		applyIfNotSet("dns-timeout", func() { cfg.DNSTimeout = v })
+	}
+
+	if v, ok := getEnvDuration("CONNECT_TIMEOUT"); ok {
+		applyIfNotSet("connect-timeout", func() { cfg.ConnectTimeout = v })
+	}
+
+	if v, ok := getEnvDuration("TUNNEL_IDLE_TIMEOUT"); ok {
+		applyIfNotSet("tunnel-idle-timeout", func() { cfg.TunnelIdleTimeout = v })
+	}
+
+	if v, ok := getEnvDuration("TUNNEL_MAX_LIFETIME"); ok {
+		applyIfNotSet("tunnel-max-lifetime", func() { cfg.TunnelMaxLifetime = v })
+	}
+
+	if v, ok := getEnvDuration("SHUTDOWN_GRACE_PERIOD"); ok {
+		applyIfNotSet("shutdown-grace-period", func() { cfg.ShutdownGracePeriod = v })
+	}
+
+	if v, ok := getEnvInt("CONNECT_RETRIES"); ok {
+		applyIfNotSet("connect-retries", func() { cfg.ConnectRetries = v })
+	}
+
+	if v, ok := getEnvDuration("CONNECT_RETRY_TIMEOUT"); ok {
+		applyIfNotSet("connect-retry-timeout", func() { cfg.ConnectRetryTimeout = v })
+	}
+
+	if v, ok := getEnvDuration("CONNECT_RETRY_BACKOFF"); ok {
+		applyIfNotSet("connect-retry-backoff", func() { cfg.ConnectRetryBackoff = v })
+	}
+
+	if v, ok := getEnvDuration("CONNECT_RETRY_MAX_BACKOFF"); ok {
+		applyIfNotSet("connect-retry-max-backoff", func() { cfg.ConnectRetryMaxBackoff = v })
+	}
+
+	if v, ok := getEnvFloat("CONNECT_RETRY_JITTER"); ok {
+		applyIfNotSet("connect-retry-jitter", func() { cfg.ConnectRetryJitter = v })
+	}
+
+	if v, ok := getEnvString("CONNECT_RETRY_ON"); ok {
+		applyIfNotSet("connect-retry-on", func() { cfg.ConnectRetryOn = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvDuration("CONNECT_HEDGE_DELAY"); ok {
+		applyIfNotSet("connect-hedge-delay", func() { cfg.ConnectHedgeDelay = v })
+	}
+
+	if v, ok := getEnvString("CONNECT_RETRY_HEADER"); ok {
+		applyIfNotSet("connect-retry-header", func() { cfg.ConnectRetryHeader = v })
+	}
+
 	// Connection limits
 	if v, ok := getEnvInt("MAX_CONNS_PER_IP"); ok {
 		applyIfNotSet("max-conns-per-ip", func() { cfg.MaxConnsPerIP = v })
@@ -428,6 +1646,18 @@ func loadFromEnv(cfg *Config) {
 		applyIfNotSet("max-conns-total", func() { cfg.MaxConnsTotal = v })
 	}
 
+	if v, ok := getEnvInt("MAX_HEADER_BYTES"); ok {
+		applyIfNotSet("max-header-bytes", func() { cfg.MaxHeaderBytes = v })
+	}
+
+	if v, ok := getEnvInt("MAX_UPLOAD_BYTES"); ok {
+		applyIfNotSet("max-upload-bytes", func() { cfg.MaxUploadBytes = v })
+	}
+
+	if v, ok := getEnvFloat("FAIR_SHARE_THRESHOLD"); ok {
+		applyIfNotSet("fair-share-threshold", func() { cfg.FairShareThreshold = v })
+	}
+
 	// Load balancer settings
 	if v, ok := getEnvDuration("HISTORY_WINDOW"); ok {
 		applyIfNotSet("history-window", func() { cfg.HistoryWindow = v })
@@ -437,6 +1667,62 @@ func loadFromEnv(cfg *Config) {
 		applyIfNotSet("history-size", func() { cfg.HistorySize = v })
 	}
 
+	if v, ok := getEnvDuration("SLOW_START_WINDOW"); ok {
+		applyIfNotSet("slow-start-window", func() { cfg.SlowStartWindow = v })
+	}
+
+	if v, ok := getEnvDuration("SESSION_AFFINITY_TTL"); ok {
+		applyIfNotSet("session-affinity-ttl", func() { cfg.SessionAffinityTTL = v })
+	}
+
+	if v, ok := getEnvString("SESSION_AFFINITY_SCOPE"); ok {
+		applyIfNotSet("session-affinity-scope", func() { cfg.SessionAffinityScope = v })
+	}
+
+	if v, ok := getEnvDuration("DESTINATION_AFFINITY_TTL"); ok {
+		applyIfNotSet("destination-affinity-ttl", func() { cfg.DestinationAffinityTTL = v })
+	}
+
+	if v, ok := getEnvDuration("USERNAME_SESSION_TTL"); ok {
+		applyIfNotSet("username-session-ttl", func() { cfg.UsernameSessionTTL = v })
+	}
+
+	if v, ok := getEnvDuration("DRAIN_PERIOD"); ok {
+		applyIfNotSet("drain-period", func() { cfg.DrainPeriod = v })
+	}
+
+	if v, ok := getEnvString("DRAIN"); ok {
+		applyIfNotSet("drain", func() { cfg.Drain = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvString("MAINTENANCE_MESSAGE"); ok {
+		applyIfNotSet("maintenance-message", func() { cfg.MaintenanceMessage = v })
+	}
+
+	if v, ok := getEnvDuration("MAINTENANCE_RETRY_AFTER"); ok {
+		applyIfNotSet("maintenance-retry-after", func() { cfg.MaintenanceRetryAfter = v })
+	}
+
+	if v, ok := getEnvBool("MAINTENANCE_CLOSE_TUNNELS"); ok {
+		applyIfNotSet("maintenance-close-tunnels", func() { cfg.MaintenanceCloseTunnels = v })
+	}
+
+	if v, ok := getEnvInt("BLOCK_STATUS"); ok {
+		applyIfNotSet("block-status", func() { cfg.BlockStatus = v })
+	}
+
+	if v, ok := getEnvString("BLOCK_BODY"); ok {
+		applyIfNotSet("block-body", func() { cfg.BlockBody = v })
+	}
+
+	if v, ok := getEnvString("BLOCK_TICKET_URL"); ok {
+		applyIfNotSet("block-ticket-url", func() { cfg.BlockTicketURL = v })
+	}
+
+	if v, ok := getEnvString("BLOCK_HEADERS"); ok {
+		applyIfNotSet("block-headers", func() { cfg.BlockHeaders = strings.Split(v, ",") })
+	}
+
 	if v, ok := getEnvInt("HISTORY_MAX_TOTAL_ENTRIES"); ok {
 		applyIfNotSet("history-max-total-entries", func() { cfg.HistoryMaxTotalEntries = v })
 	}
@@ -450,6 +1736,27 @@ func loadFromEnv(cfg *Config) {
 		applyIfNotSet("log-format", func() { cfg.LogFormat = v })
 	}
 
+	if v, ok := getEnvString("LOG_LEVELS"); ok {
+		applyIfNotSet("log-levels", func() { cfg.LogLevels = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvString("ACCESS_LOG"); ok {
+		applyIfNotSet("access-log", func() { cfg.AccessLog = v })
+	}
+
+	if v, ok := getEnvString("ACCESS_LOG_FORMAT"); ok {
+		applyIfNotSet("access-log-format", func() { cfg.AccessLogFormat = v })
+	}
+
+	// Tracing
+	if v, ok := getEnvString("OTLP_ENDPOINT"); ok {
+		applyIfNotSet("otlp-endpoint", func() { cfg.OTLPEndpoint = v })
+	}
+
+	if v, ok := getEnvString("OTLP_SERVICE_NAME"); ok {
+		applyIfNotSet("otlp-service-name", func() { cfg.OTLPServiceName = v })
+	}
+
 	// Transport tuning
 	if v, ok := getEnvDuration("TCP_KEEPALIVE"); ok {
 		applyIfNotSet("tcp-keepalive", func() { cfg.TCPKeepAlive = v })
@@ -512,4 +1819,243 @@ func loadFromEnv(cfg *Config) {
 	if v, ok := getEnvInt("HEALTH_CHECK_SUCCESS_THRESHOLD"); ok {
 		applyIfNotSet("health-check-success-threshold", func() { cfg.HealthCheckSuccessThreshold = v })
 	}
+
+	if v, ok := getEnvInt("HEALTH_CHECK_EXPECTED_STATUS"); ok {
+		applyIfNotSet("health-check-expected-status", func() { cfg.HealthCheckExpectedStatus = v })
+	}
+
+	if v, ok := getEnvString("HEALTH_CHECK_EXPECTED_BODY"); ok {
+		applyIfNotSet("health-check-expected-body", func() { cfg.HealthCheckExpectedBody = v })
+	}
+	if v, ok := getEnvString("HEALTH_STATE_FILE"); ok {
+		applyIfNotSet("health-state-file", func() { cfg.HealthStateFile = v })
+	}
+
+	if v, ok := getEnvDuration("KEEP_WARM_INTERVAL"); ok {
+		applyIfNotSet("keep-warm-interval", func() { cfg.KeepWarmInterval = v })
+	}
+
+	if v, ok := getEnvString("KEEP_WARM_TARGET"); ok {
+		applyIfNotSet("keep-warm-target", func() { cfg.KeepWarmTarget = v })
+	}
+
+	if v, ok := getEnvFloat("OUTLIER_ERROR_RATE"); ok {
+		applyIfNotSet("outlier-error-rate", func() { cfg.OutlierErrorRate = v })
+	}
+
+	if v, ok := getEnvInt("OUTLIER_MIN_REQUESTS"); ok {
+		applyIfNotSet("outlier-min-requests", func() { cfg.OutlierMinRequests = v })
+	}
+
+	if v, ok := getEnvDuration("OUTLIER_WINDOW"); ok {
+		applyIfNotSet("outlier-window", func() { cfg.OutlierWindow = v })
+	}
+
+	if v, ok := getEnvDuration("OUTLIER_EJECTION_TIME"); ok {
+		applyIfNotSet("outlier-ejection-time", func() { cfg.OutlierEjectionTime = v })
+	}
+
+	if v, ok := getEnvFloat("QUARANTINE_BLOCK_RATE"); ok {
+		applyIfNotSet("quarantine-block-rate", func() { cfg.QuarantineBlockRate = v })
+	}
+
+	if v, ok := getEnvInt("QUARANTINE_MIN_RESPONSES"); ok {
+		applyIfNotSet("quarantine-min-responses", func() { cfg.QuarantineMinResponses = v })
+	}
+
+	if v, ok := getEnvDuration("QUARANTINE_WINDOW"); ok {
+		applyIfNotSet("quarantine-window", func() { cfg.QuarantineWindow = v })
+	}
+
+	if v, ok := getEnvDuration("QUARANTINE_TIME"); ok {
+		applyIfNotSet("quarantine-time", func() { cfg.QuarantineTime = v })
+	}
+
+	if v, ok := getEnvDuration("RTT_SAMPLE_INTERVAL"); ok {
+		applyIfNotSet("rtt-sample-interval", func() { cfg.RTTSampleInterval = v })
+	}
+
+	if v, ok := getEnvFloat("RTT_DEGRADE_FACTOR"); ok {
+		applyIfNotSet("rtt-degrade-factor", func() { cfg.RTTDegradeFactor = v })
+	}
+
+	if v, ok := getEnvFloat("RTT_RETRANSMIT_THRESHOLD"); ok {
+		applyIfNotSet("rtt-retransmit-threshold", func() { cfg.RTTRetransmitThreshold = v })
+	}
+
+	if v, ok := getEnvDuration("ANOMALY_INTERVAL"); ok {
+		applyIfNotSet("anomaly-interval", func() { cfg.AnomalyInterval = v })
+	}
+
+	if v, ok := getEnvFloat("ANOMALY_THRESHOLD"); ok {
+		applyIfNotSet("anomaly-threshold", func() { cfg.AnomalyThreshold = v })
+	}
+
+	if v, ok := getEnvString("ANOMALY_WEBHOOK"); ok {
+		applyIfNotSet("anomaly-webhook", func() { cfg.AnomalyWebhook = v })
+	}
+
+	if v, ok := getEnvString("EXIT_IP_CHECK_URL"); ok {
+		applyIfNotSet("exit-ip-check-url", func() { cfg.ExitIPCheckURL = v })
+	}
+
+	if v, ok := getEnvDuration("EXIT_IP_CHECK_INTERVAL"); ok {
+		applyIfNotSet("exit-ip-check-interval", func() { cfg.ExitIPCheckInterval = v })
+	}
+
+	if v, ok := getEnvBool("EXIT_IP_CHECK_EJECT"); ok {
+		applyIfNotSet("exit-ip-check-eject", func() { cfg.ExitIPCheckEject = v })
+	}
+
+	// Backends
+	if v, ok := getEnvString("POOLS_FILE"); ok {
+		applyIfNotSet("pools-file", func() { cfg.PoolsFile = v })
+	}
+
+	if v, ok := getEnvString("GEO_HEADER"); ok {
+		applyIfNotSet("geo-header", func() { cfg.GeoHeader = v })
+	}
+
+	if v, ok := getEnvString("EXCLUDE_HEADER"); ok {
+		applyIfNotSet("exclude-header", func() { cfg.ExcludeHeader = v })
+	}
+
+	if v, ok := getEnvString("POOL_HEADER"); ok {
+		applyIfNotSet("pool-header", func() { cfg.PoolHeader = v })
+	}
+
+	if v, ok := getEnvString("POOL_HEADER_TRUSTED"); ok {
+		applyIfNotSet("pool-header-trusted", func() {
+			cfg.PoolHeaderTrusted = strings.Split(v, ",")
+		})
+	}
+
+	// Protocol sniffing
+	if v, ok := getEnvBool("SNIFF_PROTOCOLS"); ok {
+		applyIfNotSet("sniff-protocols", func() { cfg.SniffProtocols = v })
+	}
+
+	if v, ok := getEnvDuration("SNIFF_TIMEOUT"); ok {
+		applyIfNotSet("sniff-timeout", func() { cfg.SniffTimeout = v })
+	}
+
+	// FTP
+	if v, ok := getEnvBool("FTP_PASSIVE"); ok {
+		applyIfNotSet("ftp-passive", func() { cfg.FTPPassive = v })
+	}
+
+	// CONNECT header policy
+	if v, ok := getEnvString("CONNECT_LOG_HEADERS"); ok {
+		applyIfNotSet("connect-log-headers", func() { cfg.ConnectLogHeaders = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvString("CONNECT_REQUIRE_HEADERS"); ok {
+		applyIfNotSet("connect-require-headers", func() { cfg.ConnectRequireHeaders = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvString("CONNECT_REJECT_HEADERS"); ok {
+		applyIfNotSet("connect-reject-headers", func() { cfg.ConnectRejectHeaders = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvString("CONNECT_PORTS"); ok {
+		applyIfNotSet("connect-ports", func() {
+			cfg.ConnectPorts = nil
+			for _, p := range strings.Split(v, ",") {
+				if p = strings.TrimSpace(p); p != "" {
+					cfg.ConnectPorts = append(cfg.ConnectPorts, p)
+				}
+			}
+		})
+	}
+
+	if v, ok := getEnvString("ALLOWED_METHODS"); ok {
+		applyIfNotSet("allowed-methods", func() { cfg.AllowedMethods = strings.Split(v, ",") })
+	}
+
+	// Destination allowlist
+	if v, ok := getEnvString("ALLOWED_DESTINATIONS"); ok {
+		applyIfNotSet("allowed-destinations", func() { cfg.AllowedDestinations = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvString("DENIED_DESTINATIONS"); ok {
+		applyIfNotSet("denied-destinations", func() { cfg.DeniedDestinations = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvString("POLICY_FILE"); ok {
+		applyIfNotSet("policy-file", func() { cfg.PolicyFile = v })
+	}
+
+	if v, ok := getEnvBool("BLOCK_PRIVATE_DESTINATIONS"); ok {
+		applyIfNotSet("block-private-destinations", func() { cfg.BlockPrivateDestinations = v })
+	}
+
+	if v, ok := getEnvString("PRIVATE_DESTINATIONS_ALLOW"); ok {
+		applyIfNotSet("private-destinations-allow", func() { cfg.PrivateDestinationsAllow = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvString("LEARN_DESTINATIONS_FILE"); ok {
+		applyIfNotSet("learn-destinations-file", func() { cfg.LearnDestinationsFile = v })
+	}
+
+	// Upstream protocol
+	if v, ok := getEnvBool("UPSTREAM_HTTP1"); ok {
+		applyIfNotSet("upstream-http1", func() { cfg.UpstreamHTTP1 = v })
+	}
+
+	if v, ok := getEnvString("HTTP1_DESTINATIONS"); ok {
+		applyIfNotSet("http1-destinations", func() { cfg.HTTP1Destinations = strings.Split(v, ",") })
+	}
+
+	if v, ok := getEnvString("HTTP2_DESTINATIONS"); ok {
+		applyIfNotSet("http2-destinations", func() { cfg.HTTP2Destinations = strings.Split(v, ",") })
+	}
+
+	// DNS
+	if v, ok := getEnvString("DNS_SERVERS"); ok {
+		applyIfNotSet("dns-servers", func() {
+			cfg.DNSServers = strings.Split(v, ",")
+			for i, server := range cfg.DNSServers {
+				cfg.DNSServers[i] = strings.TrimSpace(server)
+			}
+		})
+	}
+
+	if v, ok := getEnvDuration("DNS_CACHE_TTL"); ok {
+		applyIfNotSet("dns-cache-ttl", func() { cfg.DNSCacheTTL = v })
+	}
+
+	if v, ok := getEnvInt("DNS_CACHE_SIZE"); ok {
+		applyIfNotSet("dns-cache-size", func() { cfg.DNSCacheSize = v })
+	}
+
+	if v, ok := getEnvInt("DNS_FORWARDER_PORT"); ok {
+		applyIfNotSet("dns-forwarder-port", func() { cfg.DNSForwarderPort = v })
+	}
+
+	if v, ok := getEnvString("DNS_FORWARDER_ADDRESS"); ok {
+		applyIfNotSet("dns-forwarder-address", func() { cfg.DNSForwarderAddress = v })
+	}
+
+	// File descriptor pressure
+	if v, ok := getEnvFloat("FD_EVICTION_THRESHOLD"); ok {
+		applyIfNotSet("fd-eviction-threshold", func() { cfg.FDEvictionThreshold = v })
+	}
+
+	if v, ok := getEnvDuration("FD_EVICTION_MIN_IDLE"); ok {
+		applyIfNotSet("fd-eviction-min-idle", func() { cfg.FDEvictionMinIdle = v })
+	}
+
+	// Tunnel progress
+	if v, ok := getEnvDuration("TUNNEL_PROGRESS_INTERVAL"); ok {
+		applyIfNotSet("tunnel-progress-interval", func() { cfg.TunnelProgressInterval = v })
+	}
+
+	if v, ok := getEnvInt("TUNNEL_PROGRESS_MIN_BYTES"); ok {
+		applyIfNotSet("tunnel-progress-min-bytes", func() { cfg.TunnelProgressMinBytes = v })
+	}
+
+	// Windows monitoring
+	if v, ok := getEnvDuration("ETW_INTERVAL"); ok {
+		applyIfNotSet("etw-interval", func() { cfg.ETWInterval = v })
+	}
 }
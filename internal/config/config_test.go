@@ -119,6 +119,629 @@ func TestConfigValidate(t *testing.T) {
 			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.LogFormat = "invalid" },
 			wantErr: true,
 		},
+		{
+			name:    "tls cert without key",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.TLSCertFile = "cert.pem" },
+			wantErr: true,
+		},
+		{
+			name:    "tls cert and key",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.TLSCertFile = "cert.pem"; c.TLSKeyFile = "key.pem" },
+			wantErr: false,
+		},
+		{
+			name:    "fd eviction threshold of 1",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.FDEvictionThreshold = 1 },
+			wantErr: true,
+		},
+		{
+			name:    "invalid sniff timeout",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.SniffProtocols = true; c.SniffTimeout = 0 },
+			wantErr: true,
+		},
+		{
+			name:    "transparent port same as proxy port",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.TransparentPort = c.Port },
+			wantErr: true,
+		},
+		{
+			name:    "valid forward mode",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ForwardPort = 9000; c.ForwardTarget = "target.example.com:443" },
+			wantErr: false,
+		},
+		{
+			name:    "forward port without target",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ForwardPort = 9000 },
+			wantErr: true,
+		},
+		{
+			name:    "public status port same as metrics port",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.PublicStatusPort = c.MetricsPort },
+			wantErr: true,
+		},
+		{
+			name:    "forward port same as socks5 port",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.SOCKS5Port = 1080; c.ForwardPort = 1080; c.ForwardTarget = "10.0.0.1:80" },
+			wantErr: true,
+		},
+		{
+			name:    "negative keep-warm interval",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.KeepWarmInterval = -time.Second },
+			wantErr: true,
+		},
+		{
+			name:    "keep-warm target without port",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.KeepWarmInterval = time.Minute; c.KeepWarmTarget = "1.1.1.1" },
+			wantErr: true,
+		},
+		{
+			name:    "negative drain period",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.DrainPeriod = -time.Minute },
+			wantErr: true,
+		},
+		{
+			name:    "circuit breaker without failure threshold",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.CircuitBreakerEnabled = true; c.CBFailureThreshold = 0 },
+			wantErr: true,
+		},
+		{
+			name:    "circuit breaker without timeout",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.CircuitBreakerEnabled = true; c.CBTimeout = 0 },
+			wantErr: true,
+		},
+		{
+			name:    "valid exit ip check",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ExitIPCheckURL = "https://api.ipify.org" },
+			wantErr: false,
+		},
+		{
+			name:    "exit ip check url without scheme",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ExitIPCheckURL = "api.ipify.org" },
+			wantErr: true,
+		},
+		{
+			name: "valid fair share",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.FairShareThreshold = 0.8
+				c.FairShareWeights = []FairShareWeight{{User: "alice", Weight: 2}, {User: "10.0.0.5", Weight: 0.5}}
+			},
+			wantErr: false,
+		},
+		{
+			name:    "fair share threshold above one",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.FairShareThreshold = 1.5 },
+			wantErr: true,
+		},
+		{
+			name:    "fair share weight not positive",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.FairShareWeights = []FairShareWeight{{User: "alice"}} },
+			wantErr: true,
+		},
+		{
+			name:    "negative connect retries",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ConnectRetries = -1 },
+			wantErr: true,
+		},
+		{
+			name: "valid connect retry policy",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.ConnectRetryBackoff = 50 * time.Millisecond
+				c.ConnectRetryOn = []string{"Refused", " timeout"}
+			},
+			wantErr: false,
+		},
+		{
+			name: "connect retry max backoff below backoff",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.ConnectRetryBackoff = 2 * time.Second
+				c.ConnectRetryMaxBackoff = time.Second
+			},
+			wantErr: true,
+		},
+		{
+			name:    "connect retry jitter above one",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ConnectRetryJitter = 1.5 },
+			wantErr: true,
+		},
+		{
+			name:    "negative connect hedge delay",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ConnectHedgeDelay = -time.Millisecond },
+			wantErr: true,
+		},
+		{
+			name:    "unknown connect retry class",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ConnectRetryOn = []string{"dns"} },
+			wantErr: true,
+		},
+		{
+			name:    "negative auth cache ttl",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AuthCacheTTL = -time.Second },
+			wantErr: true,
+		},
+		{
+			name:    "auth webhook",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AuthWebhook = "https://id.example.com/proxy-auth" },
+			wantErr: false,
+		},
+		{
+			name:    "auth webhook with auth file",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AuthWebhook = "https://id.example.com/proxy-auth"; c.AuthFile = "/etc/users" },
+			wantErr: true,
+		},
+		{
+			name:    "auth webhook not http",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AuthWebhook = "id.example.com/proxy-auth" },
+			wantErr: true,
+		},
+		{
+			name:    "auth pam service",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AuthPAMService = "outbound-lb" },
+			wantErr: false,
+		},
+		{
+			name:    "auth pam service with auth file",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AuthPAMService = "outbound-lb"; c.AuthFile = "/etc/users" },
+			wantErr: true,
+		},
+		{
+			name:    "auth pam service path",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AuthPAMService = "/etc/pam.d/login" },
+			wantErr: true,
+		},
+		{
+			name:    "anomaly detection with webhook",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AnomalyInterval = 10 * time.Second; c.AnomalyWebhook = "https://hooks.example.com/lb" },
+			wantErr: false,
+		},
+		{
+			name:    "anomaly webhook without interval",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AnomalyWebhook = "https://hooks.example.com/lb" },
+			wantErr: true,
+		},
+		{
+			name:    "non-positive anomaly threshold",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AnomalyInterval = 10 * time.Second; c.AnomalyThreshold = 0 },
+			wantErr: true,
+		},
+		{
+			name:    "client allow and deny lists",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ClientAllow = []string{"10.0.0.0/8"}; c.ClientDeny = []string{"10.0.0.66"} },
+			wantErr: false,
+		},
+		{
+			name:    "invalid client deny entry",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ClientDeny = []string{"10.0.0.0/33"} },
+			wantErr: true,
+		},
+		{
+			name:    "client ca with tls",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.TLSCertFile = "tls.crt"; c.TLSKeyFile = "tls.key"; c.TLSClientCAFile = "clients.pem"; c.TLSClientIdentity = "uri" },
+			wantErr: false,
+		},
+		{
+			name:    "client ca without tls",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.TLSClientCAFile = "clients.pem" },
+			wantErr: true,
+		},
+		{
+			name:    "optional client cert without ca",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.TLSCertFile = "tls.crt"; c.TLSKeyFile = "tls.key"; c.TLSClientCertOptional = true },
+			wantErr: true,
+		},
+		{
+			name:    "invalid client identity",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.TLSClientIdentity = "serial" },
+			wantErr: true,
+		},
+		{
+			name:    "jwt with jwks url",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.JWTJWKSURL = "https://idp.example.com/.well-known/jwks.json"; c.JWTAudience = "proxy" },
+			wantErr: false,
+		},
+		{
+			name:    "invalid jwks url",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.JWTJWKSURL = "file:///etc/jwks.json" },
+			wantErr: true,
+		},
+		{
+			name:    "jwt issuer without key",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.JWTIssuer = "https://idp.example.com" },
+			wantErr: true,
+		},
+		{
+			name:    "negative session affinity ttl",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.SessionAffinityTTL = -time.Second },
+			wantErr: true,
+		},
+		{
+			name:    "negative destination affinity ttl",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.DestinationAffinityTTL = -time.Second },
+			wantErr: true,
+		},
+		{
+			name:    "invalid session affinity scope",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.SessionAffinityScope = "path" },
+			wantErr: true,
+		},
+		{
+			name:    "session affinity by domain",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.SessionAffinityTTL = time.Minute; c.SessionAffinityScope = "domain" },
+			wantErr: false,
+		},
+		{
+			name: "user rotation policies",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.UserRotation = []UserRotation{{User: "alice", Policy: RotationPerRequest}, {User: "bob", Policy: RotationStickyUntilError, TTL: time.Hour}}
+			},
+			wantErr: false,
+		},
+		{
+			name:    "unknown user rotation policy",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.UserRotation = []UserRotation{{User: "alice", Policy: "random"}} },
+			wantErr: true,
+		},
+		{
+			name:    "sticky user rotation without ttl",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.UserRotation = []UserRotation{{User: "alice", Policy: RotationSticky}} },
+			wantErr: true,
+		},
+		{
+			name: "duplicate user rotation",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.UserRotation = []UserRotation{{User: "alice", Policy: RotationPerRequest}, {User: "alice", Policy: RotationSticky, TTL: time.Minute}}
+			},
+			wantErr: true,
+		},
+		{
+			name: "user limits",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.UserLimits = []UserLimit{{User: "alice", Rate: 10, MaxConns: 50, MonthlyBytes: 100 << 30}}
+			},
+			wantErr: false,
+		},
+		{
+			name:    "negative user limit",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.UserLimits = []UserLimit{{User: "alice", MaxConns: -1}} },
+			wantErr: true,
+		},
+		{
+			name:    "duplicate user limit",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.UserLimits = []UserLimit{{User: "alice", Rate: 1}, {User: "alice", Rate: 2}} },
+			wantErr: true,
+		},
+		{
+			name:    "negative username session ttl",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.UsernameSessionTTL = -time.Second },
+			wantErr: true,
+		},
+		{
+			name:    "auth with auth file",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.Auth = "user:pass"; c.AuthFile = "/etc/outbound-lb/users" },
+			wantErr: true,
+		},
+		{
+			name:    "valid protocol destinations",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.HTTP1Destinations = []string{"Legacy.Example.com."}; c.HTTP2Destinations = []string{"*.cdn.example.com"} },
+			wantErr: false,
+		},
+		{
+			name:    "destination in both protocol lists",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.HTTP1Destinations = []string{"api.example.com"}; c.HTTP2Destinations = []string{"API.example.com"} },
+			wantErr: true,
+		},
+		{
+			name:    "invalid http1 destination",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.HTTP1Destinations = []string{"example.com/path"} },
+			wantErr: true,
+		},
+		{
+			name:    "quarantine block rate above one",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.QuarantineBlockRate = 1.5 },
+			wantErr: true,
+		},
+		{
+			name:    "quarantine without window",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.QuarantineBlockRate = 0.5; c.QuarantineWindow = 0 },
+			wantErr: true,
+		},
+		{
+			name:    "negative maintenance retry after",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.MaintenanceRetryAfter = -time.Second },
+			wantErr: true,
+		},
+		{
+			name:    "negative shutdown grace period",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ShutdownGracePeriod = -time.Second },
+			wantErr: true,
+		},
+		{
+			name:    "negative connect timeout",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ConnectTimeout = -time.Second },
+			wantErr: true,
+		},
+		{
+			name:    "negative tunnel max lifetime",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.TunnelMaxLifetime = -time.Second },
+			wantErr: true,
+		},
+		{
+			name:    "rtt degrade factor not above 1",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.RTTSampleInterval = time.Second; c.RTTDegradeFactor = 1 },
+			wantErr: true,
+		},
+		{
+			name:    "negative tunnel progress interval",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.TunnelProgressInterval = -time.Second },
+			wantErr: true,
+		},
+		{
+			name: "health state file without check interval",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.HealthStateFile = "/tmp/health.json"
+				c.HealthCheckInterval = 0
+			},
+			wantErr: true,
+		},
+		{
+			name:    "valid outlier detection",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.OutlierErrorRate = 0.5 },
+			wantErr: false,
+		},
+		{
+			name:    "outlier error rate above one",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.OutlierErrorRate = 1.5 },
+			wantErr: true,
+		},
+		{
+			name:    "outlier detection without min requests",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.OutlierErrorRate = 0.5; c.OutlierMinRequests = 0 },
+			wantErr: true,
+		},
+		{
+			name:    "outlier detection without ejection time",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.OutlierErrorRate = 0.5; c.OutlierEjectionTime = 0 },
+			wantErr: true,
+		},
+		{
+			name:    "valid allowed destinations",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AllowedDestinations = []string{"api.example.com", "*.cdn.example.com", "::1"} },
+			wantErr: false,
+		},
+		{
+			name:    "invalid allowed destination",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AllowedDestinations = []string{"example.com:443"} },
+			wantErr: true,
+		},
+		{
+			name:    "valid denied destinations",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.DeniedDestinations = []string{"*.internal.example.com", `/^10-/`} },
+			wantErr: false,
+		},
+		{
+			name:    "invalid connect port range",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ConnectPorts = []string{"8100-8000"} },
+			wantErr: true,
+		},
+		{
+			name: "valid block response",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.BlockStatus = 451
+				c.BlockBody = "Blocked by {{.Rule}}, see {{.TicketURL}}"
+				c.BlockHeaders = []string{"X-Blocked-By: outbound-lb", "Cache-Control: no-store"}
+			},
+			wantErr: false,
+		},
+		{
+			name:    "block status out of range",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.BlockStatus = 200 },
+			wantErr: true,
+		},
+		{
+			name:    "invalid block body template",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.BlockBody = "{{.Rule" },
+			wantErr: true,
+		},
+		{
+			name:    "invalid block header",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.BlockHeaders = []string{"X-Blocked"} },
+			wantErr: true,
+		},
+		{
+			name:    "negative max upload bytes",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.MaxUploadBytes = -1 },
+			wantErr: true,
+		},
+		{
+			name:    "invalid access log format",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AccessLogFormat = "combined" },
+			wantErr: true,
+		},
+		{
+			name:    "valid otlp endpoint",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.OTLPEndpoint = "http://collector:4318/v1/traces" },
+			wantErr: false,
+		},
+		{
+			name:    "invalid otlp endpoint",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.OTLPEndpoint = "collector:4317" },
+			wantErr: true,
+		},
+		{
+			name:    "valid log levels",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.LogLevels = []string{"relay=warn", "balancer = debug"} },
+			wantErr: false,
+		},
+		{
+			name:    "unknown log level module",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.LogLevels = []string{"socks=debug"} },
+			wantErr: true,
+		},
+		{
+			name:    "invalid module log level",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.LogLevels = []string{"relay"} },
+			wantErr: true,
+		},
+		{
+			name:    "valid allowed methods",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AllowedMethods = []string{"get", "HEAD", " POST"} },
+			wantErr: false,
+		},
+		{
+			name:    "invalid allowed method",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AllowedMethods = []string{"GET POST"} },
+			wantErr: true,
+		},
+		{
+			name:    "connect in allowed methods",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.AllowedMethods = []string{"GET", "CONNECT"} },
+			wantErr: true,
+		},
+		{
+			name: "valid routing rules",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.Rules = []RoutingRule{
+					{Name: "no-smtp", Match: RuleMatch{Ports: []string{"25"}}, Action: RuleDeny},
+					{Name: "partners", Match: RuleMatch{Hosts: []string{"*.partner.example"}, Hours: "22:00-06:00"}, Action: RuleUsePool, Pool: "partners"},
+					{Name: "office", Match: RuleMatch{ClientCIDRs: []string{"10.0.0.0/8"}}, Action: RuleSetBandwidth, BandwidthMbps: 5},
+				}
+			},
+			wantErr: false,
+		},
+		{
+			name: "routing rule with unknown action",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.Rules = []RoutingRule{{Name: "r", Action: "redirect"}}
+			},
+			wantErr: true,
+		},
+		{
+			name: "duplicate routing rule",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.Rules = []RoutingRule{{Name: "r", Action: RuleDeny}, {Name: "r", Action: RuleDeny}}
+			},
+			wantErr: true,
+		},
+		{
+			name: "routing rule with unknown timezone",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.Rules = []RoutingRule{{Name: "r", Match: RuleMatch{Days: []string{"mon-fri"}, Timezone: "Mars/Olympus_Mons"}, Action: RuleDeny}}
+			},
+			wantErr: true,
+		},
+		{
+			name: "routing rule with invalid hours",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.Rules = []RoutingRule{{Name: "r", Match: RuleMatch{Hours: "9-17"}, Action: RuleDeny}}
+			},
+			wantErr: true,
+		},
+		{
+			name:    "invalid pool header trusted",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.PoolHeader = "X-Outbound-Pool"; c.PoolHeaderTrusted = []string{"not-an-ip"} },
+			wantErr: true,
+		},
+		{
+			name:    "invalid private destinations allow",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.PrivateDestinationsAllow = []string{"10.0.0.0/33"} },
+			wantErr: true,
+		},
+		{
+			name:    "invalid denied destination pattern",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.DeniedDestinations = []string{"/[a-/"} },
+			wantErr: true,
+		},
+		{
+			name: "valid http health check",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.HealthCheckEnabled = true
+				c.HealthCheckType = "http"
+				c.HealthCheckTarget = "https://example.com/health"
+			},
+			wantErr: false,
+		},
+		{
+			name:    "invalid health check type",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.HealthCheckEnabled = true; c.HealthCheckType = "icmp" },
+			wantErr: true,
+		},
+		{
+			name:    "tcp health check target is a URL",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.HealthCheckEnabled = true; c.HealthCheckTarget = "http://example.com" },
+			wantErr: true,
+		},
+		{
+			name:    "expected status with tcp check",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.HealthCheckEnabled = true; c.HealthCheckExpectedStatus = 204 },
+			wantErr: true,
+		},
+		{
+			name:    "zero health check failure threshold",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.HealthCheckEnabled = true; c.HealthCheckFailureThreshold = 0 },
+			wantErr: true,
+		},
+		{
+			name:    "dns forwarder without dns servers",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.DNSForwarderPort = 5353 },
+			wantErr: true,
+		},
+		{
+			name: "invalid dns forwarder address",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.DNSServers = []string{"1.1.1.1"}
+				c.DNSForwarderPort = 5353
+				c.DNSForwarderAddress = "localhost"
+			},
+			wantErr: true,
+		},
+		{
+			name:    "invalid proxy protocol trusted cidr",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ProxyProtocolTrusted = []string{"10.0.0.0/33"} },
+			wantErr: true,
+		},
+		{
+			name:    "valid connect header policy",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ConnectLogHeaders = []string{"user-agent", "X-*"} },
+			wantErr: false,
+		},
+		{
+			name:    "invalid connect header name",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.ConnectRejectHeaders = []string{"X-Bad Header"} },
+			wantErr: true,
+		},
+		{
+			name: "connect header both required and rejected",
+			modify: func(c *Config) {
+				c.IPs = []string{"192.168.1.1"}
+				c.ConnectRequireHeaders = []string{"x-client-id"}
+				c.ConnectRejectHeaders = []string{"X-Client-Id"}
+			},
+			wantErr: true,
+		},
+		{
+			name:    "invalid dns server",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.DNSServers = []string{"not a server"} },
+			wantErr: true,
+		},
+		{
+			name:    "bare dns server IP",
+			modify:  func(c *Config) { c.IPs = []string{"192.168.1.1"}; c.DNSServers = []string{"1.1.1.1"} },
+			wantErr: false,
+		},
 	}
 
 	for _, tt := range tests {
@@ -159,6 +782,25 @@ func TestConfigGetAuthCredentials(t *testing.T) {
 	}
 }
 
+func TestConfigEffectiveTimeouts(t *testing.T) {
+	cfg := &Config{Timeout: 30 * time.Second, IdleTimeout: time.Minute}
+	if got := cfg.EffectiveConnectTimeout(); got != cfg.Timeout {
+		t.Errorf("EffectiveConnectTimeout() = %v, want %v", got, cfg.Timeout)
+	}
+	if got := cfg.EffectiveTunnelIdleTimeout(); got != cfg.IdleTimeout {
+		t.Errorf("EffectiveTunnelIdleTimeout() = %v, want %v", got, cfg.IdleTimeout)
+	}
+
+	cfg.ConnectTimeout = 5 * time.Second
+	cfg.TunnelIdleTimeout = 10 * time.Minute
+	if got := cfg.EffectiveConnectTimeout(); got != 5*time.Second {
+		t.Errorf("EffectiveConnectTimeout() = %v, want 5s", got)
+	}
+	if got := cfg.EffectiveTunnelIdleTimeout(); got != 10*time.Minute {
+		t.Errorf("EffectiveTunnelIdleTimeout() = %v, want 10m", got)
+	}
+}
+
 func TestLoadFromFile(t *testing.T) {
 	// Create temp config file
 	tmpDir := t.TempDir()
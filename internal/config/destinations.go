@@ -0,0 +1,76 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"net"
+	"regexp"
+	"strings"
+
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// validateAllowedDestinations checks the destination allow and deny lists and
+// normalizes entries to lowercase without a trailing dot.
+func (c *Config) validateAllowedDestinations() error {
+	if err := normalizeDestinations("allowed-destinations", c.AllowedDestinations); err != nil {
+		return err
+	}
+	return normalizeDestinations("denied-destinations", c.DeniedDestinations)
+}
+
+// validateProtocolDestinations checks the per-destination protocol lists and
+// refuses a host listed in both.
+func (c *Config) validateProtocolDestinations() error {
+	if err := normalizeDestinations("http1-destinations", c.HTTP1Destinations); err != nil {
+		return err
+	}
+	if err := normalizeDestinations("http2-destinations", c.HTTP2Destinations); err != nil {
+		return err
+	}
+	for _, d := range c.HTTP2Destinations {
+		if containsString(c.HTTP1Destinations, d) {
+			return fmt.Errorf("%s is listed in both http1-destinations and http2-destinations", d)
+		}
+	}
+	return nil
+}
+
+// normalizeDestinations validates host patterns in place, rewriting them in
+// their netutil.NormalizeHost form so Unicode entries match punycode requests
+// and vice versa. Entries between slashes are regular expressions, matched
+// against the normalized host and kept as written. flag names the option in errors.
+func normalizeDestinations(flag string, destinations []string) error {
+	for i, d := range destinations {
+		d = strings.TrimSpace(d)
+		if expr, ok := DestinationRegexp(d); ok {
+			if _, err := regexp.Compile(expr); err != nil {
+				return fmt.Errorf("%s: invalid destination pattern %q: %w", flag, destinations[i], err)
+			}
+			destinations[i] = d
+			continue
+		}
+		name, wildcard := strings.CutPrefix(d, "*.")
+		if name == "" || (net.ParseIP(name) == nil && strings.ContainsAny(name, " \t/:*")) {
+			return fmt.Errorf("%s: invalid destination %q", flag, destinations[i])
+		}
+		name = netutil.NormalizeHost(name)
+		if name == "" {
+			return fmt.Errorf("%s: invalid destination %q", flag, destinations[i])
+		}
+		if wildcard {
+			name = "*." + name
+		}
+		destinations[i] = name
+	}
+	return nil
+}
+
+// DestinationRegexp returns the expression of a "/regexp/" destination entry
+// and whether entry is one.
+func DestinationRegexp(entry string) (string, bool) {
+	if len(entry) < 3 || entry[0] != '/' || entry[len(entry)-1] != '/' {
+		return "", false
+	}
+	return entry[1 : len(entry)-1], true
+}
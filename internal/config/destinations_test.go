@@ -0,0 +1,31 @@
+package config
+
+import (
+	"slices"
+	"testing"
+)
+
+func TestNormalizeDestinations(t *testing.T) {
+	destinations := []string{" API.Example.com. ", "*.Bücher.de", "xn--bcher-kva.de", "::1"}
+	if err := normalizeDestinations("allowed-destinations", destinations); err != nil {
+		t.Fatalf("normalizeDestinations() error = %v", err)
+	}
+	want := []string{"api.example.com", "*.xn--bcher-kva.de", "xn--bcher-kva.de", "::1"}
+	if !slices.Equal(destinations, want) {
+		t.Errorf("normalizeDestinations() = %q, want %q", destinations, want)
+	}
+
+	regexps := []string{`/^api[0-9]+\.example\.com$/`}
+	if err := normalizeDestinations("denied-destinations", regexps); err != nil {
+		t.Fatalf("normalizeDestinations() error = %v", err)
+	}
+	if regexps[0] != `/^api[0-9]+\.example\.com$/` {
+		t.Errorf("normalizeDestinations() rewrote a pattern to %q", regexps[0])
+	}
+
+	for _, d := range []string{".", "*.", "example.com:443", "exa mple.com", "*.*.example.com", "/api(/", "//"} {
+		if err := normalizeDestinations("allowed-destinations", []string{d}); err == nil {
+			t.Errorf("normalizeDestinations(%q) expected error", d)
+		}
+	}
+}
@@ -0,0 +1,52 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"net"
+)
+
+// defaultDNSPort is appended to DNS servers configured without a port.
+const defaultDNSPort = "53"
+
+// validateDNS checks the DNS settings and normalizes servers to host:port.
+func (c *Config) validateDNS() error {
+	for i, server := range c.DNSServers {
+		if net.ParseIP(server) != nil {
+			c.DNSServers[i] = net.JoinHostPort(server, defaultDNSPort)
+			continue
+		}
+		host, port, err := net.SplitHostPort(server)
+		if err != nil || host == "" || port == "" {
+			return fmt.Errorf("invalid dns server: %s (must be ip or host:port)", server)
+		}
+	}
+
+	if c.DNSCacheTTL < 0 {
+		return fmt.Errorf("dns-cache-ttl must not be negative")
+	}
+
+	if c.DNSCacheTTL > 0 && c.DNSCacheSize < 1 {
+		return fmt.Errorf("dns-cache-size must be at least 1 when caching is enabled")
+	}
+
+	if c.DNSForwarderPort < 0 || c.DNSForwarderPort > 65535 {
+		return fmt.Errorf("invalid dns forwarder port: %d", c.DNSForwarderPort)
+	}
+
+	if c.DNSForwarderPort != 0 {
+		if len(c.DNSServers) == 0 {
+			return fmt.Errorf("dns-forwarder-port requires dns-servers")
+		}
+		if c.DNSForwarderAddress != "" && net.ParseIP(c.DNSForwarderAddress) == nil {
+			return fmt.Errorf("invalid dns forwarder address: %s (must be an ip)", c.DNSForwarderAddress)
+		}
+		for _, port := range []int{c.Port, c.MetricsPort, c.SOCKS5Port, c.TransparentPort} {
+			if c.DNSForwarderPort == port {
+				return fmt.Errorf("dns forwarder port must differ from the other listener ports")
+			}
+		}
+	}
+
+	return nil
+}
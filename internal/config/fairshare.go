@@ -0,0 +1,42 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import "fmt"
+
+// FairShareWeight sets the share weight of one user of the pool.
+type FairShareWeight struct {
+	// User is the proxy username (without routing suffixes) or, for unauthenticated clients, the client IP.
+	User string `yaml:"user"`
+	// Weight is the user's share relative to other users (default users have weight 1).
+	Weight float64 `yaml:"weight"`
+}
+
+// validateFairShare checks the fair-share threshold and weights.
+func (c *Config) validateFairShare() error {
+	if c.FairShareThreshold < 0 || c.FairShareThreshold > 1 {
+		return fmt.Errorf("fair-share-threshold must be between 0 and 1")
+	}
+	seen := make(map[string]bool, len(c.FairShareWeights))
+	for _, w := range c.FairShareWeights {
+		if w.User == "" {
+			return fmt.Errorf("fair share weight: user is required")
+		}
+		if w.Weight <= 0 {
+			return fmt.Errorf("fair share weight %s: weight must be positive", w.User)
+		}
+		if seen[w.User] {
+			return fmt.Errorf("duplicate fair share weight: %s", w.User)
+		}
+		seen[w.User] = true
+	}
+	return nil
+}
+
+// FairShareWeightMap returns the configured share weights keyed by user.
+func (c *Config) FairShareWeightMap() map[string]float64 {
+	weights := make(map[string]float64, len(c.FairShareWeights))
+	for _, w := range c.FairShareWeights {
+		weights[w.User] = w.Weight
+	}
+	return weights
+}
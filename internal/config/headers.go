@@ -0,0 +1,39 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"net/http"
+	"strings"
+)
+
+// validateConnectHeaders checks the CONNECT header policy and canonicalizes the names.
+func (c *Config) validateConnectHeaders() error {
+	for _, list := range []struct {
+		name    string
+		headers []string
+	}{
+		{"connect-log-headers", c.ConnectLogHeaders},
+		{"connect-require-headers", c.ConnectRequireHeaders},
+		{"connect-reject-headers", c.ConnectRejectHeaders},
+	} {
+		for i, h := range list.headers {
+			h = strings.TrimSpace(h)
+			name := strings.TrimSuffix(h, "*")
+			if (name == "" && h != "*") || strings.ContainsAny(name, " \t:*") {
+				return fmt.Errorf("%s: invalid header name %q", list.name, h)
+			}
+			list.headers[i] = http.CanonicalHeaderKey(name) + h[len(name):]
+		}
+	}
+
+	for _, required := range c.ConnectRequireHeaders {
+		for _, rejected := range c.ConnectRejectHeaders {
+			if strings.EqualFold(required, rejected) {
+				return fmt.Errorf("header %s cannot be both required and rejected", required)
+			}
+		}
+	}
+
+	return nil
+}
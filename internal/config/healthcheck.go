@@ -0,0 +1,53 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"net"
+	"net/url"
+)
+
+// validateHealthCheck checks the active health check settings when they are enabled.
+func (c *Config) validateHealthCheck() error {
+	if !c.HealthCheckEnabled {
+		return nil
+	}
+	if c.HealthCheckInterval <= 0 {
+		return fmt.Errorf("health-check-interval must be positive")
+	}
+	if c.HealthCheckTimeout <= 0 {
+		return fmt.Errorf("health-check-timeout must be positive")
+	}
+	if c.HealthCheckFailureThreshold < 1 {
+		return fmt.Errorf("health-check-failure-threshold must be at least 1")
+	}
+	if c.HealthCheckSuccessThreshold < 1 {
+		return fmt.Errorf("health-check-success-threshold must be at least 1")
+	}
+	if c.HealthCheckExpectedStatus != 0 && (c.HealthCheckExpectedStatus < 100 || c.HealthCheckExpectedStatus > 599) {
+		return fmt.Errorf("invalid health-check-expected-status: %d", c.HealthCheckExpectedStatus)
+	}
+	if c.HealthCheckType != "http" && (c.HealthCheckExpectedStatus != 0 || c.HealthCheckExpectedBody != "") {
+		return fmt.Errorf("health-check-expected-status and health-check-expected-body require http checks")
+	}
+	return validateHealthCheckTarget(c.HealthCheckType, c.HealthCheckTarget)
+}
+
+// validateHealthCheckTarget checks that target suits the health check type:
+// host:port for tcp, an http(s) URL for http.
+func validateHealthCheckTarget(checkType, target string) error {
+	switch checkType {
+	case "tcp":
+		if host, port, err := net.SplitHostPort(target); err != nil || host == "" || port == "" {
+			return fmt.Errorf("health check target must be host:port for tcp checks: %q", target)
+		}
+	case "http":
+		u, err := url.Parse(target)
+		if err != nil || (u.Scheme != "http" && u.Scheme != "https") || u.Host == "" {
+			return fmt.Errorf("health check target must be an http(s) URL for http checks: %q", target)
+		}
+	default:
+		return fmt.Errorf("invalid health check type: %s (must be tcp or http)", checkType)
+	}
+	return nil
+}
@@ -0,0 +1,28 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"net/url"
+)
+
+// JWTEnabled reports whether clients may authenticate with bearer tokens.
+func (c *Config) JWTEnabled() bool {
+	return c.JWTSecret != "" || c.JWTJWKSURL != ""
+}
+
+// validateJWT checks the bearer token settings.
+func (c *Config) validateJWT() error {
+	if c.JWTJWKSURL != "" {
+		if u, err := url.Parse(c.JWTJWKSURL); err != nil || (u.Scheme != "http" && u.Scheme != "https") || u.Host == "" {
+			return fmt.Errorf("jwt-jwks-url must be an http(s) URL: %q", c.JWTJWKSURL)
+		}
+		if c.JWTJWKSRefresh < 0 {
+			return fmt.Errorf("jwt-jwks-refresh cannot be negative")
+		}
+	}
+	if (c.JWTIssuer != "" || c.JWTAudience != "") && !c.JWTEnabled() {
+		return fmt.Errorf("jwt-issuer and jwt-audience require jwt-secret or jwt-jwks-url")
+	}
+	return nil
+}
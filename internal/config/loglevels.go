@@ -0,0 +1,39 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"slices"
+	"strings"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+)
+
+// ModuleLogLevels returns LogLevels as a map of module to level.
+func (c *Config) ModuleLogLevels() map[string]string {
+	levels := make(map[string]string, len(c.LogLevels))
+	for _, entry := range c.LogLevels {
+		module, level, _ := strings.Cut(entry, "=")
+		levels[strings.TrimSpace(module)] = strings.TrimSpace(level)
+	}
+	return levels
+}
+
+// validateLogLevels checks that every "module=level" entry names a known
+// module and level.
+func (c *Config) validateLogLevels() error {
+	validLevels := map[string]bool{"trace": true, "debug": true, "info": true, "warn": true, "error": true}
+	for _, entry := range c.LogLevels {
+		module, level, ok := strings.Cut(entry, "=")
+		if !ok {
+			return fmt.Errorf("log-levels: invalid entry %q, want \"module=level\"", entry)
+		}
+		if module = strings.TrimSpace(module); !slices.Contains(logger.Modules, module) {
+			return fmt.Errorf("log-levels: unknown module %q (must be one of %s)", module, strings.Join(logger.Modules, ", "))
+		}
+		if !validLevels[strings.TrimSpace(level)] {
+			return fmt.Errorf("log-levels: invalid level %q for %s (must be trace, debug, info, warn, or error)", level, module)
+		}
+	}
+	return nil
+}
@@ -0,0 +1,24 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"errors"
+	"fmt"
+	"net/http"
+	"strings"
+)
+
+// validateAllowedMethods checks the HTTP method policy and uppercases the methods.
+func (c *Config) validateAllowedMethods() error {
+	for i, m := range c.AllowedMethods {
+		m = strings.ToUpper(strings.TrimSpace(m))
+		if m == "" || strings.ContainsAny(m, " \t\"(),/:;<=>?@[\\]{}") {
+			return fmt.Errorf("allowed-methods: invalid method %q", c.AllowedMethods[i])
+		}
+		if m == http.MethodConnect {
+			return errors.New("allowed-methods: CONNECT is controlled by connect-ports")
+		}
+		c.AllowedMethods[i] = m
+	}
+	return nil
+}
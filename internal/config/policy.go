@@ -0,0 +1,161 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"bytes"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"path/filepath"
+	"slices"
+	"time"
+
+	"github.com/fsnotify/fsnotify"
+	"gopkg.in/yaml.v3"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// policyDebounce collapses the burst of events a single save produces.
+const policyDebounce = 100 * time.Millisecond
+
+// Policy is the contents of a policy file: destination lists and routing
+// rules kept apart from the main configuration, so they can be updated and
+// reloaded on their own.
+type Policy struct {
+	// AllowedDestinations extends allowed_destinations.
+	AllowedDestinations []string `yaml:"allowed_destinations"`
+	// DeniedDestinations extends denied_destinations.
+	DeniedDestinations []string `yaml:"denied_destinations"`
+	// Rules are evaluated before the routing rules of the main configuration.
+	Rules []RoutingRule `yaml:"rules"`
+}
+
+// LoadPolicyFile reads the policy file at path and validates it against c.
+// Unknown keys are refused, so a typo can't silently drop a blocklist. An
+// empty file is an empty policy.
+func (c *Config) LoadPolicyFile(path string) (*Policy, error) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return nil, err
+	}
+	var p Policy
+	dec := yaml.NewDecoder(bytes.NewReader(data))
+	dec.KnownFields(true)
+	if err := dec.Decode(&p); err != nil && !errors.Is(err, io.EOF) {
+		return nil, fmt.Errorf("%s: %w", path, err)
+	}
+	if err := c.validatePolicy(&p); err != nil {
+		return nil, fmt.Errorf("%s: %w", path, err)
+	}
+	return &p, nil
+}
+
+// validatePolicy checks p's destination lists and rules, normalizing them in
+// place. Rule names must be unique across p and the main configuration.
+func (c *Config) validatePolicy(p *Policy) error {
+	if err := normalizeDestinations("allowed_destinations", p.AllowedDestinations); err != nil {
+		return err
+	}
+	if err := normalizeDestinations("denied_destinations", p.DeniedDestinations); err != nil {
+		return err
+	}
+	return c.checkRules(slices.Concat(p.Rules, c.Rules))
+}
+
+// PolicyWatcher reloads a policy file when it changes, so destination lists
+// and routing rules can be updated without a restart.
+type PolicyWatcher struct {
+	path    string
+	cfg     *Config
+	onLoad  func(*Policy)
+	watcher *fsnotify.Watcher
+	stopCh  chan struct{}
+}
+
+// NewPolicyWatcher creates a PolicyWatcher that passes every successfully
+// reloaded policy file at path, validated against cfg, to onLoad.
+func NewPolicyWatcher(path string, cfg *Config, onLoad func(*Policy)) (*PolicyWatcher, error) {
+	watcher, err := fsnotify.NewWatcher()
+	if err != nil {
+		return nil, err
+	}
+	return &PolicyWatcher{
+		path:    filepath.Clean(path),
+		cfg:     cfg,
+		onLoad:  onLoad,
+		watcher: watcher,
+		stopCh:  make(chan struct{}),
+	}, nil
+}
+
+// Start begins watching the policy file. Its directory is watched, so tools
+// that replace the file rather than write it in place are picked up too.
+func (w *PolicyWatcher) Start() error {
+	if err := w.watcher.Add(filepath.Dir(w.path)); err != nil {
+		return err
+	}
+	go w.watchLoop()
+	log.Info("policy_file_watcher_started", "path", w.path)
+	return nil
+}
+
+// Stop stops watching the policy file.
+func (w *PolicyWatcher) Stop() {
+	close(w.stopCh)
+	w.watcher.Close()
+}
+
+// Reload reads the policy file and passes it to onLoad. An invalid file is
+// rejected as a whole and the previous policy is kept.
+func (w *PolicyWatcher) Reload() error {
+	p, err := w.cfg.LoadPolicyFile(w.path)
+	if err != nil {
+		metrics.PolicyFileReloads.WithLabelValues("error").Inc()
+		return err
+	}
+	w.onLoad(p)
+	metrics.PolicyFileReloads.WithLabelValues("success").Inc()
+	log.Info("policy_file_reloaded", "path", w.path,
+		"allowed_destinations", len(p.AllowedDestinations),
+		"denied_destinations", len(p.DeniedDestinations),
+		"rules", len(p.Rules))
+	return nil
+}
+
+// watchLoop reloads the policy file, debounced, when it is written or replaced.
+func (w *PolicyWatcher) watchLoop() {
+	var debounceTimer *time.Timer
+	for {
+		select {
+		case event, ok := <-w.watcher.Events:
+			if !ok {
+				return
+			}
+			if filepath.Clean(event.Name) != w.path || event.Op&(fsnotify.Write|fsnotify.Create) == 0 {
+				continue
+			}
+			if debounceTimer != nil {
+				debounceTimer.Stop()
+			}
+			debounceTimer = time.AfterFunc(policyDebounce, func() {
+				if err := w.Reload(); err != nil {
+					log.Error("policy_file_reload_failed", "path", w.path, "error", err)
+				}
+			})
+
+		case err, ok := <-w.watcher.Errors:
+			if !ok {
+				return
+			}
+			log.Error("policy_file_watcher_error", "error", err)
+
+		case <-w.stopCh:
+			if debounceTimer != nil {
+				debounceTimer.Stop()
+			}
+			return
+		}
+	}
+}
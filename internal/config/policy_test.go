@@ -0,0 +1,89 @@
+package config
+
+import (
+	"os"
+	"path/filepath"
+	"slices"
+	"testing"
+	"time"
+)
+
+func TestLoadPolicyFile(t *testing.T) {
+	cfg := DefaultConfig()
+	cfg.Rules = []RoutingRule{{Name: "no-smtp", Match: RuleMatch{Ports: []string{"25"}}, Action: RuleDeny}}
+	dir := t.TempDir()
+	write := func(name, content string) string {
+		path := filepath.Join(dir, name)
+		if err := os.WriteFile(path, []byte(content), 0o600); err != nil {
+			t.Fatal(err)
+		}
+		return path
+	}
+
+	p, err := cfg.LoadPolicyFile(write("policy.yaml", `
+denied_destinations: ["Evil.Example.com.", "/^c2-/"]
+rules:
+  - name: partners
+    match: {hosts: ["*.partner.example"]}
+    action: use_pool
+    pool: partners
+`))
+	if err != nil {
+		t.Fatalf("LoadPolicyFile() error = %v", err)
+	}
+	if !slices.Equal(p.DeniedDestinations, []string{"evil.example.com", "/^c2-/"}) {
+		t.Errorf("denied destinations = %v, want normalized entries", p.DeniedDestinations)
+	}
+	if len(p.Rules) != 1 || p.Rules[0].Pool != "partners" {
+		t.Errorf("rules = %+v, want the partners rule", p.Rules)
+	}
+
+	if p, err := cfg.LoadPolicyFile(write("empty.yaml", "")); err != nil || len(p.Rules) != 0 {
+		t.Errorf("empty policy file: got %+v, %v; want an empty policy", p, err)
+	}
+
+	for name, content := range map[string]string{
+		"typo.yaml":      "denied_destination: [evil.example.com]\n",
+		"duplicate.yaml": "rules: [{name: no-smtp, action: deny}]\n",
+		"invalid.yaml":   "allowed_destinations: [\"example.com:443\"]\n",
+	} {
+		if _, err := cfg.LoadPolicyFile(write(name, content)); err == nil {
+			t.Errorf("LoadPolicyFile(%s) expected error", name)
+		}
+	}
+}
+
+func TestPolicyWatcher(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "policy.yaml")
+	if err := os.WriteFile(path, []byte("denied_destinations: [a.example.com]\n"), 0o600); err != nil {
+		t.Fatal(err)
+	}
+
+	loaded := make(chan *Policy, 4)
+	w, err := NewPolicyWatcher(path, DefaultConfig(), func(p *Policy) { loaded <- p })
+	if err != nil {
+		t.Fatal(err)
+	}
+	if err := w.Start(); err != nil {
+		t.Fatalf("Start() error = %v", err)
+	}
+	defer w.Stop()
+
+	// Replacing the file, as config managers do, reloads it
+	tmp := path + ".tmp"
+	if err := os.WriteFile(tmp, []byte("denied_destinations: [a.example.com, b.example.com]\n"), 0o600); err != nil {
+		t.Fatal(err)
+	}
+	if err := os.Rename(tmp, path); err != nil {
+		t.Fatal(err)
+	}
+
+	select {
+	case p := <-loaded:
+		if !slices.Equal(p.DeniedDestinations, []string{"a.example.com", "b.example.com"}) {
+			t.Errorf("reloaded denied destinations = %v", p.DeniedDestinations)
+		}
+	case <-time.After(5 * time.Second):
+		t.Fatal("policy file change was not picked up")
+	}
+}
@@ -0,0 +1,168 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"net"
+	"strings"
+)
+
+// Pool is a named set of outbound IPs that routing rules can refer to.
+type Pool struct {
+	// Name identifies the pool.
+	Name string `yaml:"name" json:"name"`
+	// IPs are the pool's outbound IPs. Must be listed in ips.
+	IPs []string `yaml:"ips" json:"ips"`
+}
+
+// ValidatePoolName checks that name can identify a pool.
+func ValidatePoolName(name string) error {
+	if name == "" || strings.ContainsAny(name, " \t,/") {
+		return fmt.Errorf("invalid pool name %q", name)
+	}
+	return nil
+}
+
+// validatePools checks that named pools are unique and hold configured IPs.
+func (c *Config) validatePools() error {
+	seen := make(map[string]bool, len(c.Pools))
+	for _, p := range c.Pools {
+		if err := ValidatePoolName(p.Name); err != nil {
+			return err
+		}
+		if seen[p.Name] {
+			return fmt.Errorf("duplicate pool: %s", p.Name)
+		}
+		seen[p.Name] = true
+		if len(p.IPs) == 0 {
+			return fmt.Errorf("pool %s has no ips", p.Name)
+		}
+		for _, ip := range p.IPs {
+			if !containsString(c.IPs, ip) {
+				return fmt.Errorf("pool %s: %s is not listed in ips", p.Name, ip)
+			}
+		}
+	}
+	return nil
+}
+
+// DestinationPool routes connections to a destination network through a subset
+// of the outbound IPs.
+type DestinationPool struct {
+	// CIDR is the destination network, e.g. "203.0.113.0/24".
+	CIDR string `yaml:"cidr"`
+	// IPs are the outbound IPs used for the network. Must be listed in ips.
+	IPs []string `yaml:"ips"`
+	// Country selects the outbound IPs tagged with this country instead of listing them.
+	Country string `yaml:"country"`
+	// Pool selects the outbound IPs of a named pool instead of listing them. The
+	// pool may be one created through the admin API.
+	Pool string `yaml:"pool"`
+}
+
+// validateDestinationPools checks that each pool has a valid network and
+// selects configured outbound IPs.
+func (c *Config) validateDestinationPools() error {
+	seen := make(map[string]bool, len(c.DestinationPools))
+	for _, p := range c.DestinationPools {
+		_, network, err := net.ParseCIDR(p.CIDR)
+		if err != nil {
+			return fmt.Errorf("destination pool: invalid cidr %q", p.CIDR)
+		}
+		set := 0
+		for _, ok := range []bool{len(p.IPs) > 0, p.Country != "", p.Pool != ""} {
+			if ok {
+				set++
+			}
+		}
+		if set != 1 {
+			return fmt.Errorf("destination pool %s: set exactly one of ips, country or pool", p.CIDR)
+		}
+		if p.Pool != "" {
+			if err := ValidatePoolName(p.Pool); err != nil {
+				return fmt.Errorf("destination pool %s: %w", p.CIDR, err)
+			}
+		}
+		for _, ip := range p.IPs {
+			if !containsString(c.IPs, ip) {
+				return fmt.Errorf("destination pool %s: %s is not listed in ips", p.CIDR, ip)
+			}
+		}
+		if p.Country != "" && len(c.IPsForCountry(p.Country)) == 0 {
+			return fmt.Errorf("destination pool %s: no backends tagged with country %q", p.CIDR, p.Country)
+		}
+		if seen[network.String()] {
+			return fmt.Errorf("duplicate destination pool: %s", p.CIDR)
+		}
+		seen[network.String()] = true
+	}
+	return nil
+}
+
+// PoolForDestination returns the most specific destination pool containing ip.
+func (c *Config) PoolForDestination(ip net.IP) (DestinationPool, bool) {
+	var (
+		best     DestinationPool
+		bestBits = -1
+	)
+	for _, p := range c.DestinationPools {
+		_, network, err := net.ParseCIDR(p.CIDR)
+		if err != nil || !network.Contains(ip) {
+			continue
+		}
+		if bits, _ := network.Mask.Size(); bits > bestBits {
+			best, bestBits = p, bits
+		}
+	}
+	return best, bestBits >= 0
+}
+
+// UserPool restricts a user's connections to a subset of the outbound IPs.
+type UserPool struct {
+	// User is the proxy username without routing suffixes.
+	User string `yaml:"user"`
+	// IPs are the outbound IPs the user may leave through. Must be listed in ips.
+	IPs []string `yaml:"ips"`
+	// Pool selects the outbound IPs of a named pool instead of listing them. The
+	// pool may be one created through the admin API.
+	Pool string `yaml:"pool"`
+}
+
+// validateUserPools checks that each user is assigned once, to either listed
+// outbound IPs or a named pool.
+func (c *Config) validateUserPools() error {
+	seen := make(map[string]bool, len(c.UserPools))
+	for _, p := range c.UserPools {
+		if p.User == "" {
+			return fmt.Errorf("user pool: user is required")
+		}
+		if (len(p.IPs) > 0) == (p.Pool != "") {
+			return fmt.Errorf("user pool %s: set exactly one of ips or pool", p.User)
+		}
+		if p.Pool != "" {
+			if err := ValidatePoolName(p.Pool); err != nil {
+				return fmt.Errorf("user pool %s: %w", p.User, err)
+			}
+		}
+		for _, ip := range p.IPs {
+			if !containsString(c.IPs, ip) {
+				return fmt.Errorf("user pool %s: %s is not listed in ips", p.User, ip)
+			}
+		}
+		if seen[p.User] {
+			return fmt.Errorf("duplicate user pool: %s", p.User)
+		}
+		seen[p.User] = true
+	}
+	return nil
+}
+
+// PoolForUser returns the pool assigned to user.
+func (c *Config) PoolForUser(user string) (UserPool, bool) {
+	for _, p := range c.UserPools {
+		if p.User == user {
+			return p, true
+		}
+	}
+	return UserPool{}, false
+}
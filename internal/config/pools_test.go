@@ -0,0 +1,159 @@
+package config
+
+import (
+	"net"
+	"testing"
+)
+
+func TestValidateDestinationPools(t *testing.T) {
+	tests := []struct {
+		name    string
+		pools   []DestinationPool
+		wantErr bool
+	}{
+		{
+			name:    "valid ips pool",
+			pools:   []DestinationPool{{CIDR: "203.0.113.0/24", IPs: []string{"192.168.1.1"}}},
+			wantErr: false,
+		},
+		{
+			name:    "valid country pool",
+			pools:   []DestinationPool{{CIDR: "2001:db8::/32", Country: "de"}},
+			wantErr: false,
+		},
+		{
+			name:    "invalid cidr",
+			pools:   []DestinationPool{{CIDR: "203.0.113.0", IPs: []string{"192.168.1.1"}}},
+			wantErr: true,
+		},
+		{
+			name:    "valid named pool",
+			pools:   []DestinationPool{{CIDR: "203.0.113.0/24", Pool: "partners"}},
+			wantErr: false,
+		},
+		{
+			name:    "both ips and pool",
+			pools:   []DestinationPool{{CIDR: "203.0.113.0/24", IPs: []string{"192.168.1.1"}, Pool: "partners"}},
+			wantErr: true,
+		},
+		{
+			name:    "neither ips nor country",
+			pools:   []DestinationPool{{CIDR: "203.0.113.0/24"}},
+			wantErr: true,
+		},
+		{
+			name:    "both ips and country",
+			pools:   []DestinationPool{{CIDR: "203.0.113.0/24", IPs: []string{"192.168.1.1"}, Country: "de"}},
+			wantErr: true,
+		},
+		{
+			name:    "IP not in ips",
+			pools:   []DestinationPool{{CIDR: "203.0.113.0/24", IPs: []string{"10.0.0.1"}}},
+			wantErr: true,
+		},
+		{
+			name:    "unknown country",
+			pools:   []DestinationPool{{CIDR: "203.0.113.0/24", Country: "fr"}},
+			wantErr: true,
+		},
+		{
+			name: "duplicate network",
+			pools: []DestinationPool{
+				{CIDR: "203.0.113.0/24", IPs: []string{"192.168.1.1"}},
+				{CIDR: "203.0.113.7/24", IPs: []string{"192.168.1.2"}},
+			},
+			wantErr: true,
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			cfg := DefaultConfig()
+			cfg.IPs = []string{"192.168.1.1", "192.168.1.2"}
+			cfg.Backends = []BackendConfig{{IP: "192.168.1.2", Country: "de"}}
+			cfg.DestinationPools = tt.pools
+			err := cfg.Validate()
+			if (err != nil) != tt.wantErr {
+				t.Errorf("Validate() error = %v, wantErr %v", err, tt.wantErr)
+			}
+		})
+	}
+}
+
+func TestValidatePools(t *testing.T) {
+	tests := []struct {
+		name    string
+		pools   []Pool
+		wantErr bool
+	}{
+		{"valid pool", []Pool{{Name: "residential", IPs: []string{"192.168.1.1"}}}, false},
+		{"empty name", []Pool{{IPs: []string{"192.168.1.1"}}}, true},
+		{"name with space", []Pool{{Name: "res idential", IPs: []string{"192.168.1.1"}}}, true},
+		{"no ips", []Pool{{Name: "residential"}}, true},
+		{"IP not in ips", []Pool{{Name: "residential", IPs: []string{"10.0.0.1"}}}, true},
+		{"duplicate pool", []Pool{{Name: "a", IPs: []string{"192.168.1.1"}}, {Name: "a", IPs: []string{"192.168.1.2"}}}, true},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			cfg := DefaultConfig()
+			cfg.IPs = []string{"192.168.1.1", "192.168.1.2"}
+			cfg.Pools = tt.pools
+			err := cfg.Validate()
+			if (err != nil) != tt.wantErr {
+				t.Errorf("Validate() error = %v, wantErr %v", err, tt.wantErr)
+			}
+		})
+	}
+}
+
+func TestValidateUserPools(t *testing.T) {
+	tests := []struct {
+		name    string
+		pools   []UserPool
+		wantErr bool
+	}{
+		{"valid ips", []UserPool{{User: "batch", IPs: []string{"192.168.1.1"}}}, false},
+		{"valid named pool", []UserPool{{User: "alice", Pool: "residential"}}, false},
+		{"empty user", []UserPool{{Pool: "residential"}}, true},
+		{"neither ips nor pool", []UserPool{{User: "alice"}}, true},
+		{"both ips and pool", []UserPool{{User: "alice", IPs: []string{"192.168.1.1"}, Pool: "residential"}}, true},
+		{"invalid pool name", []UserPool{{User: "alice", Pool: "res idential"}}, true},
+		{"IP not in ips", []UserPool{{User: "alice", IPs: []string{"10.0.0.1"}}}, true},
+		{"duplicate user", []UserPool{{User: "alice", Pool: "a"}, {User: "alice", Pool: "b"}}, true},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			cfg := DefaultConfig()
+			cfg.IPs = []string{"192.168.1.1", "192.168.1.2"}
+			cfg.UserPools = tt.pools
+			err := cfg.Validate()
+			if (err != nil) != tt.wantErr {
+				t.Errorf("Validate() error = %v, wantErr %v", err, tt.wantErr)
+			}
+		})
+	}
+}
+
+func TestPoolForDestination(t *testing.T) {
+	cfg := DefaultConfig()
+	cfg.IPs = []string{"10.0.0.1", "10.0.0.2", "10.0.0.3"}
+	cfg.Backends = []BackendConfig{{IP: "10.0.0.3", Country: "de"}}
+	cfg.DestinationPools = []DestinationPool{
+		{CIDR: "203.0.113.0/24", IPs: []string{"10.0.0.1", "10.0.0.2"}},
+		{CIDR: "203.0.113.128/25", Country: "de"},
+	}
+
+	p, ok := cfg.PoolForDestination(net.ParseIP("203.0.113.5"))
+	if !ok || p.CIDR != "203.0.113.0/24" {
+		t.Errorf("203.0.113.5: got %+v %v, want the /24 pool", p, ok)
+	}
+	p, ok = cfg.PoolForDestination(net.ParseIP("203.0.113.200"))
+	if !ok || p.CIDR != "203.0.113.128/25" {
+		t.Errorf("203.0.113.200: got %+v %v, want the more specific /25 pool", p, ok)
+	}
+	if _, ok := cfg.PoolForDestination(net.ParseIP("198.51.100.1")); ok {
+		t.Error("198.51.100.1: expected no pool")
+	}
+}
@@ -0,0 +1,52 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"strconv"
+	"strings"
+)
+
+// PortRange is an inclusive range of TCP ports.
+type PortRange struct {
+	From int
+	To   int
+}
+
+// Contains reports whether port is in the range.
+func (r PortRange) Contains(port int) bool {
+	return port >= r.From && port <= r.To
+}
+
+// ParsePortRanges parses ports ("443") and inclusive ranges ("8000-8100").
+func ParsePortRanges(entries []string) ([]PortRange, error) {
+	ranges := make([]PortRange, 0, len(entries))
+	for _, entry := range entries {
+		entry = strings.TrimSpace(entry)
+		from, to, isRange := strings.Cut(entry, "-")
+		if !isRange {
+			to = from
+		}
+		lo, errLo := strconv.Atoi(strings.TrimSpace(from))
+		hi, errHi := strconv.Atoi(strings.TrimSpace(to))
+		if errLo != nil || errHi != nil || lo < 1 || hi > 65535 || lo > hi {
+			return nil, fmt.Errorf("invalid port or range %q", entry)
+		}
+		ranges = append(ranges, PortRange{From: lo, To: hi})
+	}
+	return ranges, nil
+}
+
+// ConnectPortRanges returns the ports CONNECT may target; empty allows every port.
+func (c *Config) ConnectPortRanges() []PortRange {
+	ranges, _ := ParsePortRanges(c.ConnectPorts) // validated with the config
+	return ranges
+}
+
+// validateConnectPorts checks the CONNECT port policy.
+func (c *Config) validateConnectPorts() error {
+	if _, err := ParsePortRanges(c.ConnectPorts); err != nil {
+		return fmt.Errorf("connect-ports: %w", err)
+	}
+	return nil
+}
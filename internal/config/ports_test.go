@@ -0,0 +1,23 @@
+package config
+
+import (
+	"slices"
+	"testing"
+)
+
+func TestParsePortRanges(t *testing.T) {
+	ranges, err := ParsePortRanges([]string{"443", " 8000-8100 ", "1-65535"})
+	if err != nil {
+		t.Fatalf("ParsePortRanges() error = %v", err)
+	}
+	want := []PortRange{{443, 443}, {8000, 8100}, {1, 65535}}
+	if !slices.Equal(ranges, want) {
+		t.Errorf("ParsePortRanges() = %v, want %v", ranges, want)
+	}
+
+	for _, entry := range []string{"", "0", "65536", "https", "100-50", "1-2-3", "-443"} {
+		if _, err := ParsePortRanges([]string{entry}); err == nil {
+			t.Errorf("ParsePortRanges(%q) expected error", entry)
+		}
+	}
+}
@@ -0,0 +1,57 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"strings"
+)
+
+// Dial error classes that connect-retry-on can list.
+const (
+	// RetryOnRefused is a connection refused by the target.
+	RetryOnRefused = "refused"
+	// RetryOnTimeout is a dial that did not complete within its timeout.
+	RetryOnTimeout = "timeout"
+	// RetryOnUnreachable is a host or network unreachable error.
+	RetryOnUnreachable = "unreachable"
+	// RetryOnReset is a connection reset during the dial.
+	RetryOnReset = "reset"
+	// RetryOnBind is a failure to bind the outbound IP, e.g. after it was removed from the host.
+	RetryOnBind = "bind"
+	// RetryOnOther is any other dial error, such as a DNS failure.
+	RetryOnOther = "other"
+)
+
+// retryClasses lists the valid connect-retry-on values.
+var retryClasses = []string{RetryOnRefused, RetryOnTimeout, RetryOnUnreachable, RetryOnReset, RetryOnBind, RetryOnOther}
+
+// validateConnectRetry checks the CONNECT retry policy and normalizes the
+// retryable error classes to lowercase.
+func (c *Config) validateConnectRetry() error {
+	if c.ConnectRetries < 0 {
+		return fmt.Errorf("connect-retries must not be negative")
+	}
+	if c.ConnectRetryTimeout < 0 {
+		return fmt.Errorf("connect-retry-timeout must not be negative")
+	}
+	if c.ConnectRetryBackoff < 0 {
+		return fmt.Errorf("connect-retry-backoff must not be negative")
+	}
+	if c.ConnectRetryBackoff > 0 && c.ConnectRetryMaxBackoff < c.ConnectRetryBackoff {
+		return fmt.Errorf("connect-retry-max-backoff must be at least connect-retry-backoff")
+	}
+	if c.ConnectHedgeDelay < 0 {
+		return fmt.Errorf("connect-hedge-delay must not be negative")
+	}
+	if c.ConnectRetryJitter < 0 || c.ConnectRetryJitter > 1 {
+		return fmt.Errorf("connect-retry-jitter must be between 0 and 1")
+	}
+	for i, class := range c.ConnectRetryOn {
+		class = strings.ToLower(strings.TrimSpace(class))
+		if !containsString(retryClasses, class) {
+			return fmt.Errorf("connect-retry-on: unknown error class %q (valid: %s)", c.ConnectRetryOn[i], strings.Join(retryClasses, ", "))
+		}
+		c.ConnectRetryOn[i] = class
+	}
+	return nil
+}
@@ -0,0 +1,256 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"slices"
+	"strings"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// Actions a routing rule can take.
+const (
+	// RuleUsePool sends matching connections through a named pool.
+	RuleUsePool = "use_pool"
+	// RuleDeny refuses matching connections.
+	RuleDeny = "deny"
+	// RuleSetStrategy gives matching connections a rotation policy.
+	RuleSetStrategy = "set_strategy"
+	// RuleSetBandwidth caps the bandwidth of each matching tunnel.
+	RuleSetBandwidth = "set_bandwidth"
+)
+
+// RoutingRule is one entry of the ordered routing rules (config file only).
+type RoutingRule struct {
+	// Name identifies the rule in logs and errors.
+	Name string `yaml:"name"`
+	// Match selects the connections the rule applies to.
+	Match RuleMatch `yaml:"match"`
+	// Action is one of the Rule* values.
+	Action string `yaml:"action"`
+	// Pool is the named pool of a use_pool rule.
+	Pool string `yaml:"pool"`
+	// Strategy is the rotation policy (one of the Rotation* values) of a set_strategy rule.
+	Strategy string `yaml:"strategy"`
+	// TTL is how long a sticky strategy keeps an outbound IP (default: session-affinity-ttl).
+	TTL time.Duration `yaml:"ttl"`
+	// BandwidthMbps is the per-tunnel cap of a set_bandwidth rule, in each direction.
+	BandwidthMbps float64 `yaml:"bandwidth_mbps"`
+}
+
+// RuleMatch lists the conditions of a routing rule. Every condition that is
+// set must hold; a rule without conditions matches every connection.
+type RuleMatch struct {
+	// Hosts are destination patterns as in allowed_destinations.
+	Hosts []string `yaml:"hosts"`
+	// Ports are destination ports and ranges ("8000-8100").
+	Ports []string `yaml:"ports"`
+	// Users are proxy usernames without routing suffixes.
+	Users []string `yaml:"users"`
+	// ClientCIDRs are client IPs/CIDRs.
+	ClientCIDRs []string `yaml:"client_cidrs"`
+	// Hours is a time-of-day window such as "09:00-17:00"; a window ending
+	// before it starts, such as "22:00-06:00", spans midnight.
+	Hours string `yaml:"hours"`
+	// Days are weekdays ("mon") and ranges ("mon-fri"). The part of an hours
+	// window after midnight belongs to the day the window started.
+	Days []string `yaml:"days"`
+	// Timezone is the IANA zone Hours and Days are read in (default: local time).
+	Timezone string `yaml:"timezone"`
+}
+
+// Schedule is when a routing rule applies.
+type Schedule struct {
+	// Hours is the time-of-day window; nil for the whole day.
+	Hours *TimeWindow
+	// Days are the weekdays the rule applies on; empty for every day.
+	Days []time.Weekday
+	// Location is the zone the schedule is read in.
+	Location *time.Location
+}
+
+// Contains reports whether t falls within the schedule.
+func (s Schedule) Contains(t time.Time) bool {
+	t = t.In(s.Location)
+	day := t.Weekday()
+	if s.Hours != nil {
+		if !s.Hours.Contains(t) {
+			return false
+		}
+		if s.Hours.From > s.Hours.To && t.Hour()*60+t.Minute() < s.Hours.To {
+			day = (day + 6) % 7
+		}
+	}
+	return len(s.Days) == 0 || slices.Contains(s.Days, day)
+}
+
+// Schedule parses the time conditions of m. It returns false when m has none.
+func (m RuleMatch) Schedule() (Schedule, bool, error) {
+	if m.Hours == "" && len(m.Days) == 0 && m.Timezone == "" {
+		return Schedule{}, false, nil
+	}
+	s := Schedule{Location: time.Local}
+	if m.Hours != "" {
+		w, err := ParseTimeWindow(m.Hours)
+		if err != nil {
+			return Schedule{}, false, err
+		}
+		s.Hours = &w
+	}
+	days, err := ParseWeekdays(m.Days)
+	if err != nil {
+		return Schedule{}, false, err
+	}
+	s.Days = days
+	if m.Timezone != "" {
+		loc, err := time.LoadLocation(m.Timezone)
+		if err != nil {
+			return Schedule{}, false, fmt.Errorf("invalid timezone %q: %w", m.Timezone, err)
+		}
+		s.Location = loc
+	}
+	return s, true, nil
+}
+
+// weekdays maps three-letter day names to weekdays.
+var weekdays = map[string]time.Weekday{
+	"sun": time.Sunday, "mon": time.Monday, "tue": time.Tuesday, "wed": time.Wednesday,
+	"thu": time.Thursday, "fri": time.Friday, "sat": time.Saturday,
+}
+
+// ParseWeekdays parses weekday names ("mon") and ranges ("mon-fri", or
+// "fri-mon" wrapping over the weekend), case-insensitively.
+func ParseWeekdays(entries []string) ([]time.Weekday, error) {
+	var days []time.Weekday
+	for _, e := range entries {
+		from, to, isRange := strings.Cut(strings.ToLower(strings.TrimSpace(e)), "-")
+		if !isRange {
+			to = from
+		}
+		start, okFrom := weekdays[from]
+		end, okTo := weekdays[to]
+		if !okFrom || !okTo {
+			return nil, fmt.Errorf("invalid day %q: want mon..sun or a range such as mon-fri", e)
+		}
+		for d := start; ; d = (d + 1) % 7 {
+			if !slices.Contains(days, d) {
+				days = append(days, d)
+			}
+			if d == end {
+				break
+			}
+		}
+	}
+	return days, nil
+}
+
+// TimeWindow is a time-of-day window, in minutes since midnight.
+type TimeWindow struct {
+	From int
+	To   int
+}
+
+// Contains reports whether t's local time of day is in the window. The start
+// is inclusive and the end exclusive.
+func (w TimeWindow) Contains(t time.Time) bool {
+	m := t.Hour()*60 + t.Minute()
+	if w.From <= w.To {
+		return m >= w.From && m < w.To
+	}
+	return m >= w.From || m < w.To
+}
+
+// ParseTimeWindow parses a "HH:MM-HH:MM" time-of-day window.
+func ParseTimeWindow(s string) (TimeWindow, error) {
+	from, to, ok := strings.Cut(strings.TrimSpace(s), "-")
+	if !ok {
+		return TimeWindow{}, fmt.Errorf("invalid time window %q: want HH:MM-HH:MM", s)
+	}
+	start, errFrom := time.Parse("15:04", strings.TrimSpace(from))
+	end, errTo := time.Parse("15:04", strings.TrimSpace(to))
+	if errFrom != nil || errTo != nil || start.Equal(end) {
+		return TimeWindow{}, fmt.Errorf("invalid time window %q: want HH:MM-HH:MM", s)
+	}
+	return TimeWindow{From: start.Hour()*60 + start.Minute(), To: end.Hour()*60 + end.Minute()}, nil
+}
+
+// validateRules checks the routing rules and normalizes their host patterns.
+func (c *Config) validateRules() error {
+	return c.checkRules(c.Rules)
+}
+
+// checkRules checks rules, whose names must be unique, and normalizes their
+// host patterns in place.
+func (c *Config) checkRules(rules []RoutingRule) error {
+	seen := make(map[string]bool, len(rules))
+	for _, r := range rules {
+		if r.Name == "" {
+			return fmt.Errorf("rule: name is required")
+		}
+		if seen[r.Name] {
+			return fmt.Errorf("duplicate rule: %s", r.Name)
+		}
+		seen[r.Name] = true
+		if err := r.Match.validate(r.Name); err != nil {
+			return err
+		}
+		if err := c.validateRuleAction(r); err != nil {
+			return fmt.Errorf("rule %s: %w", r.Name, err)
+		}
+	}
+	return nil
+}
+
+// validate checks the conditions of the rule named name.
+func (m RuleMatch) validate(name string) error {
+	if err := normalizeDestinations("rule "+name+" hosts", m.Hosts); err != nil {
+		return err
+	}
+	if _, err := ParsePortRanges(m.Ports); err != nil {
+		return fmt.Errorf("rule %s ports: %w", name, err)
+	}
+	for _, u := range m.Users {
+		if u == "" {
+			return fmt.Errorf("rule %s users: empty username", name)
+		}
+	}
+	if _, err := netutil.ParseCIDRs(m.ClientCIDRs); err != nil {
+		return fmt.Errorf("rule %s client_cidrs: %w", name, err)
+	}
+	if _, _, err := m.Schedule(); err != nil {
+		return fmt.Errorf("rule %s schedule: %w", name, err)
+	}
+	return nil
+}
+
+// validateRuleAction checks that r names a valid action with its parameters.
+func (c *Config) validateRuleAction(r RoutingRule) error {
+	switch r.Action {
+	case RuleUsePool:
+		return ValidatePoolName(r.Pool)
+	case RuleDeny:
+		return nil
+	case RuleSetStrategy:
+		if r.TTL < 0 {
+			return fmt.Errorf("ttl cannot be negative")
+		}
+		switch r.Strategy {
+		case RotationPerRequest:
+		case RotationSticky, RotationStickyUntilError:
+			if r.TTL == 0 && c.SessionAffinityTTL == 0 {
+				return fmt.Errorf("%s needs a ttl when session-affinity-ttl is 0", r.Strategy)
+			}
+		default:
+			return fmt.Errorf("strategy must be %q, %q or %q", RotationPerRequest, RotationSticky, RotationStickyUntilError)
+		}
+		return nil
+	case RuleSetBandwidth:
+		if r.BandwidthMbps <= 0 {
+			return fmt.Errorf("bandwidth_mbps must be positive")
+		}
+		return nil
+	}
+	return fmt.Errorf("action must be %q, %q, %q or %q", RuleUsePool, RuleDeny, RuleSetStrategy, RuleSetBandwidth)
+}
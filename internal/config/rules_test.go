@@ -0,0 +1,33 @@
+package config
+
+import (
+	"slices"
+	"testing"
+	"time"
+)
+
+func TestParseWeekdays(t *testing.T) {
+	tests := []struct {
+		entries []string
+		want    []time.Weekday
+	}{
+		{[]string{"mon-fri"}, []time.Weekday{time.Monday, time.Tuesday, time.Wednesday, time.Thursday, time.Friday}},
+		{[]string{"Fri-Mon"}, []time.Weekday{time.Friday, time.Saturday, time.Sunday, time.Monday}},
+		{[]string{"sat", "sun", "sat"}, []time.Weekday{time.Saturday, time.Sunday}},
+	}
+	for _, tt := range tests {
+		got, err := ParseWeekdays(tt.entries)
+		if err != nil {
+			t.Fatalf("ParseWeekdays(%v) error = %v", tt.entries, err)
+		}
+		if !slices.Equal(got, tt.want) {
+			t.Errorf("ParseWeekdays(%v) = %v, want %v", tt.entries, got, tt.want)
+		}
+	}
+
+	for _, entry := range []string{"", "monday", "mon-", "1-5"} {
+		if _, err := ParseWeekdays([]string{entry}); err == nil {
+			t.Errorf("ParseWeekdays(%q) expected error", entry)
+		}
+	}
+}
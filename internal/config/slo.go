@@ -0,0 +1,60 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"time"
+)
+
+// SLOConfig defines latency and error objectives for a route.
+type SLOConfig struct {
+	// Name identifies the objective in metrics and reports.
+	Name string `yaml:"name"`
+	// Hosts lists destination host patterns (exact or "*.example.com").
+	Hosts []string `yaml:"hosts"`
+	// LatencyTarget is the upstream response time a request must beat.
+	LatencyTarget time.Duration `yaml:"latency_target"`
+	// LatencyObjective is the target fraction of requests under LatencyTarget (e.g. 0.99).
+	LatencyObjective float64 `yaml:"latency_objective"`
+	// ErrorObjective is the target fraction of successful requests (e.g. 0.999).
+	ErrorObjective float64 `yaml:"error_objective"`
+	// Window is the rolling evaluation window (default: 1h).
+	Window time.Duration `yaml:"window"`
+}
+
+// DefaultSLOWindow is the evaluation window used when none is configured.
+const DefaultSLOWindow = time.Hour
+
+// validateSLOs checks the SLO definitions.
+func (c *Config) validateSLOs() error {
+	names := make(map[string]bool, len(c.SLOs))
+	for i := range c.SLOs {
+		s := &c.SLOs[i]
+		if s.Name == "" {
+			return fmt.Errorf("slo %d: name is required", i)
+		}
+		if names[s.Name] {
+			return fmt.Errorf("duplicate slo name: %s", s.Name)
+		}
+		names[s.Name] = true
+		if len(s.Hosts) == 0 {
+			return fmt.Errorf("slo %s: at least one host pattern is required", s.Name)
+		}
+		if s.LatencyTarget <= 0 {
+			return fmt.Errorf("slo %s: latency_target must be positive", s.Name)
+		}
+		if s.LatencyObjective <= 0 || s.LatencyObjective >= 1 {
+			return fmt.Errorf("slo %s: latency_objective must be between 0 and 1", s.Name)
+		}
+		if s.ErrorObjective <= 0 || s.ErrorObjective >= 1 {
+			return fmt.Errorf("slo %s: error_objective must be between 0 and 1", s.Name)
+		}
+		if s.Window < 0 {
+			return fmt.Errorf("slo %s: window must not be negative", s.Name)
+		}
+		if s.Window == 0 {
+			s.Window = DefaultSLOWindow
+		}
+	}
+	return nil
+}
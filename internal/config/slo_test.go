@@ -0,0 +1,69 @@
+package config
+
+import (
+	"testing"
+	"time"
+)
+
+func validSLO() SLOConfig {
+	return SLOConfig{
+		Name:             "api",
+		Hosts:            []string{"*.example.com"},
+		LatencyTarget:    500 * time.Millisecond,
+		LatencyObjective: 0.99,
+		ErrorObjective:   0.999,
+	}
+}
+
+func TestValidateSLOs(t *testing.T) {
+	tests := []struct {
+		name    string
+		modify  func(s *SLOConfig)
+		wantErr bool
+	}{
+		{"valid", func(s *SLOConfig) {}, false},
+		{"missing name", func(s *SLOConfig) { s.Name = "" }, true},
+		{"no hosts", func(s *SLOConfig) { s.Hosts = nil }, true},
+		{"zero latency target", func(s *SLOConfig) { s.LatencyTarget = 0 }, true},
+		{"latency objective of 1", func(s *SLOConfig) { s.LatencyObjective = 1 }, true},
+		{"zero error objective", func(s *SLOConfig) { s.ErrorObjective = 0 }, true},
+		{"negative window", func(s *SLOConfig) { s.Window = -time.Minute }, true},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			cfg := DefaultConfig()
+			cfg.IPs = []string{"192.168.1.1"}
+			s := validSLO()
+			tt.modify(&s)
+			cfg.SLOs = []SLOConfig{s}
+			err := cfg.Validate()
+			if (err != nil) != tt.wantErr {
+				t.Errorf("Validate() error = %v, wantErr %v", err, tt.wantErr)
+			}
+		})
+	}
+}
+
+func TestValidateSLOs_DuplicateName(t *testing.T) {
+	cfg := DefaultConfig()
+	cfg.IPs = []string{"192.168.1.1"}
+	cfg.SLOs = []SLOConfig{validSLO(), validSLO()}
+
+	if err := cfg.Validate(); err == nil {
+		t.Error("expected error for duplicate SLO names")
+	}
+}
+
+func TestValidateSLOs_DefaultWindow(t *testing.T) {
+	cfg := DefaultConfig()
+	cfg.IPs = []string{"192.168.1.1"}
+	cfg.SLOs = []SLOConfig{validSLO()}
+
+	if err := cfg.Validate(); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if cfg.SLOs[0].Window != DefaultSLOWindow {
+		t.Errorf("expected default window %v, got %v", DefaultSLOWindow, cfg.SLOs[0].Window)
+	}
+}
@@ -0,0 +1,58 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import (
+	"fmt"
+	"time"
+)
+
+// validateTimeouts checks the fine-grained timeouts. Zero means the general
+// timeout they refine applies.
+func (c *Config) validateTimeouts() error {
+	timeouts := []struct {
+		name  string
+		value time.Duration
+	}{
+		{"header-read-timeout", c.HeaderReadTimeout},
+		{"dns-timeout", c.DNSTimeout},
+		{"connect-timeout", c.ConnectTimeout},
+		{"tls-handshake-timeout", c.TLSHandshakeTimeout},
+		{"tunnel-idle-timeout", c.TunnelIdleTimeout},
+		{"tunnel-max-lifetime", c.TunnelMaxLifetime},
+		{"shutdown-grace-period", c.ShutdownGracePeriod},
+	}
+	for _, t := range timeouts {
+		if t.value < 0 {
+			return fmt.Errorf("%s must not be negative", t.name)
+		}
+	}
+	return nil
+}
+
+// EffectiveHeaderReadTimeout returns HeaderReadTimeout, or Timeout if unset.
+func (c *Config) EffectiveHeaderReadTimeout() time.Duration {
+	return orDuration(c.HeaderReadTimeout, c.Timeout)
+}
+
+// EffectiveDNSTimeout returns DNSTimeout, or Timeout if unset.
+func (c *Config) EffectiveDNSTimeout() time.Duration {
+	return orDuration(c.DNSTimeout, c.Timeout)
+}
+
+// EffectiveConnectTimeout returns ConnectTimeout, or Timeout if unset.
+func (c *Config) EffectiveConnectTimeout() time.Duration {
+	return orDuration(c.ConnectTimeout, c.Timeout)
+}
+
+// EffectiveTunnelIdleTimeout returns TunnelIdleTimeout, or IdleTimeout if unset.
+func (c *Config) EffectiveTunnelIdleTimeout() time.Duration {
+	return orDuration(c.TunnelIdleTimeout, c.IdleTimeout)
+}
+
+// orDuration returns d, or fallback if d is not positive.
+func orDuration(d, fallback time.Duration) time.Duration {
+	if d > 0 {
+		return d
+	}
+	return fallback
+}
@@ -0,0 +1,46 @@
+// Package config handles configuration parsing from CLI flags and YAML files.
+package config
+
+import "fmt"
+
+// UserLimit caps one user's request rate, concurrent connections and monthly
+// traffic. Zero fields are unlimited.
+type UserLimit struct {
+	// User is the proxy username (without routing suffixes) or, for unauthenticated clients, the client IP.
+	User string `yaml:"user"`
+	// Rate caps the user's HTTP requests per second.
+	Rate float64 `yaml:"rate"`
+	// MaxConns caps the user's concurrent connections and tunnels.
+	MaxConns int `yaml:"max_conns"`
+	// MonthlyBytes caps the bytes the user may relay per calendar month (UTC).
+	// Usage is kept in memory and starts over on restart.
+	MonthlyBytes int64 `yaml:"monthly_bytes"`
+}
+
+// validateUserLimits checks the per-user limits.
+func (c *Config) validateUserLimits() error {
+	seen := make(map[string]bool, len(c.UserLimits))
+	for _, l := range c.UserLimits {
+		if l.User == "" {
+			return fmt.Errorf("user limit: user is required")
+		}
+		if seen[l.User] {
+			return fmt.Errorf("duplicate user limit: %s", l.User)
+		}
+		seen[l.User] = true
+		if l.Rate < 0 || l.MaxConns < 0 || l.MonthlyBytes < 0 {
+			return fmt.Errorf("user limit %s: limits cannot be negative", l.User)
+		}
+	}
+	return nil
+}
+
+// LimitsForUser returns the limits configured for user, if any.
+func (c *Config) LimitsForUser(user string) (UserLimit, bool) {
+	for _, l := range c.UserLimits {
+		if l.User == user {
+			return l, true
+		}
+	}
+	return UserLimit{}, false
+}
@@ -2,6 +2,7 @@
 package config
 
 import (
+	"slices"
 	"sync"
 	"sync/atomic"
 	"time"
@@ -11,6 +12,9 @@ import (
 	"github.com/cr0hn/outbound-lb/internal/logger"
 )
 
+// log logs on behalf of the config module, so its level can be set on its own.
+var log = logger.Module("config")
+
 // ConfigWatcher watches a configuration file for changes and notifies callbacks.
 type ConfigWatcher struct {
 	path      string
@@ -45,7 +49,7 @@ func (w *ConfigWatcher) Start() error {
 	}
 
 	go w.watchLoop()
-	logger.Info("config_watcher_started", "path", w.path)
+	log.Info("config_watcher_started", "path", w.path)
 	return nil
 }
 
@@ -53,7 +57,7 @@ func (w *ConfigWatcher) Start() error {
 func (w *ConfigWatcher) Stop() {
 	close(w.stopCh)
 	w.watcher.Close()
-	logger.Info("config_watcher_stopped")
+	log.Info("config_watcher_stopped")
 }
 
 // Current returns the current configuration.
@@ -93,7 +97,7 @@ func (w *ConfigWatcher) watchLoop() {
 				}
 				debounceTimer = time.AfterFunc(debounceDuration, func() {
 					if err := w.reload(); err != nil {
-						logger.Error("config_reload_failed", "error", err)
+						log.Error("config_reload_failed", "error", err)
 					}
 				})
 			}
@@ -102,7 +106,7 @@ func (w *ConfigWatcher) watchLoop() {
 			if !ok {
 				return
 			}
-			logger.Error("config_watcher_error", "error", err)
+			log.Error("config_watcher_error", "error", err)
 
 		case <-w.stopCh:
 			if debounceTimer != nil {
@@ -141,7 +145,7 @@ func (w *ConfigWatcher) reload() error {
 		cb(newCfg)
 	}
 
-	logger.Info("config_reloaded", "path", w.path)
+	log.Info("config_reloaded", "path", w.path)
 	return nil
 }
 
@@ -158,6 +162,9 @@ func (w *ConfigWatcher) validateReloadable(cfg *Config) error {
 	if !validFormats[cfg.LogFormat] {
 		return &ValidationError{Field: "log_format", Message: "must be json or text"}
 	}
+	if err := cfg.validateLogLevels(); err != nil {
+		return err
+	}
 
 	// Validate limits
 	if cfg.MaxConnsPerIP < 1 {
@@ -181,39 +188,45 @@ func (w *ConfigWatcher) validateReloadable(cfg *Config) error {
 // logChanges logs which configuration values changed.
 func (w *ConfigWatcher) logChanges(old, new *Config) {
 	if old.LogLevel != new.LogLevel {
-		logger.Info("config_changed", "field", "log_level", "old", old.LogLevel, "new", new.LogLevel)
+		log.Info("config_changed", "field", "log_level", "old", old.LogLevel, "new", new.LogLevel)
 	}
 	if old.LogFormat != new.LogFormat {
-		logger.Info("config_changed", "field", "log_format", "old", old.LogFormat, "new", new.LogFormat)
+		log.Info("config_changed", "field", "log_format", "old", old.LogFormat, "new", new.LogFormat)
+	}
+	if !slices.Equal(old.LogLevels, new.LogLevels) {
+		log.Info("config_changed", "field", "log_levels", "old", old.LogLevels, "new", new.LogLevels)
 	}
 	if old.MaxConnsPerIP != new.MaxConnsPerIP {
-		logger.Info("config_changed", "field", "max_conns_per_ip", "old", old.MaxConnsPerIP, "new", new.MaxConnsPerIP)
+		log.Info("config_changed", "field", "max_conns_per_ip", "old", old.MaxConnsPerIP, "new", new.MaxConnsPerIP)
 	}
 	if old.MaxConnsTotal != new.MaxConnsTotal {
-		logger.Info("config_changed", "field", "max_conns_total", "old", old.MaxConnsTotal, "new", new.MaxConnsTotal)
+		log.Info("config_changed", "field", "max_conns_total", "old", old.MaxConnsTotal, "new", new.MaxConnsTotal)
 	}
 	if old.HistoryWindow != new.HistoryWindow {
-		logger.Info("config_changed", "field", "history_window", "old", old.HistoryWindow, "new", new.HistoryWindow)
+		log.Info("config_changed", "field", "history_window", "old", old.HistoryWindow, "new", new.HistoryWindow)
 	}
 	if old.HistorySize != new.HistorySize {
-		logger.Info("config_changed", "field", "history_size", "old", old.HistorySize, "new", new.HistorySize)
+		log.Info("config_changed", "field", "history_size", "old", old.HistorySize, "new", new.HistorySize)
+	}
+	if !slices.Equal(old.Drain, new.Drain) {
+		log.Info("config_changed", "field", "drain", "old", old.Drain, "new", new.Drain)
 	}
 
 	// Warn about non-reloadable fields that changed
 	if len(old.IPs) != len(new.IPs) || !slicesEqual(old.IPs, new.IPs) {
-		logger.Warn("config_change_ignored", "field", "ips", "reason", "requires restart")
+		log.Warn("config_change_ignored", "field", "ips", "reason", "requires restart")
 	}
 	if old.Port != new.Port {
-		logger.Warn("config_change_ignored", "field", "port", "reason", "requires restart")
+		log.Warn("config_change_ignored", "field", "port", "reason", "requires restart")
 	}
 	if old.MetricsPort != new.MetricsPort {
-		logger.Warn("config_change_ignored", "field", "metrics_port", "reason", "requires restart")
+		log.Warn("config_change_ignored", "field", "metrics_port", "reason", "requires restart")
 	}
 	if old.Auth != new.Auth {
-		logger.Warn("config_change_ignored", "field", "auth", "reason", "requires restart for security")
+		log.Warn("config_change_ignored", "field", "auth", "reason", "requires restart for security")
 	}
 	if old.Timeout != new.Timeout {
-		logger.Warn("config_change_ignored", "field", "timeout", "reason", "requires restart")
+		log.Warn("config_change_ignored", "field", "timeout", "reason", "requires restart")
 	}
 }
 
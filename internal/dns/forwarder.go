@@ -0,0 +1,312 @@
+package dns
+
+import (
+	"encoding/binary"
+	"errors"
+	"io"
+	"net"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// log logs on behalf of the dns module, so its level can be set on its own.
+var log = logger.Module("dns")
+
+// maxUDPMessage is the largest DNS message accepted over UDP (EDNS0 buffer size).
+const maxUDPMessage = 4096
+
+// maxConcurrentQueries bounds the UDP queries and TCP connections served at
+// once. UDP queries over the bound are dropped; TCP connections wait.
+const maxConcurrentQueries = 256
+
+// errShortMessage is returned when a DNS message is too short to carry a header.
+var errShortMessage = errors.New("dns message too short")
+
+// SelectFunc picks the outbound IP for a query name asked by client.
+type SelectFunc func(name string, client net.Addr) (string, error)
+
+// Forwarder is a DNS proxy listening on UDP and TCP. Each query is relayed to the
+// upstream servers from the source address of the outbound IP chosen for the
+// query name, so DNS-based geo answers match the egress traffic will use.
+type Forwarder struct {
+	upstreams []string
+	timeout   time.Duration
+	selectIP  SelectFunc
+	allow     func(client net.Addr) bool
+	sem       chan struct{}
+	udpConn   net.PacketConn
+	tcpLn     net.Listener
+	wg        sync.WaitGroup
+	mu        sync.Mutex
+}
+
+// NewForwarder creates a Forwarder relaying to upstreams (host:port, tried in order).
+func NewForwarder(upstreams []string, timeout time.Duration, selectIP SelectFunc) *Forwarder {
+	return &Forwarder{
+		upstreams: upstreams,
+		timeout:   timeout,
+		selectIP:  selectIP,
+		sem:       make(chan struct{}, maxConcurrentQueries),
+	}
+}
+
+// SetClientFilter makes the forwarder ignore clients for which allow returns
+// false. It must be called before Serve.
+func (f *Forwarder) SetClientFilter(allow func(client net.Addr) bool) {
+	f.allow = allow
+}
+
+// allowed reports whether client may send queries.
+func (f *Forwarder) allowed(client net.Addr) bool {
+	return f.allow == nil || f.allow(client)
+}
+
+// ListenAndServe serves DNS on addr over UDP and TCP until Close is called.
+func (f *Forwarder) ListenAndServe(addr string) error {
+	udpConn, err := net.ListenPacket("udp", addr)
+	if err != nil {
+		return err
+	}
+	tcpLn, err := net.Listen("tcp", addr)
+	if err != nil {
+		udpConn.Close()
+		return err
+	}
+	return f.Serve(udpConn, tcpLn)
+}
+
+// Serve serves DNS on the given UDP and TCP sockets until Close is called.
+func (f *Forwarder) Serve(udpConn net.PacketConn, tcpLn net.Listener) error {
+	f.mu.Lock()
+	f.udpConn = udpConn
+	f.tcpLn = tcpLn
+	f.mu.Unlock()
+
+	errCh := make(chan error, 2)
+	f.wg.Add(2)
+	go func() {
+		defer f.wg.Done()
+		errCh <- f.serveUDP(udpConn)
+	}()
+	go func() {
+		defer f.wg.Done()
+		errCh <- f.serveTCP(tcpLn)
+	}()
+
+	err := <-errCh
+	f.Close()
+	f.wg.Wait()
+	return err
+}
+
+// Close stops the listeners.
+func (f *Forwarder) Close() {
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	if f.udpConn != nil {
+		f.udpConn.Close()
+	}
+	if f.tcpLn != nil {
+		f.tcpLn.Close()
+	}
+}
+
+// serveUDP answers UDP queries until conn is closed.
+func (f *Forwarder) serveUDP(conn net.PacketConn) error {
+	for {
+		buf := make([]byte, maxUDPMessage)
+		n, client, err := conn.ReadFrom(buf)
+		if err != nil {
+			if errors.Is(err, net.ErrClosed) {
+				return nil
+			}
+			return err
+		}
+		if !f.allowed(client) {
+			continue
+		}
+		select {
+		case f.sem <- struct{}{}:
+		default:
+			log.Debug("dns_forward_dropped", "remote", client)
+			metrics.DNSForwardedQueries.WithLabelValues("udp", "dropped").Inc()
+			continue
+		}
+		go func(query []byte) {
+			defer func() { <-f.sem }()
+			resp, err := f.forward(query, "udp", client)
+			if err != nil {
+				return
+			}
+			conn.WriteTo(resp, client)
+		}(buf[:n])
+	}
+}
+
+// serveTCP accepts TCP connections until l is closed.
+func (f *Forwarder) serveTCP(l net.Listener) error {
+	for {
+		conn, err := l.Accept()
+		if err != nil {
+			if errors.Is(err, net.ErrClosed) {
+				return nil
+			}
+			return err
+		}
+		if !f.allowed(conn.RemoteAddr()) {
+			conn.Close()
+			continue
+		}
+		f.sem <- struct{}{}
+		go func() {
+			defer func() { <-f.sem }()
+			f.serveTCPConn(conn)
+		}()
+	}
+}
+
+// serveTCPConn answers length-prefixed queries on one TCP connection.
+func (f *Forwarder) serveTCPConn(conn net.Conn) {
+	defer conn.Close()
+	for {
+		conn.SetReadDeadline(time.Now().Add(f.timeout))
+		query, err := readTCPMessage(conn)
+		if err != nil {
+			return
+		}
+		resp, err := f.forward(query, "tcp", conn.RemoteAddr())
+		if err != nil {
+			return
+		}
+		if err := writeTCPMessage(conn, resp); err != nil {
+			return
+		}
+	}
+}
+
+// forward relays client's query to the upstreams from the selected outbound IP.
+func (f *Forwarder) forward(query []byte, protocol string, client net.Addr) ([]byte, error) {
+	name, err := questionName(query)
+	if err != nil {
+		log.Debug("dns_forward_invalid", "protocol", protocol, "error", err)
+		metrics.DNSForwardedQueries.WithLabelValues(protocol, "error").Inc()
+		return nil, err
+	}
+
+	ip, err := f.selectIP(name, client)
+	if err != nil {
+		log.Debug("dns_forward_no_ip", "name", name, "error", err)
+		metrics.DNSForwardedQueries.WithLabelValues(protocol, "error").Inc()
+		return nil, err
+	}
+
+	var lastErr error
+	for _, upstream := range f.upstreams {
+		start := time.Now()
+		resp, err := f.exchange(query, protocol, ip, upstream)
+		metrics.DNSQueryDuration.WithLabelValues(upstream).Observe(time.Since(start).Seconds())
+		if err == nil {
+			log.Trace("dns_forwarded", "name", name, "protocol", protocol, "ip", ip, "server", upstream)
+			metrics.DNSForwardedQueries.WithLabelValues(protocol, "ok").Inc()
+			return resp, nil
+		}
+		metrics.DNSQueryErrors.WithLabelValues(upstream, classifyError(err)).Inc()
+		lastErr = err
+	}
+
+	log.Debug("dns_forward_failed", "name", name, "ip", ip, "error", lastErr)
+	metrics.DNSForwardedQueries.WithLabelValues(protocol, "error").Inc()
+	return nil, lastErr
+}
+
+// exchange sends query to upstream from ip and returns the response.
+func (f *Forwarder) exchange(query []byte, protocol, ip, upstream string) ([]byte, error) {
+	var local net.Addr
+	if protocol == "tcp" {
+		local = &net.TCPAddr{IP: net.ParseIP(ip)}
+	} else {
+		local = &net.UDPAddr{IP: net.ParseIP(ip)}
+	}
+	d := net.Dialer{Timeout: f.timeout, LocalAddr: local}
+	conn, err := d.Dial(protocol, upstream)
+	if err != nil {
+		return nil, err
+	}
+	defer conn.Close()
+	conn.SetDeadline(time.Now().Add(f.timeout))
+
+	if protocol == "tcp" {
+		if err := writeTCPMessage(conn, query); err != nil {
+			return nil, err
+		}
+		return readTCPMessage(conn)
+	}
+
+	if _, err := conn.Write(query); err != nil {
+		return nil, err
+	}
+	buf := make([]byte, maxUDPMessage)
+	for {
+		n, err := conn.Read(buf)
+		if err != nil {
+			return nil, err
+		}
+		// Ignore stray datagrams that do not answer this query's ID
+		if n >= 2 && buf[0] == query[0] && buf[1] == query[1] {
+			return buf[:n], nil
+		}
+	}
+}
+
+// readTCPMessage reads a length-prefixed DNS message (RFC 1035 section 4.2.2).
+func readTCPMessage(r io.Reader) ([]byte, error) {
+	var length [2]byte
+	if _, err := io.ReadFull(r, length[:]); err != nil {
+		return nil, err
+	}
+	msg := make([]byte, binary.BigEndian.Uint16(length[:]))
+	if _, err := io.ReadFull(r, msg); err != nil {
+		return nil, err
+	}
+	return msg, nil
+}
+
+// writeTCPMessage writes a length-prefixed DNS message.
+func writeTCPMessage(w io.Writer, msg []byte) error {
+	buf := binary.BigEndian.AppendUint16(make([]byte, 0, 2+len(msg)), uint16(len(msg)))
+	_, err := w.Write(append(buf, msg...))
+	return err
+}
+
+// questionName returns the name of the first question in a DNS query, without
+// the trailing dot. Compression pointers are not expected in questions.
+func questionName(msg []byte) (string, error) {
+	if len(msg) < 12 {
+		return "", errShortMessage
+	}
+	if binary.BigEndian.Uint16(msg[4:6]) == 0 {
+		return "", errors.New("dns query has no question")
+	}
+
+	var labels []string
+	p := msg[12:]
+	for {
+		if len(p) == 0 {
+			return "", errShortMessage
+		}
+		length := int(p[0])
+		if length == 0 {
+			break
+		}
+		if length&0xC0 != 0 || len(p) < 1+length {
+			return "", errors.New("malformed dns question name")
+		}
+		labels = append(labels, string(p[1:1+length]))
+		p = p[1+length:]
+	}
+	return strings.ToLower(strings.Join(labels, ".")), nil
+}
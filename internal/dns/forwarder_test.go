@@ -0,0 +1,235 @@
+package dns
+
+import (
+	"errors"
+	"net"
+	"testing"
+	"time"
+)
+
+// testQuery builds a DNS query for name with the given ID.
+func testQuery(id uint16, name string) []byte {
+	msg := []byte{byte(id >> 8), byte(id), 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0}
+	for _, label := range splitLabels(name) {
+		msg = append(msg, byte(len(label)))
+		msg = append(msg, label...)
+	}
+	return append(msg, 0, 0, 1, 0, 1)
+}
+
+// splitLabels splits a dotted name into labels.
+func splitLabels(name string) []string {
+	var labels []string
+	start := 0
+	for i := 0; i <= len(name); i++ {
+		if i == len(name) || name[i] == '.' {
+			if i > start {
+				labels = append(labels, name[start:i])
+			}
+			start = i + 1
+		}
+	}
+	return labels
+}
+
+// answer marks a query as a response.
+func answer(query []byte) []byte {
+	resp := append([]byte(nil), query...)
+	resp[2] |= 0x80
+	return resp
+}
+
+// startTestUpstream starts a UDP and TCP DNS server that echoes queries as responses.
+func startTestUpstream(t *testing.T) string {
+	t.Helper()
+	udpConn, err := net.ListenPacket("udp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen udp: %v", err)
+	}
+	addr := udpConn.LocalAddr().String()
+	tcpLn, err := net.Listen("tcp", addr)
+	if err != nil {
+		t.Fatalf("failed to listen tcp: %v", err)
+	}
+	t.Cleanup(func() {
+		udpConn.Close()
+		tcpLn.Close()
+	})
+
+	go func() {
+		buf := make([]byte, maxUDPMessage)
+		for {
+			n, client, err := udpConn.ReadFrom(buf)
+			if err != nil {
+				return
+			}
+			udpConn.WriteTo(answer(buf[:n]), client)
+		}
+	}()
+	go func() {
+		for {
+			conn, err := tcpLn.Accept()
+			if err != nil {
+				return
+			}
+			go func() {
+				defer conn.Close()
+				if query, err := readTCPMessage(conn); err == nil {
+					writeTCPMessage(conn, answer(query))
+				}
+			}()
+		}
+	}()
+	return addr
+}
+
+// startTestForwarder starts a Forwarder on random local ports.
+func startTestForwarder(t *testing.T, upstream string, selectIP SelectFunc) (udpAddr, tcpAddr string) {
+	t.Helper()
+	udpConn, err := net.ListenPacket("udp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen udp: %v", err)
+	}
+	tcpLn, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen tcp: %v", err)
+	}
+	f := NewForwarder([]string{upstream}, time.Second, selectIP)
+	go f.Serve(udpConn, tcpLn)
+	t.Cleanup(f.Close)
+	return udpConn.LocalAddr().String(), tcpLn.Addr().String()
+}
+
+func TestForwarder_UDP(t *testing.T) {
+	upstream := startTestUpstream(t)
+	selected := make(chan string, 1)
+	udpAddr, _ := startTestForwarder(t, upstream, func(name string, _ net.Addr) (string, error) {
+		selected <- name
+		return "127.0.0.1", nil
+	})
+
+	conn, err := net.Dial("udp", udpAddr)
+	if err != nil {
+		t.Fatalf("failed to dial forwarder: %v", err)
+	}
+	defer conn.Close()
+	conn.SetDeadline(time.Now().Add(2 * time.Second))
+
+	query := testQuery(0x1234, "Example.COM")
+	conn.Write(query)
+	buf := make([]byte, maxUDPMessage)
+	n, err := conn.Read(buf)
+	if err != nil {
+		t.Fatalf("failed to read response: %v", err)
+	}
+	if string(buf[:n]) != string(answer(query)) {
+		t.Errorf("unexpected response %x", buf[:n])
+	}
+	if name := <-selected; name != "example.com" {
+		t.Errorf("selected for %q, want example.com", name)
+	}
+}
+
+func TestForwarder_TCP(t *testing.T) {
+	upstream := startTestUpstream(t)
+	_, tcpAddr := startTestForwarder(t, upstream, func(string, net.Addr) (string, error) {
+		return "127.0.0.1", nil
+	})
+
+	conn, err := net.Dial("tcp", tcpAddr)
+	if err != nil {
+		t.Fatalf("failed to dial forwarder: %v", err)
+	}
+	defer conn.Close()
+	conn.SetDeadline(time.Now().Add(2 * time.Second))
+
+	query := testQuery(0xBEEF, "example.com")
+	if err := writeTCPMessage(conn, query); err != nil {
+		t.Fatalf("failed to write query: %v", err)
+	}
+	resp, err := readTCPMessage(conn)
+	if err != nil {
+		t.Fatalf("failed to read response: %v", err)
+	}
+	if string(resp) != string(answer(query)) {
+		t.Errorf("unexpected response %x", resp)
+	}
+}
+
+func TestForwarder_ClientFilter(t *testing.T) {
+	upstream := startTestUpstream(t)
+	udpConn, err := net.ListenPacket("udp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen udp: %v", err)
+	}
+	tcpLn, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen tcp: %v", err)
+	}
+	f := NewForwarder([]string{upstream}, time.Second, func(string, net.Addr) (string, error) {
+		return "127.0.0.1", nil
+	})
+	f.SetClientFilter(func(net.Addr) bool { return false })
+	go f.Serve(udpConn, tcpLn)
+	t.Cleanup(f.Close)
+
+	conn, err := net.Dial("udp", udpConn.LocalAddr().String())
+	if err != nil {
+		t.Fatalf("failed to dial forwarder: %v", err)
+	}
+	defer conn.Close()
+	conn.SetDeadline(time.Now().Add(300 * time.Millisecond))
+	conn.Write(testQuery(1, "example.com"))
+	if _, err := conn.Read(make([]byte, maxUDPMessage)); err == nil {
+		t.Error("expected a refused client's udp query to go unanswered")
+	}
+
+	tcp, err := net.Dial("tcp", tcpLn.Addr().String())
+	if err != nil {
+		t.Fatalf("failed to dial forwarder: %v", err)
+	}
+	defer tcp.Close()
+	tcp.SetDeadline(time.Now().Add(2 * time.Second))
+	writeTCPMessage(tcp, testQuery(2, "example.com"))
+	if _, err := readTCPMessage(tcp); err == nil {
+		t.Error("expected a refused client's tcp connection to be closed")
+	}
+}
+
+func TestForwarder_NoIP(t *testing.T) {
+	upstream := startTestUpstream(t)
+	f := NewForwarder([]string{upstream}, time.Second, func(string, net.Addr) (string, error) {
+		return "", errors.New("no available IPs")
+	})
+
+	if _, err := f.forward(testQuery(1, "example.com"), "udp"); err == nil {
+		t.Error("expected error when no outbound IP is available")
+	}
+}
+
+func TestQuestionName(t *testing.T) {
+	tests := []struct {
+		name    string
+		msg     []byte
+		want    string
+		wantErr bool
+	}{
+		{"simple", testQuery(1, "www.example.com"), "www.example.com", false},
+		{"root", testQuery(1, ""), "", false},
+		{"short header", []byte{0, 1, 2}, "", true},
+		{"truncated name", testQuery(1, "example.com")[:15], "", true},
+		{"compression pointer", append(testQuery(1, "")[:12], 0xC0, 0x0C), "", true},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			got, err := questionName(tt.msg)
+			if (err != nil) != tt.wantErr {
+				t.Fatalf("questionName() error = %v, wantErr %v", err, tt.wantErr)
+			}
+			if got != tt.want {
+				t.Errorf("questionName() = %q, want %q", got, tt.want)
+			}
+		})
+	}
+}
@@ -0,0 +1,281 @@
+// Package dns provides the caching resolver used for outbound connections.
+package dns
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"net"
+	"net/http"
+	"sort"
+	"strconv"
+	"sync"
+	"sync/atomic"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// systemResolverLabel is the metrics label for the system resolver.
+const systemResolverLabel = "system"
+
+// Config holds resolver configuration.
+type Config struct {
+	// Servers lists upstream DNS servers as host:port, tried in order.
+	// Empty means the system resolver.
+	Servers []string
+	// CacheTTL is how long answers are cached (0 = no caching).
+	CacheTTL time.Duration
+	// CacheSize is the maximum number of cached names.
+	CacheSize int
+	// Timeout bounds each query to an upstream server.
+	Timeout time.Duration
+}
+
+// upstream is a single resolver queried for lookups.
+type upstream struct {
+	name     string
+	resolver *net.Resolver
+}
+
+// cacheEntry holds a cached answer.
+type cacheEntry struct {
+	addrs   []string
+	expires time.Time
+	hits    atomic.Int64
+}
+
+// Resolver resolves hostnames through configured upstreams with an in-memory cache.
+type Resolver struct {
+	upstreams []upstream
+	ttl       time.Duration
+	size      int
+	cache     map[string]*cacheEntry
+	hits      atomic.Int64
+	misses    atomic.Int64
+	mu        sync.RWMutex
+}
+
+// NewResolver creates a Resolver from cfg.
+func NewResolver(cfg Config) *Resolver {
+	r := &Resolver{
+		ttl:   cfg.CacheTTL,
+		size:  cfg.CacheSize,
+		cache: make(map[string]*cacheEntry),
+	}
+
+	if len(cfg.Servers) == 0 {
+		r.upstreams = []upstream{{name: systemResolverLabel, resolver: net.DefaultResolver}}
+		return r
+	}
+
+	for _, server := range cfg.Servers {
+		server := server
+		r.upstreams = append(r.upstreams, upstream{
+			name: server,
+			resolver: &net.Resolver{
+				PreferGo: true,
+				Dial: func(ctx context.Context, network, _ string) (net.Conn, error) {
+					d := net.Dialer{Timeout: cfg.Timeout}
+					return d.DialContext(ctx, network, server)
+				},
+			},
+		})
+	}
+	return r
+}
+
+// LookupHost returns the addresses for host, from cache when possible.
+// IP literals are returned as-is.
+func (r *Resolver) LookupHost(ctx context.Context, host string) ([]string, error) {
+	if net.ParseIP(host) != nil {
+		return []string{host}, nil
+	}
+
+	if addrs, ok := r.cached(host); ok {
+		r.hits.Add(1)
+		metrics.DNSLookups.WithLabelValues("hit").Inc()
+		return addrs, nil
+	}
+	r.misses.Add(1)
+	metrics.DNSLookups.WithLabelValues("miss").Inc()
+
+	addrs, err := r.query(ctx, host)
+	if err != nil {
+		return nil, err
+	}
+	r.store(host, addrs)
+	return addrs, nil
+}
+
+// cached returns the unexpired cached answer for host.
+func (r *Resolver) cached(host string) ([]string, bool) {
+	if r.ttl <= 0 {
+		return nil, false
+	}
+	r.mu.RLock()
+	e, ok := r.cache[host]
+	r.mu.RUnlock()
+	if !ok || time.Now().After(e.expires) {
+		return nil, false
+	}
+	e.hits.Add(1)
+	return e.addrs, true
+}
+
+// query resolves host through the upstreams in order.
+// A name that does not exist is not retried on other upstreams.
+func (r *Resolver) query(ctx context.Context, host string) ([]string, error) {
+	var lastErr error
+	for _, u := range r.upstreams {
+		start := time.Now()
+		addrs, err := u.resolver.LookupHost(ctx, host)
+		metrics.DNSQueryDuration.WithLabelValues(u.name).Observe(time.Since(start).Seconds())
+		if err == nil {
+			return addrs, nil
+		}
+
+		kind := classifyError(err)
+		metrics.DNSQueryErrors.WithLabelValues(u.name, kind).Inc()
+		lastErr = err
+		if kind == "nxdomain" || ctx.Err() != nil {
+			break
+		}
+	}
+	return nil, lastErr
+}
+
+// classifyError maps a lookup error to a metrics label.
+func classifyError(err error) string {
+	var dnsErr *net.DNSError
+	if !errors.As(err, &dnsErr) {
+		return "other"
+	}
+	switch {
+	case dnsErr.IsNotFound:
+		return "nxdomain"
+	case dnsErr.IsTimeout:
+		return "timeout"
+	case dnsErr.Err == "server misbehaving":
+		// The Go resolver reports SERVFAIL this way
+		return "servfail"
+	default:
+		return "other"
+	}
+}
+
+// store caches addrs for host, evicting expired entries (or the one closest to
+// expiry) when the cache is full.
+func (r *Resolver) store(host string, addrs []string) {
+	if r.ttl <= 0 || r.size <= 0 {
+		return
+	}
+
+	now := time.Now()
+	r.mu.Lock()
+	defer r.mu.Unlock()
+
+	if _, exists := r.cache[host]; !exists && len(r.cache) >= r.size {
+		r.evictLocked(now)
+	}
+	r.cache[host] = &cacheEntry{addrs: addrs, expires: now.Add(r.ttl)}
+	metrics.DNSCacheEntries.Set(float64(len(r.cache)))
+}
+
+// evictLocked removes expired entries, or the entry closest to expiry if none have expired.
+// Caller must hold r.mu.
+func (r *Resolver) evictLocked(now time.Time) {
+	var oldestHost string
+	var oldest time.Time
+	removed := false
+	for host, e := range r.cache {
+		if now.After(e.expires) {
+			delete(r.cache, host)
+			removed = true
+			continue
+		}
+		if oldestHost == "" || e.expires.Before(oldest) {
+			oldestHost, oldest = host, e.expires
+		}
+	}
+	if !removed && oldestHost != "" {
+		delete(r.cache, oldestHost)
+	}
+}
+
+// NameStats describes a cached name.
+type NameStats struct {
+	Name      string   `json:"name"`
+	Addrs     []string `json:"addrs"`
+	Hits      int64    `json:"hits"`
+	ExpiresIn string   `json:"expires_in"`
+}
+
+// Stats is a snapshot of resolver state for the admin view.
+type Stats struct {
+	Servers      []string    `json:"servers"`
+	CacheTTL     string      `json:"cache_ttl"`
+	CacheEntries int         `json:"cache_entries"`
+	Hits         int64       `json:"hits"`
+	Misses       int64       `json:"misses"`
+	HitRate      float64     `json:"hit_rate"`
+	Hottest      []NameStats `json:"hottest"`
+}
+
+// Stats returns resolver statistics and the n most-hit cached names.
+func (r *Resolver) Stats(n int) Stats {
+	now := time.Now()
+	stats := Stats{
+		CacheTTL: r.ttl.String(),
+		Hits:     r.hits.Load(),
+		Misses:   r.misses.Load(),
+	}
+	for _, u := range r.upstreams {
+		stats.Servers = append(stats.Servers, u.name)
+	}
+	if total := stats.Hits + stats.Misses; total > 0 {
+		stats.HitRate = float64(stats.Hits) / float64(total)
+	}
+
+	r.mu.RLock()
+	stats.CacheEntries = len(r.cache)
+	names := make([]NameStats, 0, len(r.cache))
+	for host, e := range r.cache {
+		if now.After(e.expires) {
+			continue
+		}
+		names = append(names, NameStats{
+			Name:      host,
+			Addrs:     e.addrs,
+			Hits:      e.hits.Load(),
+			ExpiresIn: e.expires.Sub(now).Round(time.Second).String(),
+		})
+	}
+	r.mu.RUnlock()
+
+	sort.Slice(names, func(i, j int) bool {
+		if names[i].Hits != names[j].Hits {
+			return names[i].Hits > names[j].Hits
+		}
+		return names[i].Name < names[j].Name
+	})
+	if len(names) > n {
+		names = names[:n]
+	}
+	stats.Hottest = names
+	return stats
+}
+
+// Handler returns an HTTP handler serving resolver statistics as JSON.
+// The number of hottest names can be set with ?top=N (default 20).
+func (r *Resolver) Handler() http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, req *http.Request) {
+		top := 20
+		if v, err := strconv.Atoi(req.URL.Query().Get("top")); err == nil && v > 0 {
+			top = v
+		}
+		w.Header().Set("Content-Type", "application/json")
+		w.WriteHeader(http.StatusOK)
+		json.NewEncoder(w).Encode(r.Stats(top))
+	})
+}
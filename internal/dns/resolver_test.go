@@ -0,0 +1,160 @@
+package dns
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+func TestResolver_IPLiteral(t *testing.T) {
+	r := NewResolver(Config{})
+
+	addrs, err := r.LookupHost(context.Background(), "10.0.0.1")
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if len(addrs) != 1 || addrs[0] != "10.0.0.1" {
+		t.Errorf("addrs = %v, want [10.0.0.1]", addrs)
+	}
+	if stats := r.Stats(10); stats.Hits+stats.Misses != 0 {
+		t.Error("IP literals should not count as lookups")
+	}
+}
+
+func TestResolver_SystemUpstream(t *testing.T) {
+	r := NewResolver(Config{})
+	if stats := r.Stats(10); len(stats.Servers) != 1 || stats.Servers[0] != systemResolverLabel {
+		t.Errorf("servers = %v, want [%s]", stats.Servers, systemResolverLabel)
+	}
+
+	r = NewResolver(Config{Servers: []string{"1.1.1.1:53", "8.8.8.8:53"}})
+	if stats := r.Stats(10); len(stats.Servers) != 2 {
+		t.Errorf("servers = %v, want 2 entries", stats.Servers)
+	}
+}
+
+func TestResolver_CacheHit(t *testing.T) {
+	r := NewResolver(Config{CacheTTL: time.Minute, CacheSize: 10})
+	r.store("example.com", []string{"93.184.216.34"})
+
+	addrs, err := r.LookupHost(context.Background(), "example.com")
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if len(addrs) != 1 || addrs[0] != "93.184.216.34" {
+		t.Errorf("addrs = %v, want cached answer", addrs)
+	}
+
+	stats := r.Stats(10)
+	if stats.Hits != 1 || stats.HitRate != 1 {
+		t.Errorf("hits = %d, hit rate = %v, want 1 and 1", stats.Hits, stats.HitRate)
+	}
+}
+
+func TestResolver_CacheDisabled(t *testing.T) {
+	r := NewResolver(Config{})
+	r.store("example.com", []string{"93.184.216.34"})
+
+	if _, ok := r.cached("example.com"); ok {
+		t.Error("expected no caching with a zero TTL")
+	}
+}
+
+func TestResolver_CacheExpiry(t *testing.T) {
+	r := NewResolver(Config{CacheTTL: 10 * time.Millisecond, CacheSize: 10})
+	r.store("example.com", []string{"93.184.216.34"})
+
+	time.Sleep(20 * time.Millisecond)
+	if _, ok := r.cached("example.com"); ok {
+		t.Error("expected expired entry to miss")
+	}
+}
+
+func TestResolver_CacheEviction(t *testing.T) {
+	r := NewResolver(Config{CacheTTL: time.Minute, CacheSize: 2})
+	r.store("a.example.com", []string{"10.0.0.1"})
+	time.Sleep(time.Millisecond)
+	r.store("b.example.com", []string{"10.0.0.2"})
+	r.store("c.example.com", []string{"10.0.0.3"})
+
+	if stats := r.Stats(10); stats.CacheEntries != 2 {
+		t.Errorf("cache entries = %d, want 2", stats.CacheEntries)
+	}
+	if _, ok := r.cached("a.example.com"); ok {
+		t.Error("expected the entry closest to expiry to be evicted")
+	}
+	if _, ok := r.cached("c.example.com"); !ok {
+		t.Error("expected the newest entry to be cached")
+	}
+}
+
+func TestClassifyError(t *testing.T) {
+	tests := []struct {
+		name string
+		err  error
+		want string
+	}{
+		{"nxdomain", &net.DNSError{Err: "no such host", IsNotFound: true}, "nxdomain"},
+		{"timeout", &net.DNSError{Err: "i/o timeout", IsTimeout: true}, "timeout"},
+		{"servfail", &net.DNSError{Err: "server misbehaving"}, "servfail"},
+		{"other dns error", &net.DNSError{Err: "connection refused"}, "other"},
+		{"non-dns error", errors.New("boom"), "other"},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			if got := classifyError(tt.err); got != tt.want {
+				t.Errorf("classifyError() = %q, want %q", got, tt.want)
+			}
+		})
+	}
+}
+
+func TestResolver_StatsHottest(t *testing.T) {
+	r := NewResolver(Config{CacheTTL: time.Minute, CacheSize: 10})
+	r.store("cold.example.com", []string{"10.0.0.1"})
+	r.store("hot.example.com", []string{"10.0.0.2"})
+	r.store("warm.example.com", []string{"10.0.0.3"})
+
+	for i := 0; i < 3; i++ {
+		r.cached("hot.example.com")
+	}
+	r.cached("warm.example.com")
+
+	stats := r.Stats(2)
+	if len(stats.Hottest) != 2 {
+		t.Fatalf("hottest = %d names, want 2", len(stats.Hottest))
+	}
+	if stats.Hottest[0].Name != "hot.example.com" || stats.Hottest[0].Hits != 3 {
+		t.Errorf("hottest[0] = %+v, want hot.example.com with 3 hits", stats.Hottest[0])
+	}
+	if stats.Hottest[1].Name != "warm.example.com" {
+		t.Errorf("hottest[1] = %s, want warm.example.com", stats.Hottest[1].Name)
+	}
+}
+
+func TestResolver_Handler(t *testing.T) {
+	r := NewResolver(Config{CacheTTL: time.Minute, CacheSize: 10})
+	r.store("a.example.com", []string{"10.0.0.1"})
+	r.store("b.example.com", []string{"10.0.0.2"})
+
+	req := httptest.NewRequest(http.MethodGet, "/admin/dns?top=1", nil)
+	rec := httptest.NewRecorder()
+	r.Handler().ServeHTTP(rec, req)
+
+	if rec.Code != http.StatusOK {
+		t.Fatalf("status = %d, want 200", rec.Code)
+	}
+	var stats Stats
+	if err := json.NewDecoder(rec.Body).Decode(&stats); err != nil {
+		t.Fatalf("failed to decode response: %v", err)
+	}
+	if stats.CacheEntries != 2 || len(stats.Hottest) != 1 {
+		t.Errorf("cache entries = %d, hottest = %d, want 2 and 1", stats.CacheEntries, len(stats.Hottest))
+	}
+}
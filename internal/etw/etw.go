@@ -0,0 +1,117 @@
+// Package etw publishes proxy counters to Event Tracing for Windows so Windows
+// monitoring agents can collect them without a Prometheus stack.
+package etw
+
+import (
+	"errors"
+	"fmt"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// ProviderName is the ETW provider the counters are published under.
+const ProviderName = "outbound-lb"
+
+// ErrUnsupported is returned when ETW is not available on this platform.
+var ErrUnsupported = errors.New("etw is only supported on windows")
+
+// Snapshot holds the counters published in one event.
+type Snapshot struct {
+	// ActiveTunnels is the number of open CONNECT, SOCKS and transparent tunnels.
+	ActiveTunnels int
+	// ActiveConnections is the number of in-flight proxy connections.
+	ActiveConnections int64
+	// Bytes is the total bytes relayed in both directions.
+	Bytes int64
+}
+
+// SnapshotFunc returns the current counters.
+type SnapshotFunc func() Snapshot
+
+// provider writes events to an ETW session.
+type provider interface {
+	write(msg string) error
+	close()
+}
+
+// Publisher periodically writes a counters event to ETW.
+type Publisher struct {
+	interval time.Duration
+	snapshot SnapshotFunc
+	provider provider
+	stopCh   chan struct{}
+	wg       sync.WaitGroup
+}
+
+// NewPublisher registers the ETW provider. Returns ErrUnsupported on non-Windows platforms.
+func NewPublisher(interval time.Duration, snapshot SnapshotFunc) (*Publisher, error) {
+	p, err := newProvider()
+	if err != nil {
+		return nil, err
+	}
+	return &Publisher{
+		interval: interval,
+		snapshot: snapshot,
+		provider: p,
+		stopCh:   make(chan struct{}),
+	}, nil
+}
+
+// Start begins publishing events.
+func (p *Publisher) Start() {
+	p.wg.Add(1)
+	go p.run()
+	logger.Info("etw publisher started", "provider", ProviderName, "interval", p.interval)
+}
+
+// Stop stops publishing and unregisters the provider.
+func (p *Publisher) Stop() {
+	close(p.stopCh)
+	p.wg.Wait()
+	p.provider.close()
+}
+
+// run publishes an event every interval.
+func (p *Publisher) run() {
+	defer p.wg.Done()
+
+	ticker := time.NewTicker(p.interval)
+	defer ticker.Stop()
+
+	last := p.snapshot()
+	for {
+		select {
+		case <-p.stopCh:
+			return
+		case <-ticker.C:
+			current := p.snapshot()
+			msg := formatEvent(current, last, p.interval, countErrors())
+			if err := p.provider.write(msg); err != nil {
+				logger.Debug("etw_write_failed", "error", err)
+			}
+			last = current
+		}
+	}
+}
+
+// formatEvent renders the counters as key=value pairs, the payload of a string event.
+func formatEvent(current, last Snapshot, interval time.Duration, errs int64) string {
+	bytesPerSec := float64(current.Bytes-last.Bytes) / interval.Seconds()
+	return fmt.Sprintf("active_tunnels=%d active_connections=%d bytes_per_sec=%.0f errors_total=%d",
+		current.ActiveTunnels, current.ActiveConnections, bytesPerSec, errs)
+}
+
+// countErrors sums proxy requests answered with a 5xx status.
+func countErrors() int64 {
+	var total int64
+	for _, ec := range metrics.ErrorCounts() {
+		if strings.HasPrefix(ec.Status, "5") {
+			total += ec.Count
+		}
+	}
+	return total
+}
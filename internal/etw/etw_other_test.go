@@ -0,0 +1,15 @@
+//go:build !windows
+
+package etw
+
+import (
+	"errors"
+	"testing"
+	"time"
+)
+
+func TestNewPublisher_Unsupported(t *testing.T) {
+	if _, err := NewPublisher(time.Second, func() Snapshot { return Snapshot{} }); !errors.Is(err, ErrUnsupported) {
+		t.Errorf("expected ErrUnsupported, got %v", err)
+	}
+}
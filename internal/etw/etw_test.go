@@ -0,0 +1,65 @@
+package etw
+
+import (
+	"strings"
+	"sync"
+	"testing"
+	"time"
+)
+
+// fakeProvider records written events.
+type fakeProvider struct {
+	events []string
+	closed bool
+	mu     sync.Mutex
+}
+
+func (f *fakeProvider) write(msg string) error {
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	f.events = append(f.events, msg)
+	return nil
+}
+
+func (f *fakeProvider) close() {
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	f.closed = true
+}
+
+func TestFormatEvent(t *testing.T) {
+	last := Snapshot{Bytes: 1000}
+	current := Snapshot{ActiveTunnels: 3, ActiveConnections: 5, Bytes: 21000}
+
+	got := formatEvent(current, last, 10*time.Second, 7)
+	want := "active_tunnels=3 active_connections=5 bytes_per_sec=2000 errors_total=7"
+	if got != want {
+		t.Errorf("formatEvent() = %q, want %q", got, want)
+	}
+}
+
+func TestPublisher_Publishes(t *testing.T) {
+	fake := &fakeProvider{}
+	p := &Publisher{
+		interval: 10 * time.Millisecond,
+		snapshot: func() Snapshot { return Snapshot{ActiveTunnels: 2} },
+		provider: fake,
+		stopCh:   make(chan struct{}),
+	}
+
+	p.Start()
+	time.Sleep(50 * time.Millisecond)
+	p.Stop()
+
+	fake.mu.Lock()
+	defer fake.mu.Unlock()
+	if len(fake.events) == 0 {
+		t.Fatal("expected at least one event")
+	}
+	if !strings.Contains(fake.events[0], "active_tunnels=2") {
+		t.Errorf("unexpected event %q", fake.events[0])
+	}
+	if !fake.closed {
+		t.Error("expected provider to be closed on stop")
+	}
+}
@@ -0,0 +1,8 @@
+//go:build !windows
+
+package etw
+
+// newProvider reports that ETW is unavailable.
+func newProvider() (provider, error) {
+	return nil, ErrUnsupported
+}
@@ -0,0 +1,70 @@
+//go:build windows
+
+package etw
+
+import (
+	"fmt"
+	"syscall"
+	"unsafe"
+)
+
+// providerGUID identifies the outbound-lb ETW provider: {6F1C8E42-3B7D-4C5A-9E21-0B8D4A7F5C13}.
+var providerGUID = syscall.GUID{
+	Data1: 0x6F1C8E42,
+	Data2: 0x3B7D,
+	Data3: 0x4C5A,
+	Data4: [8]byte{0x9E, 0x21, 0x0B, 0x8D, 0x4A, 0x7F, 0x5C, 0x13},
+}
+
+// levelInformational is the ETW level of counter events.
+const levelInformational = 4
+
+var (
+	advapi32            = syscall.NewLazyDLL("advapi32.dll")
+	procEventRegister   = advapi32.NewProc("EventRegister")
+	procEventUnregister = advapi32.NewProc("EventUnregister")
+	procEventWriteStr   = advapi32.NewProc("EventWriteString")
+)
+
+// windowsProvider is a registered ETW provider handle.
+type windowsProvider struct {
+	handle uint64
+}
+
+// newProvider registers the ETW provider.
+func newProvider() (provider, error) {
+	p := &windowsProvider{}
+	r, _, _ := procEventRegister.Call(
+		uintptr(unsafe.Pointer(&providerGUID)),
+		0,
+		0,
+		uintptr(unsafe.Pointer(&p.handle)),
+	)
+	if r != 0 {
+		return nil, fmt.Errorf("EventRegister failed: %w", syscall.Errno(r))
+	}
+	return p, nil
+}
+
+// write emits msg as a string event.
+func (p *windowsProvider) write(msg string) error {
+	s, err := syscall.UTF16PtrFromString(msg)
+	if err != nil {
+		return err
+	}
+	r, _, _ := procEventWriteStr.Call(
+		uintptr(p.handle),
+		levelInformational,
+		0,
+		uintptr(unsafe.Pointer(s)),
+	)
+	if r != 0 {
+		return fmt.Errorf("EventWriteString failed: %w", syscall.Errno(r))
+	}
+	return nil
+}
+
+// close unregisters the provider.
+func (p *windowsProvider) close() {
+	procEventUnregister.Call(uintptr(p.handle))
+}
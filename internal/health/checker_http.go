@@ -1,25 +1,64 @@
 package health
 
 import (
+	"bytes"
 	"context"
 	"fmt"
+	"io"
 	"net"
 	"net/http"
 	"time"
+
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
 )
 
+// maxCheckBody bounds the response body read when matching an expected body.
+const maxCheckBody = 64 << 10
+
 // HTTPChecker implements health checking via HTTP request.
 type HTTPChecker struct {
-	url     string // Full URL (e.g., "http://httpbin.org/status/200")
-	timeout time.Duration
+	url          string // Full URL (e.g., "http://httpbin.org/status/200")
+	timeout      time.Duration
+	expectStatus int
+	expectBody   string
+	route        netutil.Route
+}
+
+// HTTPCheckOption customizes what an HTTPChecker accepts as healthy.
+type HTTPCheckOption func(*HTTPChecker)
+
+// WithExpectedStatus requires the response to have exactly this status code.
+// Redirects are not followed, so a captive portal redirect fails the check.
+func WithExpectedStatus(code int) HTTPCheckOption {
+	return func(c *HTTPChecker) {
+		c.expectStatus = code
+	}
+}
+
+// WithExpectedBody requires the response body to contain s.
+func WithExpectedBody(s string) HTTPCheckOption {
+	return func(c *HTTPChecker) {
+		c.expectBody = s
+	}
+}
+
+// WithHTTPRoute applies policy routing (fwmark or device) to the check connection.
+func WithHTTPRoute(r netutil.Route) HTTPCheckOption {
+	return func(c *HTTPChecker) {
+		c.route = r
+	}
 }
 
 // NewHTTPChecker creates a new HTTP health checker.
-func NewHTTPChecker(url string, timeout time.Duration) *HTTPChecker {
-	return &HTTPChecker{
+func NewHTTPChecker(url string, timeout time.Duration, opts ...HTTPCheckOption) *HTTPChecker {
+	c := &HTTPChecker{
 		url:     url,
 		timeout: timeout,
 	}
+	for _, opt := range opts {
+		opt(c)
+	}
+	return c
 }
 
 // Check performs an HTTP GET health check from the given source IP.
@@ -32,6 +71,7 @@ func (c *HTTPChecker) Check(ctx context.Context, sourceIP string) error {
 					IP: net.ParseIP(sourceIP),
 				},
 				Timeout: c.timeout,
+				Control: c.route.Control(),
 			}
 			return dialer.DialContext(ctx, network, addr)
 		},
@@ -42,6 +82,11 @@ func (c *HTTPChecker) Check(ctx context.Context, sourceIP string) error {
 		Transport: transport,
 		Timeout:   c.timeout,
 	}
+	if c.expectStatus != 0 {
+		client.CheckRedirect = func(*http.Request, []*http.Request) error {
+			return http.ErrUseLastResponse
+		}
+	}
 	defer client.CloseIdleConnections()
 
 	req, err := http.NewRequestWithContext(ctx, http.MethodGet, c.url, nil)
@@ -55,10 +100,23 @@ func (c *HTTPChecker) Check(ctx context.Context, sourceIP string) error {
 	}
 	defer resp.Body.Close()
 
-	// Consider 2xx and 3xx status codes as success
-	if resp.StatusCode >= 200 && resp.StatusCode < 400 {
-		return nil
+	switch {
+	case c.expectStatus != 0 && resp.StatusCode != c.expectStatus:
+		return fmt.Errorf("unexpected status code: %d (want %d)", resp.StatusCode, c.expectStatus)
+	case c.expectStatus == 0 && (resp.StatusCode < 200 || resp.StatusCode >= 400):
+		// Without an expected status, 2xx and 3xx count as success
+		return fmt.Errorf("unexpected status code: %d", resp.StatusCode)
+	}
+
+	if c.expectBody != "" {
+		body, err := io.ReadAll(io.LimitReader(resp.Body, maxCheckBody))
+		if err != nil {
+			return fmt.Errorf("failed to read response body: %w", err)
+		}
+		if !bytes.Contains(body, []byte(c.expectBody)) {
+			return fmt.Errorf("response body does not contain %q", c.expectBody)
+		}
 	}
 
-	return fmt.Errorf("unexpected status code: %d", resp.StatusCode)
+	return nil
 }
@@ -132,3 +132,43 @@ func TestHTTPChecker_Check_InvalidURL(t *testing.T) {
 		t.Error("expected invalid URL to fail check")
 	}
 }
+
+func TestHTTPChecker_Check_Expectations(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		switch r.URL.Path {
+		case "/generate_204":
+			w.WriteHeader(http.StatusNoContent)
+		case "/portal":
+			// A captive portal redirects to its login page
+			http.Redirect(w, r, "/login", http.StatusFound)
+		case "/login":
+			w.Write([]byte("<html>Please sign in</html>"))
+		default:
+			w.Write([]byte("status: ok"))
+		}
+	}))
+	defer server.Close()
+
+	tests := []struct {
+		name    string
+		path    string
+		opts    []HTTPCheckOption
+		wantErr bool
+	}{
+		{"expected status matches", "/generate_204", []HTTPCheckOption{WithExpectedStatus(204)}, false},
+		{"captive portal redirect", "/portal", []HTTPCheckOption{WithExpectedStatus(204)}, true},
+		{"captive portal page passes default check", "/portal", nil, false},
+		{"body contains substring", "/", []HTTPCheckOption{WithExpectedBody("status: ok")}, false},
+		{"body missing substring", "/login", []HTTPCheckOption{WithExpectedBody("status: ok")}, true},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			checker := NewHTTPChecker(server.URL+tt.path, 5*time.Second, tt.opts...)
+			err := checker.Check(context.Background(), "127.0.0.1")
+			if (err != nil) != tt.wantErr {
+				t.Errorf("Check() error = %v, wantErr %v", err, tt.wantErr)
+			}
+		})
+	}
+}
@@ -5,20 +5,37 @@ import (
 	"fmt"
 	"net"
 	"time"
+
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
 )
 
 // TCPChecker implements health checking via TCP connection.
 type TCPChecker struct {
 	target  string // host:port (e.g., "1.1.1.1:443")
 	timeout time.Duration
+	route   netutil.Route
+}
+
+// TCPCheckOption customizes how a TCPChecker connects.
+type TCPCheckOption func(*TCPChecker)
+
+// WithTCPRoute applies policy routing (fwmark or device) to the check connection.
+func WithTCPRoute(r netutil.Route) TCPCheckOption {
+	return func(c *TCPChecker) {
+		c.route = r
+	}
 }
 
 // NewTCPChecker creates a new TCP health checker.
-func NewTCPChecker(target string, timeout time.Duration) *TCPChecker {
-	return &TCPChecker{
+func NewTCPChecker(target string, timeout time.Duration, opts ...TCPCheckOption) *TCPChecker {
+	c := &TCPChecker{
 		target:  target,
 		timeout: timeout,
 	}
+	for _, opt := range opts {
+		opt(c)
+	}
+	return c
 }
 
 // Check performs a TCP connection health check from the given source IP.
@@ -29,6 +46,7 @@ func (c *TCPChecker) Check(ctx context.Context, sourceIP string) error {
 			IP: net.ParseIP(sourceIP),
 		},
 		Timeout: c.timeout,
+		Control: c.route.Control(),
 	}
 
 	// Dial with context
@@ -0,0 +1,235 @@
+package health
+
+import (
+	"context"
+	"fmt"
+	"io"
+	"net"
+	"net/http"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// maxExitIPBody bounds the response read from the exit IP endpoint.
+const maxExitIPBody = 256
+
+// ExitVerifierConfig holds configuration for ExitVerifier.
+type ExitVerifierConfig struct {
+	IPs []string
+	// URL is a "what is my IP" endpoint that answers with the caller's address as plain text.
+	URL      string
+	Interval time.Duration
+	Timeout  time.Duration
+	// Eject reports IPs with a shared or changed exit as unhealthy.
+	Eject bool
+	// Fetch returns the public exit address seen when connecting from an IP.
+	// Nil means an HTTP GET of URL bound to the IP.
+	Fetch func(ctx context.Context, sourceIP string) (string, error)
+}
+
+// ExitVerifier periodically fetches the public exit address of every outbound IP
+// and warns when two IPs leave through the same exit or an IP's exit changes,
+// which points to a mis-routed source bind behind NAT.
+type ExitVerifier struct {
+	config   ExitVerifierConfig
+	observed map[string]string
+	suspect  map[string]bool
+	stopCh   chan struct{}
+	wg       sync.WaitGroup
+	mu       sync.RWMutex
+}
+
+// NewExitVerifier creates a new ExitVerifier.
+func NewExitVerifier(cfg ExitVerifierConfig) *ExitVerifier {
+	v := &ExitVerifier{
+		config:   cfg,
+		observed: make(map[string]string, len(cfg.IPs)),
+		suspect:  make(map[string]bool),
+		stopCh:   make(chan struct{}),
+	}
+	if v.config.Fetch == nil {
+		v.config.Fetch = v.fetch
+	}
+	return v
+}
+
+// Start verifies every IP immediately, then every Interval.
+func (v *ExitVerifier) Start() {
+	v.wg.Add(1)
+	go v.loop()
+	log.Info("exit_ip_verifier_started", "url", v.config.URL, "interval", v.config.Interval, "eject", v.config.Eject)
+}
+
+// Stop stops the verification goroutine and waits for completion.
+func (v *ExitVerifier) Stop() {
+	close(v.stopCh)
+	v.wg.Wait()
+	log.Info("exit_ip_verifier_stopped")
+}
+
+func (v *ExitVerifier) loop() {
+	defer v.wg.Done()
+
+	v.verifyAll()
+
+	ticker := time.NewTicker(v.config.Interval)
+	defer ticker.Stop()
+
+	for {
+		select {
+		case <-ticker.C:
+			v.verifyAll()
+		case <-v.stopCh:
+			return
+		}
+	}
+}
+
+// verifyAll fetches the exit of every IP concurrently and re-evaluates them together.
+func (v *ExitVerifier) verifyAll() {
+	exits := make(map[string]string, len(v.config.IPs))
+	var mu sync.Mutex
+	var wg sync.WaitGroup
+	for _, ip := range v.config.IPs {
+		wg.Add(1)
+		go func(ip string) {
+			defer wg.Done()
+			ctx, cancel := context.WithTimeout(context.Background(), v.config.Timeout)
+			defer cancel()
+			exit, err := v.config.Fetch(ctx, ip)
+			if err != nil {
+				log.Debug("exit_ip_check_failed", "ip", ip, "error", err.Error())
+				return
+			}
+			mu.Lock()
+			exits[ip] = exit
+			mu.Unlock()
+		}(ip)
+	}
+	wg.Wait()
+	v.update(exits)
+}
+
+// update records freshly observed exits. IPs whose check failed keep their
+// previous observation. An IP is suspect while its exit changed since the
+// previous check, or while it shares its exit with an IP listed before it.
+func (v *ExitVerifier) update(exits map[string]string) {
+	v.mu.Lock()
+	defer v.mu.Unlock()
+
+	changed := make(map[string]bool)
+	for ip, exit := range exits {
+		if prev := v.observed[ip]; prev != "" && prev != exit {
+			log.Warn("exit_ip_changed", "ip", ip, "from", prev, "to", exit)
+			metrics.ExitIPChanges.WithLabelValues(ip).Inc()
+			changed[ip] = true
+		}
+		v.observed[ip] = exit
+	}
+
+	owner := make(map[string]string, len(v.observed))
+	suspect := make(map[string]bool)
+	for _, ip := range v.config.IPs {
+		exit, ok := v.observed[ip]
+		if !ok {
+			continue
+		}
+		first, shared := owner[exit]
+		if shared {
+			log.Warn("exit_ip_shared", "ip", ip, "shared_with", first, "exit", exit)
+			metrics.ExitIPShared.WithLabelValues(ip).Set(1)
+		} else {
+			owner[exit] = ip
+			metrics.ExitIPShared.WithLabelValues(ip).Set(0)
+		}
+		if shared || changed[ip] {
+			suspect[ip] = true
+		}
+	}
+	v.suspect = suspect
+}
+
+// fetch GETs URL from sourceIP and parses the address in the response body.
+func (v *ExitVerifier) fetch(ctx context.Context, sourceIP string) (string, error) {
+	client := &http.Client{
+		Transport: &http.Transport{
+			DialContext: (&net.Dialer{
+				LocalAddr: &net.TCPAddr{IP: net.ParseIP(sourceIP)},
+				Timeout:   v.config.Timeout,
+			}).DialContext,
+			DisableKeepAlives: true,
+		},
+		Timeout: v.config.Timeout,
+	}
+	defer client.CloseIdleConnections()
+
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, v.config.URL, nil)
+	if err != nil {
+		return "", fmt.Errorf("failed to create request: %w", err)
+	}
+	resp, err := client.Do(req)
+	if err != nil {
+		return "", fmt.Errorf("http request failed: %w", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return "", fmt.Errorf("unexpected status code: %d", resp.StatusCode)
+	}
+
+	body, err := io.ReadAll(io.LimitReader(resp.Body, maxExitIPBody))
+	if err != nil {
+		return "", fmt.Errorf("failed to read response body: %w", err)
+	}
+	return parseExitIP(string(body))
+}
+
+// parseExitIP returns the address in a plain-text "what is my IP" response.
+func parseExitIP(body string) (string, error) {
+	ip := net.ParseIP(strings.TrimSpace(body))
+	if ip == nil {
+		return "", fmt.Errorf("response is not an IP address: %q", strings.TrimSpace(body))
+	}
+	return ip.String(), nil
+}
+
+// Observed returns the last observed exit address of each IP.
+func (v *ExitVerifier) Observed() map[string]string {
+	v.mu.RLock()
+	defer v.mu.RUnlock()
+	observed := make(map[string]string, len(v.observed))
+	for ip, exit := range v.observed {
+		observed[ip] = exit
+	}
+	return observed
+}
+
+// Exit returns the last observed exit address of ip, or "" if none yet.
+func (v *ExitVerifier) Exit(ip string) string {
+	v.mu.RLock()
+	defer v.mu.RUnlock()
+	return v.observed[ip]
+}
+
+// IsHealthy returns false for a suspect IP when ejection is enabled.
+func (v *ExitVerifier) IsHealthy(ip string) bool {
+	if !v.config.Eject {
+		return true
+	}
+	v.mu.RLock()
+	defer v.mu.RUnlock()
+	return !v.suspect[ip]
+}
+
+// GetHealthyIPs filters the given IPs and returns the ones not ejected.
+func (v *ExitVerifier) GetHealthyIPs(ips []string) []string {
+	healthy := make([]string, 0, len(ips))
+	for _, ip := range ips {
+		if v.IsHealthy(ip) {
+			healthy = append(healthy, ip)
+		}
+	}
+	return healthy
+}
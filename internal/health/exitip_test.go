@@ -0,0 +1,122 @@
+package health
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+// fakeExits returns an ExitVerifierConfig.Fetch that answers from exits.
+func fakeExits(exits map[string]string) func(context.Context, string) (string, error) {
+	return func(_ context.Context, ip string) (string, error) {
+		exit, ok := exits[ip]
+		if !ok {
+			return "", errors.New("unreachable")
+		}
+		return exit, nil
+	}
+}
+
+func TestExitVerifier_SharedExit(t *testing.T) {
+	exits := map[string]string{
+		"10.0.0.1": "203.0.113.1",
+		"10.0.0.2": "203.0.113.2",
+		"10.0.0.3": "203.0.113.1",
+	}
+	v := NewExitVerifier(ExitVerifierConfig{
+		IPs:     []string{"10.0.0.1", "10.0.0.2", "10.0.0.3"},
+		Timeout: time.Second,
+		Eject:   true,
+		Fetch:   fakeExits(exits),
+	})
+
+	v.verifyAll()
+	if got := v.GetHealthyIPs([]string{"10.0.0.1", "10.0.0.2", "10.0.0.3"}); len(got) != 2 || got[0] != "10.0.0.1" || got[1] != "10.0.0.2" {
+		t.Errorf("expected only the later IP sharing an exit to be ejected, got healthy %v", got)
+	}
+
+	// Fixing the bind returns the IP to service
+	exits["10.0.0.3"] = "203.0.113.3"
+	v.verifyAll()
+	v.verifyAll()
+	if !v.IsHealthy("10.0.0.3") {
+		t.Error("expected IP with a distinct exit to be healthy")
+	}
+}
+
+func TestExitVerifier_ChangedExit(t *testing.T) {
+	exits := map[string]string{"10.0.0.1": "203.0.113.1"}
+	v := NewExitVerifier(ExitVerifierConfig{
+		IPs:     []string{"10.0.0.1"},
+		Timeout: time.Second,
+		Eject:   true,
+		Fetch:   fakeExits(exits),
+	})
+
+	v.verifyAll()
+	exits["10.0.0.1"] = "203.0.113.9"
+	v.verifyAll()
+	if v.IsHealthy("10.0.0.1") {
+		t.Error("expected IP whose exit changed to be ejected")
+	}
+	if got := v.Observed()["10.0.0.1"]; got != "203.0.113.9" {
+		t.Errorf("observed exit = %q, want 203.0.113.9", got)
+	}
+
+	// A stable exit on the next check clears the suspicion
+	v.verifyAll()
+	if !v.IsHealthy("10.0.0.1") {
+		t.Error("expected IP to return once its exit is stable")
+	}
+
+	// Failed checks keep the previous observation
+	delete(exits, "10.0.0.1")
+	v.verifyAll()
+	if got := v.Observed()["10.0.0.1"]; got != "203.0.113.9" {
+		t.Errorf("observed exit after a failed check = %q, want 203.0.113.9", got)
+	}
+}
+
+func TestExitVerifier_AlertOnly(t *testing.T) {
+	v := NewExitVerifier(ExitVerifierConfig{
+		IPs:     []string{"10.0.0.1", "10.0.0.2"},
+		Timeout: time.Second,
+		Fetch:   fakeExits(map[string]string{"10.0.0.1": "203.0.113.1", "10.0.0.2": "203.0.113.1"}),
+	})
+
+	v.verifyAll()
+	if !v.IsHealthy("10.0.0.2") {
+		t.Error("expected no ejection without Eject")
+	}
+}
+
+func TestExitVerifier_Fetch(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		host, _, _ := net.SplitHostPort(r.RemoteAddr)
+		fmt.Fprintf(w, "%s\n", host)
+	}))
+	defer server.Close()
+
+	v := NewExitVerifier(ExitVerifierConfig{URL: server.URL, Timeout: time.Second})
+	exit, err := v.config.Fetch(context.Background(), "127.0.0.1")
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if exit != "127.0.0.1" {
+		t.Errorf("exit = %q, want 127.0.0.1", exit)
+	}
+}
+
+func TestParseExitIP(t *testing.T) {
+	if got, err := parseExitIP(" 2001:db8::1\n"); err != nil || got != "2001:db8::1" {
+		t.Errorf("parseExitIP = %q, %v", got, err)
+	}
+	if _, err := parseExitIP("<html>captive portal</html>"); err == nil {
+		t.Error("expected error for a non-IP response")
+	}
+}
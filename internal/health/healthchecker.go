@@ -3,13 +3,18 @@ package health
 
 import (
 	"context"
+	"errors"
 	"sync"
 	"time"
 
+	"github.com/cr0hn/outbound-lb/internal/clock"
 	"github.com/cr0hn/outbound-lb/internal/logger"
 	"github.com/cr0hn/outbound-lb/internal/metrics"
 )
 
+// log logs on behalf of the health module, so its level can be set on its own.
+var log = logger.Module("health")
+
 // Checker is the interface for health check implementations.
 type Checker interface {
 	// Check performs a health check from the given source IP.
@@ -19,12 +24,16 @@ type Checker interface {
 
 // HealthCheckerConfig holds configuration for the HealthChecker.
 type HealthCheckerConfig struct {
-	IPs              []string
-	Checker          Checker
+	IPs     []string
+	Checker Checker
+	// Checkers overrides Checker for individual IPs.
+	Checkers         map[string]Checker
 	Interval         time.Duration
 	Timeout          time.Duration
 	FailureThreshold int
 	SuccessThreshold int
+	// Clock times checks and stamps LastCheck. Nil means the wall clock.
+	Clock clock.Clock
 }
 
 // HealthChecker manages health checking for multiple IPs.
@@ -38,6 +47,7 @@ type HealthChecker struct {
 
 // NewHealthChecker creates a new HealthChecker.
 func NewHealthChecker(cfg HealthCheckerConfig) *HealthChecker {
+	cfg.Clock = clock.OrReal(cfg.Clock)
 	hc := &HealthChecker{
 		config:   cfg,
 		statuses: make(map[string]*IPStatus, len(cfg.IPs)),
@@ -45,7 +55,9 @@ func NewHealthChecker(cfg HealthCheckerConfig) *HealthChecker {
 	}
 
 	for _, ip := range cfg.IPs {
-		hc.statuses[ip] = NewIPStatus(ip)
+		status := NewIPStatus(ip)
+		status.clock = cfg.Clock
+		hc.statuses[ip] = status
 		// Initialize metrics
 		metrics.IPHealthStatus.WithLabelValues(ip).Set(1) // Start as healthy
 	}
@@ -57,7 +69,7 @@ func NewHealthChecker(cfg HealthCheckerConfig) *HealthChecker {
 func (hc *HealthChecker) Start() {
 	hc.wg.Add(1)
 	go hc.checkLoop()
-	logger.Info("health_checker_started",
+	log.Info("health_checker_started",
 		"interval", hc.config.Interval,
 		"timeout", hc.config.Timeout,
 		"failure_threshold", hc.config.FailureThreshold,
@@ -69,7 +81,7 @@ func (hc *HealthChecker) Start() {
 func (hc *HealthChecker) Stop() {
 	close(hc.stopCh)
 	hc.wg.Wait()
-	logger.Info("health_checker_stopped")
+	log.Info("health_checker_stopped")
 }
 
 // IsHealthy returns true if the IP is in a healthy state.
@@ -126,6 +138,30 @@ func (hc *HealthChecker) GetAllStatus() []StatusInfo {
 	return result
 }
 
+// MarkUnhealthy takes ip out of rotation until active checks see it pass
+// SuccessThreshold times again, e.g. to restore state that outlived a restart.
+// Unknown IPs are ignored.
+func (hc *HealthChecker) MarkUnhealthy(ip, lastErr string) {
+	hc.mu.RLock()
+	status, ok := hc.statuses[ip]
+	hc.mu.RUnlock()
+	if !ok {
+		return
+	}
+
+	status.mu.Lock()
+	status.State = StateUnhealthy
+	status.ConsecutiveFailures = hc.config.FailureThreshold
+	status.ConsecutiveSuccesses = 0
+	if lastErr != "" {
+		status.LastError = errors.New(lastErr)
+	}
+	status.mu.Unlock()
+
+	metrics.IPHealthStatus.WithLabelValues(ip).Set(0)
+	hc.updateAggregateMetrics()
+}
+
 // checkLoop runs periodic health checks.
 func (hc *HealthChecker) checkLoop() {
 	defer hc.wg.Done()
@@ -169,14 +205,22 @@ func (hc *HealthChecker) checkAll() {
 	hc.updateAggregateMetrics()
 }
 
+// checkerFor returns the checker used for ip.
+func (hc *HealthChecker) checkerFor(ip string) Checker {
+	if c, ok := hc.config.Checkers[ip]; ok {
+		return c
+	}
+	return hc.config.Checker
+}
+
 // checkIP performs a health check on a single IP.
 func (hc *HealthChecker) checkIP(ip string) {
 	ctx, cancel := context.WithTimeout(context.Background(), hc.config.Timeout)
 	defer cancel()
 
-	start := time.Now()
-	err := hc.config.Checker.Check(ctx, ip)
-	duration := time.Since(start)
+	start := hc.config.Clock.Now()
+	err := hc.checkerFor(ip).Check(ctx, ip)
+	duration := hc.config.Clock.Since(start)
 
 	// Record metrics
 	metrics.HealthCheckDuration.WithLabelValues(ip).Observe(duration.Seconds())
@@ -194,7 +238,7 @@ func (hc *HealthChecker) checkIP(ip string) {
 		changed := status.RecordFailure(err, hc.config.FailureThreshold)
 		if changed {
 			newState := status.GetState()
-			logger.Warn("ip_health_state_changed",
+			log.Warn("ip_health_state_changed",
 				"ip", ip,
 				"state", newState.String(),
 				"error", err.Error(),
@@ -203,7 +247,7 @@ func (hc *HealthChecker) checkIP(ip string) {
 				metrics.IPHealthStatus.WithLabelValues(ip).Set(0)
 			}
 		} else {
-			logger.Debug("health_check_failed",
+			log.Debug("health_check_failed",
 				"ip", ip,
 				"error", err.Error(),
 				"consecutive_failures", status.ConsecutiveFailures,
@@ -214,7 +258,7 @@ func (hc *HealthChecker) checkIP(ip string) {
 		changed := status.RecordSuccess(hc.config.SuccessThreshold)
 		if changed {
 			newState := status.GetState()
-			logger.Info("ip_health_state_changed",
+			log.Info("ip_health_state_changed",
 				"ip", ip,
 				"state", newState.String(),
 			)
@@ -222,7 +266,7 @@ func (hc *HealthChecker) checkIP(ip string) {
 				metrics.IPHealthStatus.WithLabelValues(ip).Set(1)
 			}
 		} else {
-			logger.Trace("health_check_success", "ip", ip, "duration", duration)
+			log.Trace("health_check_success", "ip", ip, "duration", duration)
 		}
 	}
 }
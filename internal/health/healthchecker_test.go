@@ -320,6 +320,34 @@ func TestHealthChecker_Recovery(t *testing.T) {
 	}
 }
 
+func TestHealthChecker_PerIPChecker(t *testing.T) {
+	global := newMockChecker()
+	override := newMockChecker()
+	// The override target is unreachable from 192.168.1.2 only
+	override.SetResult("192.168.1.2", errors.New("connection refused"))
+
+	hc := NewHealthChecker(HealthCheckerConfig{
+		IPs:              []string{"192.168.1.1", "192.168.1.2"},
+		Checker:          global,
+		Checkers:         map[string]Checker{"192.168.1.2": override},
+		Interval:         time.Hour,
+		Timeout:          time.Second,
+		FailureThreshold: 1,
+		SuccessThreshold: 1,
+	})
+	hc.checkAll()
+
+	if global.GetCheckCount() != 1 || override.GetCheckCount() != 1 {
+		t.Errorf("expected one check per checker, got global=%d override=%d", global.GetCheckCount(), override.GetCheckCount())
+	}
+	if !hc.IsHealthy("192.168.1.1") {
+		t.Error("expected 192.168.1.1 to be healthy")
+	}
+	if hc.IsHealthy("192.168.1.2") {
+		t.Error("expected 192.168.1.2 to be unhealthy")
+	}
+}
+
 func TestHealthChecker_GetAllStatus(t *testing.T) {
 	checker := newMockChecker()
 	hc := NewHealthChecker(HealthCheckerConfig{
@@ -0,0 +1,128 @@
+package health
+
+import (
+	"context"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// KeepWarmConfig holds configuration for KeepWarm.
+type KeepWarmConfig struct {
+	IPs []string
+	// Checker sends the probe traffic from an IP.
+	Checker Checker
+	// Interval is how long an IP may stay idle before it is probed.
+	Interval time.Duration
+	Timeout  time.Duration
+	// LastActivity returns when an IP last carried real traffic.
+	LastActivity func(ip string) time.Time
+	// Clock decides when IPs are idle. Nil means the wall clock.
+	Clock clock.Clock
+}
+
+// KeepWarm sends lightweight probes through outbound IPs that have carried no
+// traffic for a while, so dormant PPP/LTE uplinks don't add wake-up latency to
+// the next real request. Probe results do not affect health state.
+type KeepWarm struct {
+	config    KeepWarmConfig
+	lastProbe map[string]time.Time
+	stopCh    chan struct{}
+	wg        sync.WaitGroup
+	mu        sync.Mutex
+}
+
+// NewKeepWarm creates a new KeepWarm.
+func NewKeepWarm(cfg KeepWarmConfig) *KeepWarm {
+	cfg.Clock = clock.OrReal(cfg.Clock)
+	return &KeepWarm{
+		config:    cfg,
+		lastProbe: make(map[string]time.Time, len(cfg.IPs)),
+		stopCh:    make(chan struct{}),
+	}
+}
+
+// Start starts the probe goroutine.
+func (k *KeepWarm) Start() {
+	k.wg.Add(1)
+	go k.loop()
+	log.Info("keep_warm_started", "interval", k.config.Interval)
+}
+
+// Stop stops the probe goroutine and waits for completion.
+func (k *KeepWarm) Stop() {
+	close(k.stopCh)
+	k.wg.Wait()
+	log.Info("keep_warm_stopped")
+}
+
+// loop probes idle IPs twice per interval, so no IP stays idle much longer than Interval.
+func (k *KeepWarm) loop() {
+	defer k.wg.Done()
+
+	ticker := time.NewTicker(k.config.Interval / 2)
+	defer ticker.Stop()
+
+	for {
+		select {
+		case <-ticker.C:
+			k.probeIdle()
+		case <-k.stopCh:
+			return
+		}
+	}
+}
+
+// idle returns the IPs that neither carried traffic nor were probed within Interval.
+func (k *KeepWarm) idle() []string {
+	now := k.config.Clock.Now()
+	k.mu.Lock()
+	defer k.mu.Unlock()
+
+	var ips []string
+	for _, ip := range k.config.IPs {
+		last := k.config.LastActivity(ip)
+		if probed := k.lastProbe[ip]; probed.After(last) {
+			last = probed
+		}
+		if now.Sub(last) >= k.config.Interval {
+			ips = append(ips, ip)
+		}
+	}
+	return ips
+}
+
+// probeIdle probes every idle IP concurrently.
+func (k *KeepWarm) probeIdle() {
+	var wg sync.WaitGroup
+	for _, ip := range k.idle() {
+		wg.Add(1)
+		go func(ip string) {
+			defer wg.Done()
+			k.probe(ip)
+		}(ip)
+	}
+	wg.Wait()
+}
+
+// probe sends one keep-warm probe through ip.
+func (k *KeepWarm) probe(ip string) {
+	ctx, cancel := context.WithTimeout(context.Background(), k.config.Timeout)
+	defer cancel()
+
+	err := k.config.Checker.Check(ctx, ip)
+
+	k.mu.Lock()
+	k.lastProbe[ip] = k.config.Clock.Now()
+	k.mu.Unlock()
+
+	if err != nil {
+		metrics.KeepWarmProbes.WithLabelValues(ip, "failure").Inc()
+		log.Debug("keep_warm_probe_failed", "ip", ip, "error", err.Error())
+		return
+	}
+	metrics.KeepWarmProbes.WithLabelValues(ip, "success").Inc()
+	log.Debug("keep_warm_probe", "ip", ip)
+}
@@ -0,0 +1,56 @@
+package health
+
+import (
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func TestKeepWarm_ProbesOnlyIdleIPs(t *testing.T) {
+	c := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	activity := map[string]time.Time{
+		"192.168.1.1": c.Now(),
+		"192.168.1.2": c.Now().Add(-10 * time.Minute),
+	}
+	checker := newMockChecker()
+	k := NewKeepWarm(KeepWarmConfig{
+		IPs:          []string{"192.168.1.1", "192.168.1.2"},
+		Checker:      checker,
+		Interval:     5 * time.Minute,
+		Timeout:      time.Second,
+		LastActivity: func(ip string) time.Time { return activity[ip] },
+		Clock:        c,
+	})
+
+	if got := k.idle(); len(got) != 1 || got[0] != "192.168.1.2" {
+		t.Fatalf("expected only 192.168.1.2 to be idle, got %v", got)
+	}
+
+	k.probeIdle()
+	if checker.GetCheckCount() != 1 {
+		t.Fatalf("expected 1 probe, got %d", checker.GetCheckCount())
+	}
+
+	// A probed IP is not probed again until another interval passes
+	if got := k.idle(); len(got) != 0 {
+		t.Errorf("expected no idle IPs right after probing, got %v", got)
+	}
+
+	c.Advance(5 * time.Minute)
+	if got := k.idle(); len(got) != 2 {
+		t.Errorf("expected both IPs to be idle after an interval, got %v", got)
+	}
+}
+
+func TestKeepWarm_StartStop(t *testing.T) {
+	k := NewKeepWarm(KeepWarmConfig{
+		IPs:          []string{"192.168.1.1"},
+		Checker:      newMockChecker(),
+		Interval:     time.Hour,
+		Timeout:      time.Second,
+		LastActivity: func(string) time.Time { return time.Time{} },
+	})
+	k.Start()
+	k.Stop()
+}
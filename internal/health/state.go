@@ -0,0 +1,58 @@
+package health
+
+import (
+	"encoding/json"
+	"os"
+	"path/filepath"
+	"time"
+)
+
+// BackendState is the persisted health of one outbound IP.
+type BackendState struct {
+	IP string `json:"ip"`
+	// Unhealthy is set while active checks hold the IP out of rotation.
+	Unhealthy bool   `json:"unhealthy,omitempty"`
+	LastError string `json:"last_error,omitempty"`
+	// EjectedUntil is when a passive outlier ejection ends.
+	EjectedUntil time.Time `json:"ejected_until,omitempty"`
+}
+
+// State is the health of the outbound IPs as written to the health state file,
+// so a restart doesn't send traffic straight back to backends known to be down.
+type State struct {
+	SavedAt  time.Time      `json:"saved_at"`
+	Backends []BackendState `json:"backends"`
+}
+
+// LoadState reads a state file written by SaveState.
+func LoadState(path string) (State, error) {
+	var state State
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return state, err
+	}
+	err = json.Unmarshal(data, &state)
+	return state, err
+}
+
+// SaveState writes state to path, replacing the file atomically.
+func SaveState(path string, state State) error {
+	data, err := json.MarshalIndent(state, "", "  ")
+	if err != nil {
+		return err
+	}
+
+	tmp, err := os.CreateTemp(filepath.Dir(path), ".health-state-*")
+	if err != nil {
+		return err
+	}
+	defer os.Remove(tmp.Name())
+	if _, err := tmp.Write(data); err != nil {
+		tmp.Close()
+		return err
+	}
+	if err := tmp.Close(); err != nil {
+		return err
+	}
+	return os.Rename(tmp.Name(), path)
+}
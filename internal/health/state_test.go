@@ -0,0 +1,66 @@
+package health
+
+import (
+	"path/filepath"
+	"testing"
+	"time"
+)
+
+func TestSaveLoadState(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "health.json")
+	saved := State{
+		SavedAt: time.Unix(1700000000, 0).UTC(),
+		Backends: []BackendState{
+			{IP: "192.168.1.1", Unhealthy: true, LastError: "connection refused"},
+			{IP: "192.168.1.2", EjectedUntil: time.Unix(1700000060, 0).UTC()},
+		},
+	}
+	if err := SaveState(path, saved); err != nil {
+		t.Fatalf("SaveState: %v", err)
+	}
+
+	loaded, err := LoadState(path)
+	if err != nil {
+		t.Fatalf("LoadState: %v", err)
+	}
+	if !loaded.SavedAt.Equal(saved.SavedAt) || len(loaded.Backends) != 2 {
+		t.Fatalf("LoadState = %+v, want %+v", loaded, saved)
+	}
+	if b := loaded.Backends[0]; !b.Unhealthy || b.LastError != "connection refused" {
+		t.Errorf("backend 0 = %+v", b)
+	}
+	if b := loaded.Backends[1]; b.Unhealthy || !b.EjectedUntil.Equal(saved.Backends[1].EjectedUntil) {
+		t.Errorf("backend 1 = %+v", b)
+	}
+}
+
+func TestHealthChecker_MarkUnhealthy(t *testing.T) {
+	hc := NewHealthChecker(HealthCheckerConfig{
+		IPs:              []string{"192.168.1.1"},
+		Checker:          newMockChecker(),
+		Interval:         time.Hour,
+		Timeout:          time.Second,
+		FailureThreshold: 3,
+		SuccessThreshold: 2,
+	})
+
+	hc.MarkUnhealthy("192.168.1.1", "connection refused")
+	hc.MarkUnhealthy("10.0.0.1", "")
+	if hc.IsHealthy("192.168.1.1") {
+		t.Fatal("expected IP to be unhealthy after MarkUnhealthy")
+	}
+	if info := hc.GetAllStatus()[0]; info.LastError != "connection refused" {
+		t.Errorf("LastError = %q", info.LastError)
+	}
+
+	// Recovery still takes SuccessThreshold passing checks
+	status := hc.statuses["192.168.1.1"]
+	status.RecordSuccess(2)
+	if hc.IsHealthy("192.168.1.1") {
+		t.Error("expected IP to stay out of rotation after one success")
+	}
+	status.RecordSuccess(2)
+	if !hc.IsHealthy("192.168.1.1") {
+		t.Error("expected IP to recover after SuccessThreshold successes")
+	}
+}
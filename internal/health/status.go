@@ -4,6 +4,8 @@ package health
 import (
 	"sync"
 	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
 )
 
 // HealthState represents the health state of an IP.
@@ -40,6 +42,7 @@ type IPStatus struct {
 	ConsecutiveSuccesses int
 	LastCheck            time.Time
 	LastError            error
+	clock                clock.Clock
 	mu                   sync.RWMutex
 }
 
@@ -48,6 +51,7 @@ func NewIPStatus(ip string) *IPStatus {
 	return &IPStatus{
 		IP:    ip,
 		State: StateHealthy,
+		clock: clock.Real,
 	}
 }
 
@@ -71,7 +75,7 @@ func (s *IPStatus) RecordSuccess(successThreshold int) bool {
 	s.mu.Lock()
 	defer s.mu.Unlock()
 
-	s.LastCheck = time.Now()
+	s.LastCheck = s.clock.Now()
 	s.LastError = nil
 	s.ConsecutiveFailures = 0
 	s.ConsecutiveSuccesses++
@@ -101,7 +105,7 @@ func (s *IPStatus) RecordFailure(err error, failureThreshold int) bool {
 	s.mu.Lock()
 	defer s.mu.Unlock()
 
-	s.LastCheck = time.Now()
+	s.LastCheck = s.clock.Now()
 	s.LastError = err
 	s.ConsecutiveSuccesses = 0
 	s.ConsecutiveFailures++
@@ -0,0 +1,164 @@
+// Package limiter provides connection limiting functionality.
+package limiter
+
+import (
+	"net"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// minBucketBurst is the minimum burst size of a token bucket in bytes.
+const minBucketBurst = 32 * 1024
+
+// TokenBucket is a byte-rate limiter safe for concurrent use.
+// Callers reserve tokens and sleep off any resulting debt, so concurrent
+// writers share the configured rate fairly.
+type TokenBucket struct {
+	rate   float64 // bytes per second
+	burst  float64
+	tokens float64
+	last   time.Time
+	mu     sync.Mutex
+}
+
+// NewTokenBucket creates a token bucket allowing bytesPerSec with the given burst.
+func NewTokenBucket(bytesPerSec float64, burst int) *TokenBucket {
+	return &TokenBucket{
+		rate:   bytesPerSec,
+		burst:  float64(burst),
+		tokens: float64(burst),
+		last:   time.Now(),
+	}
+}
+
+// reserve takes n tokens and returns how long the caller must wait before using them.
+func (b *TokenBucket) reserve(n int) time.Duration {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+
+	now := time.Now()
+	b.tokens += now.Sub(b.last).Seconds() * b.rate
+	if b.tokens > b.burst {
+		b.tokens = b.burst
+	}
+	b.last = now
+
+	b.tokens -= float64(n)
+	if b.tokens >= 0 {
+		return 0
+	}
+	return time.Duration(-b.tokens / b.rate * float64(time.Second))
+}
+
+// WaitN blocks until n bytes may be transferred and returns the time spent waiting.
+func (b *TokenBucket) WaitN(n int) time.Duration {
+	wait := b.reserve(n)
+	if wait > 0 {
+		time.Sleep(wait)
+	}
+	return wait
+}
+
+// shaperBuckets holds the per-direction buckets for a single IP.
+type shaperBuckets struct {
+	up   *TokenBucket
+	down *TokenBucket
+}
+
+// BandwidthShaper enforces per-IP bandwidth caps on outbound connections.
+// Each direction (upload and download) is capped independently.
+type BandwidthShaper struct {
+	buckets map[string]*shaperBuckets
+}
+
+// NewBandwidthShaper creates a shaper from per-IP caps in megabits per second.
+// IPs with a cap of zero or less are not shaped.
+func NewBandwidthShaper(capsMbps map[string]float64) *BandwidthShaper {
+	s := &BandwidthShaper{
+		buckets: make(map[string]*shaperBuckets, len(capsMbps)),
+	}
+	for ip, mbps := range capsMbps {
+		if mbps <= 0 {
+			continue
+		}
+		s.buckets[ip] = newShaperBuckets(mbps)
+	}
+	return s
+}
+
+// newShaperBuckets creates the buckets for a cap of mbps in each direction.
+func newShaperBuckets(mbps float64) *shaperBuckets {
+	bytesPerSec := mbps * 1000 * 1000 / 8
+	burst := int(bytesPerSec / 10)
+	if burst < minBucketBurst {
+		burst = minBucketBurst
+	}
+	return &shaperBuckets{
+		up:   NewTokenBucket(bytesPerSec, burst),
+		down: NewTokenBucket(bytesPerSec, burst),
+	}
+}
+
+// Wrap returns conn shaped according to the cap for ip.
+// Returns conn unchanged if the shaper is nil or the IP is uncapped.
+func (s *BandwidthShaper) Wrap(ip string, conn net.Conn) net.Conn {
+	if s == nil {
+		return conn
+	}
+	b, ok := s.buckets[ip]
+	if !ok {
+		return conn
+	}
+	return &shapedConn{Conn: conn, ip: ip, buckets: b}
+}
+
+// ShapeConn returns conn capped at mbps in each direction on its own, for
+// caps that apply per connection rather than per outbound IP. Throttling is
+// reported under ip. Returns conn unchanged if mbps is zero or less.
+func ShapeConn(ip string, conn net.Conn, mbps float64) net.Conn {
+	if mbps <= 0 {
+		return conn
+	}
+	return &shapedConn{Conn: conn, ip: ip, buckets: newShaperBuckets(mbps)}
+}
+
+// shapedConn is a net.Conn whose reads and writes are paced by token buckets.
+type shapedConn struct {
+	net.Conn
+	ip      string
+	buckets *shaperBuckets
+}
+
+// Read reads from the connection, then waits for download tokens.
+func (c *shapedConn) Read(p []byte) (int, error) {
+	n, err := c.Conn.Read(p)
+	if n > 0 {
+		if wait := c.buckets.down.WaitN(n); wait > 0 {
+			metrics.BandwidthThrottleSeconds.WithLabelValues(c.ip, "down").Add(wait.Seconds())
+		}
+	}
+	return n, err
+}
+
+// Write waits for upload tokens, then writes to the connection.
+func (c *shapedConn) Write(p []byte) (int, error) {
+	if wait := c.buckets.up.WaitN(len(p)); wait > 0 {
+		metrics.BandwidthThrottleSeconds.WithLabelValues(c.ip, "up").Add(wait.Seconds())
+	}
+	return c.Conn.Write(p)
+}
+
+// NetConn returns the underlying connection.
+func (c *shapedConn) NetConn() net.Conn {
+	return c.Conn
+}
+
+// CloseWrite half-closes the underlying connection if supported.
+func (c *shapedConn) CloseWrite() error {
+	if cw, ok := c.Conn.(interface{ CloseWrite() error }); ok {
+		return cw.CloseWrite()
+	}
+	return nil
+}
@@ -0,0 +1,85 @@
+package limiter
+
+import (
+	"io"
+	"net"
+	"testing"
+	"time"
+)
+
+func TestTokenBucket_BurstIsFree(t *testing.T) {
+	b := NewTokenBucket(1000, 1000)
+
+	if wait := b.WaitN(1000); wait != 0 {
+		t.Errorf("expected no wait within burst, got %v", wait)
+	}
+}
+
+func TestTokenBucket_Paces(t *testing.T) {
+	// 100KB/s with a 10KB burst: 30KB should take roughly 200ms
+	b := NewTokenBucket(100*1024, 10*1024)
+
+	start := time.Now()
+	for i := 0; i < 3; i++ {
+		b.WaitN(10 * 1024)
+	}
+	elapsed := time.Since(start)
+
+	if elapsed < 150*time.Millisecond {
+		t.Errorf("expected pacing of at least 150ms, got %v", elapsed)
+	}
+	if elapsed > 2*time.Second {
+		t.Errorf("pacing took too long: %v", elapsed)
+	}
+}
+
+func TestBandwidthShaper_UncappedPassthrough(t *testing.T) {
+	s := NewBandwidthShaper(map[string]float64{"10.0.0.1": 0})
+
+	c1, c2 := net.Pipe()
+	defer c1.Close()
+	defer c2.Close()
+
+	if got := s.Wrap("10.0.0.1", c1); got != c1 {
+		t.Error("expected uncapped IP to return the original connection")
+	}
+	if got := s.Wrap("10.0.0.2", c1); got != c1 {
+		t.Error("expected unknown IP to return the original connection")
+	}
+
+	var nilShaper *BandwidthShaper
+	if got := nilShaper.Wrap("10.0.0.1", c1); got != c1 {
+		t.Error("expected nil shaper to return the original connection")
+	}
+}
+
+func TestBandwidthShaper_CappedConn(t *testing.T) {
+	// 1 Mbps = 125000 bytes/s; burst is the 32KB minimum
+	s := NewBandwidthShaper(map[string]float64{"10.0.0.1": 1})
+
+	c1, c2 := net.Pipe()
+	defer c1.Close()
+	defer c2.Close()
+
+	shaped := s.Wrap("10.0.0.1", c1)
+	if shaped == c1 {
+		t.Fatal("expected capped IP to be wrapped")
+	}
+
+	go io.Copy(io.Discard, c2)
+
+	payload := make([]byte, 64*1024)
+	start := time.Now()
+	if _, err := shaped.Write(payload); err != nil {
+		t.Fatalf("write failed: %v", err)
+	}
+	if _, err := shaped.Write(payload); err != nil {
+		t.Fatalf("write failed: %v", err)
+	}
+	elapsed := time.Since(start)
+
+	// 128KB at 125KB/s with a 32KB burst needs at least ~750ms
+	if elapsed < 500*time.Millisecond {
+		t.Errorf("expected writes to be throttled, took %v", elapsed)
+	}
+}
@@ -0,0 +1,87 @@
+// Package limiter provides connection limiting functionality.
+package limiter
+
+import (
+	"errors"
+	"sync"
+)
+
+// ErrFairShareExceeded is returned when a user holds its full share of a nearly full pool.
+var ErrFairShareExceeded = errors.New("fair share of connections exceeded")
+
+// FairShare admits connections from users sharing a pool in proportion to
+// their weights once the pool is nearly full, so a user opening thousands of
+// connections can't crowd out one opening a handful. Below the threshold
+// every connection is admitted.
+type FairShare struct {
+	threshold float64
+	weights   map[string]float64
+	active    map[string]int64
+	mu        sync.Mutex
+}
+
+// NewFairShare creates a FairShare that applies once the pool is threshold
+// (0-1] full. Users without a weight have weight 1.
+func NewFairShare(threshold float64, weights map[string]float64) *FairShare {
+	return &FairShare{
+		threshold: threshold,
+		weights:   weights,
+		active:    make(map[string]int64),
+	}
+}
+
+// Acquire admits a connection for user, given inUse of capacity connection
+// slots are taken. Safe to call on a nil FairShare, which admits everything.
+func (f *FairShare) Acquire(user string, inUse, capacity int64) error {
+	if f == nil {
+		return nil
+	}
+	f.mu.Lock()
+	defer f.mu.Unlock()
+
+	if capacity > 0 && float64(inUse) >= f.threshold*float64(capacity) && float64(f.active[user]) >= f.share(user, capacity) {
+		return ErrFairShareExceeded
+	}
+	f.active[user]++
+	return nil
+}
+
+// Release releases a connection admitted for user. Safe to call on a nil FairShare.
+func (f *FairShare) Release(user string) {
+	if f == nil {
+		return
+	}
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	if f.active[user]--; f.active[user] <= 0 {
+		delete(f.active, user)
+	}
+}
+
+// Active returns the number of connections admitted for user.
+func (f *FairShare) Active(user string) int64 {
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	return f.active[user]
+}
+
+// share returns the connections user may hold: capacity split by weight among
+// the users with active connections plus user itself, and never less than one.
+// Must be called with mu held.
+func (f *FairShare) share(user string, capacity int64) float64 {
+	sum := f.weight(user)
+	for u := range f.active {
+		if u != user {
+			sum += f.weight(u)
+		}
+	}
+	return max(float64(capacity)*f.weight(user)/sum, 1)
+}
+
+// weight returns the share weight of user.
+func (f *FairShare) weight(user string) float64 {
+	if w, ok := f.weights[user]; ok {
+		return w
+	}
+	return 1
+}
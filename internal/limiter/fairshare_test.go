@@ -0,0 +1,85 @@
+package limiter
+
+import (
+	"errors"
+	"testing"
+)
+
+func TestFairShare_AdmitsBelowThreshold(t *testing.T) {
+	f := NewFairShare(0.8, nil)
+
+	// A single user may take the pool up to the threshold
+	for i := int64(0); i < 8; i++ {
+		if err := f.Acquire("greedy", i, 10); err != nil {
+			t.Fatalf("connection %d: unexpected error: %v", i, err)
+		}
+	}
+}
+
+func TestFairShare_ProtectsSmallUsers(t *testing.T) {
+	f := NewFairShare(0.5, nil)
+	const capacity = 10
+
+	// The greedy user fills half the pool, then is capped at its share
+	var inUse int64
+	for ; inUse < 5; inUse++ {
+		if err := f.Acquire("greedy", inUse, capacity); err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+	}
+	if err := f.Acquire("greedy", inUse, capacity); err != nil {
+		t.Fatalf("greedy user alone should get the whole pool, got %v", err)
+	}
+	inUse++
+
+	// A second user gets in even though the pool is nearly full
+	if err := f.Acquire("small", inUse, capacity); err != nil {
+		t.Fatalf("small user should be admitted: %v", err)
+	}
+	inUse++
+
+	// With two active users the greedy one is over its half share
+	if err := f.Acquire("greedy", inUse, capacity); !errors.Is(err, ErrFairShareExceeded) {
+		t.Errorf("expected ErrFairShareExceeded for the greedy user, got %v", err)
+	}
+	if err := f.Acquire("small", inUse, capacity); err != nil {
+		t.Errorf("small user under its share should be admitted: %v", err)
+	}
+
+	f.Release("small")
+	f.Release("small")
+	if got := f.Active("small"); got != 0 {
+		t.Errorf("Active after release = %d, want 0", got)
+	}
+}
+
+func TestFairShare_Weights(t *testing.T) {
+	f := NewFairShare(0, map[string]float64{"gold": 3})
+	const capacity = 8
+
+	// gold is entitled to 3/4 of the pool, bronze to 1/4
+	if err := f.Acquire("bronze", 0, capacity); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	var gold int
+	for f.Acquire("gold", 0, capacity) == nil {
+		gold++
+	}
+	if gold != 6 {
+		t.Errorf("gold admitted %d connections, want 6", gold)
+	}
+	if err := f.Acquire("bronze", 0, capacity); err != nil {
+		t.Errorf("bronze should be admitted up to its share of 2: %v", err)
+	}
+	if err := f.Acquire("bronze", 0, capacity); !errors.Is(err, ErrFairShareExceeded) {
+		t.Errorf("expected bronze to be at its share of 2, got %v", err)
+	}
+}
+
+func TestFairShare_Nil(t *testing.T) {
+	var f *FairShare
+	if err := f.Acquire("user", 100, 10); err != nil {
+		t.Errorf("nil FairShare should admit everything, got %v", err)
+	}
+	f.Release("user")
+}
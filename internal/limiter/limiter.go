@@ -125,6 +125,11 @@ func (l *Limiter) GetIPCount(ip string) int64 {
 	return counter.Load()
 }
 
+// MaxTotal returns the total connection limit.
+func (l *Limiter) MaxTotal() int64 {
+	return int64(l.maxTotal.Load())
+}
+
 // GetTotalCount returns the current total connection count.
 func (l *Limiter) GetTotalCount() int64 {
 	return l.total.Load()
@@ -0,0 +1,156 @@
+// Package limiter provides connection limiting functionality.
+package limiter
+
+import (
+	"errors"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// Errors returned by UserQuotas.
+var (
+	// ErrUserConnsExceeded is returned when a user holds its maximum of concurrent connections.
+	ErrUserConnsExceeded = errors.New("user connection limit exceeded")
+	// ErrQuotaExceeded is returned when a user has used up its monthly traffic.
+	ErrQuotaExceeded = errors.New("monthly traffic quota exceeded")
+)
+
+// UserQuota is the limits a connection is admitted under (0 = unlimited).
+type UserQuota struct {
+	MaxConns     int
+	MonthlyBytes int64
+}
+
+// userUsage is one user's open connections and traffic this month.
+type userUsage struct {
+	conns int
+	bytes int64
+	quota UserQuota
+}
+
+// UserQuotas caps each user's concurrent connections and the bytes it may
+// relay per calendar month (UTC). Traffic is counted when connections close,
+// so a long tunnel can overshoot the quota; the next connection is refused.
+// Only users with a limit are tracked. Usage is kept in memory and starts over
+// on restart.
+type UserQuotas struct {
+	users map[string]*userUsage
+	month time.Month
+	year  int
+	clock clock.Clock
+	mu    sync.Mutex
+}
+
+// NewUserQuotas creates an empty UserQuotas.
+func NewUserQuotas(c clock.Clock) *UserQuotas {
+	c = clock.OrReal(c)
+	year, month, _ := c.Now().UTC().Date()
+	return &UserQuotas{
+		users: make(map[string]*userUsage),
+		month: month,
+		year:  year,
+		clock: c,
+	}
+}
+
+// Acquire admits a connection for user under quota. The caller must call
+// Release when the connection closes. Safe to call on a nil UserQuotas.
+func (q *UserQuotas) Acquire(user string, quota UserQuota) error {
+	if q == nil {
+		return nil
+	}
+	q.mu.Lock()
+	defer q.mu.Unlock()
+	q.roll()
+
+	u, ok := q.users[user]
+	if !ok {
+		// Users without limits aren't tracked, so clients can't grow the map
+		if quota == (UserQuota{}) {
+			return nil
+		}
+		u = &userUsage{}
+		q.users[user] = u
+	}
+	// Limits from a reissued token or reloaded config apply from now on
+	u.quota = quota
+	if quota.MonthlyBytes > 0 && u.bytes >= quota.MonthlyBytes {
+		return ErrQuotaExceeded
+	}
+	if quota.MaxConns > 0 && u.conns >= quota.MaxConns {
+		return ErrUserConnsExceeded
+	}
+	u.conns++
+	return nil
+}
+
+// Release releases a connection admitted by Acquire.
+func (q *UserQuotas) Release(user string) {
+	if q == nil {
+		return
+	}
+	q.mu.Lock()
+	defer q.mu.Unlock()
+	u, ok := q.users[user]
+	if !ok || u.conns == 0 {
+		return
+	}
+	// Without a traffic quota there is nothing to remember once idle
+	if u.conns--; u.conns == 0 && u.quota.MonthlyBytes == 0 {
+		delete(q.users, user)
+	}
+}
+
+// AddBytes counts n bytes relayed for user against this month's traffic.
+func (q *UserQuotas) AddBytes(user string, n int64) {
+	if q == nil || n <= 0 {
+		return
+	}
+	q.mu.Lock()
+	defer q.mu.Unlock()
+	q.roll()
+	if u, ok := q.users[user]; ok {
+		u.bytes += n
+	}
+}
+
+// Usage returns the open connections, traffic this month and limits of every
+// user with a limit.
+func (q *UserQuotas) Usage() map[string]metrics.UserUsage {
+	if q == nil {
+		return nil
+	}
+	q.mu.Lock()
+	defer q.mu.Unlock()
+	q.roll()
+	usage := make(map[string]metrics.UserUsage, len(q.users))
+	for user, u := range q.users {
+		usage[user] = metrics.UserUsage{
+			ActiveConnections: u.conns,
+			MonthBytes:        u.bytes,
+			MaxConns:          u.quota.MaxConns,
+			MonthlyBytes:      u.quota.MonthlyBytes,
+		}
+	}
+	return usage
+}
+
+// roll starts a new month's traffic when the calendar month changed, dropping
+// users without open connections. The caller holds mu.
+func (q *UserQuotas) roll() {
+	year, month, _ := q.clock.Now().UTC().Date()
+	if year == q.year && month == q.month {
+		return
+	}
+	q.year, q.month = year, month
+	for user, u := range q.users {
+		if u.conns == 0 {
+			delete(q.users, user)
+			continue
+		}
+		u.bytes = 0
+	}
+}
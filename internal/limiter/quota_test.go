@@ -0,0 +1,84 @@
+package limiter
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func TestUserQuotas_MaxConns(t *testing.T) {
+	q := NewUserQuotas(clock.NewFake(time.Date(2026, 1, 15, 0, 0, 0, 0, time.UTC)))
+	quota := UserQuota{MaxConns: 2}
+
+	for i := 0; i < 2; i++ {
+		if err := q.Acquire("alice", quota); err != nil {
+			t.Fatalf("connection %d: unexpected error: %v", i, err)
+		}
+	}
+	if err := q.Acquire("alice", quota); !errors.Is(err, ErrUserConnsExceeded) {
+		t.Fatalf("Acquire() error = %v, want ErrUserConnsExceeded", err)
+	}
+	if err := q.Acquire("bob", quota); err != nil {
+		t.Errorf("Acquire(bob) unexpected error: %v", err)
+	}
+
+	q.Release("alice")
+	if err := q.Acquire("alice", quota); err != nil {
+		t.Errorf("Acquire() after release unexpected error: %v", err)
+	}
+	if got := q.Usage()["alice"].ActiveConnections; got != 2 {
+		t.Errorf("alice active connections = %d, want 2", got)
+	}
+}
+
+func TestUserQuotas_MonthlyBytes(t *testing.T) {
+	fake := clock.NewFake(time.Date(2026, 1, 31, 23, 0, 0, 0, time.UTC))
+	q := NewUserQuotas(fake)
+	quota := UserQuota{MonthlyBytes: 1000}
+
+	if err := q.Acquire("alice", quota); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	q.AddBytes("alice", 1500)
+	q.Release("alice")
+	if err := q.Acquire("alice", quota); !errors.Is(err, ErrQuotaExceeded) {
+		t.Fatalf("Acquire() error = %v, want ErrQuotaExceeded", err)
+	}
+	if got := q.Usage()["alice"].MonthBytes; got != 1500 {
+		t.Errorf("alice month bytes = %d, want 1500", got)
+	}
+
+	// A new month starts over
+	fake.Advance(2 * time.Hour)
+	if err := q.Acquire("alice", quota); err != nil {
+		t.Errorf("Acquire() in a new month unexpected error: %v", err)
+	}
+	if got := q.Usage()["alice"].MonthBytes; got != 0 {
+		t.Errorf("alice month bytes = %d after the month rolled, want 0", got)
+	}
+}
+
+func TestUserQuotas_Untracked(t *testing.T) {
+	q := NewUserQuotas(clock.NewFake(time.Date(2026, 1, 15, 0, 0, 0, 0, time.UTC)))
+
+	// Users without limits, or only a connection limit once idle, leave no entry
+	for _, user := range []string{"10.0.0.1", "10.0.0.2", "10.0.0.3"} {
+		if err := q.Acquire(user, UserQuota{}); err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		q.AddBytes(user, 100)
+		q.Release(user)
+	}
+	if err := q.Acquire("alice", UserQuota{MaxConns: 1}); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if len(q.Usage()) != 1 {
+		t.Errorf("expected only alice to be tracked, got %v", q.Usage())
+	}
+	q.Release("alice")
+	if len(q.Usage()) != 0 {
+		t.Errorf("expected no users tracked, got %v", q.Usage())
+	}
+}
@@ -0,0 +1,83 @@
+// Package limiter provides connection limiting functionality.
+package limiter
+
+import (
+	"errors"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+// ErrRateExceeded is returned when a user exceeds its request rate.
+var ErrRateExceeded = errors.New("request rate exceeded")
+
+// rateSweepInterval is how often buckets of idle users are dropped.
+const rateSweepInterval = time.Minute
+
+// UserRates limits how many requests per second each user may make, with a
+// token bucket per user that bursts up to one second's worth of requests.
+type UserRates struct {
+	buckets   map[string]*rateBucket
+	clock     clock.Clock
+	lastSweep time.Time
+	mu        sync.Mutex
+}
+
+// rateBucket is one user's token bucket.
+type rateBucket struct {
+	tokens float64
+	rate   float64
+	last   time.Time
+}
+
+// NewUserRates creates an empty UserRates.
+func NewUserRates(c clock.Clock) *UserRates {
+	c = clock.OrReal(c)
+	return &UserRates{
+		buckets:   make(map[string]*rateBucket),
+		clock:     c,
+		lastSweep: c.Now(),
+	}
+}
+
+// Allow admits a request from user limited to rate requests per second.
+// A rate of 0 admits everything. Safe to call on a nil UserRates.
+func (u *UserRates) Allow(user string, rate float64) error {
+	if u == nil || rate <= 0 {
+		return nil
+	}
+	u.mu.Lock()
+	defer u.mu.Unlock()
+
+	now := u.clock.Now()
+	u.sweep(now)
+	burst := max(rate, 1)
+	b, ok := u.buckets[user]
+	if !ok {
+		b = &rateBucket{tokens: burst, last: now}
+		u.buckets[user] = b
+	}
+	// A changed rate, e.g. from a reissued token, applies from now on
+	b.rate = rate
+	b.tokens = min(burst, b.tokens+now.Sub(b.last).Seconds()*rate)
+	b.last = now
+	if b.tokens < 1 {
+		return ErrRateExceeded
+	}
+	b.tokens--
+	return nil
+}
+
+// sweep drops the buckets of users idle long enough to have refilled.
+func (u *UserRates) sweep(now time.Time) {
+	if now.Sub(u.lastSweep) < rateSweepInterval {
+		return
+	}
+	u.lastSweep = now
+	for user, b := range u.buckets {
+		if b.tokens+now.Sub(b.last).Seconds()*b.rate >= max(b.rate, 1) {
+			delete(u.buckets, user)
+		}
+	}
+}
@@ -0,0 +1,82 @@
+package limiter
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func TestUserRates_Allow(t *testing.T) {
+	fake := clock.NewFake(time.Unix(0, 0))
+	u := NewUserRates(fake)
+
+	// Bursts up to one second's worth of requests
+	for i := 0; i < 2; i++ {
+		if err := u.Allow("alice", 2); err != nil {
+			t.Fatalf("request %d: unexpected error: %v", i, err)
+		}
+	}
+	if err := u.Allow("alice", 2); !errors.Is(err, ErrRateExceeded) {
+		t.Fatalf("Allow() error = %v, want ErrRateExceeded", err)
+	}
+
+	// Other users have their own bucket
+	if err := u.Allow("bob", 2); err != nil {
+		t.Errorf("Allow(bob) unexpected error: %v", err)
+	}
+
+	// Tokens refill at the rate
+	fake.Advance(500 * time.Millisecond)
+	if err := u.Allow("alice", 2); err != nil {
+		t.Errorf("Allow() after refill unexpected error: %v", err)
+	}
+	if err := u.Allow("alice", 2); !errors.Is(err, ErrRateExceeded) {
+		t.Errorf("Allow() error = %v, want ErrRateExceeded", err)
+	}
+}
+
+func TestUserRates_SlowRate(t *testing.T) {
+	fake := clock.NewFake(time.Unix(0, 0))
+	u := NewUserRates(fake)
+
+	if err := u.Allow("alice", 0.5); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	fake.Advance(time.Second)
+	if err := u.Allow("alice", 0.5); !errors.Is(err, ErrRateExceeded) {
+		t.Errorf("Allow() error = %v, want ErrRateExceeded", err)
+	}
+	fake.Advance(time.Second)
+	if err := u.Allow("alice", 0.5); err != nil {
+		t.Errorf("Allow() after two seconds unexpected error: %v", err)
+	}
+}
+
+func TestUserRates_Unlimited(t *testing.T) {
+	var nilRates *UserRates
+	if err := nilRates.Allow("alice", 1); err != nil {
+		t.Errorf("nil UserRates unexpected error: %v", err)
+	}
+	u := NewUserRates(nil)
+	for i := 0; i < 100; i++ {
+		if err := u.Allow("alice", 0); err != nil {
+			t.Fatalf("zero rate unexpected error: %v", err)
+		}
+	}
+}
+
+func TestUserRates_SweepsIdleUsers(t *testing.T) {
+	fake := clock.NewFake(time.Unix(0, 0))
+	u := NewUserRates(fake)
+	_ = u.Allow("alice", 1)
+	fake.Advance(rateSweepInterval)
+	_ = u.Allow("bob", 1)
+	if _, ok := u.buckets["alice"]; ok {
+		t.Error("idle user's bucket was not swept")
+	}
+	if _, ok := u.buckets["bob"]; !ok {
+		t.Error("active user's bucket was swept")
+	}
+}
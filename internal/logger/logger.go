@@ -208,9 +208,9 @@ func WithGroup(name string) *slog.Logger {
 	return Default().WithGroup(name)
 }
 
-// LogRequest logs a proxy request with standard fields.
-func LogRequest(method, host, sourceIP, outboundIP string, status int, duration int64, bytesIn, bytesOut int64) {
-	Default().Info("request",
+// LogRequest logs a proxy request with standard fields followed by any extra args.
+func LogRequest(method, host, sourceIP, outboundIP string, status int, duration int64, bytesIn, bytesOut int64, args ...any) {
+	Default().Info("request", append([]any{
 		"method", method,
 		"host", host,
 		"source_ip", sourceIP,
@@ -219,7 +219,7 @@ func LogRequest(method, host, sourceIP, outboundIP string, status int, duration
 		"duration_ms", duration,
 		"bytes_in", bytesIn,
 		"bytes_out", bytesOut,
-	)
+	}, args...)...)
 }
 
 // LogBalancerSelection logs IP selection by the balancer.
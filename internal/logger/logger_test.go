@@ -2,6 +2,7 @@ package logger
 
 import (
 	"bytes"
+	"log/slog"
 	"strings"
 	"testing"
 )
@@ -97,7 +98,7 @@ func TestLogRequest(t *testing.T) {
 	defaultLogger = log
 	defer func() { defaultLogger = oldDefault }()
 
-	LogRequest("GET", "example.com", "127.0.0.1:1234", "192.168.1.1", 200, 100, 1024, 2048)
+	LogRequest("GET", "example.com", "127.0.0.1:1234", "192.168.1.1", 200, 100, 1024, 2048, "selection", "fresh")
 
 	output := buf.String()
 	if !strings.Contains(output, "request") {
@@ -106,6 +107,9 @@ func TestLogRequest(t *testing.T) {
 	if !strings.Contains(output, "example.com") {
 		t.Error("expected host in output")
 	}
+	if !strings.Contains(output, `"selection":"fresh"`) {
+		t.Error("expected extra fields in output")
+	}
 }
 
 func TestLogBalancerSelection(t *testing.T) {
@@ -175,3 +179,35 @@ func TestDefault(t *testing.T) {
 		t.Error("expected non-nil default logger")
 	}
 }
+
+func TestModuleLevels(t *testing.T) {
+	var buf bytes.Buffer
+	oldDefault, oldLevel := defaultLogger, levelVar.Level()
+	defaultLogger = New("trace", "json", &buf)
+	levelVar.Set(slog.LevelInfo)
+	SetModuleLevels(map[string]string{"relay": "warn", "balancer": "debug"})
+	defer func() {
+		defaultLogger = oldDefault
+		levelVar.Set(oldLevel)
+		SetModuleLevels(nil)
+	}()
+
+	Module("relay").Info("relay_info")
+	Module("relay").Warn("relay_warn")
+	Module("balancer").Debug("balancer_debug")
+	Module("proxy").Debug("proxy_debug")
+	Module("proxy").Info("proxy_info")
+	Module("relay").Span("request_id", "abc").Warn("relay_span")
+
+	output := buf.String()
+	for _, want := range []string{"relay_warn", "balancer_debug", "proxy_info", `"module":"balancer"`, `"request_id":"abc"`} {
+		if !strings.Contains(output, want) {
+			t.Errorf("expected %s in output:\n%s", want, output)
+		}
+	}
+	for _, unwanted := range []string{"relay_info", "proxy_debug"} {
+		if strings.Contains(output, unwanted) {
+			t.Errorf("expected %s to be filtered:\n%s", unwanted, output)
+		}
+	}
+}
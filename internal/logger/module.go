@@ -0,0 +1,124 @@
+package logger
+
+import (
+	"context"
+	"log/slog"
+	"sync"
+	"sync/atomic"
+)
+
+// Modules are the subsystems whose level can be set on their own.
+var Modules = []string{"auth", "balancer", "config", "dns", "health", "proxy", "relay", "tracing"}
+
+// Module logs on behalf of one subsystem. Its records carry a "module"
+// attribute and are filtered by the module's own level when one is set, and
+// by the global level otherwise.
+type Module string
+
+var (
+	// moduleLevels maps module names to their level overrides.
+	moduleLevels atomic.Pointer[map[string]slog.Level]
+	// moduleLoggers caches each module's logger for the current handler.
+	moduleLoggers sync.Map
+)
+
+// moduleLogger is a module's logger and the handler it was built on.
+type moduleLogger struct {
+	base   slog.Handler
+	logger *slog.Logger
+}
+
+// SetModuleLevels replaces the per-module level overrides. Modules left out
+// follow the global level.
+func SetModuleLevels(levels map[string]string) {
+	parsed := make(map[string]slog.Level, len(levels))
+	for module, level := range levels {
+		parsed[module] = parseLevel(level)
+	}
+	moduleLevels.Store(&parsed)
+}
+
+// moduleLevel returns the minimum level logged for module.
+func moduleLevel(module string) slog.Level {
+	if levels := moduleLevels.Load(); levels != nil {
+		if level, ok := (*levels)[module]; ok {
+			return level
+		}
+	}
+	return levelVar.Level()
+}
+
+// moduleHandler applies a module's level in front of the shared handler.
+type moduleHandler struct {
+	slog.Handler
+	module string
+}
+
+// Enabled reports whether the module logs at level.
+func (h *moduleHandler) Enabled(_ context.Context, level slog.Level) bool {
+	return level >= moduleLevel(h.module)
+}
+
+// WithAttrs returns a moduleHandler for the same module with attrs added.
+func (h *moduleHandler) WithAttrs(attrs []slog.Attr) slog.Handler {
+	return &moduleHandler{Handler: h.Handler.WithAttrs(attrs), module: h.module}
+}
+
+// WithGroup returns a moduleHandler for the same module with the group opened.
+func (h *moduleHandler) WithGroup(name string) slog.Handler {
+	return &moduleHandler{Handler: h.Handler.WithGroup(name), module: h.module}
+}
+
+// Logger returns the module's logger. Loggers derived from it keep following
+// the module's level.
+func (m Module) Logger() *slog.Logger {
+	base := Default().Handler()
+	if cached, ok := moduleLoggers.Load(m); ok && cached.(*moduleLogger).base == base {
+		return cached.(*moduleLogger).logger
+	}
+	l := slog.New(&moduleHandler{Handler: base, module: string(m)}).With("module", string(m))
+	moduleLoggers.Store(m, &moduleLogger{base: base, logger: l})
+	return l
+}
+
+// Span returns the module's logger with the given attributes, such as a
+// connection's request ID, added to every record.
+func (m Module) Span(args ...any) Span {
+	return Span{m.Logger().With(args...)}
+}
+
+// Trace logs at trace level.
+func (m Module) Trace(msg string, args ...any) {
+	m.Logger().Log(context.Background(), LevelTrace, msg, args...)
+}
+
+// Debug logs at debug level.
+func (m Module) Debug(msg string, args ...any) {
+	m.Logger().Debug(msg, args...)
+}
+
+// Info logs at info level.
+func (m Module) Info(msg string, args ...any) {
+	m.Logger().Info(msg, args...)
+}
+
+// Warn logs at warn level.
+func (m Module) Warn(msg string, args ...any) {
+	m.Logger().Warn(msg, args...)
+}
+
+// Error logs at error level.
+func (m Module) Error(msg string, args ...any) {
+	m.Logger().Error(msg, args...)
+}
+
+// Span is a module logger carrying the attributes of one connection, so all
+// of its records can be found by them.
+type Span struct {
+	*slog.Logger
+}
+
+// Trace logs at trace level.
+func (s Span) Trace(msg string, args ...any) {
+	s.Log(context.Background(), LevelTrace, msg, args...)
+}
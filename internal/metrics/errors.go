@@ -0,0 +1,56 @@
+// Package metrics provides Prometheus metrics for the proxy.
+package metrics
+
+import (
+	"sort"
+	"strconv"
+
+	"github.com/prometheus/client_golang/prometheus"
+)
+
+// ErrorCount is the number of requests answered with one error status.
+type ErrorCount struct {
+	Method string `json:"method"`
+	Status string `json:"status"`
+	Count  int64  `json:"count"`
+}
+
+// ErrorCounts returns the requests answered with a 4xx or 5xx status per method
+// and status, most frequent first.
+func ErrorCounts() []ErrorCount {
+	families, err := prometheus.DefaultGatherer.Gather()
+	if err != nil {
+		return nil
+	}
+
+	var counts []ErrorCount
+	for _, mf := range families {
+		if mf.GetName() != "outbound_lb_requests_total" {
+			continue
+		}
+		for _, m := range mf.GetMetric() {
+			var ec ErrorCount
+			for _, label := range m.GetLabel() {
+				switch label.GetName() {
+				case "method":
+					ec.Method = label.GetValue()
+				case "status":
+					ec.Status = label.GetValue()
+				}
+			}
+			if code, err := strconv.Atoi(ec.Status); err != nil || code < 400 {
+				continue
+			}
+			ec.Count = int64(m.GetCounter().GetValue())
+			counts = append(counts, ec)
+		}
+	}
+
+	sort.Slice(counts, func(i, j int) bool {
+		if counts[i].Count != counts[j].Count {
+			return counts[i].Count > counts[j].Count
+		}
+		return counts[i].Method+counts[i].Status < counts[j].Method+counts[j].Status
+	})
+	return counts
+}
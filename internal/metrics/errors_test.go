@@ -0,0 +1,25 @@
+package metrics
+
+import "testing"
+
+func TestErrorCounts(t *testing.T) {
+	RequestsTotal.WithLabelValues("GET", "200").Add(5)
+	RequestsTotal.WithLabelValues("CONNECT", "502").Add(3)
+	RequestsTotal.WithLabelValues("GET", "503").Add(1)
+
+	var connect502, get503 int64
+	for _, ec := range ErrorCounts() {
+		if ec.Status == "200" {
+			t.Error("successful requests must not be counted as errors")
+		}
+		switch ec.Method + ec.Status {
+		case "CONNECT502":
+			connect502 = ec.Count
+		case "GET503":
+			get503 = ec.Count
+		}
+	}
+	if connect502 < 3 || get503 < 1 {
+		t.Errorf("CONNECT 502 = %d, GET 503 = %d, want at least 3 and 1", connect502, get503)
+	}
+}
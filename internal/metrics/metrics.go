@@ -3,6 +3,7 @@ package metrics
 
 import (
 	"sync/atomic"
+	"time"
 
 	"github.com/prometheus/client_golang/prometheus"
 	"github.com/prometheus/client_golang/prometheus/promauto"
@@ -64,6 +65,43 @@ var (
 		Help: "Total authentication failures",
 	})
 
+	// AuthCacheLookups tracks credential checks answered from or missing the auth cache.
+	AuthCacheLookups = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_auth_cache_lookups_total",
+		Help: "Total credential checks by auth cache result",
+	}, []string{"result"}) // result: "hit" or "miss"
+
+	// AuthWebhookRequests tracks credential checks delegated to the auth webhook.
+	AuthWebhookRequests = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_auth_webhook_requests_total",
+		Help: "Total auth webhook calls by result",
+	}, []string{"result"}) // result: "allow", "deny" or "error"
+
+	// AuthFileReloads tracks reloads of the --auth-file users file.
+	AuthFileReloads = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_auth_file_reloads_total",
+		Help: "Total users file reloads by result",
+	}, []string{"result"}) // result: "success" or "error"
+
+	// PolicyFileReloads tracks reloads of the --policy-file.
+	PolicyFileReloads = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_policy_file_reloads_total",
+		Help: "Total policy file reloads by result",
+	}, []string{"result"}) // result: "success" or "error"
+
+	// ClientACLRejections tracks client connections refused by the client allow/deny lists.
+	ClientACLRejections = promauto.NewCounter(prometheus.CounterOpts{
+		Name: "outbound_lb_client_acl_rejections_total",
+		Help: "Total client connections refused by the client allow/deny lists",
+	})
+
+	// PrivateDestinationRejections tracks outbound dials refused because the
+	// destination resolved to a private, loopback, link-local or metadata address.
+	PrivateDestinationRejections = promauto.NewCounter(prometheus.CounterOpts{
+		Name: "outbound_lb_private_destination_rejections_total",
+		Help: "Total outbound dials refused for private destination addresses",
+	})
+
 	// TunnelConnections tracks CONNECT tunnel connections.
 	TunnelConnections = promauto.NewCounter(prometheus.CounterOpts{
 		Name: "outbound_lb_tunnel_connections_total",
@@ -90,6 +128,99 @@ var (
 		Help: "Total health checks by IP and result",
 	}, []string{"ip", "result"}) // result: "success" or "failure"
 
+	// ExitIPChanges counts changes of the public exit address observed through an IP.
+	ExitIPChanges = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_exit_ip_changes_total",
+		Help: "Total changes of the public exit address observed through an outbound IP",
+	}, []string{"ip"})
+
+	// ExitIPShared indicates whether an IP leaves through the same exit as another IP.
+	ExitIPShared = promauto.NewGaugeVec(prometheus.GaugeOpts{
+		Name: "outbound_lb_exit_ip_shared",
+		Help: "Whether an outbound IP shares its public exit address with another IP (1) or not (0)",
+	}, []string{"ip"})
+
+	// CircuitBreakerState tracks the circuit breaker state per IP.
+	CircuitBreakerState = promauto.NewGaugeVec(prometheus.GaugeOpts{
+		Name: "outbound_lb_circuit_breaker_state",
+		Help: "Circuit breaker state per outbound IP (0=closed, 1=open, 2=half-open)",
+	}, []string{"ip"})
+
+	// ConnectRetries counts CONNECT dials retried on an alternate outbound IP.
+	ConnectRetries = promauto.NewCounter(prometheus.CounterOpts{
+		Name: "outbound_lb_connect_retries_total",
+		Help: "Total CONNECT dials retried on an alternate outbound IP",
+	})
+
+	// ConnectHedges counts hedged CONNECT dials by which attempt connected first.
+	ConnectHedges = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_connect_hedges_total",
+		Help: "Total hedged CONNECT dials by winner (primary, hedge or none)",
+	}, []string{"winner"})
+
+	// SourceFailovers counts dials that reached their destination from an
+	// alternate source address of an outbound IP after another was refused.
+	SourceFailovers = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_source_failovers_total",
+		Help: "Total dials failed over to an alternate source address, by outbound IP",
+	}, []string{"ip"})
+
+	// OutlierEjections counts IPs ejected by passive outlier detection.
+	OutlierEjections = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_outlier_ejections_total",
+		Help: "Total IPs ejected from rotation by passive outlier detection",
+	}, []string{"ip"})
+
+	// Quarantines counts IPs quarantined for a spike of 429/403 responses.
+	Quarantines = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_quarantines_total",
+		Help: "Total IPs taken out of rotation because targets answered them with a high rate of 429/403",
+	}, []string{"ip"})
+
+	// BackendConnectDuration tracks how long outbound connections take to establish from each IP.
+	BackendConnectDuration = promauto.NewHistogramVec(prometheus.HistogramOpts{
+		Name:    "outbound_lb_backend_connect_duration_seconds",
+		Help:    "Time to establish outbound connections from each IP, including DNS resolution",
+		Buckets: []float64{0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1, 2.5, 5, 10},
+	}, []string{"ip"})
+
+	// BackendTunnelDuration tracks how long tunnels through each IP stay open.
+	BackendTunnelDuration = promauto.NewHistogramVec(prometheus.HistogramOpts{
+		Name:    "outbound_lb_backend_tunnel_duration_seconds",
+		Help:    "Lifetime of CONNECT, SOCKS, forwarded, transparent and WebSocket tunnels through each IP",
+		Buckets: []float64{0.1, 1, 5, 15, 60, 300, 900, 3600, 14400},
+	}, []string{"ip"})
+
+	// BackendBytes tracks bytes exchanged with destinations through each IP.
+	BackendBytes = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_backend_bytes_total",
+		Help: "Total bytes exchanged with destinations through each IP",
+	}, []string{"ip", "direction"}) // direction: "up" or "down"
+
+	// EgressRTT tracks the mean TCP RTT of active tunnels through each IP.
+	EgressRTT = promauto.NewGaugeVec(prometheus.GaugeOpts{
+		Name: "outbound_lb_egress_rtt_seconds",
+		Help: "Mean TCP round-trip time of active tunnels through each IP",
+	}, []string{"ip"})
+
+	// EgressRetransmitRatio tracks the fraction of segments retransmitted through each IP.
+	EgressRetransmitRatio = promauto.NewGaugeVec(prometheus.GaugeOpts{
+		Name: "outbound_lb_egress_retransmit_ratio",
+		Help: "Fraction of TCP segments retransmitted by active tunnels through each IP",
+	}, []string{"ip"})
+
+	// EgressDegradations counts IPs flagged for sharply degraded RTT or retransmissions.
+	EgressDegradations = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_egress_degradations_total",
+		Help: "Total times an IP was flagged for degraded RTT or retransmissions",
+	}, []string{"ip"})
+
+	// EgressAnomalies counts anomalies raised for IPs deviating from their traffic baseline.
+	EgressAnomalies = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_egress_anomalies_total",
+		Help: "Total anomalies raised for IPs whose error rate, request rate or throughput deviated from their baseline",
+	}, []string{"ip", "metric"}) // metric: "error_rate", "requests" or "bytes"
+
 	// IPHealthStatus tracks current health status per IP (1=healthy, 0=unhealthy).
 	IPHealthStatus = promauto.NewGaugeVec(prometheus.GaugeOpts{
 		Name: "outbound_lb_ip_health_status",
@@ -103,6 +234,12 @@ var (
 		Buckets: []float64{0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1, 5},
 	}, []string{"ip"})
 
+	// KeepWarmProbes counts keep-warm probes sent through idle IPs by result.
+	KeepWarmProbes = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_keep_warm_probes_total",
+		Help: "Total keep-warm probes through idle IPs by result",
+	}, []string{"ip", "result"}) // result: "success" or "failure"
+
 	// HealthyIPs tracks the number of healthy IPs.
 	HealthyIPs = promauto.NewGauge(prometheus.GaugeOpts{
 		Name: "outbound_lb_healthy_ips",
@@ -114,6 +251,137 @@ var (
 		Name: "outbound_lb_unhealthy_ips",
 		Help: "Number of unhealthy IPs",
 	})
+
+	// Drain metrics
+
+	// IPDraining indicates whether an IP is draining (1) or in service (0).
+	IPDraining = promauto.NewGaugeVec(prometheus.GaugeOpts{
+		Name: "outbound_lb_ip_draining",
+		Help: "Whether an outbound IP is draining (1) or in service (0)",
+	}, []string{"ip"})
+
+	// DrainMigrations counts keep-alive client requests moved off a draining IP.
+	DrainMigrations = promauto.NewCounter(prometheus.CounterOpts{
+		Name: "outbound_lb_drain_migrations_total",
+		Help: "Total keep-alive client requests moved to a new IP because the previous one was draining",
+	})
+
+	// MaintenanceRejections counts new connections refused by maintenance mode.
+	MaintenanceRejections = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_maintenance_rejections_total",
+		Help: "Total new connections refused because the proxy or their pool is in maintenance",
+	}, []string{"pool"})
+
+	// MaintenanceActive is 1 while the proxy (pool="") or a pool is in maintenance.
+	MaintenanceActive = promauto.NewGaugeVec(prometheus.GaugeOpts{
+		Name: "outbound_lb_maintenance_active",
+		Help: "Whether the proxy (empty pool) or a pool is in maintenance (1) or not (0)",
+	}, []string{"pool"})
+
+	// DNS metrics
+
+	// DNSLookups counts hostname lookups by cache outcome.
+	DNSLookups = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_dns_lookups_total",
+		Help: "Total hostname lookups by cache outcome",
+	}, []string{"cache"}) // cache: "hit" or "miss"
+
+	// DNSQueryDuration tracks upstream DNS query latency per resolver.
+	DNSQueryDuration = promauto.NewHistogramVec(prometheus.HistogramOpts{
+		Name:    "outbound_lb_dns_query_duration_seconds",
+		Help:    "Upstream DNS query duration in seconds per resolver",
+		Buckets: []float64{0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1, 5},
+	}, []string{"server"})
+
+	// DNSQueryErrors counts failed upstream DNS queries per resolver and error type.
+	DNSQueryErrors = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_dns_query_errors_total",
+		Help: "Total failed upstream DNS queries per resolver and error type",
+	}, []string{"server", "type"}) // type: "nxdomain", "servfail", "timeout" or "other"
+
+	// DNSCacheEntries tracks the number of cached names.
+	DNSCacheEntries = promauto.NewGauge(prometheus.GaugeOpts{
+		Name: "outbound_lb_dns_cache_entries",
+		Help: "Number of names in the DNS cache",
+	})
+
+	// DNSForwardedQueries counts queries relayed by the DNS forwarder listener.
+	DNSForwardedQueries = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_dns_forwarded_queries_total",
+		Help: "Total queries relayed by the DNS forwarder per protocol and outcome",
+	}, []string{"protocol", "result"}) // protocol: "udp" or "tcp"; result: "ok", "error" or "dropped"
+
+	// File descriptor metrics
+
+	// FDUsageRatio tracks open file descriptors as a fraction of the process limit.
+	FDUsageRatio = promauto.NewGauge(prometheus.GaugeOpts{
+		Name: "outbound_lb_fd_usage_ratio",
+		Help: "Open file descriptors as a fraction of the process limit",
+	})
+
+	// TunnelEvictions counts idle tunnels closed to relieve file descriptor pressure.
+	TunnelEvictions = promauto.NewCounter(prometheus.CounterOpts{
+		Name: "outbound_lb_tunnel_evictions_total",
+		Help: "Total idle tunnels closed to relieve file descriptor pressure",
+	})
+
+	// Tunnel progress metrics
+
+	// TunnelProgressEvents counts progress reports emitted for long tunnels.
+	TunnelProgressEvents = promauto.NewCounter(prometheus.CounterOpts{
+		Name: "outbound_lb_tunnel_progress_events_total",
+		Help: "Total progress reports emitted for long tunnels",
+	})
+
+	// LongTunnels tracks the tunnels currently over the progress reporting thresholds.
+	LongTunnels = promauto.NewGauge(prometheus.GaugeOpts{
+		Name: "outbound_lb_long_tunnels",
+		Help: "Tunnels currently over the progress reporting thresholds",
+	})
+
+	// LongTunnelThroughput tracks the combined throughput of long tunnels.
+	LongTunnelThroughput = promauto.NewGaugeVec(prometheus.GaugeOpts{
+		Name: "outbound_lb_long_tunnel_throughput_bytes_per_second",
+		Help: "Combined throughput of tunnels over the progress reporting thresholds",
+	}, []string{"direction"}) // direction: "up" or "down"
+
+	// Bandwidth metrics
+
+	// BandwidthThrottleSeconds tracks time spent waiting on per-IP bandwidth caps.
+	BandwidthThrottleSeconds = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_bandwidth_throttle_seconds_total",
+		Help: "Total time spent throttled by per-IP bandwidth caps",
+	}, []string{"ip", "direction"}) // direction: "up" or "down"
+
+	// SLO metrics
+
+	// SLORequests counts requests evaluated against SLOs by result.
+	SLORequests = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_slo_requests_total",
+		Help: "Total requests evaluated against SLOs by result",
+	}, []string{"slo", "result"}) // result: "good", "slow" or "error"
+
+	// SLOBurnRate tracks the current error budget burn rate per SLO.
+	SLOBurnRate = promauto.NewGaugeVec(prometheus.GaugeOpts{
+		Name: "outbound_lb_slo_burn_rate",
+		Help: "Error budget burn rate per SLO over its window (1 = budget exhausted at window end)",
+	}, []string{"slo", "kind"}) // kind: "latency" or "error"
+
+	// Sniffing metrics
+
+	// SniffedProtocols counts tunnels by the protocol detected on the client side.
+	SniffedProtocols = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_sniffed_protocols_total",
+		Help: "Total tunnels by sniffed client protocol",
+	}, []string{"protocol"}) // protocol: "tls", "http" or "other"
+
+	// Tracing metrics
+
+	// TraceSpans counts spans handed to the OTLP exporter by outcome.
+	TraceSpans = promauto.NewCounterVec(prometheus.CounterOpts{
+		Name: "outbound_lb_trace_spans_total",
+		Help: "Total spans handed to the OTLP exporter by result",
+	}, []string{"result"}) // result: "exported", "dropped" or "failed"
 )
 
 // Stats holds runtime statistics for the /stats endpoint.
@@ -124,6 +392,30 @@ type Stats struct {
 	BytesReceived     int64            `json:"bytes_received"`
 	ConnectionsPerIP  map[string]int64 `json:"connections_per_ip"`
 	SelectionsPerIP   map[string]int64 `json:"selections_per_ip"`
+	// BytesPerIP is the traffic each IP has exchanged with destinations.
+	BytesPerIP map[string]IPBytes `json:"bytes_per_ip"`
+}
+
+// IPBytes is the traffic an outbound IP has sent to (Up) and received from
+// (Down) destinations.
+type IPBytes struct {
+	Up   int64 `json:"up"`
+	Down int64 `json:"down"`
+}
+
+// ipBytes accumulates an IPBytes.
+type ipBytes struct {
+	up   atomic.Int64
+	down atomic.Int64
+}
+
+// UserUsage is one user's open connections, traffic this calendar month and
+// limits (0 = unlimited).
+type UserUsage struct {
+	ActiveConnections int   `json:"active_connections"`
+	MonthBytes        int64 `json:"month_bytes"`
+	MaxConns          int   `json:"max_conns,omitempty"`
+	MonthlyBytes      int64 `json:"monthly_bytes,omitempty"`
 }
 
 // StatsCollector collects runtime statistics.
@@ -134,17 +426,23 @@ type StatsCollector struct {
 	bytesReceived     atomic.Int64
 	connectionsPerIP  map[string]*atomic.Int64
 	selectionsPerIP   map[string]*atomic.Int64
+	lastActivityPerIP map[string]*atomic.Int64
+	bytesPerIP        map[string]*ipBytes
 }
 
 // NewStatsCollector creates a new stats collector.
 func NewStatsCollector(ips []string) *StatsCollector {
 	sc := &StatsCollector{
-		connectionsPerIP: make(map[string]*atomic.Int64),
-		selectionsPerIP:  make(map[string]*atomic.Int64),
+		connectionsPerIP:  make(map[string]*atomic.Int64),
+		selectionsPerIP:   make(map[string]*atomic.Int64),
+		lastActivityPerIP: make(map[string]*atomic.Int64),
+		bytesPerIP:        make(map[string]*ipBytes),
 	}
 	for _, ip := range ips {
 		sc.connectionsPerIP[ip] = &atomic.Int64{}
 		sc.selectionsPerIP[ip] = &atomic.Int64{}
+		sc.lastActivityPerIP[ip] = &atomic.Int64{}
+		sc.bytesPerIP[ip] = &ipBytes{}
 	}
 	return sc
 }
@@ -183,6 +481,7 @@ func (sc *StatsCollector) IncConnectionsForIP(ip string) {
 	if counter, ok := sc.connectionsPerIP[ip]; ok {
 		counter.Add(1)
 	}
+	sc.touch(ip)
 	ConnectionsPerIP.WithLabelValues(ip).Inc()
 }
 
@@ -191,9 +490,31 @@ func (sc *StatsCollector) DecConnectionsForIP(ip string) {
 	if counter, ok := sc.connectionsPerIP[ip]; ok {
 		counter.Add(-1)
 	}
+	sc.touch(ip)
 	ConnectionsPerIP.WithLabelValues(ip).Dec()
 }
 
+// touch records that ip carried traffic now.
+func (sc *StatsCollector) touch(ip string) {
+	if last, ok := sc.lastActivityPerIP[ip]; ok {
+		last.Store(time.Now().UnixNano())
+	}
+}
+
+// LastActivity returns when ip last opened or closed a connection, or the
+// current time while it has connections open. Returns the zero time if ip
+// has not been used.
+func (sc *StatsCollector) LastActivity(ip string) time.Time {
+	if counter, ok := sc.connectionsPerIP[ip]; ok && counter.Load() > 0 {
+		return time.Now()
+	}
+	last, ok := sc.lastActivityPerIP[ip]
+	if !ok || last.Load() == 0 {
+		return time.Time{}
+	}
+	return time.Unix(0, last.Load())
+}
+
 // IncSelectionsForIP increments selections for an IP.
 func (sc *StatsCollector) IncSelectionsForIP(ip, host string) {
 	if counter, ok := sc.selectionsPerIP[ip]; ok {
@@ -202,6 +523,24 @@ func (sc *StatsCollector) IncSelectionsForIP(ip, host string) {
 	BalancerSelections.WithLabelValues(ip, host).Inc()
 }
 
+// AddBytesForIP adds to the bytes ip sent to (up) and received from (down)
+// destinations.
+func (sc *StatsCollector) AddBytesForIP(ip string, up, down int64) {
+	if b, ok := sc.bytesPerIP[ip]; ok {
+		b.up.Add(up)
+		b.down.Add(down)
+	}
+	BackendBytes.WithLabelValues(ip, "up").Add(float64(up))
+	BackendBytes.WithLabelValues(ip, "down").Add(float64(down))
+}
+
+// ObserveTunnel records a finished tunnel through ip: how long it was open
+// and the bytes it sent to and received from the destination.
+func (sc *StatsCollector) ObserveTunnel(ip string, d time.Duration, up, down int64) {
+	BackendTunnelDuration.WithLabelValues(ip).Observe(d.Seconds())
+	sc.AddBytesForIP(ip, up, down)
+}
+
 // GetStats returns current statistics.
 func (sc *StatsCollector) GetStats() Stats {
 	connsPerIP := make(map[string]int64)
@@ -212,6 +551,10 @@ func (sc *StatsCollector) GetStats() Stats {
 	for ip, counter := range sc.selectionsPerIP {
 		selsPerIP[ip] = counter.Load()
 	}
+	bytesPerIP := make(map[string]IPBytes)
+	for ip, b := range sc.bytesPerIP {
+		bytesPerIP[ip] = IPBytes{Up: b.up.Load(), Down: b.down.Load()}
+	}
 	return Stats{
 		ActiveConnections: sc.activeConnections.Load(),
 		TotalRequests:     sc.totalRequests.Load(),
@@ -219,5 +562,6 @@ func (sc *StatsCollector) GetStats() Stats {
 		BytesReceived:     sc.bytesReceived.Load(),
 		ConnectionsPerIP:  connsPerIP,
 		SelectionsPerIP:   selsPerIP,
+		BytesPerIP:        bytesPerIP,
 	}
 }
@@ -2,6 +2,7 @@ package metrics
 
 import (
 	"testing"
+	"time"
 )
 
 func TestNewStatsCollector(t *testing.T) {
@@ -48,6 +49,25 @@ func TestStatsCollector_TotalRequests(t *testing.T) {
 	}
 }
 
+func TestStatsCollector_BytesPerIP(t *testing.T) {
+	sc := NewStatsCollector([]string{"192.168.1.1", "192.168.1.2"})
+
+	sc.AddBytesForIP("192.168.1.1", 100, 2000)
+	sc.ObserveTunnel("192.168.1.1", 3*time.Second, 50, 500)
+	sc.AddBytesForIP("10.0.0.1", 1, 1) // unknown IPs are ignored
+
+	stats := sc.GetStats()
+	if got := stats.BytesPerIP["192.168.1.1"]; got != (IPBytes{Up: 150, Down: 2500}) {
+		t.Errorf("192.168.1.1 bytes = %+v, want 150 up and 2500 down", got)
+	}
+	if got := stats.BytesPerIP["192.168.1.2"]; got != (IPBytes{}) {
+		t.Errorf("192.168.1.2 bytes = %+v, want none", got)
+	}
+	if _, ok := stats.BytesPerIP["10.0.0.1"]; ok {
+		t.Error("expected unknown IPs to be left out")
+	}
+}
+
 func TestStatsCollector_Bytes(t *testing.T) {
 	sc := NewStatsCollector([]string{"192.168.1.1"})
 
@@ -103,6 +123,27 @@ func TestStatsCollector_SelectionsPerIP(t *testing.T) {
 	}
 }
 
+func TestStatsCollector_LastActivity(t *testing.T) {
+	sc := NewStatsCollector([]string{"192.168.1.1"})
+
+	if got := sc.LastActivity("192.168.1.1"); !got.IsZero() {
+		t.Errorf("expected zero time for unused IP, got %v", got)
+	}
+
+	before := time.Now()
+	sc.IncConnectionsForIP("192.168.1.1")
+	if got := sc.LastActivity("192.168.1.1"); got.Before(before) {
+		t.Errorf("expected activity while connection is open, got %v", got)
+	}
+
+	sc.DecConnectionsForIP("192.168.1.1")
+	closed := sc.LastActivity("192.168.1.1")
+	time.Sleep(10 * time.Millisecond)
+	if got := sc.LastActivity("192.168.1.1"); !got.Equal(closed) {
+		t.Errorf("expected activity to stay at close time %v, got %v", closed, got)
+	}
+}
+
 func TestStatsCollector_UnknownIP(t *testing.T) {
 	sc := NewStatsCollector([]string{"192.168.1.1"})
 
@@ -3,8 +3,10 @@ package metrics
 
 import (
 	"context"
+	"crypto/subtle"
 	"encoding/json"
 	"fmt"
+	"net"
 	"net/http"
 	"sync/atomic"
 	"time"
@@ -15,9 +17,13 @@ import (
 // Server is the metrics HTTP server.
 type Server struct {
 	server    *http.Server
+	mux       *http.ServeMux
 	stats     *StatsCollector
 	ready     atomic.Bool
 	startTime time.Time
+	// adminToken is the bearer token admin endpoints require; empty limits
+	// them to loopback clients.
+	adminToken string
 }
 
 // NewServer creates a new metrics server.
@@ -33,6 +39,7 @@ func NewServer(port int, stats *StatsCollector) *Server {
 	mux.HandleFunc("/ready", s.readyHandler)
 	mux.HandleFunc("/stats", s.statsHandler)
 
+	s.mux = mux
 	s.server = &http.Server{
 		Addr:         fmt.Sprintf(":%d", port),
 		Handler:      mux,
@@ -43,11 +50,63 @@ func NewServer(port int, stats *StatsCollector) *Server {
 	return s
 }
 
+// Handle registers an additional handler.
+func (s *Server) Handle(pattern string, handler http.Handler) {
+	s.mux.Handle(pattern, handler)
+}
+
+// SetAdminToken requires "Authorization: Bearer token" on admin endpoints.
+// Without a token they only answer loopback clients. It must be called
+// before HandleAdmin.
+func (s *Server) SetAdminToken(token string) {
+	s.adminToken = token
+}
+
+// HandleAdmin registers an admin endpoint, which can change the proxy's state
+// or expose its traffic, behind the admin token.
+func (s *Server) HandleAdmin(pattern string, handler http.Handler) {
+	s.mux.Handle(pattern, s.adminGuard(handler))
+}
+
+// adminGuard admits requests carrying the admin token or, without one, from
+// loopback addresses.
+func (s *Server) adminGuard(next http.Handler) http.Handler {
+	token := s.adminToken
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if token != "" {
+			if subtle.ConstantTimeCompare([]byte(r.Header.Get("Authorization")), []byte("Bearer "+token)) != 1 {
+				w.Header().Set("WWW-Authenticate", `Bearer realm="outbound-lb admin"`)
+				http.Error(w, "Unauthorized", http.StatusUnauthorized)
+				return
+			}
+		} else if !isLoopback(r.RemoteAddr) {
+			http.Error(w, "Admin endpoints only answer loopback clients unless admin_token is set", http.StatusForbidden)
+			return
+		}
+		next.ServeHTTP(w, r)
+	})
+}
+
+// isLoopback reports whether addr is a loopback host:port.
+func isLoopback(addr string) bool {
+	host, _, err := net.SplitHostPort(addr)
+	if err != nil {
+		host = addr
+	}
+	ip := net.ParseIP(host)
+	return ip != nil && ip.IsLoopback()
+}
+
 // Start starts the metrics server.
 func (s *Server) Start() error {
 	return s.server.ListenAndServe()
 }
 
+// Serve serves the metrics endpoints on a pre-bound listener.
+func (s *Server) Serve(l net.Listener) error {
+	return s.server.Serve(l)
+}
+
 // Shutdown gracefully shuts down the server.
 func (s *Server) Shutdown(ctx context.Context) error {
 	return s.server.Shutdown(ctx)
@@ -151,3 +151,37 @@ func TestServer_Shutdown(t *testing.T) {
 		t.Errorf("unexpected shutdown error: %v", err)
 	}
 }
+
+func TestServer_HandleAdmin(t *testing.T) {
+	ok := http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) { w.WriteHeader(http.StatusOK) })
+	tests := []struct {
+		name   string
+		token  string
+		remote string
+		auth   string
+		want   int
+	}{
+		{"loopback without token", "", "127.0.0.1:40000", "", http.StatusOK},
+		{"ipv6 loopback without token", "", "[::1]:40000", "", http.StatusOK},
+		{"remote without token", "", "192.0.2.10:40000", "", http.StatusForbidden},
+		{"remote with token", "s3cret", "192.0.2.10:40000", "Bearer s3cret", http.StatusOK},
+		{"wrong token", "s3cret", "192.0.2.10:40000", "Bearer guess", http.StatusUnauthorized},
+		{"loopback missing token", "s3cret", "127.0.0.1:40000", "", http.StatusUnauthorized},
+	}
+	for _, tt := range tests {
+		server := NewServer(9090, NewStatsCollector([]string{"192.168.1.1"}))
+		server.SetAdminToken(tt.token)
+		server.HandleAdmin("/admin/drain", ok)
+
+		req := httptest.NewRequest(http.MethodPost, "/admin/drain", nil)
+		req.RemoteAddr = tt.remote
+		if tt.auth != "" {
+			req.Header.Set("Authorization", tt.auth)
+		}
+		w := httptest.NewRecorder()
+		server.mux.ServeHTTP(w, req)
+		if w.Code != tt.want {
+			t.Errorf("%s: status = %d, want %d", tt.name, w.Code, tt.want)
+		}
+	}
+}
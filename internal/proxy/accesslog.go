@@ -0,0 +1,110 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"encoding/json"
+	"fmt"
+	"io"
+	"os"
+	"strings"
+	"sync"
+)
+
+// clfTime is the timestamp layout of the Common Log Format.
+const clfTime = "02/Jan/2006:15:04:05 -0700"
+
+// accessLog writes access records to a dedicated file or stdout.
+type accessLog struct {
+	w      io.Writer
+	file   *os.File // nil for stdout
+	format string
+	mu     sync.Mutex
+}
+
+// write appends rec in the log's format.
+func (l *accessLog) write(rec AccessRecord) {
+	var line []byte
+	if l.format == "clf" {
+		line = []byte(formatCLF(rec))
+	} else {
+		line, _ = json.Marshal(rec)
+		line = append(line, '\n')
+	}
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	if _, err := l.w.Write(line); err != nil {
+		log.Debug("access_log_write_failed", "error", err)
+	}
+}
+
+// close closes the log's file, if any.
+func (l *accessLog) close() {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	if l.file != nil {
+		l.file.Close()
+	}
+}
+
+// formatCLF renders rec as a Common Log Format line. The request is the
+// method and destination; the size is the bytes sent to the client.
+// Client-supplied fields are escaped as Apache does, so they can't forge
+// lines or fields.
+func formatCLF(rec AccessRecord) string {
+	user := "-"
+	if rec.User != "" {
+		user = clfEscape(strings.ReplaceAll(rec.User, " ", "_"))
+	}
+	size := "-"
+	if rec.BytesOut > 0 {
+		size = fmt.Sprint(rec.BytesOut)
+	}
+	return fmt.Sprintf("%s - %s [%s] \"%s %s\" %d %s\n",
+		rec.SourceIP, user, rec.Time.Format(clfTime), clfEscape(rec.Method), clfEscape(rec.Host), rec.Status, size)
+}
+
+// clfEscape escapes quotes and backslashes with a backslash, and control and
+// non-ASCII bytes as \xNN.
+func clfEscape(s string) string {
+	var b strings.Builder
+	for i := 0; i < len(s); i++ {
+		switch c := s[i]; {
+		case c == '"' || c == '\\':
+			b.WriteByte('\\')
+			b.WriteByte(c)
+		case c < 0x20 || c >= 0x7f:
+			fmt.Fprintf(&b, "\\x%02x", c)
+		default:
+			b.WriteByte(c)
+		}
+	}
+	return b.String()
+}
+
+// OpenAccessLog starts writing access records to the access_log file, or to
+// stdout for "-", in access_log_format, instead of as "request" records in
+// the log. It may be called again to reopen the file after it was rotated.
+func (s *Server) OpenAccessLog() error {
+	if s.cfg.AccessLog == "" {
+		return nil
+	}
+	l := &accessLog{w: os.Stdout, format: s.cfg.AccessLogFormat}
+	if s.cfg.AccessLog != "-" {
+		f, err := os.OpenFile(s.cfg.AccessLog, os.O_WRONLY|os.O_CREATE|os.O_APPEND, 0o640)
+		if err != nil {
+			return err
+		}
+		l.w, l.file = f, f
+	}
+	if prev := s.accessLog.Swap(l); prev != nil {
+		prev.close()
+	}
+	return nil
+}
+
+// SetExitLookup reports in access records the public exit address that
+// lookup returns for the outbound IP, such as the one observed by
+// --exit-ip-check-url. It must be called before Start.
+func (s *Server) SetExitLookup(lookup func(ip string) string) {
+	s.exitOf = lookup
+}
@@ -0,0 +1,70 @@
+package proxy
+
+import (
+	"encoding/json"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestServer_AccessLogJSON(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.AccessLog = filepath.Join(t.TempDir(), "access.log")
+	server.SetExitLookup(func(ip string) string { return "203.0.113.7" })
+	if err := server.OpenAccessLog(); err != nil {
+		t.Fatalf("OpenAccessLog: %v", err)
+	}
+	t.Cleanup(func() { server.accessLog.Load().close() })
+
+	server.logRequest("CONNECT", "example.com:443", "192.0.2.10:51234", "alice", "127.0.0.1", 200, 1500, 300, 4000, provenance{selectionFresh, "example.com"})
+
+	data, err := os.ReadFile(server.cfg.AccessLog)
+	if err != nil {
+		t.Fatalf("read: %v", err)
+	}
+	var rec AccessRecord
+	if err := json.Unmarshal(data, &rec); err != nil {
+		t.Fatalf("expected one JSON record, got %q: %v", data, err)
+	}
+	want := AccessRecord{Method: "CONNECT", Host: "example.com:443", SourceIP: "192.0.2.10", User: "alice", OutboundIP: "127.0.0.1", ExitIP: "203.0.113.7", Status: 200, DurationMS: 1500, BytesIn: 300, BytesOut: 4000, Selection: selectionFresh}
+	rec.Time = time.Time{}
+	if rec != want {
+		t.Errorf("record = %+v, want %+v", rec, want)
+	}
+
+	// Reopening after rotation writes to the new file
+	rotated := server.cfg.AccessLog + ".1"
+	if err := os.Rename(server.cfg.AccessLog, rotated); err != nil {
+		t.Fatalf("rename: %v", err)
+	}
+	if err := server.OpenAccessLog(); err != nil {
+		t.Fatalf("reopen: %v", err)
+	}
+	server.logRequest("GET", "example.com", "192.0.2.10:51235", "", "127.0.0.1", 404, 20, 0, 120, provenance{selectionFresh, "example.com"})
+	if data, _ := os.ReadFile(server.cfg.AccessLog); !strings.Contains(string(data), `"status":404`) {
+		t.Errorf("expected the new record in the reopened file, got %q", data)
+	}
+}
+
+func TestFormatCLF(t *testing.T) {
+	at := time.Date(2026, 3, 2, 10, 4, 5, 0, time.FixedZone("", 3600))
+	tests := []struct {
+		rec  AccessRecord
+		want string
+	}{
+		{AccessRecord{Time: at, Method: "CONNECT", Host: "example.com:443", SourceIP: "192.0.2.10", User: "alice", Status: 200, BytesOut: 4000},
+			"192.0.2.10 - alice [02/Mar/2026:10:04:05 +0100] \"CONNECT example.com:443\" 200 4000\n"},
+		{AccessRecord{Time: at, Method: "GET", Host: "example.com", SourceIP: "192.0.2.10", Status: 403},
+			"192.0.2.10 - - [02/Mar/2026:10:04:05 +0100] \"GET example.com\" 403 -\n"},
+		// Injected newlines and quotes stay on one line inside their field
+		{AccessRecord{Time: at, Method: "GET", Host: "evil.example\"\n192.0.2.66 - admin", SourceIP: "192.0.2.10", User: "bob\r\n", Status: 403},
+			"192.0.2.10 - bob\\x0d\\x0a [02/Mar/2026:10:04:05 +0100] \"GET evil.example\\\"\\x0a192.0.2.66 - admin\" 403 -\n"},
+	}
+	for _, tt := range tests {
+		if got := formatCLF(tt.rec); got != tt.want {
+			t.Errorf("formatCLF() = %q, want %q", got, tt.want)
+		}
+	}
+}
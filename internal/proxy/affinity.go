@@ -0,0 +1,230 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"cmp"
+	"net"
+	"slices"
+	"sync"
+	"time"
+
+	"golang.org/x/net/publicsuffix"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+// affinityEntry is the outbound IP a client last used for a destination.
+type affinityEntry struct {
+	ip      string
+	expires time.Time
+	// untilError drops the entry when a connection through ip fails.
+	untilError bool
+}
+
+// affinityStore remembers the outbound IP each client used per destination.
+// Entries expire ttl, or their own period, after their last use; expired
+// entries are dropped lazily and swept at most once per ttl.
+type affinityStore struct {
+	entries   map[string]affinityEntry
+	ttl       time.Duration
+	lastSweep time.Time
+	clock     clock.Clock
+	mu        sync.Mutex
+}
+
+// newAffinityStore creates an affinityStore whose entries last ttl.
+func newAffinityStore(ttl time.Duration, c clock.Clock) *affinityStore {
+	c = clock.OrReal(c)
+	return &affinityStore{entries: make(map[string]affinityEntry), ttl: ttl, lastSweep: c.Now(), clock: c}
+}
+
+// get returns the unexpired outbound IP for key.
+func (as *affinityStore) get(key string) (string, bool) {
+	as.mu.Lock()
+	defer as.mu.Unlock()
+	e, ok := as.entries[key]
+	if !ok {
+		return "", false
+	}
+	if as.clock.Now().After(e.expires) {
+		delete(as.entries, key)
+		return "", false
+	}
+	return e.ip, true
+}
+
+// set records ip for key, extending its expiry.
+func (as *affinityStore) set(key, ip string) {
+	as.setFor(key, ip, as.ttl, false)
+}
+
+// setFor records ip for key for ttl after now. With untilError, the entry is
+// also dropped by forgetFailed.
+func (as *affinityStore) setFor(key, ip string, ttl time.Duration, untilError bool) {
+	now := as.clock.Now()
+	as.mu.Lock()
+	defer as.mu.Unlock()
+	if now.Sub(as.lastSweep) >= as.ttl {
+		for k, e := range as.entries {
+			if now.After(e.expires) {
+				delete(as.entries, k)
+			}
+		}
+		as.lastSweep = now
+	}
+	as.entries[key] = affinityEntry{ip: ip, expires: now.Add(ttl), untilError: untilError}
+}
+
+// forgetFailed drops the sticky-until-error entries on ip after a connection
+// through it failed. Safe to call on a nil affinityStore.
+func (as *affinityStore) forgetFailed(ip string) {
+	if as == nil {
+		return
+	}
+	as.mu.Lock()
+	defer as.mu.Unlock()
+	for k, e := range as.entries {
+		if e.untilError && e.ip == ip {
+			delete(as.entries, k)
+		}
+	}
+}
+
+// newUserAffinityStore creates the store for users and routing rules with a
+// sticky rotation policy, sweeping at the shortest of their periods, or
+// returns nil if none has one.
+func newUserAffinityStore(cfg *config.Config, c clock.Clock) *affinityStore {
+	var sweep time.Duration
+	sticky := func(policy string, ttl time.Duration) {
+		if policy == config.RotationPerRequest {
+			return
+		}
+		if ttl = cmp.Or(ttl, cfg.SessionAffinityTTL); sweep == 0 || ttl < sweep {
+			sweep = ttl
+		}
+	}
+	for _, r := range cfg.UserRotation {
+		sticky(r.Policy, r.TTL)
+	}
+	for _, r := range cfg.Rules {
+		if r.Action == config.RuleSetStrategy {
+			sticky(r.Strategy, r.TTL)
+		}
+	}
+	if sweep == 0 {
+		return nil
+	}
+	return newAffinityStore(sweep, c)
+}
+
+// stickiness is where and for how long a client keeps its outbound IP.
+type stickiness struct {
+	store      *affinityStore
+	key        string
+	ttl        time.Duration
+	untilError bool
+}
+
+// remember records ip as the client's outbound IP.
+func (st stickiness) remember(ip string) {
+	st.store.setFor(st.key, ip, st.ttl, st.untilError)
+}
+
+// affinityScope returns the part of host session affinity is keyed on: the
+// host itself, or with the domain scope its registrable domain (eTLD+1). IP
+// literals and names that are themselves public suffixes are used as-is.
+func affinityScope(host, scope string) string {
+	name := destinationName(host)
+	if scope != config.AffinityScopeDomain || net.ParseIP(name) != nil {
+		return name
+	}
+	if domain, err := publicsuffix.EffectiveTLDPlusOne(name); err == nil {
+		return domain
+	}
+	return name
+}
+
+// affinityKey returns where a client keeps its outbound IP, or false when it
+// rotates freely. A username session is keyed on the client and session for
+// every destination; otherwise the client's rotation policy keys on the client
+// and destination, destination affinity on the destination alone, shared by
+// every client, and session affinity on the client and destination. A
+// set_strategy routing rule overrides the user's rotation policy. A user's
+// rotation TTL also applies to its sessions.
+func (s *Server) affinityKey(host string, hints RoutingHints) (stickiness, bool) {
+	user := fairShareUser(hints.Tenant, hints.ClientIP)
+	rot, hasRot := s.cfg.RotationForUser(user)
+	if d := s.matchRules(host, hints); d.Strategy != "" {
+		rot, hasRot = config.UserRotation{User: user, Policy: d.Strategy, TTL: d.StrategyTTL}, true
+	}
+	if hints.Session != "" && s.sessions != nil {
+		return stickiness{store: s.sessions, key: user + "/session/" + hints.Session, ttl: cmp.Or(rot.TTL, s.sessions.ttl)}, true
+	}
+	scope := affinityScope(host, s.cfg.SessionAffinityScope)
+	key := user + "/" + scope
+	if hasRot {
+		if rot.Policy == config.RotationPerRequest || s.userAffinity == nil {
+			return stickiness{}, false
+		}
+		return stickiness{
+			store:      s.userAffinity,
+			key:        key,
+			ttl:        cmp.Or(rot.TTL, s.cfg.SessionAffinityTTL),
+			untilError: rot.Policy == config.RotationStickyUntilError,
+		}, true
+	}
+	if s.destAffinity != nil {
+		return stickiness{store: s.destAffinity, key: "dest/" + scope, ttl: s.destAffinity.ttl}, true
+	}
+	if s.affinity == nil {
+		return stickiness{}, false
+	}
+	return stickiness{store: s.affinity, key: key, ttl: s.affinity.ttl}, true
+}
+
+// selectExit selects an outbound IP for host honoring the routing hints. With
+// a username session, a sticky rotation policy or session affinity the client
+// keeps the outbound IP it last used while that IP is still allowed and
+// available. Otherwise a recent lookup of host through the DNS forwarder
+// chooses the exit it used, under the same conditions.
+func (s *Server) selectExit(host string, hints RoutingHints) (string, provenance, error) {
+	opts, prov, err := s.selectOptions(host, hints)
+	if err != nil {
+		return "", prov, err
+	}
+	st, sticky := s.affinityKey(host, hints)
+	if sticky && prov.source != selectionAffinity {
+		if ip, ok := st.store.get(st.key); ok && affinityAllowed(opts, ip) {
+			if ip, err := s.balancer.SelectWithOptions(host, balancer.SelectOptions{Candidates: []string{ip}}); err == nil {
+				st.remember(ip)
+				return ip, provenance{selectionSticky, st.key}, nil
+			}
+		}
+	}
+	if s.dnsExits != nil && prov.source != selectionAffinity {
+		name := destinationName(host)
+		if ip, ok := s.dnsExits.get(name); ok && affinityAllowed(opts, ip) {
+			if ip, err := s.balancer.SelectWithOptions(host, balancer.SelectOptions{Candidates: []string{ip}}); err == nil {
+				if sticky {
+					st.remember(ip)
+				}
+				return ip, provenance{selectionDNS, "dns/" + name}, nil
+			}
+		}
+	}
+	ip, err := s.balancer.SelectWithOptions(host, opts)
+	if err == nil && sticky {
+		st.remember(ip)
+	}
+	return ip, prov, err
+}
+
+// affinityAllowed reports whether the selection constraints still permit ip.
+func affinityAllowed(opts balancer.SelectOptions, ip string) bool {
+	if opts.Candidates != nil && !slices.Contains(opts.Candidates, ip) {
+		return false
+	}
+	return !slices.Contains(opts.Exclude, ip)
+}
@@ -0,0 +1,266 @@
+package proxy
+
+import (
+	"net"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+func TestAffinityScope(t *testing.T) {
+	tests := []struct {
+		host  string
+		scope string
+		want  string
+	}{
+		{"www.example.com:443", config.AffinityScopeHost, "www.example.com"},
+		{"www.example.com:443", config.AffinityScopeDomain, "example.com"},
+		{"API.Example.co.uk.", config.AffinityScopeDomain, "example.co.uk"},
+		{"10.0.0.1:80", config.AffinityScopeDomain, "10.0.0.1"},
+		{"[2001:db8::1]:443", config.AffinityScopeDomain, "2001:db8::1"},
+		{"co.uk", config.AffinityScopeDomain, "co.uk"},
+	}
+	for _, tt := range tests {
+		if got := affinityScope(tt.host, tt.scope); got != tt.want {
+			t.Errorf("affinityScope(%q, %q) = %q, want %q", tt.host, tt.scope, got, tt.want)
+		}
+	}
+}
+
+func TestAffinityStore_Expiry(t *testing.T) {
+	now := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	as := newAffinityStore(time.Minute, now)
+
+	as.set("alice/example.com", "10.0.0.1")
+	now.Advance(50 * time.Second)
+	if ip, ok := as.get("alice/example.com"); !ok || ip != "10.0.0.1" {
+		t.Fatalf("get() = %q, %v; want 10.0.0.1", ip, ok)
+	}
+
+	// Use extends the entry
+	as.set("alice/example.com", "10.0.0.1")
+	now.Advance(50 * time.Second)
+	if _, ok := as.get("alice/example.com"); !ok {
+		t.Fatal("expected entry refreshed by use to survive")
+	}
+
+	now.Advance(2 * time.Minute)
+	if _, ok := as.get("alice/example.com"); ok {
+		t.Error("expected entry to expire")
+	}
+
+	// Expired entries are swept when new ones are recorded
+	as.set("bob/example.com", "10.0.0.2")
+	now.Advance(2 * time.Minute)
+	as.set("carol/example.com", "10.0.0.3")
+	if len(as.entries) != 1 {
+		t.Errorf("entries = %d after sweep, want 1", len(as.entries))
+	}
+}
+
+func TestSelectExit_SessionAffinity(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	server.cfg.SessionAffinityScope = config.AffinityScopeDomain
+	server.affinity = newAffinityStore(time.Minute, clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC)))
+	alice := RoutingHints{Tenant: "alice", ClientIP: "192.0.2.10"}
+
+	first, prov, err := server.selectExit("www.example.com:443", alice)
+	if err != nil {
+		t.Fatalf("selectExit: %v", err)
+	}
+	if prov.source != selectionFresh {
+		t.Errorf("first selection = %q, want %q", prov.source, selectionFresh)
+	}
+	server.balancer.Record("www.example.com:443", first)
+
+	// Without affinity the LRU balancer would rotate; the client keeps its exit
+	// for every host of the registrable domain
+	for _, host := range []string{"www.example.com:443", "api.example.com:443", "example.com:80"} {
+		ip, prov, err := server.selectExit(host, alice)
+		if err != nil || ip != first {
+			t.Fatalf("%s: got %s, %v; want %s", host, ip, err, first)
+		}
+		if prov.source != selectionSticky || prov.key != "alice/example.com" {
+			t.Errorf("%s: provenance = %+v, want sticky alice/example.com", host, prov)
+		}
+		server.balancer.Record(host, ip)
+	}
+
+	// Other clients are balanced independently
+	if ip, _, _ := server.selectExit("www.example.com:443", RoutingHints{ClientIP: "192.0.2.20"}); ip == first {
+		t.Errorf("expected another client to get a different exit than %s", ip)
+	}
+
+	// An excluded exit is not kept
+	excluded := alice
+	excluded.Exclude = []string{first}
+	if ip, _, _ := server.selectExit("www.example.com:443", excluded); ip == first {
+		t.Errorf("expected excluded exit %s to be avoided", first)
+	}
+}
+
+func TestSelectExit_DestinationAffinity(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	fake := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	server.destAffinity = newAffinityStore(time.Minute, fake)
+
+	first, _, err := server.selectExit("www.example.com:443", RoutingHints{Tenant: "alice", ClientIP: "192.0.2.10"})
+	if err != nil {
+		t.Fatalf("selectExit: %v", err)
+	}
+	server.balancer.Record("www.example.com:443", first)
+
+	// Every client shares the destination's exit
+	for _, hints := range []RoutingHints{{Tenant: "bob", ClientIP: "192.0.2.20"}, {ClientIP: "198.51.100.7"}} {
+		ip, prov, err := server.selectExit("www.example.com:443", hints)
+		if err != nil || ip != first {
+			t.Fatalf("%+v: got %s, %v; want %s", hints, ip, err, first)
+		}
+		if prov.source != selectionSticky || prov.key != "dest/www.example.com" {
+			t.Errorf("%+v: provenance = %+v, want sticky dest/www.example.com", hints, prov)
+		}
+		server.balancer.Record("www.example.com:443", ip)
+	}
+
+	// Other destinations are balanced independently
+	if _, prov, _ := server.selectExit("api.other.org:443", RoutingHints{Tenant: "alice"}); prov.source != selectionFresh {
+		t.Errorf("new destination selection = %q, want %q", prov.source, selectionFresh)
+	}
+
+	// The destination's exit expires once idle for the TTL
+	fake.Advance(2 * time.Minute)
+	if _, prov, _ := server.selectExit("www.example.com:443", RoutingHints{Tenant: "bob"}); prov.source == selectionSticky {
+		t.Error("expected the idle destination exit to expire")
+	}
+}
+
+func TestSelectExit_DNSForwarderExit(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	server.cfg.UserPools = []config.UserPool{{User: "batch", IPs: []string{"127.0.0.3"}}}
+	fake := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	server.dnsExits = newAffinityStore(dnsExitTTL, fake)
+	client := &net.UDPAddr{IP: net.ParseIP("192.0.2.10"), Port: 5353}
+
+	// The connection after each lookup leaves through the lookup's exit
+	for i := 0; i < 3; i++ {
+		exit, err := server.selectDNSExit("WWW.example.com.", client)
+		if err != nil {
+			t.Fatalf("selectDNSExit: %v", err)
+		}
+		ip, prov, err := server.selectExit("www.example.com:443", RoutingHints{Tenant: "alice", ClientIP: "192.0.2.10"})
+		if err != nil || ip != exit {
+			t.Fatalf("got %s, %v; want the lookup's exit %s", ip, err, exit)
+		}
+		if prov.source != selectionDNS || prov.key != "dns/www.example.com" {
+			t.Errorf("provenance = %+v, want dns dns/www.example.com", prov)
+		}
+		server.balancer.Record("www.example.com:443", ip)
+		server.balancer.Record("www.example.com", exit)
+	}
+
+	// A user's own pool still wins over the lookup's exit
+	server.dnsExits.set("www.example.com", "127.0.0.1")
+	if ip, _, err := server.selectExit("www.example.com:443", RoutingHints{Tenant: "batch"}); err != nil || ip != "127.0.0.3" {
+		t.Errorf("got %s, %v; want 127.0.0.3 from the user's pool", ip, err)
+	}
+
+	// The binding lapses after its TTL
+	fake.Advance(2 * dnsExitTTL)
+	if _, prov, _ := server.selectExit("www.example.com:443", RoutingHints{Tenant: "alice"}); prov.source == selectionDNS {
+		t.Error("expected the lookup's exit to expire")
+	}
+}
+
+func TestSelectExit_UsernameSession(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	fake := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	server.sessions = newAffinityStore(time.Minute, fake)
+	session := RoutingHints{Tenant: "alice", ClientIP: "192.0.2.10", Session: "abc123"}
+
+	first, _, err := server.selectExit("www.example.com:443", session)
+	if err != nil {
+		t.Fatalf("selectExit: %v", err)
+	}
+	server.balancer.Record("www.example.com:443", first)
+
+	// The session keeps its exit across unrelated destinations
+	for _, host := range []string{"www.example.com:443", "api.other.org:443", "10.0.0.1:80"} {
+		ip, prov, err := server.selectExit(host, session)
+		if err != nil || ip != first {
+			t.Fatalf("%s: got %s, %v; want %s", host, ip, err, first)
+		}
+		if prov.source != selectionSticky || prov.key != "alice/session/abc123" {
+			t.Errorf("%s: provenance = %+v, want sticky alice/session/abc123", host, prov)
+		}
+		server.balancer.Record(host, ip)
+	}
+
+	// A new session rotates to a fresh exit
+	rotated := session
+	rotated.Session = "def456"
+	if ip, prov, _ := server.selectExit("www.example.com:443", rotated); ip == first || prov.source != selectionFresh {
+		t.Errorf("new session got %s (%s), want a fresh exit other than %s", ip, prov.source, first)
+	}
+
+	// The session expires once idle for the TTL
+	fake.Advance(2 * time.Minute)
+	if _, prov, _ := server.selectExit("www.example.com:443", session); prov.source == selectionSticky {
+		t.Error("expected the idle session to expire")
+	}
+}
+
+func TestSelectExit_UserRotation(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	fake := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	server.cfg.SessionAffinityTTL = time.Hour
+	server.cfg.UserRotation = []config.UserRotation{
+		{User: "alice", Policy: config.RotationPerRequest},
+		{User: "bob", Policy: config.RotationSticky, TTL: time.Minute},
+		{User: "carol", Policy: config.RotationStickyUntilError},
+	}
+	server.affinity = newAffinityStore(time.Hour, fake)
+	server.userAffinity = newUserAffinityStore(server.cfg, fake)
+	const host = "www.example.com:443"
+	pick := func(user string) (string, provenance) {
+		t.Helper()
+		ip, prov, err := server.selectExit(host, RoutingHints{Tenant: user})
+		if err != nil {
+			t.Fatalf("%s: selectExit: %v", user, err)
+		}
+		server.balancer.Record(host, ip)
+		return ip, prov
+	}
+
+	// Session affinity is on, but alice rotates on every connection
+	for i := 0; i < 3; i++ {
+		if _, prov := pick("alice"); prov.source == selectionSticky {
+			t.Fatal("expected per-request rotation for alice")
+		}
+	}
+
+	// bob sticks for his own TTL instead of session-affinity-ttl
+	bob, _ := pick("bob")
+	if ip, prov := pick("bob"); ip != bob || prov.source != selectionSticky {
+		t.Fatalf("bob got %s (%s), want sticky %s", ip, prov.source, bob)
+	}
+	fake.Advance(2 * time.Minute)
+	if _, prov := pick("bob"); prov.source == selectionSticky {
+		t.Error("expected bob's affinity to expire after his TTL")
+	}
+
+	// carol keeps her exit until a connection through it fails
+	carol, _ := pick("carol")
+	if ip, _ := pick("carol"); ip != carol {
+		t.Fatalf("carol got %s, want %s", ip, carol)
+	}
+	server.recordOutcome(carol, false)
+	if ip, _ := pick("carol"); ip != carol {
+		t.Fatalf("carol got %s after a success, want %s", ip, carol)
+	}
+	server.recordOutcome(carol, true)
+	if _, prov := pick("carol"); prov.source == selectionSticky {
+		t.Error("expected carol to rotate after a failure")
+	}
+}
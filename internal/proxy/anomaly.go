@@ -0,0 +1,284 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"bytes"
+	"encoding/json"
+	"math"
+	"net"
+	"net/http"
+	"sync"
+	"sync/atomic"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+const (
+	// anomalyBaselineSamples is how many samples a metric needs before it can raise an anomaly.
+	anomalyBaselineSamples = 10
+	// anomalyBaselineWeight is the weight of a new sample in a metric's mean and variance.
+	anomalyBaselineWeight = 0.1
+	// anomalyMinRequests is how many connections an interval needs for its error rate to count.
+	anomalyMinRequests = 5
+	// anomalyWebhookTimeout bounds each webhook POST.
+	anomalyWebhookTimeout = 5 * time.Second
+)
+
+// Anomaly metrics, used as the "metric" label and webhook field.
+const (
+	anomalyErrorRate = "error_rate"
+	anomalyRequests  = "requests"
+	anomalyBytes     = "bytes"
+)
+
+// anomalyStdFloor is the smallest standard deviation assumed per metric, so a
+// quiet IP with a flat baseline doesn't raise anomalies on trivial changes.
+var anomalyStdFloor = map[string]float64{
+	anomalyErrorRate: 0.05,
+	anomalyRequests:  1,
+	anomalyBytes:     64 << 10,
+}
+
+// anomalyCounters accumulates one IP's traffic between samples.
+type anomalyCounters struct {
+	requests atomic.Int64
+	errors   atomic.Int64
+	bytes    atomic.Int64
+}
+
+// anomalyBaseline is the rolling mean and variance of one metric of one IP.
+type anomalyBaseline struct {
+	mean      float64
+	variance  float64
+	samples   int
+	anomalous bool
+}
+
+// anomalyEvent is an anomaly as logged and posted to the webhook.
+type anomalyEvent struct {
+	Event    string    `json:"event"`
+	IP       string    `json:"ip"`
+	Metric   string    `json:"metric"`
+	Value    float64   `json:"value"`
+	Baseline float64   `json:"baseline"`
+	ZScore   float64   `json:"z_score"`
+	Time     time.Time `json:"time"`
+}
+
+// anomalyDetector compares each IP's connect error rate, connection rate and
+// throughput per interval with a rolling baseline of its own, and raises an
+// event when a sample's z-score crosses the threshold. It catches exits that
+// degrade silently between health checks. Events are raised when an IP turns
+// anomalous, not on every anomalous sample.
+type anomalyDetector struct {
+	interval  time.Duration
+	threshold float64
+	webhook   string
+	client    *http.Client
+	counters  map[string]*anomalyCounters
+	baselines map[string]map[string]*anomalyBaseline
+	stopCh    chan struct{}
+	stopOnce  sync.Once
+	wg        sync.WaitGroup
+}
+
+// newAnomalyDetector creates an anomalyDetector for ips. Anomalies are POSTed
+// to webhook unless it is empty.
+func newAnomalyDetector(ips []string, interval time.Duration, threshold float64, webhook string) *anomalyDetector {
+	d := &anomalyDetector{
+		interval:  interval,
+		threshold: threshold,
+		webhook:   webhook,
+		client:    &http.Client{Timeout: anomalyWebhookTimeout},
+		counters:  make(map[string]*anomalyCounters, len(ips)),
+		baselines: make(map[string]map[string]*anomalyBaseline, len(ips)),
+		stopCh:    make(chan struct{}),
+	}
+	for _, ip := range ips {
+		d.counters[ip] = &anomalyCounters{}
+		d.baselines[ip] = make(map[string]*anomalyBaseline)
+	}
+	return d
+}
+
+// Start starts the sampling goroutine.
+func (d *anomalyDetector) Start() {
+	log.Info("anomaly_detector_started", "interval", d.interval, "threshold", d.threshold, "webhook", d.webhook != "")
+
+	d.wg.Add(1)
+	go func() {
+		defer d.wg.Done()
+		ticker := time.NewTicker(d.interval)
+		defer ticker.Stop()
+		for {
+			select {
+			case <-ticker.C:
+				d.check(d.interval)
+			case <-d.stopCh:
+				return
+			}
+		}
+	}()
+}
+
+// Stop stops the sampling goroutine and waits for pending webhook calls.
+func (d *anomalyDetector) Stop() {
+	d.stopOnce.Do(func() { close(d.stopCh) })
+	d.wg.Wait()
+}
+
+// record counts an outbound connection through ip. Safe to call on a nil detector.
+func (d *anomalyDetector) record(ip string, failed bool) {
+	if d == nil {
+		return
+	}
+	if c, ok := d.counters[ip]; ok {
+		c.requests.Add(1)
+		if failed {
+			c.errors.Add(1)
+		}
+	}
+}
+
+// addBytes counts n bytes relayed through ip. Safe to call on a nil detector.
+func (d *anomalyDetector) addBytes(ip string, n int64) {
+	if d == nil {
+		return
+	}
+	if c, ok := d.counters[ip]; ok {
+		c.bytes.Add(n)
+	}
+}
+
+// check samples the traffic of the last elapsed period and returns the anomalies raised.
+func (d *anomalyDetector) check(elapsed time.Duration) []anomalyEvent {
+	now := time.Now()
+	var events []anomalyEvent
+	for ip, c := range d.counters {
+		requests, failed, relayed := c.requests.Swap(0), c.errors.Swap(0), c.bytes.Swap(0)
+		samples := map[string]float64{
+			anomalyRequests: float64(requests) / elapsed.Seconds(),
+			anomalyBytes:    float64(relayed) / elapsed.Seconds(),
+		}
+		if requests >= anomalyMinRequests {
+			samples[anomalyErrorRate] = float64(failed) / float64(requests)
+		}
+
+		for metric, value := range samples {
+			b, ok := d.baselines[ip][metric]
+			if !ok {
+				b = &anomalyBaseline{}
+				d.baselines[ip][metric] = b
+			}
+			z, anomalous := d.score(metric, b, value)
+			if anomalous && !b.anomalous {
+				events = append(events, anomalyEvent{
+					Event:    "egress_anomaly",
+					IP:       ip,
+					Metric:   metric,
+					Value:    value,
+					Baseline: b.mean,
+					ZScore:   z,
+					Time:     now,
+				})
+			} else if !anomalous && b.anomalous {
+				log.Info("egress_anomaly_cleared", "ip", ip, "metric", metric, "value", value)
+			}
+			b.anomalous = anomalous
+			b.update(value)
+		}
+	}
+	for _, e := range events {
+		d.raise(e)
+	}
+	return events
+}
+
+// score returns the z-score of value against b and whether it is anomalous.
+// Error rates are only anomalous when they rise; rates and throughput also
+// when they collapse.
+func (d *anomalyDetector) score(metric string, b *anomalyBaseline, value float64) (float64, bool) {
+	if b.samples < anomalyBaselineSamples {
+		return 0, false
+	}
+	std := max(math.Sqrt(b.variance), 0.1*b.mean, anomalyStdFloor[metric])
+	z := (value - b.mean) / std
+	if metric == anomalyErrorRate {
+		return z, z >= d.threshold
+	}
+	return z, math.Abs(z) >= d.threshold
+}
+
+// update folds value into the rolling mean and variance.
+func (b *anomalyBaseline) update(value float64) {
+	if b.samples == 0 {
+		b.mean = value
+	} else {
+		diff := value - b.mean
+		incr := anomalyBaselineWeight * diff
+		b.mean += incr
+		b.variance = (1 - anomalyBaselineWeight) * (b.variance + diff*incr)
+	}
+	b.samples++
+}
+
+// raise logs and counts an anomaly and posts it to the webhook.
+func (d *anomalyDetector) raise(e anomalyEvent) {
+	log.Warn("egress_anomaly", "ip", e.IP, "metric", e.Metric, "value", e.Value, "baseline", e.Baseline, "z_score", e.ZScore)
+	metrics.EgressAnomalies.WithLabelValues(e.IP, e.Metric).Inc()
+	if d.webhook == "" {
+		return
+	}
+	d.wg.Add(1)
+	go func() {
+		defer d.wg.Done()
+		body, _ := json.Marshal(e)
+		resp, err := d.client.Post(d.webhook, "application/json", bytes.NewReader(body))
+		if err != nil {
+			log.Warn("anomaly_webhook_failed", "ip", e.IP, "error", err)
+			return
+		}
+		resp.Body.Close()
+		if resp.StatusCode >= 300 {
+			log.Warn("anomaly_webhook_failed", "ip", e.IP, "status", resp.StatusCode)
+		}
+	}()
+}
+
+// countingConn is a net.Conn whose relayed bytes are counted per outbound IP.
+type countingConn struct {
+	net.Conn
+	count func(n int64)
+}
+
+// Read reads from the connection and counts the bytes read.
+func (c *countingConn) Read(p []byte) (int, error) {
+	n, err := c.Conn.Read(p)
+	if n > 0 {
+		c.count(int64(n))
+	}
+	return n, err
+}
+
+// Write writes to the connection and counts the bytes written.
+func (c *countingConn) Write(p []byte) (int, error) {
+	n, err := c.Conn.Write(p)
+	if n > 0 {
+		c.count(int64(n))
+	}
+	return n, err
+}
+
+// NetConn returns the wrapped connection.
+func (c *countingConn) NetConn() net.Conn {
+	return c.Conn
+}
+
+// CloseWrite half-closes the underlying connection if supported.
+func (c *countingConn) CloseWrite() error {
+	if cw, ok := c.Conn.(closeWriter); ok {
+		return cw.CloseWrite()
+	}
+	return nil
+}
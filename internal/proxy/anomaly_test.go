@@ -0,0 +1,128 @@
+package proxy
+
+import (
+	"encoding/json"
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+// steadyTraffic records one interval of n connections with failed errors and relayed bytes through ip.
+func steadyTraffic(d *anomalyDetector, ip string, n, failed int, relayed int64) {
+	for i := 0; i < n; i++ {
+		d.record(ip, i < failed)
+	}
+	d.addBytes(ip, relayed)
+}
+
+func TestAnomalyDetector_ErrorRateSpike(t *testing.T) {
+	d := newAnomalyDetector([]string{"192.0.2.1", "192.0.2.2"}, time.Second, 4, "")
+	for i := 0; i < anomalyBaselineSamples; i++ {
+		steadyTraffic(d, "192.0.2.1", 20, i%2, 1<<20)
+		steadyTraffic(d, "192.0.2.2", 20, i%2, 1<<20)
+		if events := d.check(time.Second); len(events) != 0 {
+			t.Fatalf("sample %d: unexpected anomalies while learning: %+v", i, events)
+		}
+	}
+
+	// One exit starts failing most connects while the other keeps its baseline
+	steadyTraffic(d, "192.0.2.1", 20, 15, 1<<20)
+	steadyTraffic(d, "192.0.2.2", 20, 1, 1<<20)
+	events := d.check(time.Second)
+	if len(events) != 1 || events[0].IP != "192.0.2.1" || events[0].Metric != anomalyErrorRate {
+		t.Fatalf("expected one error rate anomaly for 192.0.2.1, got %+v", events)
+	}
+
+	// An ongoing anomaly is not raised again
+	steadyTraffic(d, "192.0.2.1", 20, 15, 1<<20)
+	steadyTraffic(d, "192.0.2.2", 20, 1, 1<<20)
+	if events := d.check(time.Second); len(events) != 0 {
+		t.Errorf("expected no repeated anomaly, got %+v", events)
+	}
+}
+
+func TestAnomalyDetector_TrafficCollapse(t *testing.T) {
+	d := newAnomalyDetector([]string{"192.0.2.1"}, time.Second, 4, "")
+	for i := 0; i < anomalyBaselineSamples; i++ {
+		steadyTraffic(d, "192.0.2.1", 100+i%3, 0, 10<<20)
+		d.check(time.Second)
+	}
+
+	// Connections and throughput drop to almost nothing without any errors
+	steadyTraffic(d, "192.0.2.1", 2, 0, 4<<10)
+	got := make(map[string]bool)
+	for _, e := range d.check(time.Second) {
+		got[e.Metric] = true
+	}
+	if !got[anomalyRequests] || !got[anomalyBytes] {
+		t.Errorf("expected requests and bytes anomalies, got %v", got)
+	}
+}
+
+func TestAnomalyDetector_QuietIP(t *testing.T) {
+	d := newAnomalyDetector([]string{"192.0.2.1"}, time.Second, 4, "")
+	for i := 0; i < anomalyBaselineSamples; i++ {
+		d.check(time.Second)
+	}
+	// A couple of connections on an idle exit stay within the floor
+	steadyTraffic(d, "192.0.2.1", 2, 0, 1<<10)
+	if events := d.check(time.Second); len(events) != 0 {
+		t.Errorf("expected no anomaly for light traffic on an idle IP, got %+v", events)
+	}
+}
+
+func TestAnomalyDetector_Webhook(t *testing.T) {
+	received := make(chan anomalyEvent, 1)
+	hook := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		var e anomalyEvent
+		if err := json.NewDecoder(r.Body).Decode(&e); err != nil {
+			t.Errorf("failed to decode webhook body: %v", err)
+		}
+		received <- e
+	}))
+	defer hook.Close()
+
+	d := newAnomalyDetector([]string{"192.0.2.1"}, time.Second, 4, hook.URL)
+	for i := 0; i < anomalyBaselineSamples; i++ {
+		steadyTraffic(d, "192.0.2.1", 20, 0, 0)
+		d.check(time.Second)
+	}
+	steadyTraffic(d, "192.0.2.1", 20, 20, 0)
+	d.check(time.Second)
+	d.Stop()
+
+	select {
+	case e := <-received:
+		if e.Event != "egress_anomaly" || e.IP != "192.0.2.1" || e.Metric != anomalyErrorRate || e.Value != 1 {
+			t.Errorf("unexpected webhook event: %+v", e)
+		}
+	default:
+		t.Fatal("expected the anomaly to be posted to the webhook")
+	}
+}
+
+func TestDialOptions_ByteCounter(t *testing.T) {
+	var counted int64
+	o := newDialOptions([]DialOption{WithByteCounter(func(ip string, n int64) {
+		if ip == "192.0.2.1" {
+			counted += n
+		}
+	})})
+	c1, c2 := net.Pipe()
+	defer c1.Close()
+	defer c2.Close()
+	conn := o.wrap("192.0.2.1", c1)
+
+	go func() {
+		buf := make([]byte, 5)
+		c2.Read(buf)
+		c2.Write([]byte("pong"))
+	}()
+	conn.Write([]byte("ping!"))
+	conn.Read(make([]byte, 4))
+	if counted != 9 {
+		t.Errorf("counted %d bytes, want 9", counted)
+	}
+}
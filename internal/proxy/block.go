@@ -0,0 +1,80 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"bytes"
+	"errors"
+	"net/http"
+	"strconv"
+)
+
+// policyDenial is a destination policy refusal that records the rule, list or
+// hook that refused it for block responses.
+type policyDenial struct {
+	err    error
+	rule   string
+	detail string
+}
+
+func (d *policyDenial) Error() string { return d.err.Error() + ": " + d.detail }
+
+func (d *policyDenial) Unwrap() error { return d.err }
+
+// blockRule names what refused a destination for block responses.
+func blockRule(err error) string {
+	var d *policyDenial
+	switch {
+	case errors.As(err, &d):
+		return d.rule
+	case errors.Is(err, ErrPrivateDestination):
+		return "block_private_destinations"
+	case errors.Is(err, ErrPortNotAllowed):
+		return "connect_ports"
+	}
+	return ""
+}
+
+// blockData is the data passed to the block_body template.
+type blockData struct {
+	Rule      string
+	Host      string
+	TicketURL string
+	RequestID string
+}
+
+// sendBlocked answers a request refused by destination policy with the
+// configured block response, falling back to message without a block_body,
+// and returns the status sent.
+func (s *Server) sendBlocked(w http.ResponseWriter, r *http.Request, host string, err error, message string) int {
+	status := s.cfg.BlockStatus
+	if status == 0 {
+		status = http.StatusForbidden
+	}
+	for name, values := range s.blockHeaders {
+		w.Header()[name] = values
+	}
+	if s.blockTemplate == nil {
+		http.Error(w, message, status)
+		return status
+	}
+	var body bytes.Buffer
+	data := blockData{
+		Rule:      blockRule(err),
+		Host:      destinationName(host),
+		TicketURL: s.cfg.BlockTicketURL,
+		RequestID: RequestIDFromContext(r.Context()),
+	}
+	if err := s.blockTemplate.Execute(&body, data); err != nil {
+		log.Error("block_body_failed", "error", err)
+		http.Error(w, message, status)
+		return status
+	}
+	if w.Header().Get("Content-Type") == "" {
+		w.Header().Set("Content-Type", "text/plain; charset=utf-8")
+	}
+	w.Header().Set("Content-Length", strconv.Itoa(body.Len()))
+	w.Header().Set("X-Content-Type-Options", "nosniff")
+	w.WriteHeader(status)
+	w.Write(body.Bytes())
+	return status
+}
@@ -0,0 +1,49 @@
+package proxy
+
+import (
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+)
+
+func TestHandler_BlockResponse(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.DeniedDestinations = []string{"blocked.example.com"}
+	handler := NewHandler(server)
+
+	r := httptest.NewRequest(http.MethodGet, "http://blocked.example.com/", nil)
+	w := httptest.NewRecorder()
+	handler.ServeHTTP(w, r)
+	if w.Code != http.StatusForbidden || !strings.Contains(w.Body.String(), "Destination not allowed") {
+		t.Errorf("got %d %q, want the built-in 403", w.Code, w.Body.String())
+	}
+
+	server.cfg.BlockStatus = http.StatusUnavailableForLegalReasons
+	server.cfg.BlockBody = "{{.Host}} is blocked by {{.Rule}}. Request access at {{.TicketURL}}\n"
+	server.cfg.BlockTicketURL = "https://tickets.example.com/new"
+	server.cfg.BlockHeaders = []string{"X-Blocked-By: outbound-lb", "Cache-Control: no-store"}
+	server.blockTemplate = server.cfg.BlockTemplate()
+	server.blockHeaders = server.cfg.BlockResponseHeaders()
+
+	w = httptest.NewRecorder()
+	handler.ServeHTTP(w, r)
+	if w.Code != http.StatusUnavailableForLegalReasons {
+		t.Errorf("status = %d, want 451", w.Code)
+	}
+	want := "blocked.example.com is blocked by denied_destinations. Request access at https://tickets.example.com/new\n"
+	if w.Body.String() != want {
+		t.Errorf("body = %q, want %q", w.Body.String(), want)
+	}
+	if w.Header().Get("X-Blocked-By") != "outbound-lb" || w.Header().Get("Cache-Control") != "no-store" {
+		t.Errorf("missing block headers: %v", w.Header())
+	}
+
+	r = httptest.NewRequest(http.MethodConnect, "http://blocked.example.com:443", nil)
+	r.Host = "blocked.example.com:443"
+	w = httptest.NewRecorder()
+	server.connectHandler.ServeHTTP(w, r)
+	if w.Code != http.StatusUnavailableForLegalReasons || !strings.Contains(w.Body.String(), "denied_destinations") {
+		t.Errorf("CONNECT got %d %q, want the block response", w.Code, w.Body.String())
+	}
+}
@@ -0,0 +1,86 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"errors"
+	"net"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// errClientDenied is returned when a client address is refused by the client ACL.
+var errClientDenied = errors.New("client address not allowed")
+
+// clientACL decides which client addresses may connect. Deny wins over allow,
+// and an empty allow list allows every client that is not denied.
+type clientACL struct {
+	allow []*net.IPNet
+	deny  []*net.IPNet
+}
+
+// newClientACL creates a clientACL, or returns nil when both lists are empty.
+func newClientACL(allow, deny []string) *clientACL {
+	if len(allow) == 0 && len(deny) == 0 {
+		return nil
+	}
+	a := &clientACL{}
+	a.allow, _ = netutil.ParseCIDRs(allow) // validated with the config
+	a.deny, _ = netutil.ParseCIDRs(deny)
+	return a
+}
+
+// allowed reports whether a client at addr may connect. Safe to call on a nil
+// clientACL, which allows everything.
+func (a *clientACL) allowed(addr net.Addr) bool {
+	if a == nil {
+		return true
+	}
+	ip := net.ParseIP(netutil.ParseHost(addr.String()))
+	if ip == nil || netutil.ContainsIP(a.deny, ip) {
+		return false
+	}
+	return len(a.allow) == 0 || netutil.ContainsIP(a.allow, ip)
+}
+
+// refused records a connection refused by the ACL.
+func (a *clientACL) refused(addr net.Addr) {
+	log.Debug("client_refused", "remote", addr)
+	metrics.ClientACLRejections.Inc()
+}
+
+// clientACLListener closes accepted connections from clients the ACL refuses.
+// For connections whose client address comes from a PROXY header, the check
+// runs once the header has been read, on the connection's own goroutine.
+type clientACLListener struct {
+	net.Listener
+	acl *clientACL
+}
+
+// Accept waits for the next allowed connection.
+func (l *clientACLListener) Accept() (net.Conn, error) {
+	for {
+		conn, err := l.Listener.Accept()
+		if err != nil {
+			return nil, err
+		}
+		if pc, ok := conn.(*proxyProtoConn); ok {
+			pc.acl = l.acl
+			return pc, nil
+		}
+		if l.acl.allowed(conn.RemoteAddr()) {
+			return conn, nil
+		}
+		l.acl.refused(conn.RemoteAddr())
+		conn.Close()
+	}
+}
+
+// withClientACL wraps l with the configured client allow/deny lists, if any.
+func (s *Server) withClientACL(l net.Listener) net.Listener {
+	acl := newClientACL(s.cfg.ClientAllow, s.cfg.ClientDeny)
+	if acl == nil {
+		return l
+	}
+	return &clientACLListener{Listener: l, acl: acl}
+}
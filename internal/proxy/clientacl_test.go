@@ -0,0 +1,106 @@
+package proxy
+
+import (
+	"errors"
+	"net"
+	"testing"
+	"time"
+)
+
+func TestClientACL_Allowed(t *testing.T) {
+	acl := newClientACL([]string{"10.0.0.0/8", "192.168.1.7"}, []string{"10.66.0.0/16"})
+	tests := []struct {
+		addr string
+		want bool
+	}{
+		{"10.1.2.3:5000", true},
+		{"192.168.1.7:5000", true},
+		{"10.66.1.1:5000", false},
+		{"203.0.113.7:5000", false},
+	}
+	for _, tt := range tests {
+		addr, _ := net.ResolveTCPAddr("tcp", tt.addr)
+		if got := acl.allowed(addr); got != tt.want {
+			t.Errorf("allowed(%s) = %v, want %v", tt.addr, got, tt.want)
+		}
+	}
+
+	// A deny list alone allows everyone else
+	denyOnly := newClientACL(nil, []string{"10.66.0.0/16"})
+	if !denyOnly.allowed(&net.TCPAddr{IP: net.ParseIP("203.0.113.7")}) {
+		t.Error("expected a deny-only ACL to allow unlisted clients")
+	}
+
+	if newClientACL(nil, nil) != nil {
+		t.Error("expected no ACL without lists")
+	}
+	var none *clientACL
+	if !none.allowed(&net.TCPAddr{IP: net.ParseIP("203.0.113.7")}) {
+		t.Error("expected a nil ACL to allow everything")
+	}
+}
+
+func TestClientACLListener(t *testing.T) {
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	defer l.Close()
+	acl := &clientACLListener{Listener: l, acl: newClientACL(nil, []string{"127.0.0.1"})}
+
+	conn, err := net.Dial("tcp", l.Addr().String())
+	if err != nil {
+		t.Fatalf("failed to dial: %v", err)
+	}
+	defer conn.Close()
+
+	accepted := make(chan net.Conn, 1)
+	go func() {
+		if c, err := acl.Accept(); err == nil {
+			accepted <- c
+		}
+	}()
+
+	// The denied connection is closed without being handed to the server
+	conn.SetReadDeadline(time.Now().Add(5 * time.Second))
+	if _, err := conn.Read(make([]byte, 1)); err == nil {
+		t.Error("expected the denied connection to be closed")
+	}
+	select {
+	case c := <-accepted:
+		c.Close()
+		t.Error("expected Accept not to return a denied connection")
+	default:
+	}
+}
+
+func TestClientACL_ProxyProtocol(t *testing.T) {
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	defer l.Close()
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.ProxyProtocol = true
+	server.cfg.ClientAllow = []string{"127.0.0.1"}
+	server.cfg.ClientDeny = []string{"203.0.113.0/24"}
+	wl := server.wrapListener(l)
+
+	go func() {
+		if conn, err := net.Dial("tcp", l.Addr().String()); err == nil {
+			conn.Write([]byte("PROXY TCP4 203.0.113.7 127.0.0.1 51234 8080\r\nGET"))
+			time.Sleep(time.Second)
+			conn.Close()
+		}
+	}()
+
+	// The load balancer's own address is allowed, so the check waits for the header
+	conn, err := wl.Accept()
+	if err != nil {
+		t.Fatalf("accept failed: %v", err)
+	}
+	defer conn.Close()
+	if _, err := conn.Read(make([]byte, 3)); !errors.Is(err, errClientDenied) {
+		t.Errorf("Read() error = %v, want errClientDenied for the denied PROXY client", err)
+	}
+}
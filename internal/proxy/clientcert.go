@@ -0,0 +1,55 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"crypto/tls"
+	"crypto/x509"
+	"net/http"
+
+	"github.com/cr0hn/outbound-lb/internal/auth"
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+// SetClientCAs verifies client certificates on the TLS listener against pool
+// and identifies clients by them. Unless tls_client_cert_optional is set,
+// clients without a valid certificate fail the handshake. It must be called
+// before Start.
+func (s *Server) SetClientCAs(pool *x509.CertPool) {
+	if s.httpServer.TLSConfig == nil {
+		return
+	}
+	s.httpServer.TLSConfig.ClientCAs = pool
+	s.httpServer.TLSConfig.ClientAuth = tls.RequireAndVerifyClientCert
+	if s.cfg.TLSClientCertOptional {
+		s.httpServer.TLSConfig.ClientAuth = tls.VerifyClientCertIfGiven
+	}
+}
+
+// clientCertIdentity returns the identity of the request's verified client
+// certificate, from the field named by tls_client_identity. It reports false
+// without a verified certificate; the identity's User is empty when the
+// certificate lacks the field.
+func (s *Server) clientCertIdentity(r *http.Request) (auth.Identity, bool) {
+	if r.TLS == nil || len(r.TLS.VerifiedChains) == 0 || len(r.TLS.VerifiedChains[0]) == 0 {
+		return auth.Identity{}, false
+	}
+	cert := r.TLS.VerifiedChains[0][0]
+	var user string
+	switch s.cfg.TLSClientIdentity {
+	case config.ClientIdentityDNS:
+		if len(cert.DNSNames) > 0 {
+			user = cert.DNSNames[0]
+		}
+	case config.ClientIdentityEmail:
+		if len(cert.EmailAddresses) > 0 {
+			user = cert.EmailAddresses[0]
+		}
+	case config.ClientIdentityURI:
+		if len(cert.URIs) > 0 {
+			user = cert.URIs[0].String()
+		}
+	default:
+		user = cert.Subject.CommonName
+	}
+	return auth.Identity{User: user}, true
+}
@@ -0,0 +1,173 @@
+package proxy
+
+import (
+	"crypto/ecdsa"
+	"crypto/elliptic"
+	"crypto/rand"
+	"crypto/tls"
+	"crypto/x509"
+	"crypto/x509/pkix"
+	"encoding/base64"
+	"math/big"
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"net/url"
+	"testing"
+	"time"
+)
+
+// newTestClientCA creates a CA and a client certificate it issued for tmpl.
+func newTestClientCA(t *testing.T, tmpl *x509.Certificate) (*x509.CertPool, tls.Certificate) {
+	t.Helper()
+	caKey, err := ecdsa.GenerateKey(elliptic.P256(), rand.Reader)
+	if err != nil {
+		t.Fatalf("failed to generate key: %v", err)
+	}
+	caTmpl := &x509.Certificate{
+		SerialNumber:          big.NewInt(1),
+		Subject:               pkix.Name{CommonName: "outbound-lb-test-ca"},
+		NotBefore:             time.Now().Add(-time.Hour),
+		NotAfter:              time.Now().Add(time.Hour),
+		KeyUsage:              x509.KeyUsageCertSign,
+		BasicConstraintsValid: true,
+		IsCA:                  true,
+	}
+	caDER, err := x509.CreateCertificate(rand.Reader, caTmpl, caTmpl, &caKey.PublicKey, caKey)
+	if err != nil {
+		t.Fatalf("failed to create CA: %v", err)
+	}
+	ca, _ := x509.ParseCertificate(caDER)
+
+	key, err := ecdsa.GenerateKey(elliptic.P256(), rand.Reader)
+	if err != nil {
+		t.Fatalf("failed to generate key: %v", err)
+	}
+	tmpl.SerialNumber = big.NewInt(2)
+	tmpl.NotBefore = time.Now().Add(-time.Hour)
+	tmpl.NotAfter = time.Now().Add(time.Hour)
+	tmpl.KeyUsage = x509.KeyUsageDigitalSignature
+	tmpl.ExtKeyUsage = []x509.ExtKeyUsage{x509.ExtKeyUsageClientAuth}
+	der, err := x509.CreateCertificate(rand.Reader, tmpl, ca, &key.PublicKey, caKey)
+	if err != nil {
+		t.Fatalf("failed to create certificate: %v", err)
+	}
+
+	pool := x509.NewCertPool()
+	pool.AddCert(ca)
+	return pool, tls.Certificate{Certificate: [][]byte{der}, PrivateKey: key}
+}
+
+// requestWithClientCert returns a request that arrived with cert verified.
+func requestWithClientCert(cert *x509.Certificate) *http.Request {
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req.TLS = &tls.ConnectionState{VerifiedChains: [][]*x509.Certificate{{cert}}}
+	return req
+}
+
+func TestServer_ClientCertIdentity(t *testing.T) {
+	spiffe, _ := url.Parse("spiffe://fleet.example/scraper-7")
+	cert := &x509.Certificate{
+		Subject:        pkix.Name{CommonName: "scraper-7"},
+		DNSNames:       []string{"scraper-7.fleet.example"},
+		EmailAddresses: []string{"scraper-7@fleet.example"},
+		URIs:           []*url.URL{spiffe},
+	}
+
+	tests := []struct {
+		field string
+		want  string
+	}{
+		{"", "scraper-7"},
+		{"cn", "scraper-7"},
+		{"dns", "scraper-7.fleet.example"},
+		{"email", "scraper-7@fleet.example"},
+		{"uri", "spiffe://fleet.example/scraper-7"},
+	}
+	server := newTestServerWithAuth(t, "")
+	for _, tt := range tests {
+		t.Run(tt.field, func(t *testing.T) {
+			server.cfg.TLSClientIdentity = tt.field
+			id, ok := server.clientCertIdentity(requestWithClientCert(cert))
+			if !ok || id.User != tt.want {
+				t.Errorf("clientCertIdentity() = %q, %v, want %q", id.User, ok, tt.want)
+			}
+		})
+	}
+
+	if _, ok := server.clientCertIdentity(httptest.NewRequest(http.MethodGet, "/", nil)); ok {
+		t.Error("expected no identity without a client certificate")
+	}
+}
+
+func TestServer_Authenticate_ClientCert(t *testing.T) {
+	server := newTestServerWithAuth(t, "user:pass")
+
+	authed, ok := server.authenticate(httptest.NewRecorder(), requestWithClientCert(&x509.Certificate{Subject: pkix.Name{CommonName: "scraper-7"}}))
+	if !ok {
+		t.Fatal("expected a verified certificate to authenticate without credentials")
+	}
+	if got := proxyUsername(authed); got != "scraper-7" {
+		t.Errorf("proxyUsername() = %q, want scraper-7", got)
+	}
+
+	// A certificate without the identity field is refused
+	server.cfg.TLSClientIdentity = "uri"
+	w := httptest.NewRecorder()
+	if _, ok := server.authenticate(w, requestWithClientCert(&x509.Certificate{Subject: pkix.Name{CommonName: "scraper-7"}})); ok || w.Code != http.StatusProxyAuthRequired {
+		t.Errorf("expected 407 for a certificate without a URI, got %d", w.Code)
+	}
+
+	// Clients without a certificate still authenticate with credentials
+	req := httptest.NewRequest(http.MethodGet, "/", nil)
+	req.Header.Set("Proxy-Authorization", "Basic "+base64.StdEncoding.EncodeToString([]byte("user:pass")))
+	if _, ok := server.authenticate(httptest.NewRecorder(), req); !ok {
+		t.Error("expected Basic credentials to pass without a certificate")
+	}
+}
+
+func TestServer_TLSListener_ClientCert(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	certFile, keyFile := writeTestCertificate(t)
+	clientCAs, clientCert := newTestClientCA(t, &x509.Certificate{Subject: pkix.Name{CommonName: "scraper-7"}})
+
+	base := newTestServerWithOptions(t, DefaultTestServerOptions())
+	base.cfg.TLSCertFile = certFile
+	base.cfg.TLSKeyFile = keyFile
+	server := NewServer(base.cfg, base.balancer, base.limiter, base.stats)
+	server.SetClientCAs(clientCAs)
+
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	go server.Serve(l)
+	defer server.httpServer.Close()
+
+	proxyURL, _ := url.Parse("https://" + l.Addr().String())
+	newClient := func(certs ...tls.Certificate) *http.Client {
+		return &http.Client{
+			Transport: &http.Transport{
+				Proxy:           http.ProxyURL(proxyURL),
+				TLSClientConfig: &tls.Config{InsecureSkipVerify: true, Certificates: certs},
+			},
+			Timeout: 5 * time.Second,
+		}
+	}
+
+	resp, err := newClient(clientCert).Get(backend.URL)
+	if err != nil {
+		t.Fatalf("request with client certificate failed: %v", err)
+	}
+	resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		t.Errorf("expected status 200, got %d", resp.StatusCode)
+	}
+
+	if resp, err := newClient().Get(backend.URL); err == nil {
+		resp.Body.Close()
+		t.Error("expected the handshake to fail without a client certificate")
+	}
+}
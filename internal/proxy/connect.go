@@ -2,6 +2,8 @@
 package proxy
 
 import (
+	"bytes"
+	"context"
 	"errors"
 	"fmt"
 	"io"
@@ -13,11 +15,15 @@ import (
 
 	"github.com/cr0hn/outbound-lb/internal/logger"
 	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
 )
 
 // ConnectHandler handles CONNECT tunnel requests.
 type ConnectHandler struct {
 	server *Server
+	// dial opens outbound connections from an IP; nil uses a Dialer. Tests override it.
+	dial func(ctx context.Context, ip, target string) (net.Conn, error)
 }
 
 // NewConnectHandler creates a new ConnectHandler.
@@ -39,70 +45,231 @@ func (h *ConnectHandler) ServeHTTP(w http.ResponseWriter, r *http.Request) {
 	if host == "" {
 		host = r.URL.Host
 	}
+	span := log.Span("request_id", requestID, "host", host)
 
-	logger.Trace("connect_request_received", "request_id", requestID, "host", host, "remote", r.RemoteAddr)
+	span.Trace("connect_request_received", "remote", r.RemoteAddr)
+
+	// Apply the CONNECT header policy
+	loggedHeaders, err := h.server.checkConnectHeaders(r)
+	if err != nil {
+		span.Warn("connect_headers_rejected", "remote", r.RemoteAddr, "error", err)
+		http.Error(w, "Forbidden by header policy", http.StatusForbidden)
+		metrics.RequestsTotal.WithLabelValues("CONNECT", "403").Inc()
+		return
+	}
+
+	// Apply the CONNECT port policy
+	if err := h.server.checkConnectPort(host); err != nil {
+		span.Warn("connect_port_rejected", "remote", r.RemoteAddr, "error", err)
+		status := h.server.sendBlocked(w, r, host, err, "Forbidden by port policy")
+		metrics.RequestsTotal.WithLabelValues("CONNECT", fmt.Sprintf("%d", status)).Inc()
+		return
+	}
 
 	// Select outbound IP
-	logger.Trace("connect_ip_selection_start", "host", host)
-	ip, err := h.server.selectIP(host)
+	span.Trace("connect_ip_selection_start")
+	selectSpan := startPhase(r.Context(), "select", tracing.KindInternal)
+	ip, prov, err := h.server.selectIPForRequest(r, host)
+	selectSpan.SetAttr("outbound_ip", ip)
+	endPhase(selectSpan, err)
 	if err != nil {
-		logger.Trace("connect_ip_selection_failed", "host", host, "error", err)
-		http.Error(w, "No available outbound IPs", http.StatusServiceUnavailable)
-		metrics.LimitRejections.WithLabelValues("total").Inc()
+		span.Trace("connect_ip_selection_failed", "error", err)
+		if errors.Is(err, ErrMaintenance) {
+			h.server.sendMaintenance(w)
+			return
+		}
+		if errors.Is(err, ErrDestinationNotAllowed) {
+			h.server.sendBlocked(w, r, host, err, selectionErrorMessage(err))
+			return
+		}
+		status := selectionErrorStatus(err)
+		http.Error(w, selectionErrorMessage(err), status)
+		if status == http.StatusServiceUnavailable {
+			metrics.LimitRejections.WithLabelValues("total").Inc()
+		}
 		return
 	}
-	logger.Trace("connect_ip_selected", "host", host, "ip", ip)
+	span.Trace("connect_ip_selected", "ip", ip)
+
+	// Admit the user under its limits and fair share of the pool
+	user, limit := h.server.requestUser(r)
+	releaseUser, err := h.server.admitUser(user, limit)
+	if err != nil {
+		h.server.rejectUser(w, err)
+		return
+	}
+	defer releaseUser()
 
 	// Acquire connection slot
-	logger.Trace("connect_acquire_attempt", "ip", ip)
-	if err := h.server.limiter.Acquire(ip); err != nil {
-		logger.Trace("connect_acquire_failed", "ip", ip, "error", err)
+	span.Trace("connect_acquire_attempt", "ip", ip)
+	release, err := h.acquireExit(ip)
+	if err != nil {
+		span.Trace("connect_acquire_failed", "ip", ip, "error", err)
 		http.Error(w, "Connection limit reached", http.StatusServiceUnavailable)
 		metrics.LimitRejections.WithLabelValues("per_ip").Inc()
 		logger.LogConnectionLimit("per_ip", ip, int(h.server.limiter.GetIPCount(ip)), h.server.cfg.MaxConnsPerIP)
 		return
 	}
-	logger.Trace("connect_acquired", "ip", ip)
-	defer h.server.limiter.Release(ip)
+	span.Trace("connect_acquired", "ip", ip)
+	defer func() { release() }()
+
+	metrics.TunnelConnections.Inc()
 
-	// Update metrics
+	// Connect to target, retrying on alternate outbound IPs
+	target := h.server.dialTarget(tenantFromRequest(r), host)
+	a, hedged, tried := h.dialHedged(r, host, target, ip, release, nil)
+	ip, release, targetConn, err := a.ip, a.release, a.conn, a.err
+	if hedged {
+		prov.source = selectionHedge
+	}
+	retries := 0
+	for attempt := 1; err != nil && attempt <= h.server.cfg.ConnectRetries && h.server.retryable(err); attempt++ {
+		if !h.server.waitRetry(r.Context(), attempt) {
+			break
+		}
+		next, ok := h.server.retryIPForRequest(r, host, tried)
+		if !ok {
+			break
+		}
+		nextRelease, acqErr := h.acquireExit(next)
+		if acqErr != nil {
+			break
+		}
+		span.Debug("connect_retry", "failed_ip", ip, "ip", next, "attempt", attempt, "error_class", dialErrorClass(err))
+		metrics.ConnectRetries.Inc()
+		release()
+		a, _, tried = h.dialHedged(r, host, target, next, nextRelease, tried)
+		ip, release, targetConn, err = a.ip, a.release, a.conn, a.err
+		prov.source = selectionRetry
+		retries = attempt
+	}
+	if err != nil {
+		span.Trace("connect_dial_failed", "ip", ip, "error", err)
+		logger.LogError("connect_dial", err, "host", host, "ip", ip)
+		status := dialErrorStatus(err)
+		if status == http.StatusForbidden {
+			status = h.server.sendBlocked(w, r, host, err, dialErrorMessage(err, ""))
+		} else {
+			http.Error(w, dialErrorMessage(err, "Failed to connect to target"), status)
+		}
+		metrics.RequestsTotal.WithLabelValues("CONNECT", fmt.Sprintf("%d", status)).Inc()
+		return
+	}
+	span.Trace("connect_dial_success", "ip", ip, "local", targetConn.LocalAddr(), "remote", targetConn.RemoteAddr())
+	defer targetConn.Close()
+	targetConn = h.server.watchFTP(targetConn, netutil.ParseHost(r.RemoteAddr), host, ip)
+	targetConn = h.server.shapeTunnel(targetConn, ip, host, h.server.routingHints(r))
+
+	clientConn, err := h.establish(w, r, host, h.server.retryAnnotation(retries, ip))
+	if err != nil {
+		return
+	}
+	defer clientConn.Close()
+
+	// Detect the tunneled protocol and apply its policy
+	tunnelConn, targetConn, _, err := h.server.inspectTunnel(clientConn, targetConn, host, requestID)
+	if err != nil {
+		span.Warn("tunnel_rejected", "error", err)
+		metrics.RequestsTotal.WithLabelValues("CONNECT", "403").Inc()
+		return
+	}
+
+	// Register the tunnel so it can be evicted under fd pressure
+	entry := h.server.tunnels.add(host, ip, func() {
+		tunnelConn.Close()
+		targetConn.Close()
+	})
+	defer h.server.endTunnel(entry)
+
+	// Bidirectional copy with idle timeout
+	relay := startPhase(r.Context(), "relay", tracing.KindInternal)
+	bytesIn, bytesOut := h.tunnel(tunnelConn, entry.wrap(targetConn), h.server.cfg.EffectiveTunnelIdleTimeout(), requestID)
+	traceRelay(relay, bytesIn, bytesOut)
+	tracing.SpanFromContext(r.Context()).SetAttr("http.response.status_code", http.StatusOK)
+
+	// Log and record metrics
+	duration := time.Since(start).Milliseconds()
+	var logArgs []any
+	if loggedHeaders != nil {
+		logArgs = append(logArgs, "connect_headers", loggedHeaders)
+	}
+	h.server.logRequest("CONNECT", host, r.RemoteAddr, tenantFromRequest(r), ip, 200, duration, bytesIn, bytesOut, prov, logArgs...)
+
+	h.server.stats.IncTotalRequests()
+	h.server.stats.AddBytesReceived(bytesIn)
+	h.server.stats.AddBytesSent(bytesOut)
+	h.server.quotas.AddBytes(user, bytesIn+bytesOut)
+
+	metrics.RequestsTotal.WithLabelValues("CONNECT", "200").Inc()
+	metrics.RequestDuration.WithLabelValues("CONNECT").Observe(time.Since(start).Seconds())
+}
+
+// acquireExit takes a connection slot on ip and counts the connection against
+// it. The returned func undoes both.
+func (h *ConnectHandler) acquireExit(ip string) (func(), error) {
+	if err := h.server.limiter.Acquire(ip); err != nil {
+		return nil, err
+	}
 	h.server.stats.IncActiveConnections()
 	h.server.stats.IncConnectionsForIP(ip)
-	defer func() {
+	return func() {
 		h.server.stats.DecActiveConnections()
 		h.server.stats.DecConnectionsForIP(ip)
-	}()
+		h.server.limiter.Release(ip)
+	}, nil
+}
 
-	// Record selection
+// dialExit records ip as selected for host and dials target from it. Dials
+// cancelled through ctx are not counted against ip's health.
+func (h *ConnectHandler) dialExit(ctx context.Context, host, target, ip string) (net.Conn, error) {
 	h.server.balancer.Record(host, ip)
 	h.server.stats.IncSelectionsForIP(ip, host)
 	logger.LogBalancerSelection(host, ip, len(h.server.cfg.IPs))
 
-	metrics.TunnelConnections.Inc()
+	dial := h.dial
+	if dial == nil {
+		dial = func(ctx context.Context, ip, target string) (net.Conn, error) {
+			dialer := NewDialer(ip, h.server.connectDialTimeout(), h.server.cfg.IdleTimeout, h.server.outboundDialOptions()...)
+			return dialer.DialContext(ctx, "tcp", target)
+		}
+	}
 
-	// Create dialer for this IP
-	dialer := NewDialer(ip, h.server.cfg.Timeout, h.server.cfg.IdleTimeout)
+	log.Trace("connect_dial_start", "host", host, "ip", ip)
+	dialStart := time.Now()
+	conn, err := dial(ctx, ip, target)
+	if err == nil || ctx.Err() == nil {
+		h.server.slo.Observe(host, ip, time.Since(dialStart), dialFailed(err))
+		h.server.recordOutcome(ip, dialFailed(err))
+	}
+	return conn, err
+}
 
-	// Connect to target
-	logger.Trace("connect_dial_start", "host", host, "ip", ip)
-	targetConn, err := dialer.Dial("tcp", host)
-	if err != nil {
-		logger.Trace("connect_dial_failed", "host", host, "ip", ip, "error", err)
-		logger.LogError("connect_dial", err, "host", host, "ip", ip)
-		http.Error(w, "Failed to connect to target", http.StatusBadGateway)
-		metrics.RequestsTotal.WithLabelValues("CONNECT", "502").Inc()
-		return
+// establish answers the CONNECT request with header added to the response and
+// returns the client side of the tunnel. HTTP/1.x connections are hijacked;
+// HTTP/2 CONNECT streams are relayed in place, so many tunnels can share one
+// client connection.
+func (h *ConnectHandler) establish(w http.ResponseWriter, r *http.Request, host string, header http.Header) (net.Conn, error) {
+	if r.ProtoMajor == 2 {
+		for name, values := range header {
+			w.Header()[name] = values
+		}
+		w.WriteHeader(http.StatusOK)
+		conn := newStreamConn(w, r)
+		if err := conn.rc.Flush(); err != nil {
+			logger.LogError("connect_response", err, "host", host)
+			return nil, err
+		}
+		return conn, nil
 	}
-	logger.Trace("connect_dial_success", "host", host, "ip", ip, "local", targetConn.LocalAddr(), "remote", targetConn.RemoteAddr())
-	defer targetConn.Close()
 
 	// Hijack client connection
 	hijacker, ok := w.(http.Hijacker)
 	if !ok {
-		logger.LogError("connect_hijack", fmt.Errorf("hijacking not supported"), "host", host)
+		err := fmt.Errorf("hijacking not supported")
+		logger.LogError("connect_hijack", err, "host", host)
 		http.Error(w, "Hijacking not supported", http.StatusInternalServerError)
 		metrics.RequestsTotal.WithLabelValues("CONNECT", "500").Inc()
-		return
+		return nil, err
 	}
 
 	clientConn, _, err := hijacker.Hijack()
@@ -110,46 +277,53 @@ func (h *ConnectHandler) ServeHTTP(w http.ResponseWriter, r *http.Request) {
 		logger.LogError("connect_hijack", err, "host", host)
 		http.Error(w, "Failed to hijack connection", http.StatusInternalServerError)
 		metrics.RequestsTotal.WithLabelValues("CONNECT", "500").Inc()
-		return
+		return nil, err
 	}
-	defer clientConn.Close()
 
 	// Send 200 Connection Established
-	_, err = clientConn.Write([]byte("HTTP/1.1 200 Connection Established\r\n\r\n"))
-	if err != nil {
+	var resp bytes.Buffer
+	resp.WriteString("HTTP/1.1 200 Connection Established\r\n")
+	header.Write(&resp)
+	resp.WriteString("\r\n")
+	if _, err := clientConn.Write(resp.Bytes()); err != nil {
 		logger.LogError("connect_response", err, "host", host)
-		return
+		clientConn.Close()
+		return nil, err
 	}
+	return clientConn, nil
+}
 
-	// Bidirectional copy with idle timeout
-	bytesIn, bytesOut := h.tunnel(clientConn, targetConn, h.server.cfg.IdleTimeout)
-
-	// Log and record metrics
-	duration := time.Since(start).Milliseconds()
-	logger.LogRequest("CONNECT", host, r.RemoteAddr, ip, 200, duration, bytesIn, bytesOut)
-
-	h.server.stats.IncTotalRequests()
-	h.server.stats.AddBytesReceived(bytesIn)
-	h.server.stats.AddBytesSent(bytesOut)
-
-	metrics.RequestsTotal.WithLabelValues("CONNECT", "200").Inc()
-	metrics.RequestDuration.WithLabelValues("CONNECT").Observe(time.Since(start).Seconds())
+// closeWriter is implemented by connections that support half-close.
+type closeWriter interface {
+	CloseWrite() error
 }
 
 // tunnel performs bidirectional copy between two connections with idle timeout.
-// The timeout is reset on each successful read/write operation.
-func (h *ConnectHandler) tunnel(client, target net.Conn, idleTimeout time.Duration) (bytesIn, bytesOut int64) {
+// The timeout is reset on each successful read/write operation. Its records
+// are logged by the relay module under requestID.
+func (h *ConnectHandler) tunnel(client, target net.Conn, idleTimeout time.Duration, requestID string) (bytesIn, bytesOut int64) {
 	var wg sync.WaitGroup
 	var in, out atomic.Int64
 	wg.Add(2)
+	span := logger.Module("relay").Span("request_id", requestID)
 
-	logger.Trace("tunnel_started", "client", client.RemoteAddr(), "target", target.RemoteAddr(), "idle_timeout", idleTimeout)
+	span.Trace("tunnel_started", "client", client.RemoteAddr(), "target", target.RemoteAddr(), "idle_timeout", idleTimeout)
 
 	// Set initial deadline
 	deadline := time.Now().Add(idleTimeout)
 	client.SetDeadline(deadline)
 	target.SetDeadline(deadline)
 
+	// Close both sides once the tunnel outlives tunnel-max-lifetime, busy or not
+	if lifetime := h.server.cfg.TunnelMaxLifetime; lifetime > 0 {
+		timer := time.AfterFunc(lifetime, func() {
+			span.Debug("tunnel_max_lifetime_reached", "client", client.RemoteAddr(), "target", target.RemoteAddr(), "lifetime", lifetime)
+			client.Close()
+			target.Close()
+		})
+		defer timer.Stop()
+	}
+
 	// Client -> Target
 	go func() {
 		defer wg.Done()
@@ -158,10 +332,10 @@ func (h *ConnectHandler) tunnel(client, target net.Conn, idleTimeout time.Durati
 			logger.LogError("tunnel_client_to_target", err)
 		}
 		in.Store(n)
-		logger.Trace("tunnel_transfer_complete", "direction", "client_to_target", "bytes", n)
+		span.Trace("tunnel_transfer_complete", "direction", "client_to_target", "bytes", n)
 		// Signal EOF to target
-		if tc, ok := target.(*net.TCPConn); ok {
-			tc.CloseWrite()
+		if cw, ok := target.(closeWriter); ok {
+			cw.CloseWrite()
 		}
 	}()
 
@@ -173,15 +347,15 @@ func (h *ConnectHandler) tunnel(client, target net.Conn, idleTimeout time.Durati
 			logger.LogError("tunnel_target_to_client", err)
 		}
 		out.Store(n)
-		logger.Trace("tunnel_transfer_complete", "direction", "target_to_client", "bytes", n)
+		span.Trace("tunnel_transfer_complete", "direction", "target_to_client", "bytes", n)
 		// Signal EOF to client
-		if tc, ok := client.(*net.TCPConn); ok {
-			tc.CloseWrite()
+		if cw, ok := client.(closeWriter); ok {
+			cw.CloseWrite()
 		}
 	}()
 
 	wg.Wait()
-	logger.Trace("tunnel_closed", "client", client.RemoteAddr(), "target", target.RemoteAddr(), "bytes_in", in.Load(), "bytes_out", out.Load())
+	span.Trace("tunnel_closed", "client", client.RemoteAddr(), "target", target.RemoteAddr(), "bytes_in", in.Load(), "bytes_out", out.Load())
 	return in.Load(), out.Load()
 }
 
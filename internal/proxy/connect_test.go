@@ -1,7 +1,11 @@
 package proxy
 
 import (
+	"bufio"
+	"fmt"
 	"net"
+	"net/http"
+	"net/http/httptest"
 	"testing"
 	"time"
 
@@ -82,7 +86,7 @@ func TestConnectHandler_tunnel(t *testing.T) {
 
 	// Run tunnel - clientRead is the "client" conn, targetRead is the "target" conn
 	// This is a simplified test that verifies the function doesn't panic
-	bytesIn, bytesOut := handler.tunnel(clientRead, targetRead, 60*time.Second)
+	bytesIn, bytesOut := handler.tunnel(clientRead, targetRead, 60*time.Second, "")
 
 	clientRead.Close()
 	targetRead.Close()
@@ -111,7 +115,7 @@ func TestConnectHandler_tunnel_EmptyData(t *testing.T) {
 	}()
 
 	// Run tunnel
-	bytesIn, bytesOut := handler.tunnel(clientRead, targetRead, 60*time.Second)
+	bytesIn, bytesOut := handler.tunnel(clientRead, targetRead, 60*time.Second, "")
 
 	clientRead.Close()
 	targetRead.Close()
@@ -155,7 +159,7 @@ func TestConnectHandler_tunnel_ConcurrentRaceDetection(t *testing.T) {
 			// Run tunnel in goroutine
 			go func() {
 				defer close(done)
-				bytesIn, bytesOut := handler.tunnel(clientRead, targetRead, 60*time.Second)
+				bytesIn, bytesOut := handler.tunnel(clientRead, targetRead, 60*time.Second, "")
 				// Verify bytes were transferred (values should match atomic operations)
 				if bytesIn < 0 || bytesOut < 0 {
 					t.Errorf("invalid byte counts: in=%d, out=%d", bytesIn, bytesOut)
@@ -211,7 +215,7 @@ func TestConnectHandler_tunnel_BidirectionalTransfer(t *testing.T) {
 
 	go func() {
 		defer close(done)
-		bytesIn, bytesOut = handler.tunnel(clientConn, targetConn, 60*time.Second)
+		bytesIn, bytesOut = handler.tunnel(clientConn, targetConn, 60*time.Second, "")
 	}()
 
 	select {
@@ -232,3 +236,148 @@ func TestConnectHandler_tunnel_BidirectionalTransfer(t *testing.T) {
 	clientConn.Close()
 	targetConn.Close()
 }
+
+func TestConnectHandler_tunnel_MaxLifetime(t *testing.T) {
+	server := newTestServerForConnect(t)
+	server.cfg.TunnelMaxLifetime = 50 * time.Millisecond
+	handler := NewConnectHandler(server)
+
+	// Both peers stay open and busy, so only the lifetime can end the tunnel
+	clientConn, clientPeer := net.Pipe()
+	targetConn, targetPeer := net.Pipe()
+	defer clientPeer.Close()
+	defer targetPeer.Close()
+	go func() {
+		for {
+			if _, err := clientPeer.Write([]byte("ping")); err != nil {
+				return
+			}
+			time.Sleep(5 * time.Millisecond)
+		}
+	}()
+	go func() {
+		buf := make([]byte, 1024)
+		for {
+			if _, err := targetPeer.Read(buf); err != nil {
+				return
+			}
+		}
+	}()
+
+	done := make(chan struct{})
+	go func() {
+		defer close(done)
+		handler.tunnel(clientConn, targetConn, 60*time.Second, "")
+	}()
+
+	select {
+	case <-done:
+	case <-time.After(5 * time.Second):
+		t.Fatal("tunnel outlived tunnel-max-lifetime")
+	}
+}
+
+// connectThrough issues a CONNECT to target through proxyAddr and returns the status code.
+func connectThrough(t *testing.T, proxyAddr, target string) int {
+	t.Helper()
+	return connectResponse(t, proxyAddr, target).StatusCode
+}
+
+// connectResponse issues a CONNECT to target through proxyAddr and returns the response.
+func connectResponse(t *testing.T, proxyAddr, target string) *http.Response {
+	t.Helper()
+	conn, err := net.Dial("tcp", proxyAddr)
+	if err != nil {
+		t.Fatalf("failed to dial proxy: %v", err)
+	}
+	defer conn.Close()
+	fmt.Fprintf(conn, "CONNECT %s HTTP/1.1\r\nHost: %s\r\n\r\n", target, target)
+	resp, err := http.ReadResponse(bufio.NewReader(conn), &http.Request{Method: http.MethodConnect})
+	if err != nil {
+		t.Fatalf("failed to read CONNECT response: %v", err)
+	}
+	return resp
+}
+
+func TestConnectHandler_RetriesAlternateIP(t *testing.T) {
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	defer l.Close()
+	go func() {
+		for {
+			c, err := l.Accept()
+			if err != nil {
+				return
+			}
+			c.Close()
+		}
+	}()
+	target := l.Addr().String()
+
+	for _, tt := range []struct {
+		retries int
+		want    int
+	}{
+		{retries: 0, want: http.StatusBadGateway},
+		{retries: 1, want: http.StatusOK},
+	} {
+		// 192.0.2.1 (TEST-NET-1) is not local, so dialing from it fails. Recording
+		// 127.0.0.1 first makes the balancer try 192.0.2.1 first.
+		s := newTestServerWithIPs(t, []string{"192.0.2.1", "127.0.0.1"})
+		s.cfg.ConnectRetries = tt.retries
+		s.cfg.ConnectRetryOn = []string{config.RetryOnBind}
+		s.balancer.Record(target, "127.0.0.1")
+
+		proxy := httptest.NewServer(NewConnectHandler(s))
+		if got := connectThrough(t, proxy.Listener.Addr().String(), target); got != tt.want {
+			t.Errorf("retries=%d: got status %d, want %d", tt.retries, got, tt.want)
+		}
+		proxy.Close()
+	}
+}
+
+func TestConnectHandler_RetryAnnotation(t *testing.T) {
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	defer l.Close()
+	go func() {
+		for {
+			c, err := l.Accept()
+			if err != nil {
+				return
+			}
+			c.Close()
+		}
+	}()
+	target := l.Addr().String()
+
+	for _, tt := range []struct {
+		name     string
+		recorded string
+		want     string
+	}{
+		// Recording an IP makes the balancer try the other one first
+		{name: "rescued", recorded: "127.0.0.1", want: "retries=1; egress=127.0.0.1"},
+		{name: "clean", recorded: "192.0.2.1", want: ""},
+	} {
+		s := newTestServerWithIPs(t, []string{"192.0.2.1", "127.0.0.1"})
+		s.cfg.ConnectRetries = 1
+		s.cfg.ConnectRetryOn = []string{config.RetryOnBind}
+		s.cfg.ConnectRetryHeader = "X-Outbound-Retry"
+		s.balancer.Record(target, tt.recorded)
+
+		proxy := httptest.NewServer(NewConnectHandler(s))
+		resp := connectResponse(t, proxy.Listener.Addr().String(), target)
+		if resp.StatusCode != http.StatusOK {
+			t.Errorf("%s: got status %d, want 200", tt.name, resp.StatusCode)
+		}
+		if got := resp.Header.Get("X-Outbound-Retry"); got != tt.want {
+			t.Errorf("%s: X-Outbound-Retry = %q, want %q", tt.name, got, tt.want)
+		}
+		proxy.Close()
+	}
+}
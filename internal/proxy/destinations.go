@@ -0,0 +1,250 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"bytes"
+	"errors"
+	"fmt"
+	"os"
+	"path/filepath"
+	"regexp"
+	"slices"
+	"sort"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+
+	"gopkg.in/yaml.v3"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// learnFlushInterval is how often learned destinations are written to disk.
+const learnFlushInterval = time.Minute
+
+// ErrDestinationNotAllowed is returned when a destination is not on the
+// allowlist or is on the denylist.
+var ErrDestinationNotAllowed = errors.New("destination not allowed")
+
+// destinationRegexps caches the compiled "/regexp/" destination entries.
+var destinationRegexps sync.Map // entry -> *regexp.Regexp
+
+// destinationName returns the canonical host of a host[:port] destination, as
+// allowlist entries and affinity keys are written (see netutil.NormalizeHost).
+func destinationName(host string) string {
+	return netutil.NormalizeHost(host)
+}
+
+// destinationAllowed reports whether name matches an allowlist entry. An empty
+// allowlist allows everything; "*.example.com" matches subdomains only.
+func destinationAllowed(allowed []string, name string) bool {
+	return len(allowed) == 0 || destinationMatches(allowed, name)
+}
+
+// destinationMatches reports whether name matches an entry of destinations.
+func destinationMatches(destinations []string, name string) bool {
+	for _, entry := range destinations {
+		if expr, ok := config.DestinationRegexp(entry); ok {
+			if re := destinationRegexp(entry, expr); re != nil && re.MatchString(name) {
+				return true
+			}
+			continue
+		}
+		if suffix, ok := strings.CutPrefix(entry, "*."); ok {
+			if strings.HasSuffix(name, "."+suffix) {
+				return true
+			}
+			continue
+		}
+		if name == entry {
+			return true
+		}
+	}
+	return false
+}
+
+// destinationRegexp returns the compiled expr of a "/regexp/" entry, or nil
+// if it doesn't compile (config validation refuses those).
+func destinationRegexp(entry, expr string) *regexp.Regexp {
+	if re, ok := destinationRegexps.Load(entry); ok {
+		return re.(*regexp.Regexp)
+	}
+	re, err := regexp.Compile(expr)
+	if err != nil {
+		return nil
+	}
+	destinationRegexps.Store(entry, re)
+	return re
+}
+
+// admitDestination records host in learning mode and checks it against the
+// allow and deny lists of the config and policy files; deny wins. Refused
+// destinations are learned too, so the proposed list shows what enforcement
+// blocks.
+func (s *Server) admitDestination(host string) error {
+	name := destinationName(host)
+	s.learner.record(name)
+	p := s.policy.Load()
+	rule := ""
+	switch {
+	case destinationMatches(s.cfg.DeniedDestinations, name) || destinationMatches(p.denied, name):
+		rule = "denied_destinations"
+	case !destinationAllowed(slices.Concat(s.cfg.AllowedDestinations, p.allowed), name):
+		rule = "allowed_destinations"
+	default:
+		return nil
+	}
+	log.Warn("destination_not_allowed", "host", host)
+	return &policyDenial{ErrDestinationNotAllowed, rule, name}
+}
+
+// destinationLearner counts requested destinations and periodically writes
+// them as a proposed allowed_destinations list, one entry per host with its
+// hit count as a comment. An existing file is loaded so learning resumes
+// across restarts.
+type destinationLearner struct {
+	path     string
+	hits     map[string]int64
+	dirty    bool
+	clock    clock.Clock
+	stopCh   chan struct{}
+	stopOnce sync.Once
+	wg       sync.WaitGroup
+	mu       sync.Mutex
+}
+
+// newDestinationLearner creates a destinationLearner writing to path.
+func newDestinationLearner(path string, c clock.Clock) *destinationLearner {
+	c = clock.OrReal(c)
+	l := &destinationLearner{
+		path:   path,
+		hits:   make(map[string]int64),
+		clock:  c,
+		stopCh: make(chan struct{}),
+	}
+	if err := l.load(); err != nil && !errors.Is(err, os.ErrNotExist) {
+		log.Warn("learn_destinations_load_failed", "path", path, "error", err)
+	}
+	return l
+}
+
+// record counts one request for name. Safe to call on a nil learner.
+func (l *destinationLearner) record(name string) {
+	if l == nil || name == "" {
+		return
+	}
+	l.mu.Lock()
+	l.hits[name]++
+	l.dirty = true
+	l.mu.Unlock()
+}
+
+// Start starts writing the learned destinations every learnFlushInterval.
+func (l *destinationLearner) Start() {
+	log.Info("learn_destinations_started", "path", l.path)
+	l.wg.Add(1)
+	go func() {
+		defer l.wg.Done()
+		ticker := time.NewTicker(learnFlushInterval)
+		defer ticker.Stop()
+		for {
+			select {
+			case <-ticker.C:
+				l.flushLogged()
+			case <-l.stopCh:
+				return
+			}
+		}
+	}()
+}
+
+// Stop stops the writer goroutine and writes any pending destinations.
+func (l *destinationLearner) Stop() {
+	l.stopOnce.Do(func() { close(l.stopCh) })
+	l.wg.Wait()
+	l.flushLogged()
+}
+
+// flushLogged writes the learned destinations, logging failures.
+func (l *destinationLearner) flushLogged() {
+	if err := l.flush(); err != nil {
+		log.Warn("learn_destinations_write_failed", "path", l.path, "error", err)
+	}
+}
+
+// flush writes the learned destinations if they changed since the last write.
+// The file is replaced atomically so reviewers never see a partial list.
+func (l *destinationLearner) flush() error {
+	l.mu.Lock()
+	if !l.dirty {
+		l.mu.Unlock()
+		return nil
+	}
+	data := l.render()
+	l.dirty = false
+	l.mu.Unlock()
+
+	tmp, err := os.CreateTemp(filepath.Dir(l.path), ".learn-*")
+	if err != nil {
+		return err
+	}
+	defer os.Remove(tmp.Name())
+	if _, err := tmp.Write(data); err != nil {
+		tmp.Close()
+		return err
+	}
+	if err := tmp.Close(); err != nil {
+		return err
+	}
+	return os.Rename(tmp.Name(), l.path)
+}
+
+// render returns the proposed allowlist, most requested destinations first.
+// Must be called with mu held.
+func (l *destinationLearner) render() []byte {
+	names := make([]string, 0, len(l.hits))
+	for name := range l.hits {
+		names = append(names, name)
+	}
+	sort.Slice(names, func(i, j int) bool {
+		if l.hits[names[i]] != l.hits[names[j]] {
+			return l.hits[names[i]] > l.hits[names[j]]
+		}
+		return names[i] < names[j]
+	})
+
+	var buf bytes.Buffer
+	fmt.Fprintf(&buf, "# Destinations learned by outbound-lb, updated %s.\n", l.clock.Now().UTC().Format(time.RFC3339))
+	buf.WriteString("# Review the list, then copy allowed_destinations into the config to enforce it.\n")
+	buf.WriteString("allowed_destinations:\n")
+	for _, name := range names {
+		fmt.Fprintf(&buf, "  - %s # hits: %d\n", strconv.Quote(name), l.hits[name])
+	}
+	return buf.Bytes()
+}
+
+// load reads a previously written list, restoring the hit counts from the entry comments.
+func (l *destinationLearner) load() error {
+	data, err := os.ReadFile(l.path)
+	if err != nil {
+		return err
+	}
+	var doc struct {
+		Destinations yaml.Node `yaml:"allowed_destinations"`
+	}
+	if err := yaml.Unmarshal(data, &doc); err != nil {
+		return err
+	}
+	for _, entry := range doc.Destinations.Content {
+		if entry.Kind != yaml.ScalarNode || entry.Value == "" {
+			continue
+		}
+		var hits int64
+		fmt.Sscanf(strings.TrimLeft(entry.LineComment, "# "), "hits: %d", &hits)
+		l.hits[entry.Value] += hits
+	}
+	return nil
+}
@@ -0,0 +1,168 @@
+package proxy
+
+import (
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func TestDestinationAllowed(t *testing.T) {
+	allowed := []string{"api.example.com", "*.cdn.example.com", "10.0.0.5"}
+
+	tests := []struct {
+		name string
+		want bool
+	}{
+		{"api.example.com", true},
+		{"www.example.com", false},
+		{"img.cdn.example.com", true},
+		{"cdn.example.com", false},
+		{"10.0.0.5", true},
+		{"evilcdn.example.com", false},
+	}
+	for _, tt := range tests {
+		if got := destinationAllowed(allowed, tt.name); got != tt.want {
+			t.Errorf("destinationAllowed(%q) = %v, want %v", tt.name, got, tt.want)
+		}
+	}
+
+	if !destinationAllowed(nil, "anything.example.org") {
+		t.Error("expected an empty allowlist to allow every destination")
+	}
+}
+
+func TestDestinationMatches_Regexp(t *testing.T) {
+	destinations := []string{`/^api[0-9]+\.example\.com$/`}
+	tests := map[string]bool{
+		"api1.example.com":      true,
+		"api42.example.com":     true,
+		"api.example.com":       false,
+		"api1.example.com.evil": false,
+	}
+	for name, want := range tests {
+		if got := destinationMatches(destinations, name); got != want {
+			t.Errorf("destinationMatches(%q) = %v, want %v", name, got, want)
+		}
+	}
+}
+
+func TestDestinationName(t *testing.T) {
+	tests := map[string]string{
+		"API.Example.com:443": "api.example.com",
+		"example.com.":        "example.com",
+		"[2001:db8::1]:443":   "2001:db8::1",
+		"BÜCHER.de:443":       "xn--bcher-kva.de",
+		"ｅｘａｍｐｌｅ．com":         "example.com",
+	}
+	for in, want := range tests {
+		if got := destinationName(in); got != want {
+			t.Errorf("destinationName(%q) = %q, want %q", in, got, want)
+		}
+	}
+}
+
+func TestSelectOptions_RefusesDestinationOffAllowlist(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.AllowedDestinations = []string{"*.example.com"}
+
+	if _, _, err := server.selectOptions("api.example.com:443", RoutingHints{}); err != nil {
+		t.Errorf("expected allowed destination to pass, got %v", err)
+	}
+	_, _, err := server.selectOptions("example.org:443", RoutingHints{})
+	if !errors.Is(err, ErrDestinationNotAllowed) {
+		t.Errorf("expected ErrDestinationNotAllowed, got %v", err)
+	}
+}
+
+func TestSelectOptions_DenylistWinsOverAllowlist(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.AllowedDestinations = []string{"*.example.com"}
+	server.cfg.DeniedDestinations = []string{"admin.example.com", "/^internal-/"}
+
+	if _, _, err := server.selectOptions("api.example.com:443", RoutingHints{}); err != nil {
+		t.Errorf("expected allowed destination to pass, got %v", err)
+	}
+	for _, host := range []string{"admin.example.com:443", "internal-db.example.com:5432"} {
+		if _, _, err := server.selectOptions(host, RoutingHints{}); !errors.Is(err, ErrDestinationNotAllowed) {
+			t.Errorf("selectOptions(%q) error = %v, want ErrDestinationNotAllowed", host, err)
+		}
+	}
+
+	// Without an allowlist, everything but the denied hosts passes
+	server.cfg.AllowedDestinations = nil
+	if _, _, err := server.selectOptions("example.org:443", RoutingHints{}); err != nil {
+		t.Errorf("expected destination off the denylist to pass, got %v", err)
+	}
+}
+
+func TestSelectOptions_AllowlistMatchesIDN(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.AllowedDestinations = []string{"*.xn--bcher-kva.de"}
+
+	if _, _, err := server.selectOptions("shop.Bücher.de:443", RoutingHints{}); err != nil {
+		t.Errorf("expected Unicode form of an allowed destination to pass, got %v", err)
+	}
+	// Fullwidth letters and dots map to ASCII and cannot disguise another host
+	_, _, err := server.selectOptions("shop.ｂｕｃｈｅｒ．de:443", RoutingHints{})
+	if !errors.Is(err, ErrDestinationNotAllowed) {
+		t.Errorf("expected ErrDestinationNotAllowed, got %v", err)
+	}
+}
+
+func TestConnectHandler_DestinationNotAllowed(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.AllowedDestinations = []string{"example.com"}
+
+	r := httptest.NewRequest(http.MethodConnect, "http://example.org:443", nil)
+	r.Host = "example.org:443"
+	w := httptest.NewRecorder()
+	server.connectHandler.ServeHTTP(w, r)
+
+	if w.Code != http.StatusForbidden {
+		t.Errorf("status = %d, want 403", w.Code)
+	}
+}
+
+func TestDestinationLearner_WritesAndResumes(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "learned.yaml")
+	c := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+
+	l := newDestinationLearner(path, c)
+	for _, name := range []string{"api.example.com", "api.example.com", "cdn.example.com"} {
+		l.record(name)
+	}
+	if err := l.flush(); err != nil {
+		t.Fatalf("flush() error = %v", err)
+	}
+
+	data, err := os.ReadFile(path)
+	if err != nil {
+		t.Fatalf("failed to read learned file: %v", err)
+	}
+	want := `allowed_destinations:
+  - "api.example.com" # hits: 2
+  - "cdn.example.com" # hits: 1
+`
+	if !strings.HasSuffix(string(data), want) {
+		t.Errorf("unexpected learned file:\n%s", data)
+	}
+
+	// A restarted learner keeps counting from the file
+	resumed := newDestinationLearner(path, c)
+	resumed.record("cdn.example.com")
+	if resumed.hits["api.example.com"] != 2 || resumed.hits["cdn.example.com"] != 2 {
+		t.Errorf("unexpected resumed hits: %v", resumed.hits)
+	}
+}
+
+func TestDestinationLearner_NilIsNoop(t *testing.T) {
+	var l *destinationLearner
+	l.record("example.com")
+}
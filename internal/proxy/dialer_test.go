@@ -5,6 +5,8 @@ import (
 	"net"
 	"testing"
 	"time"
+
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
 )
 
 func TestDialer_Dial(t *testing.T) {
@@ -68,3 +70,19 @@ func TestDialer_DialContext_Timeout(t *testing.T) {
 		t.Error("expected timeout error")
 	}
 }
+
+func TestDialer_AppliesRoute(t *testing.T) {
+	listener, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to create listener: %v", err)
+	}
+	defer listener.Close()
+
+	// Binding to a device that doesn't exist fails the dial, showing the route is applied
+	routes := map[string]netutil.Route{"127.0.0.1": {Device: "missing-dev0"}}
+	d := NewDialer("127.0.0.1", time.Second, 10*time.Second, WithRoutes(routes))
+	if conn, err := d.Dial("tcp", listener.Addr().String()); err == nil {
+		conn.Close()
+		t.Fatal("expected dial through an unknown device to fail")
+	}
+}
@@ -0,0 +1,198 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"net"
+	"net/http"
+	"slices"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// ErrUnknownIP is returned when an operation targets an IP that is not configured.
+var ErrUnknownIP = errors.New("unknown outbound IP")
+
+// clientConnKey is the context key for per-client-connection state.
+type clientConnKey struct{}
+
+// clientConnState tracks state shared by the requests of one keep-alive client connection.
+type clientConnState struct {
+	lastIP string
+	mu     sync.Mutex
+}
+
+// contextWithClientConn attaches fresh client connection state to ctx.
+// Used as the http.Server ConnContext hook.
+func contextWithClientConn(ctx context.Context, _ net.Conn) context.Context {
+	return context.WithValue(ctx, clientConnKey{}, &clientConnState{})
+}
+
+// clientConnFromContext returns the client connection state, or nil if none is attached.
+func clientConnFromContext(ctx context.Context) *clientConnState {
+	state, _ := ctx.Value(clientConnKey{}).(*clientConnState)
+	return state
+}
+
+// swapIP records ip as the connection's current egress and returns the previous one.
+func (c *clientConnState) swapIP(ip string) string {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	prev := c.lastIP
+	c.lastIP = ip
+	return prev
+}
+
+// noteEgress records the egress used by a plain-HTTP request. When the client's
+// previous request on the same keep-alive connection went out through an IP that
+// is now draining, the switch to the new IP is counted as a migration.
+func (s *Server) noteEgress(r *http.Request, ip, requestID string) {
+	state := clientConnFromContext(r.Context())
+	if state == nil {
+		return
+	}
+	prev := state.swapIP(ip)
+	if prev != "" && prev != ip && s.balancer.IsDraining(prev) {
+		metrics.DrainMigrations.Inc()
+		log.Debug("egress_migrated", "request_id", requestID, "remote", r.RemoteAddr, "from", prev, "to", ip)
+	}
+}
+
+// DrainIP stops new selections of ip. In-flight requests and tunnels finish on it,
+// keep-alive plain-HTTP clients move to another IP on their next request, and idle
+// upstream connections from ip are closed.
+func (s *Server) DrainIP(ip string) error {
+	return s.DrainIPGradually(ip, 0)
+}
+
+// DrainIPGradually lowers the share of new selections ip receives to zero over
+// period, then behaves like DrainIP. A zero period drains immediately. Idle
+// upstream connections are kept while the IP still carries traffic.
+func (s *Server) DrainIPGradually(ip string, period time.Duration) error {
+	if !slices.Contains(s.cfg.IPs, ip) {
+		return ErrUnknownIP
+	}
+	s.mu.Lock()
+	if !slices.Contains(s.adminDrains, ip) {
+		s.adminDrains = append(s.adminDrains, ip)
+	}
+	s.mu.Unlock()
+	s.drain(ip, period)
+	return nil
+}
+
+// drain takes ip out of service over period, or at once for a zero period.
+func (s *Server) drain(ip string, period time.Duration) {
+	if period > 0 {
+		if s.balancer.DrainGradually(ip, period) {
+			log.Info("ip_draining", "ip", ip, "period", period)
+		}
+		return
+	}
+	if s.balancer.Drain(ip) {
+		log.Info("ip_draining", "ip", ip)
+	}
+	s.transportPool.CloseIdle(ip)
+}
+
+// SetConfigDrains drains the IPs listed in the drain setting and returns IPs
+// removed from it since the last call to service. Drains started through the
+// admin API are left alone: an IP they hold is neither drained again nor
+// returned to service. Unknown IPs are logged and skipped.
+func (s *Server) SetConfigDrains(ips []string) {
+	s.mu.Lock()
+	prev := s.configDrains
+	s.configDrains = ips
+	admin := slices.Clone(s.adminDrains)
+	s.mu.Unlock()
+
+	for _, ip := range ips {
+		if slices.Contains(prev, ip) || slices.Contains(admin, ip) {
+			continue
+		}
+		if !slices.Contains(s.cfg.IPs, ip) {
+			log.Warn("config_drain_failed", "ip", ip, "error", ErrUnknownIP)
+			continue
+		}
+		s.drain(ip, 0)
+	}
+	for _, ip := range prev {
+		if !slices.Contains(ips, ip) && !slices.Contains(admin, ip) {
+			s.undrain(ip)
+		}
+	}
+}
+
+// UndrainIP ends an admin drain of ip, returning it to service unless the
+// drain setting still lists it.
+func (s *Server) UndrainIP(ip string) error {
+	if !slices.Contains(s.cfg.IPs, ip) {
+		return ErrUnknownIP
+	}
+	s.mu.Lock()
+	s.adminDrains = slices.DeleteFunc(s.adminDrains, func(d string) bool { return d == ip })
+	held := slices.Contains(s.configDrains, ip)
+	s.mu.Unlock()
+	if !held {
+		s.undrain(ip)
+	}
+	return nil
+}
+
+// undrain returns ip to service.
+func (s *Server) undrain(ip string) {
+	if s.balancer.Undrain(ip) {
+		log.Info("ip_undrained", "ip", ip)
+	}
+}
+
+// DrainHandler returns the admin handler for draining IPs.
+// GET lists draining IPs with their remaining weight and active connections,
+// POST ?ip=X drains X over ?period= (default drain_period) and DELETE ?ip=X
+// returns it to service.
+func (s *Server) DrainHandler() http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		var err error
+		switch r.Method {
+		case http.MethodGet:
+		case http.MethodPost:
+			period := s.cfg.DrainPeriod
+			if v := r.URL.Query().Get("period"); v != "" {
+				if period, err = time.ParseDuration(v); err != nil || period < 0 {
+					http.Error(w, "Invalid period", http.StatusBadRequest)
+					return
+				}
+			}
+			err = s.DrainIPGradually(r.URL.Query().Get("ip"), period)
+		case http.MethodDelete:
+			err = s.UndrainIP(r.URL.Query().Get("ip"))
+		default:
+			w.Header().Set("Allow", "GET, POST, DELETE")
+			http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+			return
+		}
+		if errors.Is(err, ErrUnknownIP) {
+			http.Error(w, err.Error(), http.StatusNotFound)
+			return
+		}
+
+		w.Header().Set("Content-Type", "application/json")
+		w.WriteHeader(http.StatusOK)
+		draining := s.balancer.DrainingIPs()
+		weights := make(map[string]float64, len(draining))
+		active := make(map[string]int64, len(draining))
+		for _, ip := range draining {
+			weights[ip] = s.balancer.DrainWeight(ip)
+			active[ip] = s.limiter.GetIPCount(ip)
+		}
+		json.NewEncoder(w).Encode(map[string]any{
+			"draining": draining,
+			"weights":  weights,
+			"active":   active,
+		})
+	})
+}
@@ -0,0 +1,172 @@
+package proxy
+
+import (
+	"encoding/json"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+func TestNoteEgress_CountsMigration(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req = req.WithContext(contextWithClientConn(req.Context(), nil))
+	state := clientConnFromContext(req.Context())
+
+	server.noteEgress(req, "127.0.0.1", "r1")
+	if err := server.DrainIP("127.0.0.1"); err != nil {
+		t.Fatalf("DrainIP failed: %v", err)
+	}
+
+	ip, _, err := server.selectIPForRequest(req, "example.com")
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if ip != "127.0.0.2" {
+		t.Fatalf("expected keep-alive client to move to 127.0.0.2, got %s", ip)
+	}
+	server.noteEgress(req, ip, "r2")
+
+	if state.lastIP != "127.0.0.2" {
+		t.Errorf("expected connection egress to be 127.0.0.2, got %s", state.lastIP)
+	}
+}
+
+func TestDrainHandler(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	handler := server.DrainHandler()
+
+	rec := httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/admin/drain?ip=127.0.0.2", nil))
+	if rec.Code != http.StatusOK {
+		t.Fatalf("expected 200, got %d", rec.Code)
+	}
+
+	var body struct {
+		Draining []string `json:"draining"`
+	}
+	if err := json.NewDecoder(rec.Body).Decode(&body); err != nil {
+		t.Fatalf("invalid JSON: %v", err)
+	}
+	if len(body.Draining) != 1 || body.Draining[0] != "127.0.0.2" {
+		t.Errorf("draining = %v, want [127.0.0.2]", body.Draining)
+	}
+
+	rec = httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodDelete, "/admin/drain?ip=127.0.0.2", nil))
+	if rec.Code != http.StatusOK || server.balancer.IsDraining("127.0.0.2") {
+		t.Error("expected DELETE to undrain the IP")
+	}
+
+	rec = httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/admin/drain?ip=10.9.9.9", nil))
+	if rec.Code != http.StatusNotFound {
+		t.Errorf("expected 404 for unknown IP, got %d", rec.Code)
+	}
+}
+
+func TestDrainHandler_Period(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	handler := server.DrainHandler()
+
+	rec := httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/admin/drain?ip=127.0.0.2&period=1h", nil))
+	if rec.Code != http.StatusOK {
+		t.Fatalf("expected 200, got %d", rec.Code)
+	}
+
+	var body struct {
+		Weights map[string]float64 `json:"weights"`
+	}
+	if err := json.NewDecoder(rec.Body).Decode(&body); err != nil {
+		t.Fatalf("invalid JSON: %v", err)
+	}
+	if w, ok := body.Weights["127.0.0.2"]; !ok || w <= 0 || w > 1 {
+		t.Errorf("weights = %v, want 127.0.0.2 still carrying traffic", body.Weights)
+	}
+
+	rec = httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/admin/drain?ip=127.0.0.1&period=soon", nil))
+	if rec.Code != http.StatusBadRequest {
+		t.Errorf("expected 400 for invalid period, got %d", rec.Code)
+	}
+}
+
+func TestSetConfigDrains(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	if err := server.DrainIP("127.0.0.3"); err != nil {
+		t.Fatalf("DrainIP failed: %v", err)
+	}
+
+	server.SetConfigDrains([]string{"127.0.0.1", "10.9.9.9"})
+	if !server.balancer.IsDraining("127.0.0.1") {
+		t.Error("expected 127.0.0.1 to be drained by config")
+	}
+
+	// Removing an IP from the setting undrains it, but not admin drains.
+	server.SetConfigDrains([]string{"127.0.0.2"})
+	if server.balancer.IsDraining("127.0.0.1") {
+		t.Error("expected 127.0.0.1 back in service")
+	}
+	if !server.balancer.IsDraining("127.0.0.2") || !server.balancer.IsDraining("127.0.0.3") {
+		t.Errorf("draining = %v, want 127.0.0.2 and 127.0.0.3", server.balancer.DrainingIPs())
+	}
+}
+
+func TestSetConfigDrains_AdminDrains(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+
+	// An IP drained both ways stays drained when the setting drops it
+	server.SetConfigDrains([]string{"127.0.0.1"})
+	if err := server.DrainIP("127.0.0.1"); err != nil {
+		t.Fatalf("DrainIP failed: %v", err)
+	}
+	server.SetConfigDrains(nil)
+	if !server.balancer.IsDraining("127.0.0.1") {
+		t.Error("expected the admin drain of 127.0.0.1 to survive its removal from the setting")
+	}
+
+	// Listing an IP draining gradually through the admin API doesn't cut it short
+	if err := server.DrainIPGradually("127.0.0.2", time.Hour); err != nil {
+		t.Fatalf("DrainIPGradually failed: %v", err)
+	}
+	server.SetConfigDrains([]string{"127.0.0.2"})
+	if w := server.balancer.DrainWeight("127.0.0.2"); w <= 0 {
+		t.Errorf("weight = %v, want the gradual drain still in progress", w)
+	}
+
+	// Undraining through the admin API leaves an IP the setting lists drained
+	if err := server.UndrainIP("127.0.0.2"); err != nil {
+		t.Fatalf("UndrainIP failed: %v", err)
+	}
+	if !server.balancer.IsDraining("127.0.0.2") {
+		t.Error("expected 127.0.0.2 to stay drained by the setting")
+	}
+	server.SetConfigDrains(nil)
+	if server.balancer.IsDraining("127.0.0.2") {
+		t.Error("expected 127.0.0.2 back in service once neither holds it")
+	}
+}
+
+func TestDrainHandler_ActiveConnections(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	if err := server.limiter.Acquire("127.0.0.2"); err != nil {
+		t.Fatalf("Acquire failed: %v", err)
+	}
+	defer server.limiter.Release("127.0.0.2")
+
+	rec := httptest.NewRecorder()
+	server.DrainHandler().ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/admin/drain?ip=127.0.0.2", nil))
+
+	var body struct {
+		Active map[string]int64 `json:"active"`
+	}
+	if err := json.NewDecoder(rec.Body).Decode(&body); err != nil {
+		t.Fatalf("invalid JSON: %v", err)
+	}
+	if body.Active["127.0.0.2"] != 1 {
+		t.Errorf("active = %v, want the tunnel still open on 127.0.0.2", body.Active)
+	}
+}
@@ -0,0 +1,80 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"errors"
+	"net/http"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/internal/limiter"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// fairShareUser returns the identity a connection counts against for fair
+// sharing: the proxy username, or the client IP for unauthenticated clients.
+func fairShareUser(tenant, clientIP string) string {
+	if tenant != "" {
+		return tenant
+	}
+	return clientIP
+}
+
+// admitUser admits a connection for user under its connection limit, monthly
+// traffic quota and fair share of the pool. The returned func releases the
+// admission.
+func (s *Server) admitUser(user string, limit config.UserLimit) (func(), error) {
+	if err := s.quotas.Acquire(user, limiter.UserQuota{MaxConns: limit.MaxConns, MonthlyBytes: limit.MonthlyBytes}); err != nil {
+		log.Debug("user_limit_exceeded", "user", user, "error", err)
+		return nil, err
+	}
+	if err := s.fairShare.Acquire(user, s.limiter.GetTotalCount(), s.limiter.MaxTotal()); err != nil {
+		s.quotas.Release(user)
+		log.Debug("fair_share_exceeded", "user", user, "active", s.fairShare.Active(user))
+		return nil, err
+	}
+	return func() {
+		s.fairShare.Release(user)
+		s.quotas.Release(user)
+	}, nil
+}
+
+// limitRejectionType returns the LimitRejections type for a connection admission error.
+func limitRejectionType(err error) string {
+	switch {
+	case errors.Is(err, limiter.ErrFairShareExceeded):
+		return "fair_share"
+	case errors.Is(err, limiter.ErrUserConnsExceeded):
+		return "user_conns"
+	case errors.Is(err, limiter.ErrQuotaExceeded):
+		return "quota"
+	}
+	return "total"
+}
+
+// rejectUser answers a request refused by admitUser. A used-up traffic quota
+// is answered 407 so clients can switch credentials.
+func (s *Server) rejectUser(w http.ResponseWriter, err error) {
+	status, message := http.StatusServiceUnavailable, "Fair share of connections exceeded"
+	switch {
+	case errors.Is(err, limiter.ErrUserConnsExceeded):
+		status, message = http.StatusTooManyRequests, "Concurrent connection limit exceeded"
+	case errors.Is(err, limiter.ErrQuotaExceeded):
+		status, message = http.StatusProxyAuthRequired, "Monthly traffic quota exceeded"
+		w.Header().Set("Proxy-Authenticate", `Basic realm="Proxy"`)
+	}
+	http.Error(w, message, status)
+	metrics.LimitRejections.WithLabelValues(limitRejectionType(err)).Inc()
+}
+
+// UsersHandler returns the admin handler reporting the open connections,
+// traffic this month and limits of each user with a limit.
+func (s *Server) UsersHandler() http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if r.Method != http.MethodGet {
+			w.Header().Set("Allow", "GET")
+			http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+			return
+		}
+		writeJSON(w, http.StatusOK, map[string]any{"users": s.quotas.Usage()})
+	})
+}
@@ -0,0 +1,113 @@
+package proxy
+
+import (
+	"encoding/json"
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/internal/limiter"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+func TestAcquireConnection_FairShare(t *testing.T) {
+	s := newTestServer(t)
+	s.limiter = limiter.New(100, 4, s.cfg.IPs)
+	s.fairShare = limiter.NewFairShare(0.5, nil)
+
+	quiet := RoutingHints{ClientIP: "10.0.0.9"}
+	greedy := RoutingHints{Tenant: "greedy"}
+	first, err := s.AcquireConnection("example.com:443", "req", quiet)
+	if err != nil {
+		t.Fatalf("quiet acquire: %v", err)
+	}
+	conns := []*ConnectionContext{first}
+	for i := 0; i < 2; i++ {
+		c, err := s.AcquireConnection("example.com:443", "req", greedy)
+		if err != nil {
+			t.Fatalf("greedy acquire %d: %v", i, err)
+		}
+		conns = append(conns, c)
+	}
+
+	// Above the threshold a user already at its share is turned away...
+	if _, err := s.AcquireConnection("example.com:443", "req", greedy); !errors.Is(err, limiter.ErrFairShareExceeded) {
+		t.Fatalf("greedy acquire over share: got %v, want ErrFairShareExceeded", err)
+	}
+	if got := limitRejectionType(limiter.ErrFairShareExceeded); got != "fair_share" {
+		t.Errorf("limitRejectionType = %q, want fair_share", got)
+	}
+
+	// ...while a quiet user still gets in.
+	c, err := s.AcquireConnection("example.com:443", "req", quiet)
+	if err != nil {
+		t.Fatalf("quiet acquire under share: %v", err)
+	}
+	conns = append(conns, c)
+
+	for _, c := range conns {
+		c.Release()
+	}
+	if n := s.fairShare.Active("greedy"); n != 0 {
+		t.Errorf("Active(greedy) after release = %d, want 0", n)
+	}
+}
+
+func TestAcquireConnection_UserLimits(t *testing.T) {
+	s := newTestServer(t)
+	s.cfg.UserLimits = []config.UserLimit{
+		{User: "capped", MaxConns: 1},
+		{User: "metered", MonthlyBytes: 10},
+	}
+
+	capped := RoutingHints{Tenant: "capped"}
+	c, err := s.AcquireConnection("example.com:443", "req", capped)
+	if err != nil {
+		t.Fatalf("capped acquire: %v", err)
+	}
+	if _, err := s.AcquireConnection("example.com:443", "req", capped); !errors.Is(err, limiter.ErrUserConnsExceeded) {
+		t.Fatalf("capped acquire over limit: got %v, want ErrUserConnsExceeded", err)
+	}
+	c.Release()
+	c, err = s.AcquireConnection("example.com:443", "req", capped)
+	if err != nil {
+		t.Fatalf("capped acquire after release: %v", err)
+	}
+	c.Release()
+
+	metered := RoutingHints{Tenant: "metered"}
+	c, err = s.AcquireConnection("example.com:443", "req", metered)
+	if err != nil {
+		t.Fatalf("metered acquire: %v", err)
+	}
+	s.quotas.AddBytes(c.user, 25)
+	w := httptest.NewRecorder()
+	s.UsersHandler().ServeHTTP(w, httptest.NewRequest(http.MethodGet, "/admin/users", nil))
+	var report struct {
+		Users map[string]metrics.UserUsage `json:"users"`
+	}
+	if err := json.NewDecoder(w.Body).Decode(&report); err != nil {
+		t.Fatalf("decode: %v", err)
+	}
+	if got := report.Users["metered"]; got.MonthBytes != 25 || got.MonthlyBytes != 10 {
+		t.Errorf("users[metered] = %+v, want 25 of 10 bytes", got)
+	}
+	c.Release()
+	_, err = s.AcquireConnection("example.com:443", "req", metered)
+	if !errors.Is(err, limiter.ErrQuotaExceeded) {
+		t.Fatalf("metered acquire over quota: got %v, want ErrQuotaExceeded", err)
+	}
+
+	rec := httptest.NewRecorder()
+	s.rejectUser(rec, err)
+	if rec.Code != http.StatusProxyAuthRequired || rec.Header().Get("Proxy-Authenticate") == "" {
+		t.Errorf("rejectUser(quota) = %d %v, want 407 with a challenge", rec.Code, rec.Header())
+	}
+	rec = httptest.NewRecorder()
+	s.rejectUser(rec, limiter.ErrUserConnsExceeded)
+	if rec.Code != http.StatusTooManyRequests {
+		t.Errorf("rejectUser(user conns) = %d, want 429", rec.Code)
+	}
+}
@@ -0,0 +1,22 @@
+//go:build linux
+
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"os"
+	"syscall"
+)
+
+// fdUsage returns the number of open file descriptors and the soft limit.
+func fdUsage() (open, limit int, ok bool) {
+	var rl syscall.Rlimit
+	if err := syscall.Getrlimit(syscall.RLIMIT_NOFILE, &rl); err != nil {
+		return 0, 0, false
+	}
+	entries, err := os.ReadDir("/proc/self/fd")
+	if err != nil {
+		return 0, 0, false
+	}
+	return len(entries), int(rl.Cur), true
+}
@@ -0,0 +1,9 @@
+//go:build !linux
+
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+// fdUsage reports that file descriptor usage is not available on this platform.
+func fdUsage() (open, limit int, ok bool) {
+	return 0, 0, false
+}
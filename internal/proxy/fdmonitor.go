@@ -0,0 +1,100 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// fdMonitorInterval is how often file descriptor usage is checked.
+const fdMonitorInterval = time.Second
+
+// fdMonitor evicts the longest-idle tunnels when file descriptor usage nears the
+// process limit, so new connections keep working instead of failing with EMFILE.
+type fdMonitor struct {
+	tunnels   *tunnelRegistry
+	threshold float64
+	minIdle   time.Duration
+	usage     func() (open, limit int, ok bool)
+	stopCh    chan struct{}
+	stopOnce  sync.Once
+	wg        sync.WaitGroup
+}
+
+// newFDMonitor creates an fdMonitor that starts evicting once open descriptors
+// exceed threshold (a fraction of the limit).
+func newFDMonitor(tunnels *tunnelRegistry, threshold float64, minIdle time.Duration) *fdMonitor {
+	return &fdMonitor{
+		tunnels:   tunnels,
+		threshold: threshold,
+		minIdle:   minIdle,
+		usage:     fdUsage,
+		stopCh:    make(chan struct{}),
+	}
+}
+
+// Start starts the monitoring goroutine.
+func (m *fdMonitor) Start() {
+	if _, _, ok := m.usage(); !ok {
+		log.Warn("fd_monitor_unsupported", "reason", "file descriptor usage is not available on this platform")
+		return
+	}
+	log.Info("fd_monitor_started", "threshold", m.threshold, "min_idle", m.minIdle)
+
+	m.wg.Add(1)
+	go func() {
+		defer m.wg.Done()
+		ticker := time.NewTicker(fdMonitorInterval)
+		defer ticker.Stop()
+		for {
+			select {
+			case <-ticker.C:
+				m.check()
+			case <-m.stopCh:
+				return
+			}
+		}
+	}()
+}
+
+// Stop stops the monitoring goroutine.
+func (m *fdMonitor) Stop() {
+	m.stopOnce.Do(func() { close(m.stopCh) })
+	m.wg.Wait()
+}
+
+// check evicts idle tunnels if descriptor usage is over the threshold.
+// Returns the number of tunnels evicted.
+func (m *fdMonitor) check() int {
+	open, limit, ok := m.usage()
+	if !ok || limit <= 0 {
+		return 0
+	}
+	metrics.FDUsageRatio.Set(float64(open) / float64(limit))
+
+	excess := open - int(m.threshold*float64(limit))
+	if excess <= 0 {
+		return 0
+	}
+
+	// Each tunnel holds a client and a target descriptor
+	now := time.Now()
+	evicted := m.tunnels.evictIdle((excess+1)/2, m.minIdle)
+	for _, e := range evicted {
+		log.Warn("tunnel_evicted",
+			"reason", "fd_pressure",
+			"host", e.host,
+			"ip", e.ip,
+			"idle", e.idleFor(now),
+			"age", now.Sub(e.started),
+		)
+	}
+	metrics.TunnelEvictions.Add(float64(len(evicted)))
+
+	if len(evicted)*2 < excess {
+		log.Warn("fd_pressure", "open", open, "limit", limit, "evicted", len(evicted), "active_tunnels", m.tunnels.len())
+	}
+	return len(evicted)
+}
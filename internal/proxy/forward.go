@@ -0,0 +1,125 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"errors"
+	"net"
+	"strconv"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// forwardMethod labels port-forwarded connections in logs and metrics.
+const forwardMethod = "FORWARD"
+
+// ForwardHandler relays every accepted connection to one fixed destination,
+// egressing each connection through the balanced pool. No proxy protocol is
+// spoken, so any TCP client can use it.
+type ForwardHandler struct {
+	server *Server
+	target string
+}
+
+// NewForwardHandler creates a ForwardHandler for target ("host:port").
+func NewForwardHandler(server *Server, target string) *ForwardHandler {
+	return &ForwardHandler{server: server, target: target}
+}
+
+// Serve accepts connections on l until it is closed.
+func (h *ForwardHandler) Serve(l net.Listener) error {
+	for {
+		conn, err := l.Accept()
+		if err != nil {
+			if errors.Is(err, net.ErrClosed) {
+				return nil
+			}
+			var netErr net.Error
+			if errors.As(err, &netErr) && netErr.Timeout() {
+				time.Sleep(10 * time.Millisecond)
+				continue
+			}
+			return err
+		}
+		go h.ServeConn(conn)
+	}
+}
+
+// ServeConn handles a single forwarded connection.
+func (h *ForwardHandler) ServeConn(conn net.Conn) {
+	defer conn.Close()
+
+	start := time.Now()
+	requestID := GenerateRequestID()
+	remote := conn.RemoteAddr().String()
+
+	logSpan := log.Span("request_id", requestID, "host", h.target, "remote", remote)
+	logSpan.Trace("forward_connection_accepted")
+
+	// Trace the connection; forwarded traffic carries no trace context, so it starts a trace
+	span := h.server.startSpan("proxy.forward", nil, requestID)
+	defer span.End()
+	span.SetAttr("client.address", netutil.ParseHost(remote))
+	span.SetAttr("server.address", h.target)
+	ctx := tracing.ContextWithSpan(context.Background(), span)
+
+	// Select outbound IP and acquire a connection slot
+	hints := RoutingHints{ClientIP: netutil.ParseHost(remote)}
+	selectSpan := startPhase(ctx, "select", tracing.KindInternal)
+	connCtx, err := h.server.AcquireConnection(h.target, requestID, hints)
+	endPhase(selectSpan, err)
+	if err != nil {
+		logSpan.Trace("forward_acquire_failed", "error", err)
+		if errors.Is(err, ErrDestinationNotAllowed) {
+			metrics.RequestsTotal.WithLabelValues(forwardMethod, "403").Inc()
+			return
+		}
+		metrics.LimitRejections.WithLabelValues(limitRejectionType(err)).Inc()
+		metrics.RequestsTotal.WithLabelValues(forwardMethod, "503").Inc()
+		return
+	}
+	defer connCtx.Release()
+	ip := connCtx.IP
+
+	metrics.TunnelConnections.Inc()
+
+	dialer := NewDialer(ip, h.server.cfg.EffectiveConnectTimeout(), h.server.cfg.IdleTimeout, h.server.outboundDialOptions()...)
+	dialStart := time.Now()
+	targetConn, err := dialer.DialContext(ctx, "tcp", h.target)
+	h.server.slo.Observe(h.target, ip, time.Since(dialStart), dialFailed(err))
+	h.server.recordOutcome(ip, dialFailed(err))
+	if err != nil {
+		logger.LogError("forward_dial", err, "host", h.target, "ip", ip)
+		metrics.RequestsTotal.WithLabelValues(forwardMethod, strconv.Itoa(dialErrorStatus(err))).Inc()
+		return
+	}
+	defer targetConn.Close()
+	targetConn = h.server.shapeTunnel(targetConn, ip, h.target, hints)
+
+	// Register the tunnel so it can be evicted under fd pressure
+	entry := h.server.tunnels.add(h.target, ip, func() {
+		conn.Close()
+		targetConn.Close()
+	})
+	defer h.server.endTunnel(entry)
+
+	relay := startPhase(ctx, "relay", tracing.KindInternal)
+	bytesIn, bytesOut := h.server.connectHandler.tunnel(conn, entry.wrap(targetConn), h.server.cfg.EffectiveTunnelIdleTimeout(), requestID)
+	traceRelay(relay, bytesIn, bytesOut)
+
+	// Log and record metrics
+	duration := time.Since(start).Milliseconds()
+	h.server.logRequest(forwardMethod, h.target, remote, connCtx.tenant, ip, 200, duration, bytesIn, bytesOut, connCtx.provenance)
+
+	h.server.stats.IncTotalRequests()
+	h.server.stats.AddBytesReceived(bytesIn)
+	h.server.stats.AddBytesSent(bytesOut)
+	h.server.quotas.AddBytes(connCtx.user, bytesIn+bytesOut)
+
+	metrics.RequestsTotal.WithLabelValues(forwardMethod, "200").Inc()
+	metrics.RequestDuration.WithLabelValues(forwardMethod).Observe(time.Since(start).Seconds())
+}
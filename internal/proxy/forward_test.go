@@ -0,0 +1,90 @@
+package proxy
+
+import (
+	"bufio"
+	"net"
+	"net/http"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+)
+
+func TestForward_RelaysToFixedTarget(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	target := strings.TrimPrefix(backend.URL, "http://")
+	handler := NewForwardHandler(server, target)
+
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	defer l.Close()
+	go handler.Serve(l)
+
+	// Every connection reaches the same target without any proxy handshake
+	for i := 0; i < 3; i++ {
+		conn, err := net.Dial("tcp", l.Addr().String())
+		if err != nil {
+			t.Fatalf("failed to dial forward listener: %v", err)
+		}
+		conn.Write([]byte("GET / HTTP/1.1\r\nHost: " + target + "\r\nConnection: close\r\n\r\n"))
+		resp, err := http.ReadResponse(bufio.NewReader(conn), nil)
+		if err != nil {
+			conn.Close()
+			t.Fatalf("failed to read response through forward relay: %v", err)
+		}
+		resp.Body.Close()
+		conn.Close()
+
+		if resp.StatusCode != http.StatusOK {
+			t.Errorf("expected status 200, got %d", resp.StatusCode)
+		}
+	}
+}
+
+func TestForward_UnreachableTargetClosesConnection(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+
+	// Reserve a port and release it so nothing is listening there
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	target := l.Addr().String()
+	l.Close()
+
+	client, conn := net.Pipe()
+	defer client.Close()
+	go NewForwardHandler(server, target).ServeConn(conn)
+
+	if _, err := client.Read(make([]byte, 1)); err == nil {
+		t.Error("expected connection to be closed when the target is unreachable")
+	}
+}
+
+func TestForward_DialFailureOpensCircuit(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	cb := balancer.NewCircuitBreaker(balancer.CircuitBreakerConfig{FailureThreshold: 1, SuccessThreshold: 1, Timeout: time.Minute})
+	server.SetCircuitBreaker(cb)
+
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	target := l.Addr().String()
+	l.Close()
+
+	client, conn := net.Pipe()
+	defer client.Close()
+	go NewForwardHandler(server, target).ServeConn(conn)
+	client.Read(make([]byte, 1))
+
+	if state := cb.GetState("127.0.0.1"); state != balancer.StateOpen {
+		t.Errorf("expected the failed dial to open the circuit, got %s", state)
+	}
+}
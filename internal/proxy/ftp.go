@@ -0,0 +1,190 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"fmt"
+	"net"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+// FTP passive-mode tracking parameters.
+const (
+	// ftpControlPort is the port of tunnels whose server replies are watched.
+	ftpControlPort = "21"
+	// ftpDataTTL is how long an announced data address stays bound to its exit IP.
+	ftpDataTTL = 30 * time.Second
+	// maxFTPReplyLine bounds the reply line buffered while scanning.
+	maxFTPReplyLine = 512
+)
+
+// ftpDataKey identifies an expected passive-mode data connection.
+type ftpDataKey struct {
+	client string
+	target string
+}
+
+// ftpDataEntry is the exit IP a data connection must leave through.
+type ftpDataEntry struct {
+	exit    string
+	expires time.Time
+}
+
+// ftpDataStore remembers which exit IP announced each passive-mode data address.
+type ftpDataStore struct {
+	entries map[ftpDataKey]ftpDataEntry
+	clock   clock.Clock
+	mu      sync.Mutex
+}
+
+// newFTPDataStore creates an empty ftpDataStore that expires entries by c.
+func newFTPDataStore(c clock.Clock) *ftpDataStore {
+	return &ftpDataStore{entries: make(map[ftpDataKey]ftpDataEntry), clock: clock.OrReal(c)}
+}
+
+// expect binds data connections from client to target to exit, dropping expired entries.
+func (s *ftpDataStore) expect(client, target, exit string) {
+	now := s.clock.Now()
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	for key, e := range s.entries {
+		if now.After(e.expires) {
+			delete(s.entries, key)
+		}
+	}
+	s.entries[ftpDataKey{client, target}] = ftpDataEntry{exit: exit, expires: now.Add(ftpDataTTL)}
+}
+
+// exit returns the exit IP for a data connection from client to target, if one was announced.
+func (s *ftpDataStore) exit(client, target string) (string, bool) {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	e, ok := s.entries[ftpDataKey{client, target}]
+	if !ok || s.clock.Now().After(e.expires) {
+		return "", false
+	}
+	return e.exit, true
+}
+
+// ftpControlConn scans the replies of an FTP server for passive-mode data addresses.
+type ftpControlConn struct {
+	net.Conn
+	host   string
+	line   []byte
+	onData func(target string)
+}
+
+// Read reads from the server and scans complete reply lines.
+func (c *ftpControlConn) Read(p []byte) (int, error) {
+	n, err := c.Conn.Read(p)
+	for _, b := range p[:n] {
+		if b != '\n' {
+			if len(c.line) < maxFTPReplyLine {
+				c.line = append(c.line, b)
+			}
+			continue
+		}
+		for _, target := range parsePassiveReply(string(c.line), c.host) {
+			c.onData(target)
+		}
+		c.line = c.line[:0]
+	}
+	return n, err
+}
+
+// NetConn returns the underlying connection.
+func (c *ftpControlConn) NetConn() net.Conn {
+	return c.Conn
+}
+
+// CloseWrite half-closes the underlying connection if supported.
+func (c *ftpControlConn) CloseWrite() error {
+	if cw, ok := c.Conn.(closeWriter); ok {
+		return cw.CloseWrite()
+	}
+	return nil
+}
+
+// parsePassiveReply returns the data addresses announced by a 227 (PASV) or 229
+// (EPSV) reply. PASV replies yield both the announced address and the control
+// host with the announced port, since clients commonly ignore the announced IP.
+func parsePassiveReply(line, controlHost string) []string {
+	line = strings.TrimSuffix(line, "\r")
+	switch {
+	case strings.HasPrefix(line, "227 "):
+		start := strings.IndexAny(line[4:], "0123456789")
+		if start < 0 {
+			return nil
+		}
+		rest := line[4+start:]
+		if end := strings.IndexFunc(rest, func(r rune) bool { return r != ',' && (r < '0' || r > '9') }); end >= 0 {
+			rest = rest[:end]
+		}
+		parts := strings.Split(rest, ",")
+		if len(parts) != 6 {
+			return nil
+		}
+		var n [6]int
+		for i, part := range parts {
+			v, err := strconv.Atoi(part)
+			if err != nil || v > 255 {
+				return nil
+			}
+			n[i] = v
+		}
+		port := strconv.Itoa(n[4]<<8 | n[5])
+		announced := net.JoinHostPort(fmt.Sprintf("%d.%d.%d.%d", n[0], n[1], n[2], n[3]), port)
+		if control := net.JoinHostPort(controlHost, port); control != announced {
+			return []string{announced, control}
+		}
+		return []string{announced}
+	case strings.HasPrefix(line, "229 "):
+		open, closing := strings.Index(line, "("), strings.LastIndex(line, ")")
+		if open < 0 || closing <= open+1 {
+			return nil
+		}
+		inner := line[open+1 : closing]
+		fields := strings.Split(inner, inner[:1])
+		if len(fields) != 5 {
+			return nil
+		}
+		if port, err := strconv.Atoi(fields[3]); err != nil || port <= 0 || port > 65535 {
+			return nil
+		}
+		return []string{net.JoinHostPort(controlHost, fields[3])}
+	}
+	return nil
+}
+
+// watchFTP wraps the target connection of a tunnel to an FTP control port so
+// passive-mode data connections from the same client leave through exit.
+// Returns conn unchanged when FTP tracking is disabled or target is not FTP.
+func (s *Server) watchFTP(conn net.Conn, client, target, exit string) net.Conn {
+	if !s.cfg.FTPPassive {
+		return conn
+	}
+	host, port, err := net.SplitHostPort(target)
+	if err != nil || port != ftpControlPort {
+		return conn
+	}
+	return &ftpControlConn{Conn: conn, host: host, onData: func(data string) {
+		s.ftpData.expect(client, data, exit)
+		log.Debug("ftp_passive_data", "client", client, "control", target, "data", data, "ip", exit)
+	}}
+}
+
+// ftpDataExit returns the exit IP for a passive-mode data connection from client to target.
+func (s *Server) ftpDataExit(client, target string) (string, bool) {
+	if !s.cfg.FTPPassive || client == "" {
+		return "", false
+	}
+	host, port, err := net.SplitHostPort(target)
+	if err != nil {
+		return "", false
+	}
+	return s.ftpData.exit(client, net.JoinHostPort(host, port))
+}
@@ -0,0 +1,98 @@
+package proxy
+
+import (
+	"io"
+	"net"
+	"reflect"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func TestParsePassiveReply(t *testing.T) {
+	tests := []struct {
+		name string
+		line string
+		want []string
+	}{
+		{"pasv", "227 Entering Passive Mode (192,0,2,7,195,80).\r", []string{"192.0.2.7:50000", "ftp.example.com:50000"}},
+		{"pasv without parens", "227 =192,0,2,7,195,80", []string{"192.0.2.7:50000", "ftp.example.com:50000"}},
+		{"epsv", "229 Entering Extended Passive Mode (|||50000|)\r", []string{"ftp.example.com:50000"}},
+		{"epsv custom delimiter", "229 Entering Extended Passive Mode (!!!50000!)", []string{"ftp.example.com:50000"}},
+		{"pasv out of range", "227 Entering Passive Mode (192,0,2,700,195,80)", nil},
+		{"epsv bad port", "229 Entering Extended Passive Mode (|||0|)", nil},
+		{"other reply", "230 Login successful.", nil},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			if got := parsePassiveReply(tt.line, "ftp.example.com"); !reflect.DeepEqual(got, tt.want) {
+				t.Errorf("parsePassiveReply() = %v, want %v", got, tt.want)
+			}
+		})
+	}
+}
+
+func TestFTPDataStore_Expiry(t *testing.T) {
+	now := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	s := newFTPDataStore(now)
+	s.expect("10.0.0.9", "192.0.2.7:50000", "127.0.0.2")
+
+	if exit, ok := s.exit("10.0.0.9", "192.0.2.7:50000"); !ok || exit != "127.0.0.2" {
+		t.Errorf("exit = %q, %v; want 127.0.0.2", exit, ok)
+	}
+	if _, ok := s.exit("10.0.0.10", "192.0.2.7:50000"); ok {
+		t.Error("expected other clients not to match")
+	}
+	now.Advance(ftpDataTTL + time.Second)
+	if _, ok := s.exit("10.0.0.9", "192.0.2.7:50000"); ok {
+		t.Error("expected expired entry to be ignored")
+	}
+}
+
+func TestServer_FTPPassiveDataExit(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	server.cfg.FTPPassive = true
+
+	serverSide, proxySide := net.Pipe()
+	defer serverSide.Close()
+	control := server.watchFTP(proxySide, "10.0.0.9", "ftp.example.com:21", "127.0.0.2")
+	defer control.Close()
+
+	reply := "220 ready\r\n227 Entering Passive Mode (192,0,2,7,195,80).\r\n"
+	go serverSide.Write([]byte(reply))
+	if _, err := io.ReadFull(control, make([]byte, len(reply))); err != nil {
+		t.Fatalf("failed to read reply: %v", err)
+	}
+
+	for _, target := range []string{"192.0.2.7:50000", "ftp.example.com:50000"} {
+		opts, prov, err := server.selectOptions(target, RoutingHints{ClientIP: "10.0.0.9"})
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		if !reflect.DeepEqual(opts.Candidates, []string{"127.0.0.2"}) || prov.source != selectionAffinity {
+			t.Errorf("%s: candidates = %v (%s), want the control connection's exit", target, opts.Candidates, prov.source)
+		}
+	}
+
+	opts, _, _ := server.selectOptions("192.0.2.7:50000", RoutingHints{ClientIP: "10.0.0.10"})
+	if opts.Candidates != nil {
+		t.Errorf("expected no constraint for another client, got %v", opts.Candidates)
+	}
+}
+
+func TestServer_WatchFTPDisabled(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	a, b := net.Pipe()
+	defer a.Close()
+	defer b.Close()
+
+	if server.watchFTP(a, "10.0.0.9", "ftp.example.com:21", "127.0.0.1") != a {
+		t.Error("expected connection unchanged when ftp_passive is off")
+	}
+	server.cfg.FTPPassive = true
+	if server.watchFTP(a, "10.0.0.9", "example.com:443", "127.0.0.1") != a {
+		t.Error("expected connection unchanged for non-FTP targets")
+	}
+}
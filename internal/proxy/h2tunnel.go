@@ -0,0 +1,98 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"io"
+	"net"
+	"net/http"
+	"sync/atomic"
+	"time"
+)
+
+// streamAddr is a net.Addr for the endpoints of an HTTP/2 stream.
+type streamAddr string
+
+// Network returns the address network.
+func (a streamAddr) Network() string { return "tcp" }
+
+// String returns the address in host:port form.
+func (a streamAddr) String() string { return string(a) }
+
+// streamConn adapts an HTTP/2 CONNECT stream to net.Conn so it can share the tunnel relay.
+// Reads come from the request body and writes go to the response, flushed immediately.
+type streamConn struct {
+	body   io.ReadCloser
+	w      http.ResponseWriter
+	rc     *http.ResponseController
+	local  net.Addr
+	remote net.Addr
+	closed atomic.Bool
+}
+
+// newStreamConn creates a streamConn for an HTTP/2 CONNECT request.
+func newStreamConn(w http.ResponseWriter, r *http.Request) *streamConn {
+	local := net.Addr(streamAddr(""))
+	if addr, ok := r.Context().Value(http.LocalAddrContextKey).(net.Addr); ok {
+		local = addr
+	}
+	return &streamConn{
+		body:   r.Body,
+		w:      w,
+		rc:     http.NewResponseController(w),
+		local:  local,
+		remote: streamAddr(r.RemoteAddr),
+	}
+}
+
+// Read reads client data from the stream.
+func (c *streamConn) Read(p []byte) (int, error) {
+	n, err := c.body.Read(p)
+	if err != nil && c.closed.Load() {
+		return n, io.EOF
+	}
+	return n, err
+}
+
+// Write sends data to the client and flushes it.
+func (c *streamConn) Write(p []byte) (int, error) {
+	n, err := c.w.Write(p)
+	if err != nil {
+		return n, err
+	}
+	return n, c.rc.Flush()
+}
+
+// Close stops reading from the client. The stream itself ends when the handler returns.
+func (c *streamConn) Close() error {
+	c.closed.Store(true)
+	return c.body.Close()
+}
+
+// CloseWrite is called by the tunnel once the target has finished sending.
+// An HTTP/2 stream can only be ended by returning from the handler, so this stops
+// reading from the client to let the tunnel, and then the handler, finish.
+func (c *streamConn) CloseWrite() error {
+	return c.Close()
+}
+
+// LocalAddr returns the listener address the stream arrived on.
+func (c *streamConn) LocalAddr() net.Addr { return c.local }
+
+// RemoteAddr returns the client address.
+func (c *streamConn) RemoteAddr() net.Addr { return c.remote }
+
+// SetDeadline sets the read and write deadlines of the stream.
+func (c *streamConn) SetDeadline(t time.Time) error {
+	c.rc.SetReadDeadline(t)
+	return c.rc.SetWriteDeadline(t)
+}
+
+// SetReadDeadline sets the read deadline of the stream.
+func (c *streamConn) SetReadDeadline(t time.Time) error {
+	return c.rc.SetReadDeadline(t)
+}
+
+// SetWriteDeadline sets the write deadline of the stream.
+func (c *streamConn) SetWriteDeadline(t time.Time) error {
+	return c.rc.SetWriteDeadline(t)
+}
@@ -3,14 +3,18 @@ package proxy
 
 import (
 	"context"
+	"errors"
 	"fmt"
 	"io"
+	"net"
 	"net/http"
 	"strings"
 	"time"
 
 	"github.com/cr0hn/outbound-lb/internal/logger"
 	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
 )
 
 // hopByHopHeaders contains headers that should not be forwarded to the upstream server.
@@ -62,14 +66,34 @@ func (h *Handler) ServeHTTP(w http.ResponseWriter, r *http.Request) {
 	defer cancel()
 	ctx = ContextWithRequestID(ctx, requestID)
 
+	// Trace the request, continuing the client's trace
+	span := h.server.startSpan("proxy.request", r, requestID)
+	defer span.End()
+	span.SetAttr("http.request.method", r.Method)
+	span.SetAttr("server.address", r.Host)
+	span.SetAttr("client.address", netutil.ParseHost(r.RemoteAddr))
+	ctx = tracing.ContextWithSpan(ctx, span)
+
 	// Update request with new context
 	r = r.WithContext(ctx)
 
-	logger.Trace("request_received", "request_id", requestID, "method", r.Method, "host", r.Host, "remote", r.RemoteAddr, "url", r.URL.String())
+	logSpan := log.Span("request_id", requestID, "remote", r.RemoteAddr)
+	logSpan.Trace("request_received", "method", r.Method, "host", r.Host, "url", r.URL.String())
 
 	// Check authentication
-	if !h.server.authenticate(w, r) {
-		logger.Trace("request_auth_failed", "remote", r.RemoteAddr)
+	authSpan := startPhase(ctx, "auth", tracing.KindInternal)
+	r, ok := h.server.authenticate(w, r)
+	if !ok {
+		endPhase(authSpan, errAuthFailed)
+		logSpan.Trace("request_auth_failed")
+		return
+	}
+	authSpan.End()
+
+	// Enforce the user's request rate
+	if err := h.server.admitRate(r); err != nil {
+		h.sendError(w, http.StatusTooManyRequests, "Rate limit exceeded")
+		metrics.LimitRejections.WithLabelValues("rate").Inc()
 		return
 	}
 
@@ -79,35 +103,81 @@ func (h *Handler) ServeHTTP(w http.ResponseWriter, r *http.Request) {
 		return
 	}
 
+	// Refuse methods outside the method policy
+	if !h.server.methodAllowed(r.Method) {
+		logSpan.Warn("method_rejected", "method", r.Method, "host", r.Host)
+		w.Header().Set("Allow", strings.Join(h.server.cfg.AllowedMethods, ", "))
+		h.sendError(w, http.StatusMethodNotAllowed, "Method not allowed")
+		metrics.RequestsTotal.WithLabelValues(r.Method, "405").Inc()
+		return
+	}
+
+	// Refuse bodies over the upload limit before choosing an outbound IP;
+	// chunked bodies are cut off once they cross it
+	if limit := int64(h.server.cfg.MaxUploadBytes); limit > 0 {
+		if r.ContentLength > limit {
+			h.rejectUpload(w, r)
+			return
+		}
+		if r.ContentLength < 0 {
+			r.Body = http.MaxBytesReader(w, r.Body, limit)
+		}
+	}
+
 	// Get the host
 	host := r.Host
 	if host == "" {
 		host = r.URL.Host
 	}
 
-	logger.Trace("ip_selection_start", "host", host)
+	logSpan.Logger = logSpan.With("host", host)
+	logSpan.Trace("ip_selection_start")
 
 	// Select outbound IP
-	ip, err := h.server.selectIP(host)
+	selectSpan := startPhase(ctx, "select", tracing.KindInternal)
+	ip, prov, err := h.server.selectIPForRequest(r, host)
+	selectSpan.SetAttr("outbound_ip", ip)
+	endPhase(selectSpan, err)
 	if err != nil {
-		logger.Trace("ip_selection_failed", "host", host, "error", err)
-		h.sendError(w, http.StatusServiceUnavailable, "No available outbound IPs")
-		metrics.LimitRejections.WithLabelValues("total").Inc()
+		logSpan.Trace("ip_selection_failed", "error", err)
+		if errors.Is(err, ErrMaintenance) {
+			h.server.sendMaintenance(w)
+			return
+		}
+		if errors.Is(err, ErrDestinationNotAllowed) {
+			h.server.sendBlocked(w, r, host, err, selectionErrorMessage(err))
+			return
+		}
+		status := selectionErrorStatus(err)
+		h.sendError(w, status, selectionErrorMessage(err))
+		if status == http.StatusServiceUnavailable {
+			metrics.LimitRejections.WithLabelValues("total").Inc()
+		}
 		return
 	}
 
-	logger.Trace("ip_selected", "host", host, "ip", ip)
+	logSpan.Trace("ip_selected", "ip", ip)
+	h.server.noteEgress(r, ip, requestID)
+
+	// Admit the user under its limits and fair share of the pool
+	user, limit := h.server.requestUser(r)
+	releaseUser, err := h.server.admitUser(user, limit)
+	if err != nil {
+		h.server.rejectUser(w, err)
+		return
+	}
+	defer releaseUser()
 
 	// Acquire connection slot
-	logger.Trace("connection_acquire_attempt", "ip", ip)
+	logSpan.Trace("connection_acquire_attempt", "ip", ip)
 	if err := h.server.limiter.Acquire(ip); err != nil {
-		logger.Trace("connection_acquire_failed", "ip", ip, "error", err)
+		logSpan.Trace("connection_acquire_failed", "ip", ip, "error", err)
 		h.sendError(w, http.StatusServiceUnavailable, "Connection limit reached")
 		metrics.LimitRejections.WithLabelValues("per_ip").Inc()
 		logger.LogConnectionLimit("per_ip", ip, int(h.server.limiter.GetIPCount(ip)), h.server.cfg.MaxConnsPerIP)
 		return
 	}
-	logger.Trace("connection_acquired", "ip", ip)
+	logSpan.Trace("connection_acquired", "ip", ip)
 	defer h.server.limiter.Release(ip)
 
 	// Update metrics
@@ -123,25 +193,65 @@ func (h *Handler) ServeHTTP(w http.ResponseWriter, r *http.Request) {
 	h.server.stats.IncSelectionsForIP(ip, host)
 	logger.LogBalancerSelection(host, ip, len(h.server.cfg.IPs))
 
-	// Get transport for this IP
-	transport := h.server.transportPool.Get(ip)
+	// Get transport for this IP, forcing HTTP/1.1 toward origins configured for it
+	transport := h.server.upstreamTransport(ip, host)
 
-	// Create outgoing request
+	// Create outgoing request, sent to the pinned origin if the tenant pinned the host
 	outReq := h.createOutgoingRequest(r)
+	if origin, ok := h.server.pinnedOrigin(tenantFromRequest(r), outReq.URL.Hostname()); ok {
+		port := outReq.URL.Port()
+		if port == "" {
+			port = "80"
+		}
+		outReq.URL.Host = net.JoinHostPort(origin, port)
+	}
 
-	// Execute request
-	logger.Trace("upstream_request_start", "host", host, "ip", ip, "method", r.Method)
+	// WebSocket handshakes switch to a raw relay after the 101 response
+	if isWebSocketUpgrade(r) {
+		status, bytesIn, bytesOut := h.serveWebSocket(w, outReq, host, ip)
+		h.server.logRequest(r.Method, host, r.RemoteAddr, tenantFromRequest(r), ip, status, time.Since(start).Milliseconds(), bytesIn, bytesOut, prov)
+		h.server.stats.IncTotalRequests()
+		h.server.stats.AddBytesReceived(bytesIn)
+		h.server.stats.AddBytesSent(bytesOut)
+		h.server.quotas.AddBytes(user, bytesIn+bytesOut)
+		metrics.RequestsTotal.WithLabelValues(r.Method, fmt.Sprintf("%d", status)).Inc()
+		return
+	}
+
+	// Execute request, tracing it as the next hop's parent
+	logSpan.Trace("upstream_request_start", "ip", ip, "method", r.Method)
+	upstream := startPhase(ctx, "upstream", tracing.KindClient)
+	defer upstream.End()
+	propagateTrace(outReq, upstream)
+	outReq = outReq.WithContext(tracing.ContextWithSpan(outReq.Context(), upstream))
+	upstreamStart := time.Now()
 	resp, err := transport.RoundTrip(outReq)
+	upstream.SetError(err)
+	var tooLarge *http.MaxBytesError
+	if errors.As(err, &tooLarge) {
+		// The client's body, not the outbound IP, failed
+		h.rejectUpload(w, r)
+		return
+	}
+	h.server.slo.Observe(host, ip, time.Since(upstreamStart), dialFailed(err) || (err == nil && resp.StatusCode >= 500))
+	h.server.recordOutcome(ip, dialFailed(err))
 	if err != nil {
-		logger.Trace("upstream_request_failed", "host", host, "ip", ip, "error", err)
+		logSpan.Trace("upstream_request_failed", "ip", ip, "error", err)
 		logger.LogError("proxy_request", err, "host", host, "ip", ip)
-		h.sendError(w, http.StatusBadGateway, "Failed to connect to upstream")
-		metrics.RequestsTotal.WithLabelValues(r.Method, "502").Inc()
+		status := dialErrorStatus(err)
+		if status == http.StatusForbidden {
+			status = h.server.sendBlocked(w, r, host, err, dialErrorMessage(err, ""))
+		} else {
+			h.sendError(w, status, dialErrorMessage(err, "Failed to connect to upstream"))
+		}
+		metrics.RequestsTotal.WithLabelValues(r.Method, fmt.Sprintf("%d", status)).Inc()
 		return
 	}
 	defer resp.Body.Close()
+	h.server.quarantine.Record(ip, resp.StatusCode)
+	span.SetAttr("http.response.status_code", resp.StatusCode)
 
-	logger.Trace("upstream_response_received", "host", host, "ip", ip, "status", resp.StatusCode)
+	logSpan.Trace("upstream_response_received", "ip", ip, "status", resp.StatusCode)
 
 	// Copy response headers
 	h.copyHeaders(w.Header(), resp.Header)
@@ -154,17 +264,19 @@ func (h *Handler) ServeHTTP(w http.ResponseWriter, r *http.Request) {
 		logger.LogError("response_copy", err, "host", host, "ip", ip)
 	}
 
-	logger.Trace("response_copy_complete", "host", host, "ip", ip, "bytes", bytesCopied)
+	logSpan.Trace("response_copy_complete", "ip", ip, "bytes", bytesCopied)
 
 	// Log and record metrics
 	duration := time.Since(start).Milliseconds()
-	logger.LogRequest(r.Method, host, r.RemoteAddr, ip, resp.StatusCode, duration, r.ContentLength, bytesCopied)
+	h.server.logRequest(r.Method, host, r.RemoteAddr, tenantFromRequest(r), ip, resp.StatusCode, duration, r.ContentLength, bytesCopied, prov)
 
 	h.server.stats.IncTotalRequests()
 	h.server.stats.AddBytesSent(bytesCopied)
 	if r.ContentLength > 0 {
 		h.server.stats.AddBytesReceived(r.ContentLength)
 	}
+	h.server.stats.AddBytesForIP(ip, max(r.ContentLength, 0), bytesCopied)
+	h.server.quotas.AddBytes(user, bytesCopied+max(r.ContentLength, 0))
 
 	metrics.RequestsTotal.WithLabelValues(r.Method, fmt.Sprintf("%d", resp.StatusCode)).Inc()
 	metrics.RequestDuration.WithLabelValues(r.Method).Observe(time.Since(start).Seconds())
@@ -180,8 +292,17 @@ func (h *Handler) createOutgoingRequest(r *http.Request) *http.Request {
 		outReq.URL.Host = r.Host
 	}
 
-	// Remove hop-by-hop headers
+	// Remove hop-by-hop headers, keeping the pair that requests a WebSocket upgrade
 	h.removeHopByHopHeaders(outReq.Header)
+	if isWebSocketUpgrade(r) {
+		outReq.Header.Set("Connection", "Upgrade")
+		outReq.Header.Set("Upgrade", "websocket")
+	}
+
+	// Remove routing hint headers meant for the proxy
+	for _, hdr := range h.server.routingHeaders() {
+		outReq.Header.Del(hdr)
+	}
 
 	// Set X-Forwarded-For
 	if clientIP := h.getClientIP(r); clientIP != "" {
@@ -244,6 +365,14 @@ func (h *Handler) getClientIP(r *http.Request) string {
 	return r.RemoteAddr
 }
 
+// rejectUpload refuses a request whose body exceeds max_upload_bytes.
+func (h *Handler) rejectUpload(w http.ResponseWriter, r *http.Request) {
+	log.Warn("upload_too_large", "host", r.Host, "remote", r.RemoteAddr, "content_length", r.ContentLength, "limit", h.server.cfg.MaxUploadBytes)
+	h.sendError(w, http.StatusRequestEntityTooLarge, "Request body too large")
+	metrics.LimitRejections.WithLabelValues("upload").Inc()
+	metrics.RequestsTotal.WithLabelValues(r.Method, "413").Inc()
+}
+
 // sendError sends an error response.
 func (h *Handler) sendError(w http.ResponseWriter, status int, message string) {
 	http.Error(w, message, status)
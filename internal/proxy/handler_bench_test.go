@@ -174,7 +174,7 @@ func BenchmarkHandler_authenticate_NoAuth(b *testing.B) {
 	b.ResetTimer()
 	for i := 0; i < b.N; i++ {
 		rr := httptest.NewRecorder()
-		_ = server.authenticate(rr, req)
+		_, _ = server.authenticate(rr, req)
 	}
 }
 
@@ -0,0 +1,64 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"errors"
+	"fmt"
+	"net/http"
+	"strings"
+)
+
+// ErrConnectHeaderPolicy is returned when a CONNECT request violates the header policy.
+var ErrConnectHeaderPolicy = errors.New("connect header policy violation")
+
+// matchHeaderName reports whether the canonical header name matches pattern.
+// Matching is case-insensitive and a trailing "*" matches any suffix.
+func matchHeaderName(pattern, name string) bool {
+	if prefix, ok := strings.CutSuffix(pattern, "*"); ok {
+		return len(name) >= len(prefix) && strings.EqualFold(name[:len(prefix)], prefix)
+	}
+	return strings.EqualFold(pattern, name)
+}
+
+// checkConnectHeaders applies the CONNECT header policy to r. It returns the
+// headers to record in the access log, or an error naming the violated rule.
+// Proxy-Authorization is never recorded.
+func (s *Server) checkConnectHeaders(r *http.Request) (map[string]string, error) {
+	for name := range r.Header {
+		for _, p := range s.cfg.ConnectRejectHeaders {
+			if matchHeaderName(p, name) {
+				return nil, fmt.Errorf("%w: header %s is not allowed", ErrConnectHeaderPolicy, name)
+			}
+		}
+	}
+
+	for _, p := range s.cfg.ConnectRequireHeaders {
+		found := false
+		for name := range r.Header {
+			if matchHeaderName(p, name) {
+				found = true
+				break
+			}
+		}
+		if !found {
+			return nil, fmt.Errorf("%w: header %s is required", ErrConnectHeaderPolicy, p)
+		}
+	}
+
+	var logged map[string]string
+	for name, values := range r.Header {
+		if name == "Proxy-Authorization" {
+			continue
+		}
+		for _, p := range s.cfg.ConnectLogHeaders {
+			if matchHeaderName(p, name) {
+				if logged == nil {
+					logged = make(map[string]string)
+				}
+				logged[name] = strings.Join(values, ", ")
+				break
+			}
+		}
+	}
+	return logged, nil
+}
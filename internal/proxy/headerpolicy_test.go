@@ -0,0 +1,92 @@
+package proxy
+
+import (
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"reflect"
+	"testing"
+)
+
+func TestCheckConnectHeaders(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.ConnectLogHeaders = []string{"User-Agent", "X-*"}
+	server.cfg.ConnectRequireHeaders = []string{"X-Client-Id"}
+	server.cfg.ConnectRejectHeaders = []string{"X-Debug-*"}
+
+	tests := []struct {
+		name    string
+		headers map[string]string
+		want    map[string]string
+		wantErr bool
+	}{
+		{
+			name:    "logged",
+			headers: map[string]string{"User-Agent": "curl/8.0", "X-Client-Id": "42", "Accept": "*/*", "Proxy-Authorization": "Basic eDp5"},
+			want:    map[string]string{"User-Agent": "curl/8.0", "X-Client-Id": "42"},
+		},
+		{
+			name:    "missing required",
+			headers: map[string]string{"User-Agent": "curl/8.0"},
+			wantErr: true,
+		},
+		{
+			name:    "rejected prefix",
+			headers: map[string]string{"X-Client-Id": "42", "X-Debug-Trace": "1"},
+			wantErr: true,
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			r := httptest.NewRequest(http.MethodConnect, "http://example.com:443", nil)
+			for k, v := range tt.headers {
+				r.Header.Set(k, v)
+			}
+			got, err := server.checkConnectHeaders(r)
+			if (err != nil) != tt.wantErr {
+				t.Fatalf("checkConnectHeaders() error = %v, wantErr %v", err, tt.wantErr)
+			}
+			if err != nil && !errors.Is(err, ErrConnectHeaderPolicy) {
+				t.Errorf("expected ErrConnectHeaderPolicy, got %v", err)
+			}
+			if !tt.wantErr && !reflect.DeepEqual(got, tt.want) {
+				t.Errorf("logged headers = %v, want %v", got, tt.want)
+			}
+		})
+	}
+}
+
+func TestConnectHandler_HeaderPolicyRejects(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.ConnectRejectHeaders = []string{"X-Leak"}
+
+	r := httptest.NewRequest(http.MethodConnect, "http://example.com:443", nil)
+	r.Host = "example.com:443"
+	r.Header.Set("X-Leak", "machine-42")
+	w := httptest.NewRecorder()
+	server.connectHandler.ServeHTTP(w, r)
+
+	if w.Code != http.StatusForbidden {
+		t.Errorf("status = %d, want 403", w.Code)
+	}
+}
+
+func TestMatchHeaderName(t *testing.T) {
+	tests := []struct {
+		pattern, name string
+		want          bool
+	}{
+		{"User-Agent", "User-Agent", true},
+		{"user-agent", "User-Agent", true},
+		{"X-*", "X-Client-Id", true},
+		{"X-*", "Accept", false},
+		{"*", "Accept", true},
+		{"X-Client", "X-Client-Id", false},
+	}
+	for _, tt := range tests {
+		if got := matchHeaderName(tt.pattern, tt.name); got != tt.want {
+			t.Errorf("matchHeaderName(%q, %q) = %v, want %v", tt.pattern, tt.name, got, tt.want)
+		}
+	}
+}
@@ -0,0 +1,106 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"net"
+	"net/http"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// dialAttempt is one outbound dial of a CONNECT request and the connection
+// slot it holds.
+type dialAttempt struct {
+	ip      string
+	release func()
+	conn    net.Conn
+	err     error
+}
+
+// dialHedged dials target from ip. With hedging enabled, if the dial hasn't
+// completed after the hedge delay a second dial is raced from an alternate IP
+// not in tried; the first to connect wins and the other is cancelled and
+// released. It returns the winning (or last failed) attempt, whether the hedge
+// won, and tried extended with every IP dialed.
+func (h *ConnectHandler) dialHedged(r *http.Request, host, target, ip string, release func(), tried []string) (dialAttempt, bool, []string) {
+	tried = append(tried, ip)
+	delay := h.server.cfg.ConnectHedgeDelay
+	if delay <= 0 {
+		conn, err := h.dialExit(r.Context(), host, target, ip)
+		return dialAttempt{ip: ip, release: release, conn: conn, err: err}, false, tried
+	}
+
+	ctx, cancel := context.WithCancel(r.Context())
+	defer cancel()
+	results := make(chan dialAttempt, 2)
+	dial := func(a dialAttempt) {
+		a.conn, a.err = h.dialExit(ctx, host, target, a.ip)
+		results <- a
+	}
+	go dial(dialAttempt{ip: ip, release: release})
+
+	pending := 1
+	hedgeIP := ""
+	timer := time.NewTimer(delay)
+	defer timer.Stop()
+	var failed *dialAttempt
+	for {
+		select {
+		case <-timer.C:
+			next, ok := h.server.retryIPForRequest(r, host, tried)
+			if !ok {
+				continue
+			}
+			nextRelease, err := h.acquireExit(next)
+			if err != nil {
+				continue
+			}
+			log.Debug("connect_hedge", "host", host, "ip", ip, "hedge_ip", next, "delay", delay)
+			tried = append(tried, next)
+			hedgeIP = next
+			pending++
+			go dial(dialAttempt{ip: next, release: nextRelease})
+		case a := <-results:
+			pending--
+			if a.err == nil {
+				if pending > 0 {
+					cancel()
+					go func() {
+						loser := <-results
+						if loser.conn != nil {
+							loser.conn.Close()
+						}
+						loser.release()
+					}()
+				}
+				if failed != nil {
+					failed.release()
+				}
+				if hedgeIP != "" {
+					metrics.ConnectHedges.WithLabelValues(hedgeWinner(a.ip == hedgeIP)).Inc()
+				}
+				return a, a.ip == hedgeIP, tried
+			}
+			if failed != nil {
+				failed.release()
+			}
+			if pending == 0 {
+				if hedgeIP != "" {
+					metrics.ConnectHedges.WithLabelValues("none").Inc()
+				}
+				return a, false, tried
+			}
+			failed = &a
+		}
+	}
+}
+
+// hedgeWinner returns the ConnectHedges winner label.
+func hedgeWinner(hedge bool) string {
+	if hedge {
+		return "hedge"
+	}
+	return "primary"
+}
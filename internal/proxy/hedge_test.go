@@ -0,0 +1,79 @@
+package proxy
+
+import (
+	"context"
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+// newHedgeTestHandler returns a CONNECT handler whose dials from slowIP hang
+// until cancelled while dials from other IPs connect at once.
+func newHedgeTestHandler(t *testing.T, slowIP string, delay time.Duration) *ConnectHandler {
+	t.Helper()
+	s := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	s.cfg.ConnectHedgeDelay = delay
+	h := NewConnectHandler(s)
+	h.dial = func(ctx context.Context, ip, target string) (net.Conn, error) {
+		if ip == slowIP {
+			<-ctx.Done()
+			return nil, ctx.Err()
+		}
+		client, server := net.Pipe()
+		t.Cleanup(func() { server.Close() })
+		return client, nil
+	}
+	return h
+}
+
+func TestDialHedged_HedgeWins(t *testing.T) {
+	h := newHedgeTestHandler(t, "127.0.0.1", 10*time.Millisecond)
+	release, err := h.acquireExit("127.0.0.1")
+	if err != nil {
+		t.Fatalf("acquireExit: %v", err)
+	}
+
+	req := httptest.NewRequest(http.MethodConnect, "http://example.com:443", nil)
+	a, hedged, tried := h.dialHedged(req, "example.com:443", "example.com:443", "127.0.0.1", release, nil)
+	if a.err != nil {
+		t.Fatalf("unexpected error: %v", a.err)
+	}
+	defer a.release()
+	defer a.conn.Close()
+	if a.ip != "127.0.0.2" || !hedged {
+		t.Errorf("got ip %s hedged=%v, want the hedge on 127.0.0.2 to win", a.ip, hedged)
+	}
+	if len(tried) != 2 {
+		t.Errorf("tried = %v, want both IPs", tried)
+	}
+
+	// The cancelled primary releases its slot in the background.
+	deadline := time.Now().Add(time.Second)
+	for h.server.limiter.GetIPCount("127.0.0.1") != 0 {
+		if time.Now().After(deadline) {
+			t.Fatal("cancelled primary dial did not release its connection slot")
+		}
+		time.Sleep(5 * time.Millisecond)
+	}
+}
+
+func TestDialHedged_PrimaryWinsBeforeDelay(t *testing.T) {
+	h := newHedgeTestHandler(t, "127.0.0.2", time.Hour)
+	release, err := h.acquireExit("127.0.0.1")
+	if err != nil {
+		t.Fatalf("acquireExit: %v", err)
+	}
+
+	req := httptest.NewRequest(http.MethodConnect, "http://example.com:443", nil)
+	a, hedged, tried := h.dialHedged(req, "example.com:443", "example.com:443", "127.0.0.1", release, nil)
+	if a.err != nil {
+		t.Fatalf("unexpected error: %v", a.err)
+	}
+	defer a.release()
+	defer a.conn.Close()
+	if a.ip != "127.0.0.1" || hedged || len(tried) != 1 {
+		t.Errorf("got ip %s hedged=%v tried=%v, want the primary alone", a.ip, hedged, tried)
+	}
+}
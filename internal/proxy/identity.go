@@ -0,0 +1,114 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"cmp"
+	"context"
+	"errors"
+	"net/http"
+
+	"github.com/cr0hn/outbound-lb/internal/auth"
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// identityKey is the context key for the identity of a client certificate, bearer token or auth webhook.
+type identityKey struct{}
+
+// contextWithIdentity returns a new context with the client's identity attached.
+func contextWithIdentity(ctx context.Context, id auth.Identity) context.Context {
+	return context.WithValue(ctx, identityKey{}, id)
+}
+
+// identityFromRequest returns the identity of the request's client certificate, bearer token or auth webhook.
+func identityFromRequest(r *http.Request) (auth.Identity, bool) {
+	id, ok := r.Context().Value(identityKey{}).(auth.Identity)
+	return id, ok
+}
+
+// SetJWTVerifier accepts "Proxy-Authorization: Bearer" tokens verified by v,
+// alongside any Basic credentials. It must be called before Start.
+func (s *Server) SetJWTVerifier(v *auth.JWTVerifier) {
+	s.jwt = v
+}
+
+// authenticateBearer verifies a bearer token and attaches its identity to r.
+func (s *Server) authenticateBearer(w http.ResponseWriter, r *http.Request, token string) (*http.Request, bool) {
+	id, err := s.jwt.Verify(token)
+	if err != nil {
+		log.Warn("authentication failed", "scheme", "bearer", "error", err, "remote", r.RemoteAddr)
+		s.sendProxyAuthRequired(w)
+		metrics.AuthFailures.Inc()
+		return r, false
+	}
+	return r.WithContext(contextWithIdentity(r.Context(), id)), true
+}
+
+// SetAuthWebhook checks Basic and SOCKS credentials with the auth webhook a
+// instead of --auth or --auth-file. It must be called before Start.
+func (s *Server) SetAuthWebhook(a *auth.WebhookAuthenticator) {
+	s.authWebhook = a
+}
+
+// authenticateWebhook checks Basic credentials with the auth webhook and
+// attaches the returned identity to r, so its pool and rate apply.
+func (s *Server) authenticateWebhook(w http.ResponseWriter, r *http.Request, reqUser, reqPass string) (*http.Request, bool) {
+	id, ok := s.webhookIdentity(r.Context(), reqUser, reqPass, r.RemoteAddr, r.Host, "http")
+	if !ok {
+		log.Warn("authentication failed", "scheme", "webhook", "user", reqUser, "remote", r.RemoteAddr)
+		s.sendProxyAuthRequired(w)
+		metrics.AuthFailures.Inc()
+		return r, false
+	}
+	return r.WithContext(contextWithIdentity(r.Context(), id)), true
+}
+
+// webhookIdentity asks the auth webhook about a credential, or answers from
+// the auth cache. Routing suffixes are stripped before the check and kept in
+// the identity's user, so username hints still apply.
+func (s *Server) webhookIdentity(ctx context.Context, reqUser, reqPass, remote, target, protocol string) (auth.Identity, bool) {
+	baseUser, _ := parseUsernameHints(reqUser)
+	id, ok := s.authCache.CheckIdentity(baseUser, reqPass, func(user, password string) (auth.Identity, bool) {
+		id, err := s.authWebhook.Authenticate(ctx, auth.WebhookRequest{
+			User:     user,
+			Password: password,
+			ClientIP: netutil.ParseHost(remote),
+			Target:   target,
+			Protocol: protocol,
+		})
+		if err != nil && !errors.Is(err, auth.ErrWebhookDenied) {
+			log.Warn("auth_webhook_failed", "user", user, "error", err)
+		}
+		return id, err == nil
+	})
+	id.User = reqUser
+	return id, ok
+}
+
+// userLimits returns the limits user is admitted under: its bearer token's
+// claims or auth webhook's attributes where set, otherwise its user_limits entry.
+func (s *Server) userLimits(user string, id auth.Identity) config.UserLimit {
+	limit, _ := s.cfg.LimitsForUser(user)
+	limit.User = user
+	limit.Rate = cmp.Or(id.Rate, limit.Rate)
+	limit.MaxConns = cmp.Or(id.MaxConns, limit.MaxConns)
+	limit.MonthlyBytes = cmp.Or(id.MonthlyBytes, limit.MonthlyBytes)
+	return limit
+}
+
+// requestUser returns the user a request counts against for per-user limits
+// and its limits.
+func (s *Server) requestUser(r *http.Request) (string, config.UserLimit) {
+	user := fairShareUser(tenantFromRequest(r), netutil.ParseHost(r.RemoteAddr))
+	id, _ := identityFromRequest(r)
+	return user, s.userLimits(user, id)
+}
+
+// admitRate admits a request under the request rate of its bearer token's
+// "rate" claim, auth webhook's "rate" attribute or user_limits entry.
+// Requests without a rate are always admitted.
+func (s *Server) admitRate(r *http.Request) error {
+	user, limit := s.requestUser(r)
+	return s.rates.Allow(user, limit.Rate)
+}
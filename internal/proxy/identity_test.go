@@ -0,0 +1,251 @@
+package proxy
+
+import (
+	"crypto/hmac"
+	"crypto/sha256"
+	"encoding/base64"
+	"encoding/json"
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"slices"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/auth"
+	"github.com/cr0hn/outbound-lb/internal/limiter"
+)
+
+// bearerToken returns an HS256 token for claims signed with secret.
+func bearerToken(t *testing.T, secret string, claims map[string]any) string {
+	t.Helper()
+	if _, ok := claims["exp"]; !ok {
+		claims["exp"] = time.Now().Add(time.Hour).Unix()
+	}
+	segment := func(v any) string {
+		data, err := json.Marshal(v)
+		if err != nil {
+			t.Fatal(err)
+		}
+		return base64.RawURLEncoding.EncodeToString(data)
+	}
+	signed := segment(map[string]string{"alg": "HS256", "typ": "JWT"}) + "." + segment(claims)
+	mac := hmac.New(sha256.New, []byte(secret))
+	mac.Write([]byte(signed))
+	return signed + "." + base64.RawURLEncoding.EncodeToString(mac.Sum(nil))
+}
+
+func TestServer_Authenticate_Bearer(t *testing.T) {
+	server := newTestServerWithAuth(t, "")
+	server.SetJWTVerifier(auth.NewJWTVerifier(auth.JWTConfig{Secret: "s3cret"}))
+
+	req := httptest.NewRequest(http.MethodGet, "/", nil)
+	req.Header.Set("Proxy-Authorization", "Bearer "+bearerToken(t, "s3cret", map[string]any{"sub": "alice", "pool": "premium"}))
+	authed, ok := server.authenticate(httptest.NewRecorder(), req)
+	if !ok {
+		t.Fatal("expected authentication to pass with a valid token")
+	}
+	if got := proxyUsername(authed); got != "alice" {
+		t.Errorf("proxyUsername() = %q, want alice", got)
+	}
+	if got := server.routingHints(authed).Pool; got != "premium" {
+		t.Errorf("routingHints().Pool = %q, want premium", got)
+	}
+
+	tests := []struct {
+		name   string
+		header string
+	}{
+		{"wrong secret", "Bearer " + bearerToken(t, "other", map[string]any{"sub": "alice"})},
+		{"expired", "Bearer " + bearerToken(t, "s3cret", map[string]any{"sub": "alice", "exp": time.Now().Add(-time.Hour).Unix()})},
+		{"basic without users", "Basic " + base64.StdEncoding.EncodeToString([]byte("alice:s3cret"))},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			req := httptest.NewRequest(http.MethodGet, "/", nil)
+			req.Header.Set("Proxy-Authorization", tt.header)
+			w := httptest.NewRecorder()
+			if _, ok := server.authenticate(w, req); ok {
+				t.Fatal("expected authentication to fail")
+			}
+			if got := w.Header().Values("Proxy-Authenticate"); !slices.Equal(got, []string{`Bearer realm="Proxy"`}) {
+				t.Errorf("Proxy-Authenticate = %q, want only a Bearer challenge", got)
+			}
+		})
+	}
+}
+
+func TestServer_Authenticate_BearerAndBasic(t *testing.T) {
+	server := newTestServerWithAuth(t, "user:pass")
+	server.SetJWTVerifier(auth.NewJWTVerifier(auth.JWTConfig{Secret: "s3cret"}))
+
+	req := httptest.NewRequest(http.MethodGet, "/", nil)
+	req.Header.Set("Proxy-Authorization", "Basic "+base64.StdEncoding.EncodeToString([]byte("user:pass")))
+	if _, ok := server.authenticate(httptest.NewRecorder(), req); !ok {
+		t.Error("expected Basic credentials to pass alongside bearer tokens")
+	}
+
+	w := httptest.NewRecorder()
+	if _, ok := server.authenticate(w, httptest.NewRequest(http.MethodGet, "/", nil)); ok {
+		t.Fatal("expected authentication to fail without credentials")
+	}
+	want := []string{`Basic realm="Proxy"`, `Bearer realm="Proxy"`}
+	if got := w.Header().Values("Proxy-Authenticate"); !slices.Equal(got, want) {
+		t.Errorf("Proxy-Authenticate = %q, want %q", got, want)
+	}
+}
+
+func TestServer_Authenticate_Webhook(t *testing.T) {
+	calls := 0
+	hook := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		calls++
+		var req auth.WebhookRequest
+		json.NewDecoder(r.Body).Decode(&req)
+		allow := req.User == "alice" && req.Password == "s3cret" && req.ClientIP == "192.0.2.1"
+		json.NewEncoder(w).Encode(map[string]any{"allow": allow, "pool": "premium", "rate": 1})
+	}))
+	defer hook.Close()
+
+	server := newTestServerWithAuth(t, "")
+	server.SetAuthWebhook(auth.NewWebhookAuthenticator(auth.WebhookConfig{URL: hook.URL}))
+	server.authCache = auth.NewCredentialCache(time.Minute, nil)
+	requestAs := func(credentials string) *http.Request {
+		req := httptest.NewRequest(http.MethodGet, "/", nil)
+		req.Header.Set("Proxy-Authorization", "Basic "+base64.StdEncoding.EncodeToString([]byte(credentials)))
+		return req
+	}
+
+	authed, ok := server.authenticate(httptest.NewRecorder(), requestAs("alice-country-de:s3cret"))
+	if !ok {
+		t.Fatal("expected the webhook to allow alice")
+	}
+	hints := server.routingHints(authed)
+	if hints.Tenant != "alice" || hints.Country != "de" || hints.Pool != "premium" {
+		t.Errorf("routingHints() = %+v, want tenant alice in country de with pool premium", hints)
+	}
+	if err := server.admitRate(authed); err != nil {
+		t.Fatalf("first request: unexpected error: %v", err)
+	}
+	if err := server.admitRate(authed); !errors.Is(err, limiter.ErrRateExceeded) {
+		t.Errorf("second request: expected ErrRateExceeded, got %v", err)
+	}
+
+	// A cached success skips the webhook; failures always ask it
+	if _, ok := server.authenticate(httptest.NewRecorder(), requestAs("alice:s3cret")); !ok {
+		t.Error("expected the cached credential to pass")
+	}
+	w := httptest.NewRecorder()
+	if _, ok := server.authenticate(w, requestAs("alice:wrong")); ok || w.Code != http.StatusProxyAuthRequired {
+		t.Errorf("expected 407 for a denied credential, got %d", w.Code)
+	}
+	if calls != 2 {
+		t.Errorf("webhook called %d times, want 2", calls)
+	}
+
+	// SOCKS credentials are checked with the client's address
+	if !server.checkCredentials("alice", "s3cret", "192.0.2.1:5000") {
+		t.Error("expected SOCKS credentials to pass the webhook")
+	}
+	server.authCache = nil
+	if server.checkCredentials("alice", "s3cret", "198.51.100.1:5000") {
+		t.Error("expected the webhook to deny another client")
+	}
+}
+
+func TestSOCKS5_WebhookIdentity(t *testing.T) {
+	hook := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		var req auth.WebhookRequest
+		json.NewDecoder(r.Body).Decode(&req)
+		pool := "premium"
+		if req.User == "bob" {
+			pool = "missing"
+		}
+		json.NewEncoder(w).Encode(map[string]any{"allow": true, "pool": pool, "max_conns": 1})
+	}))
+	defer hook.Close()
+	backend := newTestBackend(t)
+	defer backend.Close()
+	target := strings.TrimPrefix(backend.URL, "http://")
+
+	server := newTestServerWithAuth(t, "")
+	server.SetAuthWebhook(auth.NewWebhookAuthenticator(auth.WebhookConfig{URL: hook.URL}))
+	if _, err := server.pools.add("premium", []string{"127.0.0.1"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	l := startTestSOCKS5(t, server)
+
+	// The webhook's max_conns applies to SOCKS connections
+	first, code := socks5Connect(t, l.Addr().String(), "alice", "s3cret", target)
+	defer first.Close()
+	if code != socks5ReplySucceeded {
+		t.Fatalf("expected success reply, got %d", code)
+	}
+	second, code := socks5Connect(t, l.Addr().String(), "alice", "s3cret", target)
+	defer second.Close()
+	if code != socks5ReplyGeneralFailure {
+		t.Errorf("expected the second connection to exceed max_conns, got %d", code)
+	}
+
+	// And so does its pool, here one that doesn't exist
+	conn, code := socks5Connect(t, l.Addr().String(), "bob", "s3cret", target)
+	defer conn.Close()
+	if code != socks5ReplyGeneralFailure {
+		t.Errorf("expected bob's empty pool to refuse the connection, got %d", code)
+	}
+}
+
+func TestSelectIPForRequest_TokenPool(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	if _, err := server.pools.add("premium", []string{"127.0.0.3"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req = req.WithContext(contextWithIdentity(req.Context(), auth.Identity{User: "alice", Pool: "premium"}))
+	for i := 0; i < 4; i++ {
+		ip, prov, err := server.selectIPForRequest(req, "example.com")
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		if ip != "127.0.0.3" {
+			t.Errorf("got %s, want the premium pool's 127.0.0.3", ip)
+		}
+		if prov.key != "user/alice" {
+			t.Errorf("affinity key = %q, want user/alice", prov.key)
+		}
+	}
+
+	req = req.WithContext(contextWithIdentity(req.Context(), auth.Identity{User: "alice", Pool: "missing"}))
+	if _, _, err := server.selectIPForRequest(req, "example.com"); !errors.Is(err, ErrPoolEmpty) {
+		t.Errorf("expected ErrPoolEmpty for an unknown pool, got %v", err)
+	}
+}
+
+func TestHandler_TokenRate(t *testing.T) {
+	server := newTestServerWithAuth(t, "")
+	server.SetJWTVerifier(auth.NewJWTVerifier(auth.JWTConfig{Secret: "s3cret"}))
+	token := bearerToken(t, "s3cret", map[string]any{"sub": "alice", "rate": 1})
+
+	req := httptest.NewRequest(http.MethodGet, "/", nil)
+	req.Header.Set("Proxy-Authorization", "Bearer "+token)
+	authed, ok := server.authenticate(httptest.NewRecorder(), req)
+	if !ok {
+		t.Fatal("expected authentication to pass")
+	}
+	if err := server.admitRate(authed); err != nil {
+		t.Fatalf("first request: unexpected error: %v", err)
+	}
+	if err := server.admitRate(authed); !errors.Is(err, limiter.ErrRateExceeded) {
+		t.Fatalf("second request: expected ErrRateExceeded, got %v", err)
+	}
+
+	// The handler answers 429 once the rate is spent
+	w := httptest.NewRecorder()
+	req = httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req.Header.Set("Proxy-Authorization", "Bearer "+token)
+	NewHandler(server).ServeHTTP(w, req)
+	if w.Code != http.StatusTooManyRequests {
+		t.Errorf("expected status 429, got %d", w.Code)
+	}
+}
@@ -157,7 +157,7 @@ func TestProxy_AuthMiddleware(t *testing.T) {
 			}
 			w := httptest.NewRecorder()
 
-			result := server.authenticate(w, req)
+			_, result := server.authenticate(w, req)
 			if result != tt.expectedResult {
 				t.Errorf("authenticate() = %v, want %v", result, tt.expectedResult)
 			}
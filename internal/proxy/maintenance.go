@@ -0,0 +1,177 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"errors"
+	"fmt"
+	"net/http"
+	"slices"
+	"sort"
+	"strconv"
+	"sync"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// ErrMaintenance is returned when new traffic is refused because the proxy or
+// the destination's pool is in maintenance.
+var ErrMaintenance = errors.New("in maintenance")
+
+// maintenanceState tracks the global and per-pool maintenance toggles.
+type maintenanceState struct {
+	global bool
+	pools  map[string]bool
+	mu     sync.RWMutex
+}
+
+// newMaintenanceState creates a maintenanceState with nothing in maintenance.
+func newMaintenanceState() *maintenanceState {
+	return &maintenanceState{pools: make(map[string]bool)}
+}
+
+// set turns maintenance of pool, or of the whole proxy if pool is empty, on or
+// off and reports whether it changed.
+func (m *maintenanceState) set(pool string, on bool) bool {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	if pool == "" {
+		changed := m.global != on
+		m.global = on
+		return changed
+	}
+	changed := m.pools[pool] != on
+	if on {
+		m.pools[pool] = true
+	} else {
+		delete(m.pools, pool)
+	}
+	return changed
+}
+
+// active reports whether the proxy, or pool if not empty, is in maintenance.
+// scope is "" when the whole proxy is, or pool otherwise.
+func (m *maintenanceState) active(pool string) (scope string, on bool) {
+	m.mu.RLock()
+	defer m.mu.RUnlock()
+	if m.global {
+		return "", true
+	}
+	return pool, pool != "" && m.pools[pool]
+}
+
+// snapshot reports the global toggle and the pools in maintenance, sorted.
+func (m *maintenanceState) snapshot() (bool, []string) {
+	m.mu.RLock()
+	defer m.mu.RUnlock()
+	pools := make([]string, 0, len(m.pools))
+	for name := range m.pools {
+		pools = append(pools, name)
+	}
+	sort.Strings(pools)
+	return m.global, pools
+}
+
+// checkMaintenance refuses new traffic while the proxy, or pool if not empty,
+// is in maintenance, counting the rejection apart from organic failures.
+func (s *Server) checkMaintenance(pool string) error {
+	scope, on := s.maintenance.active(pool)
+	if !on {
+		return nil
+	}
+	metrics.MaintenanceRejections.WithLabelValues(scope).Inc()
+	if scope == "" {
+		return ErrMaintenance
+	}
+	return fmt.Errorf("%w: pool %s", ErrMaintenance, scope)
+}
+
+// SetMaintenance turns maintenance of pool, or of the whole proxy if pool is
+// empty, on or off. New connections are refused with a 503 while existing
+// tunnels keep running. Unknown pools return ErrPoolNotFound.
+func (s *Server) SetMaintenance(pool string, on bool) error {
+	if pool != "" {
+		if _, ok := s.pools.get(pool); !ok && on {
+			return fmt.Errorf("%w: %s", ErrPoolNotFound, pool)
+		}
+	}
+	if !s.maintenance.set(pool, on) {
+		return nil
+	}
+	if on {
+		metrics.MaintenanceActive.WithLabelValues(pool).Set(1)
+		log.Info("maintenance_started", "pool", pool, "active_tunnels", s.tunnels.len())
+	} else {
+		metrics.MaintenanceActive.WithLabelValues(pool).Set(0)
+		log.Info("maintenance_ended", "pool", pool)
+	}
+	return nil
+}
+
+// CloseTunnels closes the open tunnels, or only those through the IPs of pool
+// if not empty, and returns how many were closed. Combined with maintenance it
+// freezes traffic at once instead of letting tunnels finish.
+func (s *Server) CloseTunnels(pool string) int {
+	var ips []string
+	if pool != "" {
+		ips, _ = s.pools.get(pool)
+	}
+	closed := 0
+	for _, e := range s.tunnels.snapshot() {
+		if pool != "" && !slices.Contains(ips, e.ip) {
+			continue
+		}
+		e.closeFn()
+		closed++
+	}
+	log.Warn("tunnels_closed", "pool", pool, "closed_tunnels", closed)
+	return closed
+}
+
+// sendMaintenance answers a request refused by maintenance mode with a 503,
+// the configured message and Retry-After.
+func (s *Server) sendMaintenance(w http.ResponseWriter) {
+	if s.cfg.MaintenanceRetryAfter > 0 {
+		w.Header().Set("Retry-After", strconv.Itoa(int(s.cfg.MaintenanceRetryAfter.Seconds())))
+	}
+	http.Error(w, s.cfg.MaintenanceMessage, http.StatusServiceUnavailable)
+}
+
+// MaintenanceHandler returns the admin handler for maintenance mode.
+// GET reports the global toggle and the pools in maintenance, POST [?pool=X]
+// puts the proxy, or pool X, in maintenance and DELETE [?pool=X] ends it.
+// POST with close_tunnels=true also closes the tunnels already open.
+func (s *Server) MaintenanceHandler() http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		pool := r.URL.Query().Get("pool")
+		closeTunnels := r.URL.Query().Get("close_tunnels") == "true"
+		var err error
+		switch r.Method {
+		case http.MethodGet:
+		case http.MethodPost:
+			err = s.SetMaintenance(pool, true)
+		case http.MethodDelete:
+			err = s.SetMaintenance(pool, false)
+		default:
+			w.Header().Set("Allow", "GET, POST, DELETE")
+			http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+			return
+		}
+		if err != nil {
+			writePoolError(w, err)
+			return
+		}
+
+		closed := 0
+		if r.Method == http.MethodPost && closeTunnels {
+			closed = s.CloseTunnels(pool)
+		}
+
+		global, pools := s.maintenance.snapshot()
+		writeJSON(w, http.StatusOK, map[string]any{
+			"global":         global,
+			"pools":          pools,
+			"active_tunnels": s.tunnels.len(),
+			"closed_tunnels": closed,
+		})
+	})
+}
@@ -0,0 +1,118 @@
+package proxy
+
+import (
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"sync/atomic"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+func TestMaintenance_Global(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.MaintenanceMessage = "Back soon"
+	server.cfg.MaintenanceRetryAfter = 2 * time.Minute
+	handler := NewHandler(server)
+
+	if err := server.SetMaintenance("", true); err != nil {
+		t.Fatalf("SetMaintenance: %v", err)
+	}
+	rec := httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodGet, "http://example.com/", nil))
+	if rec.Code != http.StatusServiceUnavailable {
+		t.Fatalf("expected 503, got %d", rec.Code)
+	}
+	if got := rec.Header().Get("Retry-After"); got != "120" {
+		t.Errorf("Retry-After = %q, want 120", got)
+	}
+	if !strings.Contains(rec.Body.String(), "Back soon") {
+		t.Errorf("body = %q, want the maintenance message", rec.Body.String())
+	}
+
+	if err := server.SetMaintenance("", false); err != nil {
+		t.Fatalf("SetMaintenance: %v", err)
+	}
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	if _, _, err := server.selectIPForRequest(req, "example.com"); err != nil {
+		t.Errorf("expected selection after maintenance ended, got %v", err)
+	}
+}
+
+func TestMaintenance_Pool(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	server.cfg.DestinationPools = []config.DestinationPool{{CIDR: "203.0.113.0/24", Pool: "partners"}}
+	if _, err := server.pools.add("partners", []string{"127.0.0.2"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	req := httptest.NewRequest(http.MethodGet, "http://203.0.113.9/", nil)
+
+	if err := server.SetMaintenance("partners", true); err != nil {
+		t.Fatalf("SetMaintenance: %v", err)
+	}
+	if _, _, err := server.selectIPForRequest(req, "203.0.113.9:443"); !errors.Is(err, ErrMaintenance) {
+		t.Errorf("expected ErrMaintenance for the pool's destinations, got %v", err)
+	}
+	if _, _, err := server.selectIPForRequest(req, "example.com:443"); err != nil {
+		t.Errorf("expected other destinations to be served, got %v", err)
+	}
+
+	if err := server.SetMaintenance("missing", true); !errors.Is(err, ErrPoolNotFound) {
+		t.Errorf("expected ErrPoolNotFound for an unknown pool, got %v", err)
+	}
+}
+
+func TestMaintenanceHandler(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	handler := server.MaintenanceHandler()
+
+	rec := httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/admin/maintenance", nil))
+	if rec.Code != http.StatusOK || !strings.Contains(rec.Body.String(), `"global":true`) {
+		t.Fatalf("POST = %d %s, want global maintenance", rec.Code, rec.Body.String())
+	}
+
+	rec = httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodDelete, "/admin/maintenance", nil))
+	if rec.Code != http.StatusOK || !strings.Contains(rec.Body.String(), `"global":false`) {
+		t.Errorf("DELETE = %d %s, want maintenance ended", rec.Code, rec.Body.String())
+	}
+
+	rec = httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/admin/maintenance?pool=missing", nil))
+	if rec.Code != http.StatusNotFound {
+		t.Errorf("expected 404 for unknown pool, got %d", rec.Code)
+	}
+}
+
+func TestMaintenanceHandler_CloseTunnels(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	if _, err := server.pools.add("partners", []string{"127.0.0.2"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	var closedDefault, closedPartners atomic.Bool
+	server.tunnels.add("example.com", "127.0.0.1", func() { closedDefault.Store(true) })
+	server.tunnels.add("api.partner.example", "127.0.0.2", func() { closedPartners.Store(true) })
+	handler := server.MaintenanceHandler()
+
+	rec := httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/admin/maintenance?pool=partners&close_tunnels=true", nil))
+	if rec.Code != http.StatusOK || !strings.Contains(rec.Body.String(), `"closed_tunnels":1`) {
+		t.Fatalf("POST = %d %s, want one tunnel closed", rec.Code, rec.Body.String())
+	}
+	if !closedPartners.Load() || closedDefault.Load() {
+		t.Errorf("expected only the pool's tunnel closed, got partners=%v default=%v", closedPartners.Load(), closedDefault.Load())
+	}
+
+	rec = httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/admin/maintenance", nil))
+	if closedDefault.Load() {
+		t.Error("expected tunnels to be kept without close_tunnels")
+	}
+	if n := server.CloseTunnels(""); n != 2 || !closedDefault.Load() {
+		t.Errorf("CloseTunnels(\"\") = %d, want every tunnel closed", n)
+	}
+}
@@ -0,0 +1,11 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import "slices"
+
+// methodAllowed applies the allowed_methods policy to a plain HTTP request.
+// Methods are uppercased when the config is validated, and HTTP methods are
+// case-sensitive, so the comparison is exact.
+func (s *Server) methodAllowed(method string) bool {
+	return len(s.cfg.AllowedMethods) == 0 || slices.Contains(s.cfg.AllowedMethods, method)
+}
@@ -0,0 +1,41 @@
+package proxy
+
+import (
+	"net/http"
+	"net/http/httptest"
+	"testing"
+)
+
+func TestHandler_MethodPolicy(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.AllowedMethods = []string{"GET", "HEAD"}
+	handler := NewHandler(server)
+
+	r := httptest.NewRequest(http.MethodDelete, backend.URL+"/items/1", nil)
+	w := httptest.NewRecorder()
+	handler.ServeHTTP(w, r)
+	if w.Code != http.StatusMethodNotAllowed {
+		t.Errorf("DELETE status = %d, want 405", w.Code)
+	}
+	if got := w.Header().Get("Allow"); got != "GET, HEAD" {
+		t.Errorf("Allow = %q, want %q", got, "GET, HEAD")
+	}
+
+	r = httptest.NewRequest(http.MethodGet, backend.URL+"/items/1", nil)
+	w = httptest.NewRecorder()
+	handler.ServeHTTP(w, r)
+	if w.Code == http.StatusMethodNotAllowed {
+		t.Error("expected GET to be forwarded")
+	}
+
+	server.cfg.AllowedMethods = nil
+	r = httptest.NewRequest(http.MethodDelete, backend.URL+"/items/1", nil)
+	w = httptest.NewRecorder()
+	handler.ServeHTTP(w, r)
+	if w.Code == http.StatusMethodNotAllowed {
+		t.Error("expected an empty policy to allow every method")
+	}
+}
@@ -0,0 +1,258 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"net"
+	"net/http"
+	"sort"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// Pin TTL bounds for the pin API.
+const (
+	defaultPinTTL = 10 * time.Minute
+	maxPinTTL     = 24 * time.Hour
+)
+
+// ErrInvalidPin is returned when a pin request is malformed.
+var ErrInvalidPin = errors.New("invalid pin request")
+
+// Pin fixes the origin address and outbound IP a tenant uses for a host.
+type Pin struct {
+	// Tenant is the proxy username (without routing suffixes) the pin applies to.
+	// Empty means unauthenticated clients.
+	Tenant string `json:"tenant"`
+	// Host is the pinned hostname, without port.
+	Host string `json:"host"`
+	// Origin is the resolved address every connection to Host is sent to.
+	Origin string `json:"origin"`
+	// Addrs are all addresses Host resolved to when the pin was created.
+	Addrs []string `json:"addrs"`
+	// Exit is the outbound IP every connection to Host leaves through.
+	Exit string `json:"exit"`
+	// ExpiresAt is when the pin lapses.
+	ExpiresAt time.Time `json:"expires_at"`
+}
+
+// pinKey identifies a pin.
+type pinKey struct {
+	tenant string
+	host   string
+}
+
+// pinStore holds active pins. Expired pins are dropped lazily.
+type pinStore struct {
+	pins  map[pinKey]Pin
+	clock clock.Clock
+	mu    sync.Mutex
+}
+
+// newPinStore creates an empty pinStore that expires pins by c.
+func newPinStore(c clock.Clock) *pinStore {
+	return &pinStore{pins: make(map[pinKey]Pin), clock: clock.OrReal(c)}
+}
+
+// set stores p, replacing any pin for the same tenant and host.
+func (ps *pinStore) set(p Pin) {
+	ps.mu.Lock()
+	defer ps.mu.Unlock()
+	ps.pins[pinKey{p.Tenant, p.Host}] = p
+}
+
+// get returns the unexpired pin for tenant and host.
+func (ps *pinStore) get(tenant, host string) (Pin, bool) {
+	key := pinKey{tenant, netutil.NormalizeHost(host)}
+	ps.mu.Lock()
+	defer ps.mu.Unlock()
+	p, ok := ps.pins[key]
+	if !ok {
+		return Pin{}, false
+	}
+	if ps.clock.Now().After(p.ExpiresAt) {
+		delete(ps.pins, key)
+		return Pin{}, false
+	}
+	return p, true
+}
+
+// remove deletes the pin for tenant and host. Returns false if there was none.
+func (ps *pinStore) remove(tenant, host string) bool {
+	key := pinKey{tenant, netutil.NormalizeHost(host)}
+	ps.mu.Lock()
+	defer ps.mu.Unlock()
+	if _, ok := ps.pins[key]; !ok {
+		return false
+	}
+	delete(ps.pins, key)
+	return true
+}
+
+// list returns the unexpired pins, optionally restricted to one tenant, ordered by tenant and host.
+func (ps *pinStore) list(tenant string, all bool) []Pin {
+	now := ps.clock.Now()
+	ps.mu.Lock()
+	pins := make([]Pin, 0, len(ps.pins))
+	for key, p := range ps.pins {
+		if now.After(p.ExpiresAt) {
+			delete(ps.pins, key)
+			continue
+		}
+		if all || p.Tenant == tenant {
+			pins = append(pins, p)
+		}
+	}
+	ps.mu.Unlock()
+
+	sort.Slice(pins, func(i, j int) bool {
+		if pins[i].Tenant != pins[j].Tenant {
+			return pins[i].Tenant < pins[j].Tenant
+		}
+		return pins[i].Host < pins[j].Host
+	})
+	return pins
+}
+
+// PinDestination resolves host now and pins one of its addresses, together with
+// an outbound IP, for tenant until ttl elapses. Every connection the tenant makes
+// to host while the pin is active goes to the same origin through the same exit.
+// The exit is chosen as for the tenant's own connections, honoring its pool,
+// destination rules and maintenance.
+func (s *Server) PinDestination(ctx context.Context, tenant, host string, ttl time.Duration) (Pin, error) {
+	host = netutil.NormalizeHost(host)
+	if host == "" {
+		return Pin{}, fmt.Errorf("%w: host is required", ErrInvalidPin)
+	}
+	if ttl <= 0 || ttl > maxPinTTL {
+		return Pin{}, fmt.Errorf("%w: ttl must be in (0, %s]", ErrInvalidPin, maxPinTTL)
+	}
+
+	opts, _, err := s.selectOptions(host, RoutingHints{Tenant: tenant})
+	if err != nil {
+		return Pin{}, err
+	}
+	addrs, err := s.resolver.LookupHost(ctx, host)
+	if err != nil {
+		return Pin{}, err
+	}
+	exit, err := s.balancer.SelectWithOptions(host, opts)
+	if err != nil {
+		return Pin{}, err
+	}
+	origin, err := originForExit(addrs, exit)
+	if err != nil {
+		return Pin{}, err
+	}
+
+	p := Pin{
+		Tenant:    tenant,
+		Host:      host,
+		Origin:    origin,
+		Addrs:     addrs,
+		Exit:      exit,
+		ExpiresAt: s.pins.clock.Now().Add(ttl),
+	}
+	s.pins.set(p)
+	log.Info("destination_pinned", "tenant", tenant, "host", host, "origin", origin, "exit", exit, "ttl", ttl)
+	return p, nil
+}
+
+// originForExit returns the first address reachable from exit's address family.
+func originForExit(addrs []string, exit string) (string, error) {
+	exitIPv4 := net.ParseIP(exit).To4() != nil
+	for _, a := range addrs {
+		if ip := net.ParseIP(a); ip != nil && (ip.To4() != nil) == exitIPv4 {
+			return a, nil
+		}
+	}
+	return "", &net.AddrError{Err: "no address matching the exit's family", Addr: exit}
+}
+
+// pinnedExit returns the pinned outbound IP for tenant and target host:port, if any.
+func (s *Server) pinnedExit(tenant, target string) (string, bool) {
+	p, ok := s.pins.get(tenant, netutil.ParseHost(target))
+	return p.Exit, ok
+}
+
+// pinnedOrigin returns the pinned origin address for tenant and host, if any.
+func (s *Server) pinnedOrigin(tenant, host string) (string, bool) {
+	p, ok := s.pins.get(tenant, host)
+	return p.Origin, ok
+}
+
+// dialTarget returns the address to dial for target host:port, substituting the
+// pinned origin when the tenant has pinned the host.
+func (s *Server) dialTarget(tenant, target string) string {
+	host, port, err := net.SplitHostPort(target)
+	if err != nil {
+		return target
+	}
+	if origin, ok := s.pinnedOrigin(tenant, host); ok {
+		return net.JoinHostPort(origin, port)
+	}
+	return target
+}
+
+// PinHandler returns the admin handler for destination pins.
+// GET lists pins (optionally ?tenant=X), POST ?tenant=X&host=Y[&ttl=Z] creates
+// a pin and returns it, and DELETE ?tenant=X&host=Y removes it.
+func (s *Server) PinHandler() http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		q := r.URL.Query()
+		tenant := q.Get("tenant")
+
+		switch r.Method {
+		case http.MethodGet:
+			writeJSON(w, http.StatusOK, map[string]any{
+				"pins": s.pins.list(tenant, !q.Has("tenant")),
+			})
+		case http.MethodPost:
+			ttl := defaultPinTTL
+			if v := q.Get("ttl"); v != "" {
+				d, err := time.ParseDuration(v)
+				if err != nil {
+					http.Error(w, "invalid ttl", http.StatusBadRequest)
+					return
+				}
+				ttl = d
+			}
+			p, err := s.PinDestination(r.Context(), tenant, q.Get("host"), ttl)
+			switch {
+			case errors.Is(err, ErrInvalidPin):
+				http.Error(w, err.Error(), http.StatusBadRequest)
+			case errors.Is(err, ErrDestinationNotAllowed):
+				http.Error(w, err.Error(), http.StatusForbidden)
+			case errors.Is(err, ErrMaintenance):
+				http.Error(w, err.Error(), http.StatusServiceUnavailable)
+			case err != nil:
+				http.Error(w, err.Error(), http.StatusBadGateway)
+			default:
+				writeJSON(w, http.StatusOK, p)
+			}
+		case http.MethodDelete:
+			if !s.pins.remove(tenant, q.Get("host")) {
+				http.Error(w, "pin not found", http.StatusNotFound)
+				return
+			}
+			log.Info("destination_unpinned", "tenant", tenant, "host", q.Get("host"))
+			w.WriteHeader(http.StatusNoContent)
+		default:
+			w.Header().Set("Allow", "GET, POST, DELETE")
+			http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+		}
+	})
+}
+
+// writeJSON writes v as a JSON response with the given status.
+func writeJSON(w http.ResponseWriter, status int, v any) {
+	w.Header().Set("Content-Type", "application/json")
+	w.WriteHeader(status)
+	json.NewEncoder(w).Encode(v)
+}
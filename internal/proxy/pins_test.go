@@ -0,0 +1,144 @@
+package proxy
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+func TestPinStore_Expiry(t *testing.T) {
+	now := clock.NewFake(time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC))
+	ps := newPinStore(now)
+	ps.set(Pin{Tenant: "alice", Host: "example.com", ExpiresAt: now.Now().Add(time.Second)})
+	ps.set(Pin{Tenant: "bob", Host: "example.com", ExpiresAt: now.Now().Add(time.Minute)})
+	now.Advance(2 * time.Second)
+
+	if _, ok := ps.get("alice", "example.com"); ok {
+		t.Error("expected expired pin to be ignored")
+	}
+	if _, ok := ps.get("bob", "EXAMPLE.COM"); !ok {
+		t.Error("expected case-insensitive lookup of active pin")
+	}
+	if pins := ps.list("", true); len(pins) != 1 {
+		t.Errorf("expected 1 active pin, got %d", len(pins))
+	}
+}
+
+func TestServer_PinDestination(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+
+	p, err := server.PinDestination(context.Background(), "alice", "localhost:443", time.Minute)
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if p.Host != "localhost" || p.Exit != "127.0.0.1" || p.Origin != "127.0.0.1" {
+		t.Errorf("unexpected pin: %+v", p)
+	}
+
+	if got := server.dialTarget("alice", "localhost:8080"); got != "127.0.0.1:8080" {
+		t.Errorf("dialTarget() = %s, want 127.0.0.1:8080", got)
+	}
+	if got := server.dialTarget("bob", "localhost:8080"); got != "localhost:8080" {
+		t.Errorf("pin leaked to another tenant: dialTarget() = %s", got)
+	}
+
+	if _, err := server.PinDestination(context.Background(), "alice", "localhost", 48*time.Hour); err == nil {
+		t.Error("expected error for ttl above the maximum")
+	}
+}
+
+func TestServer_PinDestinationPolicy(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	server.cfg.UserPools = []config.UserPool{{User: "alice", IPs: []string{"127.0.0.2"}}}
+
+	// The exit comes from the tenant's own pool
+	for i := 0; i < 5; i++ {
+		p, err := server.PinDestination(context.Background(), "alice", "localhost", time.Minute)
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		if p.Exit != "127.0.0.2" {
+			t.Fatalf("Exit = %s, want 127.0.0.2 from alice's pool", p.Exit)
+		}
+		server.pins.remove("alice", "localhost")
+	}
+
+	if err := server.SetMaintenance("", true); err != nil {
+		t.Fatalf("SetMaintenance: %v", err)
+	}
+	if _, err := server.PinDestination(context.Background(), "bob", "localhost", time.Minute); !errors.Is(err, ErrMaintenance) {
+		t.Errorf("expected ErrMaintenance, got %v", err)
+	}
+}
+
+func TestSOCKS5_PinnedDestination(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.pins.set(Pin{Host: "pinned.invalid", Origin: "127.0.0.1", Exit: "127.0.0.1", ExpiresAt: time.Now().Add(time.Minute)})
+	l := startTestSOCKS5(t, server)
+
+	port := backend.URL[strings.LastIndex(backend.URL, ":")+1:]
+	conn, code := socks5Connect(t, l.Addr().String(), "", "", "pinned.invalid:"+port)
+	defer conn.Close()
+
+	if code != socks5ReplySucceeded {
+		t.Fatalf("expected pinned host to be dialed at its origin, got reply %d", code)
+	}
+}
+
+func TestServer_PinHandler(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	handler := server.PinHandler()
+
+	tests := []struct {
+		method string
+		query  string
+		want   int
+	}{
+		{http.MethodPost, "tenant=alice", http.StatusBadRequest},
+		{http.MethodPost, "tenant=alice&host=localhost&ttl=bogus", http.StatusBadRequest},
+		{http.MethodPost, "tenant=alice&host=localhost&ttl=5m", http.StatusOK},
+		{http.MethodDelete, "tenant=alice&host=localhost", http.StatusNoContent},
+		{http.MethodDelete, "tenant=alice&host=localhost", http.StatusNotFound},
+		{http.MethodPut, "", http.StatusMethodNotAllowed},
+	}
+
+	for _, tt := range tests {
+		req := httptest.NewRequest(tt.method, "/admin/pins?"+tt.query, nil)
+		rec := httptest.NewRecorder()
+		handler.ServeHTTP(rec, req)
+		if rec.Code != tt.want {
+			t.Errorf("%s ?%s: status = %d, want %d", tt.method, tt.query, rec.Code, tt.want)
+		}
+	}
+}
+
+func TestServer_PinHandlerList(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.pins.set(Pin{Tenant: "alice", Host: "a.example.com", ExpiresAt: time.Now().Add(time.Minute)})
+	server.pins.set(Pin{Tenant: "bob", Host: "b.example.com", ExpiresAt: time.Now().Add(time.Minute)})
+
+	req := httptest.NewRequest(http.MethodGet, "/admin/pins?tenant=bob", nil)
+	rec := httptest.NewRecorder()
+	server.PinHandler().ServeHTTP(rec, req)
+
+	var body struct {
+		Pins []Pin `json:"pins"`
+	}
+	if err := json.NewDecoder(rec.Body).Decode(&body); err != nil {
+		t.Fatalf("failed to decode response: %v", err)
+	}
+	if len(body.Pins) != 1 || body.Pins[0].Tenant != "bob" {
+		t.Errorf("expected only bob's pin, got %+v", body.Pins)
+	}
+}
@@ -0,0 +1,40 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"slices"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/internal/rules"
+)
+
+// policy is what a --policy-file adds to the configuration: destination lists
+// and the routing rules compiled together with the config file's. It is
+// replaced as a whole on reload.
+type policy struct {
+	allowed []string
+	denied  []string
+	rules   *rules.Engine
+}
+
+// newPolicy combines cfg with the policy file p, which may be nil.
+func newPolicy(cfg *config.Config, p *config.Policy) *policy {
+	if p == nil {
+		return &policy{rules: rules.New(cfg.Rules, clock.Real)}
+	}
+	return &policy{
+		allowed: p.AllowedDestinations,
+		denied:  p.DeniedDestinations,
+		rules:   rules.New(slices.Concat(p.Rules, cfg.Rules), clock.Real),
+	}
+}
+
+// SetPolicy applies a policy file validated against the server's config: its
+// destination lists extend allowed_destinations and denied_destinations, and
+// its rules are evaluated before the config file's. It may be called again
+// while serving to swap in a reloaded policy; new connections see the new
+// policy at once, while open tunnels carry on.
+func (s *Server) SetPolicy(p *config.Policy) {
+	s.policy.Store(newPolicy(s.cfg, p))
+}
@@ -0,0 +1,36 @@
+package proxy
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+func TestSetPolicy(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.DeniedDestinations = []string{"blocked.example.com"}
+
+	server.SetPolicy(&config.Policy{
+		DeniedDestinations: []string{"*.evil.example"},
+		Rules:              []config.RoutingRule{{Name: "no-smtp", Match: config.RuleMatch{Ports: []string{"25"}}, Action: config.RuleDeny}},
+	})
+	for _, host := range []string{"blocked.example.com:443", "c2.evil.example:443", "mail.example.com:25"} {
+		if _, _, err := server.selectOptions(host, RoutingHints{}); !errors.Is(err, ErrDestinationNotAllowed) {
+			t.Errorf("%s: expected ErrDestinationNotAllowed, got %v", host, err)
+		}
+	}
+
+	// A reloaded policy replaces the previous one, and its allowlist extends the config file's
+	server.cfg.AllowedDestinations = []string{"api.example.com"}
+	server.SetPolicy(&config.Policy{AllowedDestinations: []string{"*.partner.example"}})
+	for host, want := range map[string]bool{"api.example.com:443": true, "x.partner.example:443": true, "example.org:443": false, "api.example.com:25": true} {
+		_, _, err := server.selectOptions(host, RoutingHints{})
+		if want && err != nil {
+			t.Errorf("%s: expected to be allowed after reload, got %v", host, err)
+		}
+		if !want && !errors.Is(err, ErrDestinationNotAllowed) {
+			t.Errorf("%s: expected ErrDestinationNotAllowed, got %v", host, err)
+		}
+	}
+}
@@ -0,0 +1,464 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"net"
+	"net/http"
+	"os"
+	"path/filepath"
+	"slices"
+	"sort"
+	"strconv"
+	"strings"
+	"sync"
+
+	"gopkg.in/yaml.v3"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// Errors returned by pool changes.
+var (
+	// ErrInvalidPool is returned when a pool change is malformed.
+	ErrInvalidPool = errors.New("invalid pool request")
+	// ErrPoolNotFound is returned when a pool does not exist.
+	ErrPoolNotFound = errors.New("pool not found")
+	// ErrPoolConfigured is returned when changing a pool defined in the config file.
+	ErrPoolConfigured = errors.New("pool is defined in the config file")
+	// ErrPoolEmpty is returned when a destination's or user's pool has no outbound IPs.
+	ErrPoolEmpty = errors.New("no outbound IPs in pool")
+	// ErrGenerationMismatch is returned when a conditional pool update was
+	// based on an outdated generation.
+	ErrGenerationMismatch = errors.New("pool generation mismatch")
+)
+
+// maxPoolPatchBody bounds the body of a batch pool update.
+const maxPoolPatchBody = 8 << 20
+
+// PoolInfo describes a named pool in the pool API.
+type PoolInfo struct {
+	Name string   `json:"name"`
+	IPs  []string `json:"ips"`
+	// Source is "config" for pools from the config file and "admin" for pools
+	// created through the admin API.
+	Source string `json:"source"`
+}
+
+// PoolChange is one step of a batch pool update. Add creates the pool if
+// needed, Remove drops IPs from it and Delete removes the whole pool.
+type PoolChange struct {
+	Pool   string   `json:"pool"`
+	Add    []string `json:"add,omitempty"`
+	Remove []string `json:"remove,omitempty"`
+	Delete bool     `json:"delete,omitempty"`
+}
+
+// PoolUpdate is the body of a batch pool update. If IfGeneration is set, the
+// update is only applied if the pools are still at that generation.
+type PoolUpdate struct {
+	IfGeneration *uint64      `json:"if_generation,omitempty"`
+	Changes      []PoolChange `json:"changes"`
+}
+
+// poolStore holds the named pools. Pools from the config file are read-only;
+// pools created through the admin API are persisted to path, if set, and
+// loaded again on startup. Every change to the managed pools bumps the
+// generation, which is persisted along with them.
+type poolStore struct {
+	configured map[string][]string
+	managed    map[string][]string
+	ips        map[string]bool
+	path       string
+	generation uint64
+	mu         sync.RWMutex
+}
+
+// newPoolStore creates a poolStore with the config file's pools, restoring
+// managed pools from path. ips are the outbound IPs pools may hold.
+func newPoolStore(configured []config.Pool, ips []string, path string) *poolStore {
+	ps := &poolStore{
+		configured: make(map[string][]string, len(configured)),
+		managed:    make(map[string][]string),
+		ips:        make(map[string]bool, len(ips)),
+		path:       path,
+	}
+	for _, ip := range ips {
+		ps.ips[ip] = true
+	}
+	for _, p := range configured {
+		ps.configured[p.Name] = p.IPs
+	}
+	if path != "" {
+		if err := ps.load(); err != nil && !errors.Is(err, os.ErrNotExist) {
+			log.Warn("pools_load_failed", "path", path, "error", err)
+		}
+	}
+	return ps
+}
+
+// get returns the outbound IPs of the named pool.
+func (ps *poolStore) get(name string) ([]string, bool) {
+	ps.mu.RLock()
+	defer ps.mu.RUnlock()
+	if ips, ok := ps.configured[name]; ok {
+		return ips, true
+	}
+	ips, ok := ps.managed[name]
+	return ips, ok
+}
+
+// list returns all pools sorted by name.
+func (ps *poolStore) list() []PoolInfo {
+	ps.mu.RLock()
+	defer ps.mu.RUnlock()
+	pools := make([]PoolInfo, 0, len(ps.configured)+len(ps.managed))
+	for name, ips := range ps.configured {
+		pools = append(pools, PoolInfo{Name: name, IPs: ips, Source: "config"})
+	}
+	for name, ips := range ps.managed {
+		pools = append(pools, PoolInfo{Name: name, IPs: ips, Source: "admin"})
+	}
+	sort.Slice(pools, func(i, j int) bool { return pools[i].Name < pools[j].Name })
+	return pools
+}
+
+// currentGeneration returns the generation of the managed pools.
+func (ps *poolStore) currentGeneration() uint64 {
+	ps.mu.RLock()
+	defer ps.mu.RUnlock()
+	return ps.generation
+}
+
+// add creates the named pool if needed and adds ips to it, then persists the
+// managed pools.
+func (ps *poolStore) add(name string, ips []string) (PoolInfo, error) {
+	ps.mu.Lock()
+	defer ps.mu.Unlock()
+	if err := ps.applyLocked([]PoolChange{{Pool: name, Add: ips}}, nil); err != nil {
+		return PoolInfo{}, err
+	}
+	return PoolInfo{Name: name, IPs: ps.managed[name], Source: "admin"}, nil
+}
+
+// remove removes ips from the named pool, or the whole pool if ips is empty,
+// then persists the managed pools.
+func (ps *poolStore) remove(name string, ips []string) error {
+	ps.mu.Lock()
+	defer ps.mu.Unlock()
+	return ps.applyLocked([]PoolChange{{Pool: name, Remove: ips, Delete: len(ips) == 0}}, nil)
+}
+
+// update applies changes as one step: either all of them are applied and
+// persisted under a single new generation, or none is. It returns the new
+// generation.
+func (ps *poolStore) update(changes []PoolChange, ifGeneration *uint64) (uint64, error) {
+	ps.mu.Lock()
+	defer ps.mu.Unlock()
+	if len(changes) == 0 {
+		return ps.generation, fmt.Errorf("%w: no changes", ErrInvalidPool)
+	}
+	err := ps.applyLocked(changes, ifGeneration)
+	return ps.generation, err
+}
+
+// applyLocked applies changes in order, rolling all of them back if one
+// fails or the result cannot be persisted. Must be called with mu held.
+func (ps *poolStore) applyLocked(changes []PoolChange, ifGeneration *uint64) error {
+	for _, c := range changes {
+		if err := ps.validateChange(c); err != nil {
+			return err
+		}
+	}
+	if ifGeneration != nil && *ifGeneration != ps.generation {
+		return fmt.Errorf("%w: at generation %d, not %d", ErrGenerationMismatch, ps.generation, *ifGeneration)
+	}
+
+	type snapshot struct {
+		ips     []string
+		existed bool
+	}
+	prev := make(map[string]snapshot, len(changes))
+	rollback := func() {
+		for name, p := range prev {
+			ps.restore(name, p.ips, p.existed)
+		}
+	}
+	for _, c := range changes {
+		if _, ok := ps.configured[c.Pool]; ok {
+			rollback()
+			return fmt.Errorf("%w: %s", ErrPoolConfigured, c.Pool)
+		}
+		members, existed := ps.managed[c.Pool]
+		if _, ok := prev[c.Pool]; !ok {
+			prev[c.Pool] = snapshot{ips: members, existed: existed}
+		}
+		if !existed && (c.Delete || len(c.Remove) > 0) {
+			rollback()
+			return fmt.Errorf("%w: %s", ErrPoolNotFound, c.Pool)
+		}
+		if c.Delete {
+			delete(ps.managed, c.Pool)
+			continue
+		}
+		ps.managed[c.Pool] = applyPoolChange(members, c)
+	}
+
+	ps.generation++
+	if err := ps.save(); err != nil {
+		ps.generation--
+		rollback()
+		return err
+	}
+	return nil
+}
+
+// validateChange checks a change's pool name and IPs before anything is applied.
+func (ps *poolStore) validateChange(c PoolChange) error {
+	if err := config.ValidatePoolName(c.Pool); err != nil {
+		return fmt.Errorf("%w: %v", ErrInvalidPool, err)
+	}
+	if c.Delete && (len(c.Add) > 0 || len(c.Remove) > 0) {
+		return fmt.Errorf("%w: %s: delete cannot be combined with add or remove", ErrInvalidPool, c.Pool)
+	}
+	for _, ip := range c.Add {
+		if !ps.ips[ip] {
+			return fmt.Errorf("%w: %s is not an outbound IP", ErrInvalidPool, ip)
+		}
+	}
+	return nil
+}
+
+// applyPoolChange returns members with c's IPs added and removed. members is
+// not modified, so it can be restored if the update fails.
+func applyPoolChange(members []string, c PoolChange) []string {
+	in := make(map[string]bool, len(members)+len(c.Add))
+	out := make([]string, 0, len(members)+len(c.Add))
+	for _, ip := range members {
+		in[ip] = true
+		out = append(out, ip)
+	}
+	for _, ip := range c.Add {
+		if !in[ip] {
+			in[ip] = true
+			out = append(out, ip)
+		}
+	}
+	if len(c.Remove) == 0 {
+		return out
+	}
+	drop := make(map[string]bool, len(c.Remove))
+	for _, ip := range c.Remove {
+		drop[ip] = true
+	}
+	return slices.DeleteFunc(out, func(ip string) bool { return drop[ip] })
+}
+
+// restore puts back a managed pool after a change failed to persist.
+// Must be called with mu held.
+func (ps *poolStore) restore(name string, ips []string, existed bool) {
+	if existed {
+		ps.managed[name] = ips
+	} else {
+		delete(ps.managed, name)
+	}
+}
+
+// save writes the managed pools to path, replacing the file atomically.
+// Must be called with mu held.
+func (ps *poolStore) save() error {
+	if ps.path == "" {
+		return nil
+	}
+	doc := struct {
+		Generation uint64        `yaml:"generation"`
+		Pools      []config.Pool `yaml:"pools"`
+	}{Generation: ps.generation}
+	for name, ips := range ps.managed {
+		doc.Pools = append(doc.Pools, config.Pool{Name: name, IPs: ips})
+	}
+	sort.Slice(doc.Pools, func(i, j int) bool { return doc.Pools[i].Name < doc.Pools[j].Name })
+	data, err := yaml.Marshal(doc)
+	if err != nil {
+		return err
+	}
+
+	tmp, err := os.CreateTemp(filepath.Dir(ps.path), ".pools-*")
+	if err != nil {
+		return err
+	}
+	defer os.Remove(tmp.Name())
+	if _, err := tmp.Write(data); err != nil {
+		tmp.Close()
+		return err
+	}
+	if err := tmp.Close(); err != nil {
+		return err
+	}
+	return os.Rename(tmp.Name(), ps.path)
+}
+
+// load reads the managed pools from path. Pools that clash with the config
+// file or hold IPs that are no longer configured are skipped.
+func (ps *poolStore) load() error {
+	data, err := os.ReadFile(ps.path)
+	if err != nil {
+		return err
+	}
+	var doc struct {
+		Generation uint64        `yaml:"generation"`
+		Pools      []config.Pool `yaml:"pools"`
+	}
+	if err := yaml.Unmarshal(data, &doc); err != nil {
+		return err
+	}
+	ps.generation = doc.Generation
+	for _, p := range doc.Pools {
+		if _, ok := ps.configured[p.Name]; ok || config.ValidatePoolName(p.Name) != nil {
+			log.Warn("pools_load_skipped", "path", ps.path, "pool", p.Name)
+			continue
+		}
+		ps.managed[p.Name] = slices.DeleteFunc(p.IPs, func(ip string) bool {
+			if ps.ips[ip] {
+				return false
+			}
+			log.Warn("pools_load_ip_skipped", "path", ps.path, "pool", p.Name, "ip", ip)
+			return true
+		})
+	}
+	return nil
+}
+
+// destinationPoolIPs returns the outbound IPs a destination pool selects.
+func (s *Server) destinationPoolIPs(p config.DestinationPool) []string {
+	switch {
+	case p.Pool != "":
+		ips, _ := s.pools.get(p.Pool)
+		return ips
+	case p.Country != "":
+		return s.cfg.IPsForCountry(p.Country)
+	}
+	return p.IPs
+}
+
+// userPool returns the pool restricting the tenant's connections. A pool
+// named by the tenant's bearer token or pool header takes precedence over
+// user_pools.
+func (s *Server) userPool(hints RoutingHints) (config.UserPool, bool) {
+	if hints.Pool != "" {
+		return config.UserPool{User: hints.Tenant, Pool: hints.Pool}, true
+	}
+	return s.cfg.PoolForUser(hints.Tenant)
+}
+
+// poolHeaderTrusted reports whether the client at clientIP may choose its pool
+// with the pool header. No client is trusted when pool_header_trusted is empty.
+func (s *Server) poolHeaderTrusted(clientIP string) bool {
+	ip := net.ParseIP(clientIP)
+	return ip != nil && netutil.ContainsIP(s.poolHeaderClients, ip)
+}
+
+// headerPoolAllowed reports whether tenant may choose pool with the pool
+// header. A tenant listed in user_pools may only narrow its own pool.
+func (s *Server) headerPoolAllowed(tenant, pool string) bool {
+	own, ok := s.cfg.PoolForUser(tenant)
+	if !ok || own.Pool == pool {
+		return true
+	}
+	allowed := s.userPoolIPs(own)
+	ips, _ := s.pools.get(pool)
+	for _, ip := range ips {
+		if !slices.Contains(allowed, ip) {
+			return false
+		}
+	}
+	return true
+}
+
+// userPoolIPs returns the outbound IPs a user pool selects.
+func (s *Server) userPoolIPs(p config.UserPool) []string {
+	if p.Pool != "" {
+		ips, _ := s.pools.get(p.Pool)
+		return ips
+	}
+	return p.IPs
+}
+
+// PoolHandler returns the admin handler for named pools.
+// GET lists pools with their generation, POST ?name=X[&ips=A,B] creates pool X
+// and adds the IPs to it, and DELETE ?name=X[&ips=A,B] removes the IPs, or the
+// whole pool if none are given. PATCH applies a PoolUpdate body as one step, so
+// large pools can be changed incrementally without resubmitting them. Only
+// pools created through this API can be changed.
+func (s *Server) PoolHandler() http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		q := r.URL.Query()
+		name := q.Get("name")
+		var ips []string
+		for _, ip := range strings.Split(q.Get("ips"), ",") {
+			if ip = strings.TrimSpace(ip); ip != "" {
+				ips = append(ips, ip)
+			}
+		}
+
+		switch r.Method {
+		case http.MethodGet:
+			// Read the generation first, so a client never pairs it with an
+			// older list; a newer list only makes a conditional update fail.
+			generation := s.pools.currentGeneration()
+			writeJSON(w, http.StatusOK, map[string]any{"generation": generation, "pools": s.pools.list()})
+		case http.MethodPost:
+			p, err := s.pools.add(name, ips)
+			if err != nil {
+				writePoolError(w, err)
+				return
+			}
+			log.Info("pool_updated", "pool", name, "added", ips)
+			writeJSON(w, http.StatusOK, p)
+		case http.MethodDelete:
+			if err := s.pools.remove(name, ips); err != nil {
+				writePoolError(w, err)
+				return
+			}
+			log.Info("pool_updated", "pool", name, "removed", ips)
+			w.WriteHeader(http.StatusNoContent)
+		case http.MethodPatch:
+			var update PoolUpdate
+			if err := json.NewDecoder(http.MaxBytesReader(w, r.Body, maxPoolPatchBody)).Decode(&update); err != nil {
+				http.Error(w, fmt.Sprintf("%v: %v", ErrInvalidPool, err), http.StatusBadRequest)
+				return
+			}
+			generation, err := s.pools.update(update.Changes, update.IfGeneration)
+			if err != nil {
+				w.Header().Set("X-Pool-Generation", strconv.FormatUint(generation, 10))
+				writePoolError(w, err)
+				return
+			}
+			log.Info("pools_patched", "changes", len(update.Changes), "generation", generation)
+			writeJSON(w, http.StatusOK, map[string]any{"generation": generation})
+		default:
+			w.Header().Set("Allow", "GET, POST, DELETE, PATCH")
+			http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+		}
+	})
+}
+
+// writePoolError writes the response for a failed pool change.
+func writePoolError(w http.ResponseWriter, err error) {
+	switch {
+	case errors.Is(err, ErrInvalidPool):
+		http.Error(w, err.Error(), http.StatusBadRequest)
+	case errors.Is(err, ErrPoolNotFound):
+		http.Error(w, err.Error(), http.StatusNotFound)
+	case errors.Is(err, ErrPoolConfigured):
+		http.Error(w, err.Error(), http.StatusConflict)
+	case errors.Is(err, ErrGenerationMismatch):
+		http.Error(w, err.Error(), http.StatusPreconditionFailed)
+	default:
+		log.Warn("pools_write_failed", "error", err)
+		http.Error(w, "failed to persist pools", http.StatusInternalServerError)
+	}
+}
@@ -0,0 +1,280 @@
+package proxy
+
+import (
+	"encoding/base64"
+	"encoding/json"
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"path/filepath"
+	"slices"
+	"strings"
+	"testing"
+
+	"github.com/cr0hn/outbound-lb/internal/auth"
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+func TestServer_PoolHandler(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	server.pools = newPoolStore([]config.Pool{{Name: "static", IPs: []string{"127.0.0.1"}}}, server.cfg.IPs, "")
+	handler := server.PoolHandler()
+
+	tests := []struct {
+		method string
+		query  string
+		want   int
+	}{
+		{http.MethodPost, "name=bad%20name&ips=127.0.0.1", http.StatusBadRequest},
+		{http.MethodPost, "name=customer-a&ips=10.0.0.1", http.StatusBadRequest},
+		{http.MethodPost, "name=static&ips=127.0.0.2", http.StatusConflict},
+		{http.MethodPost, "name=customer-a", http.StatusOK},
+		{http.MethodPost, "name=customer-a&ips=127.0.0.1,127.0.0.2", http.StatusOK},
+		{http.MethodDelete, "name=customer-a&ips=127.0.0.1", http.StatusNoContent},
+		{http.MethodDelete, "name=static", http.StatusConflict},
+		{http.MethodDelete, "name=missing", http.StatusNotFound},
+		{http.MethodPut, "", http.StatusMethodNotAllowed},
+	}
+
+	for _, tt := range tests {
+		req := httptest.NewRequest(tt.method, "/admin/pools?"+tt.query, nil)
+		rec := httptest.NewRecorder()
+		handler.ServeHTTP(rec, req)
+		if rec.Code != tt.want {
+			t.Errorf("%s ?%s: status = %d, want %d", tt.method, tt.query, rec.Code, tt.want)
+		}
+	}
+
+	if ips, ok := server.pools.get("customer-a"); !ok || len(ips) != 1 || ips[0] != "127.0.0.2" {
+		t.Errorf("customer-a = %v, %v; want [127.0.0.2]", ips, ok)
+	}
+}
+
+func TestPoolStore_Persistence(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "pools.yaml")
+	ips := []string{"127.0.0.1", "127.0.0.2"}
+
+	ps := newPoolStore(nil, ips, path)
+	if _, err := ps.add("customer-a", []string{"127.0.0.1", "127.0.0.2"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	if _, err := ps.add("customer-b", []string{"127.0.0.2"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	if err := ps.remove("customer-b", nil); err != nil {
+		t.Fatalf("remove: %v", err)
+	}
+	if got := ps.currentGeneration(); got != 3 {
+		t.Fatalf("generation = %d, want 3", got)
+	}
+
+	// A restart with 127.0.0.2 no longer configured keeps the rest of the pool.
+	restored := newPoolStore(nil, []string{"127.0.0.1"}, path)
+	pools := restored.list()
+	if len(pools) != 1 || pools[0].Name != "customer-a" || len(pools[0].IPs) != 1 || pools[0].Source != "admin" {
+		t.Errorf("restored pools = %+v, want customer-a with 127.0.0.1", pools)
+	}
+	if got := restored.currentGeneration(); got != 3 {
+		t.Errorf("restored generation = %d, want 3", got)
+	}
+}
+
+func TestServer_PoolHandlerPatch(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	server.pools = newPoolStore([]config.Pool{{Name: "static", IPs: []string{"127.0.0.1"}}}, server.cfg.IPs, "")
+	handler := server.PoolHandler()
+	if _, err := server.pools.add("customer-a", []string{"127.0.0.1", "127.0.0.2"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+
+	tests := []struct {
+		name string
+		body string
+		want int
+	}{
+		{"malformed", `{"changes":`, http.StatusBadRequest},
+		{"no changes", `{"changes":[]}`, http.StatusBadRequest},
+		{"stale generation", `{"if_generation":0,"changes":[{"pool":"customer-a","add":["127.0.0.3"]}]}`, http.StatusPreconditionFailed},
+		{"unknown ip", `{"changes":[{"pool":"customer-b","add":["10.0.0.1"]}]}`, http.StatusBadRequest},
+		// customer-b must not be created when a later change fails
+		{"configured pool", `{"changes":[{"pool":"customer-b","add":["127.0.0.3"]},{"pool":"static","remove":["127.0.0.1"]}]}`, http.StatusConflict},
+		{"missing pool", `{"changes":[{"pool":"missing","delete":true}]}`, http.StatusNotFound},
+		{"batch", `{"if_generation":1,"changes":[{"pool":"customer-a","add":["127.0.0.3"],"remove":["127.0.0.1"]},{"pool":"customer-b","add":["127.0.0.1"]}]}`, http.StatusOK},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			req := httptest.NewRequest(http.MethodPatch, "/admin/pools", strings.NewReader(tt.body))
+			rec := httptest.NewRecorder()
+			handler.ServeHTTP(rec, req)
+			if rec.Code != tt.want {
+				t.Errorf("status = %d, want %d: %s", rec.Code, tt.want, rec.Body.String())
+			}
+		})
+	}
+
+	if ips, _ := server.pools.get("customer-a"); !slices.Equal(ips, []string{"127.0.0.2", "127.0.0.3"}) {
+		t.Errorf("customer-a = %v, want [127.0.0.2 127.0.0.3]", ips)
+	}
+	if ips, _ := server.pools.get("customer-b"); !slices.Equal(ips, []string{"127.0.0.1"}) {
+		t.Errorf("customer-b = %v, want [127.0.0.1]", ips)
+	}
+
+	rec := httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodGet, "/admin/pools", nil))
+	var listed struct {
+		Generation uint64     `json:"generation"`
+		Pools      []PoolInfo `json:"pools"`
+	}
+	if err := json.NewDecoder(rec.Body).Decode(&listed); err != nil {
+		t.Fatalf("failed to decode pools: %v", err)
+	}
+	if listed.Generation != 2 || len(listed.Pools) != 3 {
+		t.Errorf("got generation %d with %d pools, want generation 2 with 3 pools", listed.Generation, len(listed.Pools))
+	}
+}
+
+func TestSelectIPForRequest_NamedDestinationPool(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	server.cfg.DestinationPools = []config.DestinationPool{{CIDR: "203.0.113.0/24", Pool: "partners"}}
+	req := httptest.NewRequest(http.MethodGet, "http://203.0.113.9/", nil)
+
+	if _, _, err := server.selectIPForRequest(req, "203.0.113.9:443"); !errors.Is(err, ErrPoolEmpty) {
+		t.Fatalf("expected ErrPoolEmpty before the pool exists, got %v", err)
+	}
+	if _, err := server.pools.add("partners", []string{"127.0.0.2"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	for i := 0; i < 3; i++ {
+		ip, _, err := server.selectIPForRequest(req, "203.0.113.9:443")
+		if err != nil || ip != "127.0.0.2" {
+			t.Fatalf("got %s, %v; want 127.0.0.2", ip, err)
+		}
+		server.balancer.Record("203.0.113.9:443", ip)
+	}
+}
+
+func TestSelectIPForRequest_UserPool(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	server.cfg.UserPools = []config.UserPool{
+		{User: "alice", Pool: "residential"},
+		{User: "batch", IPs: []string{"127.0.0.1"}},
+	}
+	requestAs := func(user string) *http.Request {
+		req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+		req.Header.Set("Proxy-Authorization", "Basic "+base64.StdEncoding.EncodeToString([]byte(user+":secret")))
+		return req
+	}
+
+	if _, _, err := server.selectIPForRequest(requestAs("alice"), "example.com"); !errors.Is(err, ErrPoolEmpty) {
+		t.Fatalf("expected ErrPoolEmpty before the pool exists, got %v", err)
+	}
+	if _, err := server.pools.add("residential", []string{"127.0.0.2", "127.0.0.3"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+
+	tests := []struct {
+		user    string
+		allowed []string
+		key     string
+	}{
+		{"alice", []string{"127.0.0.2", "127.0.0.3"}, "user/alice"},
+		{"batch", []string{"127.0.0.1"}, "user/batch"},
+		{"carol", []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"}, "example.com"},
+	}
+	for _, tt := range tests {
+		t.Run(tt.user, func(t *testing.T) {
+			req := requestAs(tt.user)
+			for i := 0; i < 4; i++ {
+				ip, prov, err := server.selectIPForRequest(req, "example.com")
+				if err != nil {
+					t.Fatalf("unexpected error: %v", err)
+				}
+				if !slices.Contains(tt.allowed, ip) {
+					t.Errorf("got %s, want one of %v", ip, tt.allowed)
+				}
+				if prov.key != tt.key {
+					t.Errorf("affinity key = %q, want %q", prov.key, tt.key)
+				}
+				server.balancer.Record("example.com", ip)
+			}
+		})
+	}
+
+	// A country hint narrows the user's pool further
+	server.cfg.Backends = []config.BackendConfig{{IP: "127.0.0.1", Country: "de"}, {IP: "127.0.0.3", Country: "de"}}
+	ip, _, err := server.selectIPForRequest(requestAs("alice-country-de"), "example.com")
+	if err != nil || ip != "127.0.0.3" {
+		t.Errorf("got %s, %v; want 127.0.0.3", ip, err)
+	}
+}
+
+func TestSelectIPForRequest_PoolHeader(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	server.cfg.PoolHeader = "X-Outbound-Pool"
+	if _, err := server.pools.add("residential-eu", []string{"127.0.0.3"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req.Header.Set("X-Outbound-Pool", "residential-eu")
+
+	// With no trusted clients configured, nobody can choose a pool
+	if hints := server.routingHints(req); hints.Pool != "" {
+		t.Errorf("expected the header to be ignored without pool_header_trusted, got pool %q", hints.Pool)
+	}
+
+	server.poolHeaderClients, _ = netutil.ParseCIDRs([]string{"192.0.2.0/24"})
+	for i := 0; i < 3; i++ {
+		ip, _, err := server.selectIPForRequest(req, "example.com")
+		if err != nil || ip != "127.0.0.3" {
+			t.Fatalf("got %s, %v; want 127.0.0.3", ip, err)
+		}
+		server.balancer.Record("example.com", ip)
+	}
+	if !slices.Contains(server.routingHeaders(), "X-Outbound-Pool") {
+		t.Error("expected the pool header to be stripped before forwarding")
+	}
+
+	// Clients outside the trusted list are balanced as if the header were absent
+	server.poolHeaderClients, _ = netutil.ParseCIDRs([]string{"10.0.0.0/8"})
+	seen := map[string]bool{}
+	for i := 0; i < 3; i++ {
+		ip, _, err := server.selectIPForRequest(req, "example.com")
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		seen[ip] = true
+		server.balancer.Record("example.com", ip)
+	}
+	if len(seen) < 2 {
+		t.Errorf("expected an untrusted client to rotate outside the pool, got %v", seen)
+	}
+
+	// A pool from the client's identity wins over the header
+	server.poolHeaderClients, _ = netutil.ParseCIDRs([]string{"192.0.2.0/24"})
+	withID := req.WithContext(contextWithIdentity(req.Context(), auth.Identity{User: "alice", Pool: "premium"}))
+	if hints := server.routingHints(withID); hints.Pool != "premium" {
+		t.Errorf("pool = %q, want the identity's premium", hints.Pool)
+	}
+
+	// A user_pools user can only pick pools inside its own
+	if _, err := server.pools.add("batch", []string{"127.0.0.1", "127.0.0.2"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	if _, err := server.pools.add("batch-a", []string{"127.0.0.2"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	server.cfg.UserPools = []config.UserPool{{User: "bob", Pool: "batch"}}
+	asBob := func(pool string) RoutingHints {
+		r := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+		r.Header.Set("Proxy-Authorization", "Basic "+base64.StdEncoding.EncodeToString([]byte("bob:secret")))
+		r.Header.Set("X-Outbound-Pool", pool)
+		return server.routingHints(r)
+	}
+	if hints := asBob("batch-a"); hints.Pool != "batch-a" {
+		t.Errorf("pool = %q, want batch-a within bob's pool", hints.Pool)
+	}
+	if hints := asBob("residential-eu"); hints.Pool != "" {
+		t.Errorf("pool = %q, want the header rejected outside bob's pool", hints.Pool)
+	}
+}
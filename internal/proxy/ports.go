@@ -0,0 +1,36 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"errors"
+	"fmt"
+	"net"
+	"strconv"
+)
+
+// ErrPortNotAllowed is returned when a CONNECT or SOCKS CONNECT targets a port
+// outside connect_ports.
+var ErrPortNotAllowed = errors.New("destination port not allowed")
+
+// checkConnectPort applies the CONNECT port policy to a host:port target of
+// an HTTP or SOCKS CONNECT.
+// Targets without a valid port are refused while the policy is set.
+func (s *Server) checkConnectPort(host string) error {
+	if len(s.connectPorts) == 0 {
+		return nil
+	}
+	_, p, err := net.SplitHostPort(host)
+	if err != nil {
+		return fmt.Errorf("%w: %s", ErrPortNotAllowed, host)
+	}
+	port, err := strconv.Atoi(p)
+	if err != nil {
+		return fmt.Errorf("%w: %s", ErrPortNotAllowed, p)
+	}
+	for _, r := range s.connectPorts {
+		if r.Contains(port) {
+			return nil
+		}
+	}
+	return fmt.Errorf("%w: %d", ErrPortNotAllowed, port)
+}
@@ -0,0 +1,72 @@
+package proxy
+
+import (
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+func TestCheckConnectPort(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.connectPorts = []config.PortRange{{From: 443, To: 443}, {From: 8000, To: 8100}}
+
+	tests := []struct {
+		host string
+		want bool
+	}{
+		{"example.com:443", true},
+		{"[2001:db8::1]:443", true},
+		{"example.com:8050", true},
+		{"example.com:25", false},
+		{"example.com:8101", false},
+		{"example.com", false},
+	}
+	for _, tt := range tests {
+		err := server.checkConnectPort(tt.host)
+		if tt.want && err != nil {
+			t.Errorf("checkConnectPort(%q) error = %v", tt.host, err)
+		}
+		if !tt.want && !errors.Is(err, ErrPortNotAllowed) {
+			t.Errorf("checkConnectPort(%q) error = %v, want ErrPortNotAllowed", tt.host, err)
+		}
+	}
+
+	server.connectPorts = nil
+	if err := server.checkConnectPort("example.com:25"); err != nil {
+		t.Errorf("expected an empty policy to allow every port, got %v", err)
+	}
+}
+
+func TestConnectHandler_PortPolicyRejects(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.connectPorts = []config.PortRange{{From: 443, To: 443}}
+
+	r := httptest.NewRequest(http.MethodConnect, "http://mail.example.com:25", nil)
+	r.Host = "mail.example.com:25"
+	w := httptest.NewRecorder()
+	server.connectHandler.ServeHTTP(w, r)
+
+	if w.Code != http.StatusForbidden {
+		t.Errorf("status = %d, want 403", w.Code)
+	}
+}
+
+func TestSOCKS5_PortPolicyRejects(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.connectPorts = []config.PortRange{{From: 443, To: 443}}
+	l := startTestSOCKS5(t, server)
+
+	conn, code := socks5Connect(t, l.Addr().String(), "", "", strings.TrimPrefix(backend.URL, "http://"))
+	defer conn.Close()
+
+	if code != socks5ReplyNotAllowed {
+		t.Errorf("expected not-allowed reply, got %d", code)
+	}
+}
@@ -0,0 +1,142 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// tunnelProgress is one progress report of a long tunnel.
+type tunnelProgress struct {
+	id        uint64
+	host      string
+	ip        string
+	duration  time.Duration
+	bytesUp   int64
+	bytesDown int64
+	// upRate and downRate are bytes per second since the previous report.
+	upRate   float64
+	downRate float64
+}
+
+// progressMark is what a tunnel had relayed when it was last reported.
+type progressMark struct {
+	at        time.Time
+	bytesUp   int64
+	bytesDown int64
+}
+
+// progressMonitor periodically reports the bytes relayed and throughput of
+// tunnels that have been open for at least one interval and relayed at least
+// minBytes, so large transfers are visible while still in flight.
+type progressMonitor struct {
+	tunnels  *tunnelRegistry
+	interval time.Duration
+	minBytes int64
+	marks    map[uint64]progressMark
+	stopCh   chan struct{}
+	stopOnce sync.Once
+	wg       sync.WaitGroup
+}
+
+// newProgressMonitor creates a progressMonitor reporting every interval.
+func newProgressMonitor(tunnels *tunnelRegistry, interval time.Duration, minBytes int) *progressMonitor {
+	return &progressMonitor{
+		tunnels:  tunnels,
+		interval: interval,
+		minBytes: int64(minBytes),
+		marks:    make(map[uint64]progressMark),
+		stopCh:   make(chan struct{}),
+	}
+}
+
+// Start starts the reporting goroutine.
+func (m *progressMonitor) Start() {
+	log.Info("tunnel_progress_started", "interval", m.interval, "min_bytes", m.minBytes)
+
+	m.wg.Add(1)
+	go func() {
+		defer m.wg.Done()
+		ticker := time.NewTicker(m.interval)
+		defer ticker.Stop()
+		for {
+			select {
+			case now := <-ticker.C:
+				for _, p := range m.check(now) {
+					log.Info("tunnel_progress",
+						"tunnel_id", p.id,
+						"host", p.host,
+						"ip", p.ip,
+						"duration", p.duration,
+						"bytes_sent", p.bytesUp,
+						"bytes_received", p.bytesDown,
+						"send_bytes_per_second", p.upRate,
+						"receive_bytes_per_second", p.downRate,
+					)
+				}
+			case <-m.stopCh:
+				return
+			}
+		}
+	}()
+}
+
+// Stop stops the reporting goroutine.
+func (m *progressMonitor) Stop() {
+	m.stopOnce.Do(func() { close(m.stopCh) })
+	m.wg.Wait()
+}
+
+// check returns a progress report for every tunnel over the thresholds and
+// updates the long tunnel metrics.
+func (m *progressMonitor) check(now time.Time) []tunnelProgress {
+	var reports []tunnelProgress
+	var upRate, downRate float64
+	seen := make(map[uint64]bool)
+
+	for _, e := range m.tunnels.snapshot() {
+		seen[e.id] = true
+		duration := now.Sub(e.started)
+		up, down := e.bytesUp.Load(), e.bytesDown.Load()
+		if duration < m.interval || up+down < m.minBytes {
+			continue
+		}
+
+		mark, ok := m.marks[e.id]
+		if !ok {
+			mark = progressMark{at: e.started}
+		}
+		p := tunnelProgress{
+			id:        e.id,
+			host:      e.host,
+			ip:        e.ip,
+			duration:  duration,
+			bytesUp:   up,
+			bytesDown: down,
+		}
+		if elapsed := now.Sub(mark.at).Seconds(); elapsed > 0 {
+			p.upRate = float64(up-mark.bytesUp) / elapsed
+			p.downRate = float64(down-mark.bytesDown) / elapsed
+		}
+		m.marks[e.id] = progressMark{at: now, bytesUp: up, bytesDown: down}
+
+		upRate += p.upRate
+		downRate += p.downRate
+		reports = append(reports, p)
+	}
+
+	// Forget tunnels that have closed
+	for id := range m.marks {
+		if !seen[id] {
+			delete(m.marks, id)
+		}
+	}
+
+	metrics.TunnelProgressEvents.Add(float64(len(reports)))
+	metrics.LongTunnels.Set(float64(len(reports)))
+	metrics.LongTunnelThroughput.WithLabelValues("up").Set(upRate)
+	metrics.LongTunnelThroughput.WithLabelValues("down").Set(downRate)
+	return reports
+}
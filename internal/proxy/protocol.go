@@ -0,0 +1,23 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import "net/http"
+
+// upstreamHTTP1 reports whether requests to host must use HTTP/1.1. A host on
+// http1_destinations always does; with upstream_http1 every host does unless
+// it is on http2_destinations.
+func (s *Server) upstreamHTTP1(host string) bool {
+	name := destinationName(host)
+	if destinationMatches(s.cfg.HTTP1Destinations, name) {
+		return true
+	}
+	return s.cfg.UpstreamHTTP1 && !destinationMatches(s.cfg.HTTP2Destinations, name)
+}
+
+// upstreamTransport returns the transport bound to ip with the protocol chosen for host.
+func (s *Server) upstreamTransport(ip, host string) *http.Transport {
+	if s.upstreamHTTP1(host) {
+		return s.transportPool.GetHTTP1(ip)
+	}
+	return s.transportPool.Get(ip)
+}
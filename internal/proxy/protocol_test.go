@@ -0,0 +1,31 @@
+package proxy
+
+import (
+	"testing"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+func TestUpstreamHTTP1(t *testing.T) {
+	tests := []struct {
+		name string
+		cfg  config.Config
+		host string
+		want bool
+	}{
+		{"default", config.Config{}, "api.example.com:443", false},
+		{"listed http1", config.Config{HTTP1Destinations: []string{"*.example.com"}}, "API.example.com.:443", true},
+		{"unlisted host", config.Config{HTTP1Destinations: []string{"*.example.com"}}, "example.org", false},
+		{"global http1", config.Config{UpstreamHTTP1: true}, "example.org", true},
+		{"global http1 with http2 exception", config.Config{UpstreamHTTP1: true, HTTP2Destinations: []string{"cdn.example.com"}}, "cdn.example.com:443", false},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			s := &Server{cfg: &tt.cfg}
+			if got := s.upstreamHTTP1(tt.host); got != tt.want {
+				t.Errorf("upstreamHTTP1(%q) = %v, want %v", tt.host, got, tt.want)
+			}
+		})
+	}
+}
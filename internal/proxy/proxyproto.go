@@ -0,0 +1,216 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"bufio"
+	"bytes"
+	"encoding/binary"
+	"errors"
+	"fmt"
+	"io"
+	"net"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// PROXY protocol constants (https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt).
+const (
+	proxyV1Prefix    = "PROXY "
+	proxyV1MaxLength = 107
+	proxyV2CmdLocal  = 0x0
+	proxyV2CmdProxy  = 0x1
+	proxyV2FamTCP4   = 0x11
+	proxyV2FamTCP6   = 0x21
+)
+
+// proxyV2Signature starts every PROXY protocol v2 header.
+var proxyV2Signature = []byte("\r\n\r\n\x00\r\nQUIT\n")
+
+// errProxyHeader is returned when a connection does not start with a valid PROXY header.
+var errProxyHeader = errors.New("invalid proxy protocol header")
+
+// proxyProtoListener wraps accepted connections so their remote address is
+// taken from the PROXY protocol header sent by a downstream load balancer.
+type proxyProtoListener struct {
+	net.Listener
+	trusted []*net.IPNet
+	timeout time.Duration
+}
+
+// newProxyProtoListener wraps l. Connections from sources outside trusted are
+// passed through untouched; an empty trusted list requires the header from all sources.
+func newProxyProtoListener(l net.Listener, trusted []string, timeout time.Duration) *proxyProtoListener {
+	nets, _ := netutil.ParseCIDRs(trusted) // validated with the config
+	return &proxyProtoListener{Listener: l, trusted: nets, timeout: timeout}
+}
+
+// Accept waits for the next connection. The header is read lazily on the
+// connection's goroutine so a slow client cannot stall the accept loop.
+func (l *proxyProtoListener) Accept() (net.Conn, error) {
+	conn, err := l.Listener.Accept()
+	if err != nil {
+		return nil, err
+	}
+	if len(l.trusted) > 0 {
+		tcpAddr, ok := conn.RemoteAddr().(*net.TCPAddr)
+		if !ok || !netutil.ContainsIP(l.trusted, tcpAddr.IP) {
+			return conn, nil
+		}
+	}
+	return &proxyProtoConn{Conn: conn, r: bufio.NewReader(conn), timeout: l.timeout}, nil
+}
+
+// proxyProtoConn is a connection whose PROXY header is consumed before the first read.
+type proxyProtoConn struct {
+	net.Conn
+	r       *bufio.Reader
+	timeout time.Duration
+	once    sync.Once
+	remote  net.Addr
+	err     error
+	// acl, if set, refuses the connection by the client address in the header.
+	acl *clientACL
+}
+
+// init reads the PROXY header once.
+func (c *proxyProtoConn) init() {
+	c.once.Do(func() {
+		c.Conn.SetReadDeadline(time.Now().Add(c.timeout))
+		c.remote, c.err = readProxyHeader(c.r)
+		c.Conn.SetReadDeadline(time.Time{})
+		if c.err != nil {
+			log.Debug("proxy_protocol_rejected", "remote", c.Conn.RemoteAddr(), "error", c.err)
+		}
+		if c.remote == nil {
+			c.remote = c.Conn.RemoteAddr()
+		}
+		if c.err == nil && !c.acl.allowed(c.remote) {
+			c.acl.refused(c.remote)
+			c.err = errClientDenied
+		}
+	})
+}
+
+// Read reads from the connection after the PROXY header.
+func (c *proxyProtoConn) Read(p []byte) (int, error) {
+	c.init()
+	if c.err != nil {
+		return 0, c.err
+	}
+	return c.r.Read(p)
+}
+
+// RemoteAddr returns the client address from the PROXY header.
+func (c *proxyProtoConn) RemoteAddr() net.Addr {
+	c.init()
+	return c.remote
+}
+
+// CloseWrite half-closes the underlying connection if supported.
+func (c *proxyProtoConn) CloseWrite() error {
+	if cw, ok := c.Conn.(closeWriter); ok {
+		return cw.CloseWrite()
+	}
+	return nil
+}
+
+// readProxyHeader reads a v1 or v2 PROXY header. A nil address with a nil
+// error means the header carried no client address (UNKNOWN or LOCAL).
+func readProxyHeader(r *bufio.Reader) (net.Addr, error) {
+	sig, err := r.Peek(len(proxyV2Signature))
+	if err == nil && bytes.Equal(sig, proxyV2Signature) {
+		return readProxyV2(r)
+	}
+	prefix, err := r.Peek(len(proxyV1Prefix))
+	if err != nil {
+		return nil, err
+	}
+	if string(prefix) != proxyV1Prefix {
+		return nil, errProxyHeader
+	}
+	return readProxyV1(r)
+}
+
+// readProxyV1 parses a text header such as "PROXY TCP4 1.2.3.4 5.6.7.8 1111 2222\r\n".
+func readProxyV1(r *bufio.Reader) (net.Addr, error) {
+	var line []byte
+	for len(line) < proxyV1MaxLength {
+		b, err := r.ReadByte()
+		if err != nil {
+			return nil, err
+		}
+		line = append(line, b)
+		if b == '\n' {
+			break
+		}
+	}
+	if !bytes.HasSuffix(line, []byte("\r\n")) {
+		return nil, fmt.Errorf("%w: v1 header not terminated", errProxyHeader)
+	}
+
+	fields := strings.Fields(string(line[:len(line)-2]))
+	if len(fields) >= 2 && fields[1] == "UNKNOWN" {
+		return nil, nil
+	}
+	if len(fields) != 6 || (fields[1] != "TCP4" && fields[1] != "TCP6") {
+		return nil, fmt.Errorf("%w: malformed v1 header", errProxyHeader)
+	}
+	ip := net.ParseIP(fields[2])
+	port, err := strconv.Atoi(fields[4])
+	if ip == nil || err != nil || port < 0 || port > 65535 {
+		return nil, fmt.Errorf("%w: malformed v1 source address", errProxyHeader)
+	}
+	return &net.TCPAddr{IP: ip, Port: port}, nil
+}
+
+// readProxyV2 parses a binary header.
+func readProxyV2(r *bufio.Reader) (net.Addr, error) {
+	header := make([]byte, 16)
+	if _, err := io.ReadFull(r, header); err != nil {
+		return nil, err
+	}
+	if header[12]>>4 != 2 {
+		return nil, fmt.Errorf("%w: unsupported v2 version", errProxyHeader)
+	}
+	cmd, family := header[12]&0x0F, header[13]
+	payload := make([]byte, binary.BigEndian.Uint16(header[14:16]))
+	if _, err := io.ReadFull(r, payload); err != nil {
+		return nil, err
+	}
+
+	switch cmd {
+	case proxyV2CmdLocal:
+		return nil, nil
+	case proxyV2CmdProxy:
+	default:
+		return nil, fmt.Errorf("%w: unsupported v2 command", errProxyHeader)
+	}
+
+	switch family {
+	case proxyV2FamTCP4:
+		if len(payload) < 12 {
+			return nil, fmt.Errorf("%w: short v2 address", errProxyHeader)
+		}
+		return &net.TCPAddr{IP: net.IP(payload[0:4]), Port: int(binary.BigEndian.Uint16(payload[8:10]))}, nil
+	case proxyV2FamTCP6:
+		if len(payload) < 36 {
+			return nil, fmt.Errorf("%w: short v2 address", errProxyHeader)
+		}
+		return &net.TCPAddr{IP: net.IP(payload[0:16]), Port: int(binary.BigEndian.Uint16(payload[32:34]))}, nil
+	default:
+		// UDP and unix sockets carry no usable client address for a TCP proxy
+		return nil, nil
+	}
+}
+
+// wrapListener applies listener-level options shared by the proxy and SOCKS listeners.
+func (s *Server) wrapListener(l net.Listener) net.Listener {
+	if s.cfg.ProxyProtocol {
+		l = newProxyProtoListener(l, s.cfg.ProxyProtocolTrusted, s.cfg.EffectiveHeaderReadTimeout())
+	}
+	return s.withClientACL(l)
+}
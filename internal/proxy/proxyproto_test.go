@@ -0,0 +1,126 @@
+package proxy
+
+import (
+	"bufio"
+	"encoding/binary"
+	"io"
+	"net"
+	"net/http"
+	"strings"
+	"testing"
+	"time"
+)
+
+// proxyV2Header builds a v2 PROXY header for a TCP4 source.
+func proxyV2Header(cmd byte, src string, srcPort uint16) []byte {
+	header := append([]byte(nil), proxyV2Signature...)
+	header = append(header, 0x20|cmd, proxyV2FamTCP4, 0, 12)
+	header = append(header, net.ParseIP(src).To4()...)
+	header = append(header, 10, 0, 0, 1)
+	header = binary.BigEndian.AppendUint16(header, srcPort)
+	return binary.BigEndian.AppendUint16(header, 8080)
+}
+
+func TestReadProxyHeader(t *testing.T) {
+	tests := []struct {
+		name    string
+		input   string
+		want    string
+		wantErr bool
+	}{
+		{"v1 tcp4", "PROXY TCP4 203.0.113.7 10.0.0.1 51234 8080\r\nGET", "203.0.113.7:51234", false},
+		{"v1 tcp6", "PROXY TCP6 2001:db8::7 2001:db8::1 51234 8080\r\nGET", "[2001:db8::7]:51234", false},
+		{"v1 unknown", "PROXY UNKNOWN\r\nGET", "", false},
+		{"v2 proxy", string(proxyV2Header(proxyV2CmdProxy, "203.0.113.7", 51234)) + "GET", "203.0.113.7:51234", false},
+		{"v2 local", string(proxyV2Header(proxyV2CmdLocal, "203.0.113.7", 51234)) + "GET", "", false},
+		{"missing header", "GET / HTTP/1.1\r\n\r\n", "", true},
+		{"v1 malformed", "PROXY TCP4 nope\r\nGET", "", true},
+		{"v1 unterminated", "PROXY TCP4 " + strings.Repeat("1", 200), "", true},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			r := bufio.NewReader(strings.NewReader(tt.input))
+			addr, err := readProxyHeader(r)
+			if (err != nil) != tt.wantErr {
+				t.Fatalf("readProxyHeader() error = %v, wantErr %v", err, tt.wantErr)
+			}
+			if tt.wantErr {
+				return
+			}
+			got := ""
+			if addr != nil {
+				got = addr.String()
+			}
+			if got != tt.want {
+				t.Errorf("address = %q, want %q", got, tt.want)
+			}
+			if rest, _ := io.ReadAll(r); string(rest) != "GET" {
+				t.Errorf("payload after header = %q, want GET", rest)
+			}
+		})
+	}
+}
+
+func TestServer_ProxyProtocolClientIP(t *testing.T) {
+	forwardedFor := make(chan string, 1)
+	backend := newTestBackendWithHandler(t, func(w http.ResponseWriter, r *http.Request) {
+		forwardedFor <- r.Header.Get("X-Forwarded-For")
+		w.WriteHeader(http.StatusOK)
+	})
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.ProxyProtocol = true
+
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	go server.Serve(l)
+	defer server.httpServer.Close()
+
+	conn, err := net.Dial("tcp", l.Addr().String())
+	if err != nil {
+		t.Fatalf("failed to dial proxy: %v", err)
+	}
+	defer conn.Close()
+	conn.SetDeadline(time.Now().Add(5 * time.Second))
+
+	conn.Write([]byte("PROXY TCP4 203.0.113.7 127.0.0.1 51234 8080\r\n"))
+	conn.Write([]byte("GET " + backend.URL + "/ HTTP/1.1\r\nHost: " + strings.TrimPrefix(backend.URL, "http://") + "\r\nConnection: close\r\n\r\n"))
+
+	resp, err := http.ReadResponse(bufio.NewReader(conn), nil)
+	if err != nil {
+		t.Fatalf("failed to read response: %v", err)
+	}
+	resp.Body.Close()
+
+	if got := <-forwardedFor; got != "203.0.113.7" {
+		t.Errorf("X-Forwarded-For = %q, want the client IP from the PROXY header", got)
+	}
+}
+
+func TestProxyProtoListener_Untrusted(t *testing.T) {
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	defer l.Close()
+	pl := newProxyProtoListener(l, []string{"10.0.0.0/8"}, time.Second)
+
+	go func() {
+		if conn, err := net.Dial("tcp", l.Addr().String()); err == nil {
+			conn.Close()
+		}
+	}()
+
+	conn, err := pl.Accept()
+	if err != nil {
+		t.Fatalf("accept failed: %v", err)
+	}
+	defer conn.Close()
+	if _, ok := conn.(*proxyProtoConn); ok {
+		t.Error("expected connections from untrusted sources to pass through unwrapped")
+	}
+}
@@ -0,0 +1,116 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"errors"
+	"fmt"
+	"html/template"
+	"net/http"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/activation"
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+)
+
+// Public status values, from best to worst.
+const (
+	publicStatusOperational = "operational"
+	publicStatusDegraded    = "degraded"
+	publicStatusDown        = "down"
+)
+
+// PublicStatus is the aggregate health shown on the public status page.
+// It deliberately omits IPs, hosts and traffic figures.
+type PublicStatus struct {
+	Status          string `json:"status"`
+	HealthyEgresses int    `json:"healthy_egresses"`
+	TotalEgresses   int    `json:"total_egresses"`
+	UptimeSeconds   int64  `json:"uptime_seconds"`
+}
+
+// publicStatusPage renders PublicStatus as a minimal HTML page.
+var publicStatusPage = template.Must(template.New("status").Parse(`<!DOCTYPE html>
+<html lang="en">
+<head><meta charset="utf-8"><meta http-equiv="refresh" content="30"><title>Status: {{.Status}}</title></head>
+<body>
+<h1>{{.Status}}</h1>
+<p>{{.HealthyEgresses}} of {{.TotalEgresses}} egresses healthy</p>
+<p>Up for {{.Uptime}}</p>
+</body>
+</html>
+`))
+
+// PublicStatus returns the aggregate health. health may be nil when health checks are disabled.
+func (s *Server) PublicStatus(health balancer.IPHealthChecker) PublicStatus {
+	status := PublicStatus{
+		TotalEgresses: len(s.cfg.IPs),
+		UptimeSeconds: int64(time.Since(s.started).Seconds()),
+	}
+	for _, ip := range s.cfg.IPs {
+		if health == nil || health.IsHealthy(ip) {
+			status.HealthyEgresses++
+		}
+	}
+	switch status.HealthyEgresses {
+	case status.TotalEgresses:
+		status.Status = publicStatusOperational
+	case 0:
+		status.Status = publicStatusDown
+	default:
+		status.Status = publicStatusDegraded
+	}
+	return status
+}
+
+// PublicStatusHandler returns an unauthenticated handler serving the aggregate
+// health as an HTML page at / and as JSON at /status.json. Responses may be
+// embedded in other sites and are never cached.
+func (s *Server) PublicStatusHandler(health balancer.IPHealthChecker) http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if r.Method != http.MethodGet && r.Method != http.MethodHead {
+			w.Header().Set("Allow", "GET, HEAD")
+			http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+			return
+		}
+		w.Header().Set("Access-Control-Allow-Origin", "*")
+		w.Header().Set("Cache-Control", "no-store")
+
+		status := s.PublicStatus(health)
+		switch r.URL.Path {
+		case "/status.json":
+			writeJSON(w, http.StatusOK, status)
+		case "/":
+			w.Header().Set("Content-Type", "text/html; charset=utf-8")
+			publicStatusPage.Execute(w, struct {
+				PublicStatus
+				Uptime time.Duration
+			}{status, time.Duration(status.UptimeSeconds) * time.Second})
+		default:
+			http.NotFound(w, r)
+		}
+	})
+}
+
+// StartPublicStatus serves the public status page on the configured port.
+// Blocks until the listener is closed by Shutdown.
+func (s *Server) StartPublicStatus(health balancer.IPHealthChecker) error {
+	l, err := s.listen(activation.Status, fmt.Sprintf(":%d", s.cfg.PublicStatusPort), tcpListen)
+	if err != nil {
+		return err
+	}
+
+	srv := &http.Server{
+		Handler:      s.PublicStatusHandler(health),
+		ReadTimeout:  5 * time.Second,
+		WriteTimeout: 10 * time.Second,
+	}
+	s.mu.Lock()
+	s.publicStatusServer = srv
+	s.mu.Unlock()
+
+	log.Info("starting public status page", "port", s.cfg.PublicStatusPort)
+	if err := srv.Serve(l); !errors.Is(err, http.ErrServerClosed) {
+		return err
+	}
+	return nil
+}
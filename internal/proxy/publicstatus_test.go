@@ -0,0 +1,78 @@
+package proxy
+
+import (
+	"encoding/json"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+)
+
+func TestServer_PublicStatus(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+
+	tests := []struct {
+		name    string
+		health  fakeHealth
+		status  string
+		healthy int
+	}{
+		{"all healthy", fakeHealth{}, publicStatusOperational, 2},
+		{"one unhealthy", fakeHealth{"127.0.0.2": true}, publicStatusDegraded, 1},
+		{"all unhealthy", fakeHealth{"127.0.0.1": true, "127.0.0.2": true}, publicStatusDown, 0},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			got := server.PublicStatus(tt.health)
+			if got.Status != tt.status || got.HealthyEgresses != tt.healthy || got.TotalEgresses != 2 {
+				t.Errorf("PublicStatus = %+v, want %s with %d of 2 healthy", got, tt.status, tt.healthy)
+			}
+		})
+	}
+
+	if got := server.PublicStatus(nil); got.Status != publicStatusOperational {
+		t.Errorf("without health checks status = %s, want %s", got.Status, publicStatusOperational)
+	}
+}
+
+func TestPublicStatusHandler(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	handler := server.PublicStatusHandler(fakeHealth{"127.0.0.2": true})
+
+	rec := httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodGet, "/status.json", nil))
+	if rec.Code != http.StatusOK {
+		t.Fatalf("expected 200, got %d", rec.Code)
+	}
+	if strings.Contains(rec.Body.String(), "127.0.0.") {
+		t.Errorf("public status must not leak egress IPs: %s", rec.Body.String())
+	}
+	var status PublicStatus
+	if err := json.NewDecoder(rec.Body).Decode(&status); err != nil {
+		t.Fatalf("invalid JSON: %v", err)
+	}
+	if status.Status != publicStatusDegraded || status.HealthyEgresses != 1 {
+		t.Errorf("status = %+v, want degraded with 1 healthy", status)
+	}
+	if rec.Header().Get("Access-Control-Allow-Origin") != "*" {
+		t.Error("expected the JSON status to be embeddable from other origins")
+	}
+
+	rec = httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodGet, "/", nil))
+	if rec.Code != http.StatusOK || !strings.Contains(rec.Body.String(), "1 of 2 egresses healthy") {
+		t.Errorf("unexpected HTML page (%d): %s", rec.Code, rec.Body.String())
+	}
+
+	rec = httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodGet, "/admin/status", nil))
+	if rec.Code != http.StatusNotFound {
+		t.Errorf("expected 404 for other paths, got %d", rec.Code)
+	}
+
+	rec = httptest.NewRecorder()
+	handler.ServeHTTP(rec, httptest.NewRequest(http.MethodPost, "/status.json", nil))
+	if rec.Code != http.StatusMethodNotAllowed {
+		t.Errorf("expected 405 for POST, got %d", rec.Code)
+	}
+}
@@ -0,0 +1,47 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"net/http"
+	"slices"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+)
+
+// SetQuarantine feeds the status of every plain-HTTP response to q, so IPs that
+// targets start answering with 429/403 are taken out of rotation. It must be
+// called before Start.
+func (s *Server) SetQuarantine(q *balancer.Quarantine) {
+	s.quarantine = q
+}
+
+// QuarantineHandler returns the admin handler for quarantined IPs.
+// GET lists quarantined IPs with when each quarantine ends, and DELETE ?ip=X
+// returns X to rotation early. Without quarantine enabled the list is empty.
+func (s *Server) QuarantineHandler() http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		switch r.Method {
+		case http.MethodGet:
+		case http.MethodDelete:
+			ip := r.URL.Query().Get("ip")
+			if !slices.Contains(s.cfg.IPs, ip) {
+				http.Error(w, ErrUnknownIP.Error(), http.StatusNotFound)
+				return
+			}
+			if s.quarantine != nil && s.quarantine.Release(ip) {
+				log.Info("ip_quarantine_released", "ip", ip)
+			}
+		default:
+			w.Header().Set("Allow", "GET, DELETE")
+			http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+			return
+		}
+
+		quarantined := map[string]time.Time{}
+		if s.quarantine != nil {
+			quarantined = s.quarantine.QuarantinedUntil()
+		}
+		writeJSON(w, http.StatusOK, map[string]any{"quarantined": quarantined})
+	})
+}
@@ -0,0 +1,49 @@
+package proxy
+
+import (
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+)
+
+func TestHandler_QuarantinesBlockedIP(t *testing.T) {
+	backend := newTestBackendError(t, http.StatusTooManyRequests)
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	q := balancer.NewQuarantine(balancer.QuarantineConfig{
+		BlockRate:    0.5,
+		MinResponses: 3,
+		Window:       time.Minute,
+		Duration:     time.Minute,
+	})
+	server.SetQuarantine(q)
+	handler := NewHandler(server)
+
+	for i := 0; i < 3; i++ {
+		rec := httptest.NewRecorder()
+		handler.ServeHTTP(rec, httptest.NewRequest(http.MethodGet, backend.URL+"/", nil))
+		if rec.Code != http.StatusTooManyRequests {
+			t.Fatalf("expected the target's 429 to be relayed, got %d", rec.Code)
+		}
+	}
+	if q.IsHealthy("127.0.0.1") {
+		t.Fatal("expected 127.0.0.1 to be quarantined after a run of 429s")
+	}
+
+	rec := httptest.NewRecorder()
+	server.QuarantineHandler().ServeHTTP(rec, httptest.NewRequest(http.MethodGet, "/admin/quarantine", nil))
+	if !strings.Contains(rec.Body.String(), "127.0.0.1") {
+		t.Errorf("GET = %s, want 127.0.0.1 listed", rec.Body.String())
+	}
+
+	rec = httptest.NewRecorder()
+	server.QuarantineHandler().ServeHTTP(rec, httptest.NewRequest(http.MethodDelete, "/admin/quarantine?ip=127.0.0.1", nil))
+	if rec.Code != http.StatusOK || !q.IsHealthy("127.0.0.1") {
+		t.Errorf("expected DELETE to release the IP, got %d", rec.Code)
+	}
+}
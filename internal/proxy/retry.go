@@ -0,0 +1,93 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"math/rand/v2"
+	"net"
+	"net/http"
+	"slices"
+	"syscall"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+// dialErrorClass classifies a dial error as one of the config.RetryOn* classes.
+func dialErrorClass(err error) string {
+	var netErr net.Error
+	switch {
+	case errors.Is(err, syscall.ECONNREFUSED):
+		return config.RetryOnRefused
+	case errors.Is(err, syscall.EHOSTUNREACH), errors.Is(err, syscall.ENETUNREACH):
+		return config.RetryOnUnreachable
+	case errors.Is(err, syscall.ECONNRESET):
+		return config.RetryOnReset
+	case errors.Is(err, syscall.EADDRNOTAVAIL):
+		return config.RetryOnBind
+	case errors.Is(err, context.DeadlineExceeded), errors.As(err, &netErr) && netErr.Timeout():
+		return config.RetryOnTimeout
+	}
+	return config.RetryOnOther
+}
+
+// retryable reports whether a failed CONNECT dial may be retried on another
+// outbound IP. Destinations refused by policy are refused from every IP.
+func (s *Server) retryable(err error) bool {
+	if errors.Is(err, ErrDestinationNotAllowed) {
+		return false
+	}
+	return slices.Contains(s.cfg.ConnectRetryOn, dialErrorClass(err))
+}
+
+// retryAnnotation returns the ConnectRetryHeader for a CONNECT that connected
+// from ip after retries, or nil when it needed none or the header is disabled.
+func (s *Server) retryAnnotation(retries int, ip string) http.Header {
+	if retries == 0 || s.cfg.ConnectRetryHeader == "" {
+		return nil
+	}
+	header := make(http.Header)
+	header.Set(s.cfg.ConnectRetryHeader, fmt.Sprintf("retries=%d; egress=%s", retries, ip))
+	return header
+}
+
+// connectDialTimeout returns the dial timeout of a single CONNECT attempt.
+func (s *Server) connectDialTimeout() time.Duration {
+	if s.cfg.ConnectRetryTimeout > 0 {
+		return s.cfg.ConnectRetryTimeout
+	}
+	return s.cfg.EffectiveConnectTimeout()
+}
+
+// retryBackoff returns the wait before the given retry (1-based): base doubled
+// for each earlier retry and capped at limit, less a random jitter fraction.
+func retryBackoff(base, limit time.Duration, jitter float64, retry int) time.Duration {
+	if base <= 0 {
+		return 0
+	}
+	d := base
+	for i := 1; i < retry && d < limit; i++ {
+		d *= 2
+	}
+	d = min(d, limit)
+	return d - time.Duration(jitter*rand.Float64()*float64(d))
+}
+
+// waitRetry waits out the backoff before the given retry. It reports false if
+// ctx ends first.
+func (s *Server) waitRetry(ctx context.Context, retry int) bool {
+	d := retryBackoff(s.cfg.ConnectRetryBackoff, s.cfg.ConnectRetryMaxBackoff, s.cfg.ConnectRetryJitter, retry)
+	if d <= 0 {
+		return true
+	}
+	t := time.NewTimer(d)
+	defer t.Stop()
+	select {
+	case <-t.C:
+		return true
+	case <-ctx.Done():
+		return false
+	}
+}
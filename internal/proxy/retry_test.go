@@ -0,0 +1,67 @@
+package proxy
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"net"
+	"os"
+	"syscall"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+func TestDialErrorClass(t *testing.T) {
+	opErr := func(errno syscall.Errno) error {
+		return &net.OpError{Op: "dial", Net: "tcp", Err: os.NewSyscallError("connect", errno)}
+	}
+	tests := []struct {
+		err  error
+		want string
+	}{
+		{opErr(syscall.ECONNREFUSED), config.RetryOnRefused},
+		{opErr(syscall.EHOSTUNREACH), config.RetryOnUnreachable},
+		{opErr(syscall.ENETUNREACH), config.RetryOnUnreachable},
+		{opErr(syscall.ECONNRESET), config.RetryOnReset},
+		{&net.OpError{Op: "dial", Net: "tcp", Err: os.NewSyscallError("bind", syscall.EADDRNOTAVAIL)}, config.RetryOnBind},
+		{fmt.Errorf("dial: %w", context.DeadlineExceeded), config.RetryOnTimeout},
+		{errors.New("no such host"), config.RetryOnOther},
+	}
+	for _, tt := range tests {
+		if got := dialErrorClass(tt.err); got != tt.want {
+			t.Errorf("dialErrorClass(%v) = %q, want %q", tt.err, got, tt.want)
+		}
+	}
+}
+
+func TestRetryBackoff(t *testing.T) {
+	base, limit := 100*time.Millisecond, 300*time.Millisecond
+
+	if d := retryBackoff(0, limit, 0.5, 3); d != 0 {
+		t.Errorf("zero base: got %v, want 0", d)
+	}
+	for retry, want := range map[int]time.Duration{1: 100 * time.Millisecond, 2: 200 * time.Millisecond, 3: limit, 10: limit} {
+		if d := retryBackoff(base, limit, 0, retry); d != want {
+			t.Errorf("retry %d: got %v, want %v", retry, d, want)
+		}
+	}
+	for i := 0; i < 100; i++ {
+		if d := retryBackoff(base, limit, 0.5, 2); d < 100*time.Millisecond || d > 200*time.Millisecond {
+			t.Fatalf("jittered backoff %v outside [100ms, 200ms]", d)
+		}
+	}
+}
+
+func TestRetryable(t *testing.T) {
+	s := newTestServer(t)
+	s.cfg.ConnectRetryOn = []string{config.RetryOnRefused}
+
+	if !s.retryable(&net.OpError{Op: "dial", Err: os.NewSyscallError("connect", syscall.ECONNREFUSED)}) {
+		t.Error("expected refused to be retryable")
+	}
+	if s.retryable(errors.New("no such host")) {
+		t.Error("expected other errors not to be retryable")
+	}
+}
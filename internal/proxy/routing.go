@@ -0,0 +1,347 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"encoding/base64"
+	"errors"
+	"fmt"
+	"net"
+	"net/http"
+	"slices"
+	"strings"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// ErrNoBackendsForCountry is returned when no backend is tagged with the requested country.
+var ErrNoBackendsForCountry = errors.New("no backends for requested country")
+
+// RoutingHints holds per-request routing constraints supplied by the client,
+// either as structured username suffixes or via request headers.
+type RoutingHints struct {
+	// Tenant is the proxy username without routing suffixes, used to look up destination pins.
+	Tenant string
+	// ClientIP is the client's address without port, used to match FTP data connections.
+	ClientIP string
+	// Country restricts selection to backends tagged with this country.
+	Country string
+	// Exclude lists outbound IPs the client asked to avoid.
+	Exclude []string
+	// Pool restricts selection to a named pool, from the client's identity
+	// (bearer token, auth webhook or certificate) or the pool header of a
+	// trusted client.
+	Pool string
+	// Session names a client session that keeps one outbound IP for every destination.
+	Session string
+}
+
+// Selection provenance values, logged as "selection" in access-log records.
+const (
+	// selectionAffinity means a tenant's destination pin chose the outbound IP.
+	selectionAffinity = "affinity"
+	// selectionOverride means a routing header or username suffix constrained the choice.
+	selectionOverride = "override"
+	// selectionFresh means the balancer chose freely among all outbound IPs.
+	selectionFresh = "fresh"
+	// selectionPool means a destination pool for the target network chose the candidates.
+	selectionPool = "pool"
+	// selectionHedge means the first choice was slow to connect and a hedged dial on an alternate won.
+	selectionHedge = "hedge"
+	// selectionRetry means the first choice failed to connect and an alternate was used.
+	selectionRetry = "retry"
+	// selectionSticky means session affinity or a username session kept the client's previous outbound IP.
+	selectionSticky = "sticky"
+	// selectionDNS means the exit of a recent lookup through the DNS forwarder was kept.
+	selectionDNS = "dns"
+)
+
+// provenance records why an outbound IP was chosen, for auditing rotation behavior.
+type provenance struct {
+	// source is one of the selection* values.
+	source string
+	// key is the affinity key the decision was made for: the balancer host key,
+	// or tenant/host for pins.
+	key string
+}
+
+// logArgs returns the access-log fields for p.
+func (p provenance) logArgs() []any {
+	return []any{"selection", p.source, "affinity_key", p.key}
+}
+
+// hintKeys are the recognized username suffix keys.
+var hintKeys = map[string]bool{
+	"country": true,
+	"session": true,
+}
+
+// parseUsernameHints splits structured suffixes off a proxy username.
+// For example "alice-session-abc123-country-de" yields "alice", a Session hint
+// of "abc123" and a Country hint of "de".
+// Usernames without well-formed suffixes are returned unchanged.
+func parseUsernameHints(username string) (string, RoutingHints) {
+	parts := strings.Split(username, "-")
+
+	// Find the first recognized key; everything before it is the base username
+	start := -1
+	for i := 1; i < len(parts); i++ {
+		if hintKeys[strings.ToLower(parts[i])] {
+			start = i
+			break
+		}
+	}
+	if start < 0 || (len(parts)-start)%2 != 0 {
+		return username, RoutingHints{}
+	}
+
+	var hints RoutingHints
+	for i := start; i < len(parts); i += 2 {
+		key, value := strings.ToLower(parts[i]), parts[i+1]
+		if !hintKeys[key] || value == "" {
+			return username, RoutingHints{}
+		}
+		switch key {
+		case "country":
+			hints.Country = strings.ToLower(value)
+		case "session":
+			hints.Session = value
+		}
+	}
+
+	return strings.Join(parts[:start], "-"), hints
+}
+
+// proxyUsername returns the user of a verified client certificate or bearer
+// token, or the username from a Basic Proxy-Authorization header.
+// Returns empty string if the header is missing or malformed.
+func proxyUsername(r *http.Request) string {
+	if id, ok := identityFromRequest(r); ok {
+		return id.User
+	}
+	const prefix = "Basic "
+	auth := r.Header.Get("Proxy-Authorization")
+	if !strings.HasPrefix(auth, prefix) {
+		return ""
+	}
+	decoded, err := base64.StdEncoding.DecodeString(auth[len(prefix):])
+	if err != nil {
+		return ""
+	}
+	user, _, _ := strings.Cut(string(decoded), ":")
+	return user
+}
+
+// tenantFromRequest returns the request's proxy username without routing suffixes.
+func tenantFromRequest(r *http.Request) string {
+	tenant, _ := parseUsernameHints(proxyUsername(r))
+	return tenant
+}
+
+// routingHints derives routing hints from the proxy username and request headers.
+// An explicit header takes precedence over the username suffix.
+func (s *Server) routingHints(r *http.Request) RoutingHints {
+	tenant, hints := parseUsernameHints(proxyUsername(r))
+	hints.Tenant = tenant
+	hints.ClientIP = netutil.ParseHost(r.RemoteAddr)
+	if id, ok := identityFromRequest(r); ok {
+		hints.Pool = id.Pool
+	}
+
+	if s.cfg.GeoHeader != "" {
+		if v := strings.TrimSpace(r.Header.Get(s.cfg.GeoHeader)); v != "" {
+			hints.Country = strings.ToLower(v)
+		}
+	}
+
+	if s.cfg.ExcludeHeader != "" {
+		hints.Exclude = parseIPList(r.Header.Get(s.cfg.ExcludeHeader))
+	}
+
+	// A pool set by the client's identity can't be overridden by the header
+	if s.cfg.PoolHeader != "" && hints.Pool == "" && s.poolHeaderTrusted(hints.ClientIP) {
+		if v := strings.TrimSpace(r.Header.Get(s.cfg.PoolHeader)); v != "" {
+			if s.headerPoolAllowed(tenant, v) {
+				hints.Pool = v
+			} else {
+				log.Warn("pool_header_rejected", "user", tenant, "pool", v)
+			}
+		}
+	}
+
+	return hints
+}
+
+// parseIPList parses a comma-separated list of IPs, skipping invalid entries.
+func parseIPList(value string) []string {
+	if value == "" {
+		return nil
+	}
+	var ips []string
+	for _, part := range strings.Split(value, ",") {
+		part = strings.TrimSpace(part)
+		if net.ParseIP(part) != nil {
+			ips = append(ips, part)
+		}
+	}
+	return ips
+}
+
+// routingHeaders returns the configured routing hint headers, which are
+// consumed by the proxy and never forwarded upstream.
+func (s *Server) routingHeaders() []string {
+	var headers []string
+	for _, h := range []string{s.cfg.GeoHeader, s.cfg.ExcludeHeader, s.cfg.PoolHeader} {
+		if h != "" {
+			headers = append(headers, h)
+		}
+	}
+	return headers
+}
+
+// selectOptions converts routing hints into balancer constraints for host and
+// reports the provenance of the resulting choice. Destinations off the
+// allowlist or on the denylist are refused with ErrDestinationNotAllowed,
+// connections a deny rule matches with ErrRuleDenied, and all new traffic
+// (or that of a destination pool's named pool) with ErrMaintenance while in
+// maintenance.
+// A destination pin for the tenant takes precedence over the other hints, followed
+// by the control connection's exit for an announced FTP data address. IP literal
+// destinations inside a destination pool are restricted to the pool's IPs,
+// connections a use_pool rule matches to the rule's pool, and a tenant with a
+// user pool, or a token naming a pool, to the IPs of that pool.
+func (s *Server) selectOptions(host string, hints RoutingHints) (balancer.SelectOptions, provenance, error) {
+	if err := s.checkMaintenance(""); err != nil {
+		return balancer.SelectOptions{}, provenance{}, err
+	}
+	if err := s.admitDestination(host); err != nil {
+		return balancer.SelectOptions{}, provenance{}, err
+	}
+	rule := s.matchRules(host, hints)
+	if rule.Deny != "" {
+		log.Warn("rule_denied", "host", host, "user", hints.Tenant, "rule", rule.Deny)
+		return balancer.SelectOptions{}, provenance{}, &policyDenial{ErrRuleDenied, rule.Deny, rule.Deny}
+	}
+	if exit, ok := s.pinnedExit(hints.Tenant, host); ok {
+		key := netutil.ParseHost(host)
+		if hints.Tenant != "" {
+			key = hints.Tenant + "/" + key
+		}
+		return balancer.SelectOptions{Candidates: []string{exit}}, provenance{selectionAffinity, key}, nil
+	}
+	if exit, ok := s.ftpDataExit(hints.ClientIP, host); ok {
+		return balancer.SelectOptions{Candidates: []string{exit}}, provenance{selectionAffinity, "ftp/" + host}, nil
+	}
+
+	opts := balancer.SelectOptions{Exclude: hints.Exclude}
+	prov := provenance{selectionFresh, host}
+	if ip := net.ParseIP(netutil.ParseHost(host)); ip != nil {
+		if pool, ok := s.cfg.PoolForDestination(ip); ok {
+			if err := s.checkMaintenance(pool.Pool); err != nil {
+				return opts, prov, err
+			}
+			opts.Candidates = s.destinationPoolIPs(pool)
+			if len(opts.Candidates) == 0 {
+				return opts, prov, fmt.Errorf("%w: %s", ErrPoolEmpty, pool.CIDR)
+			}
+			prov = provenance{selectionPool, pool.CIDR}
+		}
+	}
+	if rule.Pool != "" {
+		if err := s.checkMaintenance(rule.Pool); err != nil {
+			return opts, prov, err
+		}
+		ips, _ := s.pools.get(rule.Pool)
+		ips = slices.Clone(ips)
+		if opts.Candidates != nil {
+			ips = slices.DeleteFunc(ips, func(ip string) bool { return !slices.Contains(opts.Candidates, ip) })
+		}
+		if len(ips) == 0 {
+			return opts, prov, fmt.Errorf("%w: %s", ErrPoolEmpty, rule.Pool)
+		}
+		opts.Candidates = ips
+		if prov.source == selectionFresh {
+			prov = provenance{selectionPool, "rule/" + rule.PoolRule}
+		}
+	}
+	if pool, ok := s.userPool(hints); ok {
+		if err := s.checkMaintenance(pool.Pool); err != nil {
+			return opts, prov, err
+		}
+		ips := slices.Clone(s.userPoolIPs(pool))
+		if opts.Candidates != nil {
+			ips = slices.DeleteFunc(ips, func(ip string) bool { return !slices.Contains(opts.Candidates, ip) })
+		}
+		if len(ips) == 0 {
+			return opts, prov, fmt.Errorf("%w: user %s", ErrPoolEmpty, hints.Tenant)
+		}
+		opts.Candidates = ips
+		if prov.source == selectionFresh {
+			prov = provenance{selectionPool, "user/" + hints.Tenant}
+		}
+	}
+	if len(hints.Exclude) > 0 {
+		prov.source = selectionOverride
+	}
+
+	if hints.Country != "" {
+		ips := s.cfg.IPsForCountry(hints.Country)
+		if opts.Candidates != nil {
+			ips = slices.DeleteFunc(ips, func(ip string) bool { return !slices.Contains(opts.Candidates, ip) })
+		}
+		if len(ips) == 0 {
+			return opts, prov, fmt.Errorf("%w: %s", ErrNoBackendsForCountry, hints.Country)
+		}
+		opts.Candidates = ips
+		prov.source = selectionOverride
+	}
+
+	return opts, prov, nil
+}
+
+// selectIPForRequest selects an outbound IP for the host honoring the request's routing hints.
+func (s *Server) selectIPForRequest(r *http.Request, host string) (string, provenance, error) {
+	hints := s.routingHints(r)
+	log.Trace("routing_hints", "host", host, "country", hints.Country, "session", hints.Session, "exclude", hints.Exclude)
+	return s.selectExit(host, hints)
+}
+
+// retryIPForRequest selects an alternate outbound IP for the host after the
+// IPs in tried failed to connect. It reports false when no other IP is allowed,
+// such as for a pinned destination.
+func (s *Server) retryIPForRequest(r *http.Request, host string, tried []string) (string, bool) {
+	hints := s.routingHints(r)
+	hints.Exclude = slices.Concat(hints.Exclude, tried)
+	opts, _, err := s.selectOptions(host, hints)
+	if err != nil {
+		return "", false
+	}
+	ip, err := s.balancer.SelectWithOptions(host, opts)
+	if err != nil || slices.Contains(tried, ip) {
+		return "", false
+	}
+	return ip, true
+}
+
+// selectionErrorMessage returns the client-facing message for a selection error.
+func selectionErrorMessage(err error) string {
+	switch {
+	case errors.Is(err, ErrNoBackendsForCountry):
+		return "No outbound IPs for requested country"
+	case errors.Is(err, ErrDestinationNotAllowed):
+		return "Destination not allowed"
+	case errors.Is(err, ErrPoolEmpty):
+		return "No outbound IPs in pool"
+	case errors.Is(err, ErrMaintenance):
+		return "Service under maintenance"
+	}
+	return "No available outbound IPs"
+}
+
+// selectionErrorStatus returns the client-facing HTTP status for a selection error.
+func selectionErrorStatus(err error) int {
+	if errors.Is(err, ErrDestinationNotAllowed) {
+		return http.StatusForbidden
+	}
+	return http.StatusServiceUnavailable
+}
@@ -0,0 +1,224 @@
+package proxy
+
+import (
+	"encoding/base64"
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"reflect"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+func TestParseUsernameHints(t *testing.T) {
+	tests := []struct {
+		username string
+		wantUser string
+		wantHint RoutingHints
+	}{
+		{"alice", "alice", RoutingHints{}},
+		{"alice-country-de", "alice", RoutingHints{Country: "de"}},
+		{"alice-country-DE", "alice", RoutingHints{Country: "de"}},
+		{"my-user-country-us", "my-user", RoutingHints{Country: "us"}},
+		{"alice-session-abc123", "alice", RoutingHints{Session: "abc123"}},
+		{"alice-session-abc123-country-us", "alice", RoutingHints{Session: "abc123", Country: "us"}},
+		{"alice-country-us-session-Abc", "alice", RoutingHints{Session: "Abc", Country: "us"}},
+		{"alice-country", "alice-country", RoutingHints{}},
+		{"alice-country-de-bogus", "alice-country-de-bogus", RoutingHints{}},
+		{"country-de", "country-de", RoutingHints{}},
+		{"", "", RoutingHints{}},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.username, func(t *testing.T) {
+			user, hints := parseUsernameHints(tt.username)
+			if user != tt.wantUser {
+				t.Errorf("user = %q, want %q", user, tt.wantUser)
+			}
+			if !reflect.DeepEqual(hints, tt.wantHint) {
+				t.Errorf("hints = %+v, want %+v", hints, tt.wantHint)
+			}
+		})
+	}
+}
+
+func newGeoTestServer(t *testing.T) *Server {
+	t.Helper()
+	opts := DefaultTestServerOptions()
+	opts.IPs = []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"}
+	server := newTestServerWithOptions(t, opts)
+	server.cfg.Backends = []config.BackendConfig{
+		{IP: "127.0.0.1", Country: "de"},
+		{IP: "127.0.0.2", Country: "us"},
+	}
+	return server
+}
+
+func TestSelectIPForRequest_CountryFromUsername(t *testing.T) {
+	server := newGeoTestServer(t)
+
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	creds := base64.StdEncoding.EncodeToString([]byte("alice-country-us:secret"))
+	req.Header.Set("Proxy-Authorization", "Basic "+creds)
+
+	for i := 0; i < 5; i++ {
+		ip, _, err := server.selectIPForRequest(req, "example.com")
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		if ip != "127.0.0.2" {
+			t.Errorf("expected 127.0.0.2, got %s", ip)
+		}
+		server.balancer.Record("example.com", ip)
+	}
+}
+
+func TestSelectIPForRequest_CountryFromHeader(t *testing.T) {
+	server := newGeoTestServer(t)
+
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req.Header.Set("X-Outbound-Country", "DE")
+
+	ip, _, err := server.selectIPForRequest(req, "example.com")
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if ip != "127.0.0.1" {
+		t.Errorf("expected 127.0.0.1, got %s", ip)
+	}
+}
+
+func TestSelectIPForRequest_UnknownCountry(t *testing.T) {
+	server := newGeoTestServer(t)
+
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req.Header.Set("X-Outbound-Country", "fr")
+
+	_, _, err := server.selectIPForRequest(req, "example.com")
+	if !errors.Is(err, ErrNoBackendsForCountry) {
+		t.Errorf("expected ErrNoBackendsForCountry, got %v", err)
+	}
+}
+
+func TestSelectIPForRequest_DestinationPool(t *testing.T) {
+	server := newGeoTestServer(t)
+	server.cfg.DestinationPools = []config.DestinationPool{{CIDR: "203.0.113.0/24", IPs: []string{"127.0.0.2", "127.0.0.3"}}}
+
+	req := httptest.NewRequest(http.MethodGet, "http://203.0.113.9/", nil)
+	for i := 0; i < 5; i++ {
+		ip, _, err := server.selectIPForRequest(req, "203.0.113.9:443")
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		if ip == "127.0.0.1" {
+			t.Errorf("selected %s outside the destination pool", ip)
+		}
+		server.balancer.Record("203.0.113.9:443", ip)
+	}
+
+	// A country hint narrows the pool; one with no IPs in the pool fails.
+	req.Header.Set("X-Outbound-Country", "us")
+	if ip, _, err := server.selectIPForRequest(req, "203.0.113.9:443"); err != nil || ip != "127.0.0.2" {
+		t.Errorf("country us in pool: got %s, %v; want 127.0.0.2", ip, err)
+	}
+	req.Header.Set("X-Outbound-Country", "de")
+	if _, _, err := server.selectIPForRequest(req, "203.0.113.9:443"); !errors.Is(err, ErrNoBackendsForCountry) {
+		t.Errorf("country de outside pool: expected ErrNoBackendsForCountry, got %v", err)
+	}
+}
+
+func TestAuthenticate_IgnoresUsernameHints(t *testing.T) {
+	server := newTestServerWithAuth(t, "alice:secret")
+
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	creds := base64.StdEncoding.EncodeToString([]byte("alice-country-de:secret"))
+	req.Header.Set("Proxy-Authorization", "Basic "+creds)
+
+	if _, ok := server.authenticate(httptest.NewRecorder(), req); !ok {
+		t.Error("expected authentication to succeed with routing hints in username")
+	}
+}
+
+func TestSelectIPForRequest_ExcludeHeader(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req.Header.Set("X-Outbound-Exclude", "127.0.0.1, 127.0.0.2, not-an-ip")
+
+	for i := 0; i < 5; i++ {
+		ip, _, err := server.selectIPForRequest(req, "example.com")
+		if err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		if ip != "127.0.0.3" {
+			t.Errorf("expected 127.0.0.3, got %s", ip)
+		}
+		server.balancer.Record("example.com", ip)
+	}
+}
+
+func TestSelectIPForRequest_ExcludeAllFallsBack(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req.Header.Set("X-Outbound-Exclude", "127.0.0.1,127.0.0.2")
+
+	if _, _, err := server.selectIPForRequest(req, "example.com"); err != nil {
+		t.Errorf("expected exclusion to be ignored when it leaves no IPs, got %v", err)
+	}
+}
+
+func TestCreateOutgoingRequest_StripsRoutingHeaders(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	handler := NewHandler(server)
+
+	req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+	req.Header.Set("X-Outbound-Country", "de")
+	req.Header.Set("X-Outbound-Exclude", "127.0.0.1")
+
+	outReq := handler.createOutgoingRequest(req)
+	if outReq.Header.Get("X-Outbound-Country") != "" || outReq.Header.Get("X-Outbound-Exclude") != "" {
+		t.Error("expected routing headers to be stripped")
+	}
+}
+
+func TestSelectIPForRequest_Provenance(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2"})
+	server.pins.set(Pin{Tenant: "alice", Host: "pinned.example.com", Exit: "127.0.0.2", ExpiresAt: time.Now().Add(time.Minute)})
+	server.cfg.DestinationPools = []config.DestinationPool{{CIDR: "203.0.113.0/24", IPs: []string{"127.0.0.2"}}}
+
+	tests := []struct {
+		name   string
+		host   string
+		user   string
+		header string
+		want   provenance
+	}{
+		{"fresh", "example.com", "", "", provenance{selectionFresh, "example.com"}},
+		{"exclude header", "example.com", "", "127.0.0.1", provenance{selectionOverride, "example.com"}},
+		{"pin", "pinned.example.com:443", "alice", "", provenance{selectionAffinity, "alice/pinned.example.com"}},
+		{"pin for another tenant", "pinned.example.com:443", "bob", "", provenance{selectionFresh, "pinned.example.com:443"}},
+		{"destination pool", "203.0.113.9:443", "", "", provenance{selectionPool, "203.0.113.0/24"}},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			req := httptest.NewRequest(http.MethodGet, "http://example.com/", nil)
+			if tt.user != "" {
+				req.Header.Set("Proxy-Authorization", "Basic "+base64.StdEncoding.EncodeToString([]byte(tt.user+":secret")))
+			}
+			if tt.header != "" {
+				req.Header.Set("X-Outbound-Exclude", tt.header)
+			}
+			_, got, err := server.selectIPForRequest(req, tt.host)
+			if err != nil {
+				t.Fatalf("unexpected error: %v", err)
+			}
+			if got != tt.want {
+				t.Errorf("provenance = %+v, want %+v", got, tt.want)
+			}
+		})
+	}
+}
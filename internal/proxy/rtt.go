@@ -0,0 +1,204 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"net"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+const (
+	// rttBaselineSamples is how many samples an IP needs before it can be flagged.
+	rttBaselineSamples = 10
+	// rttDegradedSamples is how many consecutive degraded samples flag an IP.
+	rttDegradedSamples = 3
+	// rttBaselineWeight is the weight of a new sample in an IP's RTT baseline.
+	rttBaselineWeight = 0.05
+)
+
+// tcpStats is what the kernel reports about one TCP connection.
+type tcpStats struct {
+	rtt     time.Duration
+	segsOut uint32
+	retrans uint32
+}
+
+// tcpConnOf returns the *net.TCPConn under conn's wrappers.
+func tcpConnOf(conn net.Conn) (*net.TCPConn, bool) {
+	for {
+		switch c := conn.(type) {
+		case *net.TCPConn:
+			return c, true
+		case interface{ NetConn() net.Conn }:
+			conn = c.NetConn()
+		default:
+			return nil, false
+		}
+	}
+}
+
+// egressRTT is the RTT baseline and degradation streak of one IP.
+type egressRTT struct {
+	baseline float64 // seconds
+	samples  int
+	degraded int
+}
+
+// rttMonitor samples TCP RTT and retransmissions of active tunnels per outbound
+// IP and flags IPs whose RTT jumps well above their own baseline or whose
+// tunnels retransmit heavily, before connections start failing outright.
+type rttMonitor struct {
+	tunnels   *tunnelRegistry
+	interval  time.Duration
+	factor    float64
+	retransAt float64
+	// stats reads kernel counters for a connection; eject is called for flagged IPs.
+	stats    func(net.Conn) (tcpStats, bool)
+	eject    func(ip string)
+	last     map[uint64]tcpStats
+	ips      map[string]*egressRTT
+	stopCh   chan struct{}
+	stopOnce sync.Once
+	wg       sync.WaitGroup
+}
+
+// newRTTMonitor creates an rttMonitor that calls eject for each flagged IP.
+func newRTTMonitor(tunnels *tunnelRegistry, interval time.Duration, factor, retransAt float64, eject func(ip string)) *rttMonitor {
+	return &rttMonitor{
+		tunnels:   tunnels,
+		interval:  interval,
+		factor:    factor,
+		retransAt: retransAt,
+		stats:     readTCPStats,
+		eject:     eject,
+		last:      make(map[uint64]tcpStats),
+		ips:       make(map[string]*egressRTT),
+		stopCh:    make(chan struct{}),
+	}
+}
+
+// Start starts the sampling goroutine.
+func (m *rttMonitor) Start() {
+	log.Info("rtt_monitor_started", "interval", m.interval, "degrade_factor", m.factor, "retransmit_threshold", m.retransAt)
+
+	m.wg.Add(1)
+	go func() {
+		defer m.wg.Done()
+		ticker := time.NewTicker(m.interval)
+		defer ticker.Stop()
+		for {
+			select {
+			case <-ticker.C:
+				m.check()
+			case <-m.stopCh:
+				return
+			}
+		}
+	}()
+}
+
+// Stop stops the sampling goroutine.
+func (m *rttMonitor) Stop() {
+	m.stopOnce.Do(func() { close(m.stopCh) })
+	m.wg.Wait()
+}
+
+// ejectDegraded ejects an IP flagged by the RTT monitor through passive outlier
+// detection for outlier-ejection-time. Without outlier detection the IP is only
+// logged and counted.
+func (s *Server) ejectDegraded(ip string) {
+	if s.outliers != nil {
+		s.outliers.Eject(ip, time.Now().Add(s.cfg.OutlierEjectionTime))
+	}
+}
+
+// check samples every active tunnel and returns the IPs flagged this round.
+func (m *rttMonitor) check() []string {
+	type sample struct {
+		rtt     time.Duration
+		count   int
+		segsOut uint32
+		retrans uint32
+	}
+	samples := make(map[string]*sample)
+	seen := make(map[uint64]bool)
+
+	for _, e := range m.tunnels.snapshot() {
+		target := e.target.Load()
+		if target == nil {
+			continue
+		}
+		st, ok := m.stats(*target)
+		if !ok || st.rtt <= 0 {
+			continue
+		}
+		seen[e.id] = true
+		prev := m.last[e.id]
+		m.last[e.id] = st
+
+		s, ok := samples[e.ip]
+		if !ok {
+			s = &sample{}
+			samples[e.ip] = s
+		}
+		s.rtt += st.rtt
+		s.count++
+		if st.segsOut >= prev.segsOut && st.retrans >= prev.retrans {
+			s.segsOut += st.segsOut - prev.segsOut
+			s.retrans += st.retrans - prev.retrans
+		}
+	}
+	for id := range m.last {
+		if !seen[id] {
+			delete(m.last, id)
+		}
+	}
+
+	var flagged []string
+	for ip, s := range samples {
+		rtt := (s.rtt / time.Duration(s.count)).Seconds()
+		var retransRatio float64
+		if s.segsOut > 0 {
+			retransRatio = float64(s.retrans) / float64(s.segsOut)
+		}
+		metrics.EgressRTT.WithLabelValues(ip).Set(rtt)
+		metrics.EgressRetransmitRatio.WithLabelValues(ip).Set(retransRatio)
+
+		state, ok := m.ips[ip]
+		if !ok {
+			state = &egressRTT{}
+			m.ips[ip] = state
+		}
+		if state.samples >= rttBaselineSamples && (rtt > state.baseline*m.factor || retransRatio > m.retransAt) {
+			// Degraded samples don't move the baseline, so a slow exit can't become the norm
+			state.degraded++
+			if state.degraded < rttDegradedSamples {
+				continue
+			}
+			state.degraded = 0
+			log.Warn("egress_degraded",
+				"ip", ip,
+				"rtt", time.Duration(rtt*float64(time.Second)),
+				"baseline_rtt", time.Duration(state.baseline*float64(time.Second)),
+				"retransmit_ratio", retransRatio,
+			)
+			metrics.EgressDegradations.WithLabelValues(ip).Inc()
+			flagged = append(flagged, ip)
+			if m.eject != nil {
+				m.eject(ip)
+			}
+			continue
+		}
+
+		state.degraded = 0
+		if state.samples == 0 {
+			state.baseline = rtt
+		} else {
+			state.baseline += rttBaselineWeight * (rtt - state.baseline)
+		}
+		state.samples++
+	}
+	return flagged
+}
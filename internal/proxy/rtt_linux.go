@@ -0,0 +1,36 @@
+//go:build linux
+
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"net"
+	"time"
+
+	"golang.org/x/sys/unix"
+)
+
+// readTCPStats reads the kernel's RTT and segment counters for conn from TCP_INFO.
+func readTCPStats(conn net.Conn) (tcpStats, bool) {
+	tc, ok := tcpConnOf(conn)
+	if !ok {
+		return tcpStats{}, false
+	}
+	raw, err := tc.SyscallConn()
+	if err != nil {
+		return tcpStats{}, false
+	}
+
+	var info *unix.TCPInfo
+	var sockErr error
+	if err := raw.Control(func(fd uintptr) {
+		info, sockErr = unix.GetsockoptTCPInfo(int(fd), unix.IPPROTO_TCP, unix.TCP_INFO)
+	}); err != nil || sockErr != nil {
+		return tcpStats{}, false
+	}
+	return tcpStats{
+		rtt:     time.Duration(info.Rtt) * time.Microsecond,
+		segsOut: info.Segs_out,
+		retrans: info.Total_retrans,
+	}, true
+}
@@ -0,0 +1,11 @@
+//go:build !linux
+
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import "net"
+
+// readTCPStats is not supported on this platform.
+func readTCPStats(net.Conn) (tcpStats, bool) {
+	return tcpStats{}, false
+}
@@ -0,0 +1,98 @@
+package proxy
+
+import (
+	"net"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/limiter"
+)
+
+func TestTCPConnOf(t *testing.T) {
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("listen: %v", err)
+	}
+	defer l.Close()
+	conn, err := net.Dial("tcp", l.Addr().String())
+	if err != nil {
+		t.Fatalf("dial: %v", err)
+	}
+	defer conn.Close()
+
+	shaper := limiter.NewBandwidthShaper(map[string]float64{"127.0.0.1": 1000})
+	wrapped := &ftpControlConn{Conn: shaper.Wrap("127.0.0.1", conn)}
+	if tc, ok := tcpConnOf(wrapped); !ok || tc != conn {
+		t.Errorf("tcpConnOf did not unwrap to the TCP connection")
+	}
+
+	// A sniffed upstream is still sampled
+	if tc, ok := tcpConnOf(&sniffedConn{Conn: wrapped}); !ok || tc != conn {
+		t.Errorf("tcpConnOf did not unwrap a sniffed upstream to the TCP connection")
+	}
+
+	c1, c2 := net.Pipe()
+	defer c1.Close()
+	defer c2.Close()
+	if _, ok := tcpConnOf(c1); ok {
+		t.Error("expected no TCP connection under a pipe")
+	}
+}
+
+func TestRTTMonitor_FlagsDegradedIP(t *testing.T) {
+	r := newTunnelRegistry()
+	var ejected []string
+	m := newRTTMonitor(r, time.Second, 3, 0.1, func(ip string) { ejected = append(ejected, ip) })
+
+	stats := make(map[net.Conn]*tcpStats)
+	m.stats = func(c net.Conn) (tcpStats, bool) {
+		st, ok := stats[c]
+		if !ok {
+			return tcpStats{}, false
+		}
+		return *st, true
+	}
+	add := func(ip string) *tcpStats {
+		c1, c2 := net.Pipe()
+		t.Cleanup(func() { c1.Close(); c2.Close() })
+		r.add("example.com", ip, func() {}).wrap(c1)
+		st := &tcpStats{rtt: 20 * time.Millisecond}
+		stats[c1] = st
+		return st
+	}
+	slow := add("192.0.2.1")
+	lossy := add("192.0.2.2")
+	steady := add("192.0.2.3")
+
+	// Build the baselines
+	for i := 0; i < rttBaselineSamples; i++ {
+		for _, st := range []*tcpStats{slow, lossy, steady} {
+			st.segsOut += 100
+			st.retrans++
+		}
+		if flagged := m.check(); len(flagged) != 0 {
+			t.Fatalf("no IP should be flagged while learning baselines, got %v", flagged)
+		}
+	}
+
+	slow.rtt = 200 * time.Millisecond
+	for i := 0; i < rttDegradedSamples; i++ {
+		for _, st := range []*tcpStats{slow, lossy, steady} {
+			st.segsOut += 100
+		}
+		lossy.retrans += 20
+		steady.rtt = 25 * time.Millisecond
+		flagged := m.check()
+		if i < rttDegradedSamples-1 && len(flagged) != 0 {
+			t.Fatalf("sample %d: flagged %v before %d degraded samples", i, flagged, rttDegradedSamples)
+		}
+	}
+	if len(ejected) != 2 {
+		t.Fatalf("expected the slow and lossy IPs to be ejected, got %v", ejected)
+	}
+	for _, ip := range ejected {
+		if ip == "192.0.2.3" {
+			t.Errorf("steady IP should not be ejected")
+		}
+	}
+}
@@ -0,0 +1,48 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"fmt"
+	"net"
+	"strconv"
+
+	"github.com/cr0hn/outbound-lb/internal/limiter"
+	"github.com/cr0hn/outbound-lb/internal/rules"
+)
+
+// ErrRuleDenied is returned when a deny routing rule refuses a connection. It
+// wraps ErrDestinationNotAllowed, so clients are refused the same way.
+var ErrRuleDenied = fmt.Errorf("%w: denied by rule", ErrDestinationNotAllowed)
+
+// destinationPort returns the port of a host[:port] destination. Plain HTTP
+// requests without a port use 80.
+func destinationPort(host string) int {
+	if _, p, err := net.SplitHostPort(host); err == nil {
+		port, _ := strconv.Atoi(p)
+		return port
+	}
+	return 80
+}
+
+// matchRules evaluates the routing rules for a connection to host.
+func (s *Server) matchRules(host string, hints RoutingHints) rules.Decision {
+	engine := s.policy.Load().rules
+	if engine == nil {
+		return rules.Decision{}
+	}
+	return engine.Evaluate(rules.Request{
+		Host:     destinationName(host),
+		Port:     destinationPort(host),
+		User:     hints.Tenant,
+		ClientIP: net.ParseIP(hints.ClientIP),
+	})
+}
+
+// shapeTunnel caps conn, a tunnel's connection to host through ip, at the
+// bandwidth a set_bandwidth rule gives it, if any.
+func (s *Server) shapeTunnel(conn net.Conn, ip, host string, hints RoutingHints) net.Conn {
+	if s.policy.Load().rules == nil {
+		return conn
+	}
+	return limiter.ShapeConn(ip, conn, s.matchRules(host, hints).BandwidthMbps)
+}
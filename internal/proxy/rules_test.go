@@ -0,0 +1,38 @@
+package proxy
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/internal/rules"
+)
+
+func TestSelectOptions_RoutingRules(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	server.policy.Store(&policy{rules: rules.New([]config.RoutingRule{
+		{Name: "no-smtp", Match: config.RuleMatch{Ports: []string{"25"}}, Action: config.RuleDeny},
+		{Name: "batch", Match: config.RuleMatch{Users: []string{"batch"}}, Action: config.RuleUsePool, Pool: "batch"},
+	}, clock.Real)})
+
+	if _, _, err := server.selectOptions("mail.example.com:25", RoutingHints{}); !errors.Is(err, ErrRuleDenied) || !errors.Is(err, ErrDestinationNotAllowed) {
+		t.Errorf("expected ErrRuleDenied wrapping ErrDestinationNotAllowed, got %v", err)
+	}
+	if _, _, err := server.selectOptions("example.com:443", RoutingHints{Tenant: "batch"}); !errors.Is(err, ErrPoolEmpty) {
+		t.Errorf("expected ErrPoolEmpty before the pool exists, got %v", err)
+	}
+	if _, err := server.pools.add("batch", []string{"127.0.0.3"}); err != nil {
+		t.Fatalf("add: %v", err)
+	}
+	opts, prov, err := server.selectOptions("example.com:443", RoutingHints{Tenant: "batch"})
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if len(opts.Candidates) != 1 || opts.Candidates[0] != "127.0.0.3" || prov.key != "rule/batch" {
+		t.Errorf("got candidates %v with provenance %q, want [127.0.0.3] with rule/batch", opts.Candidates, prov.key)
+	}
+	if opts, _, _ := server.selectOptions("example.com:443", RoutingHints{Tenant: "alice"}); opts.Candidates != nil {
+		t.Errorf("expected unmatched connections to use every IP, got %v", opts.Candidates)
+	}
+}
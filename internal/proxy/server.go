@@ -4,82 +4,522 @@ package proxy
 import (
 	"context"
 	"crypto/subtle"
+	"crypto/tls"
 	"encoding/base64"
 	"fmt"
+	"net"
 	"net/http"
+	"strconv"
 	"strings"
+	"sync"
+	"sync/atomic"
+	"text/template"
 	"time"
 
+	"github.com/cr0hn/outbound-lb/internal/activation"
+	"github.com/cr0hn/outbound-lb/internal/auth"
 	"github.com/cr0hn/outbound-lb/internal/balancer"
+	"github.com/cr0hn/outbound-lb/internal/clock"
 	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/internal/dns"
 	"github.com/cr0hn/outbound-lb/internal/limiter"
 	"github.com/cr0hn/outbound-lb/internal/logger"
 	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/internal/slo"
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
 )
 
+// log logs on behalf of the proxy module, so its level can be set on its own.
+var log = logger.Module("proxy")
+
 // Server is the HTTP/HTTPS proxy server.
 type Server struct {
-	cfg            *config.Config
-	httpServer     *http.Server
-	balancer       balancer.Balancer
-	limiter        *limiter.Limiter
-	transportPool  *TransportPool
-	stats          *metrics.StatsCollector
-	connectHandler *ConnectHandler
+	cfg                 *config.Config
+	httpServer          *http.Server
+	balancer            balancer.Balancer
+	limiter             *limiter.Limiter
+	fairShare           *limiter.FairShare
+	transportPool       *TransportPool
+	shaper              *limiter.BandwidthShaper
+	resolver            *dns.Resolver
+	tlsVerifier         TLSVerifier
+	slo                 *slo.Tracker
+	outliers            *balancer.OutlierDetector
+	breaker             *balancer.CircuitBreaker
+	quarantine          *balancer.Quarantine
+	users               atomic.Pointer[auth.Users]
+	authCache           *auth.CredentialCache
+	jwt                 *auth.JWTVerifier
+	authWebhook         *auth.WebhookAuthenticator
+	pam                 *auth.PAMAuthenticator
+	rates               *limiter.UserRates
+	quotas              *limiter.UserQuotas
+	routes              map[string]netutil.Route
+	sources             *sourceFailover
+	privateGuard        *privateGuard
+	connectPorts        []config.PortRange
+	blockTemplate       *template.Template
+	blockHeaders        http.Header
+	policy              atomic.Pointer[policy]
+	accessLog           atomic.Pointer[accessLog]
+	exitOf              func(ip string) string
+	tracer              *tracing.Tracer
+	tail                *tailHub
+	tunnels             *tunnelRegistry
+	pins                *pinStore
+	affinity            *affinityStore
+	sessions            *affinityStore
+	destAffinity        *affinityStore
+	userAffinity        *affinityStore
+	pools               *poolStore
+	poolHeaderClients   []*net.IPNet
+	maintenance         *maintenanceState
+	configDrains        []string
+	adminDrains         []string
+	ftpData             *ftpDataStore
+	learner             *destinationLearner
+	fdMonitor           *fdMonitor
+	progress            *progressMonitor
+	rtt                 *rttMonitor
+	anomalies           *anomalyDetector
+	stats               *metrics.StatsCollector
+	connectHandler      *ConnectHandler
+	socks5Handler       *SOCKS5Handler
+	socks5Listener      net.Listener
+	transparentHandler  *TransparentHandler
+	transparentListener net.Listener
+	forwardHandler      *ForwardHandler
+	forwardListener     net.Listener
+	publicStatusServer  *http.Server
+	dnsForwarder        *dns.Forwarder
+	dnsExits            *affinityStore
+	activated           map[string]net.Listener
+	started             time.Time
+	mu                  sync.Mutex
 }
 
 // NewServer creates a new proxy server.
 func NewServer(cfg *config.Config, bal balancer.Balancer, lim *limiter.Limiter, stats *metrics.StatsCollector) *Server {
 	s := &Server{
-		cfg:           cfg,
-		balancer:      bal,
-		limiter:       lim,
-		transportPool: NewTransportPool(cfg.IPs, cfg.Timeout),
-		stats:         stats,
+		cfg:      cfg,
+		balancer: bal,
+		limiter:  lim,
+		shaper:   limiter.NewBandwidthShaper(cfg.BandwidthCaps()),
+		resolver: dns.NewResolver(dns.Config{
+			Servers:   cfg.DNSServers,
+			CacheTTL:  cfg.DNSCacheTTL,
+			CacheSize: cfg.DNSCacheSize,
+			Timeout:   cfg.EffectiveDNSTimeout(),
+		}),
+		routes:      cfg.Routes(),
+		sources:     newSourceFailover(cfg.SourceIPs(), clock.Real),
+		slo:         slo.NewTracker(sloObjectives(cfg.SLOs)),
+		tunnels:     newTunnelRegistry(),
+		tail:        newTailHub(),
+		pins:        newPinStore(clock.Real),
+		pools:       newPoolStore(cfg.Pools, cfg.IPs, cfg.PoolsFile),
+		maintenance: newMaintenanceState(),
+		ftpData:     newFTPDataStore(clock.Real),
+		rates:       limiter.NewUserRates(clock.Real),
+		quotas:      limiter.NewUserQuotas(clock.Real),
+		stats:       stats,
+		started:     time.Now(),
+	}
+	if cfg.SessionAffinityTTL > 0 {
+		s.affinity = newAffinityStore(cfg.SessionAffinityTTL, clock.Real)
+	}
+	if cfg.AuthCacheTTL > 0 {
+		s.authCache = auth.NewCredentialCache(cfg.AuthCacheTTL, clock.Real)
+	}
+	if cfg.UsernameSessionTTL > 0 {
+		s.sessions = newAffinityStore(cfg.UsernameSessionTTL, clock.Real)
+	}
+	if cfg.DestinationAffinityTTL > 0 {
+		s.destAffinity = newAffinityStore(cfg.DestinationAffinityTTL, clock.Real)
+	}
+	s.userAffinity = newUserAffinityStore(cfg, clock.Real)
+	s.privateGuard = newPrivateGuard(cfg.BlockPrivateDestinations, cfg.PrivateDestinationsAllow)
+	s.connectPorts = cfg.ConnectPortRanges()
+	s.blockTemplate = cfg.BlockTemplate()
+	s.blockHeaders = cfg.BlockResponseHeaders()
+	s.policy.Store(newPolicy(cfg, nil))
+	s.poolHeaderClients, _ = netutil.ParseCIDRs(cfg.PoolHeaderTrusted) // validated with the config
+	if cfg.FairShareThreshold > 0 {
+		s.fairShare = limiter.NewFairShare(cfg.FairShareThreshold, cfg.FairShareWeightMap())
+	}
+	if cfg.LearnDestinationsFile != "" {
+		s.learner = newDestinationLearner(cfg.LearnDestinationsFile, clock.Real)
+	}
+	if cfg.FDEvictionThreshold > 0 {
+		s.fdMonitor = newFDMonitor(s.tunnels, cfg.FDEvictionThreshold, cfg.FDEvictionMinIdle)
+	}
+	if cfg.TunnelProgressInterval > 0 {
+		s.progress = newProgressMonitor(s.tunnels, cfg.TunnelProgressInterval, cfg.TunnelProgressMinBytes)
 	}
+	if cfg.RTTSampleInterval > 0 {
+		s.rtt = newRTTMonitor(s.tunnels, cfg.RTTSampleInterval, cfg.RTTDegradeFactor, cfg.RTTRetransmitThreshold, s.ejectDegraded)
+	}
+	if cfg.AnomalyInterval > 0 {
+		s.anomalies = newAnomalyDetector(cfg.IPs, cfg.AnomalyInterval, cfg.AnomalyThreshold, cfg.AnomalyWebhook)
+	}
+	s.transportPool = NewTransportPool(cfg.IPs, cfg.EffectiveConnectTimeout(), s.outboundDialOptions()...)
+	s.SetConfigDrains(cfg.Drain)
 
 	// Create handlers
 	handler := NewHandler(s)
 	s.connectHandler = NewConnectHandler(s)
+	s.socks5Handler = NewSOCKS5Handler(s)
+	s.transparentHandler = NewTransparentHandler(s)
+	s.forwardHandler = NewForwardHandler(s, cfg.ForwardTarget)
+	s.dnsForwarder = dns.NewForwarder(cfg.DNSServers, cfg.EffectiveDNSTimeout(), s.selectDNSExit)
+	if cfg.DNSForwarderPort > 0 {
+		s.dnsExits = newAffinityStore(dnsExitTTL, clock.Real)
+	}
+	if acl := newClientACL(cfg.ClientAllow, cfg.ClientDeny); acl != nil {
+		s.dnsForwarder.SetClientFilter(func(client net.Addr) bool {
+			if acl.allowed(client) {
+				return true
+			}
+			acl.refused(client)
+			return false
+		})
+	}
 
 	s.httpServer = &http.Server{
-		Addr:         fmt.Sprintf(":%d", cfg.Port),
-		Handler:      handler,
-		ReadTimeout:  cfg.Timeout,
-		WriteTimeout: cfg.Timeout,
-		IdleTimeout:  cfg.IdleTimeout,
+		Addr:              fmt.Sprintf(":%d", cfg.Port),
+		Handler:           handler,
+		ReadHeaderTimeout: cfg.EffectiveHeaderReadTimeout(),
+		ReadTimeout:       cfg.Timeout,
+		WriteTimeout:      cfg.Timeout,
+		IdleTimeout:       cfg.IdleTimeout,
+		MaxHeaderBytes:    cfg.MaxHeaderBytes,
+		ConnContext:       contextWithClientConn,
+	}
+
+	if s.tlsEnabled() {
+		// HTTP/2 is negotiated via ALPN; CONNECT streams are relayed without hijacking
+		s.httpServer.TLSConfig = &tls.Config{MinVersion: tls.VersionTLS12}
 	}
 
 	return s
 }
 
+// SetActivatedListeners makes the Start methods serve the given pre-bound
+// sockets, keyed by activation name, instead of binding their configured ports.
+// It must be called before Start.
+func (s *Server) SetActivatedListeners(listeners map[string]net.Listener) {
+	s.activated = listeners
+}
+
+// SetOutlierDetector feeds the outcome of every outbound connection to d, so
+// IPs failing real traffic are ejected from rotation. It must be called before Start.
+func (s *Server) SetOutlierDetector(d *balancer.OutlierDetector) {
+	s.outliers = d
+}
+
+// SetCircuitBreaker feeds the outcome of every outbound connection to cb, so
+// IPs with consecutive failures are skipped until their circuit half-opens.
+// It must be called before Start.
+func (s *Server) SetCircuitBreaker(cb *balancer.CircuitBreaker) {
+	s.breaker = cb
+}
+
+// recordOutcome feeds the outcome of an outbound connection through ip to the
+// outlier detector, circuit breaker and anomaly detector, if enabled. A
+// failure also rotates the sticky-until-error users on ip.
+func (s *Server) recordOutcome(ip string, failed bool) {
+	s.outliers.Record(ip, failed)
+	s.breaker.Record(ip, failed)
+	s.anomalies.record(ip, failed)
+	if failed {
+		s.userAffinity.forgetFailed(ip)
+	}
+}
+
+// listen returns the activated listener for name, or binds addr with bind.
+func (s *Server) listen(name, addr string, bind func(addr string) (net.Listener, error)) (net.Listener, error) {
+	if l, ok := s.activated[name]; ok {
+		log.Info("using activated socket", "listener", name, "addr", l.Addr().String())
+		return l, nil
+	}
+	return bind(addr)
+}
+
+// tcpListen binds a TCP listener on addr.
+func tcpListen(addr string) (net.Listener, error) {
+	return net.Listen("tcp", addr)
+}
+
 // Start starts the proxy server.
 func (s *Server) Start() error {
-	logger.Info("starting proxy server",
+	l, err := s.listen(activation.Proxy, s.httpServer.Addr, tcpListen)
+	if err != nil {
+		return err
+	}
+	return s.Serve(l)
+}
+
+// Serve accepts proxy connections on l, terminating TLS if a certificate is configured.
+func (s *Server) Serve(l net.Listener) error {
+	log.Info("starting proxy server",
 		"port", s.cfg.Port,
 		"ips", s.cfg.IPs,
-		"auth_enabled", s.cfg.Auth != "",
+		"auth_enabled", s.authRequired(),
+		"tls_enabled", s.tlsEnabled(),
+		"proxy_protocol", s.cfg.ProxyProtocol,
+	)
+	if s.fdMonitor != nil {
+		s.fdMonitor.Start()
+	}
+	if s.progress != nil {
+		s.progress.Start()
+	}
+	if s.rtt != nil {
+		s.rtt.Start()
+	}
+	if s.anomalies != nil {
+		s.anomalies.Start()
+	}
+	if s.learner != nil {
+		s.learner.Start()
+	}
+	l = s.wrapListener(l)
+	if s.tlsEnabled() {
+		return s.httpServer.ServeTLS(l, s.cfg.TLSCertFile, s.cfg.TLSKeyFile)
+	}
+	return s.httpServer.Serve(l)
+}
+
+// tlsEnabled reports whether the proxy listener terminates TLS.
+func (s *Server) tlsEnabled() bool {
+	return s.cfg.TLSCertFile != "" && s.cfg.TLSKeyFile != ""
+}
+
+// StartSOCKS5 starts the SOCKS5 listener on the configured port.
+// Blocks until the listener is closed by Shutdown.
+func (s *Server) StartSOCKS5() error {
+	l, err := s.listen(activation.SOCKS5, fmt.Sprintf(":%d", s.cfg.SOCKS5Port), tcpListen)
+	if err != nil {
+		return err
+	}
+
+	s.mu.Lock()
+	s.socks5Listener = l
+	s.mu.Unlock()
+
+	log.Info("starting socks5 server",
+		"port", s.cfg.SOCKS5Port,
+		"auth_enabled", s.authRequired(),
+	)
+	return s.socks5Handler.Serve(s.wrapListener(l))
+}
+
+// StartTransparent starts the transparent proxy listener on the configured port.
+// Blocks until the listener is closed by Shutdown.
+func (s *Server) StartTransparent() error {
+	l, err := s.listen(activation.Transparent, fmt.Sprintf(":%d", s.cfg.TransparentPort), listenTransparent)
+	if err != nil {
+		return err
+	}
+
+	s.mu.Lock()
+	s.transparentListener = l
+	s.mu.Unlock()
+
+	log.Info("starting transparent proxy server",
+		"port", s.cfg.TransparentPort,
+	)
+	return s.transparentHandler.Serve(s.withClientACL(l))
+}
+
+// StartForward starts the TCP port-forward listener on the configured port.
+// Blocks until the listener is closed by Shutdown.
+func (s *Server) StartForward() error {
+	l, err := s.listen(activation.Forward, fmt.Sprintf(":%d", s.cfg.ForwardPort), tcpListen)
+	if err != nil {
+		return err
+	}
+
+	s.mu.Lock()
+	s.forwardListener = l
+	s.mu.Unlock()
+
+	log.Info("starting port-forward server",
+		"port", s.cfg.ForwardPort,
+		"target", s.cfg.ForwardTarget,
 	)
-	return s.httpServer.ListenAndServe()
+	return s.forwardHandler.Serve(s.withClientACL(l))
+}
+
+// StartDNSForwarder starts the DNS forwarder on the configured address and port
+// (UDP and TCP). Blocks until the forwarder is closed by Shutdown.
+func (s *Server) StartDNSForwarder() error {
+	log.Info("starting dns forwarder",
+		"address", s.cfg.DNSForwarderAddress,
+		"port", s.cfg.DNSForwarderPort,
+		"upstreams", s.cfg.DNSServers,
+	)
+	return s.dnsForwarder.ListenAndServe(net.JoinHostPort(s.cfg.DNSForwarderAddress, strconv.Itoa(s.cfg.DNSForwarderPort)))
+}
+
+// dnsExitTTL is how long the exit of a forwarded DNS lookup stays bound to the name.
+const dnsExitTTL = 30 * time.Second
+
+// selectDNSExit picks the outbound IP for a forwarded DNS query from client and
+// binds it to the name for dnsExitTTL, so connections that follow the lookup
+// leave through the same exit when their own routing allows it (see selectExit).
+func (s *Server) selectDNSExit(name string, client net.Addr) (string, error) {
+	opts, _, err := s.selectOptions(name, RoutingHints{ClientIP: netutil.ParseHost(client.String())})
+	if err != nil {
+		return "", err
+	}
+	ip, err := s.balancer.SelectWithOptions(name, opts)
+	if err != nil {
+		return "", err
+	}
+	if s.dnsExits != nil {
+		s.dnsExits.set(destinationName(name), ip)
+	}
+	return ip, nil
 }
 
 // Shutdown gracefully shuts down the server.
 func (s *Server) Shutdown(ctx context.Context) error {
-	logger.Info("shutting down proxy server")
+	log.Info("shutting down proxy server")
+
+	s.closeListeners()
+
+	s.mu.Lock()
+	publicStatusServer := s.publicStatusServer
+	s.mu.Unlock()
+
+	if publicStatusServer != nil {
+		publicStatusServer.Shutdown(ctx)
+	}
+
+	s.dnsForwarder.Close()
+
+	if s.fdMonitor != nil {
+		s.fdMonitor.Stop()
+	}
+
+	if s.progress != nil {
+		s.progress.Stop()
+	}
+
+	if s.rtt != nil {
+		s.rtt.Stop()
+	}
+
+	if s.anomalies != nil {
+		s.anomalies.Stop()
+	}
+
+	if s.learner != nil {
+		s.learner.Stop()
+	}
+
 	s.transportPool.Close()
 	return s.httpServer.Shutdown(ctx)
 }
 
-// authenticate checks if the request is authenticated.
-func (s *Server) authenticate(w http.ResponseWriter, r *http.Request) bool {
-	// No auth configured
-	if s.cfg.Auth == "" {
+// ActiveTunnels returns the number of open CONNECT, SOCKS, transparent and forwarded tunnels.
+func (s *Server) ActiveTunnels() int {
+	return s.tunnels.len()
+}
+
+// Resolver returns the resolver used for outbound connections.
+func (s *Server) Resolver() *dns.Resolver {
+	return s.resolver
+}
+
+// SLOs returns the SLO tracker. Returns nil if no SLOs are configured.
+func (s *Server) SLOs() *slo.Tracker {
+	return s.slo
+}
+
+// sloObjectives converts SLO configuration into tracker objectives.
+func sloObjectives(cfgs []config.SLOConfig) []slo.Objective {
+	objectives := make([]slo.Objective, 0, len(cfgs))
+	for _, c := range cfgs {
+		objectives = append(objectives, slo.Objective{
+			Name:             c.Name,
+			Hosts:            c.Hosts,
+			LatencyTarget:    c.LatencyTarget,
+			LatencyObjective: c.LatencyObjective,
+			ErrorObjective:   c.ErrorObjective,
+			Window:           c.Window,
+		})
+	}
+	return objectives
+}
+
+// outboundDialOptions returns the options applied to every outbound connection.
+func (s *Server) outboundDialOptions() []DialOption {
+	opts := []DialOption{WithShaper(s.shaper), WithResolver(s.resolver), WithTLSVerifier(s.tlsVerifier), WithRoutes(s.routes), WithTLSHandshakeTimeout(s.cfg.TLSHandshakeTimeout), withSourceFailover(s.sources), withPrivateGuard(s.privateGuard)}
+	if s.anomalies != nil {
+		opts = append(opts, WithByteCounter(s.anomalies.addBytes))
+	}
+	return opts
+}
+
+// SetTLSVerifier verifies upstream TLS servers with v instead of the system
+// roots, for embedders with a private PKI or their own certificate policy.
+// It must be called before Start.
+func (s *Server) SetTLSVerifier(v TLSVerifier) {
+	s.tlsVerifier = v
+	s.transportPool = NewTransportPool(s.cfg.IPs, s.cfg.EffectiveConnectTimeout(), s.outboundDialOptions()...)
+}
+
+// SetUsers checks proxy credentials against the users of an --auth-file
+// instead of the single --auth credential. It may be called again while
+// serving to swap in a reloaded users file: cached checks are dropped so
+// removed users and changed passwords are refused at once, while open
+// connections and tunnels carry on.
+func (s *Server) SetUsers(u *auth.Users) {
+	s.users.Store(u)
+	s.authCache.Clear()
+}
+
+// SetPAM checks proxy credentials against the host's accounts through PAM
+// instead of --auth or --auth-file. It must be called before Start.
+func (s *Server) SetPAM(a *auth.PAMAuthenticator) {
+	s.pam = a
+}
+
+// authRequired reports whether clients must present proxy credentials.
+func (s *Server) authRequired() bool {
+	return s.basicAuthEnabled() || s.jwt != nil
+}
+
+// basicAuthEnabled reports whether clients may present Basic credentials.
+func (s *Server) basicAuthEnabled() bool {
+	if s.users.Load() != nil || s.authWebhook != nil || s.pam != nil {
 		return true
 	}
+	_, _, ok := s.cfg.GetAuthCredentials()
+	return ok
+}
 
-	username, password, ok := s.cfg.GetAuthCredentials()
-	if !ok {
-		return true // Invalid config, skip auth
+// authenticate checks if the request is authenticated. A request with a
+// verified client certificate or a valid bearer token is returned with the
+// client's identity in its context.
+func (s *Server) authenticate(w http.ResponseWriter, r *http.Request) (*http.Request, bool) {
+	// A verified client certificate identifies the client without credentials
+	if id, ok := s.clientCertIdentity(r); ok {
+		if id.User == "" {
+			log.Warn("authentication failed", "scheme", "certificate", "missing", s.cfg.TLSClientIdentity, "remote", r.RemoteAddr)
+			s.sendProxyAuthRequired(w)
+			metrics.AuthFailures.Inc()
+			return r, false
+		}
+		return r.WithContext(contextWithIdentity(r.Context(), id)), true
+	}
+
+	// No auth configured
+	if !s.authRequired() {
+		return r, true
 	}
 
 	// Get Proxy-Authorization header
@@ -87,7 +527,11 @@ func (s *Server) authenticate(w http.ResponseWriter, r *http.Request) bool {
 	if auth == "" {
 		s.sendProxyAuthRequired(w)
 		metrics.AuthFailures.Inc()
-		return false
+		return r, false
+	}
+
+	if token, ok := strings.CutPrefix(auth, "Bearer "); ok && s.jwt != nil {
+		return s.authenticateBearer(w, r, token)
 	}
 
 	// Parse Basic auth
@@ -95,14 +539,14 @@ func (s *Server) authenticate(w http.ResponseWriter, r *http.Request) bool {
 	if !strings.HasPrefix(auth, prefix) {
 		s.sendProxyAuthRequired(w)
 		metrics.AuthFailures.Inc()
-		return false
+		return r, false
 	}
 
 	decoded, err := base64.StdEncoding.DecodeString(auth[len(prefix):])
 	if err != nil {
 		s.sendProxyAuthRequired(w)
 		metrics.AuthFailures.Inc()
-		return false
+		return r, false
 	}
 
 	credentials := string(decoded)
@@ -110,28 +554,72 @@ func (s *Server) authenticate(w http.ResponseWriter, r *http.Request) bool {
 	if colonIdx < 0 {
 		s.sendProxyAuthRequired(w)
 		metrics.AuthFailures.Inc()
-		return false
+		return r, false
 	}
 
 	reqUser := credentials[:colonIdx]
 	reqPass := credentials[colonIdx+1:]
 
-	// Use constant-time comparison to prevent timing attacks
-	userMatch := subtle.ConstantTimeCompare([]byte(reqUser), []byte(username)) == 1
-	passMatch := subtle.ConstantTimeCompare([]byte(reqPass), []byte(password)) == 1
-	if !userMatch || !passMatch {
-		logger.Warn("authentication failed", "user", reqUser, "remote", r.RemoteAddr)
+	if s.authWebhook != nil {
+		return s.authenticateWebhook(w, r, reqUser, reqPass)
+	}
+	if !s.checkCredentials(reqUser, reqPass, r.RemoteAddr) {
+		log.Warn("authentication failed", "user", reqUser, "remote", r.RemoteAddr)
 		s.sendProxyAuthRequired(w)
 		metrics.AuthFailures.Inc()
-		return false
+		return r, false
+	}
+
+	return r, true
+}
+
+// checkCredentials reports whether the given proxy credentials are valid.
+// Routing hints encoded in the username are not part of the credential.
+// Successful users file, auth webhook and PAM checks are cached with
+// auth-cache-ttl. remote is the client's address, passed to the auth webhook.
+// Returns true if no auth is configured.
+func (s *Server) checkCredentials(reqUser, reqPass, remote string) bool {
+	_, ok := s.credentialIdentity(reqUser, reqPass, remote)
+	return ok
+}
+
+// credentialIdentity checks proxy credentials like checkCredentials and
+// returns the client's identity: the auth webhook's answer, with its pool and
+// limits, or otherwise just the username.
+func (s *Server) credentialIdentity(reqUser, reqPass, remote string) (auth.Identity, bool) {
+	if s.authWebhook != nil {
+		return s.webhookIdentity(context.Background(), reqUser, reqPass, remote, "", "socks")
+	}
+	id := auth.Identity{User: reqUser}
+	baseUser, _ := parseUsernameHints(reqUser)
+	if users := s.users.Load(); users != nil {
+		return id, s.authCache.Check(baseUser, reqPass, users.Check)
+	}
+	if s.pam != nil {
+		return id, s.authCache.Check(baseUser, reqPass, s.pam.Check)
+	}
+
+	username, password, ok := s.cfg.GetAuthCredentials()
+	if !ok {
+		// Bearer-only auth accepts no username/password
+		return id, !s.authRequired()
 	}
 
-	return true
+	// Use constant-time comparison to prevent timing attacks
+	userMatch := subtle.ConstantTimeCompare([]byte(baseUser), []byte(username)) == 1
+	passMatch := subtle.ConstantTimeCompare([]byte(reqPass), []byte(password)) == 1
+	return id, userMatch && passMatch
 }
 
-// sendProxyAuthRequired sends a 407 Proxy Authentication Required response.
+// sendProxyAuthRequired sends a 407 Proxy Authentication Required response,
+// challenging for each accepted scheme.
 func (s *Server) sendProxyAuthRequired(w http.ResponseWriter) {
-	w.Header().Set("Proxy-Authenticate", `Basic realm="Proxy"`)
+	if s.basicAuthEnabled() || s.jwt == nil {
+		w.Header().Add("Proxy-Authenticate", `Basic realm="Proxy"`)
+	}
+	if s.jwt != nil {
+		w.Header().Add("Proxy-Authenticate", `Bearer realm="Proxy"`)
+	}
 	http.Error(w, "Proxy Authentication Required", http.StatusProxyAuthRequired)
 }
 
@@ -142,10 +630,13 @@ func (s *Server) selectIP(host string) (string, error) {
 
 // ConnectionContext holds information about an acquired connection.
 type ConnectionContext struct {
-	IP        string
-	Host      string
-	RequestID string
-	release   func()
+	IP         string
+	Host       string
+	RequestID  string
+	provenance provenance
+	tenant     string
+	user       string
+	release    func()
 }
 
 // Release releases the connection resources. Must be called when done.
@@ -158,22 +649,35 @@ func (c *ConnectionContext) Release() {
 // AcquireConnection selects an IP and acquires a connection slot.
 // Returns a ConnectionContext that must be released when done.
 // Returns an error if no IPs are available or connection limit is reached.
-func (s *Server) AcquireConnection(host, requestID string) (*ConnectionContext, error) {
+func (s *Server) AcquireConnection(host, requestID string, hints RoutingHints) (*ConnectionContext, error) {
+	return s.acquireConnection(host, requestID, hints, auth.Identity{})
+}
+
+// acquireConnection is AcquireConnection for a client authenticated as id,
+// whose limits apply over the user_limits entry.
+func (s *Server) acquireConnection(host, requestID string, hints RoutingHints, id auth.Identity) (*ConnectionContext, error) {
 	// Select outbound IP
-	logger.Trace("connection_acquire_start", "request_id", requestID, "host", host)
-	ip, err := s.selectIP(host)
+	log.Trace("connection_acquire_start", "request_id", requestID, "host", host)
+	ip, prov, err := s.selectExit(host, hints)
 	if err != nil {
-		logger.Trace("connection_ip_selection_failed", "request_id", requestID, "host", host, "error", err)
+		log.Trace("connection_ip_selection_failed", "request_id", requestID, "host", host, "error", err)
 		return nil, err
 	}
-	logger.Trace("connection_ip_selected", "request_id", requestID, "host", host, "ip", ip)
+	log.Trace("connection_ip_selected", "request_id", requestID, "host", host, "ip", ip)
 
-	// Acquire connection slot
+	// Admit the user under its limits and fair share, then acquire a connection slot
+	user := fairShareUser(hints.Tenant, hints.ClientIP)
+	releaseUser, err := s.admitUser(user, s.userLimits(user, id))
+	if err != nil {
+		log.Trace("connection_acquire_failed", "request_id", requestID, "ip", ip, "error", err)
+		return nil, err
+	}
 	if err := s.limiter.Acquire(ip); err != nil {
-		logger.Trace("connection_acquire_failed", "request_id", requestID, "ip", ip, "error", err)
+		releaseUser()
+		log.Trace("connection_acquire_failed", "request_id", requestID, "ip", ip, "error", err)
 		return nil, err
 	}
-	logger.Trace("connection_acquired", "request_id", requestID, "ip", ip)
+	log.Trace("connection_acquired", "request_id", requestID, "ip", ip)
 
 	// Update metrics
 	s.stats.IncActiveConnections()
@@ -185,10 +689,14 @@ func (s *Server) AcquireConnection(host, requestID string) (*ConnectionContext,
 	logger.LogBalancerSelection(host, ip, len(s.cfg.IPs))
 
 	return &ConnectionContext{
-		IP:        ip,
-		Host:      host,
-		RequestID: requestID,
+		IP:         ip,
+		Host:       host,
+		RequestID:  requestID,
+		provenance: prov,
+		tenant:     hints.Tenant,
+		user:       user,
 		release: func() {
+			releaseUser()
 			s.limiter.Release(ip)
 			s.stats.DecActiveConnections()
 			s.stats.DecConnectionsForIP(ip)
@@ -206,11 +714,11 @@ func (s *Server) WaitForConnections(timeout time.Duration) {
 		select {
 		case <-ticker.C:
 			if s.limiter.GetTotalCount() == 0 {
-				logger.Info("all connections closed")
+				log.Info("all connections closed")
 				return
 			}
 			if time.Now().After(deadline) {
-				logger.Warn("timeout waiting for connections",
+				log.Warn("timeout waiting for connections",
 					"active", s.limiter.GetTotalCount(),
 				)
 				return
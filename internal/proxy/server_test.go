@@ -4,13 +4,16 @@ import (
 	"encoding/base64"
 	"net/http"
 	"net/http/httptest"
+	"strings"
 	"testing"
 	"time"
 
+	"github.com/cr0hn/outbound-lb/internal/auth"
 	"github.com/cr0hn/outbound-lb/internal/balancer"
 	"github.com/cr0hn/outbound-lb/internal/config"
 	"github.com/cr0hn/outbound-lb/internal/limiter"
 	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"golang.org/x/crypto/bcrypt"
 )
 
 func newTestServerWithAuth(t *testing.T, auth string) *Server {
@@ -96,7 +99,7 @@ func TestServer_Authenticate_NoAuth(t *testing.T) {
 	req := httptest.NewRequest(http.MethodGet, "/", nil)
 	w := httptest.NewRecorder()
 
-	result := server.authenticate(w, req)
+	_, result := server.authenticate(w, req)
 	if !result {
 		t.Error("expected authentication to pass when no auth configured")
 	}
@@ -108,7 +111,7 @@ func TestServer_Authenticate_MissingHeader(t *testing.T) {
 	req := httptest.NewRequest(http.MethodGet, "/", nil)
 	w := httptest.NewRecorder()
 
-	result := server.authenticate(w, req)
+	_, result := server.authenticate(w, req)
 	if result {
 		t.Error("expected authentication to fail when header missing")
 	}
@@ -124,7 +127,7 @@ func TestServer_Authenticate_InvalidScheme(t *testing.T) {
 	req.Header.Set("Proxy-Authorization", "Bearer token")
 	w := httptest.NewRecorder()
 
-	result := server.authenticate(w, req)
+	_, result := server.authenticate(w, req)
 	if result {
 		t.Error("expected authentication to fail with invalid scheme")
 	}
@@ -137,7 +140,7 @@ func TestServer_Authenticate_InvalidBase64(t *testing.T) {
 	req.Header.Set("Proxy-Authorization", "Basic not-valid-base64!!!")
 	w := httptest.NewRecorder()
 
-	result := server.authenticate(w, req)
+	_, result := server.authenticate(w, req)
 	if result {
 		t.Error("expected authentication to fail with invalid base64")
 	}
@@ -151,7 +154,7 @@ func TestServer_Authenticate_MissingColon(t *testing.T) {
 	req.Header.Set("Proxy-Authorization", "Basic "+encoded)
 	w := httptest.NewRecorder()
 
-	result := server.authenticate(w, req)
+	_, result := server.authenticate(w, req)
 	if result {
 		t.Error("expected authentication to fail without colon separator")
 	}
@@ -165,7 +168,7 @@ func TestServer_Authenticate_WrongCredentials(t *testing.T) {
 	req.Header.Set("Proxy-Authorization", "Basic "+encoded)
 	w := httptest.NewRecorder()
 
-	result := server.authenticate(w, req)
+	_, result := server.authenticate(w, req)
 	if result {
 		t.Error("expected authentication to fail with wrong credentials")
 	}
@@ -179,7 +182,7 @@ func TestServer_Authenticate_ValidCredentials(t *testing.T) {
 	req.Header.Set("Proxy-Authorization", "Basic "+encoded)
 	w := httptest.NewRecorder()
 
-	result := server.authenticate(w, req)
+	_, result := server.authenticate(w, req)
 	if !result {
 		t.Error("expected authentication to pass with valid credentials")
 	}
@@ -193,12 +196,79 @@ func TestServer_Authenticate_PasswordWithColon(t *testing.T) {
 	req.Header.Set("Proxy-Authorization", "Basic "+encoded)
 	w := httptest.NewRecorder()
 
-	result := server.authenticate(w, req)
+	_, result := server.authenticate(w, req)
 	if !result {
 		t.Error("expected authentication to pass with password containing colon")
 	}
 }
 
+func TestServer_Authenticate_UsersFile(t *testing.T) {
+	hash, err := bcrypt.GenerateFromPassword([]byte("s3cret"), bcrypt.MinCost)
+	if err != nil {
+		t.Fatal(err)
+	}
+	users, err := auth.ParseUsers(strings.NewReader("alice:" + string(hash)))
+	if err != nil {
+		t.Fatal(err)
+	}
+	server := newTestServerWithAuth(t, "")
+	server.SetUsers(users)
+
+	tests := []struct {
+		name        string
+		credentials string
+		want        bool
+	}{
+		{"valid", "alice:s3cret", true},
+		{"wrong password", "alice:wrong", false},
+		{"unknown user", "bob:s3cret", false},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			req := httptest.NewRequest(http.MethodGet, "/", nil)
+			req.Header.Set("Proxy-Authorization", "Basic "+base64.StdEncoding.EncodeToString([]byte(tt.credentials)))
+			w := httptest.NewRecorder()
+
+			if _, got := server.authenticate(w, req); got != tt.want {
+				t.Fatalf("authenticate() = %v, want %v", got, tt.want)
+			}
+			if !tt.want && w.Header().Get("Proxy-Authenticate") != `Basic realm="Proxy"` {
+				t.Errorf("expected Basic challenge, got %q", w.Header().Get("Proxy-Authenticate"))
+			}
+		})
+	}
+
+	// A request without credentials is challenged too
+	w := httptest.NewRecorder()
+	if _, ok := server.authenticate(w, httptest.NewRequest(http.MethodGet, "/", nil)); ok || w.Code != http.StatusProxyAuthRequired {
+		t.Errorf("expected 407 without credentials, got %d", w.Code)
+	}
+
+	// With the auth cache, a cached success doesn't admit other passwords
+	server.authCache = auth.NewCredentialCache(time.Minute, nil)
+	for _, user := range []string{"alice", "alice", "alice-country-de"} {
+		if !server.checkCredentials(user, "s3cret", "127.0.0.1:5000") {
+			t.Errorf("checkCredentials(%s) = false with the cache, want true", user)
+		}
+	}
+	if server.checkCredentials("alice", "wrong", "127.0.0.1:5000") {
+		t.Error("expected a wrong password to fail after a cached success")
+	}
+
+	// Reloading users drops cached checks, so a removed user is refused at once
+	reloaded, err := auth.ParseUsers(strings.NewReader("bob:" + string(hash)))
+	if err != nil {
+		t.Fatal(err)
+	}
+	server.SetUsers(reloaded)
+	if server.checkCredentials("alice", "s3cret", "127.0.0.1:5000") {
+		t.Error("expected a removed user to fail after reload")
+	}
+	if !server.checkCredentials("bob", "s3cret", "127.0.0.1:5000") {
+		t.Error("expected an added user to pass after reload")
+	}
+}
+
 func TestServer_SelectIP(t *testing.T) {
 	server := newTestServerWithAuth(t, "")
 
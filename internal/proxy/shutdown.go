@@ -0,0 +1,72 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"time"
+)
+
+// shutdownProgressInterval is how often Drain logs the connections still open.
+const shutdownProgressInterval = 5 * time.Second
+
+// closeListeners closes the SOCKS5, transparent and forward listeners.
+func (s *Server) closeListeners() {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	if s.socks5Listener != nil {
+		s.socks5Listener.Close()
+	}
+	if s.transparentListener != nil {
+		s.transparentListener.Close()
+	}
+	if s.forwardListener != nil {
+		s.forwardListener.Close()
+	}
+}
+
+// Drain stops accepting new connections and keeps relaying in-flight requests
+// and tunnels for up to grace, logging progress as they finish. Tunnels still
+// open when grace expires are closed. It returns how many tunnels were cut.
+func (s *Server) Drain(grace time.Duration) int {
+	log.Info("shutdown_draining_started", "grace", grace, "active_connections", s.limiter.GetTotalCount(), "active_tunnels", s.tunnels.len())
+
+	s.closeListeners()
+	ctx, cancel := context.WithTimeout(context.Background(), grace)
+	defer cancel()
+	// Shutdown closes the proxy listener and idle keep-alive connections; it
+	// does not track hijacked CONNECT tunnels, which are counted separately
+	go s.httpServer.Shutdown(ctx)
+
+	ticker := time.NewTicker(100 * time.Millisecond)
+	defer ticker.Stop()
+	lastProgress := time.Now()
+	for {
+		select {
+		case <-ticker.C:
+			conns, tunnels := s.limiter.GetTotalCount(), s.tunnels.len()
+			if conns == 0 && tunnels == 0 {
+				log.Info("shutdown_drained")
+				return 0
+			}
+			if time.Since(lastProgress) >= shutdownProgressInterval {
+				lastProgress = time.Now()
+				deadline, _ := ctx.Deadline()
+				log.Info("shutdown_draining",
+					"active_connections", conns,
+					"active_tunnels", tunnels,
+					"remaining", time.Until(deadline).Round(time.Second),
+				)
+			}
+		case <-ctx.Done():
+			entries := s.tunnels.snapshot()
+			for _, e := range entries {
+				e.closeFn()
+			}
+			log.Warn("shutdown_grace_expired",
+				"active_connections", s.limiter.GetTotalCount(),
+				"closed_tunnels", len(entries),
+			)
+			return len(entries)
+		}
+	}
+}
@@ -0,0 +1,51 @@
+package proxy
+
+import (
+	"sync/atomic"
+	"testing"
+	"time"
+)
+
+func TestServer_Drain_NothingOpen(t *testing.T) {
+	server := newTestServerForConnect(t)
+
+	start := time.Now()
+	if cut := server.Drain(5 * time.Second); cut != 0 {
+		t.Errorf("Drain() cut %d tunnels, want 0", cut)
+	}
+	if elapsed := time.Since(start); elapsed > time.Second {
+		t.Errorf("Drain() took %v with nothing open", elapsed)
+	}
+}
+
+func TestServer_Drain_WaitsForTunnels(t *testing.T) {
+	server := newTestServerForConnect(t)
+
+	var closed atomic.Bool
+	entry := server.tunnels.add("example.com", "127.0.0.1", func() { closed.Store(true) })
+	go func() {
+		time.Sleep(150 * time.Millisecond)
+		server.tunnels.remove(entry)
+	}()
+
+	if cut := server.Drain(5 * time.Second); cut != 0 {
+		t.Errorf("Drain() cut %d tunnels, want 0", cut)
+	}
+	if closed.Load() {
+		t.Error("tunnel finishing within the grace period was closed")
+	}
+}
+
+func TestServer_Drain_ClosesTunnelsAfterGrace(t *testing.T) {
+	server := newTestServerForConnect(t)
+
+	var closed atomic.Bool
+	server.tunnels.add("example.com", "127.0.0.1", func() { closed.Store(true) })
+
+	if cut := server.Drain(50 * time.Millisecond); cut != 1 {
+		t.Errorf("Drain() cut %d tunnels, want 1", cut)
+	}
+	if !closed.Load() {
+		t.Error("tunnel still open after the grace period")
+	}
+}
@@ -0,0 +1,307 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"bufio"
+	"bytes"
+	"encoding/binary"
+	"errors"
+	"net"
+	"net/http"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// Protocols reported by tunnel sniffing.
+const (
+	ProtocolTLS   = "tls"
+	ProtocolHTTP  = "http"
+	ProtocolOther = "other"
+)
+
+// sniffBufferSize fits a maximum-size TLS record plus its header.
+const sniffBufferSize = 5 + 16384
+
+// ErrSniffHostMismatch is returned when the sniffed server name does not match the tunnel target.
+var ErrSniffHostMismatch = errors.New("sniffed server name does not match tunnel target")
+
+// httpMethodPrefixes are the request-line prefixes recognized as HTTP.
+var httpMethodPrefixes = [][]byte{
+	[]byte("GET "), []byte("POST "), []byte("PUT "), []byte("HEAD "), []byte("DELETE "),
+	[]byte("OPTIONS "), []byte("PATCH "), []byte("TRACE "), []byte("CONNECT "),
+}
+
+// SniffResult describes the protocol detected on a tunnel.
+type SniffResult struct {
+	// Protocol is ProtocolTLS, ProtocolHTTP or ProtocolOther.
+	Protocol string
+	// ServerName is the TLS SNI or HTTP Host, if one was found.
+	ServerName string
+}
+
+// sniffedConn replays the bytes consumed while sniffing before reading from the connection.
+type sniffedConn struct {
+	net.Conn
+	r *bufio.Reader
+}
+
+// Read reads from the sniff buffer, then from the underlying connection.
+func (c *sniffedConn) Read(p []byte) (int, error) {
+	return c.r.Read(p)
+}
+
+// CloseWrite half-closes the underlying connection if supported.
+func (c *sniffedConn) CloseWrite() error {
+	if cw, ok := c.Conn.(closeWriter); ok {
+		return cw.CloseWrite()
+	}
+	return nil
+}
+
+// NetConn returns the wrapped connection.
+func (c *sniffedConn) NetConn() net.Conn {
+	return c.Conn
+}
+
+// sniffConn peeks at the first bytes the client sends and classifies the protocol.
+// The returned connection replays the peeked bytes. If the client sends nothing
+// within timeout (server-first protocols), the result is ProtocolOther.
+func sniffConn(conn net.Conn, timeout time.Duration) (net.Conn, SniffResult) {
+	br := bufio.NewReaderSize(conn, sniffBufferSize)
+	wrapped := &sniffedConn{Conn: conn, r: br}
+
+	conn.SetReadDeadline(time.Now().Add(timeout))
+	defer conn.SetReadDeadline(time.Time{})
+
+	first, err := br.Peek(1)
+	if err != nil {
+		return wrapped, SniffResult{Protocol: ProtocolOther}
+	}
+
+	switch {
+	case first[0] == 0x16:
+		if name, ok := sniffTLS(br); ok {
+			return wrapped, SniffResult{Protocol: ProtocolTLS, ServerName: name}
+		}
+	case looksLikeHTTP(br):
+		return wrapped, SniffResult{Protocol: ProtocolHTTP, ServerName: sniffHTTPHost(br)}
+	}
+	return wrapped, SniffResult{Protocol: ProtocolOther}
+}
+
+// sniffTunnel sniffs client like sniffConn, but gives up as soon as upstream
+// sends before the client does, so server-first protocols aren't held up for
+// the timeout. Both returned connections replay the bytes consumed.
+func sniffTunnel(client, upstream net.Conn, timeout time.Duration) (net.Conn, net.Conn, SniffResult) {
+	ur := bufio.NewReader(upstream)
+	var mu sync.Mutex
+	sniffing := true
+	done := make(chan struct{})
+	go func() {
+		defer close(done)
+		ur.Peek(1)
+		// The upstream spoke, or failed, first: stop waiting for the client
+		mu.Lock()
+		if sniffing {
+			client.SetReadDeadline(time.Now())
+		}
+		mu.Unlock()
+	}()
+
+	wrapped, result := sniffConn(client, timeout)
+
+	mu.Lock()
+	sniffing = false
+	mu.Unlock()
+	client.SetReadDeadline(time.Time{})
+	upstream.SetReadDeadline(time.Now())
+	<-done
+	upstream.SetReadDeadline(time.Time{})
+	return wrapped, &sniffedConn{Conn: upstream, r: ur}, result
+}
+
+// sniffTLS peeks a TLS record and returns the SNI from its ClientHello.
+// ok is false if the record is not a TLS handshake.
+func sniffTLS(br *bufio.Reader) (serverName string, ok bool) {
+	header, err := br.Peek(5)
+	if err != nil || header[1] != 0x03 {
+		return "", false
+	}
+	length := int(binary.BigEndian.Uint16(header[3:5]))
+	record, err := br.Peek(5 + length)
+	if err != nil {
+		return "", true
+	}
+	return parseClientHelloSNI(record[5:]), true
+}
+
+// parseClientHelloSNI extracts the server_name extension from a ClientHello handshake message.
+// Returns an empty string if the message is malformed or carries no SNI.
+func parseClientHelloSNI(msg []byte) string {
+	// Handshake header: type (1) + length (3)
+	if len(msg) < 4 || msg[0] != 0x01 {
+		return ""
+	}
+	p := msg[4:]
+
+	// Version (2) + random (32)
+	if len(p) < 34 {
+		return ""
+	}
+	p = p[34:]
+
+	// Session ID, cipher suites and compression methods
+	var ok bool
+	if p, ok = skipVector(p, 1); !ok {
+		return ""
+	}
+	if p, ok = skipVector(p, 2); !ok {
+		return ""
+	}
+	if p, ok = skipVector(p, 1); !ok {
+		return ""
+	}
+
+	if len(p) < 2 {
+		return ""
+	}
+	extLen := int(binary.BigEndian.Uint16(p))
+	p = p[2:]
+	if len(p) < extLen {
+		return ""
+	}
+	p = p[:extLen]
+
+	for len(p) >= 4 {
+		extType := binary.BigEndian.Uint16(p)
+		length := int(binary.BigEndian.Uint16(p[2:]))
+		p = p[4:]
+		if len(p) < length {
+			return ""
+		}
+		if extType == 0x0000 {
+			return parseServerNameList(p[:length])
+		}
+		p = p[length:]
+	}
+	return ""
+}
+
+// parseServerNameList returns the first host_name entry of a server_name extension.
+func parseServerNameList(p []byte) string {
+	if len(p) < 2 {
+		return ""
+	}
+	p = p[2:]
+	for len(p) >= 3 {
+		nameType := p[0]
+		length := int(binary.BigEndian.Uint16(p[1:]))
+		p = p[3:]
+		if len(p) < length {
+			return ""
+		}
+		if nameType == 0x00 {
+			return string(p[:length])
+		}
+		p = p[length:]
+	}
+	return ""
+}
+
+// skipVector skips a TLS vector with a length prefix of the given size.
+func skipVector(p []byte, prefix int) ([]byte, bool) {
+	if len(p) < prefix {
+		return nil, false
+	}
+	var length int
+	if prefix == 1 {
+		length = int(p[0])
+	} else {
+		length = int(binary.BigEndian.Uint16(p))
+	}
+	p = p[prefix:]
+	if len(p) < length {
+		return nil, false
+	}
+	return p[length:], true
+}
+
+// looksLikeHTTP reports whether the buffered bytes start with an HTTP request line.
+func looksLikeHTTP(br *bufio.Reader) bool {
+	peeked, _ := br.Peek(br.Buffered())
+	for _, prefix := range httpMethodPrefixes {
+		if bytes.HasPrefix(peeked, prefix) {
+			return true
+		}
+	}
+	return false
+}
+
+// sniffHTTPHost reads ahead until the end of the request headers and returns the Host.
+func sniffHTTPHost(br *bufio.Reader) string {
+	for {
+		peeked, err := br.Peek(br.Buffered())
+		if err != nil {
+			return ""
+		}
+		if bytes.Contains(peeked, []byte("\r\n\r\n")) {
+			req, err := http.ReadRequest(bufio.NewReader(bytes.NewReader(peeked)))
+			if err != nil {
+				return ""
+			}
+			return req.Host
+		}
+		if br.Buffered() >= sniffBufferSize {
+			return ""
+		}
+		if _, err := br.Peek(br.Buffered() + 1); err != nil {
+			return ""
+		}
+	}
+}
+
+// inspectTunnel sniffs the client side of a tunnel when protocol sniffing is enabled
+// and applies the policy for the detected protocol. When the upstream is already
+// connected, sniffing stops as soon as it speaks first (SMTP, SSH); a nil upstream
+// waits out the sniff timeout. The returned connections must be used in place of
+// conn and upstream.
+func (s *Server) inspectTunnel(conn, upstream net.Conn, target, requestID string) (net.Conn, net.Conn, SniffResult, error) {
+	if !s.cfg.SniffProtocols {
+		return conn, upstream, SniffResult{}, nil
+	}
+
+	var result SniffResult
+	if upstream != nil {
+		conn, upstream, result = sniffTunnel(conn, upstream, s.cfg.SniffTimeout)
+	} else {
+		conn, result = sniffConn(conn, s.cfg.SniffTimeout)
+	}
+	metrics.SniffedProtocols.WithLabelValues(result.Protocol).Inc()
+	log.Debug("tunnel_protocol",
+		"request_id", requestID,
+		"host", target,
+		"protocol", result.Protocol,
+		"server_name", result.ServerName,
+	)
+
+	return conn, upstream, result, checkSniffed(target, result)
+}
+
+// checkSniffed applies the policy for a sniffed tunnel: the TLS SNI or HTTP Host
+// must match the requested target. Targets given as IP literals are not checked.
+func checkSniffed(target string, result SniffResult) error {
+	if result.ServerName == "" {
+		return nil
+	}
+	targetHost := netutil.ParseHost(target)
+	if net.ParseIP(targetHost) != nil {
+		return nil
+	}
+	if !netutil.MatchHost(targetHost, result.ServerName) {
+		return ErrSniffHostMismatch
+	}
+	return nil
+}
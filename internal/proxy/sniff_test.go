@@ -0,0 +1,154 @@
+package proxy
+
+import (
+	"crypto/tls"
+	"errors"
+	"io"
+	"net"
+	"testing"
+	"time"
+)
+
+func TestSniffConn_TLS(t *testing.T) {
+	client, server := net.Pipe()
+	defer client.Close()
+	defer server.Close()
+
+	go func() {
+		tls.Client(client, &tls.Config{ServerName: "example.com"}).Handshake()
+	}()
+
+	_, result := sniffConn(server, time.Second)
+	if result.Protocol != ProtocolTLS {
+		t.Errorf("protocol = %q, want %q", result.Protocol, ProtocolTLS)
+	}
+	if result.ServerName != "example.com" {
+		t.Errorf("server name = %q, want example.com", result.ServerName)
+	}
+}
+
+func TestSniffConn_HTTPReplaysBytes(t *testing.T) {
+	client, server := net.Pipe()
+	defer client.Close()
+	defer server.Close()
+
+	request := "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n"
+	go client.Write([]byte(request))
+
+	conn, result := sniffConn(server, time.Second)
+	if result.Protocol != ProtocolHTTP {
+		t.Errorf("protocol = %q, want %q", result.Protocol, ProtocolHTTP)
+	}
+	if result.ServerName != "example.com" {
+		t.Errorf("server name = %q, want example.com", result.ServerName)
+	}
+
+	buf := make([]byte, len(request))
+	if _, err := io.ReadFull(conn, buf); err != nil {
+		t.Fatalf("read failed: %v", err)
+	}
+	if string(buf) != request {
+		t.Errorf("replayed %q, want %q", buf, request)
+	}
+}
+
+func TestSniffConn_Other(t *testing.T) {
+	client, server := net.Pipe()
+	defer client.Close()
+	defer server.Close()
+
+	go client.Write([]byte("SSH-2.0-OpenSSH_9.6\r\n"))
+
+	_, result := sniffConn(server, time.Second)
+	if result.Protocol != ProtocolOther {
+		t.Errorf("protocol = %q, want %q", result.Protocol, ProtocolOther)
+	}
+}
+
+func TestSniffConn_ServerFirstTimesOut(t *testing.T) {
+	client, server := net.Pipe()
+	defer client.Close()
+	defer server.Close()
+
+	start := time.Now()
+	_, result := sniffConn(server, 50*time.Millisecond)
+	if result.Protocol != ProtocolOther {
+		t.Errorf("protocol = %q, want %q", result.Protocol, ProtocolOther)
+	}
+	if elapsed := time.Since(start); elapsed > time.Second {
+		t.Errorf("sniffing did not honor timeout, took %v", elapsed)
+	}
+}
+
+func TestSniffTunnel_ServerFirst(t *testing.T) {
+	client, clientSide := net.Pipe()
+	defer client.Close()
+	defer clientSide.Close()
+	upstreamSide, upstream := net.Pipe()
+	defer upstreamSide.Close()
+	defer upstream.Close()
+
+	greeting := "220 mail.example.com ESMTP\r\n"
+	go upstreamSide.Write([]byte(greeting))
+
+	start := time.Now()
+	_, target, result := sniffTunnel(clientSide, upstream, 10*time.Second)
+	if elapsed := time.Since(start); elapsed > 5*time.Second {
+		t.Errorf("expected sniffing to stop when the upstream spoke first, took %v", elapsed)
+	}
+	if result.Protocol != ProtocolOther {
+		t.Errorf("protocol = %q, want %q", result.Protocol, ProtocolOther)
+	}
+
+	buf := make([]byte, len(greeting))
+	if _, err := io.ReadFull(target, buf); err != nil {
+		t.Fatalf("read failed: %v", err)
+	}
+	if string(buf) != greeting {
+		t.Errorf("replayed %q, want %q", buf, greeting)
+	}
+}
+
+func TestCheckSniffed(t *testing.T) {
+	tests := []struct {
+		name    string
+		target  string
+		result  SniffResult
+		wantErr bool
+	}{
+		{"matching SNI", "example.com:443", SniffResult{ProtocolTLS, "example.com"}, false},
+		{"case-insensitive", "Example.com:443", SniffResult{ProtocolTLS, "EXAMPLE.COM"}, false},
+		{"mismatched SNI", "example.com:443", SniffResult{ProtocolTLS, "evil.com"}, true},
+		{"mismatched Host", "example.com:80", SniffResult{ProtocolHTTP, "evil.com:80"}, true},
+		{"IP target", "93.184.216.34:443", SniffResult{ProtocolTLS, "example.com"}, false},
+		{"no server name", "example.com:443", SniffResult{ProtocolOther, ""}, false},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			err := checkSniffed(tt.target, tt.result)
+			if (err != nil) != tt.wantErr {
+				t.Errorf("checkSniffed() error = %v, wantErr %v", err, tt.wantErr)
+			}
+			if err != nil && !errors.Is(err, ErrSniffHostMismatch) {
+				t.Errorf("expected ErrSniffHostMismatch, got %v", err)
+			}
+		})
+	}
+}
+
+func TestInspectTunnel_Disabled(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+
+	client, conn := net.Pipe()
+	defer client.Close()
+	defer conn.Close()
+
+	got, _, result, err := server.inspectTunnel(conn, nil, "example.com:443", "test")
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if got != conn || result.Protocol != "" {
+		t.Error("expected connection to pass through untouched when sniffing is disabled")
+	}
+}
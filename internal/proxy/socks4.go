@@ -0,0 +1,107 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"encoding/binary"
+	"errors"
+	"fmt"
+	"io"
+	"net"
+	"strconv"
+	"strings"
+
+	"github.com/cr0hn/outbound-lb/internal/auth"
+)
+
+// SOCKS4 protocol constants.
+const (
+	socks4Version        = 0x04
+	socks4ReplyVersion   = 0x00
+	socks4CmdConnect     = 0x01
+	socks4ReplyGranted   = 0x5A
+	socks4ReplyRejected  = 0x5B
+	socks4MaxFieldLength = 255
+)
+
+// errSOCKS4FieldTooLong is returned when a null-terminated SOCKS4 field exceeds socks4MaxFieldLength.
+var errSOCKS4FieldTooLong = errors.New("socks4 field too long")
+
+// readRequest4 reads a SOCKS4 or SOCKS4a request and returns the client identity
+// and the target as host:port. The version byte has already been read.
+//
+// SOCKS4 has no password field, so when authentication is configured the user ID
+// must carry credentials as "user:pass".
+func (h *SOCKS5Handler) readRequest4(conn net.Conn) (auth.Identity, string, error) {
+	header := make([]byte, 7)
+	if _, err := io.ReadFull(conn, header); err != nil {
+		return auth.Identity{}, "", err
+	}
+	if header[0] != socks4CmdConnect {
+		h.writeReply4(conn, socks5ReplyCommandNotSupported, nil)
+		return auth.Identity{}, "", fmt.Errorf("unsupported socks4 command: %d", header[0])
+	}
+	port := binary.BigEndian.Uint16(header[1:3])
+	dstIP := net.IP(header[3:7])
+
+	userID, err := readNullTerminated(conn)
+	if err != nil {
+		return auth.Identity{}, "", err
+	}
+
+	host := dstIP.String()
+	// SOCKS4a: an address of 0.0.0.x (x != 0) means a domain name follows the user ID
+	if dstIP[0] == 0 && dstIP[1] == 0 && dstIP[2] == 0 && dstIP[3] != 0 {
+		if host, err = readNullTerminated(conn); err != nil {
+			return auth.Identity{}, "", err
+		}
+	}
+
+	user, pass, _ := strings.Cut(userID, ":")
+	id, ok := h.server.credentialIdentity(user, pass, conn.RemoteAddr().String())
+	if !ok {
+		h.writeReply4(conn, socks5ReplyGeneralFailure, nil)
+		return id, "", errSOCKSAuthFailed
+	}
+
+	return id, net.JoinHostPort(host, strconv.Itoa(int(port))), nil
+}
+
+// readNullTerminated reads a null-terminated string of at most socks4MaxFieldLength bytes.
+// It reads one byte at a time so nothing past the terminator is consumed.
+func readNullTerminated(r io.Reader) (string, error) {
+	var sb strings.Builder
+	b := make([]byte, 1)
+	for {
+		if _, err := io.ReadFull(r, b); err != nil {
+			return "", err
+		}
+		if b[0] == 0 {
+			return sb.String(), nil
+		}
+		if sb.Len() >= socks4MaxFieldLength {
+			return "", errSOCKS4FieldTooLong
+		}
+		sb.WriteByte(b[0])
+	}
+}
+
+// writeReply4 writes a SOCKS4 reply. code is a SOCKS5 reply code so both
+// protocols can share the request flow; anything other than success is
+// reported as rejected.
+func (h *SOCKS5Handler) writeReply4(conn net.Conn, code byte, bound net.Addr) error {
+	status := byte(socks4ReplyRejected)
+	if code == socks5ReplySucceeded {
+		status = socks4ReplyGranted
+	}
+
+	reply := []byte{socks4ReplyVersion, status, 0, 0, 0, 0, 0, 0}
+	if tcpAddr, ok := bound.(*net.TCPAddr); ok {
+		if ip4 := tcpAddr.IP.To4(); ip4 != nil {
+			binary.BigEndian.PutUint16(reply[2:4], uint16(tcpAddr.Port))
+			copy(reply[4:8], ip4)
+		}
+	}
+
+	_, err := conn.Write(reply)
+	return err
+}
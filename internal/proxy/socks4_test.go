@@ -0,0 +1,93 @@
+package proxy
+
+import (
+	"bufio"
+	"encoding/binary"
+	"io"
+	"net"
+	"net/http"
+	"strconv"
+	"strings"
+	"testing"
+)
+
+// socks4aConnect sends a SOCKS4a CONNECT for target through addr and returns the reply status.
+func socks4aConnect(t *testing.T, addr, userID, target string) (net.Conn, byte) {
+	t.Helper()
+	conn, err := net.Dial("tcp", addr)
+	if err != nil {
+		t.Fatalf("failed to dial socks server: %v", err)
+	}
+
+	host, portStr, _ := net.SplitHostPort(target)
+	port, _ := strconv.Atoi(portStr)
+	req := []byte{socks4Version, socks4CmdConnect}
+	req = binary.BigEndian.AppendUint16(req, uint16(port))
+	req = append(req, 0, 0, 0, 1)
+	req = append(req, userID...)
+	req = append(req, 0)
+	req = append(req, host...)
+	req = append(req, 0)
+	conn.Write(req)
+
+	reply := make([]byte, 8)
+	if _, err := io.ReadFull(conn, reply); err != nil {
+		t.Fatalf("failed to read connect reply: %v", err)
+	}
+	return conn, reply[1]
+}
+
+func TestSOCKS4a_Connect(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	l := startTestSOCKS5(t, server)
+
+	target := strings.Replace(strings.TrimPrefix(backend.URL, "http://"), "127.0.0.1", "localhost", 1)
+	conn, code := socks4aConnect(t, l.Addr().String(), "", target)
+	defer conn.Close()
+
+	if code != socks4ReplyGranted {
+		t.Fatalf("expected granted reply, got %#x", code)
+	}
+
+	conn.Write([]byte("GET / HTTP/1.1\r\nHost: " + target + "\r\nConnection: close\r\n\r\n"))
+	resp, err := http.ReadResponse(bufio.NewReader(conn), nil)
+	if err != nil {
+		t.Fatalf("failed to read response through tunnel: %v", err)
+	}
+	defer resp.Body.Close()
+
+	if resp.StatusCode != http.StatusOK {
+		t.Errorf("expected status 200, got %d", resp.StatusCode)
+	}
+}
+
+func TestSOCKS4_AuthInUserID(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	server := newTestServerWithAuth(t, "user:pass")
+	l := startTestSOCKS5(t, server)
+	target := strings.TrimPrefix(backend.URL, "http://")
+
+	conn, code := socks4aConnect(t, l.Addr().String(), "user:wrong", target)
+	conn.Close()
+	if code != socks4ReplyRejected {
+		t.Errorf("expected rejection for bad credentials, got %#x", code)
+	}
+
+	conn, code = socks4aConnect(t, l.Addr().String(), "user:pass", target)
+	conn.Close()
+	if code != socks4ReplyGranted {
+		t.Errorf("expected granted reply for valid credentials, got %#x", code)
+	}
+}
+
+func TestReadNullTerminated_TooLong(t *testing.T) {
+	long := strings.Repeat("a", socks4MaxFieldLength+1) + "\x00"
+	if _, err := readNullTerminated(strings.NewReader(long)); err != errSOCKS4FieldTooLong {
+		t.Errorf("expected errSOCKS4FieldTooLong, got %v", err)
+	}
+}
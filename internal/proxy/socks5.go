@@ -0,0 +1,407 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"encoding/binary"
+	"errors"
+	"fmt"
+	"io"
+	"net"
+	"strconv"
+	"strings"
+	"syscall"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/auth"
+	"github.com/cr0hn/outbound-lb/internal/logger"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// SOCKS5 protocol constants (RFC 1928, RFC 1929).
+const (
+	socks5Version        = 0x05
+	socks5AuthVersion    = 0x01
+	socks5MethodNoAuth   = 0x00
+	socks5MethodUserPass = 0x02
+	socks5MethodNone     = 0xFF
+	socks5CmdConnect     = 0x01
+	socks5AtypIPv4       = 0x01
+	socks5AtypDomain     = 0x03
+	socks5AtypIPv6       = 0x04
+)
+
+// SOCKS5 reply codes.
+const (
+	socks5ReplySucceeded           = 0x00
+	socks5ReplyGeneralFailure      = 0x01
+	socks5ReplyNotAllowed          = 0x02
+	socks5ReplyHostUnreachable     = 0x04
+	socks5ReplyConnectionRefused   = 0x05
+	socks5ReplyCommandNotSupported = 0x07
+	socks5ReplyAddrNotSupported    = 0x08
+)
+
+var (
+	// errSOCKSAuthFailed is returned when SOCKS credentials are rejected.
+	errSOCKSAuthFailed = errors.New("socks authentication failed")
+	// errSOCKSNoMethod is returned when the client offers no acceptable auth method.
+	errSOCKSNoMethod = errors.New("no acceptable socks authentication method")
+)
+
+// SOCKS5Handler serves SOCKS5 and SOCKS4/4a CONNECT requests using the proxy's
+// balancer and tunnel relay.
+type SOCKS5Handler struct {
+	server *Server
+}
+
+// NewSOCKS5Handler creates a new SOCKS5Handler.
+func NewSOCKS5Handler(server *Server) *SOCKS5Handler {
+	return &SOCKS5Handler{server: server}
+}
+
+// Serve accepts connections on l until it is closed.
+func (h *SOCKS5Handler) Serve(l net.Listener) error {
+	for {
+		conn, err := l.Accept()
+		if err != nil {
+			if errors.Is(err, net.ErrClosed) {
+				return nil
+			}
+			var netErr net.Error
+			if errors.As(err, &netErr) && netErr.Timeout() {
+				time.Sleep(10 * time.Millisecond)
+				continue
+			}
+			return err
+		}
+		go h.ServeConn(conn)
+	}
+}
+
+// ServeConn handles a single SOCKS client connection.
+// SOCKS5 and SOCKS4/4a clients are distinguished by the first byte.
+func (h *SOCKS5Handler) ServeConn(conn net.Conn) {
+	defer conn.Close()
+
+	start := time.Now()
+	requestID := GenerateRequestID()
+	remote := conn.RemoteAddr().String()
+
+	logSpan := log.Span("request_id", requestID, "remote", remote)
+	logSpan.Trace("socks_connection_accepted")
+
+	// Trace the connection; SOCKS carries no trace context, so it starts a trace
+	span := h.server.startSpan("proxy.socks", nil, requestID)
+	defer span.End()
+	span.SetAttr("client.address", netutil.ParseHost(remote))
+	ctx := tracing.ContextWithSpan(context.Background(), span)
+
+	// Bound the handshake by the connection timeout
+	conn.SetDeadline(time.Now().Add(h.server.cfg.EffectiveHeaderReadTimeout()))
+
+	version := make([]byte, 1)
+	if _, err := io.ReadFull(conn, version); err != nil {
+		logSpan.Debug("socks_handshake_failed", "error", err)
+		return
+	}
+
+	var (
+		method string
+		id     auth.Identity
+		host   string
+		reply  func(net.Conn, byte, net.Addr) error
+		err    error
+	)
+	switch version[0] {
+	case socks5Version:
+		method, reply = "SOCKS5", h.writeReply
+		id, err = h.handshake(conn)
+		if err == nil {
+			host, err = h.readRequest(conn)
+		}
+	case socks4Version:
+		method, reply = "SOCKS4", h.writeReply4
+		id, host, err = h.readRequest4(conn)
+	default:
+		logSpan.Debug("socks_handshake_failed", "error", fmt.Errorf("unsupported socks version: %d", version[0]))
+		return
+	}
+	span.SetAttr("server.address", host)
+	if err != nil {
+		span.SetError(err)
+		if errors.Is(err, errSOCKSAuthFailed) {
+			logSpan.Warn("authentication failed", "user", id.User, "protocol", strings.ToLower(method))
+			metrics.AuthFailures.Inc()
+		} else {
+			logSpan.Debug("socks_request_failed", "protocol", strings.ToLower(method), "error", err)
+		}
+		return
+	}
+
+	logSpan.Logger = logSpan.With("host", host, "protocol", strings.ToLower(method))
+	logSpan.Trace("socks_request_received")
+
+	// Apply the CONNECT port policy; SOCKS CONNECT opens the same kind of tunnel
+	if err := h.server.checkConnectPort(host); err != nil {
+		logSpan.Warn("connect_port_rejected", "error", err)
+		span.SetError(err)
+		reply(conn, socks5ReplyNotAllowed, nil)
+		metrics.RequestsTotal.WithLabelValues(method, "403").Inc()
+		return
+	}
+
+	// Select outbound IP and acquire a connection slot
+	tenant, hints := parseUsernameHints(id.User)
+	hints.Tenant = tenant
+	hints.ClientIP = netutil.ParseHost(remote)
+	hints.Pool = id.Pool
+	selectSpan := startPhase(ctx, "select", tracing.KindInternal)
+	connCtx, err := h.server.acquireConnection(host, requestID, hints, id)
+	endPhase(selectSpan, err)
+	if err != nil {
+		logSpan.Trace("socks_acquire_failed", "error", err)
+		if errors.Is(err, ErrDestinationNotAllowed) {
+			reply(conn, socks5ReplyNotAllowed, nil)
+			metrics.RequestsTotal.WithLabelValues(method, "403").Inc()
+			return
+		}
+		reply(conn, socks5ReplyGeneralFailure, nil)
+		metrics.LimitRejections.WithLabelValues(limitRejectionType(err)).Inc()
+		metrics.RequestsTotal.WithLabelValues(method, "503").Inc()
+		return
+	}
+	defer connCtx.Release()
+	ip := connCtx.IP
+
+	metrics.TunnelConnections.Inc()
+
+	// Connect to target
+	dialer := NewDialer(ip, h.server.cfg.EffectiveConnectTimeout(), h.server.cfg.IdleTimeout, h.server.outboundDialOptions()...)
+	dialStart := time.Now()
+	targetConn, err := dialer.DialContext(ctx, "tcp", h.server.dialTarget(tenant, host))
+	h.server.slo.Observe(host, ip, time.Since(dialStart), dialFailed(err))
+	h.server.recordOutcome(ip, dialFailed(err))
+	if err != nil {
+		logger.LogError("socks_dial", err, "host", host, "ip", ip)
+		reply(conn, dialErrorReply(err), nil)
+		metrics.RequestsTotal.WithLabelValues(method, strconv.Itoa(dialErrorStatus(err))).Inc()
+		return
+	}
+	defer targetConn.Close()
+	targetConn = h.server.watchFTP(targetConn, hints.ClientIP, host, ip)
+	targetConn = h.server.shapeTunnel(targetConn, ip, host, hints)
+
+	if err := reply(conn, socks5ReplySucceeded, targetConn.LocalAddr()); err != nil {
+		logger.LogError("socks_response", err, "host", host)
+		return
+	}
+
+	// Clear the handshake deadline; the tunnel manages its own idle timeout
+	conn.SetDeadline(time.Time{})
+
+	// Detect the tunneled protocol and apply its policy
+	tunnelConn, targetConn, _, err := h.server.inspectTunnel(conn, targetConn, host, requestID)
+	if err != nil {
+		logSpan.Warn("tunnel_rejected", "error", err)
+		metrics.RequestsTotal.WithLabelValues(method, "403").Inc()
+		return
+	}
+
+	// Register the tunnel so it can be evicted under fd pressure
+	entry := h.server.tunnels.add(host, ip, func() {
+		tunnelConn.Close()
+		targetConn.Close()
+	})
+	defer h.server.endTunnel(entry)
+
+	relay := startPhase(ctx, "relay", tracing.KindInternal)
+	bytesIn, bytesOut := h.server.connectHandler.tunnel(tunnelConn, entry.wrap(targetConn), h.server.cfg.EffectiveTunnelIdleTimeout(), requestID)
+	traceRelay(relay, bytesIn, bytesOut)
+
+	// Log and record metrics
+	duration := time.Since(start).Milliseconds()
+	h.server.logRequest(method, host, remote, connCtx.tenant, ip, 200, duration, bytesIn, bytesOut, connCtx.provenance)
+
+	h.server.stats.IncTotalRequests()
+	h.server.stats.AddBytesReceived(bytesIn)
+	h.server.stats.AddBytesSent(bytesOut)
+	h.server.quotas.AddBytes(connCtx.user, bytesIn+bytesOut)
+
+	metrics.RequestsTotal.WithLabelValues(method, "200").Inc()
+	metrics.RequestDuration.WithLabelValues(method).Observe(time.Since(start).Seconds())
+}
+
+// handshake negotiates the SOCKS5 authentication method and returns the client
+// identity, empty without authentication. The version byte has already been read.
+func (h *SOCKS5Handler) handshake(conn net.Conn) (auth.Identity, error) {
+	nMethods := make([]byte, 1)
+	if _, err := io.ReadFull(conn, nMethods); err != nil {
+		return auth.Identity{}, err
+	}
+
+	methods := make([]byte, nMethods[0])
+	if _, err := io.ReadFull(conn, methods); err != nil {
+		return auth.Identity{}, err
+	}
+
+	method := h.chooseMethod(methods)
+	if _, err := conn.Write([]byte{socks5Version, method}); err != nil {
+		return auth.Identity{}, err
+	}
+
+	switch method {
+	case socks5MethodNoAuth:
+		return auth.Identity{}, nil
+	case socks5MethodUserPass:
+		return h.authenticateUserPass(conn)
+	default:
+		return auth.Identity{}, errSOCKSNoMethod
+	}
+}
+
+// chooseMethod picks an authentication method from those offered by the client.
+// Username/password is preferred when offered so routing hints can be passed
+// in the username even when auth is not required.
+func (h *SOCKS5Handler) chooseMethod(methods []byte) byte {
+	authRequired := h.server.authRequired()
+	var noAuth bool
+	for _, m := range methods {
+		switch m {
+		case socks5MethodUserPass:
+			return socks5MethodUserPass
+		case socks5MethodNoAuth:
+			noAuth = true
+		}
+	}
+	if noAuth && !authRequired {
+		return socks5MethodNoAuth
+	}
+	return socks5MethodNone
+}
+
+// authenticateUserPass performs RFC 1929 username/password authentication
+// and returns the client identity.
+func (h *SOCKS5Handler) authenticateUserPass(conn net.Conn) (auth.Identity, error) {
+	header := make([]byte, 2)
+	if _, err := io.ReadFull(conn, header); err != nil {
+		return auth.Identity{}, err
+	}
+	if header[0] != socks5AuthVersion {
+		return auth.Identity{}, fmt.Errorf("unsupported auth version: %d", header[0])
+	}
+
+	user := make([]byte, header[1])
+	if _, err := io.ReadFull(conn, user); err != nil {
+		return auth.Identity{}, err
+	}
+
+	passLen := make([]byte, 1)
+	if _, err := io.ReadFull(conn, passLen); err != nil {
+		return auth.Identity{}, err
+	}
+	pass := make([]byte, passLen[0])
+	if _, err := io.ReadFull(conn, pass); err != nil {
+		return auth.Identity{}, err
+	}
+
+	id, ok := h.server.credentialIdentity(string(user), string(pass), conn.RemoteAddr().String())
+	if !ok {
+		conn.Write([]byte{socks5AuthVersion, 0x01})
+		return id, errSOCKSAuthFailed
+	}
+
+	if _, err := conn.Write([]byte{socks5AuthVersion, 0x00}); err != nil {
+		return auth.Identity{}, err
+	}
+	return id, nil
+}
+
+// readRequest reads a SOCKS5 request and returns the target as host:port.
+// Only the CONNECT command is supported.
+func (h *SOCKS5Handler) readRequest(conn net.Conn) (string, error) {
+	header := make([]byte, 4)
+	if _, err := io.ReadFull(conn, header); err != nil {
+		return "", err
+	}
+	if header[0] != socks5Version {
+		return "", fmt.Errorf("unsupported socks version: %d", header[0])
+	}
+	if header[1] != socks5CmdConnect {
+		h.writeReply(conn, socks5ReplyCommandNotSupported, nil)
+		return "", fmt.Errorf("unsupported socks command: %d", header[1])
+	}
+
+	var host string
+	switch header[3] {
+	case socks5AtypIPv4:
+		addr := make([]byte, net.IPv4len)
+		if _, err := io.ReadFull(conn, addr); err != nil {
+			return "", err
+		}
+		host = net.IP(addr).String()
+	case socks5AtypIPv6:
+		addr := make([]byte, net.IPv6len)
+		if _, err := io.ReadFull(conn, addr); err != nil {
+			return "", err
+		}
+		host = net.IP(addr).String()
+	case socks5AtypDomain:
+		length := make([]byte, 1)
+		if _, err := io.ReadFull(conn, length); err != nil {
+			return "", err
+		}
+		domain := make([]byte, length[0])
+		if _, err := io.ReadFull(conn, domain); err != nil {
+			return "", err
+		}
+		host = string(domain)
+	default:
+		h.writeReply(conn, socks5ReplyAddrNotSupported, nil)
+		return "", fmt.Errorf("unsupported address type: %d", header[3])
+	}
+
+	port := make([]byte, 2)
+	if _, err := io.ReadFull(conn, port); err != nil {
+		return "", err
+	}
+
+	return net.JoinHostPort(host, strconv.Itoa(int(binary.BigEndian.Uint16(port)))), nil
+}
+
+// writeReply writes a SOCKS5 reply with the given bound address.
+// A nil address is encoded as 0.0.0.0:0.
+func (h *SOCKS5Handler) writeReply(conn net.Conn, code byte, bound net.Addr) error {
+	ip := net.IPv4zero.To4()
+	port := 0
+	if tcpAddr, ok := bound.(*net.TCPAddr); ok {
+		ip = tcpAddr.IP
+		port = tcpAddr.Port
+	}
+
+	reply := []byte{socks5Version, code, 0x00}
+	if ip4 := ip.To4(); ip4 != nil {
+		reply = append(reply, socks5AtypIPv4)
+		reply = append(reply, ip4...)
+	} else {
+		reply = append(reply, socks5AtypIPv6)
+		reply = append(reply, ip.To16()...)
+	}
+	reply = binary.BigEndian.AppendUint16(reply, uint16(port))
+
+	_, err := conn.Write(reply)
+	return err
+}
+
+// dialErrorReply maps a dial error to a SOCKS5 reply code.
+func dialErrorReply(err error) byte {
+	switch {
+	case errors.Is(err, ErrDestinationNotAllowed):
+		return socks5ReplyNotAllowed
+	case errors.Is(err, syscall.ECONNREFUSED):
+		return socks5ReplyConnectionRefused
+	}
+	return socks5ReplyHostUnreachable
+}
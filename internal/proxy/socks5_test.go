@@ -0,0 +1,146 @@
+package proxy
+
+import (
+	"bufio"
+	"encoding/binary"
+	"io"
+	"net"
+	"net/http"
+	"strconv"
+	"strings"
+	"testing"
+)
+
+// startTestSOCKS5 starts a SOCKS5 listener for the server on a random port.
+func startTestSOCKS5(t *testing.T, server *Server) net.Listener {
+	t.Helper()
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	go server.socks5Handler.Serve(l)
+	t.Cleanup(func() { l.Close() })
+	return l
+}
+
+// socks5Connect performs a SOCKS5 handshake and CONNECT to target through addr.
+// If user is non-empty, username/password auth is offered.
+func socks5Connect(t *testing.T, addr, user, pass, target string) (net.Conn, byte) {
+	t.Helper()
+	conn, err := net.Dial("tcp", addr)
+	if err != nil {
+		t.Fatalf("failed to dial socks5 server: %v", err)
+	}
+
+	if user != "" {
+		conn.Write([]byte{socks5Version, 1, socks5MethodUserPass})
+	} else {
+		conn.Write([]byte{socks5Version, 1, socks5MethodNoAuth})
+	}
+
+	resp := make([]byte, 2)
+	if _, err := io.ReadFull(conn, resp); err != nil {
+		t.Fatalf("failed to read method selection: %v", err)
+	}
+	if resp[1] == socks5MethodNone {
+		return conn, socks5MethodNone
+	}
+
+	if resp[1] == socks5MethodUserPass {
+		msg := []byte{socks5AuthVersion, byte(len(user))}
+		msg = append(msg, user...)
+		msg = append(msg, byte(len(pass)))
+		msg = append(msg, pass...)
+		conn.Write(msg)
+		if _, err := io.ReadFull(conn, resp); err != nil {
+			t.Fatalf("failed to read auth response: %v", err)
+		}
+		if resp[1] != 0x00 {
+			return conn, socks5ReplyGeneralFailure
+		}
+	}
+
+	host, portStr, _ := net.SplitHostPort(target)
+	port, _ := strconv.Atoi(portStr)
+	req := []byte{socks5Version, socks5CmdConnect, 0x00, socks5AtypDomain, byte(len(host))}
+	req = append(req, host...)
+	req = binary.BigEndian.AppendUint16(req, uint16(port))
+	conn.Write(req)
+
+	reply := make([]byte, 10)
+	if _, err := io.ReadFull(conn, reply); err != nil {
+		t.Fatalf("failed to read connect reply: %v", err)
+	}
+	return conn, reply[1]
+}
+
+func TestSOCKS5_Connect(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	l := startTestSOCKS5(t, server)
+
+	target := strings.TrimPrefix(backend.URL, "http://")
+	conn, code := socks5Connect(t, l.Addr().String(), "", "", target)
+	defer conn.Close()
+
+	if code != socks5ReplySucceeded {
+		t.Fatalf("expected success reply, got %d", code)
+	}
+
+	conn.Write([]byte("GET / HTTP/1.1\r\nHost: " + target + "\r\nConnection: close\r\n\r\n"))
+	resp, err := http.ReadResponse(bufio.NewReader(conn), nil)
+	if err != nil {
+		t.Fatalf("failed to read response through tunnel: %v", err)
+	}
+	defer resp.Body.Close()
+
+	if resp.StatusCode != http.StatusOK {
+		t.Errorf("expected status 200, got %d", resp.StatusCode)
+	}
+}
+
+func TestSOCKS5_AuthRequired(t *testing.T) {
+	server := newTestServerWithAuth(t, "user:pass")
+	l := startTestSOCKS5(t, server)
+
+	conn, code := socks5Connect(t, l.Addr().String(), "", "", "example.com:80")
+	defer conn.Close()
+
+	if code != socks5MethodNone {
+		t.Errorf("expected no acceptable method, got %d", code)
+	}
+}
+
+func TestSOCKS5_AuthRejected(t *testing.T) {
+	server := newTestServerWithAuth(t, "user:pass")
+	l := startTestSOCKS5(t, server)
+
+	conn, code := socks5Connect(t, l.Addr().String(), "user", "wrong", "example.com:80")
+	defer conn.Close()
+
+	if code != socks5ReplyGeneralFailure {
+		t.Errorf("expected auth failure, got %d", code)
+	}
+}
+
+func TestSOCKS5_DialFailure(t *testing.T) {
+	// Reserve a port and close it so the dial is refused
+	ln, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	target := ln.Addr().String()
+	ln.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	l := startTestSOCKS5(t, server)
+
+	conn, code := socks5Connect(t, l.Addr().String(), "", "", target)
+	defer conn.Close()
+
+	if code == socks5ReplySucceeded {
+		t.Error("expected failure reply for refused connection")
+	}
+}
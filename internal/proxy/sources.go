@@ -0,0 +1,113 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"net"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+// sourcePreferenceTTL is how long a destination keeps being dialed from the
+// alternate source address that last reached it.
+const sourcePreferenceTTL = 10 * time.Minute
+
+// preferredSource is the source address that last reached a destination.
+type preferredSource struct {
+	ip      string
+	expires time.Time
+}
+
+// sourceFailover dials an outbound IP's connections from equivalent source
+// addresses on the same uplink. When a destination refuses one address, the
+// next is tried, and the one that got through is preferred for that
+// destination for sourcePreferenceTTL. Balancing, limits and metrics still
+// see only the outbound IP.
+type sourceFailover struct {
+	sources   map[string][]string
+	preferred map[string]preferredSource
+	clock     clock.Clock
+	lastSweep time.Time
+	mu        sync.Mutex
+}
+
+// newSourceFailover creates a sourceFailover for sources, keyed by outbound IP
+// with the IP itself first. Returns nil when no IP has alternates.
+func newSourceFailover(sources map[string][]string, c clock.Clock) *sourceFailover {
+	if len(sources) == 0 {
+		return nil
+	}
+	c = clock.OrReal(c)
+	return &sourceFailover{
+		sources:   sources,
+		preferred: make(map[string]preferredSource),
+		clock:     c,
+		lastSweep: c.Now(),
+	}
+}
+
+// order returns the source addresses to dial addr from for the outbound IP
+// ip, the preferred one first. Safe to call on a nil sourceFailover.
+func (f *sourceFailover) order(ip, addr string) []string {
+	if f == nil || len(f.sources[ip]) == 0 {
+		return []string{ip}
+	}
+	sources := f.sources[ip]
+	f.mu.Lock()
+	p, ok := f.preferred[sourceKey(ip, addr)]
+	f.mu.Unlock()
+	if !ok || !f.clock.Now().Before(p.expires) {
+		return sources
+	}
+	order := make([]string, 0, len(sources))
+	order = append(order, p.ip)
+	for _, src := range sources {
+		if src != p.ip {
+			order = append(order, src)
+		}
+	}
+	return order
+}
+
+// succeeded records that source reached addr for the outbound IP ip.
+// Safe to call on a nil sourceFailover.
+func (f *sourceFailover) succeeded(ip, addr, source string) {
+	if f == nil || len(f.sources[ip]) == 0 {
+		return
+	}
+	now := f.clock.Now()
+	key := sourceKey(ip, addr)
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	f.sweep(now)
+	if source == ip {
+		// The primary address works again; no need to remember anything
+		delete(f.preferred, key)
+		return
+	}
+	f.preferred[key] = preferredSource{ip: source, expires: now.Add(sourcePreferenceTTL)}
+}
+
+// sweep drops expired preferences at most once per sourcePreferenceTTL.
+// The caller holds mu.
+func (f *sourceFailover) sweep(now time.Time) {
+	if now.Sub(f.lastSweep) < sourcePreferenceTTL {
+		return
+	}
+	f.lastSweep = now
+	for key, p := range f.preferred {
+		if !now.Before(p.expires) {
+			delete(f.preferred, key)
+		}
+	}
+}
+
+// sourceKey identifies a destination host reached through the outbound IP ip.
+func sourceKey(ip, addr string) string {
+	host, _, err := net.SplitHostPort(addr)
+	if err != nil {
+		host = addr
+	}
+	return ip + "|" + host
+}
@@ -0,0 +1,70 @@
+package proxy
+
+import (
+	"net"
+	"slices"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+func TestSourceFailover_Order(t *testing.T) {
+	fake := clock.NewFake(time.Unix(1700000000, 0))
+	f := newSourceFailover(map[string][]string{"10.0.0.1": {"10.0.0.1", "10.0.0.11", "10.0.0.12"}}, fake)
+
+	if got := f.order("10.0.0.1", "example.com:443"); !slices.Equal(got, []string{"10.0.0.1", "10.0.0.11", "10.0.0.12"}) {
+		t.Errorf("order() = %v, want the primary first", got)
+	}
+	if got := f.order("10.0.0.2", "example.com:443"); !slices.Equal(got, []string{"10.0.0.2"}) {
+		t.Errorf("order() without alternates = %v, want only the IP", got)
+	}
+
+	// The alternate that got through is tried first for that host, on any port
+	f.succeeded("10.0.0.1", "example.com:443", "10.0.0.12")
+	if got := f.order("10.0.0.1", "example.com:80"); !slices.Equal(got, []string{"10.0.0.12", "10.0.0.1", "10.0.0.11"}) {
+		t.Errorf("order() after failover = %v, want the alternate first", got)
+	}
+	if got := f.order("10.0.0.1", "other.com:443"); got[0] != "10.0.0.1" {
+		t.Errorf("order() for another host = %v, want the primary first", got)
+	}
+
+	fake.Advance(sourcePreferenceTTL)
+	if got := f.order("10.0.0.1", "example.com:443"); got[0] != "10.0.0.1" {
+		t.Errorf("order() after the preference expired = %v, want the primary first", got)
+	}
+
+	var none *sourceFailover
+	if got := none.order("10.0.0.1", "example.com:443"); !slices.Equal(got, []string{"10.0.0.1"}) {
+		t.Errorf("nil order() = %v, want only the IP", got)
+	}
+}
+
+func TestDialer_SourceFailover(t *testing.T) {
+	ln, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ln.Close()
+	go func() {
+		for {
+			conn, err := ln.Accept()
+			if err != nil {
+				return
+			}
+			conn.Close()
+		}
+	}()
+
+	// 192.0.2.1 is not a local address, so dialing from it fails
+	f := newSourceFailover(map[string][]string{"192.0.2.1": {"192.0.2.1", "127.0.0.1"}}, nil)
+	d := NewDialer("192.0.2.1", 2*time.Second, time.Minute, withSourceFailover(f))
+	conn, err := d.Dial("tcp", ln.Addr().String())
+	if err != nil {
+		t.Fatalf("Dial() error = %v, want failover to 127.0.0.1", err)
+	}
+	conn.Close()
+	if got := f.order("192.0.2.1", ln.Addr().String()); got[0] != "127.0.0.1" {
+		t.Errorf("order() after failover = %v, want 127.0.0.1 first", got)
+	}
+}
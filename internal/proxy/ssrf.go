@@ -0,0 +1,103 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"errors"
+	"fmt"
+	"net"
+	"net/http"
+	"syscall"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// ErrPrivateDestination is returned when a dial would connect to a private,
+// loopback, link-local or metadata-service address while those are blocked.
+// It wraps ErrDestinationNotAllowed, so clients are refused the same way.
+var ErrPrivateDestination = fmt.Errorf("%w: private address", ErrDestinationNotAllowed)
+
+// metadataNets lists cloud metadata-service addresses outside the private,
+// loopback and link-local ranges (Alibaba Cloud's sits in the CGNAT range).
+var metadataNets, _ = netutil.ParseCIDRs([]string{"100.100.100.200"})
+
+// privateIP reports whether ip is in a range a proxy should not reach on a
+// client's behalf: RFC 1918 and unique-local, loopback, link-local (which
+// holds most metadata services), unspecified or a metadata-service address.
+func privateIP(ip net.IP) bool {
+	return ip.IsPrivate() || ip.IsLoopback() || ip.IsLinkLocalUnicast() || ip.IsUnspecified() ||
+		netutil.ContainsIP(metadataNets, ip)
+}
+
+// privateGuard refuses outbound connections to private addresses. It checks
+// the address actually dialed, after DNS resolution, so hostnames resolving to
+// internal addresses and DNS rebinding are caught as well as IP literals.
+type privateGuard struct {
+	allow []*net.IPNet
+}
+
+// newPrivateGuard creates a privateGuard that lets the allow IPs/CIDRs
+// through, or returns nil when blocking is disabled.
+func newPrivateGuard(enabled bool, allow []string) *privateGuard {
+	if !enabled {
+		return nil
+	}
+	g := &privateGuard{}
+	g.allow, _ = netutil.ParseCIDRs(allow) // validated with the config
+	return g
+}
+
+// check returns ErrPrivateDestination if address (ip:port) may not be dialed.
+// Safe to call on a nil privateGuard, which allows everything.
+func (g *privateGuard) check(address string) error {
+	if g == nil {
+		return nil
+	}
+	ip := net.ParseIP(netutil.ParseHost(address))
+	if ip == nil || !privateIP(ip) || netutil.ContainsIP(g.allow, ip) {
+		return nil
+	}
+	log.Warn("private_destination_blocked", "addr", address)
+	metrics.PrivateDestinationRejections.Inc()
+	return fmt.Errorf("%w: %s", ErrPrivateDestination, ip)
+}
+
+// control wraps a dialer Control function so the address is checked before
+// the socket connects.
+func (g *privateGuard) control(next func(network, address string, c syscall.RawConn) error) func(network, address string, c syscall.RawConn) error {
+	if g == nil {
+		return next
+	}
+	return func(network, address string, c syscall.RawConn) error {
+		if err := g.check(address); err != nil {
+			return err
+		}
+		if next == nil {
+			return nil
+		}
+		return next(network, address, c)
+	}
+}
+
+// dialFailed reports whether a dial error counts against the outbound IP.
+// Destinations refused by policy say nothing about the IP's health.
+func dialFailed(err error) bool {
+	return err != nil && !errors.Is(err, ErrDestinationNotAllowed)
+}
+
+// dialErrorMessage returns the client-facing message for a failed dial, or
+// fallback when the dial failed for other reasons than policy.
+func dialErrorMessage(err error, fallback string) string {
+	if errors.Is(err, ErrDestinationNotAllowed) {
+		return selectionErrorMessage(err)
+	}
+	return fallback
+}
+
+// dialErrorStatus returns the client-facing HTTP status for a failed dial.
+func dialErrorStatus(err error) int {
+	if errors.Is(err, ErrDestinationNotAllowed) {
+		return http.StatusForbidden
+	}
+	return http.StatusBadGateway
+}
@@ -0,0 +1,110 @@
+package proxy
+
+import (
+	"errors"
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+func TestPrivateIP(t *testing.T) {
+	tests := map[string]bool{
+		"10.1.2.3":        true,
+		"172.16.0.1":      true,
+		"192.168.1.1":     true,
+		"127.0.0.1":       true,
+		"169.254.169.254": true,
+		"100.100.100.200": true,
+		"0.0.0.0":         true,
+		"::1":             true,
+		"fe80::1":         true,
+		"fd00:ec2::254":   true,
+		"::ffff:10.0.0.1": true,
+		"8.8.8.8":         false,
+		"172.32.0.1":      false,
+		"100.64.0.1":      false,
+		"2001:4860::8888": false,
+	}
+	for addr, want := range tests {
+		if got := privateIP(net.ParseIP(addr)); got != want {
+			t.Errorf("privateIP(%s) = %v, want %v", addr, got, want)
+		}
+	}
+}
+
+func TestPrivateGuard_Check(t *testing.T) {
+	g := newPrivateGuard(true, []string{"10.20.0.0/16"})
+
+	if err := g.check("10.0.0.1:443"); !errors.Is(err, ErrPrivateDestination) {
+		t.Errorf("check(10.0.0.1) error = %v, want ErrPrivateDestination", err)
+	}
+	if !errors.Is(g.check("[::1]:80"), ErrDestinationNotAllowed) {
+		t.Error("expected a private destination to be refused as not allowed")
+	}
+	if err := g.check("10.20.3.4:443"); err != nil {
+		t.Errorf("check(allowed private address) error = %v", err)
+	}
+	if err := g.check("93.184.216.34:443"); err != nil {
+		t.Errorf("check(public address) error = %v", err)
+	}
+
+	disabled := newPrivateGuard(false, nil)
+	if err := disabled.check("127.0.0.1:80"); err != nil {
+		t.Errorf("disabled check() error = %v", err)
+	}
+}
+
+func TestDialer_RefusesPrivateDestination(t *testing.T) {
+	ln, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ln.Close()
+	_, port, _ := net.SplitHostPort(ln.Addr().String())
+
+	d := NewDialer("127.0.0.1", 2*time.Second, time.Minute, withPrivateGuard(newPrivateGuard(true, nil)))
+	// A hostname is checked after resolution, not by its name
+	for _, addr := range []string{ln.Addr().String(), net.JoinHostPort("localhost", port)} {
+		conn, err := d.Dial("tcp", addr)
+		if err == nil {
+			conn.Close()
+			t.Errorf("Dial(%s) succeeded, want it refused", addr)
+			continue
+		}
+		if !errors.Is(err, ErrPrivateDestination) {
+			t.Errorf("Dial(%s) error = %v, want ErrPrivateDestination", addr, err)
+		}
+		if dialFailed(err) {
+			t.Errorf("Dial(%s): a refused destination must not count against the outbound IP", addr)
+		}
+	}
+}
+
+func TestConnectHandler_RefusesPrivateDestination(t *testing.T) {
+	ln, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer ln.Close()
+
+	s := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	s.privateGuard = newPrivateGuard(true, nil)
+	proxy := httptest.NewServer(NewConnectHandler(s))
+	defer proxy.Close()
+
+	if got := connectThrough(t, proxy.Listener.Addr().String(), ln.Addr().String()); got != http.StatusForbidden {
+		t.Errorf("got status %d, want %d", got, http.StatusForbidden)
+	}
+
+	s.privateGuard = newPrivateGuard(true, []string{"127.0.0.1"})
+	go func() {
+		if c, err := ln.Accept(); err == nil {
+			c.Close()
+		}
+	}()
+	if got := connectThrough(t, proxy.Listener.Addr().String(), ln.Addr().String()); got != http.StatusOK {
+		t.Errorf("allowed private address: got status %d, want %d", got, http.StatusOK)
+	}
+}
@@ -0,0 +1,82 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"net/http"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+// maxStatusErrors caps the error breakdown in the status summary.
+const maxStatusErrors = 5
+
+// ListenerStatus describes one enabled listener.
+type ListenerStatus struct {
+	Name string `json:"name"`
+	Port int    `json:"port"`
+	TLS  bool   `json:"tls,omitempty"`
+}
+
+// EgressStatus describes one outbound IP.
+type EgressStatus struct {
+	IP                string `json:"ip"`
+	Healthy           bool   `json:"healthy"`
+	Draining          bool   `json:"draining"`
+	ActiveConnections int64  `json:"active_connections"`
+}
+
+// Status is a compact summary of the proxy for the status command.
+type Status struct {
+	Listeners         []ListenerStatus     `json:"listeners"`
+	Egresses          []EgressStatus       `json:"egresses"`
+	ActiveConnections int64                `json:"active_connections"`
+	ActiveTunnels     int                  `json:"active_tunnels"`
+	TotalRequests     int64                `json:"total_requests"`
+	TopErrors         []metrics.ErrorCount `json:"top_errors"`
+}
+
+// Status returns the current summary. health may be nil when health checks are disabled.
+func (s *Server) Status(health balancer.IPHealthChecker) Status {
+	stats := s.stats.GetStats()
+	status := Status{
+		ActiveConnections: stats.ActiveConnections,
+		ActiveTunnels:     s.ActiveTunnels(),
+		TotalRequests:     stats.TotalRequests,
+		TopErrors:         metrics.ErrorCounts(),
+	}
+	if len(status.TopErrors) > maxStatusErrors {
+		status.TopErrors = status.TopErrors[:maxStatusErrors]
+	}
+
+	status.Listeners = append(status.Listeners, ListenerStatus{Name: "proxy", Port: s.cfg.Port, TLS: s.tlsEnabled()})
+	for _, l := range []ListenerStatus{
+		{Name: "socks", Port: s.cfg.SOCKS5Port},
+		{Name: "transparent", Port: s.cfg.TransparentPort},
+		{Name: "forward", Port: s.cfg.ForwardPort},
+		{Name: "status", Port: s.cfg.PublicStatusPort},
+		{Name: "dns", Port: s.cfg.DNSForwarderPort},
+		{Name: "metrics", Port: s.cfg.MetricsPort},
+	} {
+		if l.Port > 0 {
+			status.Listeners = append(status.Listeners, l)
+		}
+	}
+
+	for _, ip := range s.cfg.IPs {
+		status.Egresses = append(status.Egresses, EgressStatus{
+			IP:                ip,
+			Healthy:           health == nil || health.IsHealthy(ip),
+			Draining:          s.balancer.IsDraining(ip),
+			ActiveConnections: stats.ConnectionsPerIP[ip],
+		})
+	}
+	return status
+}
+
+// StatusHandler returns an HTTP handler serving the status summary as JSON.
+func (s *Server) StatusHandler(health balancer.IPHealthChecker) http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		writeJSON(w, http.StatusOK, s.Status(health))
+	})
+}
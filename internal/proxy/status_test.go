@@ -0,0 +1,66 @@
+package proxy
+
+import (
+	"testing"
+)
+
+// fakeHealth reports the listed IPs as unhealthy.
+type fakeHealth map[string]bool
+
+func (f fakeHealth) IsHealthy(ip string) bool { return !f[ip] }
+
+func (f fakeHealth) GetHealthyIPs(ips []string) []string {
+	var healthy []string
+	for _, ip := range ips {
+		if f.IsHealthy(ip) {
+			healthy = append(healthy, ip)
+		}
+	}
+	return healthy
+}
+
+func TestServer_Status(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1", "127.0.0.2", "127.0.0.3"})
+	server.cfg.SOCKS5Port = 1080
+	server.balancer.Drain("127.0.0.3")
+
+	status := server.Status(fakeHealth{"127.0.0.2": true})
+
+	if len(status.Listeners) == 0 || status.Listeners[0].Name != "proxy" {
+		t.Fatalf("expected the proxy listener first, got %+v", status.Listeners)
+	}
+	var socks bool
+	for _, l := range status.Listeners {
+		if l.Name == "socks" && l.Port == 1080 {
+			socks = true
+		}
+		if l.Name == "transparent" {
+			t.Error("disabled listeners should not be reported")
+		}
+	}
+	if !socks {
+		t.Errorf("expected the SOCKS5 listener, got %+v", status.Listeners)
+	}
+
+	if len(status.Egresses) != 3 {
+		t.Fatalf("expected 3 egresses, got %d", len(status.Egresses))
+	}
+	want := []struct{ healthy, draining bool }{{true, false}, {false, false}, {true, true}}
+	for i, e := range status.Egresses {
+		if e.Healthy != want[i].healthy || e.Draining != want[i].draining {
+			t.Errorf("egress %s = healthy %v draining %v, want %v %v", e.IP, e.Healthy, e.Draining, want[i].healthy, want[i].draining)
+		}
+	}
+}
+
+func TestServer_StatusWithoutHealthChecks(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+
+	status := server.Status(nil)
+	if len(status.Egresses) != 1 || !status.Egresses[0].Healthy {
+		t.Errorf("expected IPs to be healthy without health checks, got %+v", status.Egresses)
+	}
+	if len(status.TopErrors) > maxStatusErrors {
+		t.Errorf("expected at most %d errors, got %d", maxStatusErrors, len(status.TopErrors))
+	}
+}
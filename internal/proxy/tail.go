@@ -0,0 +1,183 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"encoding/json"
+	"net/http"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// tailBuffer is how many records a slow tail client may fall behind before
+// records are dropped for it.
+const tailBuffer = 256
+
+// AccessRecord is an access-log record as streamed by /admin/tail.
+type AccessRecord struct {
+	Time time.Time `json:"time"`
+	// Method is the HTTP method, or CONNECT, SOCKS5, FORWARD or TRANSPARENT.
+	Method   string `json:"method"`
+	Host     string `json:"host"`
+	SourceIP string `json:"source_ip"`
+	// User is the proxy username without routing suffixes; empty when unauthenticated.
+	User       string `json:"user,omitempty"`
+	OutboundIP string `json:"outbound_ip"`
+	// ExitIP is the public address OutboundIP was last seen leaving from, if checked.
+	ExitIP     string `json:"exit_ip,omitempty"`
+	Status     int    `json:"status"`
+	DurationMS int64  `json:"duration_ms"`
+	BytesIn    int64  `json:"bytes_in"`
+	BytesOut   int64  `json:"bytes_out"`
+	// Selection tells how the outbound IP was chosen.
+	Selection string `json:"selection"`
+}
+
+// tailFilter selects the records a tail client receives. Empty fields match anything.
+type tailFilter struct {
+	user   string
+	host   string
+	egress string
+	errors bool
+}
+
+// match reports whether rec passes the filter. host matches the destination
+// and its subdomains.
+func (f tailFilter) match(rec AccessRecord) bool {
+	if f.user != "" && rec.User != f.user {
+		return false
+	}
+	if f.egress != "" && rec.OutboundIP != f.egress {
+		return false
+	}
+	if f.errors && rec.Status < 400 {
+		return false
+	}
+	if f.host != "" {
+		host := netutil.NormalizeHost(rec.Host)
+		want := netutil.NormalizeHost(f.host)
+		if host != want && !strings.HasSuffix(host, "."+want) {
+			return false
+		}
+	}
+	return true
+}
+
+// tailHub fans access records out to /admin/tail clients.
+type tailHub struct {
+	subs map[chan AccessRecord]tailFilter
+	mu   sync.RWMutex
+}
+
+// newTailHub creates a tailHub without clients.
+func newTailHub() *tailHub {
+	return &tailHub{subs: make(map[chan AccessRecord]tailFilter)}
+}
+
+// subscribe registers a client for the records passing f. The returned func
+// unregisters it.
+func (h *tailHub) subscribe(f tailFilter) (<-chan AccessRecord, func()) {
+	ch := make(chan AccessRecord, tailBuffer)
+	h.mu.Lock()
+	h.subs[ch] = f
+	h.mu.Unlock()
+	return ch, func() {
+		h.mu.Lock()
+		delete(h.subs, ch)
+		h.mu.Unlock()
+	}
+}
+
+// publish passes rec to every client whose filter it passes. Clients that
+// fell behind miss the record rather than slowing the proxy down.
+func (h *tailHub) publish(rec AccessRecord) {
+	h.mu.RLock()
+	defer h.mu.RUnlock()
+	for ch, f := range h.subs {
+		if !f.match(rec) {
+			continue
+		}
+		select {
+		case ch <- rec:
+		default:
+		}
+	}
+}
+
+// logRequest writes an access-log record, to the access log if one is open,
+// and streams it to tail clients. user is the proxy username without routing
+// suffixes, if any.
+func (s *Server) logRequest(method, host, remote, user, ip string, status int, duration, bytesIn, bytesOut int64, prov provenance, args ...any) {
+	rec := AccessRecord{
+		Time:       time.Now(),
+		Method:     method,
+		Host:       host,
+		SourceIP:   netutil.ParseHost(remote),
+		User:       user,
+		OutboundIP: ip,
+		Status:     status,
+		DurationMS: duration,
+		BytesIn:    bytesIn,
+		BytesOut:   bytesOut,
+		Selection:  prov.source,
+	}
+	if s.exitOf != nil {
+		rec.ExitIP = s.exitOf(ip)
+	}
+	if l := s.accessLog.Load(); l != nil {
+		l.write(rec)
+	} else {
+		logger.LogRequest(method, host, remote, ip, status, duration, bytesIn, bytesOut, append(prov.logArgs(), args...)...)
+	}
+	s.tail.publish(rec)
+}
+
+// TailHandler returns the handler for /admin/tail, which streams access
+// records as newline-delimited JSON until the client disconnects. The user,
+// host and egress query parameters and errors=true narrow the stream.
+func (s *Server) TailHandler() http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if r.Method != http.MethodGet {
+			w.Header().Set("Allow", "GET")
+			http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+			return
+		}
+		q := r.URL.Query()
+		filter := tailFilter{
+			user:   q.Get("user"),
+			host:   q.Get("host"),
+			egress: q.Get("egress"),
+			errors: q.Get("errors") == "true" || q.Get("errors") == "1",
+		}
+
+		// The stream outlives the metrics server's write timeout
+		rc := http.NewResponseController(w)
+		_ = rc.SetWriteDeadline(time.Time{})
+
+		records, unsubscribe := s.tail.subscribe(filter)
+		defer unsubscribe()
+
+		w.Header().Set("Content-Type", "application/x-ndjson")
+		w.Header().Set("Cache-Control", "no-cache")
+		w.WriteHeader(http.StatusOK)
+		_ = rc.Flush()
+
+		enc := json.NewEncoder(w)
+		for {
+			select {
+			case <-r.Context().Done():
+				return
+			case rec := <-records:
+				if err := enc.Encode(rec); err != nil {
+					return
+				}
+				if err := rc.Flush(); err != nil {
+					return
+				}
+			}
+		}
+	})
+}
@@ -0,0 +1,73 @@
+package proxy
+
+import (
+	"bufio"
+	"context"
+	"encoding/json"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+func TestTailFilter_Match(t *testing.T) {
+	rec := AccessRecord{Method: "CONNECT", Host: "api.example.com:443", User: "alice", OutboundIP: "10.0.0.1", Status: 200}
+	tests := []struct {
+		name   string
+		filter tailFilter
+		want   bool
+	}{
+		{"no filter", tailFilter{}, true},
+		{"user", tailFilter{user: "alice"}, true},
+		{"other user", tailFilter{user: "bob"}, false},
+		{"host", tailFilter{host: "api.example.com"}, true},
+		{"parent domain", tailFilter{host: "example.com"}, true},
+		{"other host", tailFilter{host: "ample.com"}, false},
+		{"egress", tailFilter{egress: "10.0.0.2"}, false},
+		{"errors only", tailFilter{errors: true}, false},
+	}
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			if got := tt.filter.match(rec); got != tt.want {
+				t.Errorf("match() = %v, want %v", got, tt.want)
+			}
+		})
+	}
+}
+
+func TestServer_TailHandler(t *testing.T) {
+	s := newTestServer(t)
+	srv := httptest.NewServer(s.TailHandler())
+	defer srv.Close()
+
+	ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+	defer cancel()
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, srv.URL+"?errors=true", nil)
+	if err != nil {
+		t.Fatal(err)
+	}
+	resp, err := http.DefaultClient.Do(req)
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer resp.Body.Close()
+	if ct := resp.Header.Get("Content-Type"); ct != "application/x-ndjson" {
+		t.Errorf("Content-Type = %q", ct)
+	}
+
+	// The subscription is registered before the headers are sent
+	s.logRequest("GET", "ok.example.com", "203.0.113.7:5000", "alice", "127.0.0.1", 200, 5, 0, 10, provenance{source: selectionFresh})
+	s.logRequest("GET", "fail.example.com", "203.0.113.7:5000", "alice", "127.0.0.1", 502, 5, 0, 10, provenance{source: selectionFresh})
+
+	scanner := bufio.NewScanner(resp.Body)
+	if !scanner.Scan() {
+		t.Fatalf("no record streamed: %v", scanner.Err())
+	}
+	var rec AccessRecord
+	if err := json.Unmarshal(scanner.Bytes(), &rec); err != nil {
+		t.Fatal(err)
+	}
+	if rec.Host != "fail.example.com" || rec.Status != 502 || rec.SourceIP != "203.0.113.7" || rec.User != "alice" {
+		t.Errorf("record = %+v, want the failed request only", rec)
+	}
+}
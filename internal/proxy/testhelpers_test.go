@@ -52,6 +52,8 @@ func newTestConfig(opts TestServerOptions) *config.Config {
 	cfg.LogLevel = "error"
 	cfg.LogFormat = "json"
 	cfg.Auth = opts.Auth
+	cfg.BlockPrivateDestinations = false // test origins listen on loopback
+	cfg.ConnectPorts = nil                // and on ephemeral ports
 	return cfg
 }
 
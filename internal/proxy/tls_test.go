@@ -0,0 +1,157 @@
+package proxy
+
+import (
+	"bufio"
+	"crypto/ecdsa"
+	"crypto/elliptic"
+	"crypto/rand"
+	"crypto/tls"
+	"crypto/x509"
+	"crypto/x509/pkix"
+	"encoding/pem"
+	"io"
+	"math/big"
+	"net"
+	"net/http"
+	"net/url"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+	"time"
+)
+
+// writeTestCertificate writes a self-signed certificate for 127.0.0.1 and returns the file paths.
+func writeTestCertificate(t *testing.T) (certFile, keyFile string) {
+	t.Helper()
+
+	key, err := ecdsa.GenerateKey(elliptic.P256(), rand.Reader)
+	if err != nil {
+		t.Fatalf("failed to generate key: %v", err)
+	}
+	tmpl := &x509.Certificate{
+		SerialNumber: big.NewInt(1),
+		Subject:      pkix.Name{CommonName: "outbound-lb-test"},
+		IPAddresses:  []net.IP{net.ParseIP("127.0.0.1")},
+		NotBefore:    time.Now().Add(-time.Hour),
+		NotAfter:     time.Now().Add(time.Hour),
+		KeyUsage:     x509.KeyUsageDigitalSignature,
+		ExtKeyUsage:  []x509.ExtKeyUsage{x509.ExtKeyUsageServerAuth},
+	}
+	der, err := x509.CreateCertificate(rand.Reader, tmpl, tmpl, &key.PublicKey, key)
+	if err != nil {
+		t.Fatalf("failed to create certificate: %v", err)
+	}
+	keyDER, err := x509.MarshalECPrivateKey(key)
+	if err != nil {
+		t.Fatalf("failed to marshal key: %v", err)
+	}
+
+	dir := t.TempDir()
+	certFile = filepath.Join(dir, "cert.pem")
+	keyFile = filepath.Join(dir, "key.pem")
+	os.WriteFile(certFile, pem.EncodeToMemory(&pem.Block{Type: "CERTIFICATE", Bytes: der}), 0o600)
+	os.WriteFile(keyFile, pem.EncodeToMemory(&pem.Block{Type: "EC PRIVATE KEY", Bytes: keyDER}), 0o600)
+	return certFile, keyFile
+}
+
+func TestServer_TLSListener(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	certFile, keyFile := writeTestCertificate(t)
+
+	// Rebuild the server once TLS is configured so the listener is set up for it
+	base := newTestServerWithOptions(t, DefaultTestServerOptions())
+	base.cfg.TLSCertFile = certFile
+	base.cfg.TLSKeyFile = keyFile
+	server := NewServer(base.cfg, base.balancer, base.limiter, base.stats)
+
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	go server.Serve(l)
+	defer server.httpServer.Close()
+
+	proxyURL, _ := url.Parse("https://" + l.Addr().String())
+	client := &http.Client{
+		Transport: &http.Transport{
+			Proxy:           http.ProxyURL(proxyURL),
+			TLSClientConfig: &tls.Config{InsecureSkipVerify: true},
+		},
+		Timeout: 5 * time.Second,
+	}
+
+	resp, err := client.Get(backend.URL)
+	if err != nil {
+		t.Fatalf("request through TLS proxy failed: %v", err)
+	}
+	defer resp.Body.Close()
+
+	if resp.StatusCode != http.StatusOK {
+		t.Errorf("expected status 200, got %d", resp.StatusCode)
+	}
+}
+
+func TestServer_HTTP2Connect(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	certFile, keyFile := writeTestCertificate(t)
+
+	base := newTestServerWithOptions(t, DefaultTestServerOptions())
+	base.cfg.TLSCertFile = certFile
+	base.cfg.TLSKeyFile = keyFile
+	server := NewServer(base.cfg, base.balancer, base.limiter, base.stats)
+
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	go server.Serve(l)
+	defer server.httpServer.Close()
+
+	transport := &http.Transport{
+		TLSClientConfig:   &tls.Config{InsecureSkipVerify: true},
+		ForceAttemptHTTP2: true,
+	}
+	defer transport.CloseIdleConnections()
+
+	target := strings.TrimPrefix(backend.URL, "http://")
+
+	// Open two tunnels over the same HTTP/2 connection
+	for i := 0; i < 2; i++ {
+		pr, pw := io.Pipe()
+		req := &http.Request{
+			Method: http.MethodConnect,
+			URL:    &url.URL{Scheme: "https", Host: l.Addr().String()},
+			Host:   target,
+			Header: make(http.Header),
+			Body:   pr,
+		}
+
+		resp, err := transport.RoundTrip(req)
+		if err != nil {
+			t.Fatalf("CONNECT failed: %v", err)
+		}
+		if resp.ProtoMajor != 2 {
+			t.Fatalf("expected HTTP/2, got %s", resp.Proto)
+		}
+		if resp.StatusCode != http.StatusOK {
+			t.Fatalf("expected 200, got %d", resp.StatusCode)
+		}
+
+		go pw.Write([]byte("GET / HTTP/1.1\r\nHost: " + target + "\r\nConnection: close\r\n\r\n"))
+		tunneled, err := http.ReadResponse(bufio.NewReader(resp.Body), nil)
+		if err != nil {
+			t.Fatalf("failed to read response through tunnel: %v", err)
+		}
+		if tunneled.StatusCode != http.StatusOK {
+			t.Errorf("expected tunneled status 200, got %d", tunneled.StatusCode)
+		}
+		tunneled.Body.Close()
+		pw.Close()
+		resp.Body.Close()
+	}
+}
@@ -0,0 +1,66 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"errors"
+	"net/http"
+
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+)
+
+// traceparentHeader carries the W3C trace context.
+const traceparentHeader = "Traceparent"
+
+// errAuthFailed is recorded on the auth span of rejected clients.
+var errAuthFailed = errors.New("authentication failed")
+
+// SetTracer records a span for every proxied connection, with child spans
+// for its phases. It must be called before Start.
+func (s *Server) SetTracer(t *tracing.Tracer) {
+	s.tracer = t
+}
+
+// startSpan begins the span of a connection named name, continuing the trace
+// of the client's traceparent header when r has one. It returns nil when
+// tracing is off.
+func (s *Server) startSpan(name string, r *http.Request, requestID string) *tracing.Span {
+	if s.tracer == nil {
+		return nil
+	}
+	var parent tracing.SpanContext
+	if r != nil {
+		parent, _ = tracing.ParseTraceparent(r.Header.Get(traceparentHeader))
+	}
+	span := s.tracer.Start(name, tracing.KindServer, parent)
+	span.SetAttr("request_id", requestID)
+	return span
+}
+
+// startPhase begins a child span named name of the connection span in ctx.
+func startPhase(ctx context.Context, name string, kind int) *tracing.Span {
+	return tracing.SpanFromContext(ctx).Child(name, kind)
+}
+
+// endPhase ends a phase span, recording err as its failure.
+func endPhase(span *tracing.Span, err error) {
+	span.SetError(err)
+	span.End()
+}
+
+// propagateTrace sends span's context upstream in outReq, so the next hop's
+// spans join the trace. The client's own traceparent is forwarded unchanged
+// when tracing is off.
+func propagateTrace(outReq *http.Request, span *tracing.Span) {
+	if sc := span.Context(); sc.IsValid() {
+		outReq.Header.Set(traceparentHeader, sc.Traceparent())
+	}
+}
+
+// traceRelay records the relay phase of a tunnel that moved bytesIn from the
+// client and bytesOut to it.
+func traceRelay(span *tracing.Span, bytesIn, bytesOut int64) {
+	span.SetAttr("bytes_in", bytesIn)
+	span.SetAttr("bytes_out", bytesOut)
+	span.End()
+}
@@ -0,0 +1,47 @@
+package proxy
+
+import (
+	"net/http"
+	"net/http/httptest"
+	"testing"
+
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+)
+
+func TestHandler_TraceParent(t *testing.T) {
+	var got string
+	backend := newTestBackendWithHandler(t, func(w http.ResponseWriter, r *http.Request) {
+		got = r.Header.Get("traceparent")
+		w.WriteHeader(http.StatusOK)
+	})
+	defer backend.Close()
+
+	const incoming = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+	send := func(handler *Handler) {
+		t.Helper()
+		r := httptest.NewRequest(http.MethodGet, backend.URL+"/", nil)
+		r.Header.Set("traceparent", incoming)
+		w := httptest.NewRecorder()
+		handler.ServeHTTP(w, r)
+		if w.Code != http.StatusOK {
+			t.Fatalf("status = %d, want 200", w.Code)
+		}
+	}
+
+	// Without a tracer the client's trace context passes through untouched
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	send(NewHandler(server))
+	if got != incoming {
+		t.Errorf("traceparent = %q, want %q forwarded unchanged", got, incoming)
+	}
+
+	// With one, the upstream sees the proxy's span as its parent in the same trace
+	server = newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.SetTracer(tracing.NewTracer(nil))
+	send(NewHandler(server))
+	sc, ok := tracing.ParseTraceparent(got)
+	parent, _ := tracing.ParseTraceparent(incoming)
+	if !ok || sc.TraceID != parent.TraceID || sc.SpanID == parent.SpanID || !sc.Sampled {
+		t.Errorf("traceparent = %q, want a sampled child of %q", got, incoming)
+	}
+}
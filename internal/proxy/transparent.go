@@ -0,0 +1,203 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"errors"
+	"net"
+	"strconv"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// transparentMethod labels transparent connections in logs and metrics.
+const transparentMethod = "TRANSPARENT"
+
+var (
+	// errNoOriginalDst is returned when a connection's original destination cannot be recovered.
+	errNoOriginalDst = errors.New("original destination not available")
+	// errTransparentLoop is returned when an intercepted connection targets the transparent listener itself.
+	errTransparentLoop = errors.New("connection targets the transparent listener")
+)
+
+// TransparentHandler serves connections diverted to the proxy by iptables
+// REDIRECT or TPROXY rules. The original destination is recovered from the
+// socket and the connection is relayed through the balanced pool.
+type TransparentHandler struct {
+	server *Server
+	addr   *net.TCPAddr
+}
+
+// NewTransparentHandler creates a new TransparentHandler.
+func NewTransparentHandler(server *Server) *TransparentHandler {
+	return &TransparentHandler{server: server}
+}
+
+// Serve accepts connections on l until it is closed.
+func (h *TransparentHandler) Serve(l net.Listener) error {
+	h.addr, _ = l.Addr().(*net.TCPAddr)
+	for {
+		conn, err := l.Accept()
+		if err != nil {
+			if errors.Is(err, net.ErrClosed) {
+				return nil
+			}
+			var netErr net.Error
+			if errors.As(err, &netErr) && netErr.Timeout() {
+				time.Sleep(10 * time.Millisecond)
+				continue
+			}
+			return err
+		}
+		go h.ServeConn(conn)
+	}
+}
+
+// ServeConn handles a single intercepted connection.
+func (h *TransparentHandler) ServeConn(conn net.Conn) {
+	defer conn.Close()
+
+	start := time.Now()
+	requestID := GenerateRequestID()
+	remote := conn.RemoteAddr().String()
+
+	logSpan := log.Span("request_id", requestID, "remote", remote)
+
+	// Trace the connection; intercepted traffic carries no trace context, so it starts a trace
+	span := h.server.startSpan("proxy.transparent", nil, requestID)
+	defer span.End()
+	span.SetAttr("client.address", netutil.ParseHost(remote))
+	ctx := tracing.ContextWithSpan(context.Background(), span)
+
+	dst, err := h.destination(conn)
+	if err != nil {
+		span.SetError(err)
+		logSpan.Warn("transparent_rejected", "error", err)
+		metrics.RequestsTotal.WithLabelValues(transparentMethod, "403").Inc()
+		return
+	}
+	host := dst.String()
+
+	logSpan.Logger = logSpan.With("host", host)
+	logSpan.Trace("transparent_connection_accepted")
+
+	// With sniffing enabled the TLS SNI or HTTP Host names the route instead of the bare IP
+	clientConn, _, sniffed, err := h.server.inspectTunnel(conn, nil, host, requestID)
+	if err != nil {
+		span.SetError(err)
+		logSpan.Warn("tunnel_rejected", "error", err)
+		metrics.RequestsTotal.WithLabelValues(transparentMethod, "403").Inc()
+		return
+	}
+	route := host
+	if sniffed.ServerName != "" {
+		route = net.JoinHostPort(netutil.ParseHost(sniffed.ServerName), strconv.Itoa(dst.Port))
+	}
+	span.SetAttr("server.address", route)
+
+	// Select outbound IP and acquire a connection slot
+	hints := RoutingHints{ClientIP: netutil.ParseHost(remote)}
+	selectSpan := startPhase(ctx, "select", tracing.KindInternal)
+	connCtx, err := h.server.AcquireConnection(route, requestID, hints)
+	endPhase(selectSpan, err)
+	if err != nil {
+		logSpan.Trace("transparent_acquire_failed", "route", route, "error", err)
+		if errors.Is(err, ErrDestinationNotAllowed) {
+			metrics.RequestsTotal.WithLabelValues(transparentMethod, "403").Inc()
+			return
+		}
+		metrics.LimitRejections.WithLabelValues(limitRejectionType(err)).Inc()
+		metrics.RequestsTotal.WithLabelValues(transparentMethod, "503").Inc()
+		return
+	}
+	defer connCtx.Release()
+	ip := connCtx.IP
+
+	metrics.TunnelConnections.Inc()
+
+	// Connect to the original destination; it is already an address, so no lookup is needed
+	dialer := NewDialer(ip, h.server.cfg.EffectiveConnectTimeout(), h.server.cfg.IdleTimeout, h.server.outboundDialOptions()...)
+	dialStart := time.Now()
+	targetConn, err := dialer.DialContext(ctx, "tcp", host)
+	h.server.slo.Observe(route, ip, time.Since(dialStart), dialFailed(err))
+	h.server.recordOutcome(ip, dialFailed(err))
+	if err != nil {
+		logger.LogError("transparent_dial", err, "host", host, "ip", ip)
+		metrics.RequestsTotal.WithLabelValues(transparentMethod, strconv.Itoa(dialErrorStatus(err))).Inc()
+		return
+	}
+	defer targetConn.Close()
+	targetConn = h.server.watchFTP(targetConn, hints.ClientIP, host, ip)
+	targetConn = h.server.shapeTunnel(targetConn, ip, route, hints)
+
+	// Register the tunnel so it can be evicted under fd pressure
+	entry := h.server.tunnels.add(route, ip, func() {
+		clientConn.Close()
+		targetConn.Close()
+	})
+	defer h.server.endTunnel(entry)
+
+	relay := startPhase(ctx, "relay", tracing.KindInternal)
+	bytesIn, bytesOut := h.server.connectHandler.tunnel(clientConn, entry.wrap(targetConn), h.server.cfg.EffectiveTunnelIdleTimeout(), requestID)
+	traceRelay(relay, bytesIn, bytesOut)
+
+	// Log and record metrics
+	duration := time.Since(start).Milliseconds()
+	h.server.logRequest(transparentMethod, route, remote, connCtx.tenant, ip, 200, duration, bytesIn, bytesOut, connCtx.provenance)
+
+	h.server.stats.IncTotalRequests()
+	h.server.stats.AddBytesReceived(bytesIn)
+	h.server.stats.AddBytesSent(bytesOut)
+	h.server.quotas.AddBytes(connCtx.user, bytesIn+bytesOut)
+
+	metrics.RequestsTotal.WithLabelValues(transparentMethod, "200").Inc()
+	metrics.RequestDuration.WithLabelValues(transparentMethod).Observe(time.Since(start).Seconds())
+}
+
+// destination returns the address the client originally connected to.
+// REDIRECT-ed connections carry it in SO_ORIGINAL_DST; TPROXY-diverted ones
+// keep it as their local address.
+func (h *TransparentHandler) destination(conn net.Conn) (*net.TCPAddr, error) {
+	dst, err := originalDst(conn)
+	if err != nil {
+		local, ok := conn.LocalAddr().(*net.TCPAddr)
+		if !ok {
+			return nil, errNoOriginalDst
+		}
+		dst = local
+	}
+	if h.isSelf(dst) {
+		return nil, errTransparentLoop
+	}
+	return dst, nil
+}
+
+// isSelf reports whether dst is the transparent listener itself, which happens
+// when a client connects directly or the proxy's own traffic is intercepted.
+func (h *TransparentHandler) isSelf(dst *net.TCPAddr) bool {
+	if h.addr == nil || dst.Port != h.addr.Port {
+		return false
+	}
+	if !h.addr.IP.IsUnspecified() {
+		return dst.IP.Equal(h.addr.IP)
+	}
+	return dst.IP.IsLoopback() || isLocalIP(dst.IP)
+}
+
+// isLocalIP reports whether ip is assigned to a local interface.
+func isLocalIP(ip net.IP) bool {
+	addrs, err := net.InterfaceAddrs()
+	if err != nil {
+		return false
+	}
+	for _, addr := range addrs {
+		if ipNet, ok := addr.(*net.IPNet); ok && ipNet.IP.Equal(ip) {
+			return true
+		}
+	}
+	return false
+}
@@ -0,0 +1,100 @@
+//go:build linux
+
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"context"
+	"encoding/binary"
+	"net"
+	"syscall"
+)
+
+// Netfilter socket options (linux/netfilter_ipv4.h, linux/netfilter_ipv6/ip6_tables.h).
+const (
+	soOriginalDst     = 80
+	ip6tSOOriginalDst = 80
+	ipv6Transparent   = 75
+)
+
+// listenTransparent listens on addr with IP_TRANSPARENT set so TPROXY-diverted
+// connections can be accepted. Setting the option needs CAP_NET_ADMIN; without it
+// the listener still serves REDIRECT traffic.
+func listenTransparent(addr string) (net.Listener, error) {
+	lc := net.ListenConfig{
+		Control: func(network, _ string, c syscall.RawConn) error {
+			var sockErr error
+			err := c.Control(func(fd uintptr) {
+				// Dual-stack sockets need both options to accept IPv4 and IPv6 diversions
+				sockErr = syscall.SetsockoptInt(int(fd), syscall.IPPROTO_IP, syscall.IP_TRANSPARENT, 1)
+				if sockErr == nil && network == "tcp6" {
+					sockErr = syscall.SetsockoptInt(int(fd), syscall.IPPROTO_IPV6, ipv6Transparent, 1)
+				}
+			})
+			if err != nil {
+				return err
+			}
+			if sockErr != nil {
+				log.Warn("tproxy unavailable, serving REDIRECT traffic only", "error", sockErr)
+			}
+			return nil
+		},
+	}
+	return lc.Listen(context.Background(), "tcp", addr)
+}
+
+// originalDst returns the pre-NAT destination of a REDIRECT-ed connection.
+// It fails for connections that were not NATed, including TPROXY-diverted ones.
+func originalDst(conn net.Conn) (*net.TCPAddr, error) {
+	tcpConn, ok := conn.(*net.TCPConn)
+	if !ok {
+		return nil, errNoOriginalDst
+	}
+	raw, err := tcpConn.SyscallConn()
+	if err != nil {
+		return nil, err
+	}
+
+	ipv6 := false
+	if local, ok := conn.LocalAddr().(*net.TCPAddr); ok {
+		ipv6 = local.IP.To4() == nil
+	}
+
+	var addr *net.TCPAddr
+	var sockErr error
+	err = raw.Control(func(fd uintptr) {
+		if ipv6 {
+			// sockaddr_in6 fits in the leading field of ip6_mtuinfo
+			info, err := syscall.GetsockoptIPv6MTUInfo(int(fd), syscall.IPPROTO_IPV6, ip6tSOOriginalDst)
+			if err != nil {
+				sockErr = err
+				return
+			}
+			port := binary.NativeEndian.AppendUint16(nil, info.Addr.Port)
+			addr = &net.TCPAddr{
+				IP:   net.IP(append([]byte(nil), info.Addr.Addr[:]...)),
+				Port: int(binary.BigEndian.Uint16(port)),
+			}
+			return
+		}
+
+		// sockaddr_in fits in the 16-byte buffer of ipv6_mreq: family(2) port(2) addr(4)
+		mreq, err := syscall.GetsockoptIPv6Mreq(int(fd), syscall.IPPROTO_IP, soOriginalDst)
+		if err != nil {
+			sockErr = err
+			return
+		}
+		b := mreq.Multiaddr
+		addr = &net.TCPAddr{
+			IP:   net.IPv4(b[4], b[5], b[6], b[7]),
+			Port: int(binary.BigEndian.Uint16(b[2:4])),
+		}
+	})
+	if err != nil {
+		return nil, err
+	}
+	if sockErr != nil {
+		return nil, sockErr
+	}
+	return addr, nil
+}
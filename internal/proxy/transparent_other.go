@@ -0,0 +1,16 @@
+//go:build !linux
+
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import "net"
+
+// listenTransparent listens on addr. TPROXY is only supported on Linux.
+func listenTransparent(addr string) (net.Listener, error) {
+	return net.Listen("tcp", addr)
+}
+
+// originalDst reports that REDIRECT destinations cannot be recovered on this platform.
+func originalDst(net.Conn) (*net.TCPAddr, error) {
+	return nil, errNoOriginalDst
+}
@@ -0,0 +1,93 @@
+package proxy
+
+import (
+	"bufio"
+	"net"
+	"net/http"
+	"strings"
+	"testing"
+	"time"
+)
+
+// divertedConn reports a foreign local address, as TPROXY-diverted connections do.
+type divertedConn struct {
+	net.Conn
+	local net.Addr
+}
+
+// LocalAddr returns the original destination.
+func (c *divertedConn) LocalAddr() net.Addr {
+	return c.local
+}
+
+func TestTransparent_DivertedConnection(t *testing.T) {
+	backend := newTestBackend(t)
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+
+	dst, err := net.ResolveTCPAddr("tcp", strings.TrimPrefix(backend.URL, "http://"))
+	if err != nil {
+		t.Fatalf("failed to resolve backend: %v", err)
+	}
+
+	client, conn := net.Pipe()
+	defer client.Close()
+	go server.transparentHandler.ServeConn(&divertedConn{Conn: conn, local: dst})
+
+	client.Write([]byte("GET / HTTP/1.1\r\nHost: " + dst.String() + "\r\nConnection: close\r\n\r\n"))
+	resp, err := http.ReadResponse(bufio.NewReader(client), nil)
+	if err != nil {
+		t.Fatalf("failed to read response through transparent relay: %v", err)
+	}
+	defer resp.Body.Close()
+
+	if resp.StatusCode != http.StatusOK {
+		t.Errorf("expected status 200, got %d", resp.StatusCode)
+	}
+}
+
+func TestTransparent_RejectsDirectConnection(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+
+	l, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	defer l.Close()
+	go server.transparentHandler.Serve(l)
+
+	conn, err := net.Dial("tcp", l.Addr().String())
+	if err != nil {
+		t.Fatalf("failed to dial transparent listener: %v", err)
+	}
+	defer conn.Close()
+
+	// A connection that was not intercepted targets the listener itself and is closed
+	conn.SetReadDeadline(time.Now().Add(2 * time.Second))
+	if _, err := conn.Read(make([]byte, 1)); err == nil {
+		t.Error("expected direct connection to be closed")
+	}
+}
+
+func TestTransparentHandler_IsSelf(t *testing.T) {
+	h := &TransparentHandler{addr: &net.TCPAddr{IP: net.ParseIP("10.0.0.1"), Port: 3129}}
+
+	tests := []struct {
+		name string
+		dst  *net.TCPAddr
+		want bool
+	}{
+		{"listener address", &net.TCPAddr{IP: net.ParseIP("10.0.0.1"), Port: 3129}, true},
+		{"other port", &net.TCPAddr{IP: net.ParseIP("10.0.0.1"), Port: 443}, false},
+		{"other host", &net.TCPAddr{IP: net.ParseIP("93.184.216.34"), Port: 3129}, false},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			if got := h.isSelf(tt.dst); got != tt.want {
+				t.Errorf("isSelf(%v) = %v, want %v", tt.dst, got, tt.want)
+			}
+		})
+	}
+}
@@ -3,28 +3,239 @@ package proxy
 
 import (
 	"context"
+	"crypto/tls"
 	"net"
 	"net/http"
 	"sync"
+	"syscall"
 	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/dns"
+	"github.com/cr0hn/outbound-lb/internal/limiter"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/internal/tracing"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
 )
 
+// DialOption customizes connections created by Dialer and TransportPool.
+type DialOption func(*dialOptions)
+
+// dialOptions holds the settings applied to outbound connections.
+type dialOptions struct {
+	shaper       *limiter.BandwidthShaper
+	resolver     *dns.Resolver
+	verifier     TLSVerifier
+	routes       map[string]netutil.Route
+	tlsHandshake time.Duration
+	countBytes   func(ip string, n int64)
+	sources      *sourceFailover
+	guard        *privateGuard
+}
+
+// TLSVerifier verifies the certificate chain presented by an upstream TLS
+// server. It replaces the default verification against the system roots, so
+// it must check the chain itself; returning an error aborts the handshake.
+type TLSVerifier func(cs tls.ConnectionState) error
+
+// WithShaper applies per-IP bandwidth caps to outbound connections.
+func WithShaper(shaper *limiter.BandwidthShaper) DialOption {
+	return func(o *dialOptions) {
+		o.shaper = shaper
+	}
+}
+
+// WithResolver resolves target hostnames through the given resolver instead of the dialer's.
+func WithResolver(resolver *dns.Resolver) DialOption {
+	return func(o *dialOptions) {
+		o.resolver = resolver
+	}
+}
+
+// WithTLSVerifier verifies upstream TLS servers with v instead of the system roots.
+func WithTLSVerifier(v TLSVerifier) DialOption {
+	return func(o *dialOptions) {
+		o.verifier = v
+	}
+}
+
+// WithRoutes applies per-IP policy routing (fwmark or device) and DSCP marking to outbound connections.
+func WithRoutes(routes map[string]netutil.Route) DialOption {
+	return func(o *dialOptions) {
+		o.routes = routes
+	}
+}
+
+// WithTLSHandshakeTimeout bounds TLS handshakes with upstream servers (0 = DefaultTLSHandshakeTimeout).
+func WithTLSHandshakeTimeout(d time.Duration) DialOption {
+	return func(o *dialOptions) {
+		o.tlsHandshake = d
+	}
+}
+
+// WithByteCounter calls count with the bytes read from or written to every outbound connection.
+func WithByteCounter(count func(ip string, n int64)) DialOption {
+	return func(o *dialOptions) {
+		o.countBytes = count
+	}
+}
+
+// withSourceFailover dials each outbound IP's connections from its alternate
+// source addresses when the destination refuses the IP itself.
+func withSourceFailover(f *sourceFailover) DialOption {
+	return func(o *dialOptions) {
+		o.sources = f
+	}
+}
+
+// withPrivateGuard refuses connections to addresses the guard blocks.
+func withPrivateGuard(g *privateGuard) DialOption {
+	return func(o *dialOptions) {
+		o.guard = g
+	}
+}
+
+// control returns the Control function of dialers bound to the outbound IP ip.
+func (o dialOptions) control(ip string) func(network, address string, c syscall.RawConn) error {
+	return o.guard.control(o.routes[ip].Control())
+}
+
+// tlsHandshakeTimeout returns the upstream TLS handshake timeout.
+func (o dialOptions) tlsHandshakeTimeout() time.Duration {
+	if o.tlsHandshake > 0 {
+		return o.tlsHandshake
+	}
+	return DefaultTLSHandshakeTimeout
+}
+
+// tlsConfig returns the client TLS configuration for upstream connections, or
+// nil to use the defaults.
+func (o dialOptions) tlsConfig() *tls.Config {
+	if o.verifier == nil {
+		return nil
+	}
+	// Default verification is skipped; VerifyConnection still runs on every handshake
+	return &tls.Config{
+		InsecureSkipVerify: true,
+		VerifyConnection:   o.verifier,
+	}
+}
+
+// newDialOptions builds dialOptions from the given options.
+func newDialOptions(opts []DialOption) dialOptions {
+	var o dialOptions
+	for _, opt := range opts {
+		opt(&o)
+	}
+	return o
+}
+
+// dial connects to addr with dialer, resolving the host through the configured resolver.
+// Only addresses of the same family as the dialer's local address are tried.
+func (o dialOptions) dial(ctx context.Context, dialer *net.Dialer, network, addr string) (net.Conn, error) {
+	if o.resolver == nil {
+		return dialer.DialContext(ctx, network, addr)
+	}
+	host, port, err := net.SplitHostPort(addr)
+	if err != nil || net.ParseIP(host) != nil {
+		return dialer.DialContext(ctx, network, addr)
+	}
+
+	resolve := startPhase(ctx, "resolve", tracing.KindInternal)
+	resolve.SetAttr("server.address", host)
+	addrs, err := o.resolver.LookupHost(ctx, host)
+	resolve.SetAttr("addresses", len(addrs))
+	endPhase(resolve, err)
+	if err != nil {
+		return nil, err
+	}
+
+	localIPv4 := true
+	if local, ok := dialer.LocalAddr.(*net.TCPAddr); ok && local.IP != nil {
+		localIPv4 = local.IP.To4() != nil
+	}
+
+	var firstErr error
+	for _, a := range addrs {
+		ip := net.ParseIP(a)
+		if ip == nil || (ip.To4() != nil) != localIPv4 {
+			continue
+		}
+		conn, err := dialer.DialContext(ctx, network, net.JoinHostPort(a, port))
+		if err == nil {
+			return conn, nil
+		}
+		if firstErr == nil {
+			firstErr = err
+		}
+	}
+	if firstErr == nil {
+		firstErr = &net.AddrError{Err: "no suitable address found", Addr: host}
+	}
+	return nil, firstErr
+}
+
+// dialEgress connects to addr through the outbound IP ip. With source
+// failover, a failed dial is retried from the IP's alternate source addresses
+// in turn. newDialer returns the dialer bound to a source address.
+func (o dialOptions) dialEgress(ctx context.Context, ip string, newDialer func(source string) *net.Dialer, network, addr string) (net.Conn, error) {
+	connect := startPhase(ctx, "connect", tracing.KindClient)
+	connect.SetAttr("outbound_ip", ip)
+	connect.SetAttr("server.address", addr)
+	ctx = tracing.ContextWithSpan(ctx, connect)
+	var firstErr error
+	start := time.Now()
+	for i, source := range o.sources.order(ip, addr) {
+		conn, err := o.dial(ctx, newDialer(source), network, addr)
+		if err == nil {
+			connect.End()
+			metrics.BackendConnectDuration.WithLabelValues(ip).Observe(time.Since(start).Seconds())
+			if i > 0 {
+				log.Debug("source_failover", "ip", ip, "source", source, "addr", addr)
+				metrics.SourceFailovers.WithLabelValues(ip).Inc()
+			}
+			o.sources.succeeded(ip, addr, source)
+			return conn, nil
+		}
+		if firstErr == nil {
+			firstErr = err
+		}
+		if ctx.Err() != nil {
+			break
+		}
+	}
+	endPhase(connect, firstErr)
+	return nil, firstErr
+}
+
+// wrap applies connection wrappers for the given outbound IP.
+func (o dialOptions) wrap(ip string, conn net.Conn) net.Conn {
+	conn = o.shaper.Wrap(ip, conn)
+	if o.countBytes != nil {
+		conn = &countingConn{Conn: conn, count: func(n int64) { o.countBytes(ip, n) }}
+	}
+	return conn
+}
+
 // TransportPool manages http.Transport instances per outbound IP.
 type TransportPool struct {
 	transports map[string]*http.Transport
+	http1      map[string]*http.Transport
 	timeout    time.Duration
+	opts       dialOptions
 	mu         sync.RWMutex
 }
 
 // NewTransportPool creates a new transport pool.
-func NewTransportPool(ips []string, timeout time.Duration) *TransportPool {
+func NewTransportPool(ips []string, timeout time.Duration, opts ...DialOption) *TransportPool {
 	tp := &TransportPool{
 		transports: make(map[string]*http.Transport),
+		http1:      make(map[string]*http.Transport),
 		timeout:    timeout,
+		opts:       newDialOptions(opts),
 	}
 
 	for _, ip := range ips {
-		tp.transports[ip] = tp.createTransport(ip)
+		tp.transports[ip] = tp.createTransport(ip, true)
 	}
 
 	return tp
@@ -32,8 +243,20 @@ func NewTransportPool(ips []string, timeout time.Duration) *TransportPool {
 
 // Get returns the transport for the given IP.
 func (tp *TransportPool) Get(ip string) *http.Transport {
+	return tp.get(tp.transports, ip, true)
+}
+
+// GetHTTP1 returns the transport for the given IP that never negotiates HTTP/2.
+// It keeps its own connections, so forcing HTTP/1.1 toward one origin does not
+// affect multiplexed connections to others.
+func (tp *TransportPool) GetHTTP1(ip string) *http.Transport {
+	return tp.get(tp.http1, ip, false)
+}
+
+// get returns the transport for ip from transports, creating it if needed.
+func (tp *TransportPool) get(transports map[string]*http.Transport, ip string, http2 bool) *http.Transport {
 	tp.mu.RLock()
-	t, exists := tp.transports[ip]
+	t, exists := transports[ip]
 	tp.mu.RUnlock()
 
 	if exists {
@@ -44,37 +267,59 @@ func (tp *TransportPool) Get(ip string) *http.Transport {
 	tp.mu.Lock()
 	defer tp.mu.Unlock()
 
-	if t, exists := tp.transports[ip]; exists {
+	if t, exists := transports[ip]; exists {
 		return t
 	}
 
-	t = tp.createTransport(ip)
-	tp.transports[ip] = t
+	t = tp.createTransport(ip, http2)
+	transports[ip] = t
 	return t
 }
 
-// createTransport creates a new http.Transport bound to the given IP.
-func (tp *TransportPool) createTransport(ip string) *http.Transport {
-	localAddr := &net.TCPAddr{
-		IP: net.ParseIP(ip),
-	}
-
-	dialer := &net.Dialer{
-		LocalAddr: localAddr,
-		Timeout:   tp.timeout,
-		KeepAlive: 30 * time.Second,
+// createTransport creates a new http.Transport bound to the given IP. Without
+// http2 the transport only speaks HTTP/1.1.
+func (tp *TransportPool) createTransport(ip string, http2 bool) *http.Transport {
+	newDialer := func(source string) *net.Dialer {
+		return &net.Dialer{
+			LocalAddr: &net.TCPAddr{IP: net.ParseIP(source)},
+			Timeout:   tp.timeout,
+			KeepAlive: 30 * time.Second,
+			Control:   tp.opts.control(ip),
+		}
 	}
 
-	return &http.Transport{
+	t := &http.Transport{
 		DialContext: func(ctx context.Context, network, addr string) (net.Conn, error) {
-			return dialer.DialContext(ctx, network, addr)
+			conn, err := tp.opts.dialEgress(ctx, ip, newDialer, network, addr)
+			if err != nil {
+				return nil, err
+			}
+			return tp.opts.wrap(ip, conn), nil
 		},
+		TLSClientConfig:       tp.opts.tlsConfig(),
 		MaxIdleConns:          100,
 		MaxIdleConnsPerHost:   10,
 		IdleConnTimeout:       90 * time.Second,
-		TLSHandshakeTimeout:   10 * time.Second,
+		TLSHandshakeTimeout:   tp.opts.tlsHandshakeTimeout(),
 		ExpectContinueTimeout: 1 * time.Second,
-		ForceAttemptHTTP2:     true,
+		ForceAttemptHTTP2:     http2,
+	}
+	if !http2 {
+		// A non-nil empty map keeps the transport from upgrading to HTTP/2
+		t.TLSNextProto = map[string]func(string, *tls.Conn) http.RoundTripper{}
+	}
+	return t
+}
+
+// CloseIdle closes idle connections of the transports for the given IP.
+func (tp *TransportPool) CloseIdle(ip string) {
+	tp.mu.RLock()
+	defer tp.mu.RUnlock()
+
+	for _, transports := range []map[string]*http.Transport{tp.transports, tp.http1} {
+		if t, exists := transports[ip]; exists {
+			t.CloseIdleConnections()
+		}
 	}
 }
 
@@ -86,6 +331,9 @@ func (tp *TransportPool) Close() {
 	for _, t := range tp.transports {
 		t.CloseIdleConnections()
 	}
+	for _, t := range tp.http1 {
+		t.CloseIdleConnections()
+	}
 }
 
 // Dialer creates connections bound to a specific outbound IP.
@@ -93,14 +341,16 @@ type Dialer struct {
 	localIP     string
 	timeout     time.Duration
 	idleTimeout time.Duration
+	opts        dialOptions
 }
 
 // NewDialer creates a new Dialer.
-func NewDialer(localIP string, timeout, idleTimeout time.Duration) *Dialer {
+func NewDialer(localIP string, timeout, idleTimeout time.Duration, opts ...DialOption) *Dialer {
 	return &Dialer{
 		localIP:     localIP,
 		timeout:     timeout,
 		idleTimeout: idleTimeout,
+		opts:        newDialOptions(opts),
 	}
 }
 
@@ -111,15 +361,18 @@ func (d *Dialer) Dial(network, addr string) (net.Conn, error) {
 
 // DialContext creates a connection to the given address with context.
 func (d *Dialer) DialContext(ctx context.Context, network, addr string) (net.Conn, error) {
-	localAddr := &net.TCPAddr{
-		IP: net.ParseIP(d.localIP),
+	newDialer := func(source string) *net.Dialer {
+		return &net.Dialer{
+			LocalAddr: &net.TCPAddr{IP: net.ParseIP(source)},
+			Timeout:   d.timeout,
+			KeepAlive: 30 * time.Second,
+			Control:   d.opts.control(d.localIP),
+		}
 	}
 
-	dialer := &net.Dialer{
-		LocalAddr: localAddr,
-		Timeout:   d.timeout,
-		KeepAlive: 30 * time.Second,
+	conn, err := d.opts.dialEgress(ctx, d.localIP, newDialer, network, addr)
+	if err != nil {
+		return nil, err
 	}
-
-	return dialer.DialContext(ctx, network, addr)
+	return d.opts.wrap(d.localIP, conn), nil
 }
@@ -1,6 +1,11 @@
 package proxy
 
 import (
+	"crypto/tls"
+	"crypto/x509"
+	"errors"
+	"net/http"
+	"net/http/httptest"
 	"testing"
 	"time"
 )
@@ -49,6 +54,97 @@ func TestTransportPool_Close(t *testing.T) {
 	tp.Close()
 }
 
+func TestTransportPool_TLSVerifier(t *testing.T) {
+	upstream := httptest.NewTLSServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.WriteHeader(http.StatusNoContent)
+	}))
+	defer upstream.Close()
+
+	// Trust only the test server's certificate, as a private PKI would
+	roots := x509.NewCertPool()
+	roots.AddCert(upstream.Certificate())
+	pinned := func(cs tls.ConnectionState) error {
+		_, err := cs.PeerCertificates[0].Verify(x509.VerifyOptions{Roots: roots})
+		return err
+	}
+	rejectAll := func(tls.ConnectionState) error { return errors.New("rejected by policy") }
+
+	tests := []struct {
+		name     string
+		verifier TLSVerifier
+		wantErr  bool
+	}{
+		{"default roots", nil, true},
+		{"custom verifier accepts", pinned, false},
+		{"custom verifier rejects", rejectAll, true},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			tp := NewTransportPool([]string{"127.0.0.1"}, 5*time.Second, WithTLSVerifier(tt.verifier))
+			defer tp.Close()
+
+			req, _ := http.NewRequest(http.MethodGet, upstream.URL, nil)
+			resp, err := tp.Get("127.0.0.1").RoundTrip(req)
+			if (err != nil) != tt.wantErr {
+				t.Fatalf("RoundTrip() error = %v, wantErr %v", err, tt.wantErr)
+			}
+			if err == nil {
+				resp.Body.Close()
+				if resp.StatusCode != http.StatusNoContent {
+					t.Errorf("status = %d, want 204", resp.StatusCode)
+				}
+			}
+		})
+	}
+}
+
+func TestTransportPool_GetHTTP1(t *testing.T) {
+	upstream := httptest.NewUnstartedServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.WriteHeader(http.StatusNoContent)
+	}))
+	upstream.EnableHTTP2 = true
+	upstream.StartTLS()
+	defer upstream.Close()
+
+	roots := x509.NewCertPool()
+	roots.AddCert(upstream.Certificate())
+	pinned := func(cs tls.ConnectionState) error {
+		_, err := cs.PeerCertificates[0].Verify(x509.VerifyOptions{Roots: roots})
+		return err
+	}
+
+	tp := NewTransportPool([]string{"127.0.0.1"}, 5*time.Second, WithTLSVerifier(pinned))
+	defer tp.Close()
+
+	if tp.Get("127.0.0.1") == tp.GetHTTP1("127.0.0.1") {
+		t.Fatal("expected separate HTTP/1.1 transport")
+	}
+
+	tests := []struct {
+		name      string
+		transport *http.Transport
+		wantMajor int
+	}{
+		{"default", tp.Get("127.0.0.1"), 2},
+		{"http1", tp.GetHTTP1("127.0.0.1"), 1},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			req, _ := http.NewRequest(http.MethodGet, upstream.URL, nil)
+			resp, err := tt.transport.RoundTrip(req)
+			if err != nil {
+				t.Fatalf("RoundTrip() error = %v", err)
+			}
+			resp.Body.Close()
+			if resp.ProtoMajor != tt.wantMajor {
+				t.Errorf("protocol = %s, want HTTP/%d", resp.Proto, tt.wantMajor)
+			}
+		})
+	}
+}
+
 func TestNewDialer(t *testing.T) {
 	d := NewDialer("127.0.0.1", 30*time.Second, 60*time.Second)
 
@@ -0,0 +1,170 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"net"
+	"sort"
+	"sync"
+	"sync/atomic"
+	"time"
+)
+
+// tunnelEntry tracks one active CONNECT or SOCKS tunnel.
+type tunnelEntry struct {
+	id         uint64
+	host       string
+	ip         string
+	started    time.Time
+	lastActive atomic.Int64 // unix nanos
+	closeFn    func()
+	// bytesUp and bytesDown count bytes written to and read from the target.
+	bytesUp   atomic.Int64
+	bytesDown atomic.Int64
+	// target is the connection to the target, set by wrap.
+	target atomic.Pointer[net.Conn]
+}
+
+// touch records activity on the tunnel.
+func (e *tunnelEntry) touch() {
+	e.lastActive.Store(time.Now().UnixNano())
+}
+
+// idleFor returns how long the tunnel has been without traffic.
+func (e *tunnelEntry) idleFor(now time.Time) time.Duration {
+	return now.Sub(time.Unix(0, e.lastActive.Load()))
+}
+
+// wrap returns conn with reads and writes recorded as tunnel activity.
+func (e *tunnelEntry) wrap(conn net.Conn) net.Conn {
+	e.target.Store(&conn)
+	return &activityConn{Conn: conn, entry: e}
+}
+
+// activityConn is a net.Conn that records traffic on its tunnel entry.
+type activityConn struct {
+	net.Conn
+	entry *tunnelEntry
+}
+
+// Read reads from the connection and records activity.
+func (c *activityConn) Read(p []byte) (int, error) {
+	n, err := c.Conn.Read(p)
+	if n > 0 {
+		c.entry.touch()
+		c.entry.bytesDown.Add(int64(n))
+	}
+	return n, err
+}
+
+// Write writes to the connection and records activity.
+func (c *activityConn) Write(p []byte) (int, error) {
+	n, err := c.Conn.Write(p)
+	if n > 0 {
+		c.entry.touch()
+		c.entry.bytesUp.Add(int64(n))
+	}
+	return n, err
+}
+
+// CloseWrite half-closes the underlying connection if supported.
+func (c *activityConn) CloseWrite() error {
+	if cw, ok := c.Conn.(closeWriter); ok {
+		return cw.CloseWrite()
+	}
+	return nil
+}
+
+// tunnelRegistry tracks active tunnels so idle ones can be evicted under pressure.
+type tunnelRegistry struct {
+	entries map[uint64]*tunnelEntry
+	nextID  uint64
+	mu      sync.Mutex
+}
+
+// newTunnelRegistry creates an empty tunnelRegistry.
+func newTunnelRegistry() *tunnelRegistry {
+	return &tunnelRegistry{entries: make(map[uint64]*tunnelEntry)}
+}
+
+// add registers a tunnel. closeFn must close both sides of the tunnel.
+func (r *tunnelRegistry) add(host, ip string, closeFn func()) *tunnelEntry {
+	r.mu.Lock()
+	defer r.mu.Unlock()
+
+	r.nextID++
+	e := &tunnelEntry{
+		id:      r.nextID,
+		host:    host,
+		ip:      ip,
+		started: time.Now(),
+		closeFn: closeFn,
+	}
+	e.touch()
+	r.entries[e.id] = e
+	return e
+}
+
+// remove unregisters a tunnel.
+func (r *tunnelRegistry) remove(e *tunnelEntry) {
+	r.mu.Lock()
+	delete(r.entries, e.id)
+	r.mu.Unlock()
+}
+
+// endTunnel unregisters a finished tunnel and records its lifetime and
+// traffic against its outbound IP.
+func (s *Server) endTunnel(e *tunnelEntry) {
+	s.tunnels.remove(e)
+	s.stats.ObserveTunnel(e.ip, time.Since(e.started), e.bytesUp.Load(), e.bytesDown.Load())
+}
+
+// len returns the number of active tunnels.
+func (r *tunnelRegistry) len() int {
+	r.mu.Lock()
+	defer r.mu.Unlock()
+	return len(r.entries)
+}
+
+// snapshot returns the active tunnels.
+func (r *tunnelRegistry) snapshot() []*tunnelEntry {
+	r.mu.Lock()
+	defer r.mu.Unlock()
+
+	entries := make([]*tunnelEntry, 0, len(r.entries))
+	for _, e := range r.entries {
+		entries = append(entries, e)
+	}
+	return entries
+}
+
+// evictIdle closes up to n tunnels that have been idle for at least minIdle,
+// longest-idle first, and returns the evicted entries.
+func (r *tunnelRegistry) evictIdle(n int, minIdle time.Duration) []*tunnelEntry {
+	if n <= 0 {
+		return nil
+	}
+
+	now := time.Now()
+	r.mu.Lock()
+	candidates := make([]*tunnelEntry, 0, len(r.entries))
+	for _, e := range r.entries {
+		if e.idleFor(now) >= minIdle {
+			candidates = append(candidates, e)
+		}
+	}
+	sort.Slice(candidates, func(i, j int) bool {
+		return candidates[i].lastActive.Load() < candidates[j].lastActive.Load()
+	})
+	if len(candidates) > n {
+		candidates = candidates[:n]
+	}
+	for _, e := range candidates {
+		delete(r.entries, e.id)
+	}
+	r.mu.Unlock()
+
+	for _, e := range candidates {
+		e.closeFn()
+	}
+	return candidates
+}
@@ -0,0 +1,135 @@
+package proxy
+
+import (
+	"net"
+	"testing"
+	"time"
+)
+
+func TestTunnelRegistry_EvictIdleOldestFirst(t *testing.T) {
+	r := newTunnelRegistry()
+
+	var closed []string
+	add := func(host string, idle time.Duration) *tunnelEntry {
+		e := r.add(host, "127.0.0.1", func() { closed = append(closed, host) })
+		e.lastActive.Store(time.Now().Add(-idle).UnixNano())
+		return e
+	}
+	add("recent.example.com", time.Second)
+	add("older.example.com", 2*time.Minute)
+	add("oldest.example.com", 5*time.Minute)
+
+	evicted := r.evictIdle(1, 30*time.Second)
+	if len(evicted) != 1 || evicted[0].host != "oldest.example.com" {
+		t.Fatalf("expected oldest tunnel to be evicted, got %v", closed)
+	}
+
+	// Only one remaining tunnel is idle long enough
+	evicted = r.evictIdle(5, 30*time.Second)
+	if len(evicted) != 1 || evicted[0].host != "older.example.com" {
+		t.Errorf("expected only the idle tunnel to be evicted, got %v", closed)
+	}
+	if r.len() != 1 {
+		t.Errorf("expected 1 tunnel left, got %d", r.len())
+	}
+}
+
+func TestTunnelEntry_WrapRecordsActivity(t *testing.T) {
+	r := newTunnelRegistry()
+	e := r.add("example.com", "127.0.0.1", func() {})
+	e.lastActive.Store(time.Now().Add(-time.Hour).UnixNano())
+
+	c1, c2 := net.Pipe()
+	defer c1.Close()
+	defer c2.Close()
+
+	go c2.Read(make([]byte, 4))
+	if _, err := e.wrap(c1).Write([]byte("ping")); err != nil {
+		t.Fatalf("write failed: %v", err)
+	}
+
+	if idle := e.idleFor(time.Now()); idle > time.Second {
+		t.Errorf("expected write to reset idle time, got %v", idle)
+	}
+}
+
+func TestFDMonitor_Check(t *testing.T) {
+	r := newTunnelRegistry()
+	for i := 0; i < 4; i++ {
+		e := r.add("example.com", "127.0.0.1", func() {})
+		e.lastActive.Store(time.Now().Add(-time.Minute).UnixNano())
+	}
+
+	m := newFDMonitor(r, 0.8, 30*time.Second)
+
+	m.usage = func() (int, int, bool) { return 50, 100, true }
+	if n := m.check(); n != 0 {
+		t.Errorf("expected no eviction below threshold, got %d", n)
+	}
+
+	// 84 open with a limit of 80 needs 4 descriptors, i.e. 2 tunnels
+	m.usage = func() (int, int, bool) { return 84, 100, true }
+	if n := m.check(); n != 2 {
+		t.Errorf("expected 2 evictions, got %d", n)
+	}
+	if r.len() != 2 {
+		t.Errorf("expected 2 tunnels left, got %d", r.len())
+	}
+}
+
+func TestProgressMonitor_Check(t *testing.T) {
+	r := newTunnelRegistry()
+	m := newProgressMonitor(r, time.Minute, 1000)
+	now := time.Now()
+
+	add := func(host string, age time.Duration, up, down int64) *tunnelEntry {
+		e := r.add(host, "127.0.0.1", func() {})
+		e.started = now.Add(-age)
+		e.bytesUp.Store(up)
+		e.bytesDown.Store(down)
+		return e
+	}
+	large := add("large.example.com", 2*time.Minute, 1200, 60000)
+	add("small.example.com", 2*time.Minute, 10, 10)
+	add("new.example.com", time.Second, 0, 1<<20)
+
+	reports := m.check(now)
+	if len(reports) != 1 || reports[0].host != "large.example.com" {
+		t.Fatalf("expected only the large tunnel to be reported, got %+v", reports)
+	}
+	if p := reports[0]; p.bytesUp != 1200 || p.bytesDown != 60000 || p.upRate != 10 || p.downRate != 500 {
+		t.Errorf("unexpected first report %+v", p)
+	}
+
+	// Throughput is measured since the previous report
+	large.bytesDown.Add(6000)
+	reports = m.check(now.Add(time.Minute))
+	if len(reports) != 1 || reports[0].upRate != 0 || reports[0].downRate != 100 {
+		t.Errorf("unexpected second report %+v", reports)
+	}
+
+	// The new tunnel is reported once it has been open for an interval
+	r.remove(large)
+	reports = m.check(now.Add(2 * time.Minute))
+	if len(reports) != 1 || reports[0].host != "new.example.com" {
+		t.Errorf("expected only the new tunnel to be reported, got %+v", reports)
+	}
+	if _, ok := m.marks[large.id]; ok {
+		t.Error("expected the closed tunnel to be forgotten")
+	}
+}
+
+func TestServer_EndTunnelRecordsTraffic(t *testing.T) {
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	e := server.tunnels.add("example.com", "127.0.0.1", func() {})
+	e.bytesUp.Add(300)
+	e.bytesDown.Add(4000)
+
+	server.endTunnel(e)
+	if server.tunnels.len() != 0 {
+		t.Errorf("expected the tunnel to be unregistered, %d left", server.tunnels.len())
+	}
+	if got := server.stats.GetStats().BytesPerIP["127.0.0.1"]; got.Up != 300 || got.Down != 4000 {
+		t.Errorf("bytes = %+v, want 300 up and 4000 down", got)
+	}
+}
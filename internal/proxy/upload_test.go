@@ -0,0 +1,48 @@
+package proxy
+
+import (
+	"io"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+)
+
+func TestHandler_UploadLimit(t *testing.T) {
+	var received int
+	backend := newTestBackendWithHandler(t, func(w http.ResponseWriter, r *http.Request) {
+		n, _ := io.Copy(io.Discard, r.Body)
+		received = int(n)
+		w.WriteHeader(http.StatusOK)
+	})
+	defer backend.Close()
+
+	server := newTestServerWithIPs(t, []string{"127.0.0.1"})
+	server.cfg.MaxUploadBytes = 16
+	handler := NewHandler(server)
+
+	tests := []struct {
+		name          string
+		body          string
+		contentLength int64
+		want          int
+	}{
+		{"within limit", "0123456789", 10, http.StatusOK},
+		{"declared too large", strings.Repeat("x", 32), 32, http.StatusRequestEntityTooLarge},
+		{"chunked within limit", "0123456789", -1, http.StatusOK},
+		{"chunked too large", strings.Repeat("x", 32), -1, http.StatusRequestEntityTooLarge},
+	}
+	for _, tt := range tests {
+		received = 0
+		r := httptest.NewRequest(http.MethodPost, backend.URL+"/upload", strings.NewReader(tt.body))
+		r.ContentLength = tt.contentLength
+		w := httptest.NewRecorder()
+		handler.ServeHTTP(w, r)
+		if w.Code != tt.want {
+			t.Errorf("%s: status = %d, want %d", tt.name, w.Code, tt.want)
+		}
+		if tt.want == http.StatusOK && received != len(tt.body) {
+			t.Errorf("%s: backend received %d bytes, want %d", tt.name, received, len(tt.body))
+		}
+	}
+}
@@ -0,0 +1,136 @@
+// Package proxy provides the HTTP/HTTPS proxy server.
+package proxy
+
+import (
+	"bufio"
+	"bytes"
+	"fmt"
+	"io"
+	"net"
+	"net/http"
+	"strings"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+)
+
+// isWebSocketUpgrade reports whether r asks to switch the connection to WebSocket.
+func isWebSocketUpgrade(r *http.Request) bool {
+	return headerHasToken(r.Header, "Connection", "upgrade") && strings.EqualFold(r.Header.Get("Upgrade"), "websocket")
+}
+
+// headerHasToken reports whether the comma-separated header name contains token.
+func headerHasToken(header http.Header, name, token string) bool {
+	for _, v := range header.Values(name) {
+		for _, t := range strings.Split(v, ",") {
+			if strings.EqualFold(strings.TrimSpace(t), token) {
+				return true
+			}
+		}
+	}
+	return false
+}
+
+// serveWebSocket sends the WebSocket handshake in outReq to the upstream from ip.
+// When the upstream answers 101 Switching Protocols, the client connection is
+// hijacked and frames are relayed raw in both directions until either side
+// closes or the idle timeout expires. Any other answer is forwarded as a normal
+// response. Returns the status sent to the client and the bytes relayed.
+func (h *Handler) serveWebSocket(w http.ResponseWriter, outReq *http.Request, host, ip string) (status int, bytesIn, bytesOut int64) {
+	hijacker, ok := w.(http.Hijacker)
+	if !ok {
+		h.sendError(w, http.StatusInternalServerError, "Hijacking not supported")
+		return http.StatusInternalServerError, 0, 0
+	}
+
+	addr := outReq.URL.Host
+	if outReq.URL.Port() == "" {
+		addr = net.JoinHostPort(outReq.URL.Hostname(), "80")
+	}
+
+	// Dial and send the handshake
+	dialer := NewDialer(ip, h.server.cfg.EffectiveConnectTimeout(), h.server.cfg.IdleTimeout, h.server.outboundDialOptions()...)
+	dialStart := time.Now()
+	targetConn, err := dialer.Dial("tcp", addr)
+	if err != nil {
+		h.server.slo.Observe(host, ip, time.Since(dialStart), dialFailed(err))
+		h.server.recordOutcome(ip, dialFailed(err))
+		logger.LogError("websocket_dial", err, "host", host, "ip", ip)
+		status := dialErrorStatus(err)
+		h.sendError(w, status, dialErrorMessage(err, "Failed to connect to upstream"))
+		return status, 0, 0
+	}
+	defer targetConn.Close()
+
+	targetConn.SetDeadline(time.Now().Add(h.server.cfg.Timeout))
+	targetReader := bufio.NewReader(targetConn)
+	resp, err := sendHandshake(targetConn, targetReader, outReq)
+	h.server.slo.Observe(host, ip, time.Since(dialStart), err != nil)
+	h.server.recordOutcome(ip, err != nil)
+	if err != nil {
+		logger.LogError("websocket_handshake", err, "host", host, "ip", ip)
+		h.sendError(w, http.StatusBadGateway, "Failed to connect to upstream")
+		return http.StatusBadGateway, 0, 0
+	}
+	defer resp.Body.Close()
+
+	// The upstream declined the upgrade: forward its answer as is
+	if resp.StatusCode != http.StatusSwitchingProtocols {
+		targetConn.SetDeadline(time.Now().Add(h.server.cfg.IdleTimeout))
+		h.copyHeaders(w.Header(), resp.Header)
+		w.WriteHeader(resp.StatusCode)
+		n, _ := io.Copy(w, resp.Body)
+		return resp.StatusCode, 0, n
+	}
+	if !strings.EqualFold(resp.Header.Get("Upgrade"), "websocket") {
+		log.Warn("websocket_upgrade_mismatch", "host", host, "upgrade", resp.Header.Get("Upgrade"))
+		h.sendError(w, http.StatusBadGateway, "Upstream switched to an unexpected protocol")
+		return http.StatusBadGateway, 0, 0
+	}
+
+	clientConn, clientBuf, err := hijacker.Hijack()
+	if err != nil {
+		logger.LogError("websocket_hijack", err, "host", host)
+		h.sendError(w, http.StatusInternalServerError, "Failed to hijack connection")
+		return http.StatusInternalServerError, 0, 0
+	}
+	defer clientConn.Close()
+
+	// Relay the 101 response with end-to-end headers plus the upgrade pair
+	header := make(http.Header)
+	h.copyHeaders(header, resp.Header)
+	header.Set("Connection", "Upgrade")
+	header.Set("Upgrade", "websocket")
+	var buf bytes.Buffer
+	fmt.Fprintf(&buf, "HTTP/1.1 %s\r\n", resp.Status)
+	header.Write(&buf)
+	buf.WriteString("\r\n")
+	if _, err := clientConn.Write(buf.Bytes()); err != nil {
+		logger.LogError("websocket_response", err, "host", host)
+		return http.StatusSwitchingProtocols, 0, 0
+	}
+	targetConn.SetDeadline(time.Time{})
+
+	// Both sides may already hold buffered frames
+	client := &sniffedConn{Conn: clientConn, r: clientBuf.Reader}
+	target := &sniffedConn{Conn: targetConn, r: targetReader}
+
+	// Register the relay so it can be evicted under fd pressure
+	entry := h.server.tunnels.add(host, ip, func() {
+		clientConn.Close()
+		targetConn.Close()
+	})
+	defer h.server.endTunnel(entry)
+
+	log.Debug("websocket_established", "host", host, "ip", ip)
+	bytesIn, bytesOut = h.server.connectHandler.tunnel(client, entry.wrap(target), h.server.cfg.EffectiveTunnelIdleTimeout(), RequestIDFromContext(outReq.Context()))
+	return http.StatusSwitchingProtocols, bytesIn, bytesOut
+}
+
+// sendHandshake writes req to conn and reads the response from r.
+func sendHandshake(conn net.Conn, r *bufio.Reader, req *http.Request) (*http.Response, error) {
+	if err := req.Write(conn); err != nil {
+		return nil, err
+	}
+	return http.ReadResponse(r, req)
+}
@@ -0,0 +1,164 @@
+// Package rules evaluates ordered routing rules against new connections.
+package rules
+
+import (
+	"net"
+	"regexp"
+	"slices"
+	"strings"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/config"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// Request describes a new connection as the rules see it.
+type Request struct {
+	// Host is the destination in its canonical form (see netutil.NormalizeHost), without port.
+	Host string
+	// Port is the destination port.
+	Port int
+	// User is the proxy username without routing suffixes; empty when unauthenticated.
+	User string
+	// ClientIP is the client's address; nil when unknown.
+	ClientIP net.IP
+}
+
+// Decision is the outcome of the rules for a connection. Each field is set by
+// the first matching rule with that action; the zero Decision leaves the
+// connection to the rest of the configuration.
+type Decision struct {
+	// Deny names the deny rule that refused the connection.
+	Deny string
+	// Pool is the named pool the connection must leave through, set by the rule PoolRule.
+	Pool     string
+	PoolRule string
+	// Strategy is the rotation policy for the connection, kept for StrategyTTL when sticky.
+	Strategy    string
+	StrategyTTL time.Duration
+	// BandwidthMbps caps each direction of the connection's tunnel.
+	BandwidthMbps float64
+}
+
+// rule is a config.RoutingRule with its conditions compiled.
+type rule struct {
+	config.RoutingRule
+	hosts    []hostPattern
+	ports    []config.PortRange
+	clients  []*net.IPNet
+	schedule *config.Schedule
+}
+
+// hostPattern matches destination hosts: exactly, as "*.suffix" subdomains,
+// or with a "/regexp/" expression.
+type hostPattern struct {
+	name   string
+	suffix string
+	re     *regexp.Regexp
+}
+
+// match reports whether host matches the pattern.
+func (p hostPattern) match(host string) bool {
+	switch {
+	case p.re != nil:
+		return p.re.MatchString(host)
+	case p.suffix != "":
+		return strings.HasSuffix(host, "."+p.suffix)
+	}
+	return host == p.name
+}
+
+// Engine evaluates routing rules in order.
+type Engine struct {
+	rules []rule
+	clock clock.Clock
+}
+
+// New compiles the routing rules, which must have passed config validation.
+// It returns nil when there are none.
+func New(rules []config.RoutingRule, c clock.Clock) *Engine {
+	if len(rules) == 0 {
+		return nil
+	}
+	e := &Engine{rules: make([]rule, 0, len(rules)), clock: clock.OrReal(c)}
+	for _, r := range rules {
+		compiled := rule{RoutingRule: r}
+		for _, h := range r.Match.Hosts {
+			compiled.hosts = append(compiled.hosts, compileHost(h))
+		}
+		compiled.ports, _ = config.ParsePortRanges(r.Match.Ports)
+		compiled.clients, _ = netutil.ParseCIDRs(r.Match.ClientCIDRs)
+		if s, ok, err := r.Match.Schedule(); ok && err == nil {
+			compiled.schedule = &s
+		}
+		e.rules = append(e.rules, compiled)
+	}
+	return e
+}
+
+// compileHost compiles a validated destination pattern.
+func compileHost(h string) hostPattern {
+	if expr, ok := config.DestinationRegexp(h); ok {
+		return hostPattern{re: regexp.MustCompile(expr)}
+	}
+	if suffix, ok := strings.CutPrefix(h, "*."); ok {
+		return hostPattern{suffix: suffix}
+	}
+	return hostPattern{name: h}
+}
+
+// Evaluate applies the rules to req at the current time, so scheduled rules
+// take effect for connections opened within their schedule. Evaluation stops
+// at the first matching deny rule. Safe to call on a nil Engine, which decides
+// nothing.
+func (e *Engine) Evaluate(req Request) Decision {
+	var d Decision
+	if e == nil {
+		return d
+	}
+	now := e.clock.Now()
+	for _, r := range e.rules {
+		if !r.matches(req, now) {
+			continue
+		}
+		switch r.Action {
+		case config.RuleDeny:
+			d.Deny = r.Name
+			return d
+		case config.RuleUsePool:
+			if d.Pool == "" {
+				d.Pool, d.PoolRule = r.Pool, r.Name
+			}
+		case config.RuleSetStrategy:
+			if d.Strategy == "" {
+				d.Strategy, d.StrategyTTL = r.Strategy, r.TTL
+			}
+		case config.RuleSetBandwidth:
+			if d.BandwidthMbps == 0 {
+				d.BandwidthMbps = r.BandwidthMbps
+			}
+		}
+	}
+	return d
+}
+
+// matches reports whether every condition of r holds for req at now.
+func (r rule) matches(req Request, now time.Time) bool {
+	if len(r.hosts) > 0 && !slices.ContainsFunc(r.hosts, func(p hostPattern) bool { return p.match(req.Host) }) {
+		return false
+	}
+	if len(r.ports) > 0 && !slices.ContainsFunc(r.ports, func(p config.PortRange) bool { return p.Contains(req.Port) }) {
+		return false
+	}
+	if len(r.Match.Users) > 0 && !slices.Contains(r.Match.Users, req.User) {
+		return false
+	}
+	if len(r.clients) > 0 && (req.ClientIP == nil || !netutil.ContainsIP(r.clients, req.ClientIP)) {
+		return false
+	}
+	if r.schedule != nil && !r.schedule.Contains(now) {
+		return false
+	}
+	return true
+}
@@ -0,0 +1,96 @@
+package rules
+
+import (
+	"net"
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/clock"
+	"github.com/cr0hn/outbound-lb/internal/config"
+)
+
+func TestEvaluate(t *testing.T) {
+	fake := clock.NewFake(time.Date(2026, 3, 2, 10, 0, 0, 0, time.Local))
+	e := New([]config.RoutingRule{
+		{Name: "no-smtp", Match: config.RuleMatch{Ports: []string{"25"}}, Action: config.RuleDeny},
+		{Name: "partners", Match: config.RuleMatch{Hosts: []string{"*.partner.example"}}, Action: config.RuleUsePool, Pool: "partners"},
+		{Name: "office", Match: config.RuleMatch{ClientCIDRs: []string{"10.0.0.0/8"}}, Action: config.RuleUsePool, Pool: "office"},
+		{Name: "scrapers", Match: config.RuleMatch{Users: []string{"scraper"}}, Action: config.RuleSetStrategy, Strategy: config.RotationSticky, TTL: time.Minute},
+		{Name: "business-hours", Match: config.RuleMatch{Hours: "09:00-17:00"}, Action: config.RuleSetBandwidth, BandwidthMbps: 10},
+	}, fake)
+
+	tests := []struct {
+		name string
+		req  Request
+		want Decision
+	}{
+		{"deny", Request{Host: "mail.partner.example", Port: 25}, Decision{Deny: "no-smtp"}},
+		{"first pool wins", Request{Host: "api.partner.example", Port: 443, ClientIP: net.ParseIP("10.1.2.3")}, Decision{Pool: "partners", PoolRule: "partners", BandwidthMbps: 10}},
+		{"client cidr", Request{Host: "example.com", Port: 443, ClientIP: net.ParseIP("10.1.2.3")}, Decision{Pool: "office", PoolRule: "office", BandwidthMbps: 10}},
+		{"unknown client", Request{Host: "example.com", Port: 443}, Decision{BandwidthMbps: 10}},
+		{"user", Request{Host: "example.com", Port: 443, User: "scraper"}, Decision{Strategy: config.RotationSticky, StrategyTTL: time.Minute, BandwidthMbps: 10}},
+	}
+	for _, tt := range tests {
+		if got := e.Evaluate(tt.req); got != tt.want {
+			t.Errorf("%s: Evaluate() = %+v, want %+v", tt.name, got, tt.want)
+		}
+	}
+
+	fake.Advance(8 * time.Hour)
+	if got := e.Evaluate(Request{Host: "example.com", Port: 443}); got != (Decision{}) {
+		t.Errorf("expected no decision outside business hours, got %+v", got)
+	}
+}
+
+func TestEvaluate_Schedule(t *testing.T) {
+	// 2026-03-02 is a Monday
+	fake := clock.NewFake(time.Date(2026, 3, 2, 3, 0, 0, 0, time.UTC))
+	e := New([]config.RoutingRule{
+		{Name: "night-scraping", Match: config.RuleMatch{Users: []string{"scraper"}, Hours: "00:00-06:00", Days: []string{"mon-fri"}, Timezone: "UTC"}, Action: config.RuleUsePool, Pool: "metered"},
+		{Name: "weekend-nights", Match: config.RuleMatch{Hours: "22:00-06:00", Days: []string{"fri"}, Timezone: "UTC"}, Action: config.RuleSetBandwidth, BandwidthMbps: 100},
+	}, fake)
+	scraper := Request{Host: "example.com", Port: 443, User: "scraper"}
+
+	tests := []struct {
+		at       time.Time
+		wantPool string
+		wantMbps float64
+	}{
+		{time.Date(2026, 3, 2, 3, 0, 0, 0, time.UTC), "metered", 0},
+		{time.Date(2026, 3, 2, 7, 0, 0, 0, time.UTC), "", 0},
+		{time.Date(2026, 3, 6, 2, 0, 0, 0, time.UTC), "metered", 0},
+		{time.Date(2026, 3, 6, 23, 0, 0, 0, time.UTC), "", 100},
+		{time.Date(2026, 3, 7, 2, 0, 0, 0, time.UTC), "", 100},
+		{time.Date(2026, 3, 7, 7, 0, 0, 0, time.UTC), "", 0},
+	}
+	for _, tt := range tests {
+		fake.Set(tt.at)
+		got := e.Evaluate(scraper)
+		if got.Pool != tt.wantPool || got.BandwidthMbps != tt.wantMbps {
+			t.Errorf("at %s: got pool %q and %v Mbps, want %q and %v", tt.at.Format(time.RFC1123), got.Pool, got.BandwidthMbps, tt.wantPool, tt.wantMbps)
+		}
+	}
+}
+
+func TestEvaluate_NilEngine(t *testing.T) {
+	e := New(nil, nil)
+	if e != nil {
+		t.Fatal("expected nil engine without rules")
+	}
+	if got := e.Evaluate(Request{Host: "example.com", Port: 443}); got != (Decision{}) {
+		t.Errorf("Evaluate() = %+v, want zero decision", got)
+	}
+}
+
+func TestTimeWindowAcrossMidnight(t *testing.T) {
+	w, err := config.ParseTimeWindow("22:00-06:00")
+	if err != nil {
+		t.Fatalf("ParseTimeWindow() error = %v", err)
+	}
+	for hour, want := range map[int]bool{21: false, 22: true, 2: true, 6: false, 12: false} {
+		at := time.Date(2026, 3, 2, hour, 0, 0, 0, time.Local)
+		if got := w.Contains(at); got != want {
+			t.Errorf("Contains(%02d:00) = %v, want %v", hour, got, want)
+		}
+	}
+}
@@ -0,0 +1,121 @@
+//go:build sim
+
+// Package sim replays synthetic workloads against the balancer and circuit
+// breaker on a simulated clock, so rotation, history expiry and breaker
+// timeouts can be property-tested deterministically. It is only built with
+// the sim tag:
+//
+//	go test -tags sim ./internal/sim/
+package sim
+
+import (
+	"fmt"
+	"math/rand"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+	"github.com/cr0hn/outbound-lb/internal/clock"
+)
+
+// Epoch is the simulated start time of every run.
+var Epoch = time.Date(2025, 1, 1, 0, 0, 0, 0, time.UTC)
+
+// Event is one request in a workload.
+type Event struct {
+	// At is the offset from Epoch at which the request arrives.
+	At time.Duration
+	// Host is the requested destination.
+	Host string
+}
+
+// Step is the outcome of replaying an Event.
+type Step struct {
+	Event
+	// IP is the selected outbound IP, empty if selection failed.
+	IP string
+	// Failed reports whether the request failed and was fed to the circuit breaker.
+	Failed bool
+	// Err is the selection error, if any.
+	Err error
+}
+
+// Config configures a Harness.
+type Config struct {
+	IPs           []string
+	HistoryWindow time.Duration
+	HistorySize   int
+	Breaker       balancer.CircuitBreakerConfig
+	// Fails decides whether a request through ip at offset at fails. Nil means never.
+	Fails func(ip string, at time.Duration) bool
+}
+
+// Harness wires a balancer and circuit breaker to a shared fake clock.
+type Harness struct {
+	Clock    *clock.Fake
+	Balancer *balancer.LRU
+	Breaker  *balancer.CircuitBreaker
+	fails    func(ip string, at time.Duration) bool
+}
+
+// New creates a Harness. The circuit breaker acts as the balancer's health checker.
+func New(cfg Config) *Harness {
+	c := clock.NewFake(Epoch)
+	cfg.Breaker.Clock = c
+	breaker := balancer.NewCircuitBreaker(cfg.Breaker)
+	return &Harness{
+		Clock: c,
+		Balancer: balancer.NewLRU(balancer.Config{
+			IPs:           cfg.IPs,
+			HistoryWindow: int64(cfg.HistoryWindow.Seconds()),
+			HistorySize:   cfg.HistorySize,
+			HealthChecker: breaker,
+			Clock:         c,
+		}),
+		Breaker: breaker,
+		fails:   cfg.Fails,
+	}
+}
+
+// Run replays events in order and returns one Step per event. Events must be
+// sorted by At.
+func (h *Harness) Run(events []Event) []Step {
+	steps := make([]Step, 0, len(events))
+	for _, e := range events {
+		h.Clock.Set(Epoch.Add(e.At))
+		step := Step{Event: e}
+		step.IP, step.Err = h.Balancer.Select(e.Host)
+		if step.Err == nil {
+			h.Balancer.Record(e.Host, step.IP)
+			step.Failed = h.fails != nil && h.fails(step.IP, e.At)
+			if step.Failed {
+				h.Breaker.RecordFailure(step.IP)
+			} else {
+				h.Breaker.RecordSuccess(step.IP)
+			}
+		}
+		steps = append(steps, step)
+	}
+	return steps
+}
+
+// Synthetic generates n events spread over hosts, one every interval plus a
+// seeded random jitter below interval. The same seed always yields the same workload.
+func Synthetic(seed int64, n int, hosts []string, interval time.Duration) []Event {
+	rng := rand.New(rand.NewSource(seed))
+	events := make([]Event, n)
+	var at time.Duration
+	for i := range events {
+		at += interval + time.Duration(rng.Int63n(int64(interval)))
+		events[i] = Event{At: at, Host: hosts[rng.Intn(len(hosts))]}
+	}
+	return events
+}
+
+// Hosts returns n synthetic host names.
+func Hosts(n int) []string {
+	hosts := make([]string, n)
+	for i := range hosts {
+		hosts[i] = fmt.Sprintf("host%d.example.com", i)
+	}
+	return hosts
+}
@@ -0,0 +1,112 @@
+//go:build sim
+
+package sim
+
+import (
+	"testing"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/balancer"
+)
+
+var testIPs = []string{"10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.4"}
+
+// testConfig returns a config with a long history window and no failures.
+func testConfig() Config {
+	return Config{
+		IPs:           testIPs,
+		HistoryWindow: time.Hour,
+		HistorySize:   1000,
+		Breaker:       balancer.DefaultCircuitBreakerConfig(),
+	}
+}
+
+func TestRun_Deterministic(t *testing.T) {
+	cfg := testConfig()
+	cfg.Fails = func(ip string, at time.Duration) bool { return ip == "10.0.0.2" && at%(7*time.Second) < time.Second }
+
+	for seed := int64(0); seed < 20; seed++ {
+		events := Synthetic(seed, 500, Hosts(5), time.Second)
+		a, b := New(cfg).Run(events), New(cfg).Run(events)
+		for i := range a {
+			if a[i].IP != b[i].IP || a[i].Failed != b[i].Failed {
+				t.Fatalf("seed %d: step %d differs: %+v vs %+v", seed, i, a[i], b[i])
+			}
+		}
+	}
+}
+
+func TestProperty_RotationIsBalancedPerHost(t *testing.T) {
+	for seed := int64(0); seed < 20; seed++ {
+		steps := New(testConfig()).Run(Synthetic(seed, 400, Hosts(3), time.Second))
+
+		usage := make(map[string]map[string]int)
+		for _, s := range steps {
+			if s.Err != nil {
+				t.Fatalf("seed %d: unexpected error: %v", seed, s.Err)
+			}
+			if usage[s.Host] == nil {
+				usage[s.Host] = make(map[string]int)
+			}
+			usage[s.Host][s.IP]++
+
+			// Inside the window, per-host usage never differs by more than one
+			lo, hi := usage[s.Host][testIPs[0]], usage[s.Host][testIPs[0]]
+			for _, ip := range testIPs {
+				lo, hi = min(lo, usage[s.Host][ip]), max(hi, usage[s.Host][ip])
+			}
+			if hi-lo > 1 {
+				t.Fatalf("seed %d: unbalanced usage for %s at %v: %v", seed, s.Host, s.At, usage[s.Host])
+			}
+		}
+	}
+}
+
+func TestProperty_HistoryExpiresAfterWindow(t *testing.T) {
+	cfg := testConfig()
+	cfg.HistoryWindow = time.Minute
+
+	for seed := int64(0); seed < 20; seed++ {
+		events := Synthetic(seed, 50, Hosts(2), time.Second)
+		h := New(cfg)
+		h.Run(events)
+
+		// After a full window of silence the host behaves as if never seen
+		h.Clock.Advance(cfg.HistoryWindow + time.Second)
+		got, err := h.Balancer.Select(events[0].Host)
+		if err != nil {
+			t.Fatalf("seed %d: unexpected error: %v", seed, err)
+		}
+		want, _ := New(cfg).Balancer.Select(events[0].Host)
+		if got != want {
+			t.Errorf("seed %d: selected %s after expiry, want fresh selection %s", seed, got, want)
+		}
+	}
+}
+
+func TestProperty_OpenCircuitIsNotSelected(t *testing.T) {
+	const bad = "10.0.0.3"
+	cfg := testConfig()
+	cfg.Breaker = balancer.CircuitBreakerConfig{FailureThreshold: 3, SuccessThreshold: 1, Timeout: 30 * time.Second}
+	cfg.Fails = func(ip string, _ time.Duration) bool { return ip == bad }
+
+	for seed := int64(0); seed < 20; seed++ {
+		steps := New(cfg).Run(Synthetic(seed, 600, Hosts(4), time.Second))
+
+		var picks []time.Duration
+		for _, s := range steps {
+			if s.IP == bad {
+				picks = append(picks, s.At)
+			}
+		}
+		if len(picks) <= cfg.Breaker.FailureThreshold {
+			t.Fatalf("seed %d: expected the failing IP to be probed after opening, got %d picks", seed, len(picks))
+		}
+		// Once open, the failing IP is only retried as a half-open probe after the timeout
+		for i := cfg.Breaker.FailureThreshold; i < len(picks); i++ {
+			if gap := picks[i] - picks[i-1]; gap < cfg.Breaker.Timeout {
+				t.Fatalf("seed %d: failing IP selected %v after its previous failure, want >= %v", seed, gap, cfg.Breaker.Timeout)
+			}
+		}
+	}
+}
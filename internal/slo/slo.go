@@ -0,0 +1,244 @@
+// Package slo tracks upstream latency and error objectives per route.
+package slo
+
+import (
+	"encoding/json"
+	"net/http"
+	"sort"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+	"github.com/cr0hn/outbound-lb/pkg/netutil"
+)
+
+// numBuckets is the number of buckets each rolling window is divided into.
+const numBuckets = 60
+
+// Objective defines latency and error targets for a route.
+type Objective struct {
+	// Name identifies the objective in metrics and reports.
+	Name string
+	// Hosts lists the destination host patterns this objective covers.
+	Hosts []string
+	// LatencyTarget is the upstream response time a request must beat to count as fast.
+	LatencyTarget time.Duration
+	// LatencyObjective is the target fraction of fast requests (e.g. 0.99).
+	LatencyObjective float64
+	// ErrorObjective is the target fraction of successful requests (e.g. 0.999).
+	ErrorObjective float64
+	// Window is the rolling window the objective is evaluated over.
+	Window time.Duration
+}
+
+// Counts holds request counts within a window.
+type Counts struct {
+	Total  int64 `json:"total"`
+	Slow   int64 `json:"slow"`
+	Errors int64 `json:"errors"`
+}
+
+// add accumulates other into c.
+func (c *Counts) add(other Counts) {
+	c.Total += other.Total
+	c.Slow += other.Slow
+	c.Errors += other.Errors
+}
+
+// window is a rolling window of counts split into fixed-width buckets.
+type window struct {
+	width   time.Duration
+	buckets [numBuckets]Counts
+	epochs  [numBuckets]int64
+}
+
+// newWindow creates a rolling window spanning the given duration.
+func newWindow(span time.Duration) *window {
+	width := span / numBuckets
+	if width <= 0 {
+		width = time.Second
+	}
+	return &window{width: width}
+}
+
+// add records counts at the given time.
+func (w *window) add(now time.Time, c Counts) {
+	epoch := now.UnixNano() / int64(w.width)
+	idx := epoch % numBuckets
+	if w.epochs[idx] != epoch {
+		w.epochs[idx] = epoch
+		w.buckets[idx] = Counts{}
+	}
+	w.buckets[idx].add(c)
+}
+
+// sum returns the counts within the window ending at now.
+func (w *window) sum(now time.Time) Counts {
+	epoch := now.UnixNano() / int64(w.width)
+	var total Counts
+	for i := range w.buckets {
+		if epoch-w.epochs[i] < numBuckets {
+			total.add(w.buckets[i])
+		}
+	}
+	return total
+}
+
+// objectiveState tracks the rolling counts for one objective.
+type objectiveState struct {
+	objective Objective
+	overall   *window
+	perIP     map[string]*window
+}
+
+// Tracker evaluates observations against configured objectives.
+// A nil Tracker ignores all observations.
+type Tracker struct {
+	objectives []*objectiveState
+	mu         sync.Mutex
+}
+
+// NewTracker creates a Tracker for the given objectives.
+// Returns nil if no objectives are configured.
+func NewTracker(objectives []Objective) *Tracker {
+	if len(objectives) == 0 {
+		return nil
+	}
+	t := &Tracker{}
+	for _, o := range objectives {
+		t.objectives = append(t.objectives, &objectiveState{
+			objective: o,
+			overall:   newWindow(o.Window),
+			perIP:     make(map[string]*window),
+		})
+	}
+	return t
+}
+
+// Observe records an upstream exchange for host through the outbound ip.
+// latency is the upstream response (or connect) time; failed marks an upstream error.
+func (t *Tracker) Observe(host, ip string, latency time.Duration, failed bool) {
+	if t == nil {
+		return
+	}
+
+	now := time.Now()
+	t.mu.Lock()
+	defer t.mu.Unlock()
+
+	for _, st := range t.objectives {
+		if !netutil.MatchAnyHost(st.objective.Hosts, host) {
+			continue
+		}
+
+		c := Counts{Total: 1}
+		result := "good"
+		switch {
+		case failed:
+			c.Errors = 1
+			result = "error"
+		case latency > st.objective.LatencyTarget:
+			c.Slow = 1
+			result = "slow"
+		}
+
+		st.overall.add(now, c)
+		ipWindow, ok := st.perIP[ip]
+		if !ok {
+			ipWindow = newWindow(st.objective.Window)
+			st.perIP[ip] = ipWindow
+		}
+		ipWindow.add(now, c)
+
+		metrics.SLORequests.WithLabelValues(st.objective.Name, result).Inc()
+		sum := st.overall.sum(now)
+		metrics.SLOBurnRate.WithLabelValues(st.objective.Name, "latency").Set(burnRate(sum.Slow, sum.Total, st.objective.LatencyObjective))
+		metrics.SLOBurnRate.WithLabelValues(st.objective.Name, "error").Set(burnRate(sum.Errors, sum.Total, st.objective.ErrorObjective))
+	}
+}
+
+// burnRate returns how fast the error budget is being consumed.
+// A burn rate of 1 exhausts the budget exactly at the end of the window.
+func burnRate(bad, total int64, objective float64) float64 {
+	if total == 0 || objective <= 0 || objective >= 1 {
+		return 0
+	}
+	return (float64(bad) / float64(total)) / (1 - objective)
+}
+
+// IPReport holds the counts for a single outbound IP within an objective.
+type IPReport struct {
+	IP              string  `json:"ip"`
+	Counts          Counts  `json:"counts"`
+	LatencyBurnRate float64 `json:"latency_burn_rate"`
+	ErrorBurnRate   float64 `json:"error_burn_rate"`
+}
+
+// Report is a serializable snapshot of one objective.
+type Report struct {
+	Name             string     `json:"name"`
+	Hosts            []string   `json:"hosts"`
+	Window           string     `json:"window"`
+	LatencyTarget    string     `json:"latency_target"`
+	LatencyObjective float64    `json:"latency_objective"`
+	ErrorObjective   float64    `json:"error_objective"`
+	Counts           Counts     `json:"counts"`
+	LatencyBurnRate  float64    `json:"latency_burn_rate"`
+	ErrorBurnRate    float64    `json:"error_burn_rate"`
+	PerIP            []IPReport `json:"per_ip"`
+}
+
+// Report returns a snapshot of all objectives.
+// Per-IP breakdowns help tell a slow destination (all IPs burning) from a
+// degraded egress path (one IP burning).
+func (t *Tracker) Report() []Report {
+	if t == nil {
+		return []Report{}
+	}
+
+	now := time.Now()
+	t.mu.Lock()
+	defer t.mu.Unlock()
+
+	reports := make([]Report, 0, len(t.objectives))
+	for _, st := range t.objectives {
+		o := st.objective
+		sum := st.overall.sum(now)
+		r := Report{
+			Name:             o.Name,
+			Hosts:            o.Hosts,
+			Window:           o.Window.String(),
+			LatencyTarget:    o.LatencyTarget.String(),
+			LatencyObjective: o.LatencyObjective,
+			ErrorObjective:   o.ErrorObjective,
+			Counts:           sum,
+			LatencyBurnRate:  burnRate(sum.Slow, sum.Total, o.LatencyObjective),
+			ErrorBurnRate:    burnRate(sum.Errors, sum.Total, o.ErrorObjective),
+			PerIP:            make([]IPReport, 0, len(st.perIP)),
+		}
+		for ip, w := range st.perIP {
+			c := w.sum(now)
+			if c.Total == 0 {
+				continue
+			}
+			r.PerIP = append(r.PerIP, IPReport{
+				IP:              ip,
+				Counts:          c,
+				LatencyBurnRate: burnRate(c.Slow, c.Total, o.LatencyObjective),
+				ErrorBurnRate:   burnRate(c.Errors, c.Total, o.ErrorObjective),
+			})
+		}
+		sort.Slice(r.PerIP, func(i, j int) bool { return r.PerIP[i].IP < r.PerIP[j].IP })
+		reports = append(reports, r)
+	}
+	return reports
+}
+
+// Handler returns an HTTP handler serving the SLO report as JSON.
+func (t *Tracker) Handler() http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.Header().Set("Content-Type", "application/json")
+		w.WriteHeader(http.StatusOK)
+		json.NewEncoder(w).Encode(t.Report())
+	})
+}
@@ -0,0 +1,111 @@
+package slo
+
+import (
+	"encoding/json"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+func testObjective() Objective {
+	return Objective{
+		Name:             "api",
+		Hosts:            []string{"*.example.com"},
+		LatencyTarget:    100 * time.Millisecond,
+		LatencyObjective: 0.9,
+		ErrorObjective:   0.9,
+		Window:           time.Hour,
+	}
+}
+
+func TestNewTracker_Empty(t *testing.T) {
+	if tr := NewTracker(nil); tr != nil {
+		t.Error("expected nil tracker for no objectives")
+	}
+}
+
+func TestTracker_NilSafe(t *testing.T) {
+	var tr *Tracker
+	tr.Observe("api.example.com:443", "10.0.0.1", time.Second, true)
+	if r := tr.Report(); len(r) != 0 {
+		t.Errorf("expected empty report, got %v", r)
+	}
+}
+
+func TestTracker_Observe(t *testing.T) {
+	tr := NewTracker([]Objective{testObjective()})
+
+	tr.Observe("api.example.com:443", "10.0.0.1", 10*time.Millisecond, false)
+	tr.Observe("api.example.com:443", "10.0.0.1", 200*time.Millisecond, false)
+	tr.Observe("api.example.com:443", "10.0.0.2", 10*time.Millisecond, true)
+	tr.Observe("other.org:443", "10.0.0.1", time.Second, true)
+
+	reports := tr.Report()
+	if len(reports) != 1 {
+		t.Fatalf("expected 1 report, got %d", len(reports))
+	}
+	r := reports[0]
+
+	want := Counts{Total: 3, Slow: 1, Errors: 1}
+	if r.Counts != want {
+		t.Errorf("counts = %+v, want %+v", r.Counts, want)
+	}
+
+	// 1/3 bad against a 10% budget
+	if r.ErrorBurnRate < 3.3 || r.ErrorBurnRate > 3.4 {
+		t.Errorf("expected error burn rate ~3.33, got %v", r.ErrorBurnRate)
+	}
+
+	if len(r.PerIP) != 2 {
+		t.Fatalf("expected 2 per-IP entries, got %d", len(r.PerIP))
+	}
+	if r.PerIP[0].IP != "10.0.0.1" || r.PerIP[0].Counts.Total != 2 {
+		t.Errorf("unexpected entry for 10.0.0.1: %+v", r.PerIP[0])
+	}
+	if r.PerIP[1].IP != "10.0.0.2" || r.PerIP[1].Counts.Errors != 1 {
+		t.Errorf("unexpected entry for 10.0.0.2: %+v", r.PerIP[1])
+	}
+}
+
+func TestWindow_Expires(t *testing.T) {
+	w := newWindow(time.Minute)
+	now := time.Now()
+
+	w.add(now, Counts{Total: 1})
+	if got := w.sum(now).Total; got != 1 {
+		t.Errorf("expected 1, got %d", got)
+	}
+	if got := w.sum(now.Add(2 * time.Minute)).Total; got != 0 {
+		t.Errorf("expected expired counts, got %d", got)
+	}
+}
+
+func TestBurnRate(t *testing.T) {
+	if got := burnRate(0, 0, 0.99); got != 0 {
+		t.Errorf("expected 0 with no traffic, got %v", got)
+	}
+	if got := burnRate(1, 100, 0.99); got < 0.99 || got > 1.01 {
+		t.Errorf("expected burn rate ~1, got %v", got)
+	}
+}
+
+func TestTracker_Handler(t *testing.T) {
+	tr := NewTracker([]Objective{testObjective()})
+	tr.Observe("api.example.com:443", "10.0.0.1", 10*time.Millisecond, false)
+
+	req := httptest.NewRequest(http.MethodGet, "/admin/slo", nil)
+	rec := httptest.NewRecorder()
+	tr.Handler().ServeHTTP(rec, req)
+
+	if rec.Code != http.StatusOK {
+		t.Fatalf("expected 200, got %d", rec.Code)
+	}
+	var reports []Report
+	if err := json.NewDecoder(rec.Body).Decode(&reports); err != nil {
+		t.Fatalf("invalid JSON: %v", err)
+	}
+	if len(reports) != 1 || reports[0].Name != "api" {
+		t.Errorf("unexpected reports: %+v", reports)
+	}
+}
@@ -0,0 +1,260 @@
+package tracing
+
+import (
+	"bytes"
+	"context"
+	"encoding/hex"
+	"encoding/json"
+	"fmt"
+	"io"
+	"net/http"
+	"strconv"
+	"sync"
+	"time"
+
+	"github.com/cr0hn/outbound-lb/internal/logger"
+	"github.com/cr0hn/outbound-lb/internal/metrics"
+)
+
+const (
+	// exportQueueSize bounds the spans waiting for export; more are dropped.
+	exportQueueSize = 4096
+	// exportBatchSize is the most spans sent in one request.
+	exportBatchSize = 512
+	// exportTimeout bounds one export request.
+	exportTimeout = 10 * time.Second
+)
+
+var log = logger.Module("tracing")
+
+// ExporterConfig holds configuration for Exporter.
+type ExporterConfig struct {
+	// Endpoint is the collector's OTLP/HTTP traces URL, e.g.
+	// http://collector:4318/v1/traces.
+	Endpoint string
+	// ServiceName is reported as the service.name resource attribute.
+	ServiceName string
+	// Interval is how often queued spans are sent.
+	Interval time.Duration
+	// Client sends the export requests. Nil means a client with a 10s timeout.
+	Client *http.Client
+}
+
+// Exporter batches ended spans and sends them to an OpenTelemetry collector
+// as OTLP/HTTP JSON. Spans are dropped, never blocked on, when the queue is
+// full or the collector is unreachable.
+type Exporter struct {
+	config ExporterConfig
+	queue  chan *Span
+	stopCh chan struct{}
+	wg     sync.WaitGroup
+}
+
+// NewExporter creates a new Exporter.
+func NewExporter(cfg ExporterConfig) *Exporter {
+	if cfg.Client == nil {
+		cfg.Client = &http.Client{Timeout: exportTimeout}
+	}
+	if cfg.Interval <= 0 {
+		cfg.Interval = 5 * time.Second
+	}
+	return &Exporter{
+		config: cfg,
+		queue:  make(chan *Span, exportQueueSize),
+		stopCh: make(chan struct{}),
+	}
+}
+
+// Start begins sending queued spans every Interval, or as soon as a batch fills.
+func (e *Exporter) Start() {
+	e.wg.Add(1)
+	go e.run()
+	log.Info("trace_exporter_started", "endpoint", e.config.Endpoint, "service", e.config.ServiceName)
+}
+
+// Stop sends the spans still queued and stops the exporter.
+func (e *Exporter) Stop() {
+	close(e.stopCh)
+	e.wg.Wait()
+}
+
+// enqueue queues s for export, dropping it when the queue is full.
+func (e *Exporter) enqueue(s *Span) {
+	if e == nil {
+		return
+	}
+	select {
+	case e.queue <- s:
+	default:
+		metrics.TraceSpans.WithLabelValues("dropped").Inc()
+	}
+}
+
+// run collects spans into batches and sends them.
+func (e *Exporter) run() {
+	defer e.wg.Done()
+	ticker := time.NewTicker(e.config.Interval)
+	defer ticker.Stop()
+
+	batch := make([]*Span, 0, exportBatchSize)
+	flush := func() {
+		if len(batch) == 0 {
+			return
+		}
+		e.export(batch)
+		batch = batch[:0]
+	}
+	for {
+		select {
+		case s := <-e.queue:
+			batch = append(batch, s)
+			if len(batch) == exportBatchSize {
+				flush()
+			}
+		case <-ticker.C:
+			flush()
+		case <-e.stopCh:
+			for {
+				select {
+				case s := <-e.queue:
+					batch = append(batch, s)
+					if len(batch) == exportBatchSize {
+						flush()
+					}
+				default:
+					flush()
+					return
+				}
+			}
+		}
+	}
+}
+
+// export sends spans to the collector in one request.
+func (e *Exporter) export(spans []*Span) {
+	body, err := json.Marshal(e.encode(spans))
+	if err == nil {
+		err = e.post(body)
+	}
+	if err != nil {
+		metrics.TraceSpans.WithLabelValues("failed").Add(float64(len(spans)))
+		log.Warn("trace_export_failed", "endpoint", e.config.Endpoint, "spans", len(spans), "error", err)
+		return
+	}
+	metrics.TraceSpans.WithLabelValues("exported").Add(float64(len(spans)))
+}
+
+// post sends an encoded export request.
+func (e *Exporter) post(body []byte) error {
+	ctx, cancel := context.WithTimeout(context.Background(), exportTimeout)
+	defer cancel()
+	req, err := http.NewRequestWithContext(ctx, http.MethodPost, e.config.Endpoint, bytes.NewReader(body))
+	if err != nil {
+		return err
+	}
+	req.Header.Set("Content-Type", "application/json")
+	resp, err := e.config.Client.Do(req)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	io.Copy(io.Discard, resp.Body)
+	if resp.StatusCode/100 != 2 {
+		return fmt.Errorf("collector answered %s", resp.Status)
+	}
+	return nil
+}
+
+// OTLP/JSON export request, as defined by opentelemetry-proto.
+type (
+	otlpRequest struct {
+		ResourceSpans []otlpResourceSpans `json:"resourceSpans"`
+	}
+	otlpResourceSpans struct {
+		Resource   otlpResource     `json:"resource"`
+		ScopeSpans []otlpScopeSpans `json:"scopeSpans"`
+	}
+	otlpResource struct {
+		Attributes []otlpKeyValue `json:"attributes"`
+	}
+	otlpScopeSpans struct {
+		Scope otlpScope  `json:"scope"`
+		Spans []otlpSpan `json:"spans"`
+	}
+	otlpScope struct {
+		Name string `json:"name"`
+	}
+	otlpSpan struct {
+		TraceID           string         `json:"traceId"`
+		SpanID            string         `json:"spanId"`
+		ParentSpanID      string         `json:"parentSpanId,omitempty"`
+		Name              string         `json:"name"`
+		Kind              int            `json:"kind"`
+		StartTimeUnixNano string         `json:"startTimeUnixNano"`
+		EndTimeUnixNano   string         `json:"endTimeUnixNano"`
+		Attributes        []otlpKeyValue `json:"attributes,omitempty"`
+		Status            *otlpStatus    `json:"status,omitempty"`
+	}
+	otlpKeyValue struct {
+		Key   string    `json:"key"`
+		Value otlpValue `json:"value"`
+	}
+	otlpValue struct {
+		StringValue *string `json:"stringValue,omitempty"`
+		IntValue    *string `json:"intValue,omitempty"`
+		BoolValue   *bool   `json:"boolValue,omitempty"`
+	}
+	otlpStatus struct {
+		Code    int    `json:"code"`
+		Message string `json:"message,omitempty"`
+	}
+)
+
+// otlpStatusError is the OTLP status code of a failed span.
+const otlpStatusError = 2
+
+// encode builds the export request for spans.
+func (e *Exporter) encode(spans []*Span) otlpRequest {
+	out := make([]otlpSpan, 0, len(spans))
+	for _, s := range spans {
+		s.mu.Lock()
+		span := otlpSpan{
+			TraceID:           hex.EncodeToString(s.ctx.TraceID[:]),
+			SpanID:            hex.EncodeToString(s.ctx.SpanID[:]),
+			Name:              s.name,
+			Kind:              s.kind,
+			StartTimeUnixNano: strconv.FormatInt(s.start.UnixNano(), 10),
+			EndTimeUnixNano:   strconv.FormatInt(s.end.UnixNano(), 10),
+		}
+		if s.parent != (SpanID{}) {
+			span.ParentSpanID = hex.EncodeToString(s.parent[:])
+		}
+		for _, a := range s.attrs {
+			span.Attributes = append(span.Attributes, keyValue(a.key, a.value))
+		}
+		if s.errMsg != "" {
+			span.Status = &otlpStatus{Code: otlpStatusError, Message: s.errMsg}
+		}
+		s.mu.Unlock()
+		out = append(out, span)
+	}
+	return otlpRequest{ResourceSpans: []otlpResourceSpans{{
+		Resource:   otlpResource{Attributes: []otlpKeyValue{keyValue("service.name", e.config.ServiceName)}},
+		ScopeSpans: []otlpScopeSpans{{Scope: otlpScope{Name: "github.com/cr0hn/outbound-lb"}, Spans: out}},
+	}}}
+}
+
+// keyValue encodes an attribute; value is a string, int64 or bool.
+func keyValue(key string, value any) otlpKeyValue {
+	kv := otlpKeyValue{Key: key}
+	switch v := value.(type) {
+	case int64:
+		s := strconv.FormatInt(v, 10)
+		kv.Value.IntValue = &s
+	case bool:
+		kv.Value.BoolValue = &v
+	case string:
+		kv.Value.StringValue = &v
+	}
+	return kv
+}
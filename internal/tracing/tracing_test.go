@@ -0,0 +1,123 @@
+package tracing
+
+import (
+	"encoding/json"
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"sync"
+	"testing"
+	"time"
+)
+
+func TestParseTraceparent(t *testing.T) {
+	tests := []struct {
+		value       string
+		wantOK      bool
+		wantSampled bool
+	}{
+		{"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01", true, true},
+		{"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00", true, false},
+		{"01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra", true, true},
+		{"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra", false, false},
+		{"ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01", false, false},
+		{"00-00000000000000000000000000000000-00f067aa0ba902b7-01", false, false},
+		{"00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01", false, false},
+		{"00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01", false, false},
+		{"00-4bf92f3577b34da6a3ce929d0e0e47zz-00f067aa0ba902b7-01", false, false},
+		{"", false, false},
+	}
+	for _, tt := range tests {
+		sc, ok := ParseTraceparent(tt.value)
+		if ok != tt.wantOK || sc.Sampled != tt.wantSampled {
+			t.Errorf("ParseTraceparent(%q) = sampled %v, ok %v; want sampled %v, ok %v", tt.value, sc.Sampled, ok, tt.wantSampled, tt.wantOK)
+		}
+	}
+
+	const value = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+	sc, _ := ParseTraceparent(value)
+	if got := sc.Traceparent(); got != value {
+		t.Errorf("Traceparent() = %q, want %q", got, value)
+	}
+}
+
+func TestTracer_Start(t *testing.T) {
+	tracer := NewTracer(nil)
+	parent, _ := ParseTraceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00")
+
+	child := tracer.Start("request", KindServer, parent)
+	if child.Context().TraceID != parent.TraceID || child.parent != parent.SpanID || child.Context().Sampled {
+		t.Errorf("expected the span to continue the unsampled parent trace, got %+v", child.Context())
+	}
+	root := tracer.Start("request", KindServer, SpanContext{})
+	if !root.Context().IsValid() || !root.Context().Sampled || root.Context().TraceID == parent.TraceID {
+		t.Errorf("expected a new sampled trace, got %+v", root.Context())
+	}
+
+	// A nil tracer hands out nil spans that ignore every call
+	var none *Tracer
+	span := none.Start("request", KindServer, parent)
+	span.SetAttr("key", "value")
+	span.SetError(errors.New("failed"))
+	span.Child("select", KindInternal).End()
+	span.End()
+	if span.Context().IsValid() {
+		t.Error("expected a nil span to have no context")
+	}
+}
+
+func TestExporter(t *testing.T) {
+	var mu sync.Mutex
+	var got otlpRequest
+	collector := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if r.URL.Path != "/v1/traces" || r.Header.Get("Content-Type") != "application/json" {
+			t.Errorf("unexpected export request %s %s", r.URL.Path, r.Header.Get("Content-Type"))
+		}
+		mu.Lock()
+		defer mu.Unlock()
+		if err := json.NewDecoder(r.Body).Decode(&got); err != nil {
+			t.Errorf("decode: %v", err)
+		}
+	}))
+	defer collector.Close()
+
+	exporter := NewExporter(ExporterConfig{Endpoint: collector.URL + "/v1/traces", ServiceName: "outbound-lb", Interval: time.Hour})
+	exporter.Start()
+	tracer := NewTracer(exporter)
+
+	root := tracer.Start("proxy.request", KindServer, SpanContext{})
+	root.SetAttr("http.response.status_code", 200)
+	child := root.Child("connect", KindClient)
+	child.SetError(errors.New("connection refused"))
+	child.End()
+	root.End()
+	root.End()
+
+	unsampled, _ := ParseTraceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00")
+	tracer.Start("proxy.request", KindServer, unsampled).End()
+
+	exporter.Stop()
+
+	mu.Lock()
+	defer mu.Unlock()
+	if len(got.ResourceSpans) != 1 || len(got.ResourceSpans[0].ScopeSpans) != 1 {
+		t.Fatalf("unexpected export request %+v", got)
+	}
+	if name := *got.ResourceSpans[0].Resource.Attributes[0].Value.StringValue; name != "outbound-lb" {
+		t.Errorf("service.name = %q, want outbound-lb", name)
+	}
+	spans := got.ResourceSpans[0].ScopeSpans[0].Spans
+	if len(spans) != 2 {
+		t.Fatalf("expected the 2 sampled spans, got %d", len(spans))
+	}
+	connect, request := spans[0], spans[1]
+	if connect.Name != "connect" || connect.ParentSpanID != request.SpanID || connect.TraceID != request.TraceID {
+		t.Errorf("expected connect to be a child of the request span, got %+v", connect)
+	}
+	if connect.Status == nil || connect.Status.Code != otlpStatusError || connect.Status.Message != "connection refused" {
+		t.Errorf("expected connect to carry its error, got %+v", connect.Status)
+	}
+	if request.ParentSpanID != "" || request.Kind != KindServer || *request.Attributes[0].Value.IntValue != "200" {
+		t.Errorf("unexpected request span %+v", request)
+	}
+}
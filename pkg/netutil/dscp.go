@@ -0,0 +1,35 @@
+// Package netutil provides network utility functions.
+package netutil
+
+import (
+	"fmt"
+	"strconv"
+	"strings"
+)
+
+// dscpClasses maps the standard per-hop behavior names to DSCP values.
+var dscpClasses = map[string]int{
+	"cs0": 0, "cs1": 8, "cs2": 16, "cs3": 24, "cs4": 32, "cs5": 40, "cs6": 48, "cs7": 56,
+	"af11": 10, "af12": 12, "af13": 14,
+	"af21": 18, "af22": 20, "af23": 22,
+	"af31": 26, "af32": 28, "af33": 30,
+	"af41": 34, "af42": 36, "af43": 38,
+	"ef": 46, "va": 44, "le": 1,
+}
+
+// ParseDSCP parses a DSCP value given as a number from 0 to 63 or as a class
+// name such as "ef", "af41" or "cs1". An empty string is 0.
+func ParseDSCP(s string) (int, error) {
+	s = strings.ToLower(strings.TrimSpace(s))
+	if s == "" {
+		return 0, nil
+	}
+	if v, ok := dscpClasses[s]; ok {
+		return v, nil
+	}
+	v, err := strconv.Atoi(s)
+	if err != nil || v < 0 || v > 63 {
+		return 0, fmt.Errorf("invalid dscp %q: want 0-63 or a class such as ef, af41 or cs1", s)
+	}
+	return v, nil
+}
@@ -0,0 +1,30 @@
+package netutil
+
+import "testing"
+
+func TestParseDSCP(t *testing.T) {
+	tests := []struct {
+		in      string
+		want    int
+		wantErr bool
+	}{
+		{"", 0, false},
+		{"46", 46, false},
+		{"EF", 46, false},
+		{"af41", 34, false},
+		{" cs1 ", 8, false},
+		{"64", 0, true},
+		{"-1", 0, true},
+		{"gold", 0, true},
+	}
+	for _, tt := range tests {
+		got, err := ParseDSCP(tt.in)
+		if (err != nil) != tt.wantErr {
+			t.Errorf("ParseDSCP(%q) error = %v, wantErr %v", tt.in, err, tt.wantErr)
+			continue
+		}
+		if got != tt.want {
+			t.Errorf("ParseDSCP(%q) = %d, want %d", tt.in, got, tt.want)
+		}
+	}
+}
@@ -0,0 +1,67 @@
+// Package netutil provides network utility functions.
+package netutil
+
+import (
+	"net"
+	"strings"
+	"unicode/utf8"
+
+	"golang.org/x/net/idna"
+)
+
+// NormalizeHost returns the canonical form of a host or host:port that
+// destinations are matched and keyed by: without port or trailing dot,
+// lowercase, and with internationalized labels in their ASCII (punycode) form,
+// so "BÜCHER.de." and "xn--bcher-kva.de" are the same host. Names that are not
+// valid IDNA are only lowercased.
+func NormalizeHost(host string) string {
+	h := strings.TrimSuffix(ParseHost(host), ".")
+	if isASCII(h) || net.ParseIP(h) != nil {
+		return strings.ToLower(h)
+	}
+	ascii, err := idna.Lookup.ToASCII(h)
+	if err != nil {
+		return strings.ToLower(h)
+	}
+	// UTS #46 maps ideographic and fullwidth full stops to "."
+	return strings.TrimSuffix(ascii, ".")
+}
+
+func isASCII(s string) bool {
+	for i := 0; i < len(s); i++ {
+		if s[i] >= utf8.RuneSelf {
+			return false
+		}
+	}
+	return true
+}
+
+// MatchHost reports whether host matches pattern.
+// Patterns are exact hostnames, "*.example.com" (any subdomain of example.com,
+// but not example.com itself), or "*" (any host). Both are compared in their
+// NormalizeHost form, so ports, trailing dots, case and IDN encoding are ignored.
+func MatchHost(pattern, host string) bool {
+	host = NormalizeHost(host)
+
+	if pattern == "*" {
+		return true
+	}
+	if rest, ok := strings.CutPrefix(pattern, "*"); ok {
+		suffix := NormalizeHost(strings.TrimPrefix(rest, "."))
+		if strings.HasPrefix(rest, ".") {
+			suffix = "." + suffix
+		}
+		return strings.HasSuffix(host, suffix) && len(host) > len(suffix)
+	}
+	return host == NormalizeHost(pattern)
+}
+
+// MatchAnyHost reports whether host matches any of the patterns.
+func MatchAnyHost(patterns []string, host string) bool {
+	for _, p := range patterns {
+		if MatchHost(p, host) {
+			return true
+		}
+	}
+	return false
+}
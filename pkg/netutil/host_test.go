@@ -0,0 +1,74 @@
+package netutil
+
+import "testing"
+
+func TestMatchHost(t *testing.T) {
+	tests := []struct {
+		pattern  string
+		host     string
+		expected bool
+	}{
+		{"example.com", "example.com", true},
+		{"example.com", "EXAMPLE.com:443", true},
+		{"example.com", "example.com.", true},
+		{"example.com", "www.example.com", false},
+		{"*.example.com", "www.example.com", true},
+		{"*.example.com", "a.b.example.com:8080", true},
+		{"*.example.com", "example.com", false},
+		{"*.example.com", "badexample.com", false},
+		{"*", "anything.org", true},
+		{"api.example.com", "example.com", false},
+		{"bücher.de", "xn--bcher-kva.de", true},
+		{"*.xn--bcher-kva.de", "shop.BÜCHER.de:443", true},
+		{"*.bücher.de", "shop.xn--bcher-kva.de", true},
+		{"bucher.de", "bücher.de", false},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.pattern+"_"+tt.host, func(t *testing.T) {
+			result := MatchHost(tt.pattern, tt.host)
+			if result != tt.expected {
+				t.Errorf("MatchHost(%s, %s) = %v, expected %v", tt.pattern, tt.host, result, tt.expected)
+			}
+		})
+	}
+}
+
+func TestNormalizeHost(t *testing.T) {
+	tests := []struct {
+		input    string
+		expected string
+	}{
+		{"example.com:80", "example.com"},
+		{"Example.Com:443", "example.com"},
+		{"example.com.", "example.com"},
+		{"BÜCHER.de:443", "xn--bcher-kva.de"},
+		{"xn--bcher-kva.de", "xn--bcher-kva.de"},
+		{"bücher\uff0ede", "xn--bcher-kva.de"},
+		{"[2001:DB8::1]:443", "2001:db8::1"},
+		{"_dmarc.Example.com", "_dmarc.example.com"},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.input, func(t *testing.T) {
+			result := NormalizeHost(tt.input)
+			if result != tt.expected {
+				t.Errorf("NormalizeHost(%s) = %s, expected %s", tt.input, result, tt.expected)
+			}
+		})
+	}
+}
+
+func TestMatchAnyHost(t *testing.T) {
+	patterns := []string{"example.com", "*.example.org"}
+
+	if !MatchAnyHost(patterns, "api.example.org") {
+		t.Error("expected api.example.org to match")
+	}
+	if MatchAnyHost(patterns, "example.net") {
+		t.Error("expected example.net not to match")
+	}
+	if MatchAnyHost(nil, "example.com") {
+		t.Error("expected no match for empty pattern list")
+	}
+}
@@ -88,11 +88,3 @@ func ParseHost(hostport string) string {
 	}
 	return host
 }
-
-// NormalizeHost returns a consistent host representation.
-func NormalizeHost(host string) string {
-	// Remove port if present
-	h := ParseHost(host)
-	// Lowercase for consistency
-	return h
-}
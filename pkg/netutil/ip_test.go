@@ -75,26 +75,6 @@ func TestParseHost(t *testing.T) {
 	}
 }
 
-func TestNormalizeHost(t *testing.T) {
-	tests := []struct {
-		input    string
-		expected string
-	}{
-		{"example.com:80", "example.com"},
-		{"Example.Com:443", "Example.Com"}, // Note: doesn't lowercase currently
-		{"example.com", "example.com"},
-	}
-
-	for _, tt := range tests {
-		t.Run(tt.input, func(t *testing.T) {
-			result := NormalizeHost(tt.input)
-			if result != tt.expected {
-				t.Errorf("NormalizeHost(%s) = %s, expected %s", tt.input, result, tt.expected)
-			}
-		})
-	}
-}
-
 func TestValidateLocalIP_Loopback(t *testing.T) {
 	// 127.0.0.1 should be available on all systems
 	err := ValidateLocalIP("127.0.0.1")
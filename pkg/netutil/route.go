@@ -0,0 +1,38 @@
+// Package netutil provides network utility functions.
+package netutil
+
+import (
+	"errors"
+	"syscall"
+)
+
+// ErrRouteUnsupported is returned when a Route is applied on a platform
+// without policy routing.
+var ErrRouteUnsupported = errors.New("fwmark, device and dscp are only supported on Linux")
+
+// Route selects the policy-routing table used by an outbound socket on Linux.
+// Mark sets SO_MARK, to be matched by an `ip rule ... fwmark` rule, and Device
+// binds the socket to an interface or VRF device with SO_BINDTODEVICE. DSCP
+// marks the socket's packets (IP_TOS or IPV6_TCLASS) for upstream QoS.
+type Route struct {
+	Mark   int
+	Device string
+	DSCP   int
+}
+
+// Control returns a net.Dialer Control function that applies r to every
+// socket before it connects. Returns nil if r sets nothing.
+func (r Route) Control() func(network, address string, c syscall.RawConn) error {
+	if r == (Route{}) {
+		return nil
+	}
+	return func(network, _ string, c syscall.RawConn) error {
+		var sockErr error
+		if err := c.Control(func(fd uintptr) {
+			sockErr = r.apply(network, fd)
+		}); err != nil {
+			return err
+		}
+		return sockErr
+	}
+}
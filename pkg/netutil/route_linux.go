@@ -0,0 +1,35 @@
+//go:build linux
+
+// Package netutil provides network utility functions.
+package netutil
+
+import (
+	"fmt"
+	"strings"
+	"syscall"
+)
+
+// apply sets the fwmark, bound device and DSCP of the socket fd.
+func (r Route) apply(network string, fd uintptr) error {
+	if r.Mark != 0 {
+		if err := syscall.SetsockoptInt(int(fd), syscall.SOL_SOCKET, syscall.SO_MARK, r.Mark); err != nil {
+			return fmt.Errorf("setting fwmark %d: %w", r.Mark, err)
+		}
+	}
+	if r.Device != "" {
+		if err := syscall.BindToDevice(int(fd), r.Device); err != nil {
+			return fmt.Errorf("binding to device %s: %w", r.Device, err)
+		}
+	}
+	if r.DSCP != 0 {
+		// DSCP is the upper six bits of the ToS / traffic class byte
+		level, opt := syscall.IPPROTO_IP, syscall.IP_TOS
+		if strings.HasSuffix(network, "6") {
+			level, opt = syscall.IPPROTO_IPV6, syscall.IPV6_TCLASS
+		}
+		if err := syscall.SetsockoptInt(int(fd), level, opt, r.DSCP<<2); err != nil {
+			return fmt.Errorf("setting dscp %d: %w", r.DSCP, err)
+		}
+	}
+	return nil
+}
@@ -0,0 +1,9 @@
+//go:build !linux
+
+// Package netutil provides network utility functions.
+package netutil
+
+// apply reports that policy routing is not supported on this platform.
+func (r Route) apply(string, uintptr) error {
+	return ErrRouteUnsupported
+}
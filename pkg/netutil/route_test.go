@@ -0,0 +1,18 @@
+package netutil
+
+import "testing"
+
+func TestRoute_Control(t *testing.T) {
+	if (Route{}).Control() != nil {
+		t.Error("expected no control function for an empty route")
+	}
+	if (Route{Mark: 100}).Control() == nil {
+		t.Error("expected a control function for a route with a fwmark")
+	}
+	if (Route{Device: "vrf-wan1"}).Control() == nil {
+		t.Error("expected a control function for a route with a device")
+	}
+	if (Route{DSCP: 46}).Control() == nil {
+		t.Error("expected a control function for a route with a dscp")
+	}
+}